@@ -0,0 +1,198 @@
+//! What assistive technology needs from the engine: the tree it sees, and the system
+//! settings that change how the document should behave.
+
+use crate::layout::{Rect, RenderNode};
+use crate::Id;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A node of the tree assistive technology sees, as returned by
+/// [`crate::Engine::accessibility_tree`].
+///
+/// Besides the root, only nodes with a role or an accessible name are in the tree; the
+/// children of the nodes left out take their place in their parent. `visibility: hidden`
+/// subtrees are left out entirely. With the `serde` feature it implements `Serialize` and
+/// `Deserialize` like [`crate::RenderSnapshot`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AccessNode {
+    pub id: Id,
+    /// The `role` attribute, trimmed.
+    pub role: Option<String>,
+    /// The `aria-label` attribute, or else the node's text. Nodes with a role and neither
+    /// are named by the text in them, joined by spaces.
+    pub name: Option<String>,
+    /// The border box, in document coordinates.
+    pub bounds: Rect,
+    /// In document order.
+    pub children: Vec<AccessNode>,
+}
+
+/// The accessibility tree of the render tree under `root`, which is always its root.
+pub(crate) fn accessibility_tree(root: &RenderNode) -> AccessNode {
+    let role = non_empty(root.role.as_deref());
+    let mut children = Vec::new();
+    if !root.style.is_hidden() {
+        for child in &root.children {
+            collect(child, &mut children);
+        }
+    }
+    AccessNode {
+        id: root.id,
+        name: accessible_name(root, role.is_some()),
+        role,
+        bounds: root.bounds,
+        children,
+    }
+}
+
+/// Push the nodes of the tree for `node` onto `out`: itself, or its children's if it's left
+/// out.
+fn collect(node: &RenderNode, out: &mut Vec<AccessNode>) {
+    if node.style.is_hidden() {
+        return;
+    }
+    let mut children = Vec::new();
+    for child in &node.children {
+        collect(child, &mut children);
+    }
+    let role = non_empty(node.role.as_deref());
+    let name = accessible_name(node, role.is_some());
+    if role.is_none() && name.is_none() {
+        out.append(&mut children);
+        return;
+    }
+    out.push(AccessNode {
+        id: node.id,
+        role,
+        name,
+        bounds: node.bounds,
+        children,
+    });
+}
+
+/// See [`AccessNode::name`].
+fn accessible_name(node: &RenderNode, has_role: bool) -> Option<String> {
+    non_empty(node.aria_label.as_deref())
+        .or_else(|| non_empty(node.text.as_deref()))
+        .or_else(|| {
+            if !has_role {
+                return None;
+            }
+            let mut texts = Vec::new();
+            text_content(node, &mut texts);
+            non_empty(Some(&texts.join(" ")))
+        })
+}
+
+/// The visible text of the nodes under `node`, in document order.
+fn text_content<'a>(node: &'a RenderNode, texts: &mut Vec<&'a str>) {
+    for child in &node.children {
+        if child.style.is_hidden() {
+            continue;
+        }
+        if let Some(text) = child.text.as_deref().map(str::trim) {
+            if !text.is_empty() {
+                texts.push(text);
+            }
+        }
+        text_content(child, texts);
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+/// Whether the user asked the OS to minimize non-essential motion.
+///
+/// `SONATE_REDUCED_MOTION` (`1`/`true`/`reduce` or `0`/`false`/`no-preference`) takes
+/// precedence over the platform setting, which is:
+/// - macOS: `NSWorkspace.accessibilityDisplayShouldReduceMotion`
+/// - Windows: `SPI_GETCLIENTAREAANIMATION` (animations turned off means reduced motion)
+/// - Linux: `gtk-enable-animations` in the GTK 3 `settings.ini`
+pub(crate) fn system_prefers_reduced_motion() -> bool {
+    if let Ok(value) = std::env::var("SONATE_REDUCED_MOTION") {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "reduce" => return true,
+            "0" | "false" | "no-preference" => return false,
+            _ => log::warn!("ignoring invalid SONATE_REDUCED_MOTION value: {value}"),
+        }
+    }
+
+    platform_prefers_reduced_motion()
+}
+
+#[cfg(target_os = "macos")]
+fn platform_prefers_reduced_motion() -> bool {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyClass, AnyObject, Bool};
+
+    let Some(class) = AnyClass::get(c"NSWorkspace") else {
+        return false;
+    };
+
+    unsafe {
+        let workspace: *mut AnyObject = msg_send![class, sharedWorkspace];
+        if workspace.is_null() {
+            return false;
+        }
+        let reduce: Bool = msg_send![workspace, accessibilityDisplayShouldReduceMotion];
+        reduce.as_bool()
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn platform_prefers_reduced_motion() -> bool {
+    use windows::core::BOOL;
+    use windows::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION, SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS,
+    };
+
+    let mut animations_enabled = BOOL(1);
+    let result = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            Some(&mut animations_enabled as *mut BOOL as *mut std::ffi::c_void),
+            SYSTEM_PARAMETERS_INFO_UPDATE_FLAGS(0),
+        )
+    };
+
+    result.is_ok() && !animations_enabled.as_bool()
+}
+
+#[cfg(target_os = "linux")]
+fn platform_prefers_reduced_motion() -> bool {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        });
+
+    let Some(settings) = config_dir
+        .and_then(|dir| std::fs::read_to_string(dir.join("gtk-3.0").join("settings.ini")).ok())
+    else {
+        return false;
+    };
+
+    settings.lines().any(|line| {
+        let Some((key, value)) = line.split_once('=') else {
+            return false;
+        };
+        key.trim() == "gtk-enable-animations"
+            && matches!(value.trim().to_ascii_lowercase().as_str(), "0" | "false")
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn platform_prefers_reduced_motion() -> bool {
+    false
+}
+
+#[cfg(test)]
+mod accessibility_tests;
@@ -0,0 +1,95 @@
+use super::{accessibility_tree, AccessNode};
+use crate::layout::build_render_tree;
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::Rect;
+use crate::{Engine, Id};
+
+const HTML: &str = r#"
+<style>
+  .toolbar { display: flex; flex-direction: row; width: 200px; height: 40px; }
+  .button { width: 60px; height: 20px; }
+  .hidden { visibility: hidden; }
+</style>
+<div id="toolbar" class="toolbar" role="toolbar" aria-label="Formatting">
+  <div id="wrapper">
+    <div id="bold" class="button" role="button" aria-label="Bold">B</div>
+  </div>
+  <div id="italic" class="button" role="button"><div id="italic-text">Italic</div></div>
+  <div id="spacer" class="button"></div>
+  <div id="hint" class="button">Select some text</div>
+  <div id="secret" class="button hidden" role="button">Hidden</div>
+</div>
+"#;
+
+fn tree() -> (AccessNode, std::collections::HashMap<String, Id>) {
+    let (ctx, ids) = load_html_test_example(HTML, "toolbar");
+    let render_tree = build_render_tree(ctx.document.root_node());
+    (accessibility_tree(&render_tree), ids)
+}
+
+fn ids(nodes: &[AccessNode]) -> Vec<Id> {
+    nodes.iter().map(|node| node.id).collect()
+}
+
+#[test]
+fn nodes_without_a_role_or_name_are_pruned() {
+    let (tree, ids_by_name) = tree();
+    let id = |name: &str| ids_by_name[name];
+
+    assert_eq!(ids(&tree.children), vec![id("toolbar")]);
+    let toolbar = &tree.children[0];
+    assert_eq!(toolbar.role.as_deref(), Some("toolbar"));
+    assert_eq!(toolbar.bounds, Rect::new(0.0, 0.0, 200.0, 40.0));
+    // The wrapper and the spacer have neither, and the wrapper's button takes its place;
+    // hidden nodes aren't in the tree at all.
+    assert_eq!(
+        ids(&toolbar.children),
+        vec![id("bold"), id("italic"), id("hint")]
+    );
+    assert_eq!(ids(&toolbar.children[1].children), vec![id("italic-text")]);
+}
+
+#[test]
+fn names_come_from_the_label_or_else_the_text() {
+    let (tree, _) = tree();
+    let toolbar = &tree.children[0];
+    let names: Vec<_> = toolbar
+        .children
+        .iter()
+        .map(|node| (node.role.as_deref(), node.name.as_deref()))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            // The label wins over the text.
+            (Some("button"), Some("Bold")),
+            // A role without either is named by the text in it.
+            (Some("button"), Some("Italic")),
+            (None, Some("Select some text")),
+        ]
+    );
+    assert_eq!(toolbar.name.as_deref(), Some("Formatting"));
+    // Nodes without a role don't take the text in them.
+    assert_eq!(tree.name, None);
+}
+
+#[test]
+fn blank_roles_and_labels_count_as_missing() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    let node = engine.create_node(Id::from_u64(1), Some("  Save ".to_owned()));
+    engine.set_parent(root, node);
+    engine.set_attribute(node, "role".to_owned(), " ".to_owned());
+    engine.set_attribute(node, "aria-label".to_owned(), "".to_owned());
+
+    // Before the first layout there's only the root.
+    let empty = engine.accessibility_tree();
+    assert_eq!(empty.id, root);
+    assert!(empty.children.is_empty());
+
+    engine.layout_headless(100.0, 100.0).unwrap();
+    let tree = engine.accessibility_tree();
+    assert_eq!(tree.children.len(), 1);
+    assert_eq!(tree.children[0].role, None);
+    assert_eq!(tree.children[0].name.as_deref(), Some("Save"));
+}
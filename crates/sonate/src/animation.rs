@@ -0,0 +1,282 @@
+use crate::style::{
+    Length, Rgba, Style, TimingFunction, Transition, TransitionProperty, WillChange,
+};
+use crate::Id;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How often the data thread lays out again while a transition is running.
+pub(crate) const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Properties that can be transitioned, in the order of `NodeState::properties`.
+const ANIMATABLE: [TransitionProperty; 5] = [
+    TransitionProperty::Color,
+    TransitionProperty::BackgroundColor,
+    TransitionProperty::Width,
+    TransitionProperty::Height,
+    TransitionProperty::Opacity,
+];
+
+/// CSS transitions of all nodes, advanced by layout passes.
+///
+/// Each pass resolves a node's style and hands it to [`Transitions::apply`], which compares
+/// the transitionable values with those of the previous pass. A changed value with a matching
+/// `transition` starts interpolating from the value shown so far, so an interrupted
+/// transition retargets smoothly. Nodes that aren't laid out in a pass are forgotten. When
+/// the user prefers reduced motion, changed values apply at once.
+#[derive(Default)]
+pub(crate) struct Transitions {
+    state: RefCell<State>,
+}
+
+#[derive(Default)]
+struct State {
+    epoch: u64,
+    frame_time: Option<Instant>,
+    reduced_motion: bool,
+    nodes: HashMap<Id, NodeState>,
+}
+
+struct NodeState {
+    last_seen_epoch: u64,
+    properties: [PropertyState; ANIMATABLE.len()],
+}
+
+#[derive(Clone)]
+struct PropertyState {
+    /// The value without transitions, as of the last pass.
+    target: Value,
+    running: Option<Running>,
+}
+
+#[derive(Clone)]
+struct Running {
+    from: Value,
+    to: Value,
+    /// After the delay.
+    start: Instant,
+    duration: Duration,
+    timing_function: TimingFunction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Color(Option<Rgba>),
+    Length(Option<Length>),
+    Number(Option<f64>),
+}
+
+impl Transitions {
+    /// Start a layout pass showing the document at `frame_time`, with or without motion.
+    pub fn begin_pass(&self, frame_time: Instant, reduced_motion: bool) {
+        let mut state = self.state.borrow_mut();
+        state.epoch = state.epoch.wrapping_add(1);
+        state.frame_time = Some(frame_time);
+        state.reduced_motion = reduced_motion;
+    }
+
+    /// Jump running transitions to their end values, as the next pass shows them.
+    pub fn finish_all(&self) {
+        let mut state = self.state.borrow_mut();
+        for node in state.nodes.values_mut() {
+            for property in &mut node.properties {
+                property.running = None;
+            }
+        }
+    }
+
+    /// Forget nodes that weren't laid out in this pass, e.g. because they were hidden.
+    pub fn end_pass(&self) {
+        let mut state = self.state.borrow_mut();
+        let epoch = state.epoch;
+        state.nodes.retain(|_, node| node.last_seen_epoch == epoch);
+    }
+
+    /// Whether any transition needs another pass, including ones still in their delay.
+    pub fn is_running(&self) -> bool {
+        let state = self.state.borrow();
+        state.nodes.values().any(|node| {
+            node.properties
+                .iter()
+                .any(|property| property.running.is_some())
+        })
+    }
+
+    /// Replace the transitionable values of the node's resolved `style` with the ones to show
+    /// at the frame time. Calling this again for the same node in the same pass is harmless.
+    ///
+    /// While its opacity transitions, the style also hints `will-change: opacity`, so the
+    /// subtree is composited from a cached layer instead of repainted every frame.
+    pub fn apply(&self, id: Id, style: &mut Style) {
+        let mut state = self.state.borrow_mut();
+        let epoch = state.epoch;
+        let reduced_motion = state.reduced_motion;
+        let Some(now) = state.frame_time else {
+            return;
+        };
+
+        let node = state.nodes.entry(id).or_insert_with(|| NodeState {
+            last_seen_epoch: epoch,
+            properties: ANIMATABLE.map(|property| PropertyState {
+                target: value_of(style, property),
+                running: None,
+            }),
+        });
+        node.last_seen_epoch = epoch;
+
+        for (property, property_state) in ANIMATABLE.into_iter().zip(&mut node.properties) {
+            let target = value_of(style, property);
+            if target != property_state.target {
+                let current = property_state.value_at(now);
+                property_state.running = style
+                    .transition_for(property)
+                    .filter(|transition| !transition.duration.is_zero())
+                    .filter(|_| interpolate(&current, &target, 0.0).is_some())
+                    .map(|transition| Running::new(current, target.clone(), now, transition));
+                property_state.target = target;
+            }
+            if reduced_motion {
+                property_state.running = None;
+            }
+
+            if let Some(running) = &property_state.running {
+                if now >= running.start + running.duration {
+                    property_state.running = None;
+                } else {
+                    set_value(style, property, running.value_at(now));
+                    if property == TransitionProperty::Opacity {
+                        style.will_change = Some(WillChange {
+                            opacity: true,
+                            ..style.will_change.unwrap_or_default()
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl PropertyState {
+    fn value_at(&self, now: Instant) -> Value {
+        match &self.running {
+            Some(running) => running.value_at(now),
+            None => self.target.clone(),
+        }
+    }
+}
+
+impl Running {
+    fn new(from: Value, to: Value, now: Instant, transition: &Transition) -> Self {
+        Self {
+            from,
+            to,
+            start: now + transition.delay,
+            duration: transition.duration,
+            timing_function: transition.timing_function,
+        }
+    }
+
+    fn value_at(&self, now: Instant) -> Value {
+        let elapsed = now.saturating_duration_since(self.start);
+        let t = (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0);
+        let progress = self.timing_function.progress(t);
+        interpolate(&self.from, &self.to, progress).unwrap_or_else(|| self.to.clone())
+    }
+}
+
+fn value_of(style: &Style, property: TransitionProperty) -> Value {
+    match property {
+        TransitionProperty::Color => Value::Color(style.color),
+        TransitionProperty::BackgroundColor => Value::Color(style.background_color),
+        TransitionProperty::Width => Value::Length(style.width.clone()),
+        TransitionProperty::Height => Value::Length(style.height.clone()),
+        TransitionProperty::Opacity => Value::Number(style.opacity),
+        TransitionProperty::All => unreachable!("not a single property"),
+    }
+}
+
+fn set_value(style: &mut Style, property: TransitionProperty, value: Value) {
+    match (property, value) {
+        (TransitionProperty::Color, Value::Color(color)) => style.color = color,
+        (TransitionProperty::BackgroundColor, Value::Color(color)) => {
+            style.background_color = color
+        }
+        (TransitionProperty::Width, Value::Length(length)) => style.width = length,
+        (TransitionProperty::Height, Value::Length(length)) => style.height = length,
+        (TransitionProperty::Opacity, Value::Number(number)) => style.opacity = number,
+        _ => unreachable!("value of another property"),
+    }
+}
+
+/// The value `progress` of the way from `from` to `to`, or `None` if the values can't be
+/// interpolated, e.g. from `auto` to a length. Colors interpolate per channel and lengths in
+/// px; `progress` may leave `[0, 1]` for timing functions that overshoot.
+fn interpolate(from: &Value, to: &Value, progress: f64) -> Option<Value> {
+    let lerp = |a: f64, b: f64| a + (b - a) * progress;
+
+    match (from, to) {
+        (Value::Color(Some(a)), Value::Color(Some(b))) => {
+            let channel = |a: u8, b: u8| lerp(a as f64, b as f64).round().clamp(0.0, 255.0) as u8;
+            Some(Value::Color(Some(Rgba {
+                r: channel(a.r, b.r),
+                g: channel(a.g, b.g),
+                b: channel(a.b, b.b),
+                a: channel(a.a, b.a),
+            })))
+        }
+        (Value::Length(Some(Length::Px(a))), Value::Length(Some(Length::Px(b)))) => {
+            Some(Value::Length(Some(Length::Px(lerp(*a, *b)))))
+        }
+        // Unset opacity is fully opaque.
+        (Value::Number(a), Value::Number(b)) => Some(Value::Number(Some(lerp(
+            a.unwrap_or(1.0),
+            b.unwrap_or(1.0),
+        )))),
+        _ => None,
+    }
+}
+
+impl TimingFunction {
+    /// Output progress for input progress `t` in `[0, 1]`.
+    pub fn progress(&self, t: f64) -> f64 {
+        let (x1, y1, x2, y2) = match *self {
+            TimingFunction::Linear => return t,
+            TimingFunction::Ease => (0.25, 0.1, 0.25, 1.0),
+            TimingFunction::EaseIn => (0.42, 0.0, 1.0, 1.0),
+            TimingFunction::EaseOut => (0.0, 0.0, 0.58, 1.0),
+            TimingFunction::EaseInOut => (0.42, 0.0, 0.58, 1.0),
+            TimingFunction::CubicBezier(x1, y1, x2, y2) => (x1, y1, x2, y2),
+        };
+        if t <= 0.0 || t >= 1.0 {
+            return t.clamp(0.0, 1.0);
+        }
+
+        // One coordinate of the curve from (0, 0) to (1, 1) at parameter `s`.
+        let bezier = |p1: f64, p2: f64, s: f64| {
+            let inv = 1.0 - s;
+            3.0 * inv * inv * s * p1 + 3.0 * inv * s * s * p2 + s * s * s
+        };
+
+        // x(s) is monotonic since x1 and x2 are in [0, 1], so bisection finds s with x(s) = t.
+        let (mut low, mut high) = (0.0, 1.0);
+        let mut s = t;
+        for _ in 0..64 {
+            let x = bezier(x1, x2, s);
+            if (x - t).abs() < 1e-9 {
+                break;
+            }
+            if x < t {
+                low = s;
+            } else {
+                high = s;
+            }
+            s = (low + high) / 2.0;
+        }
+
+        bezier(y1, y2, s)
+    }
+}
+
+#[cfg(test)]
+mod animation_tests;
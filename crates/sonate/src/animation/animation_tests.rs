@@ -0,0 +1,263 @@
+use crate::css_parser::parse_css;
+use crate::layout::LayoutContext;
+use crate::style::{Length, Rgba, TimingFunction};
+use crate::{Engine, Id};
+use std::time::{Duration, Instant};
+
+const BOX: Id = Id(1);
+
+/// A box whose width and background transition when `class` is swapped.
+fn context_with_box(class: &str) -> LayoutContext {
+    let mut ctx = LayoutContext::new();
+    let css = r#"
+        .box {
+            width: 100px;
+            height: 20px;
+            background-color: rgb(0, 0, 0);
+            transition: width 300ms linear, background-color 100ms linear;
+        }
+        .wide { width: 200px; background-color: rgb(200, 100, 50); }
+        .narrow { width: 20px; }
+        .instant { width: 300px; transition: none; }
+        .fading { opacity: 0.2; transition: opacity 100ms linear; }
+    "#;
+    for rule in parse_css(css).unwrap().rules {
+        ctx.style_sheet.add_rule(rule);
+    }
+
+    let root = ctx.document.root_id();
+    ctx.document.create_node(BOX, None).unwrap();
+    ctx.document.set_parent(root, BOX).unwrap();
+    ctx.document
+        .set_attribute(BOX, "class".to_owned(), class.to_owned());
+    ctx
+}
+
+fn set_class(ctx: &mut LayoutContext, class: &str) {
+    ctx.document
+        .set_attribute(BOX, "class".to_owned(), class.to_owned());
+}
+
+/// Lay out at `time` and return the box's width.
+fn width_at(ctx: &mut LayoutContext, time: Instant) -> f64 {
+    ctx.set_frame_time(time);
+    ctx.layout();
+    let node = ctx.document.get_node(BOX).unwrap();
+    let width = node.borrow().layout.bounds.width;
+    width
+}
+
+fn background_at(ctx: &mut LayoutContext, time: Instant) -> Option<Rgba> {
+    ctx.set_frame_time(time);
+    ctx.layout();
+    let node = ctx.document.get_node(BOX).unwrap();
+    let background = node.borrow().layout.style.background_color;
+    background
+}
+
+#[test]
+fn width_interpolates_over_the_duration() {
+    let t0 = Instant::now();
+    let ms = |ms: u64| t0 + Duration::from_millis(ms);
+    let mut ctx = context_with_box("box");
+    assert_eq!(width_at(&mut ctx, t0), 100.0);
+    assert!(
+        !ctx.has_running_transitions(),
+        "initial values don't animate"
+    );
+
+    set_class(&mut ctx, "box wide");
+    assert_eq!(width_at(&mut ctx, t0), 100.0);
+    assert!(ctx.has_running_transitions());
+    assert_eq!(width_at(&mut ctx, ms(150)), 150.0);
+    assert_eq!(width_at(&mut ctx, ms(300)), 200.0);
+    assert!(!ctx.has_running_transitions());
+    assert_eq!(width_at(&mut ctx, ms(1000)), 200.0);
+}
+
+#[test]
+fn colors_interpolate_per_channel() {
+    let t0 = Instant::now();
+    let mut ctx = context_with_box("box");
+    background_at(&mut ctx, t0);
+
+    set_class(&mut ctx, "box wide");
+    background_at(&mut ctx, t0);
+    assert_eq!(
+        background_at(&mut ctx, t0 + Duration::from_millis(50)),
+        Some(Rgba {
+            r: 100,
+            g: 50,
+            b: 25,
+            a: 255
+        })
+    );
+    assert_eq!(
+        background_at(&mut ctx, t0 + Duration::from_millis(100)),
+        Some(Rgba {
+            r: 200,
+            g: 100,
+            b: 50,
+            a: 255
+        })
+    );
+}
+
+#[test]
+fn interrupted_transition_retargets_from_the_current_value() {
+    let t0 = Instant::now();
+    let ms = |ms: u64| t0 + Duration::from_millis(ms);
+    let mut ctx = context_with_box("box");
+    width_at(&mut ctx, t0);
+
+    set_class(&mut ctx, "box wide");
+    width_at(&mut ctx, t0);
+    assert_eq!(width_at(&mut ctx, ms(150)), 150.0);
+
+    // Halfway to 200px, head for 20px instead: 130px to go over a fresh 300ms.
+    set_class(&mut ctx, "box narrow");
+    assert_eq!(width_at(&mut ctx, ms(150)), 150.0);
+    assert_eq!(width_at(&mut ctx, ms(300)), 85.0);
+    assert_eq!(width_at(&mut ctx, ms(450)), 20.0);
+    assert!(!ctx.has_running_transitions());
+}
+
+#[test]
+fn changes_without_a_transition_apply_at_once() {
+    let t0 = Instant::now();
+    let mut ctx = context_with_box("box");
+    width_at(&mut ctx, t0);
+
+    set_class(&mut ctx, "box instant");
+    assert_eq!(width_at(&mut ctx, t0), 300.0);
+    assert!(!ctx.has_running_transitions());
+
+    // Percentages and `auto` can't be interpolated in px.
+    let mut ctx = context_with_box("box");
+    width_at(&mut ctx, t0);
+    let node = ctx.document.get_node(BOX).unwrap();
+    node.borrow_mut().inline_style = std::sync::Arc::new(crate::style::InlineStyle {
+        declarations: crate::style::Style {
+            width: Some(Length::Percent(50.0)),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    ctx.document
+        .set_attribute(BOX, "class".to_owned(), "no-box".to_owned());
+    width_at(&mut ctx, t0);
+    assert!(!ctx.has_running_transitions());
+}
+
+#[test]
+fn timing_functions_follow_their_curves() {
+    for timing_function in [
+        TimingFunction::Linear,
+        TimingFunction::Ease,
+        TimingFunction::EaseIn,
+        TimingFunction::EaseOut,
+        TimingFunction::EaseInOut,
+    ] {
+        assert_eq!(timing_function.progress(0.0), 0.0);
+        assert_eq!(timing_function.progress(1.0), 1.0);
+    }
+
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-3;
+    assert_eq!(TimingFunction::Linear.progress(0.25), 0.25);
+    assert!(close(TimingFunction::Ease.progress(0.5), 0.8024));
+    assert!(close(TimingFunction::EaseIn.progress(0.5), 0.3153));
+    assert!(close(TimingFunction::EaseOut.progress(0.5), 0.6847));
+    assert!(close(TimingFunction::EaseInOut.progress(0.5), 0.5));
+    // Curves may overshoot.
+    assert!(TimingFunction::CubicBezier(0.3, 1.5, 0.7, 1.5).progress(0.5) > 1.0);
+}
+
+#[test]
+fn fading_promotes_the_node_until_the_transition_ends() {
+    let t0 = Instant::now();
+    let mut ctx = context_with_box("box");
+    let promoted_at = |ctx: &mut LayoutContext, time: Instant| {
+        width_at(ctx, time);
+        let node = ctx.document.get_node(BOX).unwrap();
+        let promoted = node.borrow().layout.style.promotes_layer();
+        promoted
+    };
+    assert!(!promoted_at(&mut ctx, t0));
+
+    set_class(&mut ctx, "box fading");
+    assert!(promoted_at(&mut ctx, t0));
+    assert!(promoted_at(&mut ctx, t0 + Duration::from_millis(50)));
+    assert!(!promoted_at(&mut ctx, t0 + Duration::from_millis(100)));
+}
+
+#[test]
+fn reduced_motion_applies_changes_at_once() {
+    let t0 = Instant::now();
+    let mut ctx = context_with_box("box");
+    ctx.set_prefers_reduced_motion(true);
+    assert_eq!(width_at(&mut ctx, t0), 100.0);
+
+    set_class(&mut ctx, "box wide");
+    assert_eq!(width_at(&mut ctx, t0), 200.0);
+    assert!(!ctx.has_running_transitions());
+}
+
+#[test]
+fn reducing_motion_ends_running_transitions() {
+    let t0 = Instant::now();
+    let mut ctx = context_with_box("box");
+    width_at(&mut ctx, t0);
+    set_class(&mut ctx, "box wide");
+    width_at(&mut ctx, t0);
+    assert!(ctx.has_running_transitions());
+
+    ctx.set_prefers_reduced_motion(true);
+    assert!(!ctx.has_running_transitions());
+    assert_eq!(width_at(&mut ctx, t0 + Duration::from_millis(50)), 200.0);
+}
+
+#[test]
+fn data_thread_publishes_frames_until_the_transition_ends() {
+    let engine = Engine::new();
+    engine.add_stylesheet(
+        ".box { width: 100px; height: 20px; transition: width 100ms linear; } \
+         .wide { width: 200px; }",
+    );
+    let node = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), node);
+    engine.set_attribute(node, "class".to_owned(), "box".to_owned());
+    engine.layout_headless(400.0, 100.0).unwrap();
+
+    engine.set_attribute(node, "class".to_owned(), "box wide".to_owned());
+    let started = engine.layout_headless(400.0, 100.0).unwrap();
+    assert!(started.find(node).unwrap().bounds.width < 200.0);
+
+    // No further commands: the data thread lays out new frames on its own.
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let width = engine.snapshot().unwrap().find(node).unwrap().bounds.width;
+        if width == 200.0 {
+            break;
+        }
+        assert!(Instant::now() < deadline, "stuck at {width}px");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn reduced_motion_shows_the_end_value_in_the_next_snapshot() {
+    let engine = Engine::new();
+    engine.set_reduced_motion(Some(true));
+    engine.add_stylesheet(
+        ".box { width: 100px; height: 20px; transition: width 100ms linear; } \
+         .wide { width: 200px; }",
+    );
+    let node = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), node);
+    engine.set_attribute(node, "class".to_owned(), "box".to_owned());
+    engine.layout_headless(400.0, 100.0).unwrap();
+
+    engine.set_attribute(node, "class".to_owned(), "box wide".to_owned());
+    let snapshot = engine.layout_headless(400.0, 100.0).unwrap();
+    assert_eq!(snapshot.find(node).unwrap().bounds.width, 200.0);
+}
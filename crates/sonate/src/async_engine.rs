@@ -0,0 +1,129 @@
+use crate::commands::Command;
+use crate::progress::{Goal, Progress};
+use crate::{Engine, Id, Rect, RenderSnapshot};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// An [`Engine`] whose changes can be awaited. Runtime-agnostic: the futures are woken by the
+/// data thread, and the crate spawns no executor.
+///
+/// Mutating methods send the command right away, like their `Engine` counterparts, and
+/// return a future that resolves once the data thread has applied it. Queries resolve once
+/// the snapshot shows every change sent before them.
+#[derive(Clone)]
+pub struct AsyncEngine {
+    engine: Engine,
+}
+
+impl AsyncEngine {
+    pub fn new(engine: Engine) -> Self {
+        Self { engine }
+    }
+
+    /// The wrapped engine, for what needs no acknowledgment.
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn add_stylesheet(&self, css_content: &str) -> Acknowledged {
+        self.engine.add_stylesheet(css_content);
+        self.acknowledge(Goal::Applied)
+    }
+
+    pub fn create_node(&self, id: Id, text: Option<String>) -> Acknowledged {
+        self.engine.create_node(id, text);
+        self.acknowledge(Goal::Applied)
+    }
+
+    pub fn create_element(&self, id: Id, tag: &str, text: Option<String>) -> Acknowledged {
+        self.engine.create_element(id, tag, text);
+        self.acknowledge(Goal::Applied)
+    }
+
+    pub fn set_parent(&self, parent_id: Id, child_id: Id) -> Acknowledged {
+        self.engine.set_parent(parent_id, child_id);
+        self.acknowledge(Goal::Applied)
+    }
+
+    pub fn set_attribute(&self, node_id: Id, key: String, value: String) -> Acknowledged {
+        self.engine.set_attribute(node_id, key, value);
+        self.acknowledge(Goal::Applied)
+    }
+
+    pub fn set_text(&self, node_id: Id, text: Option<String>) -> Acknowledged {
+        self.engine.set_text(node_id, text);
+        self.acknowledge(Goal::Applied)
+    }
+
+    /// Resolves once a published snapshot includes every change sent so far.
+    pub fn wait_for_layout(&self) -> Acknowledged {
+        self.acknowledge(Goal::LaidOut)
+    }
+
+    /// The snapshot once it includes every change sent so far.
+    pub async fn snapshot(&self) -> Option<RenderSnapshot> {
+        self.wait_for_layout().await;
+        self.engine.snapshot()
+    }
+
+    /// Bounds of the node once laid out with every change sent so far, or `None` if it
+    /// isn't displayed.
+    pub async fn bounds(&self, id: Id) -> Option<Rect> {
+        let snapshot = self.snapshot().await?;
+        snapshot.find(id).map(|node| node.bounds)
+    }
+
+    #[cfg(feature = "serde")]
+    pub async fn snapshot_json(&self) -> Option<String> {
+        Some(self.snapshot().await?.to_json())
+    }
+
+    /// How many times the data thread woke waiting futures, for checking that it batches.
+    pub fn wakeups(&self) -> u64 {
+        self.engine.progress.state().wakeups
+    }
+
+    fn acknowledge(&self, goal: fn(u64) -> Goal) -> Acknowledged {
+        let sequence = self.engine.progress.next_sequence();
+        // A stopped data thread resolves every future through `Progress::close`.
+        let _ = self.engine.sender.send(Command::Acknowledge(sequence));
+
+        Acknowledged {
+            progress: Arc::clone(&self.engine.progress),
+            goal: goal(sequence),
+        }
+    }
+}
+
+/// Resolves once the data thread got as far as an [`AsyncEngine`] call asked for.
+#[must_use = "futures do nothing unless awaited"]
+pub struct Acknowledged {
+    progress: Arc<Progress>,
+    goal: Goal,
+}
+
+impl Future for Acknowledged {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.progress.state();
+        if state.reached(self.goal) {
+            return Poll::Ready(());
+        }
+
+        let goal = self.goal;
+        let registered = state
+            .waiters
+            .iter()
+            .any(|(waiting, waker)| *waiting == goal && waker.will_wake(cx.waker()));
+        if !registered {
+            state.waiters.push((goal, cx.waker().clone()));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod async_engine_tests;
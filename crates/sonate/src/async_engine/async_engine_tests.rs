@@ -0,0 +1,93 @@
+use super::AsyncEngine;
+use crate::commands::Command;
+use crate::{Engine, Id};
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// The smallest executor: poll on this thread, park until woken.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn awaited_changes_are_in_the_snapshot_after_layout() {
+    let engine = AsyncEngine::new(Engine::new());
+    let (parent, child) = (Id::from_u64(1), Id::from_u64(2));
+
+    let snapshot = block_on(async {
+        engine.add_stylesheet(".box { width: 40px; }").await;
+        engine.create_node(parent, None).await;
+        engine.create_node(child, Some("Child".to_owned())).await;
+        engine.set_parent(engine.engine().root_id(), parent).await;
+        engine.set_parent(parent, child).await;
+        engine
+            .set_attribute(parent, "class".to_owned(), "box".to_owned())
+            .await;
+        engine.wait_for_layout().await;
+        engine.engine().snapshot()
+    })
+    .unwrap();
+
+    let parent_node = snapshot.find(parent).unwrap();
+    assert_eq!(parent_node.bounds.width, 40.0);
+    assert_eq!(parent_node.children[0].id, child);
+
+    // Nothing pending: layout is already up to date.
+    block_on(engine.wait_for_layout());
+    assert_eq!(block_on(engine.bounds(parent)), Some(parent_node.bounds));
+}
+
+#[test]
+fn acknowledgments_are_batched() {
+    const COMMANDS: u64 = 200;
+    let engine = AsyncEngine::new(Engine::new());
+
+    // Hold the data thread in a layout while the commands queue up behind it, so that it
+    // handles all of them in one go.
+    let snapshot = engine.engine().snapshot.read().unwrap();
+    engine.engine().sender.send(Command::Layout).unwrap();
+    let acknowledgments: Vec<_> = (1..=COMMANDS)
+        .map(|n| engine.create_node(Id::from_u64(n), None))
+        .collect();
+    let applied = thread::spawn({
+        let engine = engine.clone();
+        move || {
+            block_on(async {
+                for acknowledged in acknowledgments {
+                    acknowledged.await;
+                }
+            });
+            engine.wakeups()
+        }
+    });
+    while engine.engine().progress.state().waiters.is_empty() {
+        thread::yield_now();
+    }
+    drop(snapshot);
+
+    let wakeups = applied.join().unwrap();
+    assert!(wakeups >= 1);
+    assert!(
+        wakeups < COMMANDS / 10,
+        "{wakeups} wakeups for {COMMANDS} commands"
+    );
+}
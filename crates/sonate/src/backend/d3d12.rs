@@ -1,5 +1,9 @@
-use super::{InputState, Params, RenderingBackend};
-use anyhow::Result;
+use super::device_loss::{Failure, Recovery, MAX_ATTEMPTS};
+use super::{
+    release_dropped_surfaces, DrawFrame, GpuMemoryStats, InputState, RenderingBackend, VsyncMode,
+};
+use crate::windowing::{place_window, WindowOptions};
+use anyhow::{Context, Result};
 use skia_safe::{
     gpu::{
         d3d::{BackendContext, TextureResourceInfo},
@@ -7,6 +11,7 @@ use skia_safe::{
     },
     ColorType, Surface,
 };
+use std::time::Instant;
 use windows::{
     core::Interface,
     Win32::{
@@ -21,7 +26,7 @@ use windows::{
                 Common::{DXGI_FORMAT_R8G8B8A8_UNORM, DXGI_SAMPLE_DESC},
                 CreateDXGIFactory1, IDXGIAdapter1, IDXGIFactory4, IDXGISwapChain3,
                 DXGI_ADAPTER_FLAG, DXGI_ADAPTER_FLAG_NONE, DXGI_ADAPTER_FLAG_SOFTWARE,
-                DXGI_PRESENT, DXGI_SCALING_NONE, DXGI_SWAP_CHAIN_DESC1,
+                DXGI_ERROR_NOT_FOUND, DXGI_PRESENT, DXGI_SCALING_NONE, DXGI_SWAP_CHAIN_DESC1,
                 DXGI_SWAP_EFFECT_FLIP_DISCARD, DXGI_USAGE_RENDER_TARGET_OUTPUT,
             },
         },
@@ -38,24 +43,36 @@ const BUFFER_COUNT: usize = 2;
 
 /// Direct3D 12 rendering backend implementation
 pub struct D3D12Backend {
+    // Declared before the window so it drops first. None while the device is lost.
+    device: Option<Device>,
     window: Window,
-    #[allow(unused)]
-    factory: IDXGIFactory4,
-    // Device/queue container declared BEFORE dependents so it drops LAST
-    backend_context: BackendContext,
-    // Swap chain declared before DirectContext so it drops AFTER Skia context
-    swap_chain: IDXGISwapChain3,
+    input_state: InputState,
+    /// Refreshes to wait for before presenting, 0 for none.
+    sync_interval: u32,
+    /// The attempts at making the device again since it was lost.
+    recovery: Recovery,
+}
+
+/// The device and everything made with it, made again together when the device is lost.
+struct Device {
+    // Surfaces declared first so they drop FIRST
+    surfaces: [Option<(Surface, BackendRenderTarget)>; BUFFER_COUNT],
     // Skia context declared before backend_context so it drops BEFORE device/queue
     direct_context: DirectContext,
-    // Surfaces declared after above so they drop FIRST
-    surfaces: [Option<(Surface, BackendRenderTarget)>; BUFFER_COUNT],
-    input_state: InputState,
-    current_width: u32,
-    current_height: u32,
+    // Swap chain declared after DirectContext so it drops AFTER Skia context
+    swap_chain: IDXGISwapChain3,
+    // Device/queue container declared AFTER dependents so it drops LAST
+    backend_context: BackendContext,
+    #[allow(unused)]
+    factory: IDXGIFactory4,
+    width: u32,
+    height: u32,
+    /// Set once the device is gone, so dropping doesn't wait for it.
+    lost: bool,
 }
 
 impl RenderingBackend for D3D12Backend {
-    fn new(event_loop: &ActiveEventLoop) -> Result<Self> {
+    fn new(event_loop: &ActiveEventLoop, options: &WindowOptions) -> Result<Self> {
         // Enable D3D12 debug layer (best effort)
         #[cfg(debug_assertions)]
         unsafe {
@@ -67,15 +84,186 @@ impl RenderingBackend for D3D12Backend {
             }
         }
         let mut window_attributes = WindowAttributes::default();
-        window_attributes.inner_size = Some(Size::new(LogicalSize::new(800, 800)));
-        window_attributes.title = "Sonate CSS - Direct3D 12".into();
+        window_attributes.inner_size =
+            Some(Size::new(LogicalSize::new(options.width, options.height)));
+        window_attributes.title = options.title_or("Sonate CSS - Direct3D 12");
+        let window_attributes = place_window(window_attributes, event_loop, options);
 
         let window = event_loop
             .create_window(window_attributes)
-            .expect("Failed to create window");
+            .context("Failed to create window")?;
 
-        let hwnd = HWND(u64::from(window.id()) as *mut _);
         let (width, height) = window.inner_size().into();
+        let device = Device::new(&window, width, height)?;
+
+        log::info!("D3D12 backend initialized with {BUFFER_COUNT} surfaces");
+        Ok(Self {
+            device: Some(device),
+            window,
+            input_state: InputState::default(),
+            sync_interval: 1,
+            recovery: Recovery::default(),
+        })
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(new_size) => {
+                if new_size.width > 0 && new_size.height > 0 {
+                    // A lost device is made again at the window's size.
+                    if let Some(device) = self.device.as_mut() {
+                        if let Err(err) = device.resize(new_size.width, new_size.height) {
+                            self.failed(err);
+                        }
+                    }
+                    self.request_redraw();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn window_id(&self) -> winit::window::WindowId {
+        self.window.id()
+    }
+
+    fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.window.inner_size()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    fn render(&mut self, draw: DrawFrame) {
+        if self.device.is_none() && !self.recover() {
+            return;
+        }
+        let scale_factor = self.window.scale_factor() as f32;
+        let Some(device) = self.device.as_mut() else {
+            return;
+        };
+        match device.render(draw, scale_factor, self.sync_interval) {
+            Ok(()) => self.recovery.succeeded(),
+            Err(err) => self.failed(err),
+        }
+    }
+
+    fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        match &self.device {
+            Some(device) => GpuMemoryStats::new(
+                &device.direct_context,
+                device.surfaces.iter().flatten().count(),
+                BUFFER_COUNT,
+            ),
+            None => GpuMemoryStats::default(),
+        }
+    }
+
+    fn input_state_mut(&mut self) -> &mut InputState {
+        &mut self.input_state
+    }
+    fn input_state(&self) -> &InputState {
+        &self.input_state
+    }
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    fn next_redraw(&self) -> Option<Instant> {
+        if self.device.is_some() || self.minimized() {
+            return None;
+        }
+        self.recovery.next_attempt_at(Instant::now())
+    }
+
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn set_cursor(&self, cursor: winit::window::CursorIcon) {
+        self.window.set_cursor(cursor);
+    }
+
+    fn set_vsync(&mut self, mode: VsyncMode) -> VsyncMode {
+        self.sync_interval = match mode {
+            VsyncMode::Vsync => 1,
+            // The flip model swap chain lets the compositor show the latest frame. Tearing
+            // would take a swap chain created to allow it.
+            VsyncMode::Immediate | VsyncMode::Mailbox => 0,
+        };
+        match mode {
+            VsyncMode::Vsync => VsyncMode::Vsync,
+            VsyncMode::Immediate | VsyncMode::Mailbox => VsyncMode::Mailbox,
+        }
+    }
+}
+
+impl D3D12Backend {
+    /// Handles what rendering or resizing failed with: a lost device is dropped, to be made
+    /// again for the next frame, which draws the current snapshot. Anything else only drops
+    /// the frame.
+    fn failed(&mut self, err: anyhow::Error) {
+        let Some(device) = self.device.as_mut() else {
+            return;
+        };
+        let Some(reason) = device.lost_reason(&err) else {
+            log::error!("Direct3D 12 frame failed: {err:?}");
+            return;
+        };
+        log::warn!("Direct3D 12 device lost ({reason:?}), making it again: {err:?}");
+        device.lost = true;
+        self.device = None;
+        self.request_redraw();
+    }
+
+    fn minimized(&self) -> bool {
+        let size = self.window.inner_size();
+        size.width == 0 || size.height == 0
+    }
+
+    /// Makes the device again after it was lost, once the attempt [`Recovery`] says to
+    /// wait for is due. Returns whether there is a device to render with.
+    fn recover(&mut self) -> bool {
+        if self.minimized() {
+            // Made again once the window is resized.
+            return false;
+        }
+        let now = Instant::now();
+        let Some(due) = self.recovery.next_attempt_at(now) else {
+            return false;
+        };
+        if due > now {
+            // The event loop draws again then, see `next_redraw`.
+            return false;
+        }
+        let size = self.window.inner_size();
+        match Device::new(&self.window, size.width, size.height) {
+            Ok(device) => {
+                log::info!("Direct3D 12 device made again");
+                self.device = Some(device);
+                true
+            }
+            Err(err) => {
+                self.recovery.failed();
+                if self.recovery.next_attempt().is_some() {
+                    log::warn!("Direct3D 12 device couldn't be made again, retrying: {err:?}");
+                } else {
+                    log::error!(
+                        "Direct3D 12 device couldn't be made again in {MAX_ATTEMPTS} attempts, \
+                         the window stops rendering: {err:?}"
+                    );
+                }
+                false
+            }
+        }
+    }
+}
+
+impl Device {
+    fn new(window: &Window, width: u32, height: u32) -> Result<Self> {
+        let hwnd = HWND(u64::from(window.id()) as *mut _);
 
         let factory: IDXGIFactory4 = unsafe { CreateDXGIFactory1() }?;
         let (adapter, device) = get_hardware_adapter_and_device(&factory)?;
@@ -88,7 +276,7 @@ impl RenderingBackend for D3D12Backend {
             memory_allocator: None,
             protected_context: Protected::No,
         };
-        let direct_context = unsafe { DirectContext::new_d3d(&backend_context, None) }.unwrap();
+        let direct_context = new_direct_context(&backend_context)?;
 
         let swap_chain: IDXGISwapChain3 = unsafe {
             factory.CreateSwapChainForHwnd(
@@ -114,89 +302,89 @@ impl RenderingBackend for D3D12Backend {
         }?
         .cast()?;
 
-        let mut backend = Self {
-            window,
-            factory,
-            backend_context,
-            swap_chain,
-            direct_context,
+        let mut device = Self {
             surfaces: [None, None],
-            input_state: InputState::default(),
-            current_width: width,
-            current_height: height,
+            direct_context,
+            swap_chain,
+            backend_context,
+            factory,
+            width,
+            height,
+            lost: false,
         };
-
-        backend.recreate_surfaces(width, height)?;
-
-        println!("D3D12 backend initialized with {} surfaces.", BUFFER_COUNT);
-        Ok(backend)
+        device.recreate_surfaces()?;
+        Ok(device)
     }
 
-    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::Resized(new_size) => {
-                if new_size.width > 0 && new_size.height > 0 {
-                    // Perform safe resize
-                    if let Err(err) = self.resize(new_size.width, new_size.height) {
-                        eprintln!("Resize failed: {:?}", err);
-                    }
-                    self.request_redraw();
-                }
-                true
-            }
-            _ => false,
+    fn render(&mut self, draw: DrawFrame, scale_factor: f32, sync_interval: u32) -> Result<()> {
+        let index = unsafe { self.swap_chain.GetCurrentBackBufferIndex() } as usize;
+        if self.surfaces[index].is_none() {
+            // Resizing failed after releasing them.
+            self.attach()?;
         }
-    }
-
-    fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.window.inner_size()
-    }
-
-    fn scale_factor(&self) -> f64 {
-        self.window.scale_factor()
-    }
-
-    fn render(&mut self, params: &mut Params) {
-        let index = unsafe { self.swap_chain.GetCurrentBackBufferIndex() };
-        if self.surfaces[index as usize].is_none() {
-            // Attempt to restore valid surfaces to avoid panic
-            let _ = self.recreate_surfaces(self.current_width, self.current_height);
-        }
-        let Some((surface, _)) = self.surfaces[index as usize].as_mut() else {
-            // Give up this frame rather than panic
-            return;
+        let Some((surface, _)) = self.surfaces[index].as_mut() else {
+            return Ok(());
         };
         let canvas = surface.canvas();
 
-        let scale_factor = self.window.scale_factor() as f32;
         canvas.save();
         canvas.scale((scale_factor, scale_factor));
-        (params.on_draw)(canvas);
+        // The flip discard swap chain doesn't keep earlier frames.
+        draw(canvas, 0);
         canvas.restore();
 
         self.direct_context.flush_and_submit_surface(surface, None);
         // Extra flush to ensure state transitions back to PRESENT/COMMON before Present
         self.direct_context.flush_and_submit();
-        unsafe { self.swap_chain.Present(1, DXGI_PRESENT::default()) }.unwrap();
+        unsafe {
+            self.swap_chain
+                .Present(sync_interval, DXGI_PRESENT::default())
+        }
+        .ok()?;
+        Ok(())
     }
 
-    fn input_state_mut(&mut self) -> &mut InputState {
-        &mut self.input_state
+    fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        self.detach()?;
+        unsafe {
+            self.swap_chain.ResizeBuffers(
+                BUFFER_COUNT as u32,
+                width,
+                height,
+                DXGI_FORMAT_R8G8B8A8_UNORM,
+                Default::default(),
+            )
+        }?;
+        self.width = width;
+        self.height = height;
+        self.attach()
     }
-    fn input_state(&self) -> &InputState {
-        &self.input_state
+
+    /// Releases everything Skia holds of the swap chain's buffers and waits for the GPU to
+    /// finish with them, so they can be resized or the device dropped.
+    fn detach(&mut self) -> Result<()> {
+        // Ensure GPU is idle and release Skia refs
+        self.direct_context.flush_and_submit();
+        self.drop_surfaces();
+        self.direct_context.flush_and_submit();
+        // Fully abandon Skia context to drop any cached refs to old swapchain buffers
+        self.direct_context.abandon();
+        // Ensure GPU has finished all work on the old backbuffers
+        self.wait_for_gpu_idle()
     }
-    fn request_redraw(&self) {
-        self.window.request_redraw();
+
+    /// Makes a fresh Skia context after [`Self::detach`], without stale refs, and the
+    /// surfaces for the buffers at their size.
+    fn attach(&mut self) -> Result<()> {
+        self.direct_context = new_direct_context(&self.backend_context)?;
+        self.recreate_surfaces()
     }
-}
 
-impl D3D12Backend {
-    fn recreate_surfaces(&mut self, width: u32, height: u32) -> Result<()> {
+    fn recreate_surfaces(&mut self) -> Result<()> {
         for i in 0..BUFFER_COUNT {
-            let resource = unsafe { self.swap_chain.GetBuffer(i as u32).unwrap() };
+            let resource = unsafe { self.swap_chain.GetBuffer(i as u32) }?;
             let backend_render_target = BackendRenderTarget::new_d3d(
-                (width as i32, height as i32),
+                (self.width as i32, self.height as i32),
                 &TextureResourceInfo {
                     resource,
                     alloc: None,
@@ -217,11 +405,9 @@ impl D3D12Backend {
                 None,
                 None,
             )
-            .unwrap();
+            .context("Failed to wrap a swap chain buffer in a surface")?;
             self.surfaces[i] = Some((surface, backend_render_target));
         }
-        self.current_width = width;
-        self.current_height = height;
         Ok(())
     }
 
@@ -232,45 +418,18 @@ impl D3D12Backend {
                 drop(backend);
             }
         }
-        self.direct_context.flush_and_submit();
+        release_dropped_surfaces(&mut self.direct_context);
     }
 
-    fn resize(&mut self, width: u32, height: u32) -> Result<()> {
-        // Ensure GPU is idle and release Skia refs
-        self.direct_context.flush_and_submit();
-        self.drop_surfaces();
-        self.direct_context.flush_and_submit();
-        // Fully abandon Skia context to drop any cached refs to old swapchain buffers
-        self.direct_context.abandon();
-
-        // Ensure GPU has finished all work on the old backbuffers
-        self.wait_for_gpu_idle()?;
-
-        // Resize swap chain buffers
-        let resize_result = unsafe {
-            self.swap_chain.ResizeBuffers(
-                BUFFER_COUNT as u32,
-                width,
-                height,
-                DXGI_FORMAT_R8G8B8A8_UNORM,
-                Default::default(),
-            )
-        };
-
-        // Recreate DirectContext AFTER resize to ensure fresh Skia state without stale refs
-        self.direct_context =
-            unsafe { DirectContext::new_d3d(&self.backend_context, None) }.unwrap();
-
-        match resize_result {
-            Ok(()) => {
-                self.recreate_surfaces(width, height)?;
-            }
-            Err(e) => {
-                eprintln!("Resize failed: {:?}", e);
-                let _ = self.recreate_surfaces(self.current_width, self.current_height);
+    /// Why the device is gone, if `err` is because it is: what Present or DXGI failed with,
+    /// or what the device tells for failures reported without an `HRESULT`, as by Skia.
+    fn lost_reason(&self, err: &anyhow::Error) -> Option<windows::core::Error> {
+        if let Some(err) = err.downcast_ref::<windows::core::Error>() {
+            if Failure::of(err.code().0) == Failure::DeviceLost {
+                return Some(err.clone());
             }
         }
-        Ok(())
+        unsafe { self.backend_context.device.GetDeviceRemovedReason() }.err()
     }
 
     fn wait_for_gpu_idle(&self) -> Result<()> {
@@ -303,19 +462,23 @@ impl D3D12Backend {
     }
 }
 
-impl Drop for D3D12Backend {
+impl Drop for Device {
     fn drop(&mut self) {
+        if self.lost {
+            // Nothing made with a lost device can be flushed or waited for.
+            self.direct_context.abandon();
+            return;
+        }
         // Ensure Skia finishes and releases all refs before Device/SwapChain are dropped
-        self.direct_context.flush_and_submit();
-        self.drop_surfaces();
-        self.direct_context.flush_and_submit();
-        // Wait for GPU idle to ensure swapchain/device have no pending work
-        let _ = self.wait_for_gpu_idle();
-        // Best-effort: make Skia forget any cached GPU refs
-        self.direct_context.abandon();
+        let _ = self.detach();
     }
 }
 
+fn new_direct_context(backend_context: &BackendContext) -> Result<DirectContext> {
+    unsafe { DirectContext::new_d3d(backend_context, None) }
+        .context("Failed to create Direct3D 12 DirectContext")
+}
+
 fn get_hardware_adapter_and_device(
     factory: &IDXGIFactory4,
 ) -> windows::core::Result<(IDXGIAdapter1, ID3D12Device)> {
@@ -329,8 +492,11 @@ fn get_hardware_adapter_and_device(
         }
         let mut device = None;
         if unsafe { D3D12CreateDevice(&adapter, D3D_FEATURE_LEVEL_11_0, &mut device) }.is_ok() {
-            return Ok((adapter, device.unwrap()));
+            if let Some(device) = device {
+                return Ok((adapter, device));
+            }
         }
     }
-    unreachable!()
+    // EnumAdapters1 fails with DXGI_ERROR_NOT_FOUND past the last adapter.
+    Err(DXGI_ERROR_NOT_FOUND.into())
 }
@@ -0,0 +1,99 @@
+//! What a GPU backend does when rendering fails: whether its device is lost and has to be
+//! made again, and how often to try. Apart from the graphics APIs so it's tested on every
+//! platform, though only the D3D12 and OpenGL backends lose their device this way.
+
+use std::time::{Duration, Instant};
+
+/// The `HRESULT`s DXGI and Direct3D fail with once the device is gone: removed or reset,
+/// as by sleep or a driver update, hung, or failed in its driver.
+#[cfg_attr(not(target_os = "windows"), allow(unused))]
+const DEVICE_LOST: [u32; 4] = [
+    0x887A_0005, // DXGI_ERROR_DEVICE_REMOVED
+    0x887A_0006, // DXGI_ERROR_DEVICE_HUNG
+    0x887A_0007, // DXGI_ERROR_DEVICE_RESET
+    0x887A_0020, // DXGI_ERROR_DRIVER_INTERNAL_ERROR
+];
+
+/// What `glGetGraphicsResetStatus` returns while the OpenGL context wasn't reset.
+#[cfg_attr(not(target_os = "linux"), allow(unused))]
+const GL_NO_ERROR: u32 = 0;
+
+/// Attempts at making a lost device again before the window stops rendering.
+pub(crate) const MAX_ATTEMPTS: u32 = 8;
+
+/// How long to wait after the first attempt fails, twice as long after each next one.
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(50);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Failure {
+    /// Everything made with the device is gone: it has to be made again.
+    DeviceLost,
+    /// Only the frame failed.
+    Frame,
+}
+
+impl Failure {
+    #[cfg_attr(not(target_os = "windows"), allow(unused))]
+    pub fn of(hresult: i32) -> Self {
+        if DEVICE_LOST.contains(&(hresult as u32)) {
+            Failure::DeviceLost
+        } else {
+            Failure::Frame
+        }
+    }
+
+    /// What a `GL_KHR_robustness` reset `status` says of the OpenGL context: whether the
+    /// GPU reset caused it, it didn't or it's unknown, a context that was reset is lost.
+    /// `None` for `GL_NO_ERROR`.
+    #[cfg_attr(not(target_os = "linux"), allow(unused))]
+    pub fn of_reset_status(status: u32) -> Option<Self> {
+        (status != GL_NO_ERROR).then_some(Failure::DeviceLost)
+    }
+}
+
+/// The attempts at making a lost device again. A device can take a while to come back, as
+/// after a driver update, so they wait longer after each one that fails, and end after
+/// [`MAX_ATTEMPTS`]. The event loop isn't blocked meanwhile: a backend asks for the redraw
+/// that makes the next attempt, see [`super::RenderingBackend::next_redraw`].
+#[derive(Debug, Default)]
+pub(crate) struct Recovery {
+    failed_attempts: u32,
+    /// When the last attempt failed.
+    failed_at: Option<Instant>,
+}
+
+impl Recovery {
+    /// How long to wait before the next attempt, or `None` to give up.
+    pub fn next_attempt(&self) -> Option<Duration> {
+        match self.failed_attempts {
+            0 => Some(Duration::ZERO),
+            failed if failed >= MAX_ATTEMPTS => None,
+            failed => Some(
+                FIRST_RETRY_DELAY
+                    .saturating_mul(1 << (failed - 1))
+                    .min(MAX_RETRY_DELAY),
+            ),
+        }
+    }
+
+    /// When the next attempt is due, `now` if none failed yet, or `None` to give up.
+    pub fn next_attempt_at(&self, now: Instant) -> Option<Instant> {
+        let delay = self.next_attempt()?;
+        Some(self.failed_at.map_or(now, |failed_at| failed_at + delay))
+    }
+
+    pub fn failed(&mut self) {
+        self.failed_attempts += 1;
+        self.failed_at = Some(Instant::now());
+    }
+
+    /// A frame was presented, so a device lost later gets all attempts again.
+    pub fn succeeded(&mut self) {
+        self.failed_attempts = 0;
+        self.failed_at = None;
+    }
+}
+
+#[cfg(test)]
+mod device_loss_tests;
@@ -0,0 +1,85 @@
+use super::{Failure, Recovery, MAX_ATTEMPTS};
+use std::time::{Duration, Instant};
+
+#[test]
+fn removed_and_reset_devices_are_lost() {
+    let lost = [0x887A_0005_u32, 0x887A_0006, 0x887A_0007, 0x887A_0020];
+    for hresult in lost {
+        assert_eq!(
+            Failure::of(hresult as i32),
+            Failure::DeviceLost,
+            "{hresult:#x}"
+        );
+    }
+    // E_OUTOFMEMORY, DXGI_ERROR_INVALID_CALL and DXGI_ERROR_WAS_STILL_DRAWING.
+    for hresult in [0x8007_000E_u32, 0x887A_0001, 0x887A_000A] {
+        assert_eq!(Failure::of(hresult as i32), Failure::Frame, "{hresult:#x}");
+    }
+}
+
+#[test]
+fn reset_opengl_contexts_are_lost() {
+    assert_eq!(Failure::of_reset_status(0), None);
+    // GL_GUILTY_CONTEXT_RESET, GL_INNOCENT_CONTEXT_RESET and GL_UNKNOWN_CONTEXT_RESET.
+    for status in [0x8253, 0x8254, 0x8255] {
+        assert_eq!(
+            Failure::of_reset_status(status),
+            Some(Failure::DeviceLost),
+            "{status:#x}"
+        );
+    }
+}
+
+#[test]
+fn attempts_wait_longer_after_each_failure_and_end() {
+    let mut recovery = Recovery::default();
+    let mut delays = Vec::new();
+    while let Some(delay) = recovery.next_attempt() {
+        delays.push(delay);
+        recovery.failed();
+    }
+    let ms = Duration::from_millis;
+    assert_eq!(
+        delays,
+        [
+            ms(0),
+            ms(50),
+            ms(100),
+            ms(200),
+            ms(400),
+            ms(800),
+            ms(1000),
+            ms(1000)
+        ]
+    );
+    assert_eq!(delays.len(), MAX_ATTEMPTS as usize);
+}
+
+#[test]
+fn a_presented_frame_restores_the_attempts() {
+    let mut recovery = Recovery::default();
+    for _ in 0..MAX_ATTEMPTS {
+        recovery.failed();
+    }
+    assert_eq!(recovery.next_attempt(), None);
+    recovery.succeeded();
+    assert_eq!(recovery.next_attempt(), Some(Duration::ZERO));
+}
+
+#[test]
+fn attempts_are_due_once_their_delay_after_the_last_failure_passed() {
+    let mut recovery = Recovery::default();
+    let now = Instant::now();
+    assert_eq!(recovery.next_attempt_at(now), Some(now));
+
+    recovery.failed();
+    let due = recovery.next_attempt_at(now).unwrap();
+    assert!(due >= now + Duration::from_millis(50));
+    // The same until the attempt is made, however often the window is redrawn.
+    assert_eq!(recovery.next_attempt_at(due), Some(due));
+
+    for _ in 1..MAX_ATTEMPTS {
+        recovery.failed();
+    }
+    assert_eq!(recovery.next_attempt_at(now), None);
+}
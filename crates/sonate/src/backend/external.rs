@@ -0,0 +1,167 @@
+use super::GpuMemoryStats;
+use skia_safe::Canvas;
+
+/// A frame rendered by [`crate::Engine::render_frame`], valid until the next call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FrameHandle {
+    /// A `GL_TEXTURE_2D` of the OpenGL context that was current, RGBA8 with premultiplied
+    /// alpha, its first row at the bottom as OpenGL expects.
+    Gl {
+        texture: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+/// Renders into an offscreen texture of the host's graphics context, which the host
+/// composites and presents itself. Only OpenGL on Linux is supported so far.
+pub struct ExternalBackend {
+    target: platform::Target,
+}
+
+impl ExternalBackend {
+    /// A target for the graphics context current on this thread; `None` without one, or
+    /// where external rendering isn't supported.
+    pub fn new() -> Option<Self> {
+        Some(Self {
+            target: platform::Target::new()?,
+        })
+    }
+
+    /// Render a frame for a `width` by `height` viewport in logical pixels at `scale_factor`
+    /// device pixels per logical pixel; `draw` works in logical pixels. `None` if the size is
+    /// empty or the texture couldn't be created.
+    pub fn render(
+        &mut self,
+        width: f64,
+        height: f64,
+        scale_factor: f64,
+        draw: &mut dyn FnMut(&Canvas),
+    ) -> Option<FrameHandle> {
+        let device_width = (width * scale_factor).ceil();
+        let device_height = (height * scale_factor).ceil();
+        if !(device_width >= 1.0 && device_height >= 1.0) {
+            return None;
+        }
+
+        let size = (device_width as i32, device_height as i32);
+        self.target.render(size, scale_factor as f32, draw)
+    }
+
+    pub fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        self.target.gpu_memory_stats()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::FrameHandle;
+    use crate::backend::{release_dropped_surfaces, GpuMemoryStats};
+    use skia_safe::{
+        gpu::{self, DirectContext, SurfaceOrigin},
+        surface::BackendHandleAccess,
+        AlphaType, Canvas, Color, ColorType, ImageInfo, Surface,
+    };
+
+    pub struct Target {
+        /// `None` until the first frame, and while a resize replaces it.
+        surface: Option<Surface>,
+        context: DirectContext,
+    }
+
+    impl Drop for Target {
+        fn drop(&mut self) {
+            // The host may have destroyed its context already.
+            self.context.release_resources_and_abandon();
+        }
+    }
+
+    impl Target {
+        pub fn new() -> Option<Self> {
+            let interface = gpu::gl::Interface::new_native()?;
+            Some(Self {
+                surface: None,
+                context: gpu::direct_contexts::make_gl(interface, None)?,
+            })
+        }
+
+        pub fn render(
+            &mut self,
+            size: (i32, i32),
+            scale_factor: f32,
+            draw: &mut dyn FnMut(&Canvas),
+        ) -> Option<FrameHandle> {
+            // The host changed the context's state since the last frame.
+            self.context.reset(None);
+
+            let current = self.surface.as_ref().map(|s| (s.width(), s.height()));
+            if current != Some(size) {
+                self.surface = None;
+                release_dropped_surfaces(&mut self.context);
+                let info = ImageInfo::new(size, ColorType::RGBA8888, AlphaType::Premul, None);
+                self.surface = gpu::surfaces::render_target(
+                    &mut self.context,
+                    gpu::Budgeted::Yes,
+                    &info,
+                    None,
+                    SurfaceOrigin::BottomLeft,
+                    None,
+                    false,
+                    None,
+                );
+            }
+            let surface = self.surface.as_mut()?;
+
+            let canvas = surface.canvas();
+            canvas.clear(Color::TRANSPARENT);
+            canvas.save();
+            canvas.scale((scale_factor, scale_factor));
+            draw(canvas);
+            canvas.restore();
+
+            let texture =
+                gpu::surfaces::get_backend_texture(surface, BackendHandleAccess::FlushRead)?;
+            self.context.flush_and_submit();
+            let info = gpu::backend_textures::get_gl_texture_info(&texture)?;
+            Some(FrameHandle::Gl {
+                texture: info.id,
+                width: size.0 as u32,
+                height: size.1 as u32,
+            })
+        }
+
+        pub fn gpu_memory_stats(&self) -> GpuMemoryStats {
+            GpuMemoryStats::new(&self.context, self.surface.iter().count(), 0)
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::FrameHandle;
+    use crate::backend::GpuMemoryStats;
+    use skia_safe::Canvas;
+
+    /// Never created.
+    pub enum Target {}
+
+    impl Target {
+        pub fn new() -> Option<Self> {
+            None
+        }
+
+        pub fn render(
+            &mut self,
+            _size: (i32, i32),
+            _scale_factor: f32,
+            _draw: &mut dyn FnMut(&Canvas),
+        ) -> Option<FrameHandle> {
+            match *self {}
+        }
+
+        pub fn gpu_memory_stats(&self) -> GpuMemoryStats {
+            match *self {}
+        }
+    }
+}
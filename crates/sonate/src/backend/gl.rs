@@ -1,24 +1,30 @@
-use super::{InputState, Params, RenderingBackend};
-use anyhow::Result;
+use super::device_loss::{Failure, Recovery, MAX_ATTEMPTS};
+use super::{
+    release_dropped_surfaces, DeviceRect, DrawFrame, GpuMemoryStats, InputState, RenderingBackend,
+    VsyncMode,
+};
+use crate::windowing::{place_window, rescale_physical_size, sanitize_scale_factor, WindowOptions};
+use anyhow::{Context, Result};
 use raw_window_handle::HasWindowHandle;
 use skia_safe::{
     gpu::{self, backend_render_targets, gl::FramebufferInfo, SurfaceOrigin},
     ColorType, Surface,
 };
-use std::{ffi::CString, num::NonZeroU32};
+use std::{ffi::CString, num::NonZeroU32, time::Instant};
 use winit::{
-    dpi::{LogicalSize, Size},
+    dpi::{LogicalSize, PhysicalSize, Size},
     event::WindowEvent,
     event_loop::ActiveEventLoop,
     window::{Window, WindowAttributes},
 };
 
 use glutin::{
-    config::{ConfigTemplateBuilder, GlConfig},
-    context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext},
+    config::{Config, ConfigTemplateBuilder, GlConfig},
+    context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Robustness},
     display::{GetGlDisplay, GlDisplay},
+    error::ErrorKind,
     prelude::{GlSurface, NotCurrentGlContext},
-    surface::{Surface as GlutinSurface, SurfaceAttributesBuilder, WindowSurface},
+    surface::{Surface as GlutinSurface, SurfaceAttributesBuilder, SwapInterval, WindowSurface},
 };
 use glutin_winit::DisplayBuilder;
 
@@ -26,65 +32,56 @@ use glutin_winit::DisplayBuilder;
 ///
 /// This uses glutin to create an OpenGL context/surface and Skia's GL backend to render.
 pub struct OpenGlBackend {
-    env: Env,
-    fb_info: FramebufferInfo,
-    num_samples: usize,
-    stencil_size: usize,
+    // Declared before the window so it drops first. None while the context is lost.
+    env: Option<Env>,
+    window: Window,
+    gl_config: Config,
     input_state: InputState,
+    /// Physical pixels per logical pixel, as of the last `ScaleFactorChanged`: what frames are
+    /// drawn with, and what windowing converts input with too.
+    scale_factor: f64,
+    /// How frames are presented, set again on contexts made after one was lost.
+    swap_interval: SwapInterval,
+    /// The attempts at making the context again since it was lost.
+    recovery: Recovery,
 }
 
 // Guarantee drop order: Window must be dropped after DirectContext.
 // See: https://github.com/rust-skia/rust-skia/issues/476
+/// The OpenGL context and everything made with it, made again together when it is lost.
 struct Env {
-    surface: Surface,
+    /// `None` while a resize replaces it, or if none could be made at the window's size.
+    surface: Option<Surface>,
     gl_surface: GlutinSurface<WindowSurface>,
     gr_context: skia_safe::gpu::DirectContext,
     gl_context: PossiblyCurrentContext,
-    window: Window,
+    fb_info: FramebufferInfo,
+    num_samples: usize,
+    stencil_size: usize,
+    /// The framebuffer's size in physical pixels.
+    size: PhysicalSize<u32>,
+    /// Set once a GPU reset or EGL lost the context, so dropping doesn't call into it.
+    lost: bool,
 }
 
 impl Drop for Env {
     fn drop(&mut self) {
+        if self.lost {
+            // Nothing can be released through a lost context.
+            self.gr_context.abandon();
+            return;
+        }
         // Prevent potential driver crashes on teardown.
         self.gr_context.release_resources_and_abandon();
     }
 }
 
-impl OpenGlBackend {
-    fn create_surface(
-        window: &Window,
-        fb_info: FramebufferInfo,
-        gr_context: &mut skia_safe::gpu::DirectContext,
-        num_samples: usize,
-        stencil_size: usize,
-    ) -> Surface {
-        let size = window.inner_size();
-        let size = (
-            size.width.try_into().expect("Could not convert width"),
-            size.height.try_into().expect("Could not convert height"),
-        );
-        let backend_render_target =
-            backend_render_targets::make_gl(size, num_samples, stencil_size, fb_info);
-
-        gpu::surfaces::wrap_backend_render_target(
-            gr_context,
-            &backend_render_target,
-            SurfaceOrigin::BottomLeft,
-            ColorType::RGBA8888,
-            None,
-            None,
-        )
-        .expect("Could not create skia surface")
-    }
-}
-
 impl RenderingBackend for OpenGlBackend {
-    fn new(event_loop: &ActiveEventLoop) -> Result<Self> {
-        use gl::types::GLint;
-
+    fn new(event_loop: &ActiveEventLoop, options: &WindowOptions) -> Result<Self> {
         let window_attributes = WindowAttributes::default()
-            .with_title("Sonate CSS - OpenGL")
-            .with_inner_size(Size::new(LogicalSize::new(800, 800)));
+            .with_title(options.title_or("Sonate CSS - OpenGL"))
+            .with_inner_size(Size::new(LogicalSize::new(options.width, options.height)));
+        let window_attributes = place_window(window_attributes, event_loop, options);
 
         let template = ConfigTemplateBuilder::new()
             .with_alpha_size(8)
@@ -108,64 +105,245 @@ impl RenderingBackend for OpenGlBackend {
             })
             .map_err(|e| anyhow::anyhow!(e.to_string()))?;
 
-        let window = window.expect("Could not create window with OpenGL context");
+        let window = window.context("Could not create window with OpenGL context")?;
+        let env = Env::new(&window, &gl_config, window.inner_size())?;
+        let scale_factor = sanitize_scale_factor(window.scale_factor());
+
+        Ok(Self {
+            env: Some(env),
+            window,
+            gl_config,
+            input_state: InputState::default(),
+            scale_factor,
+            swap_interval: SwapInterval::Wait(NonZeroU32::MIN),
+            recovery: Recovery::default(),
+        })
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(physical_size) => {
+                // A lost context is made again at the window's size.
+                if let Some(env) = self.env.as_mut() {
+                    env.resize(*physical_size);
+                }
+                true
+            }
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // The logical size stays, so the framebuffer takes the exact physical size it
+                // has at the new scale, which is fractional on Wayland at 125% or 150%. The
+                // `Resized` winit sends next corrects it if the compositor picked another.
+                let scale_factor = sanitize_scale_factor(*scale_factor);
+                if let Some(env) = self.env.as_mut() {
+                    let size = rescale_physical_size(env.size, self.scale_factor, scale_factor);
+                    env.resize(size);
+                }
+                self.scale_factor = scale_factor;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn window_id(&self) -> winit::window::WindowId {
+        self.window.id()
+    }
+
+    fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.window.inner_size()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    fn render(&mut self, draw: DrawFrame) {
+        if self.env.is_none() && !self.recover() {
+            return;
+        }
+        let Some(env) = self.env.as_mut() else {
+            return;
+        };
+        match env.render(draw, self.scale_factor) {
+            Ok(()) => self.recovery.succeeded(),
+            Err(err) => self.failed(err),
+        }
+    }
+
+    fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        match &self.env {
+            Some(env) => GpuMemoryStats::new(&env.gr_context, env.surface.iter().count(), 0),
+            None => GpuMemoryStats::default(),
+        }
+    }
+
+    fn input_state_mut(&mut self) -> &mut InputState {
+        &mut self.input_state
+    }
+
+    fn input_state(&self) -> &InputState {
+        &self.input_state
+    }
+
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    fn next_redraw(&self) -> Option<Instant> {
+        if self.env.is_some() || self.minimized() {
+            return None;
+        }
+        self.recovery.next_attempt_at(Instant::now())
+    }
+
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn set_cursor(&self, cursor: winit::window::CursorIcon) {
+        self.window.set_cursor(cursor);
+    }
+
+    fn set_vsync(&mut self, mode: VsyncMode) -> VsyncMode {
+        // OpenGL only waits for the refresh or doesn't.
+        let (interval, presents_with) = match mode {
+            VsyncMode::Vsync | VsyncMode::Mailbox => {
+                (SwapInterval::Wait(NonZeroU32::MIN), VsyncMode::Vsync)
+            }
+            VsyncMode::Immediate => (SwapInterval::DontWait, VsyncMode::Immediate),
+        };
+        self.swap_interval = interval;
+        let Some(env) = self.env.as_ref() else {
+            // Set on the context once it's made again.
+            return presents_with;
+        };
+        match env.set_swap_interval(interval) {
+            Ok(()) => presents_with,
+            Err(err) => {
+                // The driver's default stays, which is to wait.
+                log::warn!("Could not set the OpenGL swap interval: {err}");
+                VsyncMode::Vsync
+            }
+        }
+    }
+}
+
+impl OpenGlBackend {
+    /// Handles what rendering failed with: a lost context is dropped, to be made again for
+    /// the next frame, which draws the current snapshot. Anything else only drops the frame.
+    fn failed(&mut self, err: anyhow::Error) {
+        let Some(env) = self.env.as_ref() else {
+            return;
+        };
+        if !env.lost {
+            log::error!("OpenGL frame failed: {err:?}");
+            return;
+        }
+        log::warn!("OpenGL context lost, making it again: {err:?}");
+        self.env = None;
+        self.request_redraw();
+    }
+
+    fn minimized(&self) -> bool {
+        let size = self.window.inner_size();
+        size.width == 0 || size.height == 0
+    }
+
+    /// Makes the context again after it was lost, once the attempt [`Recovery`] says to
+    /// wait for is due. Returns whether there is a context to render with.
+    fn recover(&mut self) -> bool {
+        if self.minimized() {
+            // Made again once the window is resized.
+            return false;
+        }
+        let now = Instant::now();
+        let Some(due) = self.recovery.next_attempt_at(now) else {
+            return false;
+        };
+        if due > now {
+            // The event loop draws again then, see `next_redraw`.
+            return false;
+        }
+        let size = self.window.inner_size();
+        match Env::new(&self.window, &self.gl_config, size) {
+            Ok(env) => {
+                log::info!("OpenGL context made again");
+                if let Err(err) = env.set_swap_interval(self.swap_interval) {
+                    log::warn!("Could not set the OpenGL swap interval: {err}");
+                }
+                self.env = Some(env);
+                true
+            }
+            Err(err) => {
+                self.recovery.failed();
+                if self.recovery.next_attempt().is_some() {
+                    log::warn!("OpenGL context couldn't be made again, retrying: {err:?}");
+                } else {
+                    log::error!(
+                        "OpenGL context couldn't be made again in {MAX_ATTEMPTS} attempts, \
+                         the window stops rendering: {err:?}"
+                    );
+                }
+                false
+            }
+        }
+    }
+}
+
+impl Env {
+    fn new(window: &Window, gl_config: &Config, size: PhysicalSize<u32>) -> Result<Self> {
+        use gl::types::GLint;
+
         let window_handle = window
             .window_handle()
-            .expect("Failed to retrieve RawWindowHandle");
+            .context("Failed to retrieve RawWindowHandle")?;
         let raw_window_handle = window_handle.as_raw();
 
-        let context_attributes = ContextAttributesBuilder::new().build(Some(raw_window_handle));
-        let fallback_context_attributes = ContextAttributesBuilder::new()
-            .with_context_api(ContextApi::Gles(None))
-            .build(Some(raw_window_handle));
-
-        let not_current_gl_context = unsafe {
-            gl_config
-                .display()
-                .create_context(&gl_config, &context_attributes)
-                .unwrap_or_else(|_| {
-                    gl_config
-                        .display()
-                        .create_context(&gl_config, &fallback_context_attributes)
-                        .expect("failed to create context")
-                })
-        };
-
-        let (width, height): (u32, u32) = window.inner_size().into();
+        // A robust context tells when a GPU reset lost it, e.g. when the GPU hangs or the
+        // laptop switches to a dock's. Drivers without GL_KHR_robustness get a plain one.
+        let context_attributes = [
+            ContextAttributesBuilder::new()
+                .with_robustness(Robustness::RobustLoseContextOnReset)
+                .build(Some(raw_window_handle)),
+            ContextAttributesBuilder::new().build(Some(raw_window_handle)),
+            ContextAttributesBuilder::new()
+                .with_context_api(ContextApi::Gles(None))
+                .build(Some(raw_window_handle)),
+        ];
+        let display = gl_config.display();
+        let not_current_gl_context = context_attributes
+            .iter()
+            .find_map(|attributes| unsafe { display.create_context(gl_config, attributes) }.ok())
+            .context("Could not create an OpenGL or OpenGL ES context")?;
+
+        let (width, height) = surface_size(size);
         let attrs = SurfaceAttributesBuilder::<WindowSurface>::new().build(
             raw_window_handle,
-            NonZeroU32::new(width.max(1)).unwrap(),
-            NonZeroU32::new(height.max(1)).unwrap(),
+            width,
+            height,
         );
 
         let gl_surface = unsafe {
-            gl_config
-                .display()
-                .create_window_surface(&gl_config, &attrs)
-                .expect("Could not create gl window surface")
+            display
+                .create_window_surface(gl_config, &attrs)
+                .context("Could not create gl window surface")?
         };
 
         let gl_context = not_current_gl_context
             .make_current(&gl_surface)
-            .expect("Could not make GL context current when setting up skia renderer");
+            .context("Could not make GL context current when setting up skia renderer")?;
 
-        gl::load_with(|s| {
-            gl_config
-                .display()
-                .get_proc_address(CString::new(s).unwrap().as_c_str())
-        });
+        gl::load_with(|s| display.get_proc_address(CString::new(s).unwrap().as_c_str()));
 
         let interface = skia_safe::gpu::gl::Interface::new_load_with(|name| {
             if name == "eglGetCurrentDisplay" {
                 return std::ptr::null();
             }
-            gl_config
-                .display()
-                .get_proc_address(CString::new(name).unwrap().as_c_str())
+            display.get_proc_address(CString::new(name).unwrap().as_c_str())
         })
         .ok_or_else(|| anyhow::anyhow!("Could not create Skia GL interface"))?;
 
-        let mut gr_context = skia_safe::gpu::direct_contexts::make_gl(interface, None)
+        let gr_context = skia_safe::gpu::direct_contexts::make_gl(interface, None)
             .ok_or_else(|| anyhow::anyhow!("Could not create Skia GL direct context"))?;
 
         let fb_info = {
@@ -173,85 +351,130 @@ impl RenderingBackend for OpenGlBackend {
             unsafe { gl::GetIntegerv(gl::FRAMEBUFFER_BINDING, &mut fboid) };
 
             FramebufferInfo {
-                fboid: fboid.try_into().unwrap(),
+                fboid: fboid.try_into()?,
                 format: skia_safe::gpu::gl::Format::RGBA8.into(),
                 ..Default::default()
             }
         };
 
-        let num_samples = gl_config.num_samples() as usize;
-        let stencil_size = gl_config.stencil_size() as usize;
-        let surface =
-            Self::create_surface(&window, fb_info, &mut gr_context, num_samples, stencil_size);
-
-        Ok(Self {
-            env: Env {
-                surface,
-                gl_surface,
-                gr_context,
-                gl_context,
-                window,
-            },
+        let mut env = Self {
+            surface: None,
+            gl_surface,
+            gr_context,
+            gl_context,
             fb_info,
-            num_samples,
-            stencil_size,
-            input_state: InputState::default(),
-        })
+            num_samples: gl_config.num_samples() as usize,
+            stencil_size: gl_config.stencil_size() as usize,
+            size,
+            lost: false,
+        };
+        env.surface = Some(
+            env.create_surface()
+                .context("Could not create skia surface")?,
+        );
+        Ok(env)
     }
 
-    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
-        match event {
-            WindowEvent::Resized(physical_size) => {
-                let (width, height): (u32, u32) = (*physical_size).into();
-
-                self.env.gl_surface.resize(
-                    &self.env.gl_context,
-                    NonZeroU32::new(width.max(1)).unwrap(),
-                    NonZeroU32::new(height.max(1)).unwrap(),
-                );
-
-                self.env.surface = Self::create_surface(
-                    &self.env.window,
-                    self.fb_info,
-                    &mut self.env.gr_context,
-                    self.num_samples,
-                    self.stencil_size,
-                );
-                true
-            }
-            _ => false,
-        }
-    }
+    /// A Skia surface over the default framebuffer, at its exact size in physical pixels.
+    fn create_surface(&mut self) -> Option<Surface> {
+        let size = (
+            self.size.width.try_into().ok()?,
+            self.size.height.try_into().ok()?,
+        );
+        let backend_render_target = backend_render_targets::make_gl(
+            size,
+            self.num_samples,
+            self.stencil_size,
+            self.fb_info,
+        );
 
-    fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
-        self.env.window.inner_size()
+        gpu::surfaces::wrap_backend_render_target(
+            &mut self.gr_context,
+            &backend_render_target,
+            SurfaceOrigin::BottomLeft,
+            ColorType::RGBA8888,
+            None,
+            None,
+        )
     }
 
-    fn scale_factor(&self) -> f64 {
-        self.env.window.scale_factor()
+    fn resize(&mut self, size: PhysicalSize<u32>) {
+        // Let go of the old surface before the framebuffer changes size under it.
+        self.gr_context.flush_and_submit();
+        self.surface = None;
+        release_dropped_surfaces(&mut self.gr_context);
+
+        let (width, height) = surface_size(size);
+        self.gl_surface.resize(&self.gl_context, width, height);
+        self.size = size;
+        self.surface = self.create_surface();
     }
 
-    fn render(&mut self, params: &mut Params) {
-        let canvas = self.env.surface.canvas();
-        let scale_factor = self.env.window.scale_factor() as f32;
+    fn render(&mut self, draw: DrawFrame, scale_factor: f64) -> Result<()> {
+        // 0 where the driver doesn't tell.
+        let buffer_age = self.gl_surface.buffer_age();
+        let Some(surface) = self.surface.as_mut() else {
+            return Ok(());
+        };
+        let canvas = surface.canvas();
         canvas.save();
-        canvas.scale((scale_factor, scale_factor));
-        (params.on_draw)(canvas);
+        canvas.scale((scale_factor as f32, scale_factor as f32));
+        let damage = draw(canvas, buffer_age);
         canvas.restore();
 
-        self.env.gr_context.flush_and_submit();
-        let _ = self.env.gl_surface.swap_buffers(&self.env.gl_context);
-    }
+        self.gr_context.flush_and_submit();
+        let size = self.size;
+        let damage = damage
+            .and_then(|rect| DeviceRect::covering(rect, scale_factor, size.width, size.height));
+        let swapped = match (damage, &self.gl_surface, &self.gl_context) {
+            // Only EGL takes damage, which spares the compositor copying the whole window.
+            (
+                Some(rect),
+                GlutinSurface::Egl(gl_surface),
+                PossiblyCurrentContext::Egl(gl_context),
+            ) => {
+                let rect = glutin::surface::Rect {
+                    x: rect.x as i32,
+                    // From the bottom left.
+                    y: (size.height - rect.y - rect.height) as i32,
+                    width: rect.width as i32,
+                    height: rect.height as i32,
+                };
+                gl_surface.swap_buffers_with_damage(gl_context, &[rect])
+            }
+            _ => self.gl_surface.swap_buffers(&self.gl_context),
+        };
 
-    fn input_state_mut(&mut self) -> &mut InputState {
-        &mut self.input_state
+        // A reset loses the context whether or not swapping failed with it.
+        let egl_lost = matches!(&swapped, Err(err) if err.error_kind() == ErrorKind::ContextLost);
+        if egl_lost || self.reset_status() == Some(Failure::DeviceLost) {
+            self.lost = true;
+        }
+        swapped.context("Could not swap the OpenGL buffers")?;
+        if self.lost {
+            anyhow::bail!("The GPU was reset");
+        }
+        Ok(())
     }
 
-    fn input_state(&self) -> &InputState {
-        &self.input_state
+    /// What the driver says of resets since the last call, where it has `GL_KHR_robustness`.
+    fn reset_status(&self) -> Option<Failure> {
+        if !gl::GetGraphicsResetStatus::is_loaded() {
+            return None;
+        }
+        Failure::of_reset_status(unsafe { gl::GetGraphicsResetStatus() })
     }
 
-    fn request_redraw(&self) {
-        self.env.window.request_redraw();
+    fn set_swap_interval(&self, interval: SwapInterval) -> glutin::error::Result<()> {
+        self.gl_surface
+            .set_swap_interval(&self.gl_context, interval)
     }
 }
+
+/// The size of a window surface, which can't be empty, in physical pixels.
+fn surface_size(size: PhysicalSize<u32>) -> (NonZeroU32, NonZeroU32) {
+    (
+        NonZeroU32::new(size.width.max(1)).unwrap(),
+        NonZeroU32::new(size.height.max(1)).unwrap(),
+    )
+}
@@ -0,0 +1,60 @@
+//! How the Metal backend presents frames around a live resize. While the user drags the
+//! window's edge, each frame waits for the GPU and goes out with the Core Animation
+//! transaction that resizes the window, so it never shows stretched or a size behind.
+//! Otherwise frames are presented as soon as they're drawn, without blocking. Apart from
+//! Metal so it's tested on every platform.
+
+use std::time::{Duration, Instant};
+
+/// How long after the last resize frames are still presented with the transaction, as a
+/// drag can pause between resize events.
+pub(crate) const SETTLE_TIME: Duration = Duration::from_millis(250);
+
+/// The refresh rate assumed where the display's is unknown.
+const DEFAULT_REFRESH_MILLIHERTZ: u32 = 60_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Present {
+    /// Wait for the GPU, then present in the transaction that resizes the window.
+    WithTransaction,
+    /// Present once the GPU is done, without waiting for it.
+    Async,
+}
+
+/// Whether a window is being resized, from when its resize events come.
+#[derive(Debug, Default)]
+pub(crate) struct LiveResize {
+    /// The last resize, while it's recent enough for the resize to go on.
+    last_resize: Option<Instant>,
+}
+
+impl LiveResize {
+    pub fn resized(&mut self, now: Instant) {
+        self.last_resize = Some(now);
+    }
+
+    /// How to present the frame drawn at `now`.
+    pub fn present(&mut self, now: Instant) -> Present {
+        match self.last_resize {
+            Some(at) if now.saturating_duration_since(at) < SETTLE_TIME => Present::WithTransaction,
+            Some(_) => {
+                self.last_resize = None;
+                Present::Async
+            }
+            None => Present::Async,
+        }
+    }
+}
+
+/// The shortest a frame stays on a display refreshing `refresh_millihertz` times per 1000
+/// seconds, as winit reports it: one refresh, so frames are paced evenly at up to 120 Hz on
+/// ProMotion displays. 60 Hz where winit doesn't know.
+pub(crate) fn refresh_interval(refresh_millihertz: Option<u32>) -> Duration {
+    let millihertz = refresh_millihertz
+        .filter(|&millihertz| millihertz > 0)
+        .unwrap_or(DEFAULT_REFRESH_MILLIHERTZ);
+    Duration::from_secs_f64(1000.0 / millihertz as f64)
+}
+
+#[cfg(test)]
+mod live_resize_tests;
@@ -0,0 +1,55 @@
+use super::{refresh_interval, LiveResize, Present, SETTLE_TIME};
+use std::time::{Duration, Instant};
+
+#[test]
+fn frames_present_without_waiting_until_the_window_is_resized() {
+    let mut live_resize = LiveResize::default();
+    assert_eq!(live_resize.present(Instant::now()), Present::Async);
+}
+
+#[test]
+fn frames_join_the_transaction_while_resizing_and_until_it_settles() {
+    let start = Instant::now();
+    let ms = Duration::from_millis;
+    let mut live_resize = LiveResize::default();
+
+    live_resize.resized(start);
+    assert_eq!(live_resize.present(start), Present::WithTransaction);
+    // A pause in the drag is still the same resize.
+    assert_eq!(
+        live_resize.present(start + ms(100)),
+        Present::WithTransaction
+    );
+    live_resize.resized(start + ms(200));
+    assert_eq!(
+        live_resize.present(start + ms(300)),
+        Present::WithTransaction
+    );
+
+    let settled = start + ms(200) + SETTLE_TIME;
+    assert_eq!(live_resize.present(settled), Present::Async);
+    assert_eq!(live_resize.present(settled + ms(1)), Present::Async);
+}
+
+#[test]
+fn a_resize_after_settling_starts_another() {
+    let start = Instant::now();
+    let mut live_resize = LiveResize::default();
+    live_resize.resized(start);
+    assert_eq!(live_resize.present(start + SETTLE_TIME), Present::Async);
+
+    let later = start + SETTLE_TIME * 2;
+    live_resize.resized(later);
+    assert_eq!(live_resize.present(later), Present::WithTransaction);
+}
+
+#[test]
+fn frames_stay_up_for_a_refresh_of_the_display() {
+    let close = |a: Duration, b: f64| (a.as_secs_f64() * 1000.0 - b).abs() < 1e-6;
+    assert!(close(refresh_interval(Some(120_000)), 1000.0 / 120.0));
+    assert!(close(refresh_interval(Some(60_000)), 1000.0 / 60.0));
+    assert!(close(refresh_interval(Some(59_940)), 1000.0 / 59.94));
+    // Unknown rates are taken for 60 Hz.
+    assert!(close(refresh_interval(None), 1000.0 / 60.0));
+    assert!(close(refresh_interval(Some(0)), 1000.0 / 60.0));
+}
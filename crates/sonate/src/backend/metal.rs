@@ -1,5 +1,10 @@
-use super::{InputState, Params, RenderingBackend};
-use anyhow::Result;
+use super::live_resize::{refresh_interval, LiveResize, Present};
+use super::{
+    release_dropped_surfaces, DrawFrame, GpuMemoryStats, InputState, RenderingBackend, VsyncMode,
+};
+use crate::windowing::{place_window, WindowOptions};
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
 use winit::{
     dpi::{LogicalSize, Size},
     event::WindowEvent,
@@ -19,7 +24,7 @@ use skia_safe::{
         backend_render_targets::make_mtl,
         direct_contexts::make_metal,
         mtl::{BackendContext, TextureInfo},
-        surfaces, BackendRenderTarget, DirectContext, SurfaceOrigin,
+        surfaces, BackendRenderTarget, DirectContext, SurfaceOrigin, SyncCpu,
     },
     ColorType, Surface,
 };
@@ -37,13 +42,23 @@ pub struct MetalBackend {
     input_state: InputState,
     current_width: u32,
     current_height: u32,
+    /// Whether the layer presents in Core Animation transactions, which it does while the
+    /// window is resized.
+    presents_with_transaction: bool,
+    live_resize: LiveResize,
+    /// Whether the layer syncs to the display, see [`RenderingBackend::set_vsync`].
+    display_sync: bool,
+    /// A refresh of the display the window is on, the shortest a frame stays up.
+    refresh_interval: Duration,
 }
 
 impl RenderingBackend for MetalBackend {
-    fn new(event_loop: &ActiveEventLoop) -> Result<Self> {
+    fn new(event_loop: &ActiveEventLoop, options: &WindowOptions) -> Result<Self> {
         let mut window_attributes = WindowAttributes::default();
-        window_attributes.inner_size = Some(Size::new(LogicalSize::new(800, 800)));
-        window_attributes.title = "Sonate CSS - Metal".into();
+        window_attributes.inner_size =
+            Some(Size::new(LogicalSize::new(options.width, options.height)));
+        window_attributes.title = options.title_or("Sonate CSS - Metal");
+        let mut window_attributes = place_window(window_attributes, event_loop, options);
 
         // Enable high DPI awareness on macOS
         #[cfg(target_os = "macos")]
@@ -53,7 +68,7 @@ impl RenderingBackend for MetalBackend {
 
         let window = event_loop
             .create_window(window_attributes)
-            .expect("Failed to create window");
+            .context("Failed to create window")?;
 
         // winit reports inner_size in physical pixels.
         let physical_size = window.inner_size();
@@ -76,7 +91,7 @@ impl RenderingBackend for MetalBackend {
         // CAMetalLayer drawable_size is in physical pixels.
         layer.set_drawable_size(CGSize::new(width as f64, height as f64));
 
-        println!("Scale factor: {}", scale_factor);
+        log::debug!("Scale factor: {scale_factor}");
 
         // Set up the layer with the window
         unsafe {
@@ -111,6 +126,7 @@ impl RenderingBackend for MetalBackend {
         let direct_context = make_metal(&backend_context, None)
             .ok_or_else(|| anyhow::anyhow!("Failed to create Metal DirectContext"))?;
 
+        let refresh_interval = monitor_refresh_interval(&window);
         let mut backend = Self {
             window,
             device,
@@ -120,11 +136,15 @@ impl RenderingBackend for MetalBackend {
             input_state: InputState::default(),
             current_width: width,
             current_height: height,
+            presents_with_transaction: false,
+            live_resize: LiveResize::default(),
+            display_sync: true,
+            refresh_interval,
         };
 
         backend.recreate_surfaces(width, height)?;
 
-        println!("Metal backend initialized with {} surfaces.", BUFFER_COUNT);
+        log::info!("Metal backend initialized with {BUFFER_COUNT} surfaces");
         Ok(backend)
     }
 
@@ -132,18 +152,41 @@ impl RenderingBackend for MetalBackend {
         match event {
             WindowEvent::Resized(new_size) => {
                 if new_size.width > 0 && new_size.height > 0 {
-                    // Perform safe resize
+                    // The drawable takes the new size right away, and windowing renders a
+                    // frame at it before the resize goes on, see `renders_on_resize`.
+                    self.live_resize.resized(Instant::now());
                     if let Err(err) = self.resize(new_size.width, new_size.height) {
-                        eprintln!("Resize failed: {:?}", err);
+                        log::error!("resize failed: {err:?}");
                     }
                     self.request_redraw();
                 }
                 true
             }
+            WindowEvent::ScaleFactorChanged { .. } => {
+                // E.g. moved to a display with another backingScaleFactor, which may
+                // refresh at another rate too.
+                self.refresh_interval = monitor_refresh_interval(&self.window);
+                let (width, height): (u32, u32) = self.window.inner_size().into();
+                if width > 0 && height > 0 {
+                    if let Err(err) = self.resize(width, height) {
+                        log::error!("resize failed: {err:?}");
+                    }
+                }
+                self.request_redraw();
+                true
+            }
+            WindowEvent::Moved(_) => {
+                self.refresh_interval = monitor_refresh_interval(&self.window);
+                false
+            }
             _ => false,
         }
     }
 
+    fn window_id(&self) -> winit::window::WindowId {
+        self.window.id()
+    }
+
     fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
         self.window.inner_size()
     }
@@ -152,12 +195,19 @@ impl RenderingBackend for MetalBackend {
         self.window.scale_factor()
     }
 
-    fn render(&mut self, params: &mut Params) {
+    fn render(&mut self, draw: DrawFrame) {
+        let present = self.live_resize.present(Instant::now());
+        let with_transaction = present == Present::WithTransaction;
+        if with_transaction != self.presents_with_transaction {
+            self.layer.set_presents_with_transaction(with_transaction);
+            self.presents_with_transaction = with_transaction;
+        }
+
         // Get next drawable from layer
         let drawable = match self.layer.next_drawable() {
             Some(drawable) => drawable,
             None => {
-                eprintln!("Failed to get next drawable");
+                log::error!("failed to get next drawable");
                 return;
             }
         };
@@ -188,20 +238,52 @@ impl RenderingBackend for MetalBackend {
             canvas.save();
             canvas.scale((scale_factor, scale_factor));
 
-            // Call the draw callback
-            (params.on_draw)(canvas);
+            // Call the draw callback; drawables don't keep earlier frames
+            draw(canvas, 0);
 
             canvas.restore();
 
-            // Flush and present
-            self.direct_context
-                .flush_and_submit_surface(&mut surface, None);
-
-            // Present the drawable
-            drawable.present();
+            match present {
+                Present::WithTransaction => {
+                    // The frame has to be on the GPU before it can join the transaction.
+                    self.direct_context
+                        .flush_and_submit_surface(&mut surface, SyncCpu::Yes);
+                    drawable.present();
+                }
+                Present::Async if self.display_sync => {
+                    self.direct_context
+                        .flush_and_submit_surface(&mut surface, None);
+                    // Each frame stays up for a refresh at least, which paces them evenly at
+                    // the display's rate, like a CADisplayLink would.
+                    unsafe {
+                        use objc2::msg_send;
+
+                        let drawable = drawable.as_ptr() as *mut AnyObject;
+                        let duration = self.refresh_interval.as_secs_f64();
+                        let _: () = msg_send![drawable, presentAfterMinimumDuration: duration];
+                    }
+                }
+                Present::Async => {
+                    self.direct_context
+                        .flush_and_submit_surface(&mut surface, None);
+                    drawable.present();
+                }
+            }
         }
     }
 
+    fn renders_on_resize(&self) -> bool {
+        true
+    }
+
+    fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        GpuMemoryStats::new(
+            &self.direct_context,
+            self.surfaces.iter().flatten().count(),
+            self.layer.maximum_drawable_count() as usize,
+        )
+    }
+
     fn input_state_mut(&mut self) -> &mut InputState {
         &mut self.input_state
     }
@@ -213,6 +295,26 @@ impl RenderingBackend for MetalBackend {
     fn request_redraw(&self) {
         self.window.request_redraw();
     }
+
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn set_cursor(&self, cursor: winit::window::CursorIcon) {
+        self.window.set_cursor(cursor);
+    }
+
+    fn set_vsync(&mut self, mode: VsyncMode) -> VsyncMode {
+        // Core Animation either syncs the layer to the display or doesn't.
+        let sync = mode != VsyncMode::Immediate;
+        self.layer.set_display_sync_enabled(sync);
+        self.display_sync = sync;
+        if sync {
+            VsyncMode::Vsync
+        } else {
+            VsyncMode::Immediate
+        }
+    }
 }
 
 impl MetalBackend {
@@ -221,21 +323,27 @@ impl MetalBackend {
     }
 
     fn recreate_surfaces(&mut self, width: u32, height: u32) -> Result<()> {
+        // Clear existing surfaces before the layer reallocates its drawables
+        self.drop_surfaces();
+
         // Update layer drawable size and DPI scale factor
         let scale_factor = self.window.scale_factor();
         self.layer.set_contents_scale(scale_factor as f64);
         self.layer
             .set_drawable_size(CGSize::new(width as f64, height as f64));
-        // Clear existing surfaces
-        for surface in &mut self.surfaces {
-            *surface = None;
-        }
 
         self.current_width = width;
         self.current_height = height;
         Ok(())
     }
 
+    fn drop_surfaces(&mut self) {
+        for surface in &mut self.surfaces {
+            *surface = None;
+        }
+        release_dropped_surfaces(&mut self.direct_context);
+    }
+
     fn resize(&mut self, width: u32, height: u32) -> Result<()> {
         // Flush any pending work
         self.direct_context.flush_and_submit();
@@ -247,14 +355,21 @@ impl MetalBackend {
     }
 }
 
+/// A refresh of the display `window` is mostly on, as winit reports its rate.
+fn monitor_refresh_interval(window: &Window) -> Duration {
+    refresh_interval(
+        window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz()),
+    )
+}
+
 impl Drop for MetalBackend {
     fn drop(&mut self) {
         // Ensure all GPU work is finished
         self.direct_context.flush_and_submit();
 
         // Clear surfaces
-        for surface in &mut self.surfaces {
-            *surface = None;
-        }
+        self.drop_surfaces();
     }
 }
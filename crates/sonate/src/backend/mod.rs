@@ -1,19 +1,186 @@
+use crate::callbacks::KeyPress;
+use crate::debug_overlay::DebugOverlay;
+use crate::editing::EditKey;
+use crate::style::Cursor;
+use crate::windowing::{WindowId, WindowOptions};
 use anyhow::Result;
-use skia_safe::Canvas;
+use skia_safe::{gpu::DirectContext, Canvas};
+use std::time::{Duration, Instant};
 use winit::{event::WindowEvent, event_loop::ActiveEventLoop};
 
 #[cfg(target_os = "windows")]
 pub mod d3d12;
+#[cfg_attr(not(any(target_os = "windows", target_os = "linux")), allow(unused))]
+mod device_loss;
+pub mod external;
 #[cfg(target_os = "linux")]
 pub mod gl;
+#[cfg_attr(not(target_os = "macos"), allow(unused))]
+mod live_resize;
 #[cfg(target_os = "macos")]
 pub mod metal;
+pub mod pdf;
+pub mod raster;
+pub mod software;
+
+/// Draws a window's content in logical pixels, see [`DrawFrame`], with the debug overlay
+/// over it while [`Params::debug_overlay`] is set.
+pub type DrawCallback =
+    Box<dyn FnMut(WindowId, &Canvas, u32, Option<DebugOverlay>) -> Option<crate::Rect>>;
+
+/// Draws a frame in logical pixels onto a target that still holds the frame drawn that many
+/// frames before, or unknown content for 0. Returns the region it repainted, in logical
+/// pixels, or `None` if it repainted all of it.
+pub type DrawFrame<'a> = &'a mut dyn FnMut(&Canvas, u32) -> Option<crate::Rect>;
 
 /// Common parameters shared across all rendering backends
 pub struct Params {
-    pub on_draw: Box<dyn FnMut(&Canvas)>,
-    pub on_click: Box<dyn FnMut(f64, f64)>, // x, y coordinates in logical pixels (points)
-    pub on_resize: Box<dyn FnMut(f64, f64)>, // width, height in logical pixels (points)
+    pub on_draw: DrawCallback,
+    pub on_click: Box<dyn FnMut(WindowId, f64, f64)>, // x, y coordinates in logical pixels (points)
+    pub on_resize: Box<dyn FnMut(WindowId, f64, f64)>, // width, height in logical pixels (points)
+    /// Called when the pointer moves, in logical pixels; returns the cursor to show there.
+    pub on_pointer_move: Box<dyn FnMut(WindowId, f64, f64) -> Cursor>,
+    /// Called when the pointer leaves the window.
+    pub on_pointer_leave: Box<dyn FnMut(WindowId)>,
+    /// Called when the left button goes up, where the pointer last was, in logical pixels.
+    pub on_release: Box<dyn FnMut(WindowId, f64, f64)>,
+    /// Called when the pointer moves with the left button down, in logical pixels.
+    pub on_drag: Box<dyn FnMut(WindowId, f64, f64)>,
+    /// Called for the platform's copy shortcut, Cmd+C on macOS and Ctrl+C elsewhere.
+    pub on_copy: Box<dyn FnMut(WindowId)>,
+    /// Called for key presses that edit text; returns whether an editable node with focus
+    /// took the key, which then does nothing else.
+    pub on_key: Box<dyn FnMut(WindowId, EditKey) -> bool>,
+    /// Called for other key presses; returns whether the host handled the key, which then
+    /// does nothing else.
+    pub on_key_press: Box<dyn FnMut(WindowId, KeyPress) -> bool>,
+    /// Called after drawing a window: when it should be drawn again without an event, e.g.
+    /// for a caret to blink.
+    pub next_redraw: Box<dyn FnMut(WindowId) -> Option<Instant>>,
+    /// Called right before each draw with the time since the event loop started. While set,
+    /// each frame requests the next; otherwise drawing only happens in response to events.
+    pub on_frame: Option<Box<dyn FnMut(Duration)>>,
+    /// Called when the user asks to close the window; returning `false` keeps it open.
+    pub on_close_requested: Option<Box<dyn FnMut() -> bool>>,
+    /// Called once the first frame has been presented.
+    pub on_ready: Option<Box<dyn FnOnce()>>,
+    /// How windows present their frames.
+    pub vsync: VsyncMode,
+    /// Whether the layout debugging overlay is painted over windows; F12 toggles it.
+    pub debug_overlay: bool,
+    /// Called about once a second with how the primary window's frames were paced.
+    pub on_frame_stats: Option<Box<dyn FnMut(crate::windowing::FrameStats)>>,
+    /// Called after a window closed, whether the user or the engine closed it.
+    pub on_window_closed: Box<dyn FnMut(WindowId)>,
+    /// The windows to open along with the primary one, asked for once the event loop is
+    /// ready to hear about windows opened later.
+    pub windows: Box<dyn FnMut() -> Vec<(WindowId, WindowOptions)>>,
+    /// How the primary window opens.
+    pub primary_window: WindowOptions,
+}
+
+/// Coarse GPU memory use of the window's rendering backend, see
+/// [`crate::Engine::gpu_memory_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GpuMemoryStats {
+    /// Bytes held by Skia's GPU resource cache.
+    pub cache_bytes: usize,
+    /// Resources held by Skia's GPU resource cache.
+    pub cache_resources: usize,
+    /// Skia surfaces the backend keeps between frames.
+    pub surfaces: usize,
+    /// Buffers of the swap chain or drawable layer; 0 where the driver keeps them, as with
+    /// OpenGL's default framebuffer.
+    pub swapchain_images: usize,
+}
+
+impl GpuMemoryStats {
+    pub(crate) fn new(context: &DirectContext, surfaces: usize, swapchain_images: usize) -> Self {
+        let usage = context.resource_cache_usage();
+        Self {
+            cache_bytes: usage.resource_bytes,
+            cache_resources: usage.resource_count,
+            surfaces,
+            swapchain_images,
+        }
+    }
+}
+
+/// A region in device pixels of a `width` by `height` target, from the top left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct DeviceRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DeviceRect {
+    /// The whole device pixels covering `rect`, in logical pixels, within the target. `None`
+    /// if that is none.
+    pub fn covering(rect: crate::Rect, scale_factor: f64, width: u32, height: u32) -> Option<Self> {
+        let clamp = |value: f64, max: u32| value.clamp(0.0, max as f64) as u32;
+        let left = clamp((rect.x * scale_factor).floor(), width);
+        let top = clamp((rect.y * scale_factor).floor(), height);
+        let right = clamp(((rect.x + rect.width) * scale_factor).ceil(), width);
+        let bottom = clamp(((rect.y + rect.height) * scale_factor).ceil(), height);
+        (right > left && bottom > top).then(|| Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+}
+
+/// Release what Skia cached for surfaces that were just dropped, before their render targets
+/// are resized or destroyed. Drivers may otherwise keep the old targets alive.
+pub(crate) fn release_dropped_surfaces(context: &mut DirectContext) {
+    let before = context.resource_cache_usage().resource_bytes;
+    context.flush_and_submit();
+    context.free_gpu_resources();
+    let after = context.resource_cache_usage().resource_bytes;
+    debug_assert!(
+        after <= before,
+        "Skia's resource cache grew from {before} to {after} bytes while releasing surfaces"
+    );
+}
+
+/// Checks the [`GpuMemoryStats`] of a backend across many resizes: the surface and swap
+/// chain counts must not change, and the cache must stay within twice what the first
+/// frame used, plus some slack for glyph atlases.
+#[derive(Default)]
+pub(crate) struct ResizeStress {
+    first: Option<GpuMemoryStats>,
+    resizes: usize,
+}
+
+impl ResizeStress {
+    const CACHE_SLACK_BYTES: usize = 16 << 20;
+
+    /// Record the stats after a resize and the frame drawn at the new size.
+    pub fn record(&mut self, stats: GpuMemoryStats) -> Result<(), String> {
+        self.resizes += 1;
+        let first = *self.first.get_or_insert(stats);
+        if (stats.surfaces, stats.swapchain_images) != (first.surfaces, first.swapchain_images) {
+            return Err(format!(
+                "resize {}: {} surfaces and {} swap chain images, {} and {} at first",
+                self.resizes,
+                stats.surfaces,
+                stats.swapchain_images,
+                first.surfaces,
+                first.swapchain_images
+            ));
+        }
+        let bound = first.cache_bytes * 2 + Self::CACHE_SLACK_BYTES;
+        if stats.cache_bytes > bound {
+            return Err(format!(
+                "resize {}: {} bytes cached, over the bound of {bound}",
+                self.resizes, stats.cache_bytes
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// State shared across all backends for input handling
@@ -21,6 +188,11 @@ pub struct InputState {
     pub x: f32,
     pub y: f32,
     pub cursor_position: Option<winit::dpi::LogicalPosition<f64>>,
+    /// Whether the left mouse button is down.
+    pub pressed: bool,
+    pub modifiers: winit::keyboard::ModifiersState,
+    /// The cursor of the element under the pointer when it last moved.
+    pub cursor: Cursor,
 }
 
 impl Default for InputState {
@@ -29,17 +201,23 @@ impl Default for InputState {
             x: 100.0,
             y: 100.0,
             cursor_position: None,
+            pressed: false,
+            modifiers: Default::default(),
+            cursor: Cursor::default(),
         }
     }
 }
 
 /// Trait that all rendering backends must implement
 pub trait RenderingBackend {
-    /// Create a new backend instance
-    fn new(event_loop: &ActiveEventLoop) -> Result<Self>
+    /// Create a new backend instance with its own window
+    fn new(event_loop: &ActiveEventLoop, options: &WindowOptions) -> Result<Self>
     where
         Self: Sized;
 
+    /// Get the id winit reports the window's events with
+    fn window_id(&self) -> winit::window::WindowId;
+
     /// Handle window events specific to this backend
     fn handle_window_event(&mut self, event: &WindowEvent) -> bool;
 
@@ -49,8 +227,17 @@ pub trait RenderingBackend {
     /// Get the current window scale factor (physical pixels per logical pixel)
     fn scale_factor(&self) -> f64;
 
-    /// Render a frame
-    fn render(&mut self, params: &mut Params);
+    /// Render a frame, presenting only what `draw` repainted where the platform can
+    fn render(&mut self, draw: DrawFrame);
+
+    /// Whether a frame is rendered right after each resize rather than at the next redraw,
+    /// for the platform to show it along with the window's new size
+    fn renders_on_resize(&self) -> bool {
+        false
+    }
+
+    /// Current GPU memory use
+    fn gpu_memory_stats(&self) -> GpuMemoryStats;
 
     /// Get mutable reference to input state
     fn input_state_mut(&mut self) -> &mut InputState;
@@ -60,9 +247,66 @@ pub trait RenderingBackend {
 
     /// Request a redraw
     fn request_redraw(&self);
+
+    /// When to draw again without an event, e.g. to retry making a lost device once its
+    /// delay passed
+    fn next_redraw(&self) -> Option<Instant> {
+        None
+    }
+
+    /// Get the window, for what the platform does with it the same way in every backend
+    fn window(&self) -> &winit::window::Window;
+
+    /// Show `cursor` while the pointer is over the window
+    fn set_cursor(&self, cursor: winit::window::CursorIcon);
+
+    /// Present frames as `mode` asks from now on, as far as the backend can; returns the mode
+    /// it presents with.
+    fn set_vsync(&mut self, mode: VsyncMode) -> VsyncMode;
+}
+
+/// When a window's frames reach the screen, see [`crate::Params::vsync`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VsyncMode {
+    /// Wait for the display's refresh to present each frame: no tearing, and drawing slows
+    /// down to the refresh rate.
+    #[default]
+    Vsync,
+    /// Present each frame right away, which may tear, for the lowest latency.
+    Immediate,
+    /// Show the latest frame at each refresh without waiting for it, so drawing can run
+    /// ahead of the display without tearing. Falls back to `Vsync` where unsupported.
+    Mailbox,
+}
+
+/// Create a window with its backend of the given type.
+pub(crate) fn create_backend(
+    backend_type: BackendType,
+    event_loop: &ActiveEventLoop,
+    options: &WindowOptions,
+    vsync: VsyncMode,
+) -> Result<Box<dyn RenderingBackend>> {
+    let mut backend: Box<dyn RenderingBackend> = match backend_type {
+        #[cfg(target_os = "windows")]
+        BackendType::D3D12 => Box::new(d3d12::D3D12Backend::new(event_loop, options)?),
+        #[cfg(target_os = "macos")]
+        BackendType::Metal => Box::new(metal::MetalBackend::new(event_loop, options)?),
+        #[cfg(target_os = "linux")]
+        BackendType::OpenGL => Box::new(gl::OpenGlBackend::new(event_loop, options)?),
+        BackendType::Software => Box::new(software::SoftwareBackend::new(event_loop, options)?),
+    };
+    let presents_with = backend.set_vsync(vsync);
+    if presents_with != vsync {
+        log::info!(
+            "The {} backend can't present with {vsync:?}, using {presents_with:?}",
+            backend_type.name()
+        );
+    }
+    Ok(backend)
 }
 
-/// Available backend types
+/// The window rendering backends, see [`crate::Params::backend`] and
+/// [`crate::Engine::backend`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BackendType {
     #[cfg(all(target_os = "windows"))]
@@ -71,11 +315,13 @@ pub enum BackendType {
     Metal,
     #[cfg(target_os = "linux")]
     OpenGL,
+    /// Renders on the CPU, for machines without a working GPU driver.
+    Software,
 }
 
-impl BackendType {
-    /// Get the default backend for the current platform
-    pub fn default() -> Self {
+/// The platform's usual backend.
+impl Default for BackendType {
+    fn default() -> Self {
         #[cfg(all(target_os = "windows"))]
         return BackendType::D3D12;
 
@@ -88,7 +334,9 @@ impl BackendType {
         #[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
         compile_error!("No default backend available for this platform");
     }
+}
 
+impl BackendType {
     /// Get a human-readable name for the backend
     pub fn name(&self) -> &'static str {
         match self {
@@ -98,6 +346,50 @@ impl BackendType {
             BackendType::Metal => "Metal",
             #[cfg(target_os = "linux")]
             BackendType::OpenGL => "OpenGL",
+            BackendType::Software => "Software",
+        }
+    }
+
+    /// The backend named as in `SONATE_BACKEND`: `vulkan`, `gl`, `d3d12`, `metal` or
+    /// `software`, in any case. `None` if the name is none of them, or names a backend this
+    /// build doesn't have.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            #[cfg(target_os = "windows")]
+            "d3d12" => Some(BackendType::D3D12),
+            #[cfg(target_os = "macos")]
+            "metal" => Some(BackendType::Metal),
+            #[cfg(target_os = "linux")]
+            "gl" | "opengl" => Some(BackendType::OpenGL),
+            "software" => Some(BackendType::Software),
+            _ => None,
+        }
+    }
+
+    /// The backend `SONATE_BACKEND` asks for, if it's set. Names of other backends are
+    /// logged and ignored.
+    pub(crate) fn from_env() -> Option<Self> {
+        let name = std::env::var("SONATE_BACKEND").ok()?;
+        let backend = Self::from_name(&name);
+        if backend.is_none() {
+            log::warn!("SONATE_BACKEND={name} names no backend available here, ignoring it");
         }
+        backend
+    }
+
+    /// The backends to try in order: `requested` first, then the others this platform has.
+    pub(crate) fn candidates(requested: Option<Self>) -> Vec<Self> {
+        let mut candidates: Vec<Self> = requested.into_iter().collect();
+        for backend in Self::fallback_order() {
+            if !candidates.contains(&backend) {
+                candidates.push(backend);
+            }
+        }
+        candidates
+    }
+
+    /// The backends of this platform, the default first and software rendering last.
+    fn fallback_order() -> Vec<Self> {
+        vec![Self::default(), BackendType::Software]
     }
 }
@@ -0,0 +1,125 @@
+use crate::layout::RenderNode;
+use crate::painter::Painter;
+use crate::snapshot::Edges;
+use crate::text::FontProvider;
+use skia_safe::{pdf, Canvas, Rect};
+
+/// Size of a PDF page in points, 1/72 inch each.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PageSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl PageSize {
+    /// ISO A4, 210 × 297 mm.
+    pub const A4: PageSize = PageSize {
+        width: 595.28,
+        height: 841.89,
+    };
+    /// US Letter, 8.5 × 11 in.
+    pub const LETTER: PageSize = PageSize {
+        width: 612.0,
+        height: 792.0,
+    };
+}
+
+/// How [`crate::Engine::export_pdf`] lays the document out on pages.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PdfOptions {
+    pub page_size: PageSize,
+    /// Blank space around the content of every page, in points.
+    pub margins: Edges,
+    /// Points per CSS pixel. The default of 0.75 prints 96 px to the inch, as browsers do.
+    pub scale: f64,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        Self {
+            page_size: PageSize::A4,
+            margins: Edges {
+                top: 36.0,
+                right: 36.0,
+                bottom: 36.0,
+                left: 36.0,
+            },
+            scale: 0.75,
+        }
+    }
+}
+
+impl PdfOptions {
+    /// The area of a page inside the margins in CSS pixels, or `None` if there is none.
+    pub(crate) fn content_size(&self) -> Option<(f64, f64)> {
+        let width = (self.page_size.width - self.margins.left - self.margins.right) / self.scale;
+        let height = (self.page_size.height - self.margins.top - self.margins.bottom) / self.scale;
+        (width >= 1.0 && height >= 1.0 && width.is_finite() && height.is_finite())
+            .then_some((width, height))
+    }
+}
+
+/// How many pages of `page_height` px it takes to show `document_height` px; at least one,
+/// so an empty document still prints a blank page.
+pub(crate) fn page_count(document_height: f64, page_height: f64) -> usize {
+    // Ignore the rounding error of layout, which would otherwise add a blank page for a
+    // document exactly as tall as its pages.
+    let pages = (document_height / page_height - 1e-6).ceil();
+    if pages.is_finite() && pages > 1.0 {
+        pages as usize
+    } else {
+        1
+    }
+}
+
+/// Paint page `index` of `root`, laid out at the content width of `options`, onto a canvas
+/// in points for the whole page. The page shows the content from `index` content heights
+/// down; nodes straddling a page boundary are painted on both pages and clipped by each.
+pub(crate) fn paint_page(
+    canvas: &Canvas,
+    root: &RenderNode,
+    fonts: FontProvider,
+    options: &PdfOptions,
+    index: usize,
+) {
+    let Some((width, height)) = options.content_size() else {
+        return;
+    };
+
+    canvas.save();
+    canvas.translate((options.margins.left as f32, options.margins.top as f32));
+    canvas.scale((options.scale as f32, options.scale as f32));
+    canvas.clip_rect(
+        Rect::from_xywh(0.0, 0.0, width as f32, height as f32),
+        None,
+        Some(true),
+    );
+    canvas.translate((0.0, -(index as f64 * height) as f32));
+    Painter::with_fonts(canvas, fonts).paint(root);
+    canvas.restore();
+}
+
+/// Write `root` as a PDF with `pages` pages, see [`paint_page`]. Text is drawn as text, so
+/// it stays selectable and searchable.
+pub(crate) fn render_pdf(
+    root: &RenderNode,
+    fonts: FontProvider,
+    options: &PdfOptions,
+    pages: usize,
+) -> Vec<u8> {
+    let page_size = (
+        options.page_size.width as f32,
+        options.page_size.height as f32,
+    );
+
+    let mut pdf = Vec::new();
+    let mut document = pdf::new_document(&mut pdf, None);
+    for index in 0..pages {
+        let mut page = document.begin_page(page_size, None);
+        paint_page(page.canvas(), root, fonts.clone(), options, index);
+        document = page.end_page();
+    }
+    document.close();
+
+    pdf
+}
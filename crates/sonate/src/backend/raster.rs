@@ -0,0 +1,107 @@
+use super::GpuMemoryStats;
+use skia_safe::{
+    graphics, surfaces, AlphaType, Canvas, ColorType, EncodedImageFormat, ISize, ImageInfo, Surface,
+};
+
+/// Software rendering into memory, for pixel output without a window or a GPU, and for the
+/// software window backend, which copies each frame to its window.
+pub struct RasterBackend {
+    surface: Surface,
+    scale_factor: f32,
+}
+
+impl RasterBackend {
+    /// A target for a `width` by `height` viewport in logical pixels, rendered at
+    /// `scale_factor` device pixels per logical pixel. `None` if that is empty.
+    pub fn new(width: f64, height: f64, scale_factor: f64) -> Option<Self> {
+        let size = Self::device_size(width, height, scale_factor)?;
+        Self::with_device_size(size, scale_factor)
+    }
+
+    /// A target of `size` device pixels, such as a window's inner size, rendered at
+    /// `scale_factor` device pixels per logical pixel. `None` if that is empty.
+    pub fn with_device_size(size: ISize, scale_factor: f64) -> Option<Self> {
+        Some(Self {
+            surface: Self::create_surface(size)?,
+            scale_factor: scale_factor as f32,
+        })
+    }
+
+    fn device_size(width: f64, height: f64, scale_factor: f64) -> Option<ISize> {
+        let device_width = (width * scale_factor).ceil();
+        let device_height = (height * scale_factor).ceil();
+        if !(device_width >= 1.0 && device_height >= 1.0) {
+            return None;
+        }
+        Some(ISize::new(device_width as i32, device_height as i32))
+    }
+
+    fn create_surface(size: ISize) -> Option<Surface> {
+        if size.width < 1 || size.height < 1 {
+            return None;
+        }
+        surfaces::raster_n32_premul(size)
+    }
+
+    /// Device pixels per logical pixel from the next frame on, e.g. after the window moved
+    /// to another monitor.
+    pub fn set_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor as f32;
+    }
+
+    /// Render a frame; `draw` works in logical pixels like `Params::on_draw`.
+    pub fn render(&mut self, draw: impl FnOnce(&Canvas)) {
+        let canvas = self.surface.canvas();
+        canvas.save();
+        canvas.scale((self.scale_factor, self.scale_factor));
+        draw(canvas);
+        canvas.restore();
+    }
+
+    /// The last rendered frame as PNG.
+    pub fn encode_png(&mut self) -> Option<Vec<u8>> {
+        let image = self.surface.image_snapshot();
+        let data = image.encode(None, EncodedImageFormat::PNG, None)?;
+        Some(data.as_bytes().to_vec())
+    }
+
+    /// Copy the last rendered frame into `pixels` as rows of premultiplied BGRA bytes,
+    /// resizing it to fit. Returns whether the copy succeeded.
+    pub fn read_bgra(&mut self, pixels: &mut Vec<u8>) -> bool {
+        let size = ISize::new(self.surface.width(), self.surface.height());
+        let info = ImageInfo::new(size, ColorType::BGRA8888, AlphaType::Premul, None);
+        let row_bytes = info.min_row_bytes();
+        pixels.resize(row_bytes * size.height as usize, 0);
+        self.surface.read_pixels(&info, pixels, row_bytes, (0, 0))
+    }
+
+    /// Replace the target with one of `size` device pixels, the way window backends do on
+    /// resize. Keeps the old target and returns `None` if the size is empty.
+    pub fn resize_device(&mut self, size: ISize) -> Option<()> {
+        self.surface = Self::create_surface(size)?;
+        Some(())
+    }
+}
+
+// Resizing by viewport, for the resize stress test.
+#[cfg_attr(not(test), allow(dead_code))]
+impl RasterBackend {
+    /// Replace the target with one for a `width` by `height` viewport, the way window
+    /// backends do on resize. Keeps the old target and returns `None` if the size is empty.
+    pub fn resize(&mut self, width: f64, height: f64) -> Option<()> {
+        let scale_factor = self.scale_factor as f64;
+        self.resize_device(Self::device_size(width, height, scale_factor)?)
+    }
+}
+
+impl RasterBackend {
+    /// Memory use in the terms of window backends. Nothing lives on a GPU, so the cache is
+    /// Skia's CPU resource cache, which is shared by the process and doesn't count resources.
+    pub fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        GpuMemoryStats {
+            cache_bytes: graphics::resource_cache_total_bytes_used(),
+            surfaces: 1,
+            ..Default::default()
+        }
+    }
+}
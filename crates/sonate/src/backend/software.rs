@@ -0,0 +1,190 @@
+use super::raster::RasterBackend;
+use super::{DeviceRect, DrawFrame, GpuMemoryStats, InputState, RenderingBackend, VsyncMode};
+use crate::windowing::{place_window, WindowOptions};
+use anyhow::{Context, Result};
+use skia_safe::ISize;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use winit::{
+    dpi::{LogicalSize, Size},
+    event::WindowEvent,
+    event_loop::ActiveEventLoop,
+    window::{Window, WindowAttributes},
+};
+
+/// Software rendering backend, for machines where no GPU backend starts.
+///
+/// Frames are drawn by Skia on the CPU into a [`RasterBackend`] and copied to the window
+/// through softbuffer.
+pub struct SoftwareBackend {
+    window: Rc<Window>,
+    surface: softbuffer::Surface<Rc<Window>, Rc<Window>>,
+    /// `None` while the window has no area.
+    target: Option<RasterBackend>,
+    /// The last frame as premultiplied BGRA, kept between frames.
+    pixels: Vec<u8>,
+    /// Whether the target was made or resized since the last frame, so holds no frame.
+    fresh: bool,
+    input_state: InputState,
+}
+
+impl SoftwareBackend {
+    /// Size the window's buffer and the raster target to the window.
+    fn resize(&mut self, width: u32, height: u32) -> Result<()> {
+        let (Some(buffer_width), Some(buffer_height)) =
+            (NonZeroU32::new(width), NonZeroU32::new(height))
+        else {
+            self.target = None;
+            return Ok(());
+        };
+        self.surface
+            .resize(buffer_width, buffer_height)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+        self.fresh = true;
+        let size = ISize::new(width.try_into()?, height.try_into()?);
+        let scale_factor = self.window.scale_factor();
+        let resized = match self.target.as_mut() {
+            Some(target) => target.resize_device(size),
+            None => RasterBackend::with_device_size(size, scale_factor)
+                .map(|target| self.target = Some(target)),
+        };
+        resized.context("Could not create the raster surface")
+    }
+}
+
+impl RenderingBackend for SoftwareBackend {
+    fn new(event_loop: &ActiveEventLoop, options: &WindowOptions) -> Result<Self> {
+        let window_attributes = WindowAttributes::default()
+            .with_title(options.title_or("Sonate CSS - Software"))
+            .with_inner_size(Size::new(LogicalSize::new(options.width, options.height)));
+        let window_attributes = place_window(window_attributes, event_loop, options);
+        let window = Rc::new(
+            event_loop
+                .create_window(window_attributes)
+                .context("Failed to create window")?,
+        );
+
+        let context = softbuffer::Context::new(Rc::clone(&window))
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let surface = softbuffer::Surface::new(&context, Rc::clone(&window))
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+        let mut backend = Self {
+            window,
+            surface,
+            target: None,
+            pixels: Vec::new(),
+            fresh: true,
+            input_state: InputState::default(),
+        };
+        let (width, height) = backend.window.inner_size().into();
+        backend.resize(width, height)?;
+        Ok(backend)
+    }
+
+    fn handle_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::Resized(physical_size) => {
+                let (width, height) = (*physical_size).into();
+                if let Err(err) = self.resize(width, height) {
+                    log::error!("Could not resize the software backend: {err:#}");
+                    self.target = None;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn window_id(&self) -> winit::window::WindowId {
+        self.window.id()
+    }
+
+    fn window_inner_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.window.inner_size()
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.window.scale_factor()
+    }
+
+    fn render(&mut self, draw: DrawFrame) {
+        let Some(target) = self.target.as_mut() else {
+            return;
+        };
+        let scale_factor = self.window.scale_factor();
+        target.set_scale_factor(scale_factor);
+        // The raster target keeps the last frame.
+        let age = if self.fresh { 0 } else { 1 };
+        let mut damage = None;
+        target.render(|canvas| damage = draw(canvas, age));
+        self.fresh = false;
+        if !target.read_bgra(&mut self.pixels) {
+            return;
+        }
+
+        let Ok(mut buffer) = self.surface.buffer_mut() else {
+            return;
+        };
+        copy_to_buffer(&self.pixels, &mut buffer);
+        self.window.pre_present_notify();
+        let size = self.window.inner_size();
+        let damage = damage
+            .and_then(|rect| DeviceRect::covering(rect, scale_factor, size.width, size.height));
+        let _ = match damage {
+            Some(rect) => buffer.present_with_damage(&[softbuffer::Rect {
+                x: rect.x,
+                y: rect.y,
+                width: NonZeroU32::new(rect.width).unwrap(),
+                height: NonZeroU32::new(rect.height).unwrap(),
+            }]),
+            None => buffer.present(),
+        };
+    }
+
+    fn gpu_memory_stats(&self) -> GpuMemoryStats {
+        match &self.target {
+            Some(target) => GpuMemoryStats {
+                swapchain_images: 1,
+                ..target.gpu_memory_stats()
+            },
+            None => GpuMemoryStats::default(),
+        }
+    }
+
+    fn input_state_mut(&mut self) -> &mut InputState {
+        &mut self.input_state
+    }
+
+    fn input_state(&self) -> &InputState {
+        &self.input_state
+    }
+
+    fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    fn window(&self) -> &Window {
+        &self.window
+    }
+
+    fn set_cursor(&self, cursor: winit::window::CursorIcon) {
+        self.window.set_cursor(cursor);
+    }
+
+    fn set_vsync(&mut self, _mode: VsyncMode) -> VsyncMode {
+        // softbuffer hands frames to the window system as soon as they are drawn.
+        VsyncMode::Immediate
+    }
+}
+
+/// Convert premultiplied BGRA pixels to softbuffer's `0RGB` ones, as over black.
+pub(crate) fn copy_to_buffer(bgra: &[u8], buffer: &mut [u32]) {
+    for (pixel, bgra) in buffer.iter_mut().zip(bgra.chunks_exact(4)) {
+        *pixel = u32::from_le_bytes([bgra[0], bgra[1], bgra[2], 0]);
+    }
+}
+
+#[cfg(test)]
+mod software_tests;
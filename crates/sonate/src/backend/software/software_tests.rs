@@ -0,0 +1,34 @@
+use super::copy_to_buffer;
+use crate::backend::raster::RasterBackend;
+use skia_safe::{Color, ISize, Paint, Rect};
+
+#[test]
+fn pixels_are_converted_to_0rgb() {
+    let bgra = [0x30, 0x20, 0x10, 0xFF, 0x00, 0x00, 0x80, 0x80];
+    let mut buffer = [u32::MAX; 2];
+    copy_to_buffer(&bgra, &mut buffer);
+    assert_eq!(buffer, [0x0010_2030, 0x0080_0000]);
+}
+
+#[test]
+fn frames_reach_the_buffer_at_the_window_scale() {
+    let (width, height) = (8, 4);
+    let mut target = RasterBackend::with_device_size(ISize::new(width, height), 2.0).unwrap();
+    target.render(|canvas| {
+        canvas.clear(Color::WHITE);
+        let mut paint = Paint::default();
+        paint.set_color(Color::from_argb(0xFF, 0xFF, 0, 0));
+        canvas.draw_rect(Rect::from_xywh(0.0, 0.0, 1.0, 1.0), &paint);
+    });
+
+    let mut pixels = Vec::new();
+    assert!(target.read_bgra(&mut pixels));
+    assert_eq!(pixels.len(), (width * height * 4) as usize);
+    let mut buffer = vec![0; (width * height) as usize];
+    copy_to_buffer(&pixels, &mut buffer);
+
+    // The logical pixel covers 2 by 2 device pixels.
+    let at = |x: i32, y: i32| buffer[(y * width + x) as usize];
+    assert_eq!([at(0, 0), at(1, 1)], [0x00FF_0000; 2]);
+    assert_eq!([at(2, 0), at(0, 2), at(7, 3)], [0x00FF_FFFF; 3]);
+}
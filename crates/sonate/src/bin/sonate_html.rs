@@ -1,15 +1,22 @@
 use anyhow::{Context, Result};
-use sonate::{Engine, Id, Params};
+use sonate::{Engine, FullscreenMode, Id, Params, WindowOptions};
+use std::cell::Cell;
 
-fn main() -> Result<()> {
-    let mut args = std::env::args().skip(1);
-    let html_path = args
-        .next()
-        .context("Usage: sonate_html <path/to/file.html>")?;
+const USAGE: &str = "Usage: sonate_html [--fullscreen] <path/to/file.html>";
 
-    if args.next().is_some() {
-        anyhow::bail!("Usage: sonate_html <path/to/file.html>");
+fn main() -> Result<()> {
+    let mut fullscreen = false;
+    let mut html_path = None;
+    for arg in std::env::args().skip(1) {
+        if arg == "--fullscreen" {
+            fullscreen = true;
+        } else if html_path.is_none() {
+            html_path = Some(arg);
+        } else {
+            anyhow::bail!(USAGE);
+        }
     }
+    let html_path = html_path.context(USAGE)?;
 
     let html = std::fs::read_to_string(&html_path)
         .with_context(|| format!("Failed to read HTML file: {html_path}"))?;
@@ -17,8 +24,26 @@ fn main() -> Result<()> {
     let engine = Engine::new();
     load_html_into_engine(&engine, &html)?;
 
+    // F11 toggles fullscreen, as in browsers.
+    let toggled = engine.clone();
+    let is_fullscreen = Cell::new(fullscreen);
+    let params = Params {
+        on_key: Some(Box::new(move |press| {
+            if press.key != "F11" {
+                return false;
+            }
+            is_fullscreen.set(!is_fullscreen.get());
+            toggled.set_fullscreen(is_fullscreen.get());
+            true
+        })),
+        window: WindowOptions {
+            fullscreen: fullscreen.then_some(FullscreenMode::Borderless),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
     engine
-        .run(Params::default())
+        .run(params)
         .map_err(|e| anyhow::anyhow!("Engine failed: {e:?}"))
 }
 
@@ -61,12 +86,9 @@ fn copy_nodes(
                 .and_then(|child| child.text())
                 .map(|s| s.to_owned());
 
-            engine.create_node(id, text);
+            engine.create_element(id, &element.name, text);
             engine.set_parent(parent, id);
 
-            // Store element tag for tag selector matching.
-            engine.set_attribute(id, "tag".to_owned(), element.name.to_ascii_lowercase());
-
             // Preserve element id as an attribute.
             if let Some(id_attr) = &element.id {
                 engine.set_attribute(id, "id".to_owned(), id_attr.to_owned());
@@ -0,0 +1,318 @@
+use crate::pointer_capture::Drag;
+use crate::Id;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::{Rc, Weak};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Called with a point in logical pixels and the nodes under it, innermost first.
+pub type PointerHandler = Box<dyn Fn(f64, f64, Vec<Id>)>;
+
+/// Returns whether the host handled the key, which then does nothing else.
+pub type KeyHandler = Box<dyn Fn(&KeyPress) -> bool>;
+
+/// Called for each event of its kind on its node, see [`crate::Engine::add_event_listener`].
+pub type EventListener = Box<dyn Fn(&Event) -> EventStatus>;
+
+type SharedPointerHandler = Rc<dyn Fn(f64, f64, Vec<Id>)>;
+type SharedKeyHandler = Rc<dyn Fn(&KeyPress) -> bool>;
+type SharedEventListener = Rc<dyn Fn(&Event) -> EventStatus>;
+
+/// A key the user pressed that no editable node with focus took, see
+/// [`crate::Engine::set_on_key`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    /// The key as the DOM's `KeyboardEvent.key` names it: the text it types, such as "a",
+    /// "A" or " ", or a name such as "Enter", "Escape" or "ArrowUp".
+    pub key: String,
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    /// Cmd on macOS, the Windows key elsewhere.
+    pub meta: bool,
+}
+
+/// What an event listener is called for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    /// The primary button went down, right after [`EventKind::MouseDown`]: what
+    /// [`crate::Params::on_click`] is called for.
+    Click,
+    MouseDown,
+    /// The primary button went up, over the nodes under the pointer then.
+    MouseUp,
+    /// The pointer moved over the node: what [`crate::Params::on_hover`] is called for.
+    Hover,
+    /// A key that no editable node took went down while the node or one in it had focus,
+    /// or anywhere in the window if none had: what [`crate::Params::on_key`] is called for.
+    KeyDown,
+    /// The node, which has the pointer captured, was dragged: what
+    /// [`crate::Params::on_drag`] is called for. The button going down over a node with a
+    /// listener for these captures the pointer for it, see
+    /// [`crate::Engine::set_pointer_capture`].
+    Drag,
+}
+
+/// Whether an event goes on to the listeners of the node's ancestors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventStatus {
+    #[default]
+    Continue,
+    /// The listeners of this node are still all called, then no others; neither is the
+    /// handler of the event's kind in [`crate::Params`].
+    StopPropagation,
+}
+
+/// An event as a listener sees it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// The topmost node under the pointer, or for keys the node with focus or the window's
+    /// root.
+    pub target: Id,
+    /// The node whose listener is called: the target, or an ancestor it bubbled up to.
+    pub current_target: Id,
+    /// Where the pointer is, in logical pixels; `None` for keys.
+    pub position: Option<(f64, f64)>,
+    /// The key of a [`EventKind::KeyDown`].
+    pub key: Option<KeyPress>,
+    /// The drag of a [`EventKind::Drag`].
+    pub drag: Option<Drag>,
+}
+
+/// Identifies a listener to [`crate::Engine::remove_event_listener`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+impl ListenerId {
+    /// Unique across engines and their runs.
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+struct Listener {
+    id: ListenerId,
+    kind: EventKind,
+    callback: SharedEventListener,
+}
+
+#[derive(Default)]
+struct Handlers {
+    on_click: Option<SharedPointerHandler>,
+    on_hover: Option<SharedPointerHandler>,
+    on_key: Option<SharedKeyHandler>,
+    /// By node, in the order they were added.
+    listeners: HashMap<Id, Vec<Listener>>,
+}
+
+thread_local! {
+    /// The handlers of the engines running on this thread, by engine, for the engine's
+    /// methods to reach them. Held weakly: the callbacks of the engine's windows own them.
+    static RUNNING: RefCell<HashMap<usize, Weak<RefCell<Handlers>>>> =
+        RefCell::new(HashMap::new());
+    /// The handlers registered on this thread for the next run of each engine, which takes
+    /// them over when it starts here.
+    static PENDING: RefCell<HashMap<usize, Callbacks>> = RefCell::new(HashMap::new());
+}
+
+/// Where an engine and its clones find their handlers on this thread, see [`Callbacks`].
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CallbackRegistry {
+    key: Arc<RegistryKey>,
+}
+
+/// Tells apart the handlers of engines until the last clone of one drops, which drops the
+/// handlers still waiting for its next run on that thread.
+#[derive(Debug, Default)]
+struct RegistryKey;
+
+impl Drop for RegistryKey {
+    fn drop(&mut self) {
+        let key = self as *const Self as usize;
+        // Taken out before they're dropped, which may drop other engines.
+        let pending = PENDING.try_with(|pending| pending.borrow_mut().remove(&key));
+        drop(pending);
+    }
+}
+
+impl CallbackRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The handlers of a run starting on this thread: those registered for it here, which
+    /// the engine reaches through this registry until [`Self::stop`].
+    pub fn start(&self) -> Callbacks {
+        let key = self.key();
+        let callbacks = PENDING
+            .with(|pending| pending.borrow_mut().remove(&key))
+            .unwrap_or_default();
+        let handlers = Rc::downgrade(&callbacks.0);
+        RUNNING.with(|running| running.borrow_mut().insert(key, handlers));
+        callbacks
+    }
+
+    /// Forget the handlers once the engine stopped running on this thread.
+    pub fn stop(&self) {
+        RUNNING.with(|running| running.borrow_mut().remove(&self.key()));
+    }
+
+    /// The handlers of the run on this thread, or else those the next run here starts with.
+    pub fn current(&self) -> Callbacks {
+        let key = self.key();
+        let running = RUNNING.with(|running| running.borrow().get(&key).and_then(Weak::upgrade));
+        match running {
+            Some(handlers) => Callbacks(handlers),
+            None => PENDING.with(|pending| pending.borrow_mut().entry(key).or_default().clone()),
+        }
+    }
+
+    fn key(&self) -> usize {
+        Arc::as_ptr(&self.key) as usize
+    }
+}
+
+/// The host's event handlers and listeners of a run of an engine, which can be replaced
+/// while it runs. The callbacks of its windows own them, so they're dropped when it stops,
+/// along with the engine clones they hold; until it starts, the registry does. A handler is taken out before it's called, so it
+/// may replace handlers itself; the replacement handles the next event.
+#[derive(Clone, Default)]
+pub(crate) struct Callbacks(Rc<RefCell<Handlers>>);
+
+impl Callbacks {
+    pub fn set_on_click(&self, on_click: Option<PointerHandler>) {
+        self.0.borrow_mut().on_click = on_click.map(Rc::from);
+    }
+
+    pub fn set_on_hover(&self, on_hover: Option<PointerHandler>) {
+        self.0.borrow_mut().on_hover = on_hover.map(Rc::from);
+    }
+
+    pub fn set_on_key(&self, on_key: Option<KeyHandler>) {
+        self.0.borrow_mut().on_key = on_key.map(Rc::from);
+    }
+
+    pub fn on_click(&self) -> Option<SharedPointerHandler> {
+        self.0.borrow().on_click.clone()
+    }
+
+    pub fn on_hover(&self) -> Option<SharedPointerHandler> {
+        self.0.borrow().on_hover.clone()
+    }
+
+    pub fn on_key(&self) -> Option<SharedKeyHandler> {
+        self.0.borrow().on_key.clone()
+    }
+
+    pub fn add_listener(&self, node: Id, kind: EventKind, listener: EventListener) -> ListenerId {
+        let id = ListenerId::next();
+        self.0
+            .borrow_mut()
+            .listeners
+            .entry(node)
+            .or_default()
+            .push(Listener {
+                id,
+                kind,
+                callback: Rc::from(listener),
+            });
+        id
+    }
+
+    /// Returns whether the listener was still there.
+    pub fn remove_listener(&self, listener: ListenerId) -> bool {
+        let mut handlers = self.0.borrow_mut();
+        let Some((&node, listeners)) = handlers
+            .listeners
+            .iter_mut()
+            .find(|(_, listeners)| listeners.iter().any(|l| l.id == listener))
+        else {
+            return false;
+        };
+        listeners.retain(|l| l.id != listener);
+        if listeners.is_empty() {
+            handlers.listeners.remove(&node);
+        }
+        true
+    }
+
+    /// Call the listeners for events of the kind on `path`, the target then its ancestors as
+    /// [`crate::layout::RenderNode::find_element_at_position`] gives them, until one stops
+    /// the event. Listeners added or removed meanwhile count from the next node on.
+    pub fn dispatch(
+        &self,
+        kind: EventKind,
+        path: &[Id],
+        position: Option<(f64, f64)>,
+        key: Option<&KeyPress>,
+    ) -> EventStatus {
+        self.dispatch_event(kind, path, position, key.cloned(), None)
+    }
+
+    /// Call the drag listeners on `path`, from the node that has the pointer captured, like
+    /// [`Self::dispatch`] does.
+    pub fn dispatch_drag(&self, path: &[Id], position: (f64, f64), drag: Drag) -> EventStatus {
+        self.dispatch_event(EventKind::Drag, path, Some(position), None, Some(drag))
+    }
+
+    /// The first node on `path` with a listener for events of the kind.
+    pub fn first_listening(&self, kind: EventKind, path: &[Id]) -> Option<Id> {
+        let handlers = self.0.borrow();
+        path.iter().copied().find(|node| {
+            handlers
+                .listeners
+                .get(node)
+                .is_some_and(|listeners| listeners.iter().any(|l| l.kind == kind))
+        })
+    }
+
+    fn dispatch_event(
+        &self,
+        kind: EventKind,
+        path: &[Id],
+        position: Option<(f64, f64)>,
+        key: Option<KeyPress>,
+        drag: Option<Drag>,
+    ) -> EventStatus {
+        let Some(&target) = path.first() else {
+            return EventStatus::Continue;
+        };
+        let mut event = Event {
+            kind,
+            target,
+            current_target: target,
+            position,
+            key,
+            drag,
+        };
+        for &node in path {
+            let listeners: Vec<SharedEventListener> = self
+                .0
+                .borrow()
+                .listeners
+                .get(&node)
+                .into_iter()
+                .flatten()
+                .filter(|listener| listener.kind == kind)
+                .map(|listener| listener.callback.clone())
+                .collect();
+            event.current_target = node;
+            let mut status = EventStatus::Continue;
+            for listener in listeners {
+                if listener(&event) == EventStatus::StopPropagation {
+                    status = EventStatus::StopPropagation;
+                }
+            }
+            if status == EventStatus::StopPropagation {
+                return status;
+            }
+        }
+        EventStatus::Continue
+    }
+}
+
+#[cfg(test)]
+mod callbacks_tests;
@@ -0,0 +1,157 @@
+use super::{Callbacks, Event, EventKind, EventListener, EventStatus};
+use crate::Id;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type Calls = Rc<RefCell<Vec<(&'static str, u64, u64)>>>;
+
+/// A listener recording its name, the event's target and current target, and returning
+/// `status`.
+fn record(calls: &Calls, name: &'static str, status: EventStatus) -> EventListener {
+    let calls = Rc::clone(calls);
+    Box::new(move |event: &Event| {
+        calls
+            .borrow_mut()
+            .push((name, event.target.as_u64(), event.current_target.as_u64()));
+        status
+    })
+}
+
+fn id(id: u64) -> Id {
+    Id::from_u64(id)
+}
+
+/// The path of a node 3 in a node 2 in the root.
+const PATH: [u64; 3] = [3, 2, 0];
+
+fn dispatch(callbacks: &Callbacks, kind: EventKind) -> EventStatus {
+    let path: Vec<Id> = PATH.into_iter().map(id).collect();
+    callbacks.dispatch(kind, &path, Some((1.0, 2.0)), None)
+}
+
+#[test]
+fn events_bubble_from_the_target_up_through_its_ancestors() {
+    let callbacks = Callbacks::default();
+    let calls = Calls::default();
+    callbacks.add_listener(
+        id(0),
+        EventKind::Click,
+        record(&calls, "root", EventStatus::Continue),
+    );
+    callbacks.add_listener(
+        id(3),
+        EventKind::Click,
+        record(&calls, "first", EventStatus::Continue),
+    );
+    callbacks.add_listener(
+        id(2),
+        EventKind::Click,
+        record(&calls, "parent", EventStatus::Continue),
+    );
+    callbacks.add_listener(
+        id(3),
+        EventKind::Click,
+        record(&calls, "second", EventStatus::Continue),
+    );
+    // Other kinds and nodes off the path aren't called.
+    callbacks.add_listener(
+        id(3),
+        EventKind::Hover,
+        record(&calls, "hover", EventStatus::Continue),
+    );
+    callbacks.add_listener(
+        id(4),
+        EventKind::Click,
+        record(&calls, "other", EventStatus::Continue),
+    );
+
+    assert_eq!(
+        dispatch(&callbacks, EventKind::Click),
+        EventStatus::Continue
+    );
+    assert_eq!(
+        *calls.borrow(),
+        [
+            ("first", 3, 3),
+            ("second", 3, 3),
+            ("parent", 3, 2),
+            ("root", 3, 0)
+        ]
+    );
+}
+
+#[test]
+fn stopping_propagation_still_calls_the_node_s_other_listeners() {
+    let callbacks = Callbacks::default();
+    let calls = Calls::default();
+    let stop = EventStatus::StopPropagation;
+    callbacks.add_listener(id(2), EventKind::MouseDown, record(&calls, "stops", stop));
+    callbacks.add_listener(
+        id(2),
+        EventKind::MouseDown,
+        record(&calls, "after", EventStatus::Continue),
+    );
+    callbacks.add_listener(
+        id(0),
+        EventKind::MouseDown,
+        record(&calls, "root", EventStatus::Continue),
+    );
+
+    assert_eq!(dispatch(&callbacks, EventKind::MouseDown), stop);
+    assert_eq!(*calls.borrow(), [("stops", 3, 2), ("after", 3, 2)]);
+
+    // Nothing to dispatch to.
+    assert_eq!(
+        callbacks.dispatch(EventKind::MouseDown, &[], None, None),
+        EventStatus::Continue
+    );
+}
+
+#[test]
+fn removed_listeners_are_no_longer_called() {
+    let callbacks = Callbacks::default();
+    let calls = Calls::default();
+    let stop = EventStatus::StopPropagation;
+    let stops = callbacks.add_listener(id(3), EventKind::MouseUp, record(&calls, "stops", stop));
+    callbacks.add_listener(
+        id(2),
+        EventKind::MouseUp,
+        record(&calls, "parent", EventStatus::Continue),
+    );
+
+    assert!(callbacks.remove_listener(stops));
+    assert!(!callbacks.remove_listener(stops));
+    assert_eq!(
+        dispatch(&callbacks, EventKind::MouseUp),
+        EventStatus::Continue
+    );
+    assert_eq!(*calls.borrow(), [("parent", 3, 2)]);
+
+    // Each engine has listeners of its own.
+    let other = Callbacks::default();
+    assert_eq!(dispatch(&other, EventKind::MouseUp), EventStatus::Continue);
+    assert_eq!(calls.borrow().len(), 1);
+}
+
+#[test]
+fn a_listener_can_remove_listeners_of_the_nodes_above() {
+    let callbacks = Callbacks::default();
+    let calls = Calls::default();
+    let parent = callbacks.add_listener(
+        id(2),
+        EventKind::Hover,
+        record(&calls, "parent", EventStatus::Continue),
+    );
+    let removing = callbacks.clone();
+    callbacks.add_listener(
+        id(3),
+        EventKind::Hover,
+        Box::new(move |_| {
+            removing.remove_listener(parent);
+            EventStatus::Continue
+        }),
+    );
+
+    dispatch(&callbacks, EventKind::Hover);
+    assert!(calls.borrow().is_empty());
+}
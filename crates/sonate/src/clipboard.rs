@@ -0,0 +1,95 @@
+/// Put `text` on the system clipboard. Returns `false` if that failed or isn't supported:
+/// on Linux the X11 and Wayland clipboards need their owner to keep serving the text, which
+/// this doesn't do, so hosts copy it themselves from [`crate::Params::on_copy`].
+pub(crate) fn set_text(text: &str) -> bool {
+    platform::set_text(text)
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::Win32::Foundation::{GlobalFree, HANDLE};
+    use windows::Win32::System::DataExchange::{
+        CloseClipboard, EmptyClipboard, OpenClipboard, SetClipboardData,
+    };
+    use windows::Win32::System::Memory::{GlobalAlloc, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+
+    /// `CF_UNICODETEXT`: NUL-terminated UTF-16.
+    const UNICODE_TEXT: u32 = 13;
+
+    pub fn set_text(text: &str) -> bool {
+        let wide: Vec<u16> = text.encode_utf16().chain([0]).collect();
+        unsafe {
+            if OpenClipboard(None).is_err() {
+                return false;
+            }
+            let copied = replace_contents(&wide);
+            let _ = CloseClipboard();
+            copied
+        }
+    }
+
+    /// Safety: the clipboard must be open.
+    unsafe fn replace_contents(wide: &[u16]) -> bool {
+        if EmptyClipboard().is_err() {
+            return false;
+        }
+        let Ok(memory) = GlobalAlloc(GMEM_MOVEABLE, std::mem::size_of_val(wide)) else {
+            return false;
+        };
+        let target = GlobalLock(memory) as *mut u16;
+        if target.is_null() {
+            let _ = GlobalFree(Some(memory));
+            return false;
+        }
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), target, wide.len());
+        let _ = GlobalUnlock(memory);
+
+        // The clipboard owns the memory once it accepted it.
+        if SetClipboardData(UNICODE_TEXT, Some(HANDLE(memory.0))).is_err() {
+            let _ = GlobalFree(Some(memory));
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use objc2::msg_send;
+    use objc2::runtime::{AnyClass, AnyObject, Bool};
+    use std::ffi::CString;
+
+    pub fn set_text(text: &str) -> bool {
+        let (Some(pasteboard_class), Some(string_class)) =
+            (AnyClass::get(c"NSPasteboard"), AnyClass::get(c"NSString"))
+        else {
+            return false;
+        };
+        let Ok(text) = CString::new(text) else {
+            return false;
+        };
+
+        unsafe {
+            let pasteboard: *mut AnyObject = msg_send![pasteboard_class, generalPasteboard];
+            let string: *mut AnyObject =
+                msg_send![string_class, stringWithUTF8String: text.as_ptr()];
+            // `NSPasteboardTypeString`.
+            let kind: *mut AnyObject =
+                msg_send![string_class, stringWithUTF8String: c"public.utf8-plain-text".as_ptr()];
+            if pasteboard.is_null() || string.is_null() || kind.is_null() {
+                return false;
+            }
+
+            let _: isize = msg_send![pasteboard, clearContents];
+            let written: Bool = msg_send![pasteboard, setString: string, forType: kind];
+            written.as_bool()
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+mod platform {
+    pub fn set_text(_text: &str) -> bool {
+        false
+    }
+}
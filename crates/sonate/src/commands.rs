@@ -1,90 +1,438 @@
-use crate::css_parser::parse_css;
-use crate::layout::{build_render_tree, LayoutContext, RenderNode};
+use crate::animation::FRAME_INTERVAL;
+use crate::css_parser::parse_css_with_warnings;
+use crate::diagnostics::{DiagnosticCode, Diagnostics};
+use crate::health::Health;
+use crate::hit_index::IndexedTree;
+use crate::idle::{IdleWarmup, WarmupCounters};
+use crate::images::ImageLoader;
+use crate::layout::{rebuild_render_tree, IdInUse, LayoutContext, RenderNode, SetParentError};
+use crate::markup::write_markup;
+use crate::progress::Progress;
+use crate::query::QuerySelector;
+use crate::resources::{CacheAttachment, ImageError, ImageHandle};
+use crate::stats::StatsRecorder;
+use crate::style::Rgba;
+use crate::template::{Fragment, TemplateId, TemplateStats};
+use crate::text::{FontProvider, SkiaTextMeasurer};
+use crate::threading;
 use crate::Id;
+use log::{debug, trace, warn};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{
     mpsc::{self, Receiver},
-    Arc, RwLock,
+    Arc, RwLock, Weak,
 };
 use std::time::{Duration, Instant};
 
 use crate::windowing::{WindowMessage, WindowMessageSender};
 
+/// How many commands may wait for the data thread, unless
+/// [`crate::EngineOptions::command_capacity`] says otherwise.
+pub const DEFAULT_COMMAND_CAPACITY: usize = 4096;
+
 pub(crate) enum Command {
     AddStylesheet(String),
-    CreateNode(Id, Option<String>),
+    /// A node with its text, replying whether it was created if asked to. Not created if
+    /// another node has the id.
+    CreateNode(
+        Id,
+        Option<String>,
+        Option<mpsc::Sender<Result<(), IdInUse>>>,
+    ),
+    /// A node with a tag, then its text, created unless another node has the id.
+    CreateElement(Id, String, Option<String>),
     SetParent(Id, Id),
+    /// Insert the second node under the first, before the third, and reply whether it was.
+    InsertBefore(Id, Id, Id, mpsc::Sender<Result<(), SetParentError>>),
+    /// Move the second node to the index among the first's children, and reply whether it
+    /// was.
+    MoveChild(Id, Id, usize, mpsc::Sender<Result<(), SetParentError>>),
     SetAttribute(Id, String, String),
-    SetViewportSize(f64, f64),
+    /// The user edited the value of an editable node, or the host replaced a node's text.
+    SetText(Id, Option<String>),
+    /// The viewport of the tree under the given root changed.
+    SetViewportSize(Id, f64, f64),
+    /// A window with the given root opened; its tree is laid out from now on.
+    CreateWindow(Id),
+    /// The window with the given root closed; its tree is destroyed.
+    CloseWindow(Id),
+    SetReducedMotion(bool),
+    /// The user focused the node, or took focus away from the one that had it.
+    SetFocus(Option<Id>),
+    SetFocusAccentColor(Rgba),
+    /// Wakes the thread for fonts registered through an engine sharing its caches, which
+    /// it takes up before the batch the command is in, see [`CacheAttachment`].
+    FontsChanged,
+    /// The image of a `src` attribute finished decoding, or failed to.
+    ImageLoaded(String, Result<ImageHandle, ImageError>),
+    DefineTemplate(TemplateId, Fragment),
+    /// The top-level nodes just created from a template.
+    AddTemplateInstances(TemplateId, Vec<Id>),
+    TemplateStats(mpsc::Sender<TemplateStats>),
+    /// The queries of the tree, answered as of the commands sent before them.
+    Children(Id, mpsc::Sender<Vec<Id>>),
+    Parent(Id, mpsc::Sender<Option<Id>>),
+    Descendants(Id, mpsc::Sender<Vec<Id>>),
+    /// Whether the second node is the first or one of its descendants.
+    Contains(Id, Id, mpsc::Sender<bool>),
+    /// The markup of the document, see `markup::write_markup`.
+    DumpDocument(mpsc::Sender<String>),
+    /// The ids of at most so many nodes the selector matches, in document order.
+    Query(QuerySelector, usize, mpsc::Sender<Vec<Id>>),
+    /// Lay out at the given viewport size right away and reply with the snapshot.
+    LayoutHeadless(f64, f64, mpsc::Sender<Arc<RenderNode>>),
+    /// A frame callback ran; lay out its changes for the next frame instead of debouncing.
+    Frame,
+    /// Marks the commands sent before it for `Progress`.
+    #[cfg_attr(not(feature = "async"), allow(unused))]
+    Acknowledge(u64),
     #[allow(unused)]
     Layout,
+    /// Stops the data thread once the commands sent before it are handled.
+    Shutdown,
+    /// Panics while it's handled, as a command with a bug would.
+    #[cfg(test)]
+    Panic,
+    /// Panics outside of the commands' handling, which stops the data thread.
+    #[cfg(test)]
+    PanicThread,
 }
 
+/// What a command does to which nodes, without the stylesheet, text and template payloads
+/// or the reply channels.
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::AddStylesheet(css) => write!(f, "AddStylesheet({} bytes)", css.len()),
+            Command::CreateNode(id, _, _) => write!(f, "CreateNode({})", id.as_u64()),
+            Command::CreateElement(id, tag, _) => {
+                write!(f, "CreateElement({}, {tag})", id.as_u64())
+            }
+            Command::SetParent(parent, child) => {
+                write!(f, "SetParent({}, {})", parent.as_u64(), child.as_u64())
+            }
+            Command::InsertBefore(parent, child, reference, _) => write!(
+                f,
+                "InsertBefore({}, {}, {})",
+                parent.as_u64(),
+                child.as_u64(),
+                reference.as_u64()
+            ),
+            Command::MoveChild(parent, child, index, _) => write!(
+                f,
+                "MoveChild({}, {}, {index})",
+                parent.as_u64(),
+                child.as_u64()
+            ),
+            Command::SetAttribute(id, key, _) => {
+                write!(f, "SetAttribute({}, {key:?})", id.as_u64())
+            }
+            Command::SetText(id, _) => write!(f, "SetText({})", id.as_u64()),
+            Command::SetViewportSize(root, width, height) => {
+                write!(f, "SetViewportSize({}, {width}, {height})", root.as_u64())
+            }
+            Command::CreateWindow(root) => write!(f, "CreateWindow({})", root.as_u64()),
+            Command::CloseWindow(root) => write!(f, "CloseWindow({})", root.as_u64()),
+            Command::SetReducedMotion(reduce) => write!(f, "SetReducedMotion({reduce})"),
+            Command::SetFocus(focused) => {
+                write!(f, "SetFocus({:?})", focused.map(|id| id.as_u64()))
+            }
+            Command::SetFocusAccentColor(accent) => write!(f, "SetFocusAccentColor({accent:?})"),
+            Command::FontsChanged => write!(f, "FontsChanged"),
+            Command::ImageLoaded(src, result) => {
+                write!(f, "ImageLoaded({src:?}, {})", result.is_ok())
+            }
+            Command::DefineTemplate(id, _) => write!(f, "DefineTemplate({id:?})"),
+            Command::AddTemplateInstances(id, roots) => {
+                write!(f, "AddTemplateInstances({id:?}, {} roots)", roots.len())
+            }
+            Command::TemplateStats(_) => write!(f, "TemplateStats"),
+            Command::Children(id, _) => write!(f, "Children({})", id.as_u64()),
+            Command::Parent(id, _) => write!(f, "Parent({})", id.as_u64()),
+            Command::Descendants(id, _) => write!(f, "Descendants({})", id.as_u64()),
+            Command::Contains(ancestor, node, _) => {
+                write!(f, "Contains({}, {})", ancestor.as_u64(), node.as_u64())
+            }
+            Command::DumpDocument(_) => write!(f, "DumpDocument"),
+            Command::Query(selector, limit, _) => write!(f, "Query({selector:?}, {limit})"),
+            Command::LayoutHeadless(width, height, _) => {
+                write!(f, "LayoutHeadless({width}, {height})")
+            }
+            Command::Frame => write!(f, "Frame"),
+            Command::Acknowledge(mark) => write!(f, "Acknowledge({mark})"),
+            Command::Layout => write!(f, "Layout"),
+            Command::Shutdown => write!(f, "Shutdown"),
+            #[cfg(test)]
+            Command::Panic => write!(f, "Panic"),
+            #[cfg(test)]
+            Command::PanicThread => write!(f, "PanicThread"),
+        }
+    }
+}
+
+/// Where the data thread publishes what it laid out.
+#[derive(Clone, Default)]
+pub(crate) struct Snapshots {
+    /// The tree under the document root, shown by the primary window.
+    pub primary: Arc<RwLock<Option<Arc<IndexedTree>>>>,
+    /// The trees of secondary windows by their root.
+    pub windows: Arc<RwLock<HashMap<Id, Arc<IndexedTree>>>>,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn handle_commands(
     rx: Receiver<Command>,
-    snapshot: Arc<RwLock<Option<RenderNode>>>,
+    snapshot: Snapshots,
     message_sender: WindowMessageSender,
+    fonts: FontProvider,
+    resources: Weak<CacheAttachment>,
+    diagnostics: Arc<Diagnostics>,
+    mut idle: IdleWarmup,
+    progress: Arc<Progress>,
+    health: Arc<Health>,
+    capacity: usize,
+    stats: Arc<StatsRecorder>,
 ) {
     let mut ctx = LayoutContext::new();
+    ctx.text_measurer = Arc::new(SkiaTextMeasurer::with_fonts(fonts));
+    ctx.images = ImageLoader::new(resources.clone());
     let mut deadline: Option<Instant> = None;
+    let warmup_counters = Arc::clone(idle.counters());
+    // A command that arrived during warmup, handled before receiving the next one.
+    let mut preempted_by: Option<Command> = None;
+    // Acknowledgments handled, and those covered by the published snapshot.
+    let mut applied = 0;
+    let mut laid_out = 0;
 
-    loop {
+    'commands: loop {
         // Determine timeout based on debounce deadline
         let timeout = match deadline {
             Some(dl) => {
                 let now = Instant::now();
                 if dl <= now {
                     // Deadline expired: run layout now
-                    ctx.layout();
-                    let root = ctx.document.root_node();
-                    let snap = build_render_tree(root);
-                    *snapshot.write().unwrap() = Some(snap);
-                    message_sender.send(WindowMessage::Redraw);
-                    deadline = None;
+                    let published = panic::catch_unwind(AssertUnwindSafe(|| {
+                        layout_and_publish(
+                            &mut ctx,
+                            &snapshot,
+                            &message_sender,
+                            &warmup_counters,
+                            &stats,
+                        )
+                    }));
+                    laid_out = applied;
+                    deadline = match published {
+                        Ok(_) => next_frame(&ctx),
+                        // The snapshot stays as it was until something changes.
+                        Err(payload) => {
+                            health.recovered(&*payload);
+                            None
+                        }
+                    };
+                    idle.activity(Instant::now());
                     // After layout, continue to next iteration
                     continue;
                 } else {
                     dl - now
                 }
             }
-            None => Duration::from_millis(u64::MAX / 2), // effectively wait forever
+            // Nothing to lay out: warm caches once idle, yielding to the next command.
+            None if preempted_by.is_none() && idle.is_due(Instant::now()) => {
+                let mut disconnected = false;
+                idle.run(&ctx, || match rx.try_recv() {
+                    Ok(cmd) => {
+                        preempted_by = Some(cmd);
+                        true
+                    }
+                    Err(mpsc::TryRecvError::Empty) => false,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        disconnected = true;
+                        true
+                    }
+                });
+                if disconnected {
+                    break;
+                }
+                continue;
+            }
+            None => match idle.deadline() {
+                Some(idle_deadline) => idle_deadline.saturating_duration_since(Instant::now()),
+                None => Duration::from_millis(u64::MAX / 2), // effectively wait forever
+            },
         };
 
-        match rx.recv_timeout(timeout) {
-            Ok(cmd) => match cmd {
-                Command::AddStylesheet(css) => match parse_css(&css) {
-                    Ok(sheet) => {
-                        for rule in sheet.rules {
-                            ctx.style_sheet.add_rule(rule);
-                        }
-                        if deadline.is_none() {
-                            deadline = Some(Instant::now() + Duration::from_millis(100));
-                        }
+        // Commands the thread sent itself come first, as nothing else wakes it for them.
+        let received = match preempted_by.take().or_else(threading::take_deferred) {
+            Some(cmd) => Ok(cmd),
+            None => rx.try_recv().or_else(|_| {
+                // Out of commands: tell waiters about everything handled so far at once.
+                progress.publish(applied, laid_out);
+                rx.recv_timeout(timeout)
+            }),
+        };
+        if received.is_ok() {
+            idle.activity(Instant::now());
+        }
+
+        let cmd = match received {
+            Ok(cmd) => cmd,
+            // Handled at the top of the loop, as the deadline expired.
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        // The rest of the queue is handled before deciding whether to lay out, without the
+        // changes that later ones make redundant.
+        let mut batch = vec![cmd];
+        batch.extend(std::iter::from_fn(threading::take_deferred));
+        batch.extend(rx.try_iter().take(capacity));
+        // Also when the queue had no room for `FontsChanged`: text measured with the
+        // fallback fonts is measured again.
+        if resources
+            .upgrade()
+            .is_some_and(|resources| resources.take_fonts_changed())
+        {
+            ctx.text_measurer.invalidate();
+            ctx.templates.invalidate();
+            if deadline.is_none() {
+                deadline = Some(Instant::now() + Duration::from_millis(100));
+            }
+        }
+        for cmd in coalesce(batch) {
+            debug!("command {cmd:?}");
+            if let Command::Shutdown = cmd {
+                break 'commands;
+            }
+            #[cfg(test)]
+            if let Command::PanicThread = cmd {
+                panic!("the data thread panicked");
+            }
+
+            // A command that panics is dropped, and the document keeps what the others did.
+            let handled = panic::catch_unwind(AssertUnwindSafe(|| match cmd {
+                Command::AddStylesheet(css) => {
+                    let first_rule = ctx.style_sheet.rules.len();
+                    let (sheet, warnings) = parse_css_with_warnings(&css);
+                    for warning in warnings {
+                        let rule_index = warning.rule_index.map(|i| first_rule + i);
+                        diagnostics.report(warning.code, None, rule_index, || warning.message);
                     }
-                    Err(e) => {
-                        eprintln!("Failed to parse CSS: {}", e);
+
+                    for rule in sheet.rules {
+                        ctx.style_sheet.add_rule(rule);
                     }
-                },
-                Command::CreateNode(id, text) => {
-                    ctx.document.create_node(id, text);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::CreateNode(id, text, reply) => {
+                    let result = ctx.document.create_node(id, text).map(drop);
+                    if let Err(err) = result {
+                        report_id_in_use(&diagnostics, err);
+                    }
+                    if let Some(reply) = reply {
+                        let _ = reply.send(result);
+                    }
+                    if result.is_err() {
+                        return;
+                    }
+                    ctx.templates.detach(id);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::CreateElement(id, tag, text) => {
+                    if let Err(err) = ctx.document.create_element(id, &tag, text) {
+                        report_id_in_use(&diagnostics, err);
+                        return;
+                    }
+                    ctx.templates.detach(id);
                     if deadline.is_none() {
                         deadline = Some(Instant::now() + Duration::from_millis(100));
                     }
                 }
                 Command::SetParent(p, c) => {
-                    ctx.document.set_parent(p, c).expect("data thread down");
+                    if let Err(err) = ctx.document.set_parent(p, c) {
+                        warn!(
+                            "failed to set parent of node {} to node {}: {err}",
+                            c.as_u64(),
+                            p.as_u64()
+                        );
+                        report_parent_error(&diagnostics, err, p, c, c, || {
+                            format!("set_parent({}, {})", p.as_u64(), c.as_u64())
+                        });
+                        return;
+                    }
+                    ctx.templates.detach(p);
+                    ctx.templates.detach(c);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::InsertBefore(p, c, reference, reply) => {
+                    let result = ctx.document.insert_before(p, c, reference);
+                    if let Err(err) = result {
+                        report_parent_error(&diagnostics, err, p, c, reference, || {
+                            let (p, c, r) = (p.as_u64(), c.as_u64(), reference.as_u64());
+                            format!("insert_before({p}, {c}, {r})")
+                        });
+                    }
+                    let _ = reply.send(result);
+                    if result.is_err() {
+                        return;
+                    }
+                    ctx.templates.detach(p);
+                    ctx.templates.detach(c);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::MoveChild(p, c, index, reply) => {
+                    let result = ctx.document.move_child(p, c, index);
+                    if let Err(err) = result {
+                        report_parent_error(&diagnostics, err, p, c, c, || {
+                            format!("move_child({}, {}, {index})", p.as_u64(), c.as_u64())
+                        });
+                    }
+                    let _ = reply.send(result);
+                    if result.is_err() {
+                        return;
+                    }
+                    ctx.templates.detach(p);
                     if deadline.is_none() {
                         deadline = Some(Instant::now() + Duration::from_millis(100));
                     }
                 }
                 Command::SetAttribute(id, k, v) => {
+                    if ctx.document.get_node(id).is_none() {
+                        diagnostics.report(DiagnosticCode::UnknownNode, Some(id), None, || {
+                            format!("set_attribute({}, {:?}): node not found", id.as_u64(), k)
+                        });
+                        return;
+                    }
+                    ctx.templates.detach(id);
                     ctx.document.set_attribute(id, k, v);
                     if deadline.is_none() {
                         deadline = Some(Instant::now() + Duration::from_millis(100));
                     }
                 }
-                Command::SetViewportSize(width, height) => {
+                Command::SetText(id, text) => {
+                    if ctx.document.get_node(id).is_none() {
+                        diagnostics.report(DiagnosticCode::UnknownNode, Some(id), None, || {
+                            format!("set_text({}): node not found", id.as_u64())
+                        });
+                        return;
+                    }
+                    ctx.templates.detach(id);
+                    ctx.document.set_text(id, text);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::SetViewportSize(root, width, height) => {
                     if width > 0.0 && height > 0.0 {
-                        ctx.set_viewport_size(width, height);
+                        ctx.set_root_viewport_size(root, width, height);
 
                         // Keep resize responsive without relayouting on every single event.
                         let new_deadline = Instant::now() + Duration::from_millis(16);
@@ -94,21 +442,253 @@ pub(crate) fn handle_commands(
                         });
                     }
                 }
+                Command::CreateWindow(root) => {
+                    ctx.document.create_window_root(root);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::CloseWindow(root) => {
+                    ctx.remove_window_root(root);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                Command::SetReducedMotion(reduce) => {
+                    if ctx.media.prefers_reduced_motion != reduce {
+                        ctx.set_prefers_reduced_motion(reduce);
+                        if deadline.is_none() {
+                            deadline = Some(Instant::now() + Duration::from_millis(100));
+                        }
+                    }
+                }
+                Command::SetFocus(focused) => {
+                    // Both nodes restyle, even when they came from a template.
+                    for id in [ctx.focused(), focused].into_iter().flatten() {
+                        ctx.templates.detach(id);
+                    }
+                    ctx.set_focus(focused);
+                    // The ring follows the click within a frame.
+                    let new_deadline = Instant::now() + FRAME_INTERVAL;
+                    deadline = Some(match deadline {
+                        Some(existing) => existing.min(new_deadline),
+                        None => new_deadline,
+                    });
+                }
+                Command::SetFocusAccentColor(accent) => {
+                    if let Some(focused) = ctx.focused() {
+                        ctx.templates.detach(focused);
+                    }
+                    ctx.set_focus_accent_color(accent);
+                    if deadline.is_none() {
+                        deadline = Some(Instant::now() + Duration::from_millis(100));
+                    }
+                }
+                // Taken up before the batch.
+                Command::FontsChanged => {}
+                Command::ImageLoaded(src, result) => {
+                    match result {
+                        Err(ImageError::Load) => {
+                            diagnostics.report(DiagnosticCode::ImageLoadFailed, None, None, || {
+                                format!("`src` image `{src}` couldn't be read")
+                            })
+                        }
+                        Err(ImageError::Decode) => diagnostics.report(
+                            DiagnosticCode::ImageDecodeFailed,
+                            None,
+                            None,
+                            || format!("`src` image `{src}` couldn't be decoded"),
+                        ),
+                        Ok(_) => {}
+                    }
+                    if ctx.images.loaded(src, result) && deadline.is_none() {
+                        // The natural size may move the nodes around the image.
+                        deadline = Some(Instant::now() + FRAME_INTERVAL);
+                    }
+                }
+                Command::DefineTemplate(id, fragment) => {
+                    ctx.templates.define(id, fragment, &ctx);
+                }
+                Command::AddTemplateInstances(template, roots) => {
+                    ctx.templates.add_instances(template, &roots, &ctx.document);
+                }
+                Command::TemplateStats(reply) => {
+                    let _ = reply.send(ctx.templates.stats());
+                }
+                Command::Children(id, reply) => {
+                    let _ = reply.send(ctx.document.children(id));
+                }
+                Command::Parent(id, reply) => {
+                    let _ = reply.send(ctx.document.parent(id));
+                }
+                Command::Descendants(id, reply) => {
+                    let _ = reply.send(ctx.document.descendants(id));
+                }
+                Command::Contains(ancestor, node, reply) => {
+                    let _ = reply.send(ctx.document.contains(ancestor, node));
+                }
+                Command::DumpDocument(reply) => {
+                    let _ = reply.send(write_markup(&ctx.document));
+                }
+                Command::Query(selector, limit, reply) => {
+                    let found = selector.matching(&ctx.document, ctx.focused(), limit);
+                    let _ = reply.send(found);
+                }
+                Command::LayoutHeadless(width, height, reply) => {
+                    ctx.set_viewport_size(width, height);
+                    let snap = layout_and_publish(
+                        &mut ctx,
+                        &snapshot,
+                        &message_sender,
+                        &warmup_counters,
+                        &stats,
+                    );
+                    laid_out = applied;
+                    deadline = next_frame(&ctx);
+                    let _ = reply.send(snap);
+                }
+                Command::Frame => {
+                    if let Some(pending) = deadline {
+                        deadline = Some(pending.min(Instant::now()));
+                    }
+                }
+                Command::Acknowledge(sequence) => {
+                    applied = applied.max(sequence);
+                    if deadline.is_none() {
+                        // Nothing changed since the last layout.
+                        laid_out = applied;
+                    }
+                }
                 Command::Layout => {
                     // Immediate layout flush
-                    ctx.layout();
-                    let root = ctx.document.root_node();
-                    let snap = build_render_tree(root);
-                    *snapshot.write().unwrap() = Some(snap);
-                    message_sender.send(WindowMessage::Redraw);
-                    deadline = None;
+                    layout_and_publish(
+                        &mut ctx,
+                        &snapshot,
+                        &message_sender,
+                        &warmup_counters,
+                        &stats,
+                    );
+                    laid_out = applied;
+                    deadline = next_frame(&ctx);
                 }
-            },
-            Err(mpsc::RecvTimeoutError::Timeout) => {
-                // handled at top loop when checking expired deadline
-                continue;
+                #[cfg(test)]
+                Command::Panic => panic!("the command panicked"),
+                Command::Shutdown => unreachable!("stops the thread before it's handled"),
+                #[cfg(test)]
+                Command::PanicThread => unreachable!("panics before it's handled"),
+            }));
+            if let Err(payload) = handled {
+                health.recovered(&*payload);
             }
-            Err(mpsc::RecvTimeoutError::Disconnected) => break,
         }
     }
+
+    progress.close();
 }
+
+/// Drop the commands of a batch that later ones make redundant: a `SetAttribute` followed by
+/// one of the same node and key, and every `Layout` but the last.
+fn coalesce(batch: Vec<Command>) -> Vec<Command> {
+    let last_layout = batch.iter().rposition(|cmd| matches!(cmd, Command::Layout));
+    let mut kept: Vec<Command> = Vec::with_capacity(batch.len());
+    for (index, cmd) in batch.into_iter().enumerate() {
+        if matches!(cmd, Command::Layout) && Some(index) != last_layout {
+            continue;
+        }
+        if let (
+            Command::SetAttribute(id, key, _),
+            Some(Command::SetAttribute(last_id, last_key, _)),
+        ) = (&cmd, kept.last())
+        {
+            if id == last_id && key == last_key {
+                kept.pop();
+            }
+        }
+        kept.push(cmd);
+    }
+    kept
+}
+
+/// Report why a tree edit failed. `call` names the edit; `not_a_child` is the node that
+/// wasn't a child of `parent`.
+fn report_parent_error(
+    diagnostics: &Diagnostics,
+    err: SetParentError,
+    parent: Id,
+    child: Id,
+    not_a_child: Id,
+    call: impl FnOnce() -> String,
+) {
+    let (code, node) = match err {
+        SetParentError::SameNode => (DiagnosticCode::InvalidParent, child),
+        SetParentError::ChildNotFound => (DiagnosticCode::UnknownNode, child),
+        SetParentError::ParentNotFound => (DiagnosticCode::UnknownNode, parent),
+        SetParentError::Cycle => (DiagnosticCode::ParentCycle, child),
+        SetParentError::NotAChild => (DiagnosticCode::NotAChild, not_a_child),
+    };
+    diagnostics.report(code, Some(node), None, || format!("{}: {}", call(), err));
+}
+
+fn report_id_in_use(diagnostics: &Diagnostics, err: IdInUse) {
+    diagnostics.report(DiagnosticCode::IdInUse, Some(err.0), None, || {
+        format!("create_node({}): {err}", err.0.as_u64())
+    });
+}
+
+/// When to lay out again to show the next frame of running transitions, if any.
+fn next_frame(ctx: &LayoutContext) -> Option<Instant> {
+    ctx.has_running_transitions()
+        .then(|| Instant::now() + FRAME_INTERVAL)
+}
+
+/// Lay out the document, make the result the current snapshots and ask for a redraw.
+/// Returns the primary window's snapshot.
+fn layout_and_publish(
+    ctx: &mut LayoutContext,
+    snapshot: &Snapshots,
+    message_sender: &WindowMessageSender,
+    warmup_counters: &WarmupCounters,
+    stats: &StatsRecorder,
+) -> Arc<RenderNode> {
+    let started = Instant::now();
+    ctx.set_frame_time(started);
+    let laid_out_before = ctx.laid_out_nodes();
+    ctx.layout();
+    stats.record_layout(started.elapsed(), ctx.laid_out_nodes() - laid_out_before);
+    warmup_counters.record_text_measurements(ctx);
+    trace!("layout took {:?}", started.elapsed());
+    // Subtrees that didn't change are shared with the previous snapshots.
+    let previous = snapshot.primary.read().unwrap().clone();
+    let measurer = Arc::clone(&ctx.text_measurer);
+    let snap = rebuild_render_tree(
+        &ctx.document.root_node(),
+        previous.as_ref().map(|previous| &previous.tree),
+        measurer.as_ref(),
+    );
+    let previous_windows = snapshot.windows.read().unwrap().clone();
+    let windows = ctx
+        .document
+        .window_roots()
+        .iter()
+        .filter_map(|root| {
+            let previous = previous_windows.get(root);
+            let tree = rebuild_render_tree(
+                &ctx.document.get_node(*root)?,
+                previous.map(|previous| &previous.tree),
+                measurer.as_ref(),
+            );
+            let tree = IndexedTree::after(previous, tree, ctx.tooltip_style(*root));
+            Some((*root, Arc::new(tree)))
+        })
+        .collect();
+    let tooltip_style = ctx.tooltip_style(ctx.document.root_id());
+    let primary = IndexedTree::after(previous.as_ref(), Arc::clone(&snap), tooltip_style);
+    *snapshot.primary.write().unwrap() = Some(Arc::new(primary));
+    *snapshot.windows.write().unwrap() = windows;
+    message_sender.send(WindowMessage::Redraw);
+    trace!("layout and snapshots took {:?}", started.elapsed());
+    snap
+}
+
+#[cfg(test)]
+mod commands_tests;
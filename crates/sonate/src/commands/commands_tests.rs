@@ -0,0 +1,110 @@
+use super::{coalesce, Command};
+use crate::windowing::WindowMessage;
+use crate::{Engine, EngineError, EngineOptions, Id};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+
+fn set(id: u64, key: &str, value: &str) -> Command {
+    Command::SetAttribute(Id::from_u64(id), key.to_owned(), value.to_owned())
+}
+
+/// The commands as their `Debug` says, with the values of attributes.
+fn describe(commands: &[Command]) -> Vec<String> {
+    commands
+        .iter()
+        .map(|cmd| match cmd {
+            Command::SetAttribute(_, _, value) => format!("{cmd:?} = {value}"),
+            cmd => format!("{cmd:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn coalescing_keeps_the_last_of_consecutive_updates_and_layouts() {
+    let batch = vec![
+        set(1, "a", "1"),
+        set(1, "a", "2"),
+        set(1, "b", "3"),
+        Command::Layout,
+        set(1, "b", "4"),
+        set(2, "b", "5"),
+        set(1, "b", "6"),
+        Command::CreateNode(Id::from_u64(3), None, None),
+        Command::Layout,
+        set(1, "b", "7"),
+    ];
+    assert_eq!(
+        describe(&coalesce(batch)),
+        [
+            "SetAttribute(1, \"a\") = 2",
+            // The layout between them was dropped for the last one.
+            "SetAttribute(1, \"b\") = 4",
+            "SetAttribute(2, \"b\") = 5",
+            "SetAttribute(1, \"b\") = 6",
+            "CreateNode(3)",
+            "Layout",
+            "SetAttribute(1, \"b\") = 7",
+        ]
+    );
+}
+
+#[test]
+fn redundant_updates_are_applied_in_few_layout_passes() {
+    let engine = Engine::with_options(EngineOptions {
+        command_capacity: 64,
+        ..Default::default()
+    });
+    let layouts = Arc::new(AtomicUsize::new(0));
+    let counted = Arc::clone(&layouts);
+    engine.message_sender.set_receiver(move |message| {
+        if let WindowMessage::Redraw = message {
+            counted.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+    let root = engine.root_id();
+    engine.set_attribute(root, "style".to_owned(), "display: flex;".to_owned());
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, item);
+
+    for i in 0..100_000 {
+        let style = format!("width: {}px; height: 10px;", i % 200);
+        engine.set_attribute(item, "style".to_owned(), style);
+    }
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    assert_eq!(snapshot.find(item).unwrap().bounds.width, 199.0);
+    let layouts = layouts.load(Ordering::SeqCst);
+    assert!(layouts < 100, "{layouts} layout passes");
+}
+
+#[test]
+fn nonblocking_updates_fail_while_the_queue_is_full() {
+    let engine = Engine::with_options(EngineOptions {
+        command_capacity: 1,
+        idle_warmup_delay: None,
+        ..Default::default()
+    });
+    // Holds the data thread in its next layout until released.
+    let (release, released) = channel::<()>();
+    let released = Mutex::new(released);
+    let (entered, layout_entered) = channel();
+    engine.message_sender.set_receiver(move |message| {
+        if let WindowMessage::Redraw = message {
+            let _ = entered.send(());
+            let _ = released.lock().unwrap().recv();
+        }
+    });
+    engine.sender.send(Command::Layout).unwrap();
+    layout_entered.recv().unwrap();
+
+    let item = Id::from_u64(1);
+    let update = |value: &str| {
+        engine.try_set_attribute_nonblocking(item, "class".to_owned(), value.to_owned())
+    };
+    assert!(update("a").is_ok());
+    assert!(matches!(update("b"), Err(EngineError::Busy)));
+
+    drop(release);
+    assert!(engine.children(engine.root_id()).is_empty());
+    assert!(update("c").is_ok());
+}
@@ -1,12 +1,13 @@
 use super::parser::StyleDeclarationParser;
 use crate::style::{BorderStyle, Directional, Length, Radius, Rgba, Style};
+use crate::CssErrorKind;
 use cssparser::{ParseError, Parser};
 
 impl StyleDeclarationParser {
     pub(crate) fn try_parse_line_width<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Option<Length>, ParseError<'i, ()>> {
+    ) -> Result<Option<Length>, ParseError<'i, CssErrorKind>> {
         // <line-width> = <length> | thin | medium | thick
         if let Ok(len) = input.try_parse(|i| self.parse_length_value(i)) {
             return Ok(Some(len));
@@ -34,7 +35,7 @@ impl StyleDeclarationParser {
     pub(crate) fn try_parse_line_style<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Option<BorderStyle>, ParseError<'i, ()>> {
+    ) -> Result<Option<BorderStyle>, ParseError<'i, CssErrorKind>> {
         // <line-style> = none | hidden | solid | dotted | dashed | double | groove | ridge | inset | outset
         if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
             return Ok(Some(BorderStyle::None));
@@ -101,7 +102,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         style: &mut Style,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         // border: <line-width> || <line-style> || <color>
         let mut saw_width = false;
         let mut saw_color = false;
@@ -141,32 +142,71 @@ impl StyleDeclarationParser {
         Ok(())
     }
 
+    pub(crate) fn parse_outline_shorthand<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+        style: &mut Style,
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
+        // outline: <line-width> || <line-style> || <color>
+        // An omitted width or style is reset to `medium` or `none`, but an omitted color keeps
+        // an earlier one: `None` is the text color only when nothing else set one.
+        let (mut width, mut color, mut line_style) = (None, None, None);
+        while !input.is_exhausted() {
+            if width.is_none() {
+                if let Some(value) = self.try_parse_line_width(input)? {
+                    width = Some(value);
+                    continue;
+                }
+            }
+            if color.is_none() {
+                if let Ok(value) = input.try_parse(|i| self.parse_color_value(i)) {
+                    color = Some(value);
+                    continue;
+                }
+            }
+            if line_style.is_none() {
+                if let Some(value) = self.try_parse_line_style(input)? {
+                    line_style = Some(value);
+                    continue;
+                }
+            }
+            return Err(input.new_error_for_next_token());
+        }
+        if width.is_none() && color.is_none() && line_style.is_none() {
+            return Err(input.new_error_for_next_token());
+        }
+        style.outline_width = Some(width.unwrap_or(Length::Px(3.0)));
+        style.outline_color = color;
+        style.outline_style = Some(line_style.unwrap_or(BorderStyle::None));
+        Ok(())
+    }
+
     fn parse_border_radius_value<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Radius, ParseError<'i, ()>> {
+    ) -> Result<Radius, ParseError<'i, CssErrorKind>> {
         let x = self.parse_length_value(input)?;
         let y = input
             .try_parse(|input| self.parse_length_value(input))
-            .unwrap_or(x);
+            .unwrap_or_else(|_| x.clone());
         Ok(Radius { x, y })
     }
 
     fn parse_border_radius_1_to_4<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<(Length, Length, Length, Length), ParseError<'i, ()>> {
+    ) -> Result<(Length, Length, Length, Length), ParseError<'i, CssErrorKind>> {
         // Matches CSS 1-4 expansion order: top-left, top-right, bottom-right, bottom-left.
         let first = self.parse_length_value(input)?;
         let second = input
             .try_parse(|input| self.parse_length_value(input))
-            .unwrap_or(first);
+            .unwrap_or_else(|_| first.clone());
         let third = input
             .try_parse(|input| self.parse_length_value(input))
-            .unwrap_or(first);
+            .unwrap_or_else(|_| first.clone());
         let fourth = input
             .try_parse(|input| self.parse_length_value(input))
-            .unwrap_or(second);
+            .unwrap_or_else(|_| second.clone());
 
         Ok((first, second, third, fourth))
     }
@@ -175,7 +215,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         style: &mut Style,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         // border-radius: <length-percentage>{1,4} [ / <length-percentage>{1,4} ]?
         // Values before '/' are the horizontal radii; values after '/' are the vertical radii.
         // If '/' is omitted, vertical radii equal horizontal radii.
@@ -185,7 +225,7 @@ impl StyleDeclarationParser {
         let (tl_y, tr_y, br_y, bl_y) = if input.try_parse(|i| i.expect_delim('/')).is_ok() {
             self.parse_border_radius_1_to_4(input)?
         } else {
-            (tl_x, tr_x, br_x, bl_x)
+            (tl_x.clone(), tr_x.clone(), br_x.clone(), bl_x.clone())
         };
 
         if !input.is_exhausted() {
@@ -204,7 +244,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         corner: &mut Option<Radius>,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         // border-*-radius: <length-percentage [0,∞]>{1,2}
         let radius = self.parse_border_radius_value(input)?;
         if !input.is_exhausted() {
@@ -218,7 +258,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         side: &mut Option<Rgba>,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         let color = self.parse_color_value(input)?;
         if !input.is_exhausted() {
             return Err(input.new_error_for_next_token());
@@ -231,7 +271,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         side: &mut Option<Length>,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         let width = self
             .try_parse_line_width(input)?
             .ok_or_else(|| input.new_error_for_next_token())?;
@@ -246,7 +286,7 @@ impl StyleDeclarationParser {
         &mut self,
         input: &mut Parser<'i, 't>,
         side: &mut Option<BorderStyle>,
-    ) -> Result<(), ParseError<'i, ()>> {
+    ) -> Result<(), ParseError<'i, CssErrorKind>> {
         let v = self
             .try_parse_line_style(input)?
             .ok_or_else(|| input.new_error_for_next_token())?;
@@ -0,0 +1,119 @@
+//! `calc()` expressions over numbers and lengths, where `*` and `/` bind tighter than `+`
+//! and `-`.
+
+use super::values::dimension_length;
+use crate::style::{Calc, Length};
+use crate::CssErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+type Result<'i, T> = std::result::Result<T, ParseError<'i, CssErrorKind>>;
+
+/// How many numbers, lengths and parenthesized expressions a `calc()` may have. Expressions
+/// are parsed and evaluated recursively, so a bound keeps long ones from overflowing the stack.
+const MAX_VALUES: usize = 64;
+
+/// The inside of `calc(…)`, which must be a length rather than a number.
+pub(super) fn parse_calc_length<'i>(input: &mut Parser<'i, '_>) -> Result<'i, Calc> {
+    let location = input.current_source_location();
+    let mut values_left = MAX_VALUES;
+    let calc = parse_sum(input, &mut values_left)?;
+    if calc.is_number() {
+        return Err(location.new_custom_error(CssErrorKind::InvalidValue));
+    }
+    Ok(calc)
+}
+
+/// `values_left` counts down the values `calc()` may still have.
+fn parse_sum<'i>(input: &mut Parser<'i, '_>, values_left: &mut usize) -> Result<'i, Calc> {
+    let mut sum = parse_product(input, values_left)?;
+    loop {
+        let start = input.state();
+        let Some(subtract) = sum_operator(input) else {
+            input.reset(&start);
+            return Ok(sum);
+        };
+        let location = input.current_source_location();
+        let term = parse_product(input, values_left)?;
+        // Numbers and lengths don't add up.
+        if term.is_number() != sum.is_number() {
+            return Err(location.new_custom_error(CssErrorKind::InvalidValue));
+        }
+        let (a, b) = (Box::new(sum), Box::new(term));
+        sum = if subtract {
+            Calc::Difference(a, b)
+        } else {
+            Calc::Sum(a, b)
+        };
+    }
+}
+
+/// A `+` or `-` with whitespace on both sides, which tells it from the sign of a number.
+/// `true` for `-`.
+fn sum_operator(input: &mut Parser) -> Option<bool> {
+    input.expect_whitespace().ok()?;
+    let subtract = match input.next_including_whitespace().ok()? {
+        Token::Delim('+') => false,
+        Token::Delim('-') => true,
+        _ => return None,
+    };
+    input.expect_whitespace().ok()?;
+    Some(subtract)
+}
+
+fn parse_product<'i>(input: &mut Parser<'i, '_>, values_left: &mut usize) -> Result<'i, Calc> {
+    let mut product = parse_value(input, values_left)?;
+    loop {
+        let start = input.state();
+        let divide = match input.next() {
+            Ok(Token::Delim('*')) => Some(false),
+            Ok(Token::Delim('/')) => Some(true),
+            _ => None,
+        };
+        let Some(divide) = divide else {
+            input.reset(&start);
+            return Ok(product);
+        };
+        let location = input.current_source_location();
+        let factor = parse_value(input, values_left)?;
+        // One side of a product is a number, and so is what a quotient is divided by.
+        let valid = if divide {
+            factor.is_number()
+        } else {
+            product.is_number() || factor.is_number()
+        };
+        if !valid {
+            return Err(location.new_custom_error(CssErrorKind::InvalidValue));
+        }
+        let (a, b) = (Box::new(product), Box::new(factor));
+        product = if divide {
+            Calc::Quotient(a, b)
+        } else {
+            Calc::Product(a, b)
+        };
+    }
+}
+
+/// A number, a length, or an expression in parentheses or a nested `calc()`.
+fn parse_value<'i>(input: &mut Parser<'i, '_>, values_left: &mut usize) -> Result<'i, Calc> {
+    let location = input.current_source_location();
+    if *values_left == 0 {
+        return Err(location.new_custom_error(CssErrorKind::InvalidValue));
+    }
+    *values_left -= 1;
+    let token = input.next()?.clone();
+    match &token {
+        Token::Number { value, .. } => Ok(Calc::Number(*value as f64)),
+        Token::Percentage { unit_value, .. } => {
+            Ok(Calc::Length(Length::Percent(*unit_value as f64 * 100.0)))
+        }
+        Token::Dimension { value, unit, .. } => match dimension_length(*value as f64, unit) {
+            Some(length) => Ok(Calc::Length(length)),
+            None => Err(location.new_unexpected_token_error(token)),
+        },
+        Token::ParenthesisBlock => input.parse_nested_block(|input| parse_sum(input, values_left)),
+        Token::Function(name) if name.eq_ignore_ascii_case("calc") => {
+            input.parse_nested_block(|input| parse_sum(input, values_left))
+        }
+        _ => Err(location.new_unexpected_token_error(token)),
+    }
+}
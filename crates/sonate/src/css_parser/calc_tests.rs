@@ -0,0 +1,83 @@
+use crate::css_parser::parse_declaration;
+use crate::style::{Calc, Length, LengthContext};
+
+fn width(value: &str) -> Option<Length> {
+    parse_declaration("width", value)?.width
+}
+
+fn calc(value: &str) -> Calc {
+    match width(value) {
+        Some(Length::Calc(calc)) => calc.as_ref().clone(),
+        other => panic!("{value} parsed as {other:?}"),
+    }
+}
+
+fn px(value: f64) -> Box<Calc> {
+    Box::new(Calc::Length(Length::Px(value)))
+}
+
+const CONTEXT: LengthContext = LengthContext {
+    font_size: 10.0,
+    root_font_size: 20.0,
+    viewport_width: 1000.0,
+    viewport_height: 500.0,
+};
+
+#[test]
+fn products_bind_tighter_than_sums() {
+    assert_eq!(
+        calc("calc(1px + 2 * 3px)"),
+        Calc::Sum(
+            px(1.0),
+            Box::new(Calc::Product(Box::new(Calc::Number(2.0)), px(3.0)))
+        )
+    );
+    assert_eq!(calc("calc(10px - 4px / 2 + 1px)").evaluate(0.0), 9.0);
+    assert_eq!(calc("calc(2 * (1px + 2px))").evaluate(0.0), 6.0);
+    assert_eq!(calc("calc(100% - calc(2 * 10px))").evaluate(300.0), 280.0);
+    assert_eq!(calc("calc( 1px*2 )").evaluate(0.0), 2.0);
+}
+
+#[test]
+fn sums_need_whitespace_around_the_operator() {
+    // Without it, the sign belongs to the number after.
+    assert_eq!(width("calc(10px+5px)"), None);
+    assert_eq!(width("calc(10px -5px)"), None);
+    assert_eq!(width("calc(10px- 5px)"), None);
+    assert_eq!(calc("calc(10px - -5px)").evaluate(0.0), 15.0);
+}
+
+#[test]
+fn numbers_and_lengths_only_combine_where_they_make_a_length() {
+    for invalid in [
+        "calc(1px * 2px)",
+        "calc(2 / 1px)",
+        "calc(1px + 2)",
+        "calc(2 * 3)",
+        "calc(1px 2px)",
+        "calc(1deg)",
+        "calc()",
+    ] {
+        assert_eq!(width(invalid), None, "{invalid}");
+    }
+    assert_eq!(calc("calc(2 * 3 * 1px)").evaluate(0.0), 6.0);
+    assert_eq!(calc("calc(1px / 0)").evaluate(0.0), 0.0);
+}
+
+#[test]
+fn viewport_and_font_relative_lengths_resolve_inside_calc() {
+    assert_eq!(width("50vw"), Some(Length::Vw(50.0)));
+    assert_eq!(width("10vh"), Some(Length::Vh(10.0)));
+    assert_eq!(Length::Vw(50.0).resolve(&CONTEXT), Length::Px(500.0));
+    assert_eq!(
+        width("calc(10vh - 2em + 1rem)").unwrap().resolve(&CONTEXT),
+        Length::Px(50.0)
+    );
+    // Percentages are left until the size they are of is known.
+    let resolved = width("calc(50% + 1em)").unwrap().resolve(&CONTEXT);
+    assert_eq!(
+        resolved,
+        Length::Calc(Calc::Sum(Box::new(Calc::Length(Length::Percent(50.0))), px(10.0)).into())
+    );
+    assert_eq!(resolved.resolve_percentage(200.0), Length::Px(110.0));
+}
@@ -1,6 +1,7 @@
 use super::named_colors;
 use super::parser::StyleDeclarationParser;
 use crate::style::Rgba;
+use crate::CssErrorKind;
 use cssparser::{ParseError, Parser, Token};
 
 impl StyleDeclarationParser {
@@ -11,7 +12,7 @@ impl StyleDeclarationParser {
     fn parse_rgb_channel<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<u8, ParseError<'i, ()>> {
+    ) -> Result<u8, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         match token {
             Token::Number { value, .. } => Ok(Self::clamp_u8(*value as f32)),
@@ -25,7 +26,7 @@ impl StyleDeclarationParser {
     fn parse_rgb_channel_or_none<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<u8, ParseError<'i, ()>> {
+    ) -> Result<u8, ParseError<'i, CssErrorKind>> {
         // CSS Color 4 modern rgb() syntax allows `none` components. We can't represent
         // missing channels here, so treat them as 0.
         if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
@@ -37,7 +38,7 @@ impl StyleDeclarationParser {
     fn parse_alpha_channel<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<u8, ParseError<'i, ()>> {
+    ) -> Result<u8, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         let alpha_0_1 = match token {
             Token::Number { value, .. } => *value as f32,
@@ -51,7 +52,7 @@ impl StyleDeclarationParser {
     fn parse_hue_value<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<f32, ParseError<'i, ()>> {
+    ) -> Result<f32, ParseError<'i, CssErrorKind>> {
         // Modern syntax allows 'none' (missing component). We can't represent
         // missing here, so treat it as 0deg.
         if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
@@ -64,7 +65,7 @@ impl StyleDeclarationParser {
     fn parse_hsl_percent_or_number<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<f32, ParseError<'i, ()>> {
+    ) -> Result<f32, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         match token {
             Token::Percentage { unit_value, .. } => Ok((*unit_value as f32) * 100.0),
@@ -77,7 +78,7 @@ impl StyleDeclarationParser {
     fn parse_hwb_percent_or_number<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<f32, ParseError<'i, ()>> {
+    ) -> Result<f32, ParseError<'i, CssErrorKind>> {
         // HWB W and B allow mixed <percentage>/<number> in modern syntax, and also allow 'none'.
         self.parse_hsl_percent_or_number(input)
     }
@@ -85,7 +86,7 @@ impl StyleDeclarationParser {
     fn parse_alpha_value_u8<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<u8, ParseError<'i, ()>> {
+    ) -> Result<u8, ParseError<'i, CssErrorKind>> {
         // <alpha-value> = <number> | <percentage> in Level 4.
         // Modern syntax also allows 'none'.
         let token = input.next()?;
@@ -124,7 +125,7 @@ impl StyleDeclarationParser {
     fn parse_hsl_color<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Rgba, ParseError<'i, ()>> {
+    ) -> Result<Rgba, ParseError<'i, CssErrorKind>> {
         // Supports both legacy comma-separated and modern space-separated syntax.
         // See CSS Color 4 §7.
         let hue = self.parse_hue_value(input)?;
@@ -196,7 +197,7 @@ impl StyleDeclarationParser {
     fn parse_hwb_color<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Rgba, ParseError<'i, ()>> {
+    ) -> Result<Rgba, ParseError<'i, CssErrorKind>> {
         // CSS Color 4 §8: hwb() does NOT support legacy comma-separated syntax.
         // Using commas inside hwb() is an error.
         let hue = self.parse_hue_value(input)?;
@@ -221,7 +222,7 @@ impl StyleDeclarationParser {
     pub(crate) fn parse_color_value<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Rgba, ParseError<'i, ()>> {
+    ) -> Result<Rgba, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         match token {
             Token::Ident(name) => named_colors::named_color(name.as_ref())
@@ -297,6 +298,10 @@ impl StyleDeclarationParser {
 /// Parse a hex color string into Rgba
 fn parse_hex_color(hex: &str) -> Result<Rgba, &'static str> {
     let hex = hex.trim_start_matches('#');
+    // Checked first, as the digits are sliced by byte.
+    if !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("Invalid hex digit");
+    }
 
     match hex.len() {
         3 => {
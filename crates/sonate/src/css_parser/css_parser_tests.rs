@@ -1,5 +1,11 @@
-use crate::css_parser::parse_css;
-use crate::style::{Display, Selector};
+use crate::css_parser::{parse_css, parse_css_with_warnings, parse_inline_style};
+use crate::diagnostics::DiagnosticCode;
+use crate::media::{MediaCondition, MediaEnvironment, MediaQuery};
+use crate::style::{
+    AttributeOperator, AttributeSelector, Display, Length, Selector, Specificity, TimingFunction,
+    Transition, TransitionProperty,
+};
+use std::time::Duration;
 
 #[test]
 fn test_parse_simple_css_document() {
@@ -53,8 +59,8 @@ fn test_parse_simple_css_document() {
     let mut found_width = false;
 
     for declaration in &container_rule.declarations {
-        match declaration.display {
-            Display::Flex => found_display = true,
+        if declaration.display == Some(Display::Flex) {
+            found_display = true;
         }
         if declaration.background_color.is_some() {
             found_background = true;
@@ -189,3 +195,349 @@ fn test_single_rule_css() {
         Selector::Class("single".to_string())
     );
 }
+
+#[test]
+fn test_parse_media_rule_attaches_query_to_nested_rules() {
+    let css = r#"
+        .a { width: 10px; }
+        @media (prefers-reduced-motion: reduce) {
+            .b { width: 20px; }
+            .c { width: 30px; }
+        }
+        .d { width: 40px; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let selectors: Vec<_> = stylesheet.rules.iter().map(|r| &r.selector).collect();
+    assert_eq!(
+        selectors,
+        vec![
+            &Selector::Class("a".to_string()),
+            &Selector::Class("b".to_string()),
+            &Selector::Class("c".to_string()),
+            &Selector::Class("d".to_string()),
+        ]
+    );
+
+    let reduce = MediaQuery {
+        queries: vec![vec![MediaCondition::PrefersReducedMotion(true)]],
+    };
+    assert!(stylesheet.rules[0].media.is_empty());
+    assert_eq!(stylesheet.rules[1].media, vec![reduce.clone()]);
+    assert_eq!(stylesheet.rules[2].media, vec![reduce]);
+    assert!(stylesheet.rules[3].media.is_empty());
+}
+
+#[test]
+fn test_parse_attribute_and_compound_selectors() {
+    let css = r#"
+        [data-state="open"] { width: 1px; }
+        [hidden] { width: 2px; }
+        [lang^=en] { width: 3px; }
+        [src$=".png"] { width: 4px; }
+        [title*='note'] { width: 5px; }
+        .panel[data-state="open"] { width: 6px; }
+        INPUT.field.wide { width: 7px; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let attribute = |name: &str, value: Option<(AttributeOperator, &str)>| {
+        Selector::Attribute(AttributeSelector {
+            name: name.to_owned(),
+            value: value.map(|(operator, value)| (operator, value.to_owned())),
+        })
+    };
+    let selectors: Vec<_> = stylesheet.rules.iter().map(|r| &r.selector).collect();
+    assert_eq!(
+        selectors,
+        vec![
+            &attribute("data-state", Some((AttributeOperator::Equals, "open"))),
+            &attribute("hidden", None),
+            &attribute("lang", Some((AttributeOperator::Prefix, "en"))),
+            &attribute("src", Some((AttributeOperator::Suffix, ".png"))),
+            &attribute("title", Some((AttributeOperator::Substring, "note"))),
+            &Selector::Compound(vec![
+                Selector::Class("panel".to_owned()),
+                attribute("data-state", Some((AttributeOperator::Equals, "open"))),
+            ]),
+            &Selector::Compound(vec![
+                Selector::Tag("input".to_owned()),
+                Selector::Class("field".to_owned()),
+                Selector::Class("wide".to_owned()),
+            ]),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_id_selectors_and_important_declarations() {
+    let stylesheet = parse_css(
+        "#main { width: 1px !important; height: 2px; }
+         div#main.x { color: red; }
+         .bad { width: 3px !importantx; height: 4px; }",
+    )
+    .expect("Failed to parse CSS");
+    let rules = &stylesheet.rules;
+    assert_eq!(rules[0].selector, Selector::Id("main".to_owned()));
+    assert_eq!(rules[0].important.len(), 1);
+    assert_eq!(rules[0].important[0].width, Some(Length::Px(1.0)));
+    assert_eq!(rules[0].declarations.len(), 1);
+    assert_eq!(rules[0].declarations[0].height, Some(Length::Px(2.0)));
+    assert_eq!(
+        rules[1].selector,
+        Selector::Compound(vec![
+            Selector::Tag("div".to_owned()),
+            Selector::Id("main".to_owned()),
+            Selector::Class("x".to_owned()),
+        ])
+    );
+    assert_eq!(
+        rules[1].selector.specificity(),
+        Specificity {
+            ids: 1,
+            classes: 1,
+            tags: 1
+        }
+    );
+    // A malformed `!important` drops the declaration.
+    assert!(rules[2].important.is_empty());
+    assert_eq!(rules[2].declarations.len(), 1);
+    assert_eq!(rules[2].declarations[0].height, Some(Length::Px(4.0)));
+}
+
+#[test]
+fn test_parse_universal_selector() {
+    let stylesheet = parse_css("* { width: 1px; } *.a { width: 2px; } * .b { width: 3px; }")
+        .expect("Failed to parse CSS");
+    let selectors: Vec<_> = stylesheet.rules.iter().map(|r| &r.selector).collect();
+    // `* .b` is a descendant combinator.
+    assert_eq!(
+        selectors,
+        vec![&Selector::Universal, &Selector::Class("a".to_owned())]
+    );
+}
+
+#[test]
+fn test_descendant_and_malformed_attribute_selectors_are_skipped() {
+    let css = r#"
+        .a .b { width: 1px; }
+        .a [x] { width: 2px; }
+        [x~=y] { width: 3px; }
+        [=y] { width: 4px; }
+        .c { width: 5px; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let selectors: Vec<_> = stylesheet.rules.iter().map(|r| &r.selector).collect();
+    assert_eq!(selectors, vec![&Selector::Class("c".to_owned())]);
+}
+
+#[test]
+fn test_media_query_matching() {
+    let reduced = MediaEnvironment {
+        prefers_reduced_motion: true,
+        ..MediaEnvironment::default()
+    };
+    let default = MediaEnvironment::default();
+
+    let query_for = |prelude: &str| {
+        let css = format!("@media {} {{ .x {{ width: 1px; }} }}", prelude);
+        let stylesheet = parse_css(&css).expect("Failed to parse CSS");
+        assert_eq!(stylesheet.rules.len(), 1, "rule dropped for {}", prelude);
+        stylesheet.rules[0].media[0].clone()
+    };
+
+    let query = query_for("(prefers-reduced-motion: reduce)");
+    assert!(query.matches(&reduced));
+    assert!(!query.matches(&default));
+
+    let query = query_for("(prefers-reduced-motion: no-preference)");
+    assert!(!query.matches(&reduced));
+    assert!(query.matches(&default));
+
+    let query = query_for("(prefers-reduced-motion)");
+    assert!(query.matches(&reduced));
+
+    let query = query_for("only screen and (prefers-reduced-motion: reduce)");
+    assert!(query.matches(&reduced));
+
+    let query = query_for("print");
+    assert!(!query.matches(&default));
+
+    let query = query_for("print, (prefers-reduced-motion: no-preference)");
+    assert!(query.matches(&default));
+
+    let query = query_for("(unknown-feature: 1)");
+    assert!(!query.matches(&default));
+}
+
+#[test]
+fn test_width_media_queries() {
+    let (stylesheet, warnings) = parse_css_with_warnings(
+        "@media (min-width: 20em) and (max-width: 600px) { .x { width: 1px; } }
+         @media (max-width: 0) { .y { width: 1px; } }
+         @media (orientation: portrait), (min-width: 10vw) { .z { width: 1px; } }",
+    );
+    assert!(stylesheet.depends_on_viewport_width());
+    let at_width = |viewport_width| MediaEnvironment {
+        viewport_width,
+        ..MediaEnvironment::default()
+    };
+
+    let query = &stylesheet.rules[0].media[0];
+    assert_eq!(
+        query.queries,
+        vec![vec![
+            MediaCondition::MinWidth(240.0),
+            MediaCondition::MaxWidth(600.0)
+        ]]
+    );
+    assert!(!query.matches(&at_width(239.0)));
+    assert!(query.matches(&at_width(240.0)));
+    assert!(query.matches(&at_width(600.0)));
+    assert!(!query.matches(&at_width(600.5)));
+    assert!(stylesheet.rules[1].media[0].matches(&at_width(0.0)));
+
+    // Unsupported features and units never match, with a warning each.
+    assert!(!stylesheet.rules[2].media[0].matches(&at_width(800.0)));
+    let messages: Vec<_> = warnings
+        .iter()
+        .filter(|warning| warning.code == DiagnosticCode::CssUnsupportedMediaFeature)
+        .map(|warning| warning.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        vec![
+            "Unsupported media feature `(orientation: portrait)` never matches",
+            "Unsupported media feature `(min-width: 10vw)` never matches",
+        ]
+    );
+
+    assert!(
+        !parse_css("@media (prefers-reduced-motion) { .x { width: 1px; } }")
+            .unwrap()
+            .depends_on_viewport_width()
+    );
+}
+
+#[test]
+fn test_nested_media_rules_require_all_queries() {
+    let css = r#"
+        @media screen {
+            @media (prefers-reduced-motion: reduce) {
+                .x { width: 1px; }
+            }
+        }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    assert_eq!(stylesheet.rules.len(), 1);
+    assert_eq!(stylesheet.rules[0].media.len(), 2);
+}
+
+#[test]
+fn test_transition_shorthand() {
+    let css = r#"
+        .a { transition: background-color 200ms ease, width 0.3s linear 50ms; }
+        .b { transition: 1s cubic-bezier(0.25, 0.5, 1.0, 0.75) opacity; }
+        .c { transition: none; }
+        .d { transition: 150ms; }
+    "#;
+    let stylesheet = parse_css(css).unwrap();
+    let transitions: Vec<_> = stylesheet
+        .rules
+        .iter()
+        .map(|rule| rule.declarations[0].transition.clone().unwrap())
+        .collect();
+
+    assert_eq!(
+        transitions[0],
+        vec![
+            Transition {
+                property: TransitionProperty::BackgroundColor,
+                duration: Duration::from_millis(200),
+                timing_function: TimingFunction::Ease,
+                delay: Duration::ZERO,
+            },
+            Transition {
+                property: TransitionProperty::Width,
+                duration: Duration::from_millis(300),
+                timing_function: TimingFunction::Linear,
+                delay: Duration::from_millis(50),
+            },
+        ]
+    );
+    assert_eq!(
+        transitions[1],
+        vec![Transition {
+            property: TransitionProperty::Opacity,
+            duration: Duration::from_secs(1),
+            timing_function: TimingFunction::CubicBezier(0.25, 0.5, 1.0, 0.75),
+            delay: Duration::ZERO,
+        }]
+    );
+    assert_eq!(transitions[2], vec![]);
+    // Everything transitions with the default timing function unless named otherwise.
+    assert_eq!(transitions[3][0].property, TransitionProperty::All);
+    assert_eq!(transitions[3][0].timing_function, TimingFunction::Ease);
+}
+
+#[test]
+fn test_invalid_transitions_are_ignored() {
+    for value in [
+        "width -1s",
+        "width 1s 2s 3s",
+        "width height 1s",
+        "margin 1s",
+        "width 1s cubic-bezier(2, 0, 0, 1)",
+        "width 1s linear ease",
+        "width 100px",
+    ] {
+        let stylesheet = parse_css(&format!(".a {{ transition: {value}; }}")).unwrap();
+        assert!(
+            stylesheet.rules[0].declarations.is_empty(),
+            "`{value}` should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_parse_focus_selectors() {
+    let stylesheet = parse_css(
+        ":focus { width: 1px; }
+         .field:FOCUS { width: 2px; }
+         .field:hover { width: 3px; }
+         .field :focus { width: 4px; }",
+    )
+    .expect("Failed to parse CSS");
+    let selectors: Vec<_> = stylesheet.rules.iter().map(|r| &r.selector).collect();
+    // Other pseudo-classes and combinators drop their rule.
+    assert_eq!(
+        selectors,
+        vec![
+            &Selector::Focus,
+            &Selector::Compound(vec![Selector::Class("field".to_owned()), Selector::Focus,]),
+        ]
+    );
+    assert_eq!(
+        selectors[1].specificity(),
+        Specificity {
+            ids: 0,
+            classes: 2,
+            tags: 0
+        }
+    );
+}
+
+#[test]
+fn inline_styles_keep_their_valid_declarations() {
+    let style = parse_inline_style(
+        "width: 10px !important; width: 20px; height: bogus; --x: 1px; color: var(--x)",
+    );
+    assert_eq!(style.important.width, Some(Length::Px(10.0)));
+    assert_eq!(style.declarations.width, Some(Length::Px(20.0)));
+    assert_eq!(style.declarations.height, None);
+    assert_eq!(style.declarations.custom_properties.get("--x"), Some("1px"));
+    assert_eq!(style.declarations.color, None);
+    assert_eq!(parse_inline_style("").declarations.width, None);
+}
@@ -0,0 +1,361 @@
+//! Stylesheets come from files, so the parser has to take anything without panicking. These
+//! tests feed it random text built from CSS syntax, stray characters and multi-byte UTF-8,
+//! and mutations of a valid stylesheet, from seeds so failures can be reproduced.
+
+use crate::css_parser::{
+    parse_css_with_warnings, parse_declaration, parse_inline_style, parse_selector_chain,
+    resolve_custom_properties, substitute,
+};
+use crate::style::CustomProperties;
+
+/// Deterministic pseudo-random numbers, so failures can be reproduced.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+const PROPERTIES: &[&str] = &[
+    "display",
+    "width",
+    "height",
+    "min-width",
+    "max-height",
+    "margin",
+    "padding-left",
+    "border",
+    "border-radius",
+    "border-top-color",
+    "outline",
+    "color",
+    "background",
+    "background-color",
+    "opacity",
+    "font-family",
+    "font-size",
+    "font-weight",
+    "line-height",
+    "letter-spacing",
+    "flex",
+    "flex-basis",
+    "flex-grow",
+    "flex-direction",
+    "gap",
+    "column-gap",
+    "grid-template-columns",
+    "grid-column",
+    "aspect-ratio",
+    "transform",
+    "transition",
+    "z-index",
+    "order",
+    "cursor",
+    "line-clamp",
+    "--accent",
+    "--gap",
+];
+
+const VALUES: &[&str] = &[
+    "0",
+    "-1",
+    "12px",
+    "50%",
+    "1.5em",
+    "2rem",
+    "1e308px",
+    "-1e308",
+    "1fr",
+    "auto",
+    "none",
+    "flex",
+    "grid",
+    "#abc",
+    "#aabbcc",
+    "#aabbccdd",
+    "#αα",
+    "#ααα",
+    "#αa",
+    "#ab€",
+    "#",
+    "red",
+    "rgb(1, 2, 3)",
+    "rgba(1 2 3 / 50%)",
+    "hsl(120deg 50% 50%)",
+    "calc(100% - 10px)",
+    "calc(1px / 0)",
+    "calc(",
+    "var(--accent)",
+    "var(--gap, 4px)",
+    "var(",
+    "var(--)",
+    "repeat(3, 1fr)",
+    "span 2",
+    "16 / 9",
+    "/",
+    "rotate(45deg)",
+    "translate(10px, -5%)",
+    "scale(",
+    "opacity 0.3s ease-in",
+    "\"Helvetica Neue\", sans-serif",
+    "!important",
+    "inherit",
+    "url(",
+];
+
+const SYNTAX: &[&str] = &[
+    "{",
+    "}",
+    ":",
+    ";",
+    ",",
+    "(",
+    ")",
+    "[",
+    "]",
+    " ",
+    "\n",
+    "/*",
+    "*/",
+    "\"",
+    "'",
+    "\\",
+    "@",
+    "@media",
+    "(max-width: 600px)",
+    "and",
+    "!",
+    ".",
+    "#",
+    ">",
+    "+",
+    "~",
+    "*",
+    "::",
+    "-",
+    "--",
+    "\u{0}",
+    "\u{feff}",
+    "\u{fffd}",
+    "é",
+    "α",
+    "€",
+    "😀",
+    "\u{301}",
+];
+
+const SELECTORS: &[&str] = &[
+    "div",
+    ".card",
+    "#main",
+    "*",
+    ".a.b",
+    "div > .item",
+    ".row .cell",
+    ":hover",
+    ".button:active",
+    "[data-x]",
+    ".α",
+    "#ααα",
+];
+
+const STYLESHEET: &str = r#"
+.card { display: flex; width: calc(100% - 2 * 8px); border: 1px solid #ccc; }
+.card > .title { color: var(--accent, #336699); font: 600 14px "Inter", sans-serif; }
+#main .row:hover { background: rgba(0, 0, 0, 0.5); transition: opacity 0.2s ease-out; }
+@media (max-width: 600px) { .card { flex-direction: column; gap: 4px 8px; } }
+.grid { display: grid; grid-template-columns: 100px 1fr 2fr; aspect-ratio: 16 / 9; }
+"#;
+
+/// A value made of known values and stray syntax.
+fn random_value(rng: &mut Lcg) -> String {
+    let mut value = String::new();
+    for _ in 0..=rng.below(4) {
+        if rng.below(4) == 0 {
+            value.push_str(rng.pick(SYNTAX));
+        } else {
+            value.push_str(rng.pick(VALUES));
+        }
+        value.push(' ');
+    }
+    value
+}
+
+/// Rules, declarations, media queries and stray syntax, in about the order CSS has them,
+/// and sometimes not.
+fn random_stylesheet(rng: &mut Lcg) -> String {
+    let mut css = String::new();
+    for _ in 0..rng.below(12) {
+        match rng.below(8) {
+            0 => css.push_str(rng.pick(SYNTAX)),
+            1 => css.push_str("@media (max-width: 600px) { "),
+            2 => css.push_str(&random_value(rng)),
+            _ => {
+                css.push_str(rng.pick(SELECTORS));
+                css.push_str(" { ");
+                for _ in 0..rng.below(5) {
+                    css.push_str(rng.pick(PROPERTIES));
+                    css.push_str(": ");
+                    css.push_str(&random_value(rng));
+                    css.push_str("; ");
+                }
+                if rng.below(6) != 0 {
+                    css.push('}');
+                }
+            }
+        }
+        css.push('\n');
+    }
+    css
+}
+
+/// `STYLESHEET` with characters taken out, repeated and put in at random.
+fn mutated_stylesheet(rng: &mut Lcg) -> String {
+    let mut chars: Vec<char> = STYLESHEET.chars().collect();
+    for _ in 0..=rng.below(8) {
+        let at = rng.below(chars.len() as u64 + 1) as usize;
+        match rng.below(3) {
+            0 if at < chars.len() => {
+                chars.remove(at);
+            }
+            1 if at < chars.len() => chars.insert(at, chars[at]),
+            _ => chars
+                .splice(at..at, rng.pick(SYNTAX).chars())
+                .for_each(drop),
+        }
+    }
+    chars.into_iter().collect()
+}
+
+#[test]
+fn hex_colors_with_multibyte_characters_are_invalid() {
+    for hex in [
+        "#ααα",
+        "#αa",
+        "#aαa",
+        "#ab€",
+        "#αααααα",
+        "#aabbcc€",
+        "#😀😀",
+    ] {
+        assert!(parse_declaration("color", hex).is_none(), "{hex}");
+    }
+    assert!(parse_declaration("color", "#abc").is_some());
+}
+
+#[test]
+fn random_stylesheets_parse_without_panicking() {
+    for seed in 0..2000 {
+        let mut rng = Lcg(seed);
+        let css = random_stylesheet(&mut rng);
+        let result = std::panic::catch_unwind(|| {
+            parse_css_with_warnings(&css);
+            parse_inline_style(&css);
+        });
+        assert!(result.is_ok(), "seed {seed}: {css:?}");
+    }
+}
+
+#[test]
+fn mutated_stylesheets_parse_without_panicking() {
+    for seed in 0..2000 {
+        let mut rng = Lcg(seed);
+        let css = mutated_stylesheet(&mut rng);
+        let result = std::panic::catch_unwind(|| parse_css_with_warnings(&css));
+        assert!(result.is_ok(), "seed {seed}: {css:?}");
+    }
+}
+
+#[test]
+fn random_declarations_and_selectors_parse_without_panicking() {
+    for seed in 0..2000 {
+        let mut rng = Lcg(seed);
+        let property = rng.pick(PROPERTIES);
+        let value = random_value(&mut rng);
+        let selector = format!("{} {}", rng.pick(SELECTORS), rng.pick(SYNTAX));
+        let result = std::panic::catch_unwind(|| {
+            parse_declaration(property, &value);
+            parse_selector_chain(&selector);
+        });
+        assert!(
+            result.is_ok(),
+            "seed {seed}: {property}: {value:?}, selector {selector:?}"
+        );
+    }
+}
+
+#[test]
+fn random_custom_properties_resolve_without_panicking() {
+    let names = ["--a", "--b", "--c"];
+    for seed in 0..1000 {
+        let mut rng = Lcg(seed);
+        let mut properties = CustomProperties::default();
+        for name in names {
+            let mut value = random_value(&mut rng);
+            if rng.below(2) == 0 {
+                value.push_str(&format!(
+                    "var({}, {})",
+                    rng.pick(&names),
+                    random_value(&mut rng)
+                ));
+            }
+            properties.set(name.to_owned(), value);
+        }
+        let value = format!("var({}) {}", rng.pick(&names), random_value(&mut rng));
+        let result = std::panic::catch_unwind(|| {
+            let resolved = resolve_custom_properties(&properties);
+            substitute(&value, &mut |name| resolved.get(name).map(str::to_owned));
+        });
+        assert!(result.is_ok(), "seed {seed}: {properties:?}, {value:?}");
+    }
+}
+
+#[test]
+fn deeply_nested_values_parse_without_overflowing_the_stack() {
+    for nesting in ["(", "[", "{", "calc(", "var(--a, ", "rgb("] {
+        let value = nesting.repeat(10_000);
+        let css = format!(".a {{ width: {value}; }} .b {{ color: red; }}");
+        let (sheet, _) = parse_css_with_warnings(&css);
+        assert_eq!(sheet.rules.len(), 1, "{nesting}");
+        assert!(parse_declaration("width", &value).is_none(), "{nesting}");
+        assert_eq!(substitute(&value, &mut |_| None), None, "{nesting}");
+    }
+
+    let sum = format!("calc({}1px)", "1px + ".repeat(10_000));
+    assert!(parse_declaration("width", &sum).is_none());
+}
+
+#[test]
+fn deeply_nested_media_rules_parse_without_overflowing_the_stack() {
+    let css = format!(
+        "{} .a {{ color: red; }}",
+        "@media (min-width: 1px) { ".repeat(10_000)
+    );
+    parse_css_with_warnings(&css);
+}
+
+#[test]
+fn long_chains_of_references_resolve_without_overflowing_the_stack() {
+    let names: Vec<String> = (0..10_000).map(|i| format!("--p{i:05}")).collect();
+    let values: Vec<String> = (1..=10_000)
+        .map(|i| format!("var(--p{i:05}, 1px)"))
+        .collect();
+    let properties: CustomProperties = names
+        .iter()
+        .zip(&values)
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    let resolved = resolve_custom_properties(&properties);
+    // Each property falls back where the chain ends or goes too deep.
+    assert_eq!(resolved.get("--p00000"), Some("1px"));
+    assert_eq!(resolved.get("--p09999"), Some("1px"));
+}
@@ -0,0 +1,114 @@
+use crate::media::{MediaCondition, MediaQuery};
+use crate::text::DEFAULT_FONT_SIZE_PX;
+use crate::CssErrorKind;
+use cssparser::{ParseError, Parser, Token};
+
+/// Parse the prelude of an `@media` rule.
+///
+/// Supported subset of Media Queries Level 4:
+/// - media types `all` and `screen` (other types never match), optionally prefixed by `only`
+/// - `(prefers-reduced-motion: reduce | no-preference)` and its boolean form
+/// - `(min-width: …)` and `(max-width: …)`, in `px`, or `em` and `rem` of the default font size
+/// - `and` between conditions, `,` between queries
+///
+/// Other features never match; their conditions, as written, are added to `unsupported`.
+pub(crate) fn parse_media_query_list<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    unsupported: &mut Vec<String>,
+) -> Result<MediaQuery, ParseError<'i, CssErrorKind>> {
+    let queries = input.parse_comma_separated(|input| parse_media_query(input, unsupported))?;
+    Ok(MediaQuery { queries })
+}
+
+fn parse_media_query<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    unsupported: &mut Vec<String>,
+) -> Result<Vec<MediaCondition>, ParseError<'i, CssErrorKind>> {
+    let mut conditions = Vec::new();
+
+    // Optional leading media type.
+    let media_type = input.try_parse(|i| i.expect_ident_cloned());
+    let mut expect_condition = match media_type {
+        Ok(name) => {
+            let name = if name.eq_ignore_ascii_case("only") {
+                input.expect_ident_cloned()?
+            } else {
+                name
+            };
+            if !name.eq_ignore_ascii_case("all") && !name.eq_ignore_ascii_case("screen") {
+                conditions.push(MediaCondition::Never);
+            }
+            false
+        }
+        Err(_) => true,
+    };
+
+    loop {
+        if expect_condition {
+            input.expect_parenthesis_block()?;
+            let start = input.position();
+            let condition = input.parse_nested_block(parse_media_feature)?;
+            if condition == MediaCondition::Never {
+                let written = input.slice_from(start);
+                unsupported.push(format!("({written}"));
+            }
+            conditions.push(condition);
+        }
+
+        if input.is_exhausted() {
+            break;
+        }
+        input.expect_ident_matching("and")?;
+        expect_condition = true;
+    }
+
+    Ok(conditions)
+}
+
+fn parse_media_feature<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<MediaCondition, ParseError<'i, CssErrorKind>> {
+    let name = input.expect_ident_cloned()?;
+
+    let value = if input.try_parse(|i| i.expect_colon()).is_ok() {
+        Some(input.next()?.clone())
+    } else {
+        None
+    };
+
+    let condition = match (name.to_ascii_lowercase().as_str(), value) {
+        ("prefers-reduced-motion", None) => MediaCondition::PrefersReducedMotion(true),
+        ("prefers-reduced-motion", Some(Token::Ident(v))) => match v.as_ref() {
+            "reduce" => MediaCondition::PrefersReducedMotion(true),
+            "no-preference" => MediaCondition::PrefersReducedMotion(false),
+            _ => MediaCondition::Never,
+        },
+        ("min-width", Some(value)) => {
+            width_px(&value).map_or(MediaCondition::Never, MediaCondition::MinWidth)
+        }
+        ("max-width", Some(value)) => {
+            width_px(&value).map_or(MediaCondition::Never, MediaCondition::MaxWidth)
+        }
+        _ => MediaCondition::Never,
+    };
+
+    Ok(condition)
+}
+
+/// A width in a media feature. `em` and `rem` are of the default font size, as media queries
+/// don't depend on styles.
+fn width_px(value: &Token) -> Option<f64> {
+    let px = match value {
+        Token::Dimension { value, unit, .. } => {
+            let value = f64::from(*value);
+            match unit.to_ascii_lowercase().as_str() {
+                "px" => value,
+                "em" | "rem" => value * DEFAULT_FONT_SIZE_PX,
+                _ => return None,
+            }
+        }
+        Token::Number { value, .. } if *value == 0.0 => 0.0,
+        _ => return None,
+    };
+    px.is_finite().then_some(px)
+}
@@ -1,10 +1,19 @@
 mod borders;
+mod calc;
 mod colors;
+mod media_queries;
 mod named_colors;
 mod parser;
+mod transitions;
 mod values;
+mod variables;
 
+#[cfg(test)]
 pub use parser::parse_css;
+pub(crate) use parser::{
+    parse_css_with_warnings, parse_declaration, parse_inline_style, parse_selector_chain,
+};
+pub(crate) use variables::{resolve_custom_properties, substitute};
 
 #[cfg(test)]
 mod color_tests;
@@ -17,3 +26,12 @@ mod css_parser_tests;
 
 #[cfg(test)]
 mod border_tests;
+
+#[cfg(test)]
+mod variable_tests;
+
+#[cfg(test)]
+mod calc_tests;
+
+#[cfg(test)]
+mod fuzz_tests;
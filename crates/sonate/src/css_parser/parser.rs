@@ -1,64 +1,147 @@
+use super::variables;
+use crate::css_parser::media_queries::parse_media_query_list;
+use crate::diagnostics::DiagnosticCode;
+use crate::media::MediaQuery;
 use crate::style::{
-    AlignContent, AlignItems, AlignSelf, BoxSizing, Directional, Display, FlexDirection, FlexWrap,
-    JustifyContent, Rule, Selector, Style, StyleSheet,
+    AlignContent, AlignItems, AlignSelf, AspectRatio, AttributeOperator, AttributeSelector,
+    BoxSizing, Cursor, Direction, Directional, Display, FlexDirection, FlexWrap, FontStyle,
+    GridColumn, InlineStyle, JustifyContent, Length, LineClamp, LineHeight, ObjectFit, Overflow,
+    PointerEvents, Rule, Selector, Style, StyleSheet, TextAlign, TextOverflow, UserSelect,
+    VarDeclaration, Visibility, WhiteSpace, WillChange, ZIndex,
 };
+use crate::text::MAX_FONT_SIZE_PX;
+use crate::CssErrorKind;
 use cssparser::{
-    AtRuleParser, CowRcStr, DeclarationParser, ParseError, Parser, ParserInput, ParserState,
-    QualifiedRuleParser, RuleBodyItemParser, RuleBodyParser, StyleSheetParser,
+    AtRuleParser, CowRcStr, DeclarationParser, Delimiter, ParseError, ParseErrorKind, Parser,
+    ParserInput, ParserState, QualifiedRuleParser, RuleBodyItemParser, RuleBodyParser,
+    StyleSheetParser, Token,
 };
+use log::warn;
 
 /// Parse a CSS string into a StyleSheet
+#[cfg(test)]
 pub fn parse_css(css: &str) -> Result<StyleSheet, String> {
+    Ok(parse_css_with_warnings(css).0)
+}
+
+/// Something in a stylesheet that was skipped or adjusted while parsing.
+pub(crate) struct CssWarning {
+    pub code: DiagnosticCode,
+    pub message: String,
+    /// Index into the parsed stylesheet's rules.
+    pub rule_index: Option<usize>,
+}
+
+impl CssWarning {
+    fn syntax_error(source: String) -> Self {
+        Self {
+            code: DiagnosticCode::CssSyntaxError,
+            message: format!("Skipped invalid rule: {source}"),
+            rule_index: None,
+        }
+    }
+}
+
+/// Parse a CSS string, also returning what was skipped or adjusted.
+pub(crate) fn parse_css_with_warnings(css: &str) -> (StyleSheet, Vec<CssWarning>) {
     let mut input = ParserInput::new(css);
     let mut parser = Parser::new(&mut input);
 
     let mut stylesheet = StyleSheet::new();
     let mut css_parser = CssParser::new();
 
-    let rules = StyleSheetParser::new(&mut parser, &mut css_parser);
-
-    for rule in rules {
+    let mut errors = Vec::new();
+    for rule in StyleSheetParser::new(&mut parser, &mut css_parser) {
         match rule {
-            Ok(parsed_rule) => {
-                stylesheet.add_rule(parsed_rule);
+            Ok(parsed_rules) => {
+                for parsed_rule in parsed_rules {
+                    stylesheet.add_rule(parsed_rule);
+                }
             }
-            Err(err) => {
-                eprintln!("CSS parsing error: {:?}", err);
+            Err((err, source)) => {
+                warn!("CSS parsing error: {err:?}");
+                errors.push(source.trim().to_owned());
             }
         }
     }
 
-    Ok(stylesheet)
+    let mut warnings = css_parser.warnings;
+    warnings.extend(errors.into_iter().map(CssWarning::syntax_error));
+
+    (stylesheet, warnings)
+}
+
+/// Parse the value of one declaration, such as one whose `var()` were substituted. `None` if
+/// the property is unknown or the value invalid for it.
+pub(crate) fn parse_declaration(property: &str, value: &str) -> Option<Style> {
+    let mut input = ParserInput::new(value);
+    let mut parser = Parser::new(&mut input);
+    parser
+        .parse_entirely(|input| {
+            StyleDeclarationParser::new().parse_property(property.into(), input)
+        })
+        .ok()
+}
+
+/// The declarations of a `style` attribute, such as `width: 10px; color: red`, merged in
+/// order, the `!important` ones apart. Invalid declarations, and those with `var()`, are
+/// dropped.
+pub(crate) fn parse_inline_style(text: &str) -> InlineStyle {
+    let mut input = ParserInput::new(text);
+    let mut input = Parser::new(&mut input);
+    let mut declaration_parser = StyleDeclarationParser::new();
+    let mut style = InlineStyle::default();
+    for item in RuleBodyParser::new(&mut input, &mut declaration_parser) {
+        match item {
+            Ok(Declaration {
+                value: DeclaredValue::Parsed(parsed),
+                important: false,
+            }) => style.declarations.merge(&parsed),
+            Ok(Declaration {
+                value: DeclaredValue::Parsed(parsed),
+                important: true,
+            }) => style.important.merge(&parsed),
+            Ok(_) => {}
+            Err((err, source)) => warn!("ignored inline declaration `{}`: {err:?}", source.trim()),
+        }
+    }
+    style
 }
 
 /// CSS Parser implementation
 pub struct CssParser {
-    // We can add state here if needed
+    warnings: Vec<CssWarning>,
+    /// Rules produced so far, i.e. the index of the next one.
+    rule_count: usize,
+    /// How many `@media` blocks the parser is in.
+    media_depth: usize,
 }
 
+/// How deep `@media` blocks may nest. Each is parsed recursively, so deeper ones are
+/// skipped rather than overflow the stack.
+const MAX_MEDIA_NESTING: usize = 32;
+
 impl CssParser {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            warnings: Vec::new(),
+            rule_count: 0,
+            media_depth: 0,
+        }
     }
 }
 
 impl<'i> QualifiedRuleParser<'i> for CssParser {
     type Prelude = Selector;
-    type QualifiedRule = Rule;
-    type Error = ();
+    // A list so that `@media` blocks can yield all of their nested rules.
+    type QualifiedRule = Vec<Rule>;
+    type Error = CssErrorKind;
 
     fn parse_prelude<'t>(
         &mut self,
         input: &mut Parser<'i, 't>,
     ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
-        // Parse selector - for now we'll support simple class and tag selectors
-        if input.try_parse(|input| input.expect_delim('.')).is_ok() {
-            let class_name = input.expect_ident()?;
-            Ok(Selector::Class(class_name.to_string()))
-        } else {
-            let name = input.expect_ident()?;
-            Ok(Selector::Tag(name.as_ref().to_ascii_lowercase()))
-        }
+        parse_compound_selector(input)
     }
 
     fn parse_block<'t>(
@@ -67,46 +150,251 @@ impl<'i> QualifiedRuleParser<'i> for CssParser {
         _start: &ParserState,
         input: &mut Parser<'i, 't>,
     ) -> Result<Self::QualifiedRule, ParseError<'i, Self::Error>> {
-        let mut declarations = Vec::new();
+        let (mut declarations, mut important) = (Vec::new(), Vec::new());
+        let mut var_declarations = Vec::new();
         let mut declaration_parser = StyleDeclarationParser::new();
+        let rule_index = self.rule_count;
+        self.rule_count += 1;
 
         let parser = RuleBodyParser::new(input, &mut declaration_parser);
         for item in parser {
             match item {
-                Ok(declaration) => declarations.push(declaration),
-                Err(err) => {
-                    eprintln!("Declaration parsing error: {:?}", err);
+                Ok(Declaration {
+                    value: DeclaredValue::Parsed(style),
+                    important: true,
+                }) => important.push(*style),
+                Ok(Declaration {
+                    value: DeclaredValue::Parsed(style),
+                    important: false,
+                }) => declarations.push(*style),
+                Ok(Declaration {
+                    value: DeclaredValue::UsesVar { property, value },
+                    important: is_important,
+                }) => var_declarations.push(VarDeclaration {
+                    property,
+                    value,
+                    important: is_important,
+                    index: if is_important {
+                        important.len()
+                    } else {
+                        declarations.len()
+                    },
+                }),
+                Err((err, source)) => {
+                    warn!("declaration parsing error: {err:?}");
+                    let (code, what) = match err.kind {
+                        ParseErrorKind::Custom(CssErrorKind::UnknownProperty) => {
+                            (DiagnosticCode::CssUnknownProperty, "unknown property")
+                        }
+                        _ => (DiagnosticCode::CssInvalidValue, "invalid value"),
+                    };
+                    self.warnings.push(CssWarning {
+                        code,
+                        message: format!("Ignored declaration `{}`: {what}", source.trim()),
+                        rule_index: Some(rule_index),
+                    });
                 }
             }
         }
 
-        Ok(Rule {
+        self.warnings.extend(
+            declaration_parser
+                .clamped
+                .into_iter()
+                .map(|message| CssWarning {
+                    code: DiagnosticCode::CssValueClamped,
+                    message,
+                    rule_index: Some(rule_index),
+                }),
+        );
+
+        Ok(vec![Rule {
             selector: prelude,
             declarations,
-        })
+            important,
+            var_declarations,
+            media: Vec::new(),
+        }])
     }
 }
 
 impl<'i> AtRuleParser<'i> for CssParser {
-    type Prelude = ();
-    type AtRule = Rule;
-    type Error = ();
+    type Prelude = MediaQuery;
+    type AtRule = Vec<Rule>;
+    type Error = CssErrorKind;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Prelude, ParseError<'i, Self::Error>> {
+        if name.eq_ignore_ascii_case("media") {
+            let mut unsupported = Vec::new();
+            let query = parse_media_query_list(input, &mut unsupported)?;
+            self.warnings
+                .extend(unsupported.into_iter().map(|condition| CssWarning {
+                    code: DiagnosticCode::CssUnsupportedMediaFeature,
+                    message: format!("Unsupported media feature `{condition}` never matches"),
+                    rule_index: None,
+                }));
+            Ok(query)
+        } else {
+            Err(input.new_error(cssparser::BasicParseErrorKind::AtRuleInvalid(name)))
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::Prelude,
+        _start: &ParserState,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::AtRule, ParseError<'i, Self::Error>> {
+        if self.media_depth >= MAX_MEDIA_NESTING {
+            return Err(input.new_custom_error(CssErrorKind::Syntax));
+        }
+        let mut rules = Vec::new();
+        let mut errors = Vec::new();
+
+        self.media_depth += 1;
+        for nested in StyleSheetParser::new(input, self) {
+            match nested {
+                Ok(nested_rules) => rules.extend(nested_rules),
+                Err((err, source)) => {
+                    warn!("CSS parsing error: {err:?}");
+                    errors.push(source.trim().to_owned());
+                }
+            }
+        }
+        self.media_depth -= 1;
+
+        self.warnings
+            .extend(errors.into_iter().map(CssWarning::syntax_error));
+
+        for rule in &mut rules {
+            rule.media.push(prelude.clone());
+        }
+
+        Ok(rules)
+    }
+}
+
+/// Compound selectors separated by whitespace, each matching a descendant of what the one
+/// before matches, like `.list .item[data-done]`: the selectors of `Engine::query`. `None`
+/// if the text isn't one.
+pub(crate) fn parse_selector_chain(text: &str) -> Option<Vec<Selector>> {
+    let mut input = ParserInput::new(text.trim());
+    let mut parser = Parser::new(&mut input);
+    parser
+        .parse_entirely(|input| {
+            let mut compounds = vec![parse_compound_selector(input)?];
+            while !input.is_exhausted() {
+                input.expect_whitespace()?;
+                compounds.push(parse_compound_selector(input)?);
+            }
+            Ok::<_, ParseError<CssErrorKind>>(compounds)
+        })
+        .ok()
+}
+
+/// An optional tag or `*`, then any classes, attribute selectors and `:focus`.
+fn parse_compound_selector<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, CssErrorKind>> {
+    let mut parts = Vec::new();
+    let universal = input.try_parse(|input| input.expect_delim('*')).is_ok();
+    if !universal {
+        if let Ok(name) = input.try_parse(|input| input.expect_ident_cloned()) {
+            parts.push(Selector::Tag(name.as_ref().to_ascii_lowercase()));
+        }
+    }
+    while let Ok(part) = input.try_parse(parse_subclass_selector) {
+        parts.push(part);
+    }
+    match parts.len() {
+        // `*.panel` is just `.panel`.
+        0 if universal => Ok(Selector::Universal),
+        0 => Err(input.new_error_for_next_token()),
+        1 => Ok(parts.pop().unwrap()),
+        _ => Ok(Selector::Compound(parts)),
+    }
+}
+
+/// `#id`, `.class`, `[attribute]` or `:focus`, right after the previous part of a compound selector: whitespace
+/// would be a descendant combinator, which isn't supported.
+fn parse_subclass_selector<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<Selector, ParseError<'i, CssErrorKind>> {
+    let location = input.current_source_location();
+    match input.next_including_whitespace()?.clone() {
+        Token::IDHash(name) => Ok(Selector::Id(name.to_string())),
+        Token::Delim('.') => match input.next_including_whitespace()?.clone() {
+            Token::Ident(name) => Ok(Selector::Class(name.to_string())),
+            token => Err(location.new_unexpected_token_error(token)),
+        },
+        Token::SquareBracketBlock => input
+            .parse_nested_block(parse_attribute_selector)
+            .map(Selector::Attribute),
+        Token::Colon => match input.next_including_whitespace()?.clone() {
+            Token::Ident(name) if name.eq_ignore_ascii_case("focus") => Ok(Selector::Focus),
+            token => Err(location.new_unexpected_token_error(token)),
+        },
+        token => Err(location.new_unexpected_token_error(token)),
+    }
+}
+
+/// The inside of `[name]`, `[name=value]`, `[name^="value"]`, `[name$=…]` or `[name*=…]`.
+fn parse_attribute_selector<'i>(
+    input: &mut Parser<'i, '_>,
+) -> Result<AttributeSelector, ParseError<'i, CssErrorKind>> {
+    let name = input.expect_ident()?.to_string();
+    if input.is_exhausted() {
+        return Ok(AttributeSelector { name, value: None });
+    }
+    let location = input.current_source_location();
+    let operator = match input.next()?.clone() {
+        Token::Delim('=') => AttributeOperator::Equals,
+        Token::PrefixMatch => AttributeOperator::Prefix,
+        Token::SuffixMatch => AttributeOperator::Suffix,
+        Token::SubstringMatch => AttributeOperator::Substring,
+        token => return Err(location.new_unexpected_token_error(token)),
+    };
+    let value = input.expect_ident_or_string()?.to_string();
+    Ok(AttributeSelector {
+        name,
+        value: Some((operator, value)),
+    })
 }
 
 /// Declaration parser for style properties
 pub struct StyleDeclarationParser {
-    // State can be added here if needed
+    /// Messages about values that will be clamped.
+    clamped: Vec<String>,
 }
 
 impl StyleDeclarationParser {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            clamped: Vec::new(),
+        }
     }
 }
 
+pub struct Declaration {
+    pub value: DeclaredValue,
+    /// Marked `!important`.
+    pub important: bool,
+}
+
+pub enum DeclaredValue {
+    /// The style the declaration sets.
+    Parsed(Box<Style>),
+    /// A value with `var()`, as written.
+    UsesVar { property: String, value: String },
+}
+
 impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
-    type Declaration = Style;
-    type Error = ();
+    type Declaration = Declaration;
+    type Error = CssErrorKind;
 
     fn parse_value<'t>(
         &mut self,
@@ -114,13 +402,44 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
         input: &mut Parser<'i, 't>,
         _declaration_start: &ParserState,
     ) -> Result<Self::Declaration, ParseError<'i, Self::Error>> {
+        let value = input.parse_until_before(Delimiter::Bang, |input| {
+            let start = input.state();
+            // Custom properties keep their `var()` until they're resolved among themselves.
+            if !name.starts_with("--") && variables::uses_var(input) {
+                let value = input.slice_from(start.position()).trim().to_owned();
+                let property = name.to_string();
+                return Ok(DeclaredValue::UsesVar { property, value });
+            }
+            input.reset(&start);
+            let style = self.parse_property(name, input)?;
+            Ok(DeclaredValue::Parsed(Box::new(style)))
+        })?;
+        let important = input.try_parse(cssparser::parse_important).is_ok();
+        Ok(Declaration { value, important })
+    }
+}
+
+impl StyleDeclarationParser {
+    fn parse_property<'i>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, '_>,
+    ) -> Result<Style, ParseError<'i, CssErrorKind>> {
         let mut style = Style::default();
 
         match name.as_ref() {
+            custom if custom.starts_with("--") => {
+                let start = input.position();
+                while input.next_including_whitespace_and_comments().is_ok() {}
+                let value = input.slice_from(start).trim().to_owned();
+                style.custom_properties.set(custom.to_owned(), value);
+            }
             "display" => {
                 let ident = input.expect_ident()?;
                 match ident.as_ref() {
-                    "flex" => style.display = Display::Flex,
+                    "flex" => style.display = Some(Display::Flex),
+                    "grid" => style.display = Some(Display::Grid),
+                    "none" => style.display = Some(Display::None),
                     _ => return Err(input.new_error_for_next_token()),
                 }
             }
@@ -186,6 +505,21 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
             "border" => {
                 self.parse_border_shorthand(input, &mut style)?;
             }
+            "outline" => {
+                self.parse_outline_shorthand(input, &mut style)?;
+            }
+            "outline-width" => {
+                self.parse_border_side_width(input, &mut style.outline_width)?;
+            }
+            "outline-style" => {
+                self.parse_border_side_style(input, &mut style.outline_style)?;
+            }
+            "outline-color" => {
+                self.parse_border_side_color(input, &mut style.outline_color)?;
+            }
+            "outline-offset" => {
+                style.outline_offset = Some(self.parse_length_value(input)?);
+            }
             "border-radius" => {
                 self.parse_border_radius_shorthand(input, &mut style)?;
             }
@@ -209,6 +543,224 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
                     _ => return Err(input.new_error_for_next_token()),
                 });
             }
+            "text-align" => {
+                let ident = input.expect_ident()?;
+                style.text_align = Some(match ident.as_ref() {
+                    "left" => TextAlign::Left,
+                    "center" => TextAlign::Center,
+                    "right" => TextAlign::Right,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "white-space" => {
+                let ident = input.expect_ident()?;
+                style.white_space = Some(match ident.as_ref() {
+                    "normal" => WhiteSpace::Normal,
+                    "nowrap" => WhiteSpace::Nowrap,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "text-overflow" => {
+                let ident = input.expect_ident()?;
+                style.text_overflow = Some(match ident.as_ref() {
+                    "clip" => TextOverflow::Clip,
+                    "ellipsis" => TextOverflow::Ellipsis,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "line-clamp" | "-webkit-line-clamp" => {
+                if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
+                    style.line_clamp = Some(LineClamp::None);
+                } else {
+                    let lines = input.expect_integer()?;
+                    if lines < 1 {
+                        return Err(input.new_error_for_next_token());
+                    }
+                    style.line_clamp = Some(LineClamp::Lines(lines as u32));
+                }
+            }
+            "object-fit" => {
+                let ident = input.expect_ident()?;
+                style.object_fit = Some(match ident.as_ref() {
+                    "fill" => ObjectFit::Fill,
+                    "contain" => ObjectFit::Contain,
+                    "cover" => ObjectFit::Cover,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "opacity" => {
+                style.opacity = Some(self.parse_opacity_value(input)?);
+            }
+            "visibility" => {
+                let ident = input.expect_ident()?;
+                style.visibility = Some(match ident.as_ref() {
+                    "visible" => Visibility::Visible,
+                    "hidden" => Visibility::Hidden,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "user-select" => {
+                let ident = input.expect_ident()?;
+                style.user_select = Some(match ident.as_ref() {
+                    "none" => UserSelect::None,
+                    "text" => UserSelect::Text,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "pointer-events" => {
+                let ident = input.expect_ident()?;
+                style.pointer_events = Some(match ident.as_ref() {
+                    "auto" => PointerEvents::Auto,
+                    "none" => PointerEvents::None,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "cursor" => {
+                let ident = input.expect_ident()?;
+                style.cursor = Some(match ident.as_ref() {
+                    "auto" => Cursor::Auto,
+                    "default" => Cursor::Default,
+                    "pointer" => Cursor::Pointer,
+                    "text" => Cursor::Text,
+                    "move" => Cursor::Move,
+                    "not-allowed" => Cursor::NotAllowed,
+                    "grab" => Cursor::Grab,
+                    "grabbing" => Cursor::Grabbing,
+                    "crosshair" => Cursor::Crosshair,
+                    "wait" => Cursor::Wait,
+                    "progress" => Cursor::Progress,
+                    "help" => Cursor::Help,
+                    "col-resize" => Cursor::ColResize,
+                    "row-resize" => Cursor::RowResize,
+                    "ew-resize" => Cursor::EwResize,
+                    "ns-resize" => Cursor::NsResize,
+                    "zoom-in" => Cursor::ZoomIn,
+                    "zoom-out" => Cursor::ZoomOut,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "direction" => {
+                let ident = input.expect_ident()?;
+                style.direction = Some(match ident.as_ref() {
+                    "ltr" => Direction::Ltr,
+                    "rtl" => Direction::Rtl,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "overflow" => {
+                let ident = input.expect_ident()?;
+                style.overflow = Some(match ident.as_ref() {
+                    "visible" => Overflow::Visible,
+                    "hidden" | "clip" | "scroll" | "auto" => Overflow::Hidden,
+                    _ => return Err(input.new_error_for_next_token()),
+                });
+            }
+            "will-change" => {
+                let mut will_change = WillChange::default();
+                if input
+                    .try_parse(|i| i.expect_ident_matching("auto"))
+                    .is_err()
+                {
+                    input.parse_comma_separated(|i| {
+                        match i.expect_ident()?.as_ref() {
+                            "transform" => will_change.transform = true,
+                            "opacity" => will_change.opacity = true,
+                            // Other animatable properties don't benefit from a layer.
+                            _ => {}
+                        }
+                        Ok::<_, ParseError<'i, CssErrorKind>>(())
+                    })?;
+                }
+                style.will_change = Some(will_change);
+            }
+            "transition" => {
+                style.transition = Some(self.parse_transition_list(input)?);
+            }
+            "font-family" => {
+                style.font_family = Some(self.parse_font_family_value(input)?);
+            }
+            "font-weight" => {
+                let weight = match input.try_parse(|i| i.expect_ident_cloned()) {
+                    Ok(ident) => match ident.as_ref() {
+                        "normal" => 400.0,
+                        "bold" => 700.0,
+                        _ => return Err(input.new_error_for_next_token()),
+                    },
+                    Err(_) => input.expect_number()?,
+                };
+                if !(1.0..=1000.0).contains(&weight) {
+                    return Err(input.new_error_for_next_token());
+                }
+                style.font_weight = Some(weight.round() as u16);
+            }
+            "font-style" => {
+                let ident = input.expect_ident()?;
+                let font_style = match ident.as_ref() {
+                    "normal" => FontStyle::Normal,
+                    "italic" => FontStyle::Italic,
+                    "oblique" => FontStyle::Oblique,
+                    _ => return Err(input.new_error_for_next_token()),
+                };
+                if font_style == FontStyle::Oblique {
+                    // The angle of `oblique <angle>` isn't used.
+                    let _ = input.try_parse(|input| self.parse_angle_degrees(input));
+                }
+                style.font_style = Some(font_style);
+            }
+            "line-height" => {
+                let line_height = if input
+                    .try_parse(|i| i.expect_ident_matching("normal"))
+                    .is_ok()
+                {
+                    LineHeight::Normal
+                } else if let Ok(number) = input.try_parse(|i| i.expect_number()) {
+                    LineHeight::Number(number as f64)
+                } else {
+                    LineHeight::Length(self.parse_length_value(input)?)
+                };
+                // Lines can't be negative or `auto` high.
+                let invalid = match &line_height {
+                    LineHeight::Normal => false,
+                    LineHeight::Number(number) => *number < 0.0,
+                    LineHeight::Length(Length::Auto) => true,
+                    LineHeight::Length(
+                        Length::Px(value)
+                        | Length::Em(value)
+                        | Length::Rem(value)
+                        | Length::Percent(value),
+                    ) => *value < 0.0,
+                    LineHeight::Length(_) => false,
+                };
+                if invalid {
+                    return Err(input.new_error_for_next_token());
+                }
+                style.line_height = Some(line_height);
+            }
+            "letter-spacing" => {
+                if input
+                    .try_parse(|i| i.expect_ident_matching("normal"))
+                    .is_ok()
+                {
+                    style.letter_spacing = Some(Length::Px(0.0));
+                } else {
+                    let spacing = self.parse_length_value(input)?;
+                    if spacing == Length::Auto {
+                        return Err(input.new_error_for_next_token());
+                    }
+                    style.letter_spacing = Some(spacing);
+                }
+            }
+            "font-size" => {
+                let size = self.parse_length_value(input)?;
+                if let Length::Px(px) = size {
+                    if px > MAX_FONT_SIZE_PX {
+                        self.clamped.push(format!(
+                            "font-size: {px}px is larger than the supported {MAX_FONT_SIZE_PX}px"
+                        ));
+                    }
+                }
+                style.font_size = Some(size);
+            }
             "width" => {
                 style.width = Some(self.parse_length_value(input)?);
             }
@@ -357,6 +909,27 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
                     _ => return Err(input.new_error_for_next_token()),
                 });
             }
+            "aspect-ratio" => {
+                if input.try_parse(|i| i.expect_ident_matching("auto")).is_ok() {
+                    style.aspect_ratio = Some(AspectRatio::Auto);
+                } else {
+                    let width = input.expect_number()? as f64;
+                    let height = if input.try_parse(|i| i.expect_delim('/')).is_ok() {
+                        input.expect_number()? as f64
+                    } else {
+                        1.0
+                    };
+                    if width < 0.0 || height < 0.0 {
+                        return Err(input.new_error_for_next_token());
+                    }
+                    // A ratio with a zero in it is degenerate, which is `auto`.
+                    style.aspect_ratio = Some(if width > 0.0 && height > 0.0 {
+                        AspectRatio::Ratio(width / height)
+                    } else {
+                        AspectRatio::Auto
+                    });
+                }
+            }
             "flex-grow" => {
                 let value = input.expect_number()?;
                 style.flex_grow = Some(value as f64);
@@ -368,15 +941,48 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
             "flex-basis" => {
                 style.flex_basis = Some(self.parse_length_value(input)?);
             }
+            "z-index" => {
+                style.z_index = Some(
+                    if input.try_parse(|i| i.expect_ident_matching("auto")).is_ok() {
+                        ZIndex::Auto
+                    } else {
+                        ZIndex::Integer(input.expect_integer()?)
+                    },
+                );
+            }
             "order" => {
                 let value = input.expect_number()?;
                 style.order = Some(value as i32);
             }
             "gap" => {
                 let gap = self.parse_length_value(input)?;
-                style.row_gap = Some(gap);
+                style.row_gap = Some(gap.clone());
                 style.column_gap = Some(gap);
             }
+            "grid-template-columns" | "grid-template-rows" => {
+                let tracks = if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
+                    Some(Vec::new())
+                } else {
+                    Some(self.parse_grid_track_list(input)?)
+                };
+                if name.as_ref() == "grid-template-columns" {
+                    style.grid_template_columns = tracks;
+                } else {
+                    style.grid_template_rows = tracks;
+                }
+            }
+            "grid-column" => {
+                if input.try_parse(|i| i.expect_ident_matching("auto")).is_ok() {
+                    style.grid_column = Some(GridColumn::Auto);
+                } else {
+                    input.expect_ident_matching("span")?;
+                    let span = input.expect_integer()?;
+                    if span < 1 {
+                        return Err(input.new_error_for_next_token());
+                    }
+                    style.grid_column = Some(GridColumn::Span(span as u32));
+                }
+            }
             "row-gap" => {
                 style.row_gap = Some(self.parse_length_value(input)?);
             }
@@ -385,7 +991,7 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
             }
             _ => {
                 // Skip unknown properties
-                return Err(input.new_error_for_next_token());
+                return Err(input.new_custom_error(CssErrorKind::UnknownProperty));
             }
         }
 
@@ -395,17 +1001,17 @@ impl<'i> DeclarationParser<'i> for StyleDeclarationParser {
 
 impl<'i> AtRuleParser<'i> for StyleDeclarationParser {
     type Prelude = ();
-    type AtRule = Style;
-    type Error = ();
+    type AtRule = Declaration;
+    type Error = CssErrorKind;
 }
 
 impl<'i> QualifiedRuleParser<'i> for StyleDeclarationParser {
     type Prelude = ();
-    type QualifiedRule = Style;
-    type Error = ();
+    type QualifiedRule = Declaration;
+    type Error = CssErrorKind;
 }
 
-impl<'i> RuleBodyItemParser<'i, Style, ()> for StyleDeclarationParser {
+impl<'i> RuleBodyItemParser<'i, Declaration, CssErrorKind> for StyleDeclarationParser {
     fn parse_qualified(&self) -> bool {
         false
     }
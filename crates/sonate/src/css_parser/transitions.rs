@@ -0,0 +1,135 @@
+use super::parser::StyleDeclarationParser;
+use crate::style::{TimingFunction, Transition, TransitionProperty};
+use crate::CssErrorKind;
+use cssparser::{ParseError, Parser, Token};
+use std::time::Duration;
+
+impl StyleDeclarationParser {
+    /// `transition: none | <single-transition>#`, where each entry is a property, a duration,
+    /// a timing function and a delay, in any order and each optional.
+    pub(crate) fn parse_transition_list<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Vec<Transition>, ParseError<'i, CssErrorKind>> {
+        if input.try_parse(|i| i.expect_ident_matching("none")).is_ok() {
+            return Ok(Vec::new());
+        }
+
+        input.parse_comma_separated(|input| self.parse_single_transition(input))
+    }
+
+    fn parse_single_transition<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Transition, ParseError<'i, CssErrorKind>> {
+        let mut property = None;
+        let mut duration = None;
+        let mut delay = None;
+        let mut timing_function = None;
+
+        while !input.is_exhausted() {
+            // The first time is the duration, the second the delay.
+            if let Ok(time) = input.try_parse(parse_time) {
+                match (duration, delay) {
+                    (None, _) => duration = Some(time),
+                    (Some(_), None) => delay = Some(time),
+                    _ => return Err(input.new_error_for_next_token()),
+                }
+                continue;
+            }
+            if timing_function.is_none() {
+                timing_function = input.try_parse(parse_timing_function).ok();
+                if timing_function.is_some() {
+                    continue;
+                }
+            }
+            if property.is_none() {
+                property = input.try_parse(parse_transition_property).ok();
+                if property.is_some() {
+                    continue;
+                }
+            }
+            return Err(input.new_error_for_next_token());
+        }
+
+        Ok(Transition {
+            property: property.unwrap_or(TransitionProperty::All),
+            duration: duration.unwrap_or_default(),
+            timing_function: timing_function.unwrap_or_default(),
+            delay: delay.unwrap_or_default(),
+        })
+    }
+}
+
+/// A non-negative `<time>` in `s` or `ms`, to the microsecond.
+fn parse_time<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Duration, ParseError<'i, CssErrorKind>> {
+    let micros = match input.next()? {
+        Token::Dimension { value, unit, .. } if unit.eq_ignore_ascii_case("s") => {
+            *value as f64 * 1_000_000.0
+        }
+        Token::Dimension { value, unit, .. } if unit.eq_ignore_ascii_case("ms") => {
+            *value as f64 * 1_000.0
+        }
+        _ => return Err(input.new_error_for_next_token()),
+    };
+    if !(0.0..=u64::MAX as f64).contains(&micros) {
+        return Err(input.new_error_for_next_token());
+    }
+
+    Ok(Duration::from_micros(micros.round() as u64))
+}
+
+fn parse_timing_function<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<TimingFunction, ParseError<'i, CssErrorKind>> {
+    let location = input.current_source_location();
+    match input.next()?.clone() {
+        Token::Ident(ident) => Ok(match ident.as_ref() {
+            "linear" => TimingFunction::Linear,
+            "ease" => TimingFunction::Ease,
+            "ease-in" => TimingFunction::EaseIn,
+            "ease-out" => TimingFunction::EaseOut,
+            "ease-in-out" => TimingFunction::EaseInOut,
+            _ => return Err(location.new_unexpected_token_error(Token::Ident(ident))),
+        }),
+        Token::Function(name) if name.eq_ignore_ascii_case("cubic-bezier") => {
+            input.parse_nested_block(|input| {
+                let next = |input: &mut Parser<'i, '_>, comma: bool| {
+                    if comma {
+                        input.expect_comma()?;
+                    }
+                    Ok::<_, ParseError<'i, CssErrorKind>>(input.expect_number()? as f64)
+                };
+                let x1 = next(input, false)?;
+                let y1 = next(input, true)?;
+                let x2 = next(input, true)?;
+                let y2 = next(input, true)?;
+
+                // The curve must be a function of time.
+                if !(0.0..=1.0).contains(&x1) || !(0.0..=1.0).contains(&x2) {
+                    return Err(input.new_error_for_next_token());
+                }
+                Ok(TimingFunction::CubicBezier(x1, y1, x2, y2))
+            })
+        }
+        token => Err(location.new_unexpected_token_error(token)),
+    }
+}
+
+fn parse_transition_property<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<TransitionProperty, ParseError<'i, CssErrorKind>> {
+    let location = input.current_source_location();
+    let ident = input.expect_ident()?.clone();
+    Ok(match ident.as_ref() {
+        "all" => TransitionProperty::All,
+        "color" => TransitionProperty::Color,
+        "background-color" => TransitionProperty::BackgroundColor,
+        "width" => TransitionProperty::Width,
+        "height" => TransitionProperty::Height,
+        "opacity" => TransitionProperty::Opacity,
+        _ => return Err(location.new_unexpected_token_error(Token::Ident(ident))),
+    })
+}
@@ -1,5 +1,9 @@
 use crate::css_parser::parse_css;
-use crate::style::{BoxSizing, Length, Radius, Selector};
+use crate::style::{
+    AspectRatio, BorderStyle, BoxSizing, Cursor, Direction, Display, FontStyle, GridColumn,
+    GridTrack, Length, LineClamp, LineHeight, ObjectFit, Overflow, PointerEvents, Radius, Rgba,
+    Selector, TextAlign, TextOverflow, UserSelect, Visibility, WhiteSpace, WillChange, ZIndex,
+};
 
 #[test]
 fn test_parse_lengths() {
@@ -26,6 +30,16 @@ fn test_parse_lengths() {
     }
 }
 
+#[test]
+fn font_relative_lengths_keep_their_unit() {
+    let stylesheet = parse_css(".a { width: 2rem; height: 1.5em; font-size: 50%; }").unwrap();
+    let declarations = &stylesheet.rules[0].declarations;
+    assert_eq!(declarations[0].width, Some(Length::Rem(2.0)));
+    assert_eq!(declarations[1].height, Some(Length::Em(1.5)));
+    assert_eq!(declarations[2].font_size, Some(Length::Percent(50.0)));
+    assert!(stylesheet.depends_on_ancestors());
+}
+
 #[test]
 fn test_parse_box_sizing() {
     let css = r#"
@@ -314,3 +328,561 @@ fn test_parse_padding_sides() {
         .iter()
         .any(|d| matches!(d.padding.left, Some(Length::Auto))));
 }
+
+#[test]
+fn test_parse_opacity() {
+    let css = r#"
+        .number { opacity: 0.25; }
+        .percent { opacity: 40%; }
+        .clamped { opacity: 2; }
+        .invalid { opacity: 10px; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    assert_eq!(stylesheet.rules.len(), 4);
+
+    let opacity_of = |index: usize| {
+        stylesheet.rules[index]
+            .declarations
+            .iter()
+            .find_map(|d| d.opacity)
+    };
+
+    assert_eq!(opacity_of(0), Some(0.25));
+    assert!((opacity_of(1).unwrap() - 0.4).abs() < 1e-6);
+    assert_eq!(opacity_of(2), Some(1.0));
+    assert_eq!(opacity_of(3), None);
+}
+
+#[test]
+fn test_parse_user_select() {
+    let css = r#"
+        .copyable { user-select: text; }
+        .label { user-select: none; }
+        .unsupported { user-select: all; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let user_select_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.user_select)
+    };
+    assert_eq!(user_select_of(0), Some(UserSelect::Text));
+    assert_eq!(user_select_of(1), Some(UserSelect::None));
+    assert_eq!(user_select_of(2), None);
+}
+
+#[test]
+fn test_parse_pointer_events() {
+    let css = r#"
+        .overlay { pointer-events: none; }
+        .button { pointer-events: auto; }
+        .unsupported { pointer-events: visiblePainted; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let pointer_events_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.pointer_events)
+    };
+    assert_eq!(pointer_events_of(0), Some(PointerEvents::None));
+    assert_eq!(pointer_events_of(1), Some(PointerEvents::Auto));
+    assert_eq!(pointer_events_of(2), None);
+}
+
+#[test]
+fn test_parse_object_fit() {
+    let css = r#"
+        .photo { object-fit: cover; }
+        .logo { object-fit: contain; }
+        .stretched { object-fit: fill; }
+        .unsupported { object-fit: scale-down; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let object_fit_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.object_fit)
+    };
+    assert_eq!(object_fit_of(0), Some(ObjectFit::Cover));
+    assert_eq!(object_fit_of(1), Some(ObjectFit::Contain));
+    assert_eq!(object_fit_of(2), Some(ObjectFit::Fill));
+    assert_eq!(object_fit_of(3), None);
+}
+
+#[test]
+fn test_parse_text_align_and_white_space() {
+    let css = r#"
+        .title { text-align: center; white-space: nowrap; }
+        .price { text-align: right; white-space: normal; }
+        .body { text-align: left; }
+        .unsupported { text-align: justify; white-space: pre; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let text_align_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.text_align)
+    };
+    let white_space_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.white_space)
+    };
+    assert_eq!(text_align_of(0), Some(TextAlign::Center));
+    assert_eq!(white_space_of(0), Some(WhiteSpace::Nowrap));
+    assert_eq!(text_align_of(1), Some(TextAlign::Right));
+    assert_eq!(white_space_of(1), Some(WhiteSpace::Normal));
+    assert_eq!(text_align_of(2), Some(TextAlign::Left));
+    assert_eq!(text_align_of(3), None);
+    assert_eq!(white_space_of(3), None);
+}
+
+#[test]
+fn test_parse_text_overflow_and_line_clamp() {
+    let css = r#"
+        .row { text-overflow: ellipsis; line-clamp: 2; }
+        .plain { text-overflow: clip; line-clamp: none; }
+        .webkit { -webkit-line-clamp: 3; }
+        .unsupported { text-overflow: fade; line-clamp: 0; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let text_overflow_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.text_overflow)
+    };
+    let line_clamp_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.line_clamp)
+    };
+    assert_eq!(text_overflow_of(0), Some(TextOverflow::Ellipsis));
+    assert_eq!(line_clamp_of(0), Some(LineClamp::Lines(2)));
+    assert_eq!(text_overflow_of(1), Some(TextOverflow::Clip));
+    assert_eq!(line_clamp_of(1), Some(LineClamp::None));
+    assert_eq!(line_clamp_of(2), Some(LineClamp::Lines(3)));
+    assert_eq!(text_overflow_of(3), None);
+    assert_eq!(line_clamp_of(3), None);
+}
+
+#[test]
+fn test_parse_cursor() {
+    let css = r#"
+        .link { cursor: pointer; }
+        .disabled { cursor: not-allowed; }
+        .handle { cursor: col-resize; }
+        .unsupported { cursor: url(hand.png), pointer; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let cursor_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.cursor)
+    };
+    assert_eq!(cursor_of(0), Some(Cursor::Pointer));
+    assert_eq!(cursor_of(1), Some(Cursor::NotAllowed));
+    assert_eq!(cursor_of(2), Some(Cursor::ColResize));
+    assert_eq!(cursor_of(3), None);
+}
+
+#[test]
+fn test_parse_outline() {
+    let css = r#"
+        .ring { outline: 2px dashed #ff0000; outline-offset: -1px; }
+        .style-only { outline: solid; }
+        .none { outline: none; }
+        .longhands { outline-width: thick; outline-style: dotted; outline-color: blue; }
+        .invalid { outline: 2px 3px solid; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let declarations = |i: usize| {
+        let mut style = crate::style::Style::default();
+        for declaration in &stylesheet.rules[i].declarations {
+            style.merge(declaration);
+        }
+        (
+            style.outline_width,
+            style.outline_style,
+            style.outline_color,
+            style.outline_offset,
+        )
+    };
+    let red = Rgba {
+        r: 255,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    assert_eq!(
+        declarations(0),
+        (
+            Some(Length::Px(2.0)),
+            Some(BorderStyle::Dashed),
+            Some(red),
+            Some(Length::Px(-1.0))
+        )
+    );
+    // The shorthand resets the width and style it leaves out.
+    assert_eq!(
+        declarations(1),
+        (Some(Length::Px(3.0)), Some(BorderStyle::Solid), None, None)
+    );
+    assert_eq!(
+        declarations(2),
+        (Some(Length::Px(3.0)), Some(BorderStyle::None), None, None)
+    );
+    let blue = Rgba {
+        r: 0,
+        g: 0,
+        b: 255,
+        a: 255,
+    };
+    assert_eq!(
+        declarations(3),
+        (
+            Some(Length::Px(5.0)),
+            Some(BorderStyle::Dotted),
+            Some(blue),
+            None
+        )
+    );
+    assert_eq!(declarations(4), (None, None, None, None));
+}
+
+#[test]
+fn test_parse_direction() {
+    let css = r#"
+        .hebrew { direction: rtl; }
+        .english { direction: ltr; }
+        .unsupported { direction: auto; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let direction_of = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.direction)
+    };
+    assert_eq!(direction_of(0), Some(Direction::Rtl));
+    assert_eq!(direction_of(1), Some(Direction::Ltr));
+    assert_eq!(direction_of(2), None);
+}
+
+#[test]
+fn test_parse_visibility() {
+    let css = r#"
+        .shown { visibility: visible; }
+        .hidden { visibility: hidden; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    assert_eq!(stylesheet.rules.len(), 2);
+
+    assert!(stylesheet.rules[0]
+        .declarations
+        .iter()
+        .any(|d| d.visibility == Some(Visibility::Visible)));
+    assert!(stylesheet.rules[1]
+        .declarations
+        .iter()
+        .any(|d| d.visibility == Some(Visibility::Hidden)));
+}
+
+#[test]
+fn test_parse_will_change() {
+    let css = r#"
+        .auto { will-change: auto; }
+        .transform { will-change: transform; }
+        .both { will-change: scroll-position, opacity, transform; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    assert_eq!(stylesheet.rules.len(), 3);
+
+    let will_change = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.will_change)
+            .expect("missing will-change")
+    };
+
+    assert_eq!(will_change(0), WillChange::default());
+    assert_eq!(
+        will_change(1),
+        WillChange {
+            transform: true,
+            opacity: false,
+        }
+    );
+    assert_eq!(
+        will_change(2),
+        WillChange {
+            transform: true,
+            opacity: true,
+        }
+    );
+}
+
+#[test]
+fn test_parse_display_none() {
+    let stylesheet = parse_css(".gone { display: none; }").expect("Failed to parse CSS");
+
+    assert!(stylesheet.rules[0]
+        .declarations
+        .iter()
+        .any(|d| d.display == Some(Display::None)));
+}
+
+#[test]
+fn test_parse_font_family_and_size() {
+    let css = r#"
+        .quoted { font-family: "Fira Sans", Arial, sans-serif; font-size: 18px; }
+        .unquoted { font-family: Sonate Test Font; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    assert_eq!(stylesheet.rules.len(), 2);
+
+    let family = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.font_family.clone())
+    };
+    let names = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect());
+    assert_eq!(family(0), names(&["Fira Sans", "Arial", "sans-serif"]));
+    assert_eq!(family(1), names(&["Sonate Test Font"]));
+    assert!(stylesheet.rules[0]
+        .declarations
+        .iter()
+        .any(|d| d.font_size == Some(Length::Px(18.0))));
+}
+
+#[test]
+fn test_parse_font_family_lists() {
+    let css = r#"
+        .ui { font-family: "Inter", "Segoe UI", sans-serif; }
+        .mixed { font-family: Noto Sans CJK JP , 'Apple Color Emoji',monospace; }
+        .empty { font-family: ; }
+        .trailing { font-family: Arial, ; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let family = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.font_family.clone())
+    };
+    let names = |names: &[&str]| Some(names.iter().map(|name| name.to_string()).collect());
+    assert_eq!(family(0), names(&["Inter", "Segoe UI", "sans-serif"]));
+    assert_eq!(
+        family(1),
+        names(&["Noto Sans CJK JP", "Apple Color Emoji", "monospace"])
+    );
+    assert_eq!(family(2), None);
+    assert_eq!(family(3), None);
+}
+
+#[test]
+fn test_parse_font_weight_and_style() {
+    let css = r#"
+        .bold { font-weight: bold; font-style: italic; }
+        .numeric { font-weight: 650; font-style: oblique 10deg; }
+        .normal { font-weight: normal; font-style: normal; }
+        .unsupported { font-weight: 1200; font-style: slanted; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let weight = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.font_weight)
+    };
+    let font_style = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.font_style)
+    };
+    assert_eq!(weight(0), Some(700));
+    assert_eq!(font_style(0), Some(FontStyle::Italic));
+    assert_eq!(weight(1), Some(650));
+    assert_eq!(font_style(1), Some(FontStyle::Oblique));
+    assert_eq!(weight(2), Some(400));
+    assert_eq!(font_style(2), Some(FontStyle::Normal));
+    assert_eq!(weight(3), None);
+    assert_eq!(font_style(3), None);
+}
+
+#[test]
+fn test_parse_line_height_and_letter_spacing() {
+    let css = r#"
+        .number { line-height: 1.5; letter-spacing: 2px; }
+        .length { line-height: 18px; letter-spacing: -0.5em; }
+        .percent { line-height: 150%; letter-spacing: normal; }
+        .normal { line-height: normal; }
+        .unsupported { line-height: -2; letter-spacing: auto; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let line_height = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.line_height.clone())
+    };
+    let letter_spacing = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.letter_spacing.clone())
+    };
+    assert_eq!(line_height(0), Some(LineHeight::Number(1.5)));
+    assert_eq!(letter_spacing(0), Some(Length::Px(2.0)));
+    assert_eq!(line_height(1), Some(LineHeight::Length(Length::Px(18.0))));
+    assert_eq!(letter_spacing(1), Some(Length::Em(-0.5)));
+    assert_eq!(
+        line_height(2),
+        Some(LineHeight::Length(Length::Percent(150.0)))
+    );
+    assert_eq!(letter_spacing(2), Some(Length::Px(0.0)));
+    assert_eq!(line_height(3), Some(LineHeight::Normal));
+    assert_eq!(line_height(4), None);
+    assert_eq!(letter_spacing(4), None);
+}
+
+#[test]
+fn test_parse_grid() {
+    let css = r#"
+        .grid { display: grid; grid-template-columns: 100px 1fr 25%; grid-template-rows: repeat(2, 2em 0.5fr); }
+        .item { grid-column: span 2; }
+        .auto { grid-column: auto; grid-template-rows: none; }
+        .unsupported { grid-template-columns: auto -1fr; grid-column: span 0; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let declarations = |i: usize| &stylesheet.rules[i].declarations;
+    assert!(declarations(0)
+        .iter()
+        .any(|d| d.display == Some(Display::Grid)));
+    assert_eq!(
+        declarations(0)
+            .iter()
+            .find_map(|d| d.grid_template_columns.clone()),
+        Some(vec![
+            GridTrack::Length(Length::Px(100.0)),
+            GridTrack::Fr(1.0),
+            GridTrack::Length(Length::Percent(25.0)),
+        ])
+    );
+    let row = [GridTrack::Length(Length::Em(2.0)), GridTrack::Fr(0.5)];
+    assert_eq!(
+        declarations(0)
+            .iter()
+            .find_map(|d| d.grid_template_rows.clone()),
+        Some(row.iter().chain(&row).cloned().collect())
+    );
+    assert_eq!(
+        declarations(1).iter().find_map(|d| d.grid_column),
+        Some(GridColumn::Span(2))
+    );
+    assert_eq!(
+        declarations(2).iter().find_map(|d| d.grid_column),
+        Some(GridColumn::Auto)
+    );
+    assert_eq!(
+        declarations(2)
+            .iter()
+            .find_map(|d| d.grid_template_rows.clone()),
+        Some(Vec::new())
+    );
+    assert!(declarations(3)
+        .iter()
+        .all(|d| d.grid_template_columns.is_none() && d.grid_column.is_none()));
+}
+
+#[test]
+fn test_parse_aspect_ratio() {
+    let css = r#"
+        .ratio { aspect-ratio: 16 / 9; }
+        .number { aspect-ratio: 2; }
+        .auto { aspect-ratio: auto; }
+        .degenerate { aspect-ratio: 0 / 1; }
+        .unsupported { aspect-ratio: -1; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let aspect_ratio = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.aspect_ratio)
+    };
+    assert_eq!(aspect_ratio(0), Some(AspectRatio::Ratio(16.0 / 9.0)));
+    assert_eq!(aspect_ratio(1), Some(AspectRatio::Ratio(2.0)));
+    assert_eq!(aspect_ratio(2), Some(AspectRatio::Auto));
+    assert_eq!(aspect_ratio(3), Some(AspectRatio::Auto));
+    assert_eq!(aspect_ratio(4), None);
+}
+
+#[test]
+fn test_parse_z_index() {
+    let css = r#"
+        .above { z-index: 10; }
+        .below { z-index: -1; }
+        .auto { z-index: auto; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let z_index = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.z_index)
+    };
+
+    assert_eq!(z_index(0), Some(ZIndex::Integer(10)));
+    assert_eq!(z_index(1), Some(ZIndex::Integer(-1)));
+    assert_eq!(z_index(2), Some(ZIndex::Auto));
+}
+
+#[test]
+fn test_parse_overflow() {
+    let css = r#"
+        .visible { overflow: visible; }
+        .hidden { overflow: hidden; }
+        .scroll { overflow: scroll; }
+    "#;
+
+    let stylesheet = parse_css(css).expect("Failed to parse CSS");
+    let overflow = |i: usize| {
+        stylesheet.rules[i]
+            .declarations
+            .iter()
+            .find_map(|d| d.overflow)
+    };
+
+    assert_eq!(overflow(0), Some(Overflow::Visible));
+    assert_eq!(overflow(1), Some(Overflow::Hidden));
+    assert_eq!(overflow(2), Some(Overflow::Hidden));
+}
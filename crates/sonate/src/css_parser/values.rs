@@ -1,6 +1,9 @@
+use super::calc::parse_calc_length;
 use super::parser::StyleDeclarationParser;
-use crate::style::Length;
+use crate::style::{GridTrack, Length};
+use crate::CssErrorKind;
 use cssparser::{ParseError, Parser, Token};
+use std::sync::Arc;
 
 impl StyleDeclarationParser {
     fn normalize_hue_degrees(hue_degrees: f32) -> f32 {
@@ -22,7 +25,7 @@ impl StyleDeclarationParser {
     pub(crate) fn parse_angle_degrees<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<f32, ParseError<'i, ()>> {
+    ) -> Result<f32, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         let degrees = match token {
             Token::Number { value, .. } => *value as f32,
@@ -42,7 +45,7 @@ impl StyleDeclarationParser {
     pub(crate) fn parse_percentage<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<f32, ParseError<'i, ()>> {
+    ) -> Result<f32, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         match token {
             Token::Percentage { unit_value, .. } => Ok((*unit_value as f32) * 100.0),
@@ -50,17 +53,50 @@ impl StyleDeclarationParser {
         }
     }
 
+    /// Parse an `<alpha-value>` for `opacity`: a `<number>` or `<percentage>`, clamped to [0, 1].
+    pub(crate) fn parse_opacity_value<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<f64, ParseError<'i, CssErrorKind>> {
+        let token = input.next()?;
+        let value = match token {
+            Token::Number { value, .. } => *value as f64,
+            Token::Percentage { unit_value, .. } => *unit_value as f64,
+            _ => return Err(input.new_error_for_next_token()),
+        };
+        Ok(value.clamp(0.0, 1.0))
+    }
+
+    /// Parse a `font-family` list, in order. Generic families are passed through by name.
+    pub(crate) fn parse_font_family_value<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Vec<String>, ParseError<'i, CssErrorKind>> {
+        let families = input.parse_comma_separated(|input| {
+            if let Ok(name) = input.try_parse(|i| i.expect_string_cloned()) {
+                return Ok(name.to_string());
+            }
+
+            // Unquoted family names are a sequence of identifiers separated by whitespace.
+            let mut words = vec![input.expect_ident()?.to_string()];
+            while let Ok(word) = input.try_parse(|i| i.expect_ident_cloned()) {
+                words.push(word.to_string());
+            }
+            Ok(words.join(" "))
+        })?;
+
+        Ok(families)
+    }
+
     pub(crate) fn parse_length_value<'i, 't>(
         &mut self,
         input: &mut Parser<'i, 't>,
-    ) -> Result<Length, ParseError<'i, ()>> {
+    ) -> Result<Length, ParseError<'i, CssErrorKind>> {
         let token = input.next()?;
         match token {
-            Token::Dimension { value, unit, .. } => match unit.as_ref() {
-                "px" => Ok(Length::Px(*value as f64)),
-                "em" => Ok(Length::Em(*value as f64)),
-                "%" => Ok(Length::Percent(*value as f64)),
-                _ => Err(input.new_error_for_next_token()),
+            Token::Dimension { value, unit, .. } => match dimension_length(*value as f64, unit) {
+                Some(length) => Ok(length),
+                None => Err(input.new_error_for_next_token()),
             },
             Token::Number { value, .. } => {
                 // Numbers without units are treated as pixels
@@ -74,7 +110,96 @@ impl StyleDeclarationParser {
                 "auto" => Ok(Length::Auto),
                 _ => Err(input.new_error_for_next_token()),
             },
+            Token::Function(name) if name.eq_ignore_ascii_case("calc") => {
+                let calc = input.parse_nested_block(parse_calc_length)?;
+                Ok(Length::Calc(Arc::new(calc)))
+            }
             _ => Err(input.new_error_for_next_token()),
         }
     }
+
+    /// Parse a `grid-template-columns` or `grid-template-rows` track list: lengths,
+    /// percentages and `fr` flex factors, none of them negative, and `repeat(N, ...)` of
+    /// those.
+    pub(crate) fn parse_grid_track_list<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Vec<GridTrack>, ParseError<'i, CssErrorKind>> {
+        let mut tracks = Vec::new();
+        loop {
+            let repeat: Result<_, ParseError<'i, CssErrorKind>> = input.try_parse(|i| {
+                i.expect_function_matching("repeat")?;
+                i.parse_nested_block(|i| {
+                    let count = i.expect_integer()?;
+                    if count < 1 {
+                        return Err(i.new_error_for_next_token());
+                    }
+                    i.expect_comma()?;
+                    let mut repeated = vec![self.parse_grid_track(i)?];
+                    while let Ok(track) = i.try_parse(|i| self.parse_grid_track(i)) {
+                        repeated.push(track);
+                    }
+                    Ok((count as usize, repeated))
+                })
+            });
+            if let Ok((count, repeated)) = repeat {
+                for _ in 0..count {
+                    tracks.extend(repeated.iter().cloned());
+                }
+            } else if let Ok(track) = input.try_parse(|i| self.parse_grid_track(i)) {
+                tracks.push(track);
+            } else {
+                break;
+            }
+        }
+        if tracks.is_empty() {
+            return Err(input.new_error_for_next_token());
+        }
+        Ok(tracks)
+    }
+
+    fn parse_grid_track<'i, 't>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<GridTrack, ParseError<'i, CssErrorKind>> {
+        if let Ok(fr) = input.try_parse(|i| match i.next()? {
+            Token::Dimension { value, unit, .. } if unit.eq_ignore_ascii_case("fr") => {
+                Ok(*value as f64)
+            }
+            _ => Err(i.new_error_for_next_token::<CssErrorKind>()),
+        }) {
+            if fr < 0.0 {
+                return Err(input.new_error_for_next_token());
+            }
+            return Ok(GridTrack::Fr(fr));
+        }
+        let length = self.parse_length_value(input)?;
+        let invalid = match &length {
+            Length::Auto => true,
+            Length::Px(value)
+            | Length::Em(value)
+            | Length::Rem(value)
+            | Length::Percent(value)
+            | Length::Vw(value)
+            | Length::Vh(value) => *value < 0.0,
+            Length::Calc(_) => false,
+        };
+        if invalid {
+            return Err(input.new_error_for_next_token());
+        }
+        Ok(GridTrack::Length(length))
+    }
+}
+
+/// The length of a dimension in one of the supported units.
+pub(super) fn dimension_length(value: f64, unit: &str) -> Option<Length> {
+    Some(match unit {
+        "px" => Length::Px(value),
+        "em" => Length::Em(value),
+        "rem" => Length::Rem(value),
+        "vw" => Length::Vw(value),
+        "vh" => Length::Vh(value),
+        "%" => Length::Percent(value),
+        _ => return None,
+    })
 }
@@ -0,0 +1,139 @@
+use crate::css_parser::{parse_css, parse_declaration, resolve_custom_properties, substitute};
+use crate::style::{CustomProperties, Length, Rgba, VarDeclaration};
+
+fn lookup(properties: &CustomProperties) -> impl FnMut(&str) -> Option<String> + '_ {
+    |name| properties.get(name).map(str::to_owned)
+}
+
+#[test]
+fn custom_properties_keep_their_value_as_written() {
+    let stylesheet = parse_css(
+        ".theme { --primary: #3366ff; --Spacing:  4px  8px ; --empty-ish: var(--x) !important; }",
+    )
+    .unwrap();
+    let rule = &stylesheet.rules[0];
+    let normal: Vec<_> = rule
+        .declarations
+        .iter()
+        .flat_map(|declaration| declaration.custom_properties.iter())
+        .collect();
+    // Names are case-sensitive.
+    assert_eq!(
+        normal,
+        vec![("--primary", "#3366ff"), ("--Spacing", "4px  8px")]
+    );
+    assert_eq!(
+        rule.important[0].custom_properties.get("--empty-ish"),
+        Some("var(--x)")
+    );
+    assert!(rule.var_declarations.is_empty());
+}
+
+#[test]
+fn declarations_with_var_are_kept_in_place_for_later() {
+    let stylesheet = parse_css(
+        ".a {
+            width: 1px;
+            background-color: var(--primary, red);
+            height: 2px;
+            border: 1px solid rgb(var(--r), 0, 0) !important;
+            color: red;
+        }",
+    )
+    .unwrap();
+    let rule = &stylesheet.rules[0];
+    assert_eq!(rule.declarations.len(), 3);
+    assert_eq!(
+        rule.var_declarations,
+        vec![
+            VarDeclaration {
+                property: "background-color".to_owned(),
+                value: "var(--primary, red)".to_owned(),
+                important: false,
+                index: 1,
+            },
+            VarDeclaration {
+                property: "border".to_owned(),
+                value: "1px solid rgb(var(--r), 0, 0)".to_owned(),
+                important: true,
+                index: 0,
+            },
+        ]
+    );
+    assert!(stylesheet.depends_on_ancestors());
+    assert!(!parse_css(".a { width: 1px; }")
+        .unwrap()
+        .depends_on_ancestors());
+}
+
+#[test]
+fn references_are_replaced_at_any_depth() {
+    let properties: CustomProperties = [("--r", "51"), ("--blue", "255"), ("--gap", "4px")]
+        .into_iter()
+        .collect();
+    let mut lookup = lookup(&properties);
+    assert_eq!(
+        substitute("rgb(var(--r), 0, var(--blue))", &mut lookup).as_deref(),
+        Some("rgb(51, 0, 255)")
+    );
+    assert_eq!(
+        substitute("var(--gap) calc(var(--gap))", &mut lookup).as_deref(),
+        Some("4px calc(4px)")
+    );
+    // A missing property takes the fallback, which may refer to others.
+    assert_eq!(
+        substitute("var(--missing, var(--gap))", &mut lookup).as_deref(),
+        Some("4px")
+    );
+    assert_eq!(
+        substitute("var(--gap, var(--also-missing))", &mut lookup).as_deref(),
+        Some("4px")
+    );
+    assert_eq!(substitute("var(--missing)", &mut lookup), None);
+    assert_eq!(substitute("var(not-custom)", &mut lookup), None);
+}
+
+#[test]
+fn substituted_values_parse_like_written_ones() {
+    let properties: CustomProperties = [("--r", "51"), ("--blue", "#3366ff")].into_iter().collect();
+    let value = substitute("rgb(var(--r), 0, 0)", &mut lookup(&properties)).unwrap();
+    let style = parse_declaration("background-color", &value).unwrap();
+    assert_eq!(
+        style.background_color,
+        Some(Rgba {
+            r: 51,
+            g: 0,
+            b: 0,
+            a: 255
+        })
+    );
+    let style = parse_declaration("width", "12px").unwrap();
+    assert_eq!(style.width, Some(Length::Px(12.0)));
+    assert!(parse_declaration("width", "#3366ff").is_none());
+    assert!(parse_declaration("no-such-property", "1px").is_none());
+}
+
+#[test]
+fn custom_properties_resolve_among_themselves_except_in_cycles() {
+    let properties: CustomProperties = [
+        ("--base", "10px"),
+        ("--double", "var(--base) var(--base)"),
+        ("--a", "var(--b, 1px)"),
+        ("--b", "var(--a, 2px)"),
+        ("--self", "var(--self)"),
+        ("--after-cycle", "var(--a, 3px)"),
+        ("--broken", "var(--nothing)"),
+    ]
+    .into_iter()
+    .collect();
+    let resolved = resolve_custom_properties(&properties);
+    let resolved: Vec<_> = resolved.iter().collect();
+    assert_eq!(
+        resolved,
+        vec![
+            ("--after-cycle", "3px"),
+            ("--base", "10px"),
+            ("--double", "10px 10px"),
+        ]
+    );
+}
@@ -0,0 +1,178 @@
+//! `var()` references to custom properties, substituted once the custom properties of the
+//! node a declaration applies to are known.
+
+use crate::style::CustomProperties;
+use cssparser::{ParseError, Parser, ParserInput, ToCss, Token};
+use std::collections::{HashMap, HashSet};
+
+/// How deep blocks and references may nest. Values are walked recursively, so a bound keeps
+/// deeper ones from overflowing the stack; they don't substitute.
+const MAX_NESTING: usize = 32;
+
+/// Whether the rest of the value has a `var()`, at any depth up to `MAX_NESTING`. Consumes it.
+pub(super) fn uses_var(input: &mut Parser) -> bool {
+    uses_var_at(input, 0)
+}
+
+fn uses_var_at(input: &mut Parser, depth: usize) -> bool {
+    let mut found = false;
+    while let Ok(token) = input.next() {
+        match token {
+            Token::Function(name) if name.eq_ignore_ascii_case("var") => found = true,
+            Token::Function(_)
+            | Token::ParenthesisBlock
+            | Token::SquareBracketBlock
+            | Token::CurlyBracketBlock
+                if depth < MAX_NESTING =>
+            {
+                found |= input
+                    .parse_nested_block(|input| {
+                        Ok::<_, ParseError<()>>(uses_var_at(input, depth + 1))
+                    })
+                    .unwrap_or(false);
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+/// `value` with each `var(--name)` or `var(--name, fallback)` replaced by what `lookup`
+/// gives for the name, or else the fallback. `None` if a reference has neither, or is
+/// malformed, or nests deeper than `MAX_NESTING`.
+pub(crate) fn substitute(
+    value: &str,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+) -> Option<String> {
+    substitute_at(value, lookup, 0)
+}
+
+fn substitute_at(
+    value: &str,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    depth: usize,
+) -> Option<String> {
+    let mut input = ParserInput::new(value);
+    let mut input = Parser::new(&mut input);
+    let mut substituted = String::new();
+    substitute_tokens(&mut input, lookup, &mut substituted, depth)?;
+    Some(substituted.trim().to_owned())
+}
+
+fn substitute_tokens(
+    input: &mut Parser,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    out: &mut String,
+    depth: usize,
+) -> Option<()> {
+    if depth > MAX_NESTING {
+        return None;
+    }
+    while let Ok(token) = input.next_including_whitespace_and_comments() {
+        let token = token.clone();
+        let closing = match &token {
+            Token::Function(name) if name.eq_ignore_ascii_case("var") => {
+                let value = input
+                    .parse_nested_block(|input| {
+                        Ok::<_, ParseError<()>>(reference(input, lookup, depth + 1))
+                    })
+                    .ok()??;
+                out.push_str(&value);
+                continue;
+            }
+            Token::Function(_) | Token::ParenthesisBlock => ")",
+            Token::SquareBracketBlock => "]",
+            Token::CurlyBracketBlock => "}",
+            _ => {
+                token.to_css(out).ok()?;
+                continue;
+            }
+        };
+        token.to_css(out).ok()?;
+        input
+            .parse_nested_block(|input| {
+                Ok::<_, ParseError<()>>(substitute_tokens(input, lookup, out, depth + 1))
+            })
+            .ok()??;
+        out.push_str(closing);
+    }
+    Some(())
+}
+
+/// The inside of `var(…)`.
+fn reference(
+    input: &mut Parser,
+    lookup: &mut dyn FnMut(&str) -> Option<String>,
+    depth: usize,
+) -> Option<String> {
+    let name = input.expect_ident_cloned().ok()?;
+    if !name.starts_with("--") {
+        return None;
+    }
+    if input.is_exhausted() {
+        return lookup(&name);
+    }
+    input.expect_comma().ok()?;
+    // The fallback is only substituted when it's used, so its own references may fail.
+    let fallback_start = input.position();
+    while input.next_including_whitespace_and_comments().is_ok() {}
+    let fallback = input.slice_from(fallback_start);
+    lookup(&name).or_else(|| substitute_at(fallback, lookup, depth))
+}
+
+/// The custom properties with the `var()` in their values substituted. Properties whose
+/// references can't be, or that refer to themselves through others, are left out, so
+/// references to them take their fallback, as do those more than `MAX_NESTING` properties
+/// down a chain of references.
+pub(crate) fn resolve_custom_properties(properties: &CustomProperties) -> CustomProperties {
+    if !properties.iter().any(|(_, value)| value.contains("var(")) {
+        return properties.clone();
+    }
+    let mut resolver = Resolver {
+        properties,
+        resolved: HashMap::new(),
+        resolving: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    let names: Vec<&str> = properties.iter().map(|(name, _)| name).collect();
+    for name in names {
+        resolver.resolve(name);
+    }
+    let resolved = resolver.resolved;
+    properties
+        .iter()
+        .filter_map(|(name, _)| Some((name, resolved.get(name)?.as_deref()?)))
+        .collect()
+}
+
+struct Resolver<'a> {
+    properties: &'a CustomProperties,
+    /// `None` for properties that didn't resolve.
+    resolved: HashMap<String, Option<String>>,
+    /// The properties whose value is being substituted, each referred to by the previous.
+    resolving: Vec<String>,
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        if let Some(value) = self.resolved.get(name) {
+            return value.clone();
+        }
+        if let Some(at) = self.resolving.iter().position(|n| n == name) {
+            // Every property of the cycle is invalid, fallbacks or not.
+            self.cyclic.extend(self.resolving[at..].iter().cloned());
+            return None;
+        }
+        if self.resolving.len() >= MAX_NESTING {
+            return None;
+        }
+        let value = self.properties.get(name)?.to_owned();
+        self.resolving.push(name.to_owned());
+        let value = substitute(&value, &mut |name| self.resolve(name));
+        self.resolving.pop();
+        let value = value.filter(|_| !self.cyclic.contains(name));
+        self.resolved.insert(name.to_owned(), value.clone());
+        value
+    }
+}
@@ -0,0 +1,255 @@
+//! What changed on screen between two render trees, so a frame only repaints that.
+//!
+//! The damage of a snapshot against the one published before it is computed on the data
+//! thread when it's published. Each window remembers the last few frames' damage, as a
+//! backend's buffer may hold a frame from several frames ago; what it repaints is everything
+//! that changed since.
+
+use crate::hit_index::IndexedTree;
+use crate::layout::{Rect, RenderNode};
+use crate::Id;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Weak};
+
+/// Beyond this fraction of the window, a frame repaints all of it.
+const FULL_REPAINT_FRACTION: f64 = 0.6;
+
+/// How many frames back a window remembers damage; older buffers are repainted in full.
+const HISTORY: usize = 4;
+
+/// What a node may paint outside its box: a pixel of antialiasing, its border being inside
+/// it. Text overflowing its box isn't covered.
+const PAINT_MARGIN: f64 = 1.0;
+
+/// Where the node and its descendants paint.
+pub(crate) fn paint_extent(node: &RenderNode) -> Rect {
+    node.descendant_bounds.outset(PAINT_MARGIN)
+}
+
+fn index<'a>(node: &'a RenderNode, nodes: &mut HashMap<Id, &'a RenderNode>) {
+    nodes.insert(node.id, node);
+    for child in &node.children {
+        index(child, nodes);
+    }
+}
+
+/// Index the nodes of `old` and `new`, a node of the same id in both, except in subtrees the
+/// trees share, which paint the same.
+fn index_unshared<'a>(
+    old: &'a RenderNode,
+    new: &'a RenderNode,
+    old_nodes: &mut HashMap<Id, &'a RenderNode>,
+    new_nodes: &mut HashMap<Id, &'a RenderNode>,
+) {
+    old_nodes.insert(old.id, old);
+    new_nodes.insert(new.id, new);
+    let mut old_children: HashMap<Id, &Arc<RenderNode>> =
+        old.children.iter().map(|child| (child.id, child)).collect();
+    for new_child in &new.children {
+        match old_children.remove(&new_child.id) {
+            Some(old_child) if Arc::ptr_eq(old_child, new_child) => {}
+            Some(old_child) => index_unshared(old_child, new_child, old_nodes, new_nodes),
+            None => index(new_child, new_nodes),
+        }
+    }
+    for old_child in old_children.into_values() {
+        index(old_child, old_nodes);
+    }
+}
+
+/// Whether the node paints differently in itself, apart from changes of its children.
+fn paints_differently(old: &RenderNode, new: &RenderNode) -> bool {
+    old.bounds != new.bounds
+        || old.descendant_bounds != new.descendant_bounds
+        || old.text != new.text
+        || old.z_index != new.z_index
+        || !(Arc::ptr_eq(&old.style, &new.style) || old.style == new.style)
+}
+
+/// Whether the children both trees have are painted in another order, which matters where
+/// they overlap.
+fn reordered(old: &RenderNode, new: &RenderNode) -> bool {
+    let ids = |node: &RenderNode| -> HashSet<Id> { node.children.iter().map(|c| c.id).collect() };
+    let (old_ids, new_ids) = (ids(old), ids(new));
+    let order = |node: &RenderNode, others: &HashSet<Id>| -> Vec<Id> {
+        let (behind, in_front) = node.children_in_paint_order();
+        behind
+            .into_iter()
+            .chain(in_front)
+            .map(|child| child.id)
+            .filter(|id| others.contains(id))
+            .collect()
+    };
+    order(old, &new_ids) != order(new, &old_ids)
+}
+
+fn add(damage: &mut Option<Rect>, rect: Rect) {
+    *damage = Some(match damage {
+        Some(damage) => damage.union(&rect),
+        None => rect,
+    });
+}
+
+/// The region that looks different in `new` than in `old`, in logical pixels: where nodes
+/// were added, removed, moved, resized, restyled or given other text, before and after.
+/// `None` if both paint the same.
+pub(crate) fn compute_damage(old: &RenderNode, new: &RenderNode) -> Option<Rect> {
+    let (mut old_nodes, mut new_nodes) = (HashMap::new(), HashMap::new());
+    if old.id == new.id {
+        index_unshared(old, new, &mut old_nodes, &mut new_nodes);
+    } else {
+        index(old, &mut old_nodes);
+        index(new, &mut new_nodes);
+    }
+
+    let mut damage = None;
+    for (id, new_node) in &new_nodes {
+        match old_nodes.get(id) {
+            None => add(&mut damage, paint_extent(new_node)),
+            Some(old_node) => {
+                if paints_differently(old_node, new_node) || reordered(old_node, new_node) {
+                    add(&mut damage, paint_extent(old_node));
+                    add(&mut damage, paint_extent(new_node));
+                }
+            }
+        }
+    }
+    for (id, old_node) in &old_nodes {
+        if !new_nodes.contains_key(id) {
+            add(&mut damage, paint_extent(old_node));
+        }
+    }
+    damage
+}
+
+/// A snapshot's damage against the one published before it.
+pub(crate) struct TreeDamage {
+    previous: Weak<IndexedTree>,
+    /// `None` if nothing changed.
+    rect: Option<Rect>,
+}
+
+impl TreeDamage {
+    /// The damage of `tree` since `previous`, if there was a snapshot before.
+    pub fn new(previous: Option<&Arc<IndexedTree>>, tree: &RenderNode) -> Option<Self> {
+        let previous = previous?;
+        Some(Self {
+            previous: Arc::downgrade(previous),
+            rect: compute_damage(&previous.tree, tree),
+        })
+    }
+}
+
+/// What a window painted last, to tell what its next frame must repaint.
+#[derive(Default)]
+pub(crate) struct PaintedWindow {
+    tree: Weak<IndexedTree>,
+    /// Painted over the tree: the selection and the edited text, and the canvas scale.
+    overlay: Option<Overlay>,
+    /// What changed in each of the last frames, the latest first.
+    history: VecDeque<Change>,
+}
+
+/// What changed from one frame to the next.
+#[derive(Clone, Copy)]
+enum Change {
+    /// `None` if nothing did.
+    Region(Option<Rect>),
+    Everything,
+}
+
+/// What is painted over or instead of the tree's content.
+#[derive(Clone, PartialEq)]
+pub(crate) struct Overlay {
+    pub selection: Option<(Id, std::ops::Range<usize>)>,
+    pub editing: Option<crate::editing::EditedText>,
+    pub tooltip: Option<crate::tooltip::Tooltip>,
+    pub debug: Option<crate::debug_overlay::DebugOverlay>,
+    /// The panel of [`crate::Params::show_stats`].
+    pub stats: Option<RenderNode>,
+    pub scale: f32,
+}
+
+impl Overlay {
+    fn nodes(&self) -> impl Iterator<Item = Id> + '_ {
+        let selected = self.selection.as_ref().map(|(id, _)| *id);
+        selected
+            .into_iter()
+            .chain(self.editing.as_ref().map(|edited| edited.node))
+    }
+}
+
+impl PaintedWindow {
+    /// Record a frame of `tree` with `overlay`, drawn onto a buffer holding the frame from
+    /// `buffer_age` frames ago, or unknown content for 0. Returns the region to repaint, or
+    /// `None` to repaint everything.
+    pub fn frame(
+        &mut self,
+        tree: &Arc<IndexedTree>,
+        overlay: Overlay,
+        buffer_age: u32,
+    ) -> Option<Rect> {
+        let change = self.change(tree, &overlay);
+        self.tree = Arc::downgrade(tree);
+        self.overlay = Some(overlay);
+        self.history.push_front(change);
+        self.history.truncate(HISTORY);
+
+        let age = buffer_age as usize;
+        if age == 0 || age > self.history.len() {
+            return None;
+        }
+        let mut region = None;
+        for change in self.history.iter().take(age) {
+            match *change {
+                Change::Region(Some(rect)) => add(&mut region, rect),
+                Change::Region(None) => {}
+                Change::Everything => return None,
+            }
+        }
+        // Nothing changed since the buffer's frame: repaint nothing.
+        let region = region.unwrap_or_default();
+        let viewport = tree.tree.bounds;
+        let area = |rect: &Rect| rect.width.max(0.0) * rect.height.max(0.0);
+        (area(&region) <= FULL_REPAINT_FRACTION * area(&viewport)).then_some(region)
+    }
+
+    /// What changed since the last frame. Everything, unless the tree is the last one or
+    /// the one published right after it.
+    fn change(&self, tree: &Arc<IndexedTree>, overlay: &Overlay) -> Change {
+        let Some(last_overlay) = self.overlay.as_ref() else {
+            return Change::Everything;
+        };
+        // The debug overlay covers every node, and follows the pointer.
+        if last_overlay.scale != overlay.scale || last_overlay.debug.or(overlay.debug).is_some() {
+            return Change::Everything;
+        }
+        let mut change = if Weak::ptr_eq(&self.tree, &Arc::downgrade(tree)) {
+            None
+        } else {
+            match &tree.damage {
+                Some(damage) if Weak::ptr_eq(&damage.previous, &self.tree) => damage.rect,
+                _ => return Change::Everything,
+            }
+        };
+        if last_overlay != overlay {
+            for id in last_overlay.nodes().chain(overlay.nodes()) {
+                // Nodes that moved are damaged where they were anyway.
+                if let Some(node) = crate::selection::find_node(&tree.tree, id) {
+                    add(&mut change, node.bounds.outset(PAINT_MARGIN));
+                }
+            }
+            let tooltips = last_overlay.tooltip.iter().chain(&overlay.tooltip);
+            for tooltip in tooltips {
+                add(&mut change, paint_extent(&tooltip.node()));
+            }
+            for panel in last_overlay.stats.iter().chain(&overlay.stats) {
+                add(&mut change, paint_extent(panel));
+            }
+        }
+        Change::Region(change)
+    }
+}
+
+#[cfg(test)]
+mod damage_tests;
@@ -0,0 +1,235 @@
+use super::*;
+use crate::debug_overlay::DebugOverlay;
+use crate::layout::descendant_bounds;
+use crate::snapshot::Edges;
+use crate::style::{Rgba, Style};
+
+fn rect(x: f64, y: f64, width: f64, height: f64) -> Rect {
+    Rect {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+fn node(id: u64, bounds: Rect, style: Style, children: Vec<RenderNode>) -> RenderNode {
+    let children: Vec<_> = children.into_iter().map(Arc::new).collect();
+    RenderNode {
+        id: Id::from_u64(id),
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::resolved(&style.padding),
+        border_width: Edges::from(&style.used_border_width()),
+        descendant_bounds: descendant_bounds(bounds, &style, &children),
+        z_index: style.resolved_z_index(),
+        style: Arc::new(style),
+        text: None,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children,
+    }
+}
+
+fn leaf(id: u64, bounds: Rect) -> RenderNode {
+    node(id, bounds, Style::default(), Vec::new())
+}
+
+fn root(children: Vec<RenderNode>) -> RenderNode {
+    node(1, rect(0.0, 0.0, 100.0, 100.0), Style::default(), children)
+}
+
+/// A node's box with the pixel of antialiasing around it.
+fn painted(bounds: Rect) -> Rect {
+    bounds.outset(1.0)
+}
+
+#[test]
+fn identical_trees_have_no_damage() {
+    let tree = || root(vec![leaf(2, rect(10.0, 10.0, 10.0, 10.0))]);
+    assert_eq!(compute_damage(&tree(), &tree()), None);
+}
+
+#[test]
+fn a_moved_node_is_damaged_where_it_was_and_is() {
+    let old = root(vec![leaf(2, rect(10.0, 10.0, 10.0, 10.0))]);
+    let new = root(vec![leaf(2, rect(30.0, 10.0, 10.0, 10.0))]);
+    assert_eq!(
+        compute_damage(&old, &new),
+        Some(painted(rect(10.0, 10.0, 30.0, 10.0)))
+    );
+}
+
+#[test]
+fn a_restyled_node_is_damaged_in_place() {
+    let bounds = rect(10.0, 10.0, 10.0, 10.0);
+    let old = root(vec![leaf(2, bounds)]);
+    let style = Style {
+        background_color: Some(Rgba {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        }),
+        ..Style::default()
+    };
+    let new = root(vec![node(2, bounds, style, Vec::new())]);
+    assert_eq!(compute_damage(&old, &new), Some(painted(bounds)));
+}
+
+#[test]
+fn added_and_removed_nodes_are_damaged() {
+    let added = rect(50.0, 50.0, 10.0, 10.0);
+    let removed = rect(10.0, 10.0, 10.0, 10.0);
+    let old = root(vec![leaf(2, removed)]);
+    let new = root(vec![leaf(3, added)]);
+    assert_eq!(
+        compute_damage(&old, &new),
+        Some(painted(removed).union(&painted(added)))
+    );
+}
+
+#[test]
+fn reordered_siblings_damage_their_parent() {
+    let (a, b) = (rect(10.0, 10.0, 20.0, 20.0), rect(20.0, 20.0, 20.0, 20.0));
+    let old = root(vec![leaf(2, a), leaf(3, b)]);
+    let new = root(vec![leaf(3, b), leaf(2, a)]);
+    let damage = compute_damage(&old, &new).unwrap();
+    assert_eq!(damage, painted(old.descendant_bounds));
+}
+
+#[test]
+fn only_what_isnt_shared_is_compared() {
+    let kept = Arc::new(leaf(2, rect(10.0, 10.0, 10.0, 10.0)));
+    let tree = |child: RenderNode| {
+        let mut tree = root(vec![child]);
+        tree.children.insert(0, Arc::clone(&kept));
+        tree
+    };
+    let old = tree(leaf(3, rect(50.0, 50.0, 10.0, 10.0)));
+    let new = tree(leaf(3, rect(60.0, 50.0, 10.0, 10.0)));
+    assert_eq!(
+        compute_damage(&old, &new),
+        Some(painted(rect(50.0, 50.0, 20.0, 10.0)))
+    );
+}
+
+fn overlay() -> Overlay {
+    Overlay {
+        selection: None,
+        editing: None,
+        tooltip: None,
+        debug: None,
+        scale: 1.0,
+    }
+}
+
+/// The snapshot published after `previous`, with a leaf at `bounds`.
+fn publish(previous: Option<&Arc<IndexedTree>>, bounds: Rect) -> Arc<IndexedTree> {
+    let tree = Arc::new(root(vec![leaf(2, bounds)]));
+    Arc::new(IndexedTree::after(previous, tree, Arc::default()))
+}
+
+#[test]
+fn frames_repaint_what_changed_since_the_buffer_was_drawn() {
+    let start = rect(10.0, 10.0, 10.0, 10.0);
+    let moved = rect(20.0, 10.0, 10.0, 10.0);
+    let first = publish(None, start);
+    let second = publish(Some(&first), moved);
+    let third = publish(Some(&second), moved);
+
+    let mut window = PaintedWindow::default();
+    // Nothing is known about the first frame's buffer.
+    assert_eq!(window.frame(&first, overlay(), 1), None);
+    let moving = painted(rect(10.0, 10.0, 20.0, 10.0));
+    assert_eq!(window.frame(&second, overlay(), 1), Some(moving));
+    // Unchanged since the last frame, but the buffer is from the one before.
+    assert_eq!(window.frame(&third, overlay(), 2), Some(moving));
+    assert_eq!(window.frame(&third, overlay(), 1), Some(Rect::default()));
+    // A buffer of unknown content, or older than the history, is repainted in full.
+    assert_eq!(window.frame(&third, overlay(), 0), None);
+    assert_eq!(window.frame(&third, overlay(), HISTORY as u32 + 1), None);
+}
+
+#[test]
+fn skipped_snapshots_and_new_scales_repaint_everything() {
+    let first = publish(None, rect(10.0, 10.0, 10.0, 10.0));
+    let second = publish(Some(&first), rect(20.0, 10.0, 10.0, 10.0));
+    let third = publish(Some(&second), rect(30.0, 10.0, 10.0, 10.0));
+
+    let mut window = PaintedWindow::default();
+    window.frame(&first, overlay(), 1);
+    // The window never painted the second snapshot, which the third's damage is against.
+    assert_eq!(window.frame(&third, overlay(), 1), None);
+
+    let scaled = Overlay {
+        scale: 2.0,
+        ..overlay()
+    };
+    assert_eq!(window.frame(&third, scaled, 1), None);
+}
+
+#[test]
+fn the_debug_overlay_repaints_everything_until_it_is_hidden() {
+    let tree = publish(None, rect(10.0, 10.0, 10.0, 10.0));
+    let debugging = Overlay {
+        debug: Some(DebugOverlay {
+            pointer: Some((15.0, 15.0)),
+        }),
+        ..overlay()
+    };
+
+    let mut window = PaintedWindow::default();
+    window.frame(&tree, overlay(), 1);
+    assert_eq!(window.frame(&tree, debugging.clone(), 1), None);
+    assert_eq!(window.frame(&tree, debugging, 1), None);
+    // Hiding it repaints what it covered.
+    assert_eq!(window.frame(&tree, overlay(), 1), None);
+    assert_eq!(window.frame(&tree, overlay(), 1), Some(Rect::default()));
+}
+
+#[test]
+fn large_damage_repaints_everything() {
+    let first = publish(None, rect(0.0, 0.0, 10.0, 10.0));
+    let second = publish(Some(&first), rect(0.0, 0.0, 90.0, 90.0));
+
+    let mut window = PaintedWindow::default();
+    window.frame(&first, overlay(), 1);
+    assert_eq!(window.frame(&second, overlay(), 1), None);
+}
+
+#[test]
+fn a_new_selection_damages_its_node() {
+    let bounds = rect(10.0, 10.0, 10.0, 10.0);
+    let tree = publish(None, bounds);
+
+    let mut window = PaintedWindow::default();
+    window.frame(&tree, overlay(), 1);
+    let selecting = Overlay {
+        selection: Some((Id::from_u64(2), 0..1)),
+        ..overlay()
+    };
+    assert_eq!(window.frame(&tree, selecting, 1), Some(painted(bounds)));
+}
+
+#[test]
+fn new_stats_damage_the_panel() {
+    let tree = publish(None, rect(50.0, 50.0, 10.0, 10.0));
+    let showing = |width| Overlay {
+        stats: Some(leaf(3, rect(5.0, 80.0, width, 15.0))),
+        ..overlay()
+    };
+
+    let mut window = PaintedWindow::default();
+    window.frame(&tree, showing(30.0), 1);
+    assert_eq!(window.frame(&tree, showing(30.0), 1), Some(Rect::default()));
+    // Where the old panel was too, for narrower stats.
+    assert_eq!(
+        window.frame(&tree, showing(20.0), 1),
+        Some(painted(rect(5.0, 80.0, 30.0, 15.0)))
+    );
+}
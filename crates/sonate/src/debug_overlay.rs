@@ -0,0 +1,321 @@
+//! The layout debugging overlay, painted over a window's frame while F12 toggles it on: the
+//! border box of every node outlined, its margin and padding tinted the way browser
+//! developer tools do, and the computed style of the node under the pointer in a panel.
+//!
+//! Like tooltips, it's painted over the tree and isn't part of it, see
+//! [`crate::painter::Painter::with_debug_overlay`].
+
+use crate::layout::{Rect, RenderNode};
+use crate::snapshot::Edges;
+use crate::style::{AspectRatio, Directional, GridColumn, GridTrack, Length, Rgba, Style, ZIndex};
+use crate::text::{FontSpec, TextMeasurer};
+use crate::Id;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// The overlay as painted over a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct DebugOverlay {
+    /// Where the pointer is in the window, in logical pixels. The node under it has its
+    /// content box tinted too, and its style shown.
+    pub pointer: Option<(f64, f64)>,
+}
+
+pub(crate) const MARGIN_TINT: Rgba = Rgba {
+    r: 246,
+    g: 178,
+    b: 107,
+    a: 102,
+};
+
+pub(crate) const PADDING_TINT: Rgba = Rgba {
+    r: 147,
+    g: 196,
+    b: 125,
+    a: 102,
+};
+
+pub(crate) const CONTENT_TINT: Rgba = Rgba {
+    r: 111,
+    g: 168,
+    b: 220,
+    a: 102,
+};
+
+pub(crate) const OUTLINE_COLOR: Rgba = Rgba {
+    r: 255,
+    g: 0,
+    b: 255,
+    a: 160,
+};
+
+const PANEL_BACKGROUND: Rgba = Rgba {
+    r: 32,
+    g: 32,
+    b: 32,
+    a: 230,
+};
+
+const PANEL_TEXT: Rgba = Rgba {
+    r: 255,
+    g: 255,
+    b: 255,
+    a: 255,
+};
+
+const PANEL_FONT_SIZE: f64 = 12.0;
+
+/// Between the panel's edge and its text, and the panel and the window's.
+pub(crate) const PANEL_PADDING: f64 = 6.0;
+
+/// The id the panel's nodes are painted with, the tooltip's: no node has it.
+const PANEL_ID: Id = Id(u64::MAX);
+
+/// The areas tinted around and in `node`, each as its outer and inner edge: the margin,
+/// between the margin edge and the border box, then the padding, between the padding box
+/// and the content box.
+pub(crate) fn tinted_areas(node: &RenderNode) -> [(Rect, Rect, Rgba); 2] {
+    let border_box = node.border_box();
+    [
+        (border_box.outset_by(&node.margin), border_box, MARGIN_TINT),
+        (node.padding_box(), node.content_box(), PADDING_TINT),
+    ]
+}
+
+fn px(length: &Length) -> String {
+    match length {
+        Length::Px(value) => format!("{value}px"),
+        Length::Percent(value) => format!("{value}%"),
+        Length::Auto => "auto".to_owned(),
+        other => format!("{other:?}"),
+    }
+}
+
+fn color(color: &Rgba) -> String {
+    let Rgba { r, g, b, a } = color;
+    format!("rgba({r}, {g}, {b}, {a})")
+}
+
+fn sides(edges: &Edges) -> String {
+    let Edges {
+        top,
+        right,
+        bottom,
+        left,
+    } = edges;
+    format!("{top} {right} {bottom} {left}")
+}
+
+fn directional(lengths: &Directional<Option<Length>>) -> Option<String> {
+    let sides = [&lengths.top, &lengths.right, &lengths.bottom, &lengths.left];
+    if sides.iter().all(|side| side.is_none()) {
+        return None;
+    }
+    let sides: Vec<_> = sides
+        .iter()
+        .map(|side| side.as_ref().map_or("-".to_owned(), px))
+        .collect();
+    Some(sides.join(" "))
+}
+
+fn tracks(tracks: &[GridTrack]) -> String {
+    if tracks.is_empty() {
+        return "none".to_owned();
+    }
+    let tracks: Vec<String> = tracks
+        .iter()
+        .map(|track| match track {
+            GridTrack::Length(length) => px(length),
+            GridTrack::Fr(fr) => format!("{fr}fr"),
+        })
+        .collect();
+    tracks.join(" ")
+}
+
+fn debug(value: &impl Debug) -> String {
+    format!("{value:?}").to_lowercase()
+}
+
+/// What the panel shows for `node`: its boxes, then the properties its style sets.
+pub(crate) fn style_lines(node: &RenderNode) -> Vec<String> {
+    let style = &node.style;
+    let Rect {
+        x,
+        y,
+        width,
+        height,
+    } = node.bounds;
+    let mut lines = vec![
+        format!("#{}  {width} × {height} at {x}, {y}", node.id.as_u64()),
+        format!("margin  {}", sides(&node.margin)),
+        format!("border  {}", sides(&node.border_width)),
+        format!("padding  {}", sides(&node.padding)),
+    ];
+
+    let properties = [
+        ("display", style.display.as_ref().map(debug)),
+        ("box-sizing", style.box_sizing.as_ref().map(debug)),
+        ("width", style.width.as_ref().map(px)),
+        ("height", style.height.as_ref().map(px)),
+        (
+            "aspect-ratio",
+            style.aspect_ratio.map(|ratio| match ratio {
+                AspectRatio::Auto => "auto".to_owned(),
+                AspectRatio::Ratio(ratio) => ratio.to_string(),
+            }),
+        ),
+        ("margin", directional(&style.margin)),
+        ("padding", directional(&style.padding)),
+        ("flex-direction", style.flex_direction.as_ref().map(debug)),
+        ("flex-wrap", style.flex_wrap.as_ref().map(debug)),
+        ("justify-content", style.justify_content.as_ref().map(debug)),
+        ("align-items", style.align_items.as_ref().map(debug)),
+        ("align-content", style.align_content.as_ref().map(debug)),
+        ("align-self", style.align_self.as_ref().map(debug)),
+        ("flex-grow", style.flex_grow.map(|grow| grow.to_string())),
+        (
+            "flex-shrink",
+            style.flex_shrink.map(|shrink| shrink.to_string()),
+        ),
+        ("flex-basis", style.flex_basis.as_ref().map(px)),
+        ("row-gap", style.row_gap.as_ref().map(px)),
+        ("column-gap", style.column_gap.as_ref().map(px)),
+        ("order", style.order.map(|order| order.to_string())),
+        (
+            "grid-template-columns",
+            style.grid_template_columns.as_deref().map(tracks),
+        ),
+        (
+            "grid-template-rows",
+            style.grid_template_rows.as_deref().map(tracks),
+        ),
+        (
+            "grid-column",
+            style.grid_column.map(|column| match column {
+                GridColumn::Auto => "auto".to_owned(),
+                GridColumn::Span(span) => format!("span {span}"),
+            }),
+        ),
+        (
+            "font-family",
+            style
+                .font_family
+                .as_ref()
+                .map(|families| families.join(", ")),
+        ),
+        ("font-size", style.font_size.as_ref().map(px)),
+        ("color", style.color.as_ref().map(color)),
+        (
+            "background-color",
+            style.background_color.as_ref().map(color),
+        ),
+        ("opacity", style.opacity.map(|opacity| opacity.to_string())),
+        ("overflow", style.overflow.as_ref().map(debug)),
+        (
+            "z-index",
+            style.z_index.map(|z_index| match z_index {
+                ZIndex::Auto => "auto".to_owned(),
+                ZIndex::Integer(z_index) => z_index.to_string(),
+            }),
+        ),
+    ];
+    lines.extend(
+        properties
+            .into_iter()
+            .filter_map(|(name, value)| Some(format!("{name}: {}", value?))),
+    );
+    lines
+}
+
+/// The panel showing `lines`, a node with one text child a line, in the top corner of
+/// `window` farther from the pointer at `x`.
+pub(crate) fn panel(
+    lines: Vec<String>,
+    measurer: &dyn TextMeasurer,
+    x: f64,
+    window: Rect,
+) -> RenderNode {
+    panel_at(lines, measurer, |width, _| {
+        let left = if x < window.x + window.width / 2.0 {
+            window.x + window.width - width - PANEL_PADDING
+        } else {
+            window.x + PANEL_PADDING
+        };
+        (left, window.y + PANEL_PADDING)
+    })
+}
+
+/// A panel showing `lines`, at where `place` puts the top left corner of a panel of the
+/// given width and height.
+pub(crate) fn panel_at(
+    lines: Vec<String>,
+    measurer: &dyn TextMeasurer,
+    place: impl FnOnce(f64, f64) -> (f64, f64),
+) -> RenderNode {
+    let style = Style {
+        color: Some(PANEL_TEXT),
+        font_size: Some(Length::Px(PANEL_FONT_SIZE)),
+        ..Style::default()
+    };
+    let font = FontSpec::from_style(&style);
+    let style = Arc::new(style);
+
+    let sizes: Vec<_> = lines
+        .iter()
+        .map(|line| measurer.measure_unwrapped(line, &font))
+        .collect();
+    let width = sizes.iter().map(|size| size.width).fold(0.0, f64::max) + 2.0 * PANEL_PADDING;
+    let height = sizes.iter().map(|size| size.height).sum::<f64>() + 2.0 * PANEL_PADDING;
+    let (left, top) = place(width, height);
+    let bounds = Rect::new(left, top, width, height);
+
+    let mut top = bounds.y + PANEL_PADDING;
+    let children = lines
+        .into_iter()
+        .zip(sizes)
+        .map(|(line, size)| {
+            let line_bounds = Rect::new(bounds.x + PANEL_PADDING, top, size.width, size.height);
+            top += size.height;
+            Arc::new(text_node(
+                line_bounds,
+                Some(line),
+                style.clone(),
+                Vec::new(),
+            ))
+        })
+        .collect();
+    let panel_style = Arc::new(Style {
+        background_color: Some(PANEL_BACKGROUND),
+        ..Style::default()
+    });
+    text_node(bounds, None, panel_style, children)
+}
+
+fn text_node(
+    bounds: Rect,
+    text: Option<String>,
+    style: Arc<Style>,
+    children: Vec<Arc<RenderNode>>,
+) -> RenderNode {
+    RenderNode {
+        id: PANEL_ID,
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::default(),
+        border_width: Edges::default(),
+        descendant_bounds: bounds,
+        z_index: 0,
+        style,
+        text,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod debug_overlay_tests;
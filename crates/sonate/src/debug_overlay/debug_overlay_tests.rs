@@ -0,0 +1,91 @@
+use super::{panel, style_lines, tinted_areas, MARGIN_TINT, PADDING_TINT};
+use crate::layout::{Rect, RenderNode};
+use crate::text::TestTextMeasurer;
+use crate::{Engine, Id};
+use std::sync::Arc;
+
+/// The laid out tree of a row holding a boxed cell, and the cell.
+fn boxed_cell() -> (Arc<RenderNode>, Id) {
+    let engine = Engine::new();
+    engine.add_stylesheet(
+        ".row { display: flex; flex-direction: row; padding: 10px; }
+         .cell { width: 40px; height: 20px; margin: 2px 4px; border: 1px solid; padding: 3px; }",
+    );
+    let root = engine.root_id();
+    let row = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, row);
+    engine.set_attribute(row, "class".to_owned(), "row".to_owned());
+    let cell = engine.create_node(Id::from_u64(2), None);
+    engine.set_parent(row, cell);
+    engine.set_attribute(cell, "class".to_owned(), "cell".to_owned());
+    engine.layout_headless(400.0, 100.0).unwrap();
+    let snapshot = engine.get_current_snapshot().unwrap();
+    (Arc::clone(&snapshot.tree), cell)
+}
+
+#[test]
+fn margins_and_padding_are_tinted_around_the_content() {
+    let (tree, cell) = boxed_cell();
+    let cell = crate::selection::find_node(&tree, cell).unwrap();
+    let bounds = cell.bounds;
+    assert_eq!(bounds.width, 48.0);
+
+    let [(margin_edge, border_box, margin), (padding_box, content, padding)] = tinted_areas(cell);
+    assert_eq!((margin, padding), (MARGIN_TINT, PADDING_TINT));
+    assert_eq!(border_box, bounds);
+    assert_eq!(
+        margin_edge,
+        Rect::new(
+            bounds.x - 4.0,
+            bounds.y - 2.0,
+            bounds.width + 8.0,
+            bounds.height + 4.0
+        )
+    );
+    assert_eq!(
+        padding_box,
+        Rect::new(bounds.x + 1.0, bounds.y + 1.0, 46.0, 26.0)
+    );
+    assert_eq!(
+        content,
+        Rect::new(bounds.x + 4.0, bounds.y + 4.0, 40.0, 20.0)
+    );
+    assert_eq!(cell.content_box(), content);
+}
+
+#[test]
+fn the_panel_lists_the_boxes_and_the_style_set() {
+    let (tree, cell) = boxed_cell();
+    let cell = crate::selection::find_node(&tree, cell).unwrap();
+    let lines = style_lines(cell);
+    let Rect { x, y, .. } = cell.bounds;
+    assert_eq!(lines[0], format!("#2  48 × 28 at {x}, {y}"));
+    assert_eq!(
+        &lines[1..4],
+        ["margin  2 4 2 4", "border  1 1 1 1", "padding  3 3 3 3"]
+    );
+    assert!(lines.contains(&"width: 40px".to_owned()));
+    assert!(lines.contains(&"margin: 2px 4px 2px 4px".to_owned()));
+    // What the style doesn't set isn't listed.
+    assert!(!lines.iter().any(|line| line.starts_with("flex-grow")));
+
+    let row = &tree.children[0];
+    let lines = style_lines(row);
+    assert!(lines.contains(&"display: flex".to_owned()));
+    assert!(lines.contains(&"flex-direction: row".to_owned()));
+}
+
+#[test]
+fn the_panel_keeps_away_from_the_pointer() {
+    let window = Rect::new(0.0, 0.0, 400.0, 300.0);
+    let lines = || vec!["ab".to_owned(), "abcd".to_owned()];
+    // 12px text is 7.2px a character and 14.4px a line, with 6px around it.
+    let left = panel(lines(), &TestTextMeasurer, 10.0, window);
+    assert_eq!(left.bounds, Rect::new(400.0 - 40.8 - 6.0, 6.0, 40.8, 40.8));
+    assert_eq!(left.children.len(), 2);
+    assert_eq!(left.children[1].text.as_deref(), Some("abcd"));
+    assert_eq!(left.children[1].bounds.y, 6.0 + 6.0 + 14.4);
+
+    let right = panel(lines(), &TestTextMeasurer, 390.0, window);
+    assert_eq!(right.bounds.x, 6.0);
+}
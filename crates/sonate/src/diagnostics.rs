@@ -0,0 +1,183 @@
+use crate::Id;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::{Duration, Instant};
+
+/// How long repeats of the same diagnostic are suppressed.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(1);
+
+/// At most this many diagnostics with the same code are delivered per window, whatever
+/// their nodes or rules, so a large broken stylesheet can't flood subscribers.
+const MAX_PER_CODE_PER_WINDOW: u32 = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// What a [`Diagnostic`] is about. [`DiagnosticCode::as_str`] gives a stable identifier
+/// that hosts can match on; new codes may be added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiagnosticCode {
+    /// A rule or at-rule couldn't be parsed and was skipped.
+    CssSyntaxError,
+    /// A declaration names a property Sonate doesn't support.
+    CssUnknownProperty,
+    /// A supported property has a value that couldn't be parsed.
+    CssInvalidValue,
+    /// A value is outside the range Sonate supports and will be clamped.
+    CssValueClamped,
+    /// An `@media` condition tests a feature Sonate doesn't support, so it never matches.
+    CssUnsupportedMediaFeature,
+    /// A mutation refers to a node that doesn't exist.
+    UnknownNode,
+    /// `set_parent` with the same node as parent and child.
+    InvalidParent,
+    /// `set_parent` would have made a node its own ancestor.
+    ParentCycle,
+    /// `insert_before` or `move_child` with a node that isn't a child of the parent.
+    NotAChild,
+    /// A node was to be created with the id of another.
+    IdInUse,
+    /// An image loader returned no data.
+    ImageLoadFailed,
+    /// Image data couldn't be decoded.
+    ImageDecodeFailed,
+    /// Font data couldn't be loaded.
+    InvalidFontData,
+}
+
+impl DiagnosticCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::CssSyntaxError => "css-syntax-error",
+            DiagnosticCode::CssUnknownProperty => "css-unknown-property",
+            DiagnosticCode::CssInvalidValue => "css-invalid-value",
+            DiagnosticCode::CssValueClamped => "css-value-clamped",
+            DiagnosticCode::CssUnsupportedMediaFeature => "css-unsupported-media-feature",
+            DiagnosticCode::UnknownNode => "unknown-node",
+            DiagnosticCode::InvalidParent => "invalid-parent",
+            DiagnosticCode::ParentCycle => "parent-cycle",
+            DiagnosticCode::NotAChild => "not-a-child",
+            DiagnosticCode::IdInUse => "id-in-use",
+            DiagnosticCode::ImageLoadFailed => "image-load-failed",
+            DiagnosticCode::ImageDecodeFailed => "image-decode-failed",
+            DiagnosticCode::InvalidFontData => "invalid-font-data",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            DiagnosticCode::CssValueClamped => Severity::Info,
+            DiagnosticCode::CssSyntaxError
+            | DiagnosticCode::CssUnknownProperty
+            | DiagnosticCode::CssInvalidValue
+            | DiagnosticCode::CssUnsupportedMediaFeature
+            | DiagnosticCode::ImageLoadFailed
+            | DiagnosticCode::ImageDecodeFailed
+            | DiagnosticCode::InvalidFontData => Severity::Warning,
+            DiagnosticCode::UnknownNode
+            | DiagnosticCode::InvalidParent
+            | DiagnosticCode::ParentCycle
+            | DiagnosticCode::NotAChild
+            | DiagnosticCode::IdInUse => Severity::Error,
+        }
+    }
+}
+
+/// A problem detected by the engine, delivered through [`crate::Engine::subscribe_diagnostics`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: DiagnosticCode,
+    pub message: String,
+    /// The node the problem is about, if any.
+    pub node: Option<Id>,
+    /// Index of the stylesheet rule the problem is about, counting rules of all stylesheets
+    /// added to the engine in order.
+    pub rule_index: Option<usize>,
+}
+
+/// Fans diagnostics out to subscribers, rate-limited per (code, node, rule).
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    has_subscribers: AtomicBool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    subscribers: Vec<Sender<Diagnostic>>,
+    window_start: Option<Instant>,
+    seen: HashSet<(DiagnosticCode, Option<Id>, Option<usize>)>,
+    per_code: HashMap<DiagnosticCode, u32>,
+}
+
+impl Diagnostics {
+    pub fn subscribe(&self) -> Receiver<Diagnostic> {
+        let (tx, rx) = channel();
+        let mut state = self.state.lock();
+        state.subscribers.push(tx);
+        self.has_subscribers.store(true, Ordering::Release);
+        rx
+    }
+
+    /// Deliver a diagnostic unless it was rate-limited. `message` is only called when there
+    /// are subscribers, so reporting is free otherwise.
+    pub fn report(
+        &self,
+        code: DiagnosticCode,
+        node: Option<Id>,
+        rule_index: Option<usize>,
+        message: impl FnOnce() -> String,
+    ) {
+        if !self.has_subscribers.load(Ordering::Acquire) {
+            return;
+        }
+
+        let mut state = self.state.lock();
+
+        let now = Instant::now();
+        if state
+            .window_start
+            .is_none_or(|start| now.duration_since(start) >= RATE_LIMIT_WINDOW)
+        {
+            state.window_start = Some(now);
+            state.seen.clear();
+            state.per_code.clear();
+        }
+
+        if !state.seen.insert((code, node, rule_index)) {
+            return;
+        }
+        let count = state.per_code.entry(code).or_default();
+        if *count >= MAX_PER_CODE_PER_WINDOW {
+            return;
+        }
+        *count += 1;
+
+        let diagnostic = Diagnostic {
+            severity: code.severity(),
+            code,
+            message: message(),
+            node,
+            rule_index,
+        };
+
+        // Receivers that were dropped unsubscribe; sending never blocks.
+        state
+            .subscribers
+            .retain(|subscriber| subscriber.send(diagnostic.clone()).is_ok());
+        if state.subscribers.is_empty() {
+            self.has_subscribers.store(false, Ordering::Release);
+        }
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests;
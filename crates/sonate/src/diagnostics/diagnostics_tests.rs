@@ -0,0 +1,173 @@
+use super::{DiagnosticCode, Diagnostics, Severity, MAX_PER_CODE_PER_WINDOW};
+use crate::{Diagnostic, DocumentErrorKind, Engine, EngineError, Id};
+use std::sync::mpsc::Receiver;
+
+/// Everything reported up to now. A headless layout is processed after every earlier command,
+/// so it doubles as a barrier for the data thread.
+fn drain(engine: &Engine, diagnostics: &Receiver<Diagnostic>) -> Vec<Diagnostic> {
+    engine.layout_headless(100.0, 100.0).unwrap();
+    diagnostics.try_iter().collect()
+}
+
+#[test]
+fn unknown_property_is_reported_with_its_rule() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".a { width: 10px; }");
+    let diagnostics = engine.subscribe_diagnostics();
+
+    engine.add_stylesheet(".b { height: 10px; } .c { colr: red; }");
+
+    let reported = drain(&engine, &diagnostics);
+    assert_eq!(reported.len(), 1, "{reported:?}");
+    assert_eq!(reported[0].code, DiagnosticCode::CssUnknownProperty);
+    assert_eq!(reported[0].code.as_str(), "css-unknown-property");
+    assert_eq!(reported[0].severity, Severity::Warning);
+    assert_eq!(reported[0].node, None);
+    // Counting the rule of the first stylesheet.
+    assert_eq!(reported[0].rule_index, Some(2));
+}
+
+#[test]
+fn set_attribute_on_unknown_node_is_reported() {
+    let engine = Engine::new();
+    let diagnostics = engine.subscribe_diagnostics();
+
+    engine.set_attribute(Id::from_u64(42), "class".to_owned(), "a".to_owned());
+
+    let reported = drain(&engine, &diagnostics);
+    assert_eq!(reported.len(), 1, "{reported:?}");
+    assert_eq!(reported[0].code, DiagnosticCode::UnknownNode);
+    assert_eq!(reported[0].severity, Severity::Error);
+    assert_eq!(reported[0].node, Some(Id::from_u64(42)));
+}
+
+#[test]
+fn parent_cycle_is_reported_and_rejected() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    let outer = engine.create_node(Id::from_u64(1), None);
+    let inner = engine.create_node(Id::from_u64(2), None);
+    engine.set_parent(root, outer);
+    engine.set_parent(outer, inner);
+    let diagnostics = engine.subscribe_diagnostics();
+
+    engine.set_parent(inner, outer);
+
+    let reported = drain(&engine, &diagnostics);
+    assert_eq!(reported.len(), 1, "{reported:?}");
+    assert_eq!(reported[0].code, DiagnosticCode::ParentCycle);
+    assert_eq!(reported[0].node, Some(outer));
+
+    // The tree is unchanged.
+    let snapshot = engine.snapshot().unwrap();
+    assert_eq!(snapshot.children.len(), 1);
+    assert_eq!(snapshot.children[0].id, outer);
+    assert_eq!(snapshot.children[0].children[0].id, inner);
+}
+
+#[test]
+fn reordering_by_a_node_that_is_not_a_child_is_refused_and_reported() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    let list = engine.create_node(Id::from_u64(1), None);
+    let item = engine.create_node(Id::from_u64(2), None);
+    let stray = engine.create_node(Id::from_u64(3), None);
+    engine.set_parent(root, list);
+    engine.set_parent(list, item);
+    let diagnostics = engine.subscribe_diagnostics();
+
+    assert!(matches!(
+        engine.insert_before(list, stray, root),
+        Err(EngineError::Document {
+            kind: DocumentErrorKind::NotAChild,
+            ..
+        })
+    ));
+    assert!(matches!(
+        engine.move_child(list, stray, 0),
+        Err(EngineError::Document {
+            kind: DocumentErrorKind::NotAChild,
+            ..
+        })
+    ));
+    engine.insert_before(list, stray, item).unwrap();
+
+    let reported = drain(&engine, &diagnostics);
+    assert_eq!(reported.len(), 2, "{reported:?}");
+    assert_eq!(reported[0].code, DiagnosticCode::NotAChild);
+    assert_eq!(reported[0].node, Some(root));
+    assert_eq!(reported[1].node, Some(stray));
+    let snapshot = engine.snapshot().unwrap();
+    let children: Vec<Id> = snapshot.children[0].children.iter().map(|c| c.id).collect();
+    assert_eq!(children, [stray, item]);
+}
+
+#[test]
+fn repeats_are_rate_limited() {
+    let engine = Engine::new();
+    let diagnostics = engine.subscribe_diagnostics();
+
+    for _ in 0..10 {
+        engine.set_attribute(Id::from_u64(7), "class".to_owned(), "a".to_owned());
+    }
+    engine.set_attribute(Id::from_u64(8), "class".to_owned(), "a".to_owned());
+
+    let nodes: Vec<_> = drain(&engine, &diagnostics)
+        .into_iter()
+        .map(|diagnostic| diagnostic.node)
+        .collect();
+    assert_eq!(nodes, vec![Some(Id::from_u64(7)), Some(Id::from_u64(8))]);
+}
+
+#[test]
+fn distinct_problems_are_capped_per_code() {
+    let diagnostics = Diagnostics::default();
+    let rx = diagnostics.subscribe();
+
+    for i in 0..100 {
+        diagnostics.report(
+            DiagnosticCode::UnknownNode,
+            Some(Id::from_u64(i)),
+            None,
+            || "unknown".to_owned(),
+        );
+    }
+    diagnostics.report(DiagnosticCode::ParentCycle, None, None, || {
+        "cycle".to_owned()
+    });
+
+    let reported: Vec<_> = rx.try_iter().collect();
+    assert_eq!(reported.len(), MAX_PER_CODE_PER_WINDOW as usize + 1);
+    assert_eq!(reported.last().unwrap().code, DiagnosticCode::ParentCycle);
+}
+
+#[test]
+fn messages_are_not_built_without_subscribers() {
+    let diagnostics = Diagnostics::default();
+    diagnostics.report(DiagnosticCode::UnknownNode, None, None, || {
+        panic!("no one is listening")
+    });
+
+    drop(diagnostics.subscribe());
+    // The first report after the receiver is dropped finds out and unsubscribes it.
+    diagnostics.report(DiagnosticCode::UnknownNode, None, None, String::new);
+    diagnostics.report(DiagnosticCode::InvalidParent, None, None, || {
+        panic!("no one is listening")
+    });
+}
+
+#[test]
+fn dropped_receiver_does_not_block_the_data_thread() {
+    let engine = Engine::new();
+    drop(engine.subscribe_diagnostics());
+
+    for i in 0..1000 {
+        engine.set_attribute(Id::from_u64(100 + i), "class".to_owned(), "a".to_owned());
+    }
+
+    // Still processing commands.
+    let label = engine.create_node(Id::from_u64(1), Some("still here".to_owned()));
+    engine.set_parent(engine.root_id(), label);
+    let snapshot = engine.layout_headless(100.0, 100.0).unwrap();
+    assert!(snapshot.find(label).is_some());
+}
@@ -0,0 +1,133 @@
+use crate::{Id, WindowId};
+use std::time::{Duration, Instant};
+
+/// How long the caret stays shown, and then hidden.
+pub(crate) const CARET_BLINK: Duration = Duration::from_millis(530);
+
+/// A key press that edits the focused editable node, see [`Editing::apply`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum EditKey {
+    /// Typed text, inserted at the caret.
+    Insert(String),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+}
+
+/// The focused node's value as painted, with the caret while it's shown.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct EditedText {
+    pub node: Id,
+    pub text: String,
+    pub caret: Option<usize>,
+}
+
+/// The editable node with focus: a node with `editable="true"` the user clicked. Holds the
+/// value being edited, which the document catches up with on the data thread.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Editing {
+    pub window: WindowId,
+    pub node: Id,
+    pub text: String,
+    /// Before the `caret`th character.
+    pub caret: usize,
+    /// When the caret last moved; it blinks from then on, shown first.
+    pub moved_at: Instant,
+}
+
+impl Editing {
+    pub fn new(window: WindowId, node: Id, text: String, caret: usize, now: Instant) -> Self {
+        let caret = caret.min(text.chars().count());
+        Self {
+            window,
+            node,
+            text,
+            caret,
+            moved_at: now,
+        }
+    }
+
+    /// Edit the text or move the caret. Returns whether the text changed.
+    pub fn apply(&mut self, key: &EditKey, now: Instant) -> bool {
+        let len = self.text.chars().count();
+        let changed = match key {
+            EditKey::Insert(text) => {
+                // The value is a single line.
+                let text: String = text.chars().filter(|c| !c.is_control()).collect();
+                let offset = self.byte_offset(self.caret);
+                self.text.insert_str(offset, &text);
+                self.caret += text.chars().count();
+                !text.is_empty()
+            }
+            EditKey::Backspace if self.caret > 0 => {
+                self.caret -= 1;
+                self.text.remove(self.byte_offset(self.caret));
+                true
+            }
+            EditKey::Delete if self.caret < len => {
+                self.text.remove(self.byte_offset(self.caret));
+                true
+            }
+            EditKey::Backspace | EditKey::Delete => false,
+            EditKey::Left => {
+                self.caret = self.caret.saturating_sub(1);
+                false
+            }
+            EditKey::Right => {
+                self.caret = (self.caret + 1).min(len);
+                false
+            }
+            EditKey::Home => {
+                self.caret = 0;
+                false
+            }
+            EditKey::End => {
+                self.caret = len;
+                false
+            }
+        };
+        self.moved_at = now;
+        changed
+    }
+
+    /// Replace the value, e.g. because the host set the node's text, keeping the caret
+    /// where it was if the new value is long enough.
+    pub fn set_text(&mut self, text: String) {
+        self.caret = self.caret.min(text.chars().count());
+        self.text = text;
+    }
+
+    pub fn painted(&self, now: Instant) -> EditedText {
+        EditedText {
+            node: self.node,
+            text: self.text.clone(),
+            caret: self.caret_visible(now).then_some(self.caret),
+        }
+    }
+
+    /// Whether the caret is shown at `now`.
+    pub fn caret_visible(&self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.moved_at);
+        (elapsed.as_nanos() / CARET_BLINK.as_nanos()).is_multiple_of(2)
+    }
+
+    /// When the caret next appears or disappears after `now`.
+    pub fn next_blink(&self, now: Instant) -> Instant {
+        let elapsed = now.saturating_duration_since(self.moved_at);
+        let blinks = elapsed.as_nanos() / CARET_BLINK.as_nanos() + 1;
+        self.moved_at + CARET_BLINK * blinks as u32
+    }
+
+    fn byte_offset(&self, index: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(index)
+            .map_or(self.text.len(), |(offset, _)| offset)
+    }
+}
+
+#[cfg(test)]
+mod editing_tests;
@@ -0,0 +1,152 @@
+use super::{EditKey, Editing, CARET_BLINK};
+use crate::selection::find_node;
+use crate::{Engine, Id, WindowId};
+use std::time::Instant;
+
+fn editing(text: &str, caret: usize) -> Editing {
+    Editing::new(
+        WindowId::PRIMARY,
+        Id::from_u64(1),
+        text.to_owned(),
+        caret,
+        Instant::now(),
+    )
+}
+
+fn insert(text: &str) -> EditKey {
+    EditKey::Insert(text.to_owned())
+}
+
+#[test]
+fn keys_edit_the_text_at_the_caret() {
+    let now = Instant::now();
+    let mut editing = editing("ac", 1);
+
+    assert!(editing.apply(&insert("b"), now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("abc", 2));
+    assert!(editing.apply(&EditKey::Backspace, now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("ac", 1));
+    assert!(editing.apply(&EditKey::Delete, now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("a", 1));
+
+    // Nothing left to delete on that side.
+    assert!(!editing.apply(&EditKey::Delete, now));
+    assert!(!editing.apply(&EditKey::Home, now));
+    assert!(!editing.apply(&EditKey::Backspace, now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("a", 0));
+
+    // The value stays on one line.
+    assert!(editing.apply(&insert("x\ny"), now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("xya", 2));
+    assert!(!editing.apply(&insert("\t"), now));
+}
+
+#[test]
+fn the_caret_moves_by_characters() {
+    let now = Instant::now();
+    let mut editing = editing("héllo", 9);
+    assert_eq!(editing.caret, 5);
+
+    for (key, caret) in [
+        (EditKey::Left, 4),
+        (EditKey::Home, 0),
+        (EditKey::Left, 0),
+        (EditKey::Right, 1),
+        (EditKey::Right, 2),
+        (EditKey::End, 5),
+        (EditKey::Right, 5),
+    ] {
+        assert!(!editing.apply(&key, now));
+        assert_eq!(editing.caret, caret, "after {key:?}");
+    }
+
+    editing.caret = 2;
+    assert!(editing.apply(&EditKey::Backspace, now));
+    assert_eq!(editing.text, "hllo");
+    assert!(editing.apply(&insert("€"), now));
+    assert_eq!((editing.text.as_str(), editing.caret), ("h€llo", 2));
+
+    editing.set_text("hi".to_owned());
+    assert_eq!((editing.text.as_str(), editing.caret), ("hi", 2));
+}
+
+#[test]
+fn the_caret_blinks_from_when_it_last_moved() {
+    let mut editing = editing("abc", 3);
+    let start = editing.moved_at;
+
+    assert!(editing.caret_visible(start));
+    assert!(!editing.caret_visible(start + CARET_BLINK));
+    assert!(editing.caret_visible(start + CARET_BLINK * 2));
+    assert_eq!(editing.next_blink(start), start + CARET_BLINK);
+    assert_eq!(
+        editing.next_blink(start + CARET_BLINK * 3 / 2),
+        start + CARET_BLINK * 2
+    );
+    assert_eq!(editing.painted(start + CARET_BLINK).caret, None);
+
+    // Moving shows it again.
+    let moved = start + CARET_BLINK;
+    editing.apply(&EditKey::Left, moved);
+    assert!(editing.caret_visible(moved));
+    assert_eq!(editing.painted(moved).caret, Some(2));
+}
+
+#[test]
+fn typing_into_an_editable_node_updates_the_document() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".field { padding-left: 4px; } .label { width: 100px; }");
+    let field = engine.create_node(Id::from_u64(1), Some("ab".to_owned()));
+    let label = engine.create_node(Id::from_u64(2), Some("Label".to_owned()));
+    engine.set_parent(engine.root_id(), field);
+    engine.set_parent(engine.root_id(), label);
+    engine.set_attribute(field, "class".to_owned(), "field".to_owned());
+    engine.set_attribute(field, "editable".to_owned(), "true".to_owned());
+    engine.set_attribute(label, "class".to_owned(), "label".to_owned());
+    engine.layout_headless(400.0, 100.0).unwrap();
+
+    let snapshot = engine.get_window_snapshot(WindowId::PRIMARY).unwrap();
+    let before = find_node(&snapshot, field).unwrap().bounds;
+    assert!(find_node(&snapshot, field).unwrap().editable);
+    assert!(!find_node(&snapshot, label).unwrap().editable);
+
+    // Not focused yet.
+    assert_eq!(engine.edit(WindowId::PRIMARY, &insert("x")), None);
+    engine.focus_at(WindowId::PRIMARY, &snapshot, before.x + 1.0, before.y + 1.0);
+
+    let edits = [insert("c"), EditKey::End, insert("d"), EditKey::Left];
+    let values: Vec<_> = edits
+        .iter()
+        .map(|key| engine.edit(WindowId::PRIMARY, key).unwrap())
+        .collect();
+    assert_eq!(
+        values,
+        [
+            Some((field, "cab".to_owned())),
+            None,
+            Some((field, "cabd".to_owned())),
+            None,
+        ]
+    );
+    assert_eq!(
+        engine.edit(WindowId::PRIMARY, &EditKey::Backspace),
+        Some(Some((field, "cad".to_owned())))
+    );
+
+    // The document has the new value, measured again.
+    engine.layout_headless(400.0, 100.0).unwrap();
+    let snapshot = engine.get_window_snapshot(WindowId::PRIMARY).unwrap();
+    let after = find_node(&snapshot, field).unwrap();
+    assert_eq!(after.text.as_deref(), Some("cad"));
+    assert!(after.bounds.width > before.width);
+
+    // Clicking a node that isn't editable takes focus away.
+    let label_bounds = find_node(&snapshot, label).unwrap().bounds;
+    engine.focus_at(
+        WindowId::PRIMARY,
+        &snapshot,
+        label_bounds.x + 1.0,
+        label_bounds.y + 1.0,
+    );
+    assert_eq!(engine.edit(WindowId::PRIMARY, &insert("x")), None);
+}
@@ -0,0 +1,164 @@
+use crate::backend::external::{ExternalBackend, FrameHandle};
+use crate::backend::InputState;
+use crate::debug_overlay::DebugOverlay;
+use crate::windowing::{self, GpuMemoryReport, Params, PointerInput, WindowId, WindowOptions};
+use crate::{EngineError, WindowErrorKind};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// How [`crate::Engine::run`] gets frames on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Open windows and present to them.
+    #[default]
+    Window,
+    /// Open no window and present nothing, for hosts that composite the engine's output with
+    /// their own rendering. `run` returns right away; from then on the host calls
+    /// [`crate::Engine::render_frame`] on the same thread whenever it wants a frame of the
+    /// primary window, and presents it on its own schedule. The size comes from
+    /// [`crate::Engine::set_frame_size`] and input from [`crate::Engine::inject_input`].
+    /// Secondary windows aren't shown.
+    External,
+}
+
+/// The primary window of an engine that presents externally: the callbacks `run` set up
+/// and the offscreen target. Lives on the thread that called `run`, like a window would.
+struct Presenter {
+    params: Params,
+    /// Created by the first frame, in the graphics context current then.
+    backend: Option<ExternalBackend>,
+    input_state: InputState,
+    started: Instant,
+    size: (f64, f64),
+    scale_factor: f64,
+    gpu_memory: GpuMemoryReport,
+}
+
+thread_local! {
+    /// By engine. A presenter is taken out while its callbacks run, so that they may call
+    /// back into the engine; `None` marks it as in use.
+    static PRESENTERS: RefCell<HashMap<usize, Option<Presenter>>> = RefCell::new(HashMap::new());
+}
+
+/// Start presenting the engine externally on this thread, replacing what an earlier `run`
+/// set up. The primary window starts at the default window size.
+pub(crate) fn attach(engine: usize, mut params: Params, gpu_memory: GpuMemoryReport) {
+    let options = WindowOptions::default();
+    (params.on_resize)(WindowId::PRIMARY, options.width, options.height);
+    let presenter = Presenter {
+        params,
+        backend: None,
+        input_state: InputState::default(),
+        started: Instant::now(),
+        size: (options.width, options.height),
+        scale_factor: 1.0,
+        gpu_memory,
+    };
+    PRESENTERS.with(|presenters| presenters.borrow_mut().insert(engine, Some(presenter)));
+}
+
+/// Stop presenting the engine on this thread and release the offscreen target. Returns
+/// whether it was presenting.
+pub(crate) fn detach(engine: usize) -> bool {
+    let Some(presenter) = PRESENTERS.with(|presenters| presenters.borrow_mut().remove(&engine))
+    else {
+        return false;
+    };
+    if let Some(presenter) = presenter {
+        *presenter.gpu_memory.lock().unwrap() = None;
+    }
+    true
+}
+
+/// Run `f` with the engine's presenter, if it presents externally on this thread and isn't
+/// busy in a callback already.
+fn with_presenter<R>(engine: usize, f: impl FnOnce(&mut Presenter) -> R) -> Option<R> {
+    let mut presenter = PRESENTERS.with(|presenters| {
+        presenters
+            .borrow_mut()
+            .get_mut(&engine)
+            .and_then(Option::take)
+    })?;
+    let result = f(&mut presenter);
+    PRESENTERS.with(|presenters| {
+        // Unless a callback detached it.
+        if let Some(slot) = presenters.borrow_mut().get_mut(&engine) {
+            if slot.is_none() {
+                *slot = Some(presenter);
+            }
+        }
+    });
+    Some(result)
+}
+
+/// Render a frame for the host, see [`crate::Engine::render_frame`].
+pub(crate) fn render_frame(engine: usize) -> Result<FrameHandle, EngineError> {
+    with_presenter(engine, Presenter::render).unwrap_or(Err(EngineError::Window {
+        kind: WindowErrorKind::NotPresentingExternally,
+        source: None,
+    }))
+}
+
+/// Change the viewport, in logical pixels, and the device pixels per logical pixel. Returns
+/// whether the engine presents externally on this thread.
+pub(crate) fn set_frame_size(engine: usize, width: f64, height: f64, scale_factor: f64) -> bool {
+    with_presenter(engine, |presenter| {
+        presenter.size = (width, height);
+        presenter.scale_factor = windowing::sanitize_scale_factor(scale_factor);
+        (presenter.params.on_resize)(WindowId::PRIMARY, width, height);
+    })
+    .is_some()
+}
+
+/// Apply input to the primary window. Returns whether the engine presents externally on
+/// this thread.
+pub(crate) fn inject_input(engine: usize, input: PointerInput) -> bool {
+    with_presenter(engine, |presenter| {
+        let Presenter {
+            params,
+            input_state,
+            ..
+        } = presenter;
+        windowing::apply_pointer_input(params, input_state, WindowId::PRIMARY, input);
+    })
+    .is_some()
+}
+
+impl Presenter {
+    fn render(&mut self) -> Result<FrameHandle, EngineError> {
+        if let Some(on_frame) = self.params.on_frame.as_mut() {
+            on_frame(self.started.elapsed());
+        }
+
+        let backend = match &mut self.backend {
+            Some(backend) => backend,
+            None => self
+                .backend
+                .insert(ExternalBackend::new().ok_or(EngineError::RenderFailed)?),
+        };
+        let debug_overlay = self.params.debug_overlay.then(|| DebugOverlay {
+            pointer: self
+                .input_state
+                .cursor_position
+                .map(|position| (position.x, position.y)),
+        });
+        let on_draw = &mut self.params.on_draw;
+        let (width, height) = self.size;
+        let frame = backend
+            .render(width, height, self.scale_factor, &mut |canvas| {
+                // The texture's content is the host's to use: draw all of it.
+                on_draw(WindowId::PRIMARY, canvas, 0, debug_overlay);
+            })
+            .ok_or(EngineError::RenderFailed)?;
+
+        *self.gpu_memory.lock().unwrap() = Some(backend.gpu_memory_stats());
+        if let Some(on_ready) = self.params.on_ready.take() {
+            on_ready();
+        }
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod external_tests;
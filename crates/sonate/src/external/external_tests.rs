@@ -0,0 +1,427 @@
+use crate::{
+    DragPhase, Engine, EngineError, EngineOptions, Event, EventKind, EventStatus, Id, Params,
+    PointerHandler, PointerInput, PresentMode, WindowErrorKind, WindowId,
+};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn external_params() -> Params {
+    Params {
+        present_mode: PresentMode::External,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn frames_are_rendered_only_while_presenting_externally() {
+    let engine = Engine::new();
+    assert!(matches!(
+        engine.render_frame(),
+        Err(EngineError::Window {
+            kind: WindowErrorKind::NotPresentingExternally,
+            ..
+        })
+    ));
+
+    // Returns right away instead of running an event loop.
+    engine.run(external_params()).unwrap();
+    // No graphics context is current in tests.
+    assert!(matches!(
+        engine.render_frame(),
+        Err(EngineError::RenderFailed)
+    ));
+    assert!(matches!(
+        std::thread::spawn(move || engine.render_frame())
+            .join()
+            .unwrap(),
+        Err(EngineError::Window {
+            kind: WindowErrorKind::NotPresentingExternally,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn quitting_stops_presenting() {
+    let engine = Engine::new();
+    engine.run(external_params()).unwrap();
+    engine.quit();
+    assert!(matches!(
+        engine.render_frame(),
+        Err(EngineError::Window {
+            kind: WindowErrorKind::NotPresentingExternally,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn injected_clicks_reach_the_host() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; height: 20px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    let params = Params {
+        on_click: Some(Box::new({
+            let clicks = Rc::clone(&clicks);
+            move |x, y, elements| clicks.borrow_mut().push((x, y, elements))
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 10.0, y: 10.0 });
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Move { x: 30.0, y: 10.0 });
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Release);
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 150.0, y: 80.0 });
+
+    let clicks = clicks.borrow();
+    assert_eq!(clicks.len(), 2);
+    assert_eq!((clicks[0].0, clicks[0].1), (10.0, 10.0));
+    assert!(clicks[0].2.contains(&item));
+    assert!(!clicks[1].2.contains(&item));
+}
+
+#[test]
+fn a_click_handler_can_replace_itself_for_the_next_click() {
+    let engine = Engine::new();
+    let clicks = Rc::new(RefCell::new(Vec::new()));
+    let params = Params {
+        on_click: Some(Box::new({
+            let engine = engine.clone();
+            let clicks = Rc::clone(&clicks);
+            move |x, _y, _elements| {
+                clicks.borrow_mut().push(("old", x));
+                let clicks = Rc::clone(&clicks);
+                let replacement = move |x, _y, _elements| clicks.borrow_mut().push(("new", x));
+                engine.set_on_click(Some(Box::new(replacement))).unwrap();
+            }
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    for x in [10.0, 20.0, 30.0] {
+        engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x, y: 10.0 });
+        engine.inject_input(WindowId::PRIMARY, PointerInput::Release);
+    }
+
+    assert_eq!(
+        *clicks.borrow(),
+        [("old", 10.0), ("new", 20.0), ("new", 30.0)]
+    );
+}
+
+#[test]
+fn clearing_a_handler_stops_only_its_events() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; height: 20px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let record = |kind: &'static str| -> Option<PointerHandler> {
+        let events = Rc::clone(&events);
+        Some(Box::new(move |x, y, elements| {
+            events
+                .borrow_mut()
+                .push((kind, x, y, elements.contains(&item)))
+        }))
+    };
+    let params = Params {
+        on_click: record("click"),
+        on_hover: record("hover"),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Move { x: 10.0, y: 10.0 });
+    engine.set_on_hover(None).unwrap();
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Move { x: 150.0, y: 80.0 });
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 150.0, y: 80.0 });
+
+    assert_eq!(
+        *events.borrow(),
+        [("hover", 10.0, 10.0, true), ("click", 150.0, 80.0, false)]
+    );
+}
+
+#[test]
+fn handlers_are_dropped_when_the_engine_stops_running() {
+    let engine = Engine::new();
+    let held = Rc::new(());
+    let params = Params {
+        on_click: Some(Box::new({
+            let held = Rc::clone(&held);
+            let engine = engine.clone();
+            move |_x, _y, _elements| {
+                let _ = (&held, &engine);
+            }
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    let listener = {
+        let held = Rc::clone(&held);
+        move |_: &Event| {
+            let _ = &held;
+            EventStatus::Continue
+        }
+    };
+    let listener = engine
+        .add_event_listener(engine.root_id(), EventKind::Click, Box::new(listener))
+        .unwrap();
+    assert_eq!(Rc::strong_count(&held), 3);
+
+    engine.quit();
+    assert_eq!(Rc::strong_count(&held), 1);
+    assert!(!engine.remove_event_listener(listener).unwrap());
+
+    // Set while it doesn't run, they wait for the next run and go when it ends.
+    let handler = {
+        let held = Rc::clone(&held);
+        move |_x, _y, _elements| {
+            let _ = &held;
+        }
+    };
+    engine.set_on_click(Some(Box::new(handler))).unwrap();
+    assert_eq!(Rc::strong_count(&held), 2);
+    engine.run(external_params()).unwrap();
+    assert_eq!(Rc::strong_count(&held), 2);
+    engine.quit();
+    assert_eq!(Rc::strong_count(&held), 1);
+}
+
+#[test]
+fn handlers_belong_to_the_thread_running_the_engine() {
+    let engine = Engine::with_options(EngineOptions {
+        strict_threading: true,
+        ..Default::default()
+    });
+    let elsewhere = engine.clone();
+    let result = std::thread::spawn(move || elsewhere.set_on_hover(None))
+        .join()
+        .unwrap();
+    assert!(matches!(result, Err(EngineError::WrongThread { .. })));
+    assert!(engine.set_on_hover(None).is_ok());
+
+    engine.run(external_params()).unwrap();
+    let elsewhere = engine.clone();
+    let result = std::thread::spawn(move || {
+        elsewhere.add_event_listener(
+            elsewhere.root_id(),
+            EventKind::Click,
+            Box::new(|_| EventStatus::Continue),
+        )
+    })
+    .join()
+    .unwrap();
+    assert!(matches!(result, Err(EngineError::WrongThread { .. })));
+    assert!(engine.set_on_hover(None).is_ok());
+    engine.quit();
+}
+
+#[test]
+fn listeners_added_before_the_engine_runs_get_its_events() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; height: 20px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let listener = {
+        let events = Rc::clone(&events);
+        move |event: &Event| {
+            let (x, _) = event.position.unwrap();
+            events.borrow_mut().push(format!("{:?} {x}", event.kind));
+            EventStatus::Continue
+        }
+    };
+    engine
+        .add_event_listener(item, EventKind::Click, Box::new(listener))
+        .unwrap();
+    let on_click = {
+        let events = Rc::clone(&events);
+        move |x: f64, _y: f64, _elements: Vec<Id>| events.borrow_mut().push(format!("on_click {x}"))
+    };
+    engine.set_on_click(Some(Box::new(on_click))).unwrap();
+
+    engine.run(external_params()).unwrap();
+    engine.layout_headless(200.0, 100.0).unwrap();
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 10.0, y: 10.0 });
+
+    assert_eq!(*events.borrow(), ["Click 10", "on_click 10"]);
+    engine.quit();
+}
+
+#[test]
+fn listeners_stopping_a_click_keep_it_from_the_click_handler() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; height: 20px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let params = Params {
+        on_click: Some(Box::new({
+            let events = Rc::clone(&events);
+            move |x, _y, _elements| events.borrow_mut().push(format!("on_click {x}"))
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    for kind in [EventKind::MouseDown, EventKind::Click, EventKind::MouseUp] {
+        let events = Rc::clone(&events);
+        let listener = move |event: &Event| {
+            let (x, _) = event.position.unwrap();
+            events.borrow_mut().push(format!("{:?} {x}", event.kind));
+            match event.kind {
+                EventKind::Click => EventStatus::StopPropagation,
+                _ => EventStatus::Continue,
+            }
+        };
+        engine
+            .add_event_listener(item, kind, Box::new(listener))
+            .unwrap();
+    }
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 10.0, y: 10.0 });
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Move { x: 20.0, y: 10.0 });
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Release);
+    // Outside the node, the click handler gets it.
+    engine.inject_input(WindowId::PRIMARY, PointerInput::Press { x: 150.0, y: 80.0 });
+
+    assert_eq!(
+        *events.borrow(),
+        ["MouseDown 10", "Click 10", "MouseUp 20", "on_click 150"]
+    );
+}
+
+#[test]
+fn dragging_captures_the_pointer_until_release() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".handle { width: 20px; height: 20px; }");
+    let handle = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), handle);
+    engine.set_attribute(handle, "class".to_owned(), "handle".to_owned());
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let params = Params {
+        on_drag: Some(Box::new({
+            let events = Rc::clone(&events);
+            move |node, dx, dy, phase| {
+                let node = node.as_u64();
+                events
+                    .borrow_mut()
+                    .push(format!("on_drag {node} {phase:?} {dx} {dy}"));
+            }
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    for kind in [EventKind::Drag, EventKind::MouseUp] {
+        let events = Rc::clone(&events);
+        let listener = move |event: &Event| {
+            let target = event.target.as_u64();
+            events
+                .borrow_mut()
+                .push(format!("{:?} {target}", event.kind));
+            EventStatus::Continue
+        };
+        engine
+            .add_event_listener(handle, kind, Box::new(listener))
+            .unwrap();
+    }
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let input = |input| engine.inject_input(WindowId::PRIMARY, input);
+    input(PointerInput::Press { x: 10.0, y: 10.0 });
+    // Far outside the handle, its drag goes on.
+    input(PointerInput::Move { x: 50.0, y: 10.0 });
+    input(PointerInput::Move { x: 120.0, y: 40.0 });
+    input(PointerInput::Release);
+    // Released, moves aren't drags anymore.
+    input(PointerInput::Move { x: 10.0, y: 10.0 });
+
+    assert_eq!(
+        *events.borrow(),
+        [
+            "Drag 1",
+            "on_drag 1 Start 40 0",
+            "Drag 1",
+            "on_drag 1 Move 110 30",
+            "MouseUp 1",
+            "Drag 1",
+            "on_drag 1 End 110 30",
+        ]
+    );
+
+    // The handle going away mid-drag ends it.
+    events.borrow_mut().clear();
+    input(PointerInput::Press { x: 10.0, y: 10.0 });
+    input(PointerInput::Move { x: 15.0, y: 10.0 });
+    engine.set_attribute(handle, "style".to_owned(), "display: none".to_owned());
+    engine.layout_headless(200.0, 100.0).unwrap();
+    input(PointerInput::Move { x: 30.0, y: 10.0 });
+    input(PointerInput::Release);
+
+    assert_eq!(
+        *events.borrow(),
+        ["Drag 1", "on_drag 1 Start 5 0", "on_drag 1 End 5 0",]
+    );
+}
+
+#[test]
+fn listeners_capture_the_pointer_for_any_node() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; height: 20px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+
+    let drags = Rc::new(RefCell::new(Vec::new()));
+    let params = Params {
+        on_drag: Some(Box::new({
+            let drags = Rc::clone(&drags);
+            move |node, dx, _dy, phase| drags.borrow_mut().push((node, dx, phase))
+        })),
+        ..external_params()
+    };
+    engine.run(params).unwrap();
+    let capturing = engine.clone();
+    engine
+        .add_event_listener(
+            item,
+            EventKind::MouseDown,
+            Box::new(move |event: &Event| {
+                assert!(capturing.set_pointer_capture(event.current_target));
+                EventStatus::Continue
+            }),
+        )
+        .unwrap();
+    engine.layout_headless(200.0, 100.0).unwrap();
+    assert!(!engine.set_pointer_capture(Id::from_u64(99)));
+
+    let input = |input| engine.inject_input(WindowId::PRIMARY, input);
+    input(PointerInput::Press { x: 10.0, y: 10.0 });
+    input(PointerInput::Move { x: 25.0, y: 10.0 });
+    assert!(engine.release_pointer_capture());
+    input(PointerInput::Move { x: 30.0, y: 10.0 });
+    input(PointerInput::Release);
+
+    assert_eq!(
+        *drags.borrow(),
+        [(item, 15.0, DragPhase::Start)],
+        "released captures end no drag"
+    );
+}
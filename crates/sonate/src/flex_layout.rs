@@ -1,9 +1,11 @@
+use crate::intrinsic::{automatic_minimum_main_size, intrinsic_sizes};
 use crate::layout::{LayoutContext, Node};
+use crate::resources::ImageHandle;
 use crate::style::{
-    AlignContent, AlignItems, AlignSelf, BoxSizing, Directional, FlexDirection, FlexWrap,
+    AlignContent, AlignItems, AlignSelf, BoxSizing, Directional, Display, FlexDirection, FlexWrap,
     JustifyContent, Length, Style,
 };
-use crate::text::FontSpec;
+use crate::text::{measure_clamped, FontSpec};
 use std::cell::RefCell;
 use std::rc::Rc;
 
@@ -52,12 +54,12 @@ impl FlexLayoutEngine {
             }
         };
 
-        // Positioning origin for the flex container’s content box.
-        // Sonate currently models `width/height` as the primary size and uses padding as an
-        // inset for child placement.
+        // Positioning origin for the flex container’s content box, inside its border and
+        // padding.
         let padding = container_style.padding.resolved();
-        let content_origin_x = container_x + padding.left.to_px();
-        let content_origin_y = container_y + padding.top.to_px();
+        let border = container_style.used_border_width();
+        let content_origin_x = container_x + border.left.to_px() + padding.left.to_px();
+        let content_origin_y = container_y + border.top.to_px() + padding.top.to_px();
 
         // === §9.2 Line Length Determination ===
         // §9.2 #2 Determine the available main and cross space for the flex items.
@@ -75,11 +77,17 @@ impl FlexLayoutEngine {
         let available_cross =
             determine_available_space(container_cross, container_style, &direction, Axis::Cross);
 
-        let row_gap_px = container_style.row_gap.unwrap_or(Length::Px(0.0)).to_px();
-        let column_gap_px = container_style
-            .column_gap
-            .unwrap_or(Length::Px(0.0))
-            .to_px();
+        // Percentages of the container's content box. Those in gaps, margins and padding are
+        // all of its width, whatever the direction.
+        let (content_width, content_height) = match direction {
+            FlexDirection::Row | FlexDirection::RowReverse => (available_main, available_cross),
+            FlexDirection::Column | FlexDirection::ColumnReverse => {
+                (available_cross, available_main)
+            }
+        };
+
+        let row_gap_px = gap_px(container_style.row_gap.as_ref(), content_width);
+        let column_gap_px = gap_px(container_style.column_gap.as_ref(), content_width);
         let (main_gap_px, cross_gap_px) = match direction {
             FlexDirection::Row | FlexDirection::RowReverse => (column_gap_px, row_gap_px),
             FlexDirection::Column | FlexDirection::ColumnReverse => (row_gap_px, column_gap_px),
@@ -113,10 +121,20 @@ impl FlexLayoutEngine {
                 }
             }
 
-            let style = resolve_style(&child, ctx, container_style);
+            let mut style = resolve_style(&child, ctx, container_style);
+            if style.resolved_display() == Display::None {
+                // Not a flex item: takes no space and is left out of the render tree.
+                let mut node_borrow = child.borrow_mut();
+                node_borrow.layout.bounds = Default::default();
+                node_borrow.layout.style = std::sync::Arc::new(style);
+                continue;
+            }
+            style.resolve_size_percentages(content_width, content_height, available_main);
+            style.resolve_box_percentages(content_width);
+
             let margins = style.margin.resolved();
             let (main_before, main_after, cross_before, cross_after) =
-                margins_for_direction(&margins, &direction);
+                margins_for_direction(margins, &direction);
             // NOTE: This currently approximates §9.2 #3 “Determine the flex base size and
             // hypothetical main size of each item”.
             //
@@ -124,15 +142,17 @@ impl FlexLayoutEngine {
             // `base_sizes_for_item()` currently applies `flex-basis` directly, but the spec’s
             // detailed cases (definite basis vs content-based basis, etc.) will replace this.
             //
-            // Where aspect ratio will later be handled:
-            // The spec has cases where an item’s preferred/intrinsic aspect ratio affects its
-            // flex base size (see §9.2 #3). Sonate does not model aspect ratio yet.
+            // Aspect ratio: an item's preferred aspect ratio gives it the size on an axis its
+            // style leaves auto from the one it sizes, which for the main axis is its flex
+            // base size (see §9.2 #3). See `base_sizes_for_item()`.
             let (base_main, base_cross) = base_sizes_for_item(&child, &style, &direction, ctx);
+            let min_main = automatic_minimum_main_size(&child, &style, &direction, ctx);
 
             items.push(FlexItem {
                 node: child,
                 style,
                 base_main,
+                min_main,
                 final_main: base_main,
                 final_cross: base_cross,
                 margin_main_before: main_before,
@@ -219,9 +239,32 @@ impl FlexLayoutEngine {
                 if total_weight > 0.0 {
                     for (i, idx) in line.iter().enumerate() {
                         let weight = weights[i];
-                        items[*idx].final_main =
+                        // Items don't shrink below their automatic minimum size, §4.5.
+                        let shrunk =
                             items[*idx].base_main - (shrink_needed * (weight / total_weight));
+                        let min_main = items[*idx].min_main.min(items[*idx].base_main);
+                        items[*idx].final_main = shrunk.max(min_main);
+                    }
+                }
+            }
+
+            // Items with an aspect ratio and an auto cross size take theirs from their
+            // flexed main size.
+            for idx in line {
+                let item = &mut items[*idx];
+                if !cross_size_is_auto(&item.style, &direction) {
+                    continue;
+                }
+                let cross = match direction {
+                    FlexDirection::Row | FlexDirection::RowReverse => {
+                        height_from_aspect_ratio(&item.style, item.final_main)
+                    }
+                    FlexDirection::Column | FlexDirection::ColumnReverse => {
+                        width_from_aspect_ratio(&item.style, item.final_main)
                     }
+                };
+                if let Some(cross) = cross {
+                    item.final_cross = cross;
                 }
             }
 
@@ -281,8 +324,10 @@ impl FlexLayoutEngine {
                     AlignSelf::Stretch => AlignItems::Stretch,
                 };
 
+                // Items with an aspect ratio keep the cross size it gives them.
                 if matches!(align, AlignItems::Stretch)
                     && cross_size_is_auto(&items[*idx].style, &direction)
+                    && items[*idx].style.resolved_aspect_ratio().is_none()
                 {
                     let margins = length_px_or_zero(&items[*idx].margin_cross_before)
                         + length_px_or_zero(&items[*idx].margin_cross_after);
@@ -421,17 +466,22 @@ impl FlexLayoutEngine {
                     ),
                 };
 
+                let image = ctx.image_of(&item.node.borrow());
                 {
                     let mut node_borrow = item.node.borrow_mut();
+                    node_borrow.layout.image = image;
                     node_borrow.layout.bounds.x = x;
                     node_borrow.layout.bounds.y = y;
                     node_borrow.layout.bounds.width = w;
                     node_borrow.layout.bounds.height = h;
                     node_borrow.layout.style = std::sync::Arc::new(item.style.clone());
                 }
+                ctx.count_laid_out_node();
 
-                if !item.node.borrow().children.is_empty() {
-                    self.layout_flex_children(item.node.clone(), &item.style, ctx);
+                if !item.node.borrow().children.is_empty()
+                    && !ctx.templates.reuse_skeleton(&item.node, ctx)
+                {
+                    ctx.layout_children(item.node.clone(), &item.style);
                 }
 
                 cursor_main += item.final_main + main_after_px;
@@ -447,6 +497,8 @@ struct FlexItem {
     node: Rc<RefCell<Node>>,
     style: Style,
     base_main: f64,
+    /// The automatic minimum size on the main axis, which shrinking stops at.
+    min_main: f64,
     final_main: f64,
     final_cross: f64,
     margin_main_before: Length,
@@ -461,7 +513,7 @@ struct FlexLine {
     cross_size: f64,
 }
 
-fn base_sizes_for_item(
+pub(crate) fn base_sizes_for_item(
     node: &Rc<RefCell<Node>>,
     style: &Style,
     direction: &FlexDirection,
@@ -473,7 +525,7 @@ fn base_sizes_for_item(
     let padding = style.padding.resolved();
     let padding_w = padding.left.to_px() + padding.right.to_px();
     let padding_h = padding.top.to_px() + padding.bottom.to_px();
-    let border = style.border_width.resolved();
+    let border = style.used_border_width();
     let border_w = border.left.to_px() + border.right.to_px();
     let border_h = border.top.to_px() + border.bottom.to_px();
     let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
@@ -493,38 +545,35 @@ fn base_sizes_for_item(
         _ => None,
     };
 
-    let mut width = width_opt.unwrap_or(100.0);
-    let mut height = height_opt.unwrap_or(30.0);
-
-    // If this looks like a text node and doesn't have explicit sizes, prefer intrinsic text sizing.
-    let is_text_node = node.borrow().is_text_node();
-
-    if is_text_node {
-        if let Some(text) = node.borrow().text.as_deref() {
-            let font = FontSpec::from_style(style);
-
-            if width_opt.is_none() {
-                let text_size = ctx.text_measurer.measure_unwrapped(text, &font);
-                width = text_size.width + padding_w + border_w;
-            }
-
-            if height_opt.is_none() {
-                let text_size = match style.width {
-                    Some(Length::Px(specified_width_px)) if specified_width_px > 0.0 => {
-                        let content_max_width = match box_sizing {
-                            BoxSizing::ContentBox => specified_width_px,
-                            BoxSizing::BorderBox => {
-                                (specified_width_px - padding_w - border_w).max(0.0)
-                            }
-                        };
-                        ctx.text_measurer
-                            .measure_wrapped(text, &font, content_max_width)
-                    }
-                    _ => ctx.text_measurer.measure_unwrapped(text, &font),
-                };
+    // With an aspect ratio, the axis the style sizes sizes the other.
+    let (width_opt, height_opt) = match (width_opt, height_opt) {
+        (Some(width), None) => (Some(width), height_from_aspect_ratio(style, width)),
+        (None, Some(height)) => (width_from_aspect_ratio(style, height), Some(height)),
+        sizes => sizes,
+    };
 
-                height = text_size.height + padding_h + border_h;
-            }
+    // Without explicit sizes, items are as big as their content laid out on one line.
+    let intrinsic = intrinsic_sizes(node, style, ctx);
+    let mut width = width_opt.unwrap_or(intrinsic.max_content_width);
+    let mut height = height_opt.unwrap_or(intrinsic.height);
+
+    // Image nodes take their image's natural size, and text nodes their text's, on the axes
+    // without explicit sizes.
+    if let Some(image) = ctx.image_of(&node.borrow()) {
+        let (image_width, image_height) = image_item_size(&image, style);
+        if width_opt.is_none() {
+            width = image_width.unwrap_or(width);
+        }
+        if height_opt.is_none() {
+            height = image_height.unwrap_or(height);
+        }
+    } else if let Some(text) = node.borrow().text.as_deref() {
+        let (text_width, text_height) = text_item_size(text, style, ctx);
+        if width_opt.is_none() {
+            width = text_width.unwrap_or(width);
+        }
+        if height_opt.is_none() {
+            height = text_height.unwrap_or(height);
         }
     }
 
@@ -533,40 +582,127 @@ fn base_sizes_for_item(
         FlexDirection::Column | FlexDirection::ColumnReverse => (height, width),
     };
 
-    let mut main = match style.flex_basis.as_ref() {
+    let main = match style.flex_basis.as_ref() {
         Some(Length::Px(px)) => *px,
         Some(Length::Auto) => main_from_size,
         Some(other) => other.to_px(),
         None => main_from_size,
     };
 
-    // If the item is itself a container and has no explicit main size, approximate
-    // shrink-to-fit by looking at its children’s fixed sizes.
-    // This is a pragmatic bridge until we implement the full intrinsic sizing path.
-    let is_container = !node.borrow().children.is_empty();
-    let has_explicit_main = match direction {
-        FlexDirection::Row | FlexDirection::RowReverse => {
-            matches!(style.width, Some(Length::Px(_)))
-        }
-        FlexDirection::Column | FlexDirection::ColumnReverse => {
-            matches!(style.height, Some(Length::Px(_)))
+    (main, cross_from_size)
+}
+
+/// The border-box height `aspect-ratio` gives a box `width` pixels wide, or `None` without
+/// one.
+pub(crate) fn height_from_aspect_ratio(style: &Style, width: f64) -> Option<f64> {
+    let ratio = style.resolved_aspect_ratio()?;
+    let padding = style.padding.resolved();
+    let border = style.used_border_width();
+    let extra_w =
+        padding.left.to_px() + padding.right.to_px() + border.left.to_px() + border.right.to_px();
+    let extra_h =
+        padding.top.to_px() + padding.bottom.to_px() + border.top.to_px() + border.bottom.to_px();
+    Some(match style.box_sizing.unwrap_or(BoxSizing::ContentBox) {
+        BoxSizing::ContentBox => (width - extra_w).max(0.0) / ratio + extra_h,
+        BoxSizing::BorderBox => (width / ratio).max(extra_h),
+    })
+}
+
+/// The border-box width `aspect-ratio` gives a box `height` pixels high, or `None` without
+/// one.
+pub(crate) fn width_from_aspect_ratio(style: &Style, height: f64) -> Option<f64> {
+    let ratio = style.resolved_aspect_ratio()?;
+    let padding = style.padding.resolved();
+    let border = style.used_border_width();
+    let extra_w =
+        padding.left.to_px() + padding.right.to_px() + border.left.to_px() + border.right.to_px();
+    let extra_h =
+        padding.top.to_px() + padding.bottom.to_px() + border.top.to_px() + border.bottom.to_px();
+    Some(match style.box_sizing.unwrap_or(BoxSizing::ContentBox) {
+        BoxSizing::ContentBox => (height - extra_h).max(0.0) * ratio + extra_w,
+        BoxSizing::BorderBox => (height * ratio).max(extra_w),
+    })
+}
+
+/// The border-box width and height a text node takes from its text, on the axes its style
+/// doesn't size explicitly.
+pub(crate) fn text_item_size(
+    text: &str,
+    style: &Style,
+    ctx: &LayoutContext,
+) -> (Option<f64>, Option<f64>) {
+    let padding = style.padding.resolved();
+    let padding_w = padding.left.to_px() + padding.right.to_px();
+    let padding_h = padding.top.to_px() + padding.bottom.to_px();
+    let border = style.used_border_width();
+    let border_w = border.left.to_px() + border.right.to_px();
+    let border_h = border.top.to_px() + border.bottom.to_px();
+    let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
+    let font = FontSpec::from_style(style);
+
+    let width = match style.width {
+        Some(Length::Px(px)) if px > 0.0 => None,
+        _ => Some(ctx.text_measurer.measure_unwrapped(text, &font).width + padding_w + border_w),
+    };
+    let height = match (&style.height, &style.width) {
+        (Some(Length::Px(px)), _) if *px > 0.0 => None,
+        (_, Some(Length::Px(specified_width_px)))
+            if *specified_width_px > 0.0 && style.wraps_text() =>
+        {
+            let specified_width_px = *specified_width_px;
+            let content_max_width = match box_sizing {
+                BoxSizing::ContentBox => specified_width_px,
+                BoxSizing::BorderBox => (specified_width_px - padding_w - border_w).max(0.0),
+            };
+            let measurer = ctx.text_measurer.as_ref();
+            let clamp = style.resolved_line_clamp();
+            let text_size = measure_clamped(measurer, text, &font, content_max_width, clamp);
+            Some(text_size.height + padding_h + border_h)
         }
+        _ => Some(ctx.text_measurer.measure_unwrapped(text, &font).height + padding_h + border_h),
+    };
+
+    (width, height)
+}
+
+/// The border-box width and height an image node takes from its image, on the axes its
+/// style doesn't size explicitly: the image's natural size in pixels, or where the other
+/// axis is sized, what keeps the image's aspect ratio at that size.
+pub(crate) fn image_item_size(image: &ImageHandle, style: &Style) -> (Option<f64>, Option<f64>) {
+    let padding = style.padding.resolved();
+    let padding_w = padding.left.to_px() + padding.right.to_px();
+    let padding_h = padding.top.to_px() + padding.bottom.to_px();
+    let border = style.used_border_width();
+    let border_w = border.left.to_px() + border.right.to_px();
+    let border_h = border.top.to_px() + border.bottom.to_px();
+    let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
+    let content_size = |specified: &Option<Length>, extra: f64| match (specified, box_sizing) {
+        (Some(Length::Px(px)), BoxSizing::ContentBox) if *px > 0.0 => Some(*px),
+        (Some(Length::Px(px)), BoxSizing::BorderBox) if *px > 0.0 => Some((px - extra).max(0.0)),
+        _ => None,
     };
-    if is_container && !has_explicit_main && style.flex_basis.is_none() {
-        // If the main size is currently coming from our hardcoded default, prefer
-        // a child-derived intrinsic size (this is needed for shrink-to-fit flex items).
-        let main_was_default = match direction {
-            FlexDirection::Row | FlexDirection::RowReverse => width_opt.is_none(),
-            FlexDirection::Column | FlexDirection::ColumnReverse => height_opt.is_none(),
-        };
 
-        let intrinsic = intrinsic_main_from_children(node, direction, ctx, style);
-        if intrinsic > 0.0 && main_was_default {
-            main = intrinsic;
+    let natural_width = image.width().max(0) as f64;
+    let natural_height = image.height().max(0) as f64;
+    let content_width = content_size(&style.width, padding_w + border_w);
+    let content_height = content_size(&style.height, padding_h + border_h);
+    match (content_width, content_height) {
+        (Some(_), Some(_)) => (None, None),
+        (Some(width), None) if natural_width > 0.0 => {
+            let height = width * natural_height / natural_width;
+            (None, Some(height + padding_h + border_h))
+        }
+        (None, Some(height)) if natural_height > 0.0 => {
+            let width = height * natural_width / natural_height;
+            (Some(width + padding_w + border_w), None)
         }
+        (Some(_), None) => (None, Some(padding_h + border_h)),
+        (None, Some(_)) => (Some(padding_w + border_w), None),
+        (None, None) => (
+            Some(natural_width + padding_w + border_w),
+            Some(natural_height + padding_h + border_h),
+        ),
     }
-
-    (main, cross_from_size)
 }
 
 #[derive(Clone, Copy)]
@@ -586,12 +722,16 @@ fn is_definite_container_content_box_size(
     )
 }
 
-fn specified_axis_length(style: &Style, direction: &FlexDirection, axis: Axis) -> Option<Length> {
+fn specified_axis_length<'a>(
+    style: &'a Style,
+    direction: &FlexDirection,
+    axis: Axis,
+) -> Option<&'a Length> {
     match (direction, axis) {
-        (FlexDirection::Row | FlexDirection::RowReverse, Axis::Main) => style.width,
-        (FlexDirection::Row | FlexDirection::RowReverse, Axis::Cross) => style.height,
-        (FlexDirection::Column | FlexDirection::ColumnReverse, Axis::Main) => style.height,
-        (FlexDirection::Column | FlexDirection::ColumnReverse, Axis::Cross) => style.width,
+        (FlexDirection::Row | FlexDirection::RowReverse, Axis::Main) => style.width.as_ref(),
+        (FlexDirection::Row | FlexDirection::RowReverse, Axis::Cross) => style.height.as_ref(),
+        (FlexDirection::Column | FlexDirection::ColumnReverse, Axis::Main) => style.height.as_ref(),
+        (FlexDirection::Column | FlexDirection::ColumnReverse, Axis::Cross) => style.width.as_ref(),
     }
 }
 
@@ -605,7 +745,7 @@ fn content_box_axis_size(
     let border = axis_border_sum_px(style, direction, axis);
 
     let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
-    if let Some(Length::Px(px)) = specified_axis_length(style, direction, axis) {
+    if let Some(&Length::Px(px)) = specified_axis_length(style, direction, axis) {
         return match box_sizing {
             BoxSizing::ContentBox => px,
             BoxSizing::BorderBox => (px - padding - border).max(0.0),
@@ -645,7 +785,7 @@ fn axis_padding_sum_px(style: &Style, direction: &FlexDirection, axis: Axis) ->
 }
 
 fn axis_border_sum_px(style: &Style, direction: &FlexDirection, axis: Axis) -> f64 {
-    let b = style.border_width.resolved();
+    let b = style.used_border_width();
 
     match (direction, axis) {
         (FlexDirection::Row | FlexDirection::RowReverse, Axis::Main)
@@ -659,39 +799,6 @@ fn axis_border_sum_px(style: &Style, direction: &FlexDirection, axis: Axis) -> f
     }
 }
 
-fn intrinsic_main_from_children(
-    node: &Rc<RefCell<Node>>,
-    parent_direction: &FlexDirection,
-    ctx: &LayoutContext,
-    fallback: &Style,
-) -> f64 {
-    // Best-effort intrinsic main size used for shrink-to-fit containers.
-    // We intentionally keep this conservative (max of child fixed sizes), since Sonate
-    // does not yet implement min/max-content constraints or full intrinsic sizing.
-
-    let children = node.borrow().children.clone();
-    if children.is_empty() {
-        return 0.0;
-    }
-
-    let is_row_main = matches!(
-        parent_direction,
-        FlexDirection::Row | FlexDirection::RowReverse
-    );
-
-    children
-        .iter()
-        .map(|c| {
-            let s = resolve_style(c, ctx, fallback);
-            if is_row_main {
-                s.width.as_ref().map(|l| l.to_px()).unwrap_or(100.0)
-            } else {
-                s.height.as_ref().map(|l| l.to_px()).unwrap_or(30.0)
-            }
-        })
-        .fold(0.0, f64::max)
-}
-
 fn cross_size_is_auto(style: &Style, direction: &FlexDirection) -> bool {
     match direction {
         FlexDirection::Row | FlexDirection::RowReverse => {
@@ -704,14 +811,20 @@ fn cross_size_is_auto(style: &Style, direction: &FlexDirection) -> bool {
 }
 
 fn margins_for_direction(
-    m: &Directional<Length>,
+    m: Directional<Length>,
     direction: &FlexDirection,
 ) -> (Length, Length, Length, Length) {
+    let Directional {
+        top,
+        right,
+        bottom,
+        left,
+    } = m;
     match direction {
-        FlexDirection::Row => (m.left, m.right, m.top, m.bottom),
-        FlexDirection::RowReverse => (m.right, m.left, m.top, m.bottom),
-        FlexDirection::Column => (m.top, m.bottom, m.left, m.right),
-        FlexDirection::ColumnReverse => (m.bottom, m.top, m.left, m.right),
+        FlexDirection::Row => (left, right, top, bottom),
+        FlexDirection::RowReverse => (right, left, top, bottom),
+        FlexDirection::Column => (top, bottom, left, right),
+        FlexDirection::ColumnReverse => (bottom, top, left, right),
     }
 }
 
@@ -726,6 +839,14 @@ fn length_px_or_zero(length: &Length) -> f64 {
     }
 }
 
+/// A gap in pixels, with percentages of the container's content width. Gaps are never
+/// negative, however little room a percentage leaves the items.
+fn gap_px(gap: Option<&Length>, content_width: f64) -> f64 {
+    gap.map_or(0.0, |gap| {
+        gap.resolve_percentage(content_width).to_px().max(0.0)
+    })
+}
+
 fn resolve_margin_px(length: &Length, auto_share: Option<f64>) -> f64 {
     match (length, auto_share) {
         (Length::Auto, Some(share)) => share,
@@ -824,21 +945,19 @@ fn justify_offsets(
     }
 }
 
-fn resolve_style(node: &Rc<RefCell<Node>>, ctx: &LayoutContext, fallback: &Style) -> Style {
+pub(crate) fn resolve_style(
+    node: &Rc<RefCell<Node>>,
+    ctx: &LayoutContext,
+    fallback: &Style,
+) -> Style {
     let node_borrow = node.borrow();
 
-    // Start with existing style as base.
-    let mut style = node_borrow.layout.style.as_ref().clone();
-
-    crate::style_matching::apply_matching_rules(
-        &mut style,
-        &node_borrow.attributes,
-        &ctx.style_sheet,
-    );
+    // The inline style with the matching rules applied.
+    let mut style = ctx.computed_style(&node_borrow);
 
     // Best-effort inheritance for anonymous items.
     if node_borrow.attributes.is_empty() && node_borrow.children.is_empty() {
-        style.display = fallback.display.clone();
+        style.display = fallback.display;
     }
 
     style
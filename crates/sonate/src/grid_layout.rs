@@ -0,0 +1,283 @@
+//! Grid layout, a minimal subset of CSS Grid: tracks in pixels, percentages and `fr` from
+//! `grid-template-columns` and `grid-template-rows`, the gaps between them, and items placed
+//! row by row in `order`, each `grid-column: span N` columns wide.
+//!
+//! Items fill their cells but on the axes their style sizes in pixels, where they sit at the
+//! start, and items with an `aspect-ratio` take their height from their width. Rows past the
+//! template are as high as their tallest item.
+
+use crate::flex_layout::{base_sizes_for_item, height_from_aspect_ratio, resolve_style};
+use crate::intrinsic::specified_border_box;
+use crate::layout::{LayoutContext, Node};
+use crate::style::{BoxSizing, Display, FlexDirection, GridTrack, Length, Style};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct GridLayoutEngine;
+
+impl GridLayoutEngine {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Places the children of `container`, which is already sized and positioned, in the
+    /// cells of its grid, then lays out their own children.
+    pub fn layout_grid_children(
+        &self,
+        container: Rc<RefCell<Node>>,
+        container_style: &Style,
+        ctx: &LayoutContext,
+    ) {
+        let bounds = container.borrow().layout.bounds;
+        let padding = container_style.padding.resolved();
+        let border = container_style.used_border_width();
+        let content_x = bounds.x + border.left.to_px() + padding.left.to_px();
+        let content_y = bounds.y + border.top.to_px() + padding.top.to_px();
+        let content_width = (bounds.width
+            - border.left.to_px()
+            - border.right.to_px()
+            - padding.left.to_px()
+            - padding.right.to_px())
+        .max(0.0);
+        let content_height = (bounds.height
+            - border.top.to_px()
+            - border.bottom.to_px()
+            - padding.top.to_px()
+            - padding.bottom.to_px())
+        .max(0.0);
+
+        let row_gap = container_style.row_gap.as_ref().map_or(0.0, Length::to_px);
+        let column_gap = container_style
+            .column_gap
+            .as_ref()
+            .map_or(0.0, Length::to_px);
+
+        let columns = track_sizes(template_columns(container_style), content_width, column_gap);
+
+        let mut children: Vec<Rc<RefCell<Node>>> = container.borrow().children.clone();
+        children.sort_by_key(|child| {
+            let style = resolve_style(child, ctx, container_style);
+            style.order.unwrap_or(0)
+        });
+
+        let mut items: Vec<GridItem> = Vec::new();
+        let mut placement = AutoPlacement::default();
+        for child in children {
+            if child.borrow().is_text_node() {
+                let is_blank = child
+                    .borrow()
+                    .text
+                    .as_deref()
+                    .is_none_or(|text| text.trim().is_empty());
+                if is_blank {
+                    // Whitespace-only text is not rendered.
+                    continue;
+                }
+            }
+
+            let mut style = resolve_style(&child, ctx, container_style);
+            if style.resolved_display() == Display::None {
+                // Not a grid item: takes no cell and is left out of the render tree.
+                let mut node_borrow = child.borrow_mut();
+                node_borrow.layout.bounds = Default::default();
+                node_borrow.layout.style = Arc::new(style);
+                continue;
+            }
+
+            let (row, column, span) = placement.place(&style, columns.len());
+            // Widths are percentages of the cell.
+            let cell_width = span_size(&columns, column, span, column_gap);
+            style.width = style
+                .width
+                .as_ref()
+                .map(|width| width.resolve_percentage(cell_width));
+
+            items.push(GridItem {
+                node: child,
+                style,
+                row,
+                column,
+                span,
+            });
+        }
+
+        let Some(last) = items.last() else {
+            return;
+        };
+
+        // Rows past the template are as high as their tallest item, margins included.
+        let mut tallest = vec![0.0_f64; last.row + 1];
+        for item in &items {
+            let (_, mut height) =
+                base_sizes_for_item(&item.node, &item.style, &FlexDirection::Row, ctx);
+            if !has_specified_height(&item.style) {
+                let cell_width = span_size(&columns, item.column, item.span, column_gap);
+                let width = item_width(&item.style, cell_width);
+                height = height_from_aspect_ratio(&item.style, width).unwrap_or(height);
+            }
+            let margin = item.style.margin.resolved();
+            let outer_height = height + margin.top.to_px() + margin.bottom.to_px();
+            tallest[item.row] = tallest[item.row].max(outer_height);
+        }
+        let template_rows = container_style.grid_template_rows.as_deref().unwrap_or(&[]);
+        let row_tracks: Vec<GridTrack> = (0..tallest.len().max(template_rows.len()))
+            .map(|row| {
+                template_rows
+                    .get(row)
+                    .cloned()
+                    .unwrap_or_else(|| GridTrack::Length(Length::Px(tallest[row])))
+            })
+            .collect();
+        let rows = track_sizes(&row_tracks, content_height, row_gap);
+
+        for mut item in items {
+            let cell_x = content_x + track_offset(&columns, item.column, column_gap);
+            let cell_y = content_y + track_offset(&rows, item.row, row_gap);
+            let cell_width = span_size(&columns, item.column, item.span, column_gap);
+            let cell_height = rows[item.row];
+            item.style.height = item
+                .style
+                .height
+                .as_ref()
+                .map(|height| height.resolve_percentage(cell_height));
+
+            let style = &item.style;
+            let margin = style.margin.resolved();
+            let padding = style.padding.resolved();
+            let border = style.used_border_width();
+            let width = item_width(style, cell_width);
+            let height = specified_border_box(
+                style.height.as_ref(),
+                padding.top.to_px()
+                    + padding.bottom.to_px()
+                    + border.top.to_px()
+                    + border.bottom.to_px(),
+                style.box_sizing.unwrap_or(BoxSizing::ContentBox),
+            )
+            .or_else(|| height_from_aspect_ratio(style, width))
+            .unwrap_or(cell_height - margin.top.to_px() - margin.bottom.to_px())
+            .max(0.0);
+
+            let image = ctx.image_of(&item.node.borrow());
+            {
+                let mut node_borrow = item.node.borrow_mut();
+                node_borrow.layout.image = image;
+                node_borrow.layout.bounds.x = cell_x + margin.left.to_px();
+                node_borrow.layout.bounds.y = cell_y + margin.top.to_px();
+                node_borrow.layout.bounds.width = width;
+                node_borrow.layout.bounds.height = height;
+                node_borrow.layout.style = Arc::new(item.style.clone());
+            }
+            ctx.count_laid_out_node();
+
+            if !item.node.borrow().children.is_empty()
+                && !ctx.templates.reuse_skeleton(&item.node, ctx)
+            {
+                ctx.layout_children(item.node.clone(), &item.style);
+            }
+        }
+    }
+}
+
+/// The columns of `grid-template-columns`, or the one column without it.
+pub(crate) fn template_columns(style: &Style) -> &[GridTrack] {
+    const ONE_COLUMN: &[GridTrack] = &[GridTrack::Fr(1.0)];
+    style
+        .grid_template_columns
+        .as_deref()
+        .filter(|tracks| !tracks.is_empty())
+        .unwrap_or(ONE_COLUMN)
+}
+
+/// Auto-placement: each item goes after the one before it, on the next row when it doesn't
+/// fit in what's left of this one.
+#[derive(Default)]
+pub(crate) struct AutoPlacement {
+    row: usize,
+    column: usize,
+}
+
+impl AutoPlacement {
+    /// The row and column of the next item, with `style`, in a grid `columns` wide, and how
+    /// many columns it spans, which is at most all of them.
+    pub fn place(&mut self, style: &Style, columns: usize) -> (usize, usize, usize) {
+        let span = (style.resolved_grid_column_span() as usize).clamp(1, columns.max(1));
+        if self.column + span > columns {
+            self.row += 1;
+            self.column = 0;
+        }
+        let column = self.column;
+        self.column += span;
+        (self.row, column, span)
+    }
+}
+
+struct GridItem {
+    node: Rc<RefCell<Node>>,
+    style: Style,
+    row: usize,
+    column: usize,
+    span: usize,
+}
+
+/// The sizes of `tracks` in `available` pixels with `gap` between each: lengths first, with
+/// percentages of `available`, then what they and the gaps leave shared among the `fr`
+/// tracks by their factors. Factors adding up to less than 1 leave some of it unused.
+fn track_sizes(tracks: &[GridTrack], available: f64, gap: f64) -> Vec<f64> {
+    let gaps = gap * tracks.len().saturating_sub(1) as f64;
+    let fixed: f64 = tracks
+        .iter()
+        .map(|track| match track {
+            GridTrack::Length(length) => length.resolve_percentage(available).to_px(),
+            GridTrack::Fr(_) => 0.0,
+        })
+        .sum();
+    let total_fr: f64 = tracks
+        .iter()
+        .map(|track| match track {
+            GridTrack::Fr(fr) => *fr,
+            GridTrack::Length(_) => 0.0,
+        })
+        .sum();
+    let per_fr = (available - fixed - gaps).max(0.0) / total_fr.max(1.0);
+
+    tracks
+        .iter()
+        .map(|track| match track {
+            GridTrack::Length(length) => length.resolve_percentage(available).to_px().max(0.0),
+            GridTrack::Fr(fr) => fr * per_fr,
+        })
+        .collect()
+}
+
+/// Where the track at `index` starts, after those before it and their gaps.
+fn track_offset(sizes: &[f64], index: usize, gap: f64) -> f64 {
+    sizes[..index].iter().sum::<f64>() + gap * index as f64
+}
+
+/// How far `count` tracks from `start` reach, the gaps between them included.
+fn span_size(sizes: &[f64], start: usize, count: usize, gap: f64) -> f64 {
+    let tracks = &sizes[start..start + count];
+    tracks.iter().sum::<f64>() + gap * count.saturating_sub(1) as f64
+}
+
+/// The border-box width of an item in a cell `cell_width` pixels wide: what its style sets,
+/// or the cell's less its margins.
+fn item_width(style: &Style, cell_width: f64) -> f64 {
+    let margin = style.margin.resolved();
+    let padding = style.padding.resolved();
+    let border = style.used_border_width();
+    specified_border_box(
+        style.width.as_ref(),
+        padding.left.to_px() + padding.right.to_px() + border.left.to_px() + border.right.to_px(),
+        style.box_sizing.unwrap_or(BoxSizing::ContentBox),
+    )
+    .unwrap_or(cell_width - margin.left.to_px() - margin.right.to_px())
+    .max(0.0)
+}
+
+fn has_specified_height(style: &Style) -> bool {
+    matches!(style.height, Some(Length::Px(px)) if px > 0.0)
+}
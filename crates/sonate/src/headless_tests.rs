@@ -0,0 +1,389 @@
+use crate::commands::Command;
+use crate::{DocumentErrorKind, Engine, EngineError, ErrorCode, Id, Rect};
+use skia_safe::{FontMgr, FontStyle};
+use std::collections::HashSet;
+use std::sync::mpsc::sync_channel;
+use std::time::{Duration, Instant};
+
+fn build_document(engine: &Engine) -> (Id, Id) {
+    engine.add_stylesheet(
+        r#"
+        .row { display: flex; flex-direction: row; }
+        .box { width: 50px; height: 20px; }
+        .label { width: auto; height: auto; font-size: 20px; }
+    "#,
+    );
+
+    let root = engine.root_id();
+    let item = engine.create_node(Id::from_u64(1), None);
+    let label = engine.create_node(Id::from_u64(2), Some("Headless".to_owned()));
+    engine.set_parent(root, item);
+    engine.set_parent(root, label);
+    engine.set_attribute(root, "class".to_owned(), "row".to_owned());
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+    engine.set_attribute(label, "class".to_owned(), "label".to_owned());
+
+    (item, label)
+}
+
+#[test]
+fn headless_layout_applies_pending_changes_and_viewport() {
+    let engine = Engine::new();
+    let (item, label) = build_document(&engine);
+
+    // No waiting for the layout debounce: the snapshot already reflects every change.
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    assert_eq!(snapshot.bounds, Rect::new(0.0, 0.0, 400.0, 300.0));
+    assert_eq!(
+        snapshot.find(item).unwrap().bounds,
+        Rect::new(0.0, 0.0, 50.0, 20.0)
+    );
+
+    // Text is measured without a GPU context.
+    let label_bounds = snapshot.find(label).unwrap().bounds;
+    assert_eq!(label_bounds.x, 50.0);
+    assert!(label_bounds.width > 0.0 && label_bounds.height > 0.0);
+
+    assert_eq!(engine.snapshot(), Some(snapshot));
+
+    engine.set_attribute(item, "class".to_owned(), String::new());
+    let snapshot = engine.layout_headless(200.0, 100.0).unwrap();
+    assert_eq!(snapshot.bounds, Rect::new(0.0, 0.0, 200.0, 100.0));
+    assert_eq!(snapshot.find(item).unwrap().bounds.width, 0.0);
+}
+
+#[test]
+fn width_media_queries_follow_the_viewport() {
+    let engine = Engine::new();
+    let (item, label) = build_document(&engine);
+    engine.add_stylesheet(
+        "@media (min-width: 300px) and (max-width: 600px) { .row { flex-direction: column; } }",
+    );
+
+    let wide = engine.layout_headless(800.0, 300.0).unwrap();
+    assert_eq!(wide.find(label).unwrap().bounds.x, 50.0);
+
+    let narrow = engine.layout_headless(400.0, 300.0).unwrap();
+    let label_bounds = narrow.find(label).unwrap().bounds;
+    assert_eq!((label_bounds.x, label_bounds.y), (0.0, 20.0));
+    assert_eq!(narrow.find(item).unwrap().bounds.y, 0.0);
+
+    // Back above the breakpoint.
+    let wide = engine.layout_headless(601.0, 300.0).unwrap();
+    assert_eq!(wide.find(label).unwrap().bounds.x, 50.0);
+}
+
+#[test]
+fn every_snapshot_comes_with_a_hit_index() {
+    let engine = Engine::new();
+    assert_eq!(engine.hit_index_stats(), None);
+
+    let (item, _label) = build_document(&engine);
+    engine.layout_headless(400.0, 300.0).unwrap();
+    assert_eq!(engine.hit_index_stats().unwrap().nodes, 3);
+    let snapshot = engine.get_current_snapshot().unwrap();
+    assert_eq!(snapshot.find_element_at_position(10.0, 10.0)[0], item);
+
+    let extra = engine.create_node(Id::from_u64(3), None);
+    engine.set_parent(engine.root_id(), extra);
+    engine.layout_headless(400.0, 300.0).unwrap();
+    let stats = engine.hit_index_stats().unwrap();
+    assert_eq!(stats.nodes, 4);
+    assert!(stats.entries >= stats.nodes && stats.bytes > 0);
+}
+
+#[test]
+fn changing_text_remeasures_the_node() {
+    let engine = Engine::new();
+    let (_, label) = build_document(&engine);
+    let width = |engine: &Engine| {
+        let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+        snapshot.find(label).unwrap().bounds.width
+    };
+    let short = width(&engine);
+
+    engine.set_text(label, Some("Headless, and longer".to_owned()));
+    let long = width(&engine);
+    assert!(long > short, "{long} <= {short}");
+
+    // Without text the label is sized like an empty container.
+    let empty = engine.create_node(Id::from_u64(3), None);
+    engine.set_parent(engine.root_id(), empty);
+    engine.set_attribute(empty, "class".to_owned(), "label".to_owned());
+    engine.set_text(label, None);
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    let node = snapshot.find(label).unwrap();
+    assert_eq!(node.text, None);
+    let empty = snapshot.find(empty).unwrap();
+    assert_eq!(
+        (node.bounds.width, node.bounds.height),
+        (empty.bounds.width, empty.bounds.height)
+    );
+}
+
+#[test]
+fn headless_layout_is_refused_while_running() {
+    let engine = Engine::new();
+    build_document(&engine);
+
+    // Stand in for `run`, which holds this lock while the window is open.
+    let _running = engine.running.lock().unwrap();
+    assert!(matches!(
+        engine.clone().layout_headless(400.0, 300.0),
+        Err(EngineError::Reentrancy)
+    ));
+}
+
+#[test]
+fn frame_callback_changes_skip_the_debounce() {
+    let engine = Engine::new();
+    let (item, _) = build_document(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+    engine.add_stylesheet(".wide { width: 80px; height: 20px; }");
+
+    // What Params::on_frame does after calling back.
+    let sent = Instant::now();
+    engine.set_attribute(item, "class".to_owned(), "wide".to_owned());
+    engine.sender.send(Command::Frame).unwrap();
+
+    let width = || engine.snapshot().unwrap().find(item).unwrap().bounds.width;
+    while width() != 80.0 {
+        std::thread::sleep(Duration::from_millis(1));
+        assert!(sent.elapsed() < Duration::from_secs(5), "never laid out");
+    }
+    // Layout is otherwise debounced by 100ms.
+    assert!(sent.elapsed() < Duration::from_millis(100));
+}
+
+#[test]
+fn bad_tree_changes_are_skipped_and_later_ones_applied() {
+    let engine = Engine::new();
+    let (item, _) = build_document(&engine);
+
+    engine
+        .try_set_parent(Id::from_u64(404), item)
+        .expect("reported on the data thread, not to the caller");
+    engine.set_parent(item, engine.root_id());
+    engine.add_stylesheet(".box { width: 70px; }");
+
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    assert_eq!(snapshot.children[0].id, item);
+    assert_eq!(snapshot.find(item).unwrap().bounds.width, 70.0);
+}
+
+#[test]
+fn refused_reorders_name_the_node_and_why() {
+    let engine = Engine::new();
+    let (item, _) = build_document(&engine);
+    let stray = engine.create_node(Id::from_u64(77), None);
+
+    let err = engine.move_child(engine.root_id(), stray, 0).unwrap_err();
+    assert!(matches!(
+        err,
+        EngineError::Document {
+            kind: DocumentErrorKind::NotAChild,
+            id: Some(77)
+        }
+    ));
+    assert_eq!(err.code(), ErrorCode::DocumentNotAChild);
+    assert_eq!(
+        engine
+            .insert_before(Id::from_u64(404), stray, item)
+            .unwrap_err()
+            .code(),
+        ErrorCode::DocumentParentNotFound
+    );
+}
+
+#[test]
+fn nodes_are_not_created_over_others() {
+    let engine = Engine::new();
+    assert_eq!(
+        engine.create_node_with_id(Id::from_u64(5)).unwrap(),
+        Id::from_u64(5)
+    );
+    let in_use = |result: Result<Id, EngineError>, expected: u64| {
+        matches!(
+            result,
+            Err(EngineError::Document {
+                kind: DocumentErrorKind::IdInUse,
+                id: Some(id),
+            }) if id == expected
+        )
+    };
+    assert!(in_use(engine.create_node_with_id(Id::from_u64(5)), 5));
+    assert!(in_use(engine.create_node_with_id(engine.root_id()), 0));
+    let generated = Id::from_u64(Id::GENERATED_BASE + 3);
+    assert_eq!(
+        engine.create_node_with_id(generated).unwrap_err().code(),
+        ErrorCode::DocumentIdInUse
+    );
+
+    // Without waiting, the node is kept as it was.
+    engine.set_text(Id::from_u64(5), Some("first".to_owned()));
+    engine.set_parent(engine.root_id(), Id::from_u64(5));
+    engine.create_node(Id::from_u64(5), Some("second".to_owned()));
+    let snapshot = engine.layout_headless(200.0, 100.0).unwrap();
+    assert_eq!(
+        snapshot.find(Id::from_u64(5)).unwrap().text.as_deref(),
+        Some("first")
+    );
+}
+
+#[test]
+fn generated_and_chosen_ids_mix_without_colliding() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    let mut ids = HashSet::new();
+    for n in 1..=10_000 {
+        let id = if n % 3 == 0 {
+            engine.create_node_auto()
+        } else {
+            engine.create_node_with_id(Id::from_u64(n)).unwrap()
+        };
+        assert!(ids.insert(id), "{id:?} given out twice");
+        engine.set_parent(root, id);
+    }
+    assert_eq!(ids.iter().filter(|id| id.is_generated()).count(), 3_333);
+
+    let snapshot = engine.layout_headless(200.0, 100.0).unwrap();
+    assert_eq!(snapshot.children.len(), 10_000);
+}
+
+#[test]
+fn the_tree_can_be_walked_from_the_engine() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    let id = Id::from_u64;
+    for (parent, child) in [(0, 1), (1, 2), (1, 3), (2, 4), (0, 5)] {
+        engine.create_node(id(child), None);
+        engine.set_parent(id(parent), id(child));
+    }
+    engine.create_node(id(6), None);
+    engine.move_child(id(1), id(3), 0).unwrap();
+
+    assert_eq!(engine.children(root), vec![id(1), id(5)]);
+    assert_eq!(engine.children(id(1)), vec![id(3), id(2)]);
+    assert_eq!(engine.parent(id(4)), Some(id(2)));
+    assert_eq!(engine.parent(root), None);
+    assert_eq!(engine.parent(id(6)), None);
+    assert_eq!(
+        engine.descendants(root),
+        vec![id(1), id(3), id(2), id(4), id(5)]
+    );
+    assert!(engine.contains(id(1), id(4)));
+    assert!(engine.contains(id(4), id(4)));
+    assert!(!engine.contains(id(4), id(1)));
+    assert!(!engine.contains(root, id(6)));
+
+    // Unknown nodes have nothing around them.
+    assert!(engine.children(id(404)).is_empty());
+    assert!(engine.descendants(id(404)).is_empty());
+    assert_eq!(engine.parent(id(404)), None);
+    assert!(!engine.contains(id(404), id(404)));
+}
+
+#[test]
+fn changes_fail_instead_of_panicking_once_the_data_thread_is_gone() {
+    let mut engine = Engine::new();
+    let (sender, receiver) = sync_channel(1);
+    drop(receiver);
+    engine.sender = sender;
+    let root = engine.root_id();
+
+    assert!(matches!(
+        engine.try_add_stylesheet(".a { width: 1px; }"),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.try_create_node(Id::from_u64(1), None),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.try_set_parent(root, Id::from_u64(1)),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.try_set_attribute(root, "class".to_owned(), "a".to_owned()),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.try_set_text(root, None),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.move_child(root, Id::from_u64(1), 0),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(
+        engine.layout_headless(100.0, 100.0),
+        Err(EngineError::ShuttingDown)
+    ));
+}
+
+/// A font with one glyph, a box one em wide, for every printable ASCII character. Relative to
+/// the crate, where tests run.
+const TEST_MONO: &str = "src/text/sonate_test_mono.ttf";
+
+fn label_width(engine: &Engine, label: Id) -> f64 {
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    snapshot.find(label).unwrap().bounds.width
+}
+
+#[test]
+fn registered_fonts_are_measured_before_installed_ones() {
+    let engine = Engine::new();
+    engine.add_stylesheet(
+        r#"
+        .row { display: flex; flex-direction: row; }
+        .mono { font-family: "Sonate Test Mono", sans-serif; font-size: 20px; }
+        .replaced { font-family: "Sonate Replaced"; font-size: 20px; }
+    "#,
+    );
+    let root = engine.root_id();
+    engine.set_attribute(root, "class".to_owned(), "row".to_owned());
+    let label = engine.create_node(Id::from_u64(1), Some("Hello".to_owned()));
+    engine.set_parent(root, label);
+    engine.set_attribute(label, "class".to_owned(), "mono".to_owned());
+
+    // `sans-serif` until the family is registered, then five glyphs 20px wide.
+    let fallback = label_width(&engine, label);
+    assert_eq!(
+        engine.register_font_file(TEST_MONO, None).ok().as_deref(),
+        Some("Sonate Test Mono")
+    );
+    assert!((label_width(&engine, label) - 100.0).abs() < 0.5);
+    assert!((fallback - 100.0).abs() >= 0.5);
+
+    // Registering a family again replaces its font.
+    let data = std::fs::read(TEST_MONO).unwrap();
+    engine
+        .register_font(Some("Sonate Replaced"), &data)
+        .unwrap();
+    engine.set_attribute(label, "class".to_owned(), "replaced".to_owned());
+    assert!((label_width(&engine, label) - 100.0).abs() < 0.5);
+    let Some((system, _)) = FontMgr::default()
+        .legacy_make_typeface(None, FontStyle::normal())
+        .and_then(|typeface| typeface.to_font_data())
+    else {
+        eprintln!("skipping: no system typeface to register");
+        return;
+    };
+    engine
+        .register_font(Some("Sonate Replaced"), &system)
+        .unwrap();
+    assert!((label_width(&engine, label) - 100.0).abs() >= 0.5);
+}
+
+#[test]
+fn fonts_that_fail_to_load_are_refused() {
+    let engine = Engine::new();
+    assert!(matches!(
+        engine.register_font_file("src/text/missing.ttf", None),
+        Err(EngineError::Io(_))
+    ));
+    assert!(matches!(
+        engine.register_font(Some("Sonate Broken"), b"not a font"),
+        Err(EngineError::InvalidFont)
+    ));
+}
@@ -0,0 +1,95 @@
+use log::error;
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+
+/// Whether the data thread applies changes, see [`crate::Engine::health`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EngineHealth {
+    #[default]
+    Healthy,
+    /// Commands or layout passes panicked on the data thread and were dropped. The document
+    /// kept what everything else changed, so the engine works on; `last` is the message of
+    /// the latest panic.
+    Recovered { panics: u64, last: String },
+    /// The data thread panicked outside of a command and stopped, with the message of the
+    /// panic. The engine takes no more changes: the `try_` methods fail with
+    /// [`crate::EngineError::ShuttingDown`], the others panic, and the snapshot stays as it
+    /// was.
+    Unhealthy(String),
+}
+
+/// Told on the data thread of each panic it caught, with the health after it; see
+/// [`crate::EngineOptions::on_engine_error`].
+pub type EngineErrorHandler = Arc<dyn Fn(&EngineHealth) + Send + Sync>;
+
+/// [`EngineHealth`] as kept by the data thread.
+pub(crate) struct Health {
+    state: Mutex<EngineHealth>,
+    on_error: Option<EngineErrorHandler>,
+}
+
+impl Health {
+    pub fn new(on_error: Option<EngineErrorHandler>) -> Self {
+        Self {
+            state: Mutex::default(),
+            on_error,
+        }
+    }
+
+    pub fn get(&self) -> EngineHealth {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Record that a command or a layout pass panicked, and was dropped.
+    pub fn recovered(&self, payload: &(dyn Any + Send)) {
+        self.update(|health| {
+            let panics = match health {
+                EngineHealth::Healthy => 0,
+                EngineHealth::Recovered { panics, .. } => *panics,
+                // Nothing recovers after the thread stopped.
+                EngineHealth::Unhealthy(_) => return false,
+            };
+            *health = EngineHealth::Recovered {
+                panics: panics + 1,
+                last: panic_message(payload),
+            };
+            true
+        });
+    }
+
+    /// Record that the data thread stopped.
+    pub fn stopped(&self, payload: &(dyn Any + Send)) {
+        self.update(|health| {
+            *health = EngineHealth::Unhealthy(panic_message(payload));
+            true
+        });
+    }
+
+    /// Apply `change`, and tell the handler unless it returns `false` for no change.
+    fn update(&self, change: impl FnOnce(&mut EngineHealth) -> bool) {
+        let mut state = self.state.lock().unwrap();
+        if !change(&mut state) {
+            return;
+        }
+        let health = state.clone();
+        drop(state);
+        error!("data thread panicked: {health:?}");
+        if let Some(on_error) = &self.on_error {
+            on_error(&health);
+        }
+    }
+}
+
+/// What a panic was started with, when it's a message.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_owned()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panicked without a message".to_owned()
+    }
+}
+
+#[cfg(test)]
+mod health_tests;
@@ -0,0 +1,77 @@
+use super::{EngineHealth, Health};
+use crate::commands::Command;
+use crate::{Engine, EngineError, EngineOptions, Id};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An engine, and what its data thread reports of the panics it catches.
+fn engine() -> (Engine, Receiver<EngineHealth>) {
+    let (sender, receiver) = channel();
+    let engine = Engine::with_options(EngineOptions {
+        on_engine_error: Some(Arc::new(move |health: &EngineHealth| {
+            let _ = sender.send(health.clone());
+        })),
+        ..Default::default()
+    });
+    (engine, receiver)
+}
+
+#[test]
+fn a_command_that_panics_is_dropped_and_the_document_kept() {
+    let (engine, errors) = engine();
+    let root = engine.root_id();
+    engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, Id::from_u64(1));
+    engine.send(Command::Panic).unwrap();
+    engine.create_node(Id::from_u64(2), None);
+    engine.set_parent(root, Id::from_u64(2));
+
+    // Answered after the panic, so the data thread recorded it.
+    assert_eq!(engine.children(root), [Id::from_u64(1), Id::from_u64(2)]);
+    let recovered = EngineHealth::Recovered {
+        panics: 1,
+        last: "the command panicked".to_owned(),
+    };
+    assert_eq!(engine.health(), recovered);
+    assert_eq!(errors.try_recv(), Ok(recovered));
+
+    let snapshot = engine.layout_headless(200.0, 100.0).unwrap();
+    assert!(snapshot.find(Id::from_u64(2)).is_some());
+}
+
+#[test]
+fn a_panic_outside_of_commands_stops_the_engine() {
+    let (engine, errors) = engine();
+    assert_eq!(engine.health(), EngineHealth::Healthy);
+    engine.send(Command::PanicThread).unwrap();
+
+    let stopped = EngineHealth::Unhealthy("the data thread panicked".to_owned());
+    assert_eq!(
+        errors.recv_timeout(Duration::from_secs(5)),
+        Ok(stopped.clone())
+    );
+    assert_eq!(engine.health(), stopped);
+    assert!(matches!(
+        engine.try_create_node(Id::from_u64(1), None),
+        Err(EngineError::ShuttingDown)
+    ));
+}
+
+#[test]
+fn panics_are_counted_until_the_thread_stops() {
+    let health = Health::new(None);
+    health.recovered(&"first");
+    health.recovered(&42);
+    assert_eq!(
+        health.get(),
+        EngineHealth::Recovered {
+            panics: 2,
+            last: "panicked without a message".to_owned(),
+        }
+    );
+
+    health.stopped(&String::from("stopped"));
+    health.recovered(&"after");
+    assert_eq!(health.get(), EngineHealth::Unhealthy("stopped".to_owned()));
+}
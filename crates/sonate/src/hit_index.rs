@@ -0,0 +1,313 @@
+//! A spatial index over the border boxes of a laid out tree, so that hit testing a point
+//! only visits the nodes around it rather than the whole tree.
+//!
+//! The index is a stack of grids over the tree's extent, each with half the resolution of
+//! the one below. A node is kept in the finest grid where it covers at most four cells, so
+//! every node is kept at most four times however large it is. A query collects the nodes
+//! whose bounds contain the point from the one cell under it in each grid; the topmost of
+//! them is then found as a full hit test would, visiting only their ancestors.
+
+use crate::damage::TreeDamage;
+use crate::layout::{Rect, RenderNode};
+use crate::style::Style;
+use crate::Id;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Nodes per cell of the finest grid the index aims for.
+const NODES_PER_CELL: usize = 2;
+
+/// The finest grid has at most 2^8 by 2^8 cells.
+const MAX_GRID_SHIFT: u32 = 8;
+
+/// The most cells of a grid a node is kept in.
+const MAX_CELLS_PER_NODE: usize = 4;
+
+/// Size of the hit index of a snapshot, see [`crate::Engine::hit_index_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HitIndexStats {
+    /// Nodes in the snapshot.
+    pub nodes: usize,
+    /// Cells of all grids.
+    pub cells: usize,
+    /// Nodes kept in cells, at most four per node.
+    pub entries: usize,
+    /// Memory taken by the index.
+    pub bytes: usize,
+}
+
+/// A laid out tree with its hit index, as the data thread publishes it. Immutable: the next
+/// layout publishes a new one.
+pub(crate) struct IndexedTree {
+    pub tree: Arc<RenderNode>,
+    pub hit_index: HitIndex,
+    /// What changed since the tree published before, if there was one.
+    pub damage: Option<TreeDamage>,
+    /// What tooltips over the tree are painted with.
+    pub tooltip_style: Arc<Style>,
+}
+
+impl IndexedTree {
+    /// The tree published after `previous`, if there was one.
+    pub fn after(
+        previous: Option<&Arc<IndexedTree>>,
+        tree: Arc<RenderNode>,
+        tooltip_style: Arc<Style>,
+    ) -> Self {
+        let hit_index = HitIndex::new(&tree);
+        let damage = TreeDamage::new(previous, &tree);
+        Self {
+            tree,
+            hit_index,
+            damage,
+            tooltip_style,
+        }
+    }
+
+    /// The same as [`RenderNode::find_element_at_position`] on the tree, through the index.
+    pub fn find_element_at_position(&self, x: f64, y: f64) -> Vec<Id> {
+        self.hit_index.find_element_at_position(&self.tree, x, y)
+    }
+}
+
+impl Deref for IndexedTree {
+    type Target = RenderNode;
+
+    fn deref(&self) -> &RenderNode {
+        &self.tree
+    }
+}
+
+/// Where a node is in the tree, by its index in pre-order.
+struct IndexedNode {
+    bounds: Rect,
+    /// `None` for the root.
+    parent: Option<u32>,
+    /// Among the parent's children.
+    child_index: u32,
+}
+
+/// A grid with the nodes in each cell, row by row.
+struct Grid {
+    /// Cells per side.
+    size: usize,
+    /// Where the nodes of each cell start in `entries`, with where the last one ends.
+    starts: Vec<u32>,
+    entries: Vec<u32>,
+}
+
+pub(crate) struct HitIndex {
+    /// In pre-order, so the root is the first.
+    nodes: Vec<IndexedNode>,
+    /// The union of the nodes' bounds, which the grids cover.
+    extent: Rect,
+    /// Cells per side of the finest grid, `grids[0]`; each next grid has half as many.
+    size: usize,
+    grids: Vec<Grid>,
+    /// Nodes whose bounds aren't finite, which may contain any point.
+    unplaced: Vec<u32>,
+}
+
+impl HitIndex {
+    pub fn new(tree: &RenderNode) -> Self {
+        let mut nodes = Vec::new();
+        collect(tree, None, 0, &mut nodes);
+
+        let is_finite = |rect: &Rect| {
+            [rect.x, rect.y, rect.width, rect.height]
+                .iter()
+                .all(|v| v.is_finite())
+        };
+        let extent = nodes
+            .iter()
+            .map(|node| node.bounds)
+            .filter(is_finite)
+            .reduce(|union, bounds| union.union(&bounds))
+            .unwrap_or_default();
+
+        let shift = (nodes.len() / NODES_PER_CELL).max(1).ilog2().div_ceil(2);
+        let shift = shift.min(MAX_GRID_SHIFT);
+        let mut index = Self {
+            nodes: Vec::new(),
+            extent,
+            size: 1 << shift,
+            grids: Vec::new(),
+            unplaced: Vec::new(),
+        };
+
+        // The cells each node is kept in, by grid.
+        let mut cells_by_grid: Vec<Vec<(usize, u32)>> = vec![Vec::new(); shift as usize + 1];
+        for (position, node) in nodes.iter().enumerate() {
+            let position = position as u32;
+            if !is_finite(&node.bounds) {
+                index.unplaced.push(position);
+                continue;
+            }
+            let (left, top) = index.cell_at(node.bounds.x, node.bounds.y);
+            let (right, bottom) = index.cell_at(
+                node.bounds.x + node.bounds.width,
+                node.bounds.y + node.bounds.height,
+            );
+            let level = (0..=shift)
+                .find(|level| {
+                    let columns = (right >> level) - (left >> level) + 1;
+                    let rows = (bottom >> level) - (top >> level) + 1;
+                    columns * rows <= MAX_CELLS_PER_NODE
+                })
+                .unwrap_or(shift);
+            let size = index.size >> level;
+            for row in top >> level..=bottom >> level {
+                for column in left >> level..=right >> level {
+                    cells_by_grid[level as usize].push((row * size + column, position));
+                }
+            }
+        }
+
+        for (level, mut cells) in cells_by_grid.into_iter().enumerate() {
+            let size = index.size >> level;
+            cells.sort_unstable();
+            let mut starts = Vec::with_capacity(size * size + 1);
+            let mut next = 0;
+            for cell in 0..size * size {
+                starts.push(next as u32);
+                while next < cells.len() && cells[next].0 == cell {
+                    next += 1;
+                }
+            }
+            starts.push(next as u32);
+            let entries = cells.into_iter().map(|(_, position)| position).collect();
+            index.grids.push(Grid {
+                size,
+                starts,
+                entries,
+            });
+        }
+
+        index.nodes = nodes;
+        index
+    }
+
+    /// The same as [`RenderNode::find_element_at_position`] on `tree`, which the index
+    /// was made from.
+    pub fn find_element_at_position(&self, tree: &RenderNode, x: f64, y: f64) -> Vec<Id> {
+        let candidates = self.candidates(x, y);
+
+        // The children to visit of each node that has candidates under it, in document
+        // order: the candidates and their ancestors.
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for candidate in candidates {
+            let mut position = candidate;
+            while let Some(parent) = self.nodes[position as usize].parent {
+                let siblings = children.entry(parent).or_default();
+                let known = !siblings.is_empty();
+                siblings.push(position);
+                if known {
+                    break;
+                }
+                position = parent;
+            }
+        }
+        for siblings in children.values_mut() {
+            siblings.sort_unstable();
+            siblings.dedup();
+        }
+
+        self.hit_test(tree, 0, x, y, &children).unwrap_or_default()
+    }
+
+    pub fn stats(&self) -> HitIndexStats {
+        let cells = self.grids.iter().map(|grid| grid.size * grid.size).sum();
+        let entries = self.grids.iter().map(|grid| grid.entries.len()).sum();
+        let bytes = std::mem::size_of::<Self>()
+            + self.nodes.capacity() * std::mem::size_of::<IndexedNode>()
+            + self.grids.capacity() * std::mem::size_of::<Grid>()
+            + self
+                .grids
+                .iter()
+                .map(|grid| (grid.starts.capacity() + grid.entries.capacity()) * 4)
+                .sum::<usize>()
+            + self.unplaced.capacity() * 4;
+        HitIndexStats {
+            nodes: self.nodes.len(),
+            cells,
+            entries,
+            bytes,
+        }
+    }
+
+    /// The nodes whose bounds contain the point.
+    fn candidates(&self, x: f64, y: f64) -> Vec<u32> {
+        let (column, row) = self.cell_at(x, y);
+        self.grids
+            .iter()
+            .enumerate()
+            .flat_map(|(level, grid)| {
+                let cell = (row >> level) * grid.size + (column >> level);
+                let (start, end) = (grid.starts[cell], grid.starts[cell + 1]);
+                &grid.entries[start as usize..end as usize]
+            })
+            .chain(&self.unplaced)
+            .copied()
+            .filter(|&position| self.nodes[position as usize].bounds.contains_point(x, y))
+            .collect()
+    }
+
+    /// The cell of the finest grid the point is in, or nearest to. A node covers the cells
+    /// from the one its top left corner is in to that of its bottom right corner.
+    fn cell_at(&self, x: f64, y: f64) -> (usize, usize) {
+        let cell = |v: f64, start: f64, length: f64| {
+            if length <= 0.0 {
+                return 0;
+            }
+            // Saturates to 0 for NaN and negative values.
+            let cell = ((v - start) / length * self.size as f64).floor() as usize;
+            cell.min(self.size - 1)
+        };
+        (
+            cell(x, self.extent.x, self.extent.width),
+            cell(y, self.extent.y, self.extent.height),
+        )
+    }
+
+    fn hit_test(
+        &self,
+        node: &RenderNode,
+        position: u32,
+        x: f64,
+        y: f64,
+        children: &HashMap<u32, Vec<u32>>,
+    ) -> Option<Vec<Id>> {
+        let hittable = children
+            .get(&position)
+            .map(|positions| {
+                positions
+                    .iter()
+                    .map(|&child| {
+                        let child_index = self.nodes[child as usize].child_index;
+                        (&*node.children[child_index as usize], child)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        node.hit_test_among(x, y, hittable, |child, child_position| {
+            self.hit_test(child, child_position, x, y, children)
+        })
+    }
+}
+
+/// Add `node` and its descendants to `nodes` in pre-order.
+fn collect(node: &RenderNode, parent: Option<u32>, child_index: u32, nodes: &mut Vec<IndexedNode>) {
+    let position = nodes.len() as u32;
+    nodes.push(IndexedNode {
+        bounds: node.bounds,
+        parent,
+        child_index,
+    });
+    for (child_index, child) in node.children.iter().enumerate() {
+        collect(child, Some(position), child_index as u32, nodes);
+    }
+}
+
+#[cfg(test)]
+mod hit_index_tests;
@@ -0,0 +1,218 @@
+use super::*;
+use crate::layout::descendant_bounds;
+use crate::snapshot::Edges;
+use crate::style::{Overflow, Style, Visibility, ZIndex};
+use std::sync::Arc;
+
+fn node(id: u64, bounds: Rect, style: Style, children: Vec<RenderNode>) -> RenderNode {
+    let children: Vec<_> = children.into_iter().map(Arc::new).collect();
+    RenderNode {
+        id: Id::from_u64(id),
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::resolved(&style.padding),
+        border_width: Edges::from(&style.used_border_width()),
+        descendant_bounds: descendant_bounds(bounds, &style, &children),
+        z_index: style.resolved_z_index(),
+        style: Arc::new(style),
+        text: None,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children,
+    }
+}
+
+/// Deterministic pseudo-random numbers, so failures can be reproduced.
+struct Lcg(u64);
+
+impl Lcg {
+    fn below(&mut self, bound: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % bound
+    }
+}
+
+/// Nodes overlapping their siblings and overflowing their parents, with negative and
+/// positive z-indices, hidden subtrees, clipping and empty boxes.
+fn random_tree(rng: &mut Lcg, next_id: &mut u64, depth: u32, bounds: Rect) -> RenderNode {
+    let id = *next_id;
+    *next_id += 1;
+    let children = if depth == 0 {
+        Vec::new()
+    } else {
+        (0..rng.below(6))
+            .map(|_| {
+                let child = Rect {
+                    x: bounds.x + rng.below(bounds.width as u64 + 40) as f64 - 20.0,
+                    y: bounds.y + rng.below(bounds.height as u64 + 40) as f64 - 20.0,
+                    width: rng.below(bounds.width as u64 / 2 + 20) as f64,
+                    height: rng.below(bounds.height as u64 / 2 + 20) as f64,
+                };
+                random_tree(rng, next_id, depth - 1, child)
+            })
+            .collect()
+    };
+    let style = Style {
+        z_index: [None, None, None, Some(-1), Some(2)][rng.below(5) as usize].map(ZIndex::Integer),
+        visibility: (rng.below(10) == 0).then_some(Visibility::Hidden),
+        overflow: (rng.below(6) == 0).then_some(Overflow::Hidden),
+        ..Default::default()
+    };
+    node(id, bounds, style, children)
+}
+
+/// `columns` by `rows` cells of 10 by 10 pixels, in rows.
+fn grid_scene(columns: u64, rows: u64) -> RenderNode {
+    let mut next_id = 1;
+    let mut id = || {
+        next_id += 1;
+        next_id
+    };
+    let row_nodes = (0..rows)
+        .map(|row| {
+            let y = row as f64 * 10.0;
+            let cells = (0..columns)
+                .map(|column| {
+                    let bounds = Rect::new(column as f64 * 10.0, y, 10.0, 10.0);
+                    node(id(), bounds, Style::default(), Vec::new())
+                })
+                .collect();
+            let bounds = Rect::new(0.0, y, columns as f64 * 10.0, 10.0);
+            node(id(), bounds, Style::default(), cells)
+        })
+        .collect();
+    let bounds = Rect::new(0.0, 0.0, columns as f64 * 10.0, rows as f64 * 10.0);
+    node(1, bounds, Style::default(), row_nodes)
+}
+
+/// How many nodes a query at the point checks the bounds of.
+fn entries_scanned(index: &HitIndex, x: f64, y: f64) -> usize {
+    let (column, row) = index.cell_at(x, y);
+    let in_grids: usize = index
+        .grids
+        .iter()
+        .enumerate()
+        .map(|(level, grid)| {
+            let cell = (row >> level) * grid.size + (column >> level);
+            (grid.starts[cell + 1] - grid.starts[cell]) as usize
+        })
+        .sum();
+    in_grids + index.unplaced.len()
+}
+
+/// Points on and around the edges of every node, and across and around the tree.
+fn query_points(tree: &RenderNode) -> Vec<(f64, f64)> {
+    fn edges(node: &RenderNode, points: &mut Vec<(f64, f64)>) {
+        let Rect {
+            x,
+            y,
+            width,
+            height,
+        } = node.bounds;
+        for px in [x - 0.5, x, x + width / 2.0, x + width, x + width + 0.5] {
+            for py in [y - 0.5, y, y + height / 2.0, y + height, y + height + 0.5] {
+                points.push((px, py));
+            }
+        }
+        for child in &node.children {
+            edges(child, points);
+        }
+    }
+    let mut points = Vec::new();
+    edges(tree, &mut points);
+    for y in (-30..240).step_by(3) {
+        for x in (-30..340).step_by(3) {
+            points.push((x as f64, y as f64));
+        }
+    }
+    points
+}
+
+#[test]
+fn the_index_agrees_with_a_full_hit_test_everywhere() {
+    for seed in 0..8 {
+        let mut rng = Lcg(seed);
+        let mut next_id = 1;
+        let tree = random_tree(&mut rng, &mut next_id, 4, Rect::new(0.0, 0.0, 300.0, 200.0));
+        let index = HitIndex::new(&tree);
+
+        for (x, y) in query_points(&tree) {
+            assert_eq!(
+                index.find_element_at_position(&tree, x, y),
+                tree.find_element_at_position_exhaustive(x, y),
+                "seed {seed} at ({x}, {y})"
+            );
+        }
+    }
+}
+
+#[test]
+fn nodes_without_finite_bounds_are_still_hit() {
+    let unbounded = Rect::new(0.0, 0.0, f64::INFINITY, f64::INFINITY);
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 100.0, 100.0),
+        Style::default(),
+        vec![node(2, unbounded, Style::default(), Vec::new())],
+    );
+    let index = HitIndex::new(&tree);
+    assert_eq!(index.unplaced, [1]);
+    for (x, y) in [(50.0, 50.0), (500.0, 500.0), (-1.0, 50.0)] {
+        assert_eq!(
+            index.find_element_at_position(&tree, x, y),
+            tree.find_element_at_position_exhaustive(x, y)
+        );
+    }
+}
+
+#[test]
+fn queries_scan_a_small_part_of_large_scenes() {
+    // About 2k and 20k cells.
+    let small = grid_scene(45, 45);
+    let large = grid_scene(142, 142);
+    let (small_index, large_index) = (HitIndex::new(&small), HitIndex::new(&large));
+
+    let most_scanned = |tree: &RenderNode, index: &HitIndex| {
+        let (width, height) = (tree.bounds.width, tree.bounds.height);
+        (0..400)
+            .map(|i| {
+                let (x, y) = ((i * 37 % 400) as f64, (i * 91 % 400) as f64);
+                let (x, y) = (x / 400.0 * width, y / 400.0 * height);
+                assert_eq!(
+                    index.find_element_at_position(tree, x, y),
+                    tree.find_element_at_position(x, y)
+                );
+                entries_scanned(index, x, y)
+            })
+            .max()
+            .unwrap()
+    };
+    let small_scanned = most_scanned(&small, &small_index);
+    let large_scanned = most_scanned(&large, &large_index);
+
+    let stats = large_index.stats();
+    assert_eq!(stats.nodes, 1 + 142 + 142 * 142);
+    assert!(large_scanned * 100 < stats.nodes, "scanned {large_scanned}");
+    // Ten times the nodes, not ten times the work.
+    assert!(
+        large_scanned < small_scanned * 5,
+        "scanned {small_scanned} of 2k nodes and {large_scanned} of 20k"
+    );
+}
+
+#[test]
+fn memory_grows_with_the_nodes_only() {
+    for side in [10, 100, 200] {
+        let stats = HitIndex::new(&grid_scene(side, side)).stats();
+        assert!(stats.entries <= MAX_CELLS_PER_NODE * stats.nodes);
+        assert!(stats.cells <= 2 * stats.nodes.max(4));
+        assert!(stats.bytes < 100 * stats.nodes, "{stats:?}");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::layout::LayoutContext;
+use crate::style::{BoxSizing, Length, Style};
+use crate::text::{measure_clamped, FontSpec, TextMeasurer};
+use crate::Id;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the data thread waits without commands before it warms caches, unless
+/// [`crate::EngineOptions::idle_warmup_delay`] says otherwise.
+pub const DEFAULT_IDLE_WARMUP_DELAY: Duration = Duration::from_millis(500);
+
+/// Work done ahead of layout while the engine was idle, see [`crate::Engine::warmup_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WarmupStats {
+    /// Node styles resolved by warmup rather than by a layout pass.
+    pub warmed_styles: u64,
+    /// Text measured by warmup rather than by a layout pass.
+    pub warmed_text_measurements: u64,
+    /// Text measured without a cache hit so far, by layout passes and warmup alike.
+    pub text_measurements: u64,
+}
+
+/// [`WarmupStats`] as kept by the data thread.
+#[derive(Default)]
+pub(crate) struct WarmupCounters {
+    warmed_styles: AtomicU64,
+    warmed_text_measurements: AtomicU64,
+    text_measurements: AtomicU64,
+}
+
+impl WarmupCounters {
+    pub fn stats(&self) -> WarmupStats {
+        WarmupStats {
+            warmed_styles: self.warmed_styles.load(Ordering::Relaxed),
+            warmed_text_measurements: self.warmed_text_measurements.load(Ordering::Relaxed),
+            text_measurements: self.text_measurements.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Catch up with the measurements of the context's text measurer.
+    pub fn record_text_measurements(&self, ctx: &LayoutContext) {
+        self.text_measurements
+            .store(ctx.text_measurer.measurement_count(), Ordering::Relaxed);
+    }
+}
+
+/// Low-priority cache warming for the data thread.
+///
+/// Layout only resolves styles and measures text of nodes that are displayed, so a hover or
+/// click that reveals a `display: none` subtree or attaches prepared nodes pays for cold
+/// caches. Once the thread has been idle for the configured delay, this visits every node in
+/// small units, one node each, resolving its style and measuring its text the way layout
+/// will. Nodes already in the caches cost a lookup. Nothing is laid out or published.
+pub(crate) struct IdleWarmup {
+    delay: Option<Duration>,
+    last_activity: Instant,
+    /// Whether anything changed since warmup last ran to completion.
+    stale: bool,
+    /// Nodes left to visit when warmup was preempted.
+    queue: Option<Vec<Id>>,
+    counters: Arc<WarmupCounters>,
+}
+
+impl IdleWarmup {
+    /// `delay` of `None` disables warmup.
+    pub fn new(delay: Option<Duration>, counters: Arc<WarmupCounters>, now: Instant) -> Self {
+        Self {
+            delay,
+            last_activity: now,
+            stale: true,
+            queue: None,
+            counters,
+        }
+    }
+
+    pub fn counters(&self) -> &Arc<WarmupCounters> {
+        &self.counters
+    }
+
+    /// Note that a command was handled; warmup waits for the thread to be idle again and
+    /// starts over, as the document may have changed.
+    pub fn activity(&mut self, now: Instant) {
+        self.last_activity = now;
+        self.stale = true;
+        self.queue = None;
+    }
+
+    /// When warmup should start or resume, or `None` if there is nothing to do.
+    pub fn deadline(&self) -> Option<Instant> {
+        let delay = self.delay.filter(|_| self.stale)?;
+        self.last_activity.checked_add(delay)
+    }
+
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.deadline().is_some_and(|deadline| deadline <= now)
+    }
+
+    /// Warm caches one node at a time, asking `should_yield` before each node. Returns early
+    /// when it says to; the next call resumes where this one stopped.
+    pub fn run(&mut self, ctx: &LayoutContext, mut should_yield: impl FnMut() -> bool) {
+        let queue = self.queue.get_or_insert_with(|| ctx.document.node_ids());
+
+        loop {
+            if should_yield() {
+                return;
+            }
+            let Some(id) = queue.pop() else {
+                break;
+            };
+
+            let styles_before = ctx.style_cache_misses();
+            let measurements_before = ctx.text_measurer.measurement_count();
+            warm_node(ctx, id);
+
+            let counters = &self.counters;
+            counters
+                .warmed_styles
+                .fetch_add(ctx.style_cache_misses() - styles_before, Ordering::Relaxed);
+            counters.warmed_text_measurements.fetch_add(
+                ctx.text_measurer.measurement_count() - measurements_before,
+                Ordering::Relaxed,
+            );
+            counters.record_text_measurements(ctx);
+        }
+
+        self.queue = None;
+        self.stale = false;
+    }
+}
+
+fn warm_node(ctx: &LayoutContext, id: Id) {
+    let Some(node) = ctx.document.get_node(id) else {
+        return;
+    };
+    let node = node.borrow();
+    let style = ctx.resolve_style(&node);
+
+    if let Some(text) = node.text.as_deref() {
+        if node.children.is_empty() {
+            measure_text_like_layout(ctx.text_measurer.as_ref(), text, &style);
+        }
+    }
+}
+
+/// Make the measurements layout makes for a text leaf with this style, either in its own
+/// right or as a flex item: the unwrapped size unless the width is specified and the text
+/// wraps, and the size wrapped to the content-box width if so.
+fn measure_text_like_layout(measurer: &dyn TextMeasurer, text: &str, style: &Style) {
+    let font = FontSpec::from_style(style);
+
+    match style.width {
+        Some(Length::Px(specified_width_px)) if specified_width_px > 0.0 && style.wraps_text() => {
+            let content_max_width = match style.box_sizing.unwrap_or(BoxSizing::ContentBox) {
+                BoxSizing::ContentBox => specified_width_px,
+                BoxSizing::BorderBox => {
+                    let padding = style.padding.resolved();
+                    let border = style.used_border_width();
+                    let horizontal = padding.left.to_px()
+                        + padding.right.to_px()
+                        + border.left.to_px()
+                        + border.right.to_px();
+                    (specified_width_px - horizontal).max(0.0)
+                }
+            };
+            let clamp = style.resolved_line_clamp();
+            measure_clamped(measurer, text, &font, content_max_width, clamp);
+        }
+        _ => {
+            measurer.measure_unwrapped(text, &font);
+        }
+    }
+}
+
+#[cfg(test)]
+mod idle_tests;
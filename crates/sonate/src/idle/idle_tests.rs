@@ -0,0 +1,172 @@
+use super::{IdleWarmup, WarmupCounters};
+use crate::css_parser::parse_css;
+use crate::layout::LayoutContext;
+use crate::text::SkiaTextMeasurer;
+use crate::{Engine, EngineOptions, Id};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const DELAY: Duration = Duration::from_millis(500);
+
+/// A row with a visible label and a hidden panel of two labels.
+fn document_with_hidden_panel() -> (LayoutContext, Id) {
+    let mut ctx = LayoutContext::new();
+    ctx.text_measurer = Arc::new(SkiaTextMeasurer::new());
+    let css = r#"
+        .row { display: flex; flex-direction: row; }
+        .hidden { display: none; }
+        .label { font-size: 20px; }
+    "#;
+    for rule in parse_css(css).unwrap().rules {
+        ctx.style_sheet.add_rule(rule);
+    }
+
+    let document = &mut ctx.document;
+    let root = document.root_id();
+    document.set_attribute(root, "class".to_owned(), "row".to_owned());
+    let visible = document
+        .create_node(Id::from_u64(1), Some("Visible".to_owned()))
+        .unwrap();
+    let panel = document.create_node(Id::from_u64(2), None).unwrap();
+    for (id, text) in [(3, "First hidden"), (4, "Second hidden")] {
+        let label = document
+            .create_node(Id::from_u64(id), Some(text.to_owned()))
+            .unwrap();
+        document.set_attribute(label, "class".to_owned(), "label".to_owned());
+        document.set_parent(panel, label).unwrap();
+    }
+    document.set_parent(root, visible).unwrap();
+    document.set_parent(root, panel).unwrap();
+    document.set_attribute(panel, "class".to_owned(), "row hidden".to_owned());
+
+    (ctx, panel)
+}
+
+#[test]
+fn warmup_starts_once_idle_for_the_delay() {
+    let t0 = Instant::now();
+    let mut idle = IdleWarmup::new(Some(DELAY), Arc::default(), t0);
+    assert_eq!(idle.deadline(), Some(t0 + DELAY));
+    assert!(!idle.is_due(t0 + DELAY - Duration::from_millis(1)));
+
+    // Every command pushes the deadline back.
+    let t1 = t0 + Duration::from_millis(300);
+    idle.activity(t1);
+    assert!(!idle.is_due(t0 + DELAY));
+    assert!(idle.is_due(t1 + DELAY));
+
+    // Nothing to do until the next command once warmup completed.
+    let (ctx, _) = document_with_hidden_panel();
+    idle.run(&ctx, || false);
+    assert_eq!(idle.deadline(), None);
+    idle.activity(t1 + DELAY);
+    assert_eq!(idle.deadline(), Some(t1 + DELAY * 2));
+
+    let disabled = IdleWarmup::new(None, Arc::default(), t0);
+    assert_eq!(disabled.deadline(), None);
+}
+
+#[test]
+fn warmed_text_is_not_measured_again_when_revealed() {
+    let (mut ctx, panel) = document_with_hidden_panel();
+    let counters = Arc::new(WarmupCounters::default());
+    let t0 = Instant::now();
+    let mut idle = IdleWarmup::new(Some(DELAY), Arc::clone(&counters), t0);
+
+    ctx.layout();
+    let measured_by_layout = ctx.text_measurer.measurement_count();
+    assert_eq!(measured_by_layout, 1);
+
+    idle.activity(t0);
+    assert!(idle.is_due(t0 + DELAY));
+    idle.run(&ctx, || false);
+    let stats = counters.stats();
+    assert_eq!(stats.warmed_text_measurements, 2);
+    // The hidden panel's labels; everything else was resolved by layout.
+    assert_eq!(stats.warmed_styles, 2);
+    assert_eq!(stats.text_measurements, measured_by_layout + 2);
+
+    // Running again finds everything cached.
+    idle.activity(t0);
+    idle.run(&ctx, || false);
+    assert_eq!(counters.stats().warmed_text_measurements, 2);
+    assert_eq!(counters.stats().warmed_styles, 2);
+
+    ctx.document
+        .set_attribute(panel, "class".to_owned(), "row".to_owned());
+    ctx.layout();
+    assert_eq!(
+        ctx.text_measurer.measurement_count(),
+        measured_by_layout + 2
+    );
+
+    let first = ctx.document.get_node(Id::from_u64(3)).unwrap();
+    assert!(first.borrow().layout.bounds.width > 0.0);
+}
+
+#[test]
+fn warmup_yields_between_nodes() {
+    let (mut ctx, _) = document_with_hidden_panel();
+    ctx.layout();
+    let counters = Arc::new(WarmupCounters::default());
+    let mut idle = IdleWarmup::new(Some(DELAY), Arc::clone(&counters), Instant::now());
+
+    // A command arrives right after the first node.
+    let mut checks = 0;
+    idle.run(&ctx, || {
+        checks += 1;
+        checks > 1
+    });
+    assert_eq!(checks, 2);
+    assert!(
+        idle.deadline().is_some(),
+        "preempted warmup is still pending"
+    );
+
+    // Resuming visits each remaining node once.
+    let node_count = ctx.document.node_ids().len();
+    let mut resumed_checks = 0;
+    idle.run(&ctx, || {
+        resumed_checks += 1;
+        false
+    });
+    assert_eq!(resumed_checks, node_count);
+    assert_eq!(counters.stats().warmed_styles, 2);
+    assert_eq!(idle.deadline(), None);
+
+    // A command that arrived first preempts warmup before any work.
+    idle.activity(Instant::now());
+    let mut visited = 0;
+    idle.run(&ctx, || {
+        visited += 1;
+        true
+    });
+    assert_eq!(visited, 1);
+}
+
+#[test]
+fn engine_warms_hidden_content_when_idle() {
+    let engine = Engine::with_options(EngineOptions {
+        idle_warmup_delay: Some(Duration::ZERO),
+        ..Default::default()
+    });
+    engine.add_stylesheet(".hidden { display: none; }");
+    let panel = engine.create_node(Id::from_u64(1), None);
+    let label = engine.create_node(Id::from_u64(2), Some("Details".to_owned()));
+    engine.set_parent(engine.root_id(), panel);
+    engine.set_parent(panel, label);
+    engine.set_attribute(panel, "class".to_owned(), "hidden".to_owned());
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while engine.warmup_stats().warmed_text_measurements == 0 {
+        assert!(Instant::now() < deadline, "warmup didn't run");
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let measured = engine.warmup_stats().text_measurements;
+
+    engine.set_attribute(panel, "class".to_owned(), String::new());
+    let snapshot = engine.layout_headless(200.0, 100.0).unwrap();
+    assert!(snapshot.find(label).is_some());
+    assert_eq!(engine.warmup_stats().text_measurements, measured);
+}
@@ -0,0 +1,165 @@
+//! Image nodes: a node with a `src` attribute shows the image file at that path, relative to
+//! the working directory, in its content box as its `object-fit` says.
+//!
+//! Images decode on a thread of their own, not the data thread. Until one is ready its node
+//! is laid out and painted without it, showing its background; then the document is laid out
+//! again, since the image's natural size may change the node's.
+
+use crate::commands::Command;
+use crate::layout::Rect;
+use crate::resources::{CacheAttachment, ImageError, ImageHandle};
+use crate::style::ObjectFit;
+use log::warn;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Weak;
+use std::thread;
+
+/// How many decoded images the loader keeps handles to. Older ones stay in the engine's
+/// [`crate::ResourceCaches`] while they fit its budget, so showing them again doesn't
+/// decode them again.
+const CACHED_IMAGES: usize = 64;
+
+/// Where an image `width` by `height` pixels goes when fit into `content` as `fit` says,
+/// centered in it. Painting clips it to `content`, which `cover` overflows.
+pub(crate) fn object_fit_rect(fit: ObjectFit, (width, height): (f64, f64), content: Rect) -> Rect {
+    if fit == ObjectFit::Fill || width <= 0.0 || height <= 0.0 {
+        return content;
+    }
+    let (scale_x, scale_y) = (content.width / width, content.height / height);
+    let scale = match fit {
+        ObjectFit::Contain => scale_x.min(scale_y),
+        _ => scale_x.max(scale_y),
+    };
+    let (fit_width, fit_height) = (width * scale, height * scale);
+    Rect::new(
+        content.x + (content.width - fit_width) / 2.0,
+        content.y + (content.height - fit_height) / 2.0,
+        fit_width,
+        fit_height,
+    )
+}
+
+/// The images of `src` attributes, decoded or on their way.
+#[derive(Default)]
+pub(crate) struct ImageLoader {
+    /// The engine's caches, which decode images and tell the data thread when they're done.
+    /// Weak so the data thread doesn't keep the engine's command channel open.
+    resources: Weak<CacheAttachment>,
+    /// Paths to decode, started on the first one.
+    decoder: RefCell<Option<Sender<String>>>,
+    entries: RefCell<HashMap<String, Entry>>,
+    /// Paths that failed to load, which aren't tried again.
+    failed: HashSet<String>,
+    clock: Cell<u64>,
+}
+
+struct Entry {
+    /// `None` while decoding.
+    image: Option<ImageHandle>,
+    last_used: u64,
+}
+
+impl ImageLoader {
+    pub fn new(resources: Weak<CacheAttachment>) -> Self {
+        Self {
+            resources,
+            ..Default::default()
+        }
+    }
+
+    /// The decoded image at `src`, or `None` while it decodes, which the first call for it
+    /// starts, or if it failed to.
+    pub fn image(&self, src: &str) -> Option<ImageHandle> {
+        if self.failed.contains(src) {
+            return None;
+        }
+        let clock = self.clock.get() + 1;
+        self.clock.set(clock);
+        let mut entries = self.entries.borrow_mut();
+        if let Some(entry) = entries.get_mut(src) {
+            entry.last_used = clock;
+            return entry.image.clone();
+        }
+        if self.decode(src) {
+            let entry = Entry {
+                image: None,
+                last_used: clock,
+            };
+            entries.insert(src.to_owned(), entry);
+        }
+        None
+    }
+
+    /// The image at `src` finished decoding, or failed to, which is logged. Returns whether
+    /// the document should be laid out again with it.
+    pub fn loaded(&mut self, src: String, result: Result<ImageHandle, ImageError>) -> bool {
+        let entries = self.entries.get_mut();
+        let Some(entry) = entries.get_mut(&src) else {
+            return false;
+        };
+        match result {
+            Ok(image) => {
+                entry.image = Some(image);
+                self.evict();
+                true
+            }
+            Err(err) => {
+                warn!("image `{src}` failed to load: {err:?}");
+                entries.remove(&src);
+                self.failed.insert(src);
+                false
+            }
+        }
+    }
+
+    /// Drop the least recently used decoded images beyond [`CACHED_IMAGES`].
+    fn evict(&mut self) {
+        let entries = self.entries.get_mut();
+        while entries
+            .values()
+            .filter(|entry| entry.image.is_some())
+            .count()
+            > CACHED_IMAGES
+        {
+            let oldest = entries
+                .iter()
+                .filter(|(_, entry)| entry.image.is_some())
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(src, _)| src.clone());
+            if let Some(src) = oldest {
+                entries.remove(&src);
+            }
+        }
+    }
+
+    /// Queue `src` for decoding. Returns whether it was queued, which it isn't when the
+    /// engine is gone.
+    fn decode(&self, src: &str) -> bool {
+        if self.resources.strong_count() == 0 {
+            return false;
+        }
+        let mut decoder = self.decoder.borrow_mut();
+        let decoder = decoder.get_or_insert_with(|| {
+            let (tx, rx) = mpsc::channel::<String>();
+            let resources = Weak::clone(&self.resources);
+            thread::spawn(move || {
+                for src in rx {
+                    let Some(resources) = resources.upgrade() else {
+                        break;
+                    };
+                    let result = resources.image(&src, || std::fs::read(&src).ok());
+                    if !resources.send(Command::ImageLoaded(src, result)) {
+                        break;
+                    }
+                }
+            });
+            tx
+        });
+        decoder.send(src.to_owned()).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod images_tests;
@@ -0,0 +1,119 @@
+use super::object_fit_rect;
+use crate::layout::Rect;
+use crate::style::ObjectFit;
+use crate::{DiagnosticCode, Engine, Id};
+use std::time::{Duration, Instant};
+
+/// A 4 by 2 pixel PNG, relative to the crate, where tests run.
+const FIXTURE: &str = "src/images/red_4x2.png";
+
+/// Lay out until the node's bounds are `done`, which they get once its image decoded.
+fn layout_until(engine: &Engine, id: Id, done: impl Fn(&Rect) -> bool) -> Rect {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+        let bounds = snapshot.find(id).unwrap().bounds;
+        if done(&bounds) || Instant::now() > deadline {
+            return bounds;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+fn image_node(engine: &Engine, class: &str) -> Id {
+    engine.add_stylesheet(
+        r#"
+        .row { display: flex; flex-direction: row; align-items: flex-start; }
+        .sized { width: 40px; }
+        .padded { padding: 5px; height: 10px; }
+    "#,
+    );
+    let root = engine.root_id();
+    engine.set_attribute(root, "class".to_owned(), "row".to_owned());
+    let image = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, image);
+    engine.set_attribute(image, "class".to_owned(), class.to_owned());
+    engine.set_attribute(image, "src".to_owned(), FIXTURE.to_owned());
+    image
+}
+
+#[test]
+fn images_take_their_natural_size_once_decoded() {
+    let engine = Engine::new();
+    let image = image_node(&engine, "");
+
+    let bounds = layout_until(&engine, image, |bounds| bounds.width == 4.0);
+    assert_eq!(bounds, Rect::new(0.0, 0.0, 4.0, 2.0));
+}
+
+#[test]
+fn images_sized_on_one_axis_keep_their_aspect_ratio() {
+    let engine = Engine::new();
+    let image = image_node(&engine, "sized");
+    let bounds = layout_until(&engine, image, |bounds| bounds.height == 20.0);
+    assert_eq!(bounds, Rect::new(0.0, 0.0, 40.0, 20.0));
+
+    let engine = Engine::new();
+    let image = image_node(&engine, "padded");
+    // 10px high content is 20px wide, and the padding goes around it.
+    let bounds = layout_until(&engine, image, |bounds| bounds.width == 30.0);
+    assert_eq!(bounds, Rect::new(0.0, 0.0, 30.0, 20.0));
+}
+
+#[test]
+fn images_that_fail_to_load_are_reported_once_and_paint_nothing() {
+    let engine = Engine::new();
+    let diagnostics = engine.subscribe_diagnostics();
+    let root = engine.root_id();
+    for id in [1, 2] {
+        let node = engine.create_node(Id::from_u64(id), None);
+        engine.set_parent(root, node);
+        engine.set_attribute(node, "src".to_owned(), "src/images/missing.png".to_owned());
+    }
+    engine.layout_headless(400.0, 300.0).unwrap();
+
+    let failure = diagnostics
+        .recv_timeout(Duration::from_secs(5))
+        .expect("no diagnostic");
+    assert_eq!(failure.code, DiagnosticCode::ImageLoadFailed);
+    // Laid out as if it had no `src`: with nothing in it, it has no width.
+    let snapshot = engine.layout_headless(400.0, 300.0).unwrap();
+    assert_eq!(snapshot.find(Id::from_u64(1)).unwrap().bounds.width, 0.0);
+    assert!(diagnostics
+        .recv_timeout(Duration::from_millis(200))
+        .is_err());
+}
+
+#[test]
+fn object_fit_scales_images_into_the_content_box() {
+    let content = Rect::new(10.0, 20.0, 100.0, 50.0);
+    // A square image in a wide box.
+    let square = (20.0, 20.0);
+    assert_eq!(object_fit_rect(ObjectFit::Fill, square, content), content);
+    assert_eq!(
+        object_fit_rect(ObjectFit::Contain, square, content),
+        Rect::new(35.0, 20.0, 50.0, 50.0)
+    );
+    assert_eq!(
+        object_fit_rect(ObjectFit::Cover, square, content),
+        Rect::new(10.0, -5.0, 100.0, 100.0)
+    );
+
+    // A wide image in a tall box.
+    let content = Rect::new(0.0, 0.0, 40.0, 80.0);
+    let wide = (4.0, 2.0);
+    assert_eq!(
+        object_fit_rect(ObjectFit::Contain, wide, content),
+        Rect::new(0.0, 30.0, 40.0, 20.0)
+    );
+    assert_eq!(
+        object_fit_rect(ObjectFit::Cover, wide, content),
+        Rect::new(-60.0, 0.0, 160.0, 80.0)
+    );
+
+    // Nothing to scale.
+    assert_eq!(
+        object_fit_rect(ObjectFit::Contain, (0.0, 0.0), content),
+        content
+    );
+}
@@ -0,0 +1,367 @@
+//! Intrinsic sizes: how wide a node is at its narrowest without its content overflowing
+//! (min-content) and with nothing wrapped (max-content), and how high it is then.
+//!
+//! Flex and grid items whose style doesn't size them take their max-content size, and flex
+//! items don't shrink below their min-content size. Text is measured, images have their
+//! natural size, and containers size to their children along their own axes, gaps, padding
+//! and border included. Nodes without any content are as big as their padding and border.
+
+use crate::flex_layout::{
+    height_from_aspect_ratio, image_item_size, resolve_style, text_item_size,
+    width_from_aspect_ratio,
+};
+use crate::grid_layout::{template_columns, AutoPlacement};
+use crate::layout::{LayoutContext, Node};
+use crate::style::{
+    BoxSizing, Display, FlexDirection, FlexWrap, GridTrack, Length, Overflow, Style,
+};
+use crate::text::FontSpec;
+use crate::Id;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A node's intrinsic sizes, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct IntrinsicSizes {
+    pub min_content_width: f64,
+    pub max_content_width: f64,
+    /// At the max-content width.
+    pub height: f64,
+}
+
+/// The intrinsic sizes of nodes' content, for one layout pass. Nodes outside the document,
+/// such as template skeletons', aren't cached.
+#[derive(Default)]
+pub(crate) struct IntrinsicSizeCache(RefCell<HashMap<Id, IntrinsicSizes>>);
+
+impl IntrinsicSizeCache {
+    /// Forget the sizes of the last pass, whose styles and tree may have changed since.
+    pub fn begin_layout_pass(&self) {
+        self.0.borrow_mut().clear();
+    }
+}
+
+/// The border-box intrinsic sizes of the node with the computed `style`: its content's,
+/// with its padding and border, but on axes its style sets in pixels, or derives from them
+/// with an `aspect-ratio`.
+pub(crate) fn intrinsic_sizes(
+    node: &Rc<RefCell<Node>>,
+    style: &Style,
+    ctx: &LayoutContext,
+) -> IntrinsicSizes {
+    let (extra_width, extra_height) = padding_and_border(style);
+    let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
+    let mut specified_width = specified_border_box(style.width.as_ref(), extra_width, box_sizing);
+    let mut specified_height =
+        specified_border_box(style.height.as_ref(), extra_height, box_sizing);
+    match (specified_width, specified_height) {
+        (Some(width), None) => specified_height = height_from_aspect_ratio(style, width),
+        (None, Some(height)) => specified_width = width_from_aspect_ratio(style, height),
+        _ => {}
+    }
+
+    if style.resolved_aspect_ratio().is_none() {
+        if let Some(image) = ctx.image_of(&node.borrow()) {
+            // Images keep their own aspect ratio where one axis is sized.
+            let (width, height) = image_item_size(&image, style);
+            let width = specified_width.or(width).unwrap_or(extra_width);
+            return IntrinsicSizes {
+                min_content_width: width,
+                max_content_width: width,
+                height: specified_height.or(height).unwrap_or(extra_height),
+            };
+        }
+    }
+
+    let content = content_sizes(node, style, ctx);
+    let max_content_width = specified_width.unwrap_or(content.max_content_width + extra_width);
+    // Text wraps in the width its style sets, as it does when laid out.
+    let text_height = || {
+        let node = node.borrow();
+        let text = node.text.as_deref().filter(|_| node.children.is_empty())?;
+        text_item_size(text, style, ctx).1
+    };
+    let height = specified_height
+        .or_else(|| height_from_aspect_ratio(style, max_content_width))
+        .or_else(text_height)
+        .unwrap_or(content.height + extra_height);
+    IntrinsicSizes {
+        min_content_width: specified_width.unwrap_or(content.min_content_width + extra_width),
+        max_content_width,
+        height,
+    }
+}
+
+/// The border-box size on the main axis of `direction` a flex item with `style` doesn't
+/// shrink below, §4.5: its min-content size, or its specified size if that's smaller. Items
+/// that clip what overflows them can shrink to nothing.
+pub(crate) fn automatic_minimum_main_size(
+    node: &Rc<RefCell<Node>>,
+    style: &Style,
+    direction: &FlexDirection,
+    ctx: &LayoutContext,
+) -> f64 {
+    if matches!(style.overflow, Some(Overflow::Hidden)) {
+        return 0.0;
+    }
+    let (extra_width, extra_height) = padding_and_border(style);
+    let box_sizing = style.box_sizing.unwrap_or(BoxSizing::ContentBox);
+    let content = content_sizes(node, style, ctx);
+    let (content_minimum, specified) = match direction {
+        FlexDirection::Row | FlexDirection::RowReverse => (
+            content.min_content_width + extra_width,
+            specified_border_box(style.width.as_ref(), extra_width, box_sizing),
+        ),
+        FlexDirection::Column | FlexDirection::ColumnReverse => (
+            content.height + extra_height,
+            specified_border_box(style.height.as_ref(), extra_height, box_sizing),
+        ),
+    };
+    specified.map_or(content_minimum, |specified| specified.min(content_minimum))
+}
+
+/// The content-box intrinsic sizes of the node's content, which its own size doesn't
+/// change.
+fn content_sizes(node: &Rc<RefCell<Node>>, style: &Style, ctx: &LayoutContext) -> IntrinsicSizes {
+    let id = node.borrow().id;
+    let cached = id != Id::default();
+    if cached {
+        if let Some(sizes) = ctx.intrinsic_sizes.0.borrow().get(&id) {
+            return *sizes;
+        }
+    }
+
+    let is_leaf = node.borrow().children.is_empty();
+    let sizes = if let Some(image) = ctx.image_of(&node.borrow()) {
+        let width = image.width().max(0) as f64;
+        IntrinsicSizes {
+            min_content_width: width,
+            max_content_width: width,
+            height: image.height().max(0) as f64,
+        }
+    } else if is_leaf {
+        match node.borrow().text.as_deref() {
+            Some(text) => text_content_sizes(text, style, ctx),
+            None => IntrinsicSizes::default(),
+        }
+    } else {
+        let children = child_contributions(node, style, ctx);
+        match style.resolved_display() {
+            Display::Grid => grid_content_sizes(style, &children),
+            Display::Flex | Display::None => flex_content_sizes(style, &children),
+        }
+    };
+
+    if cached {
+        ctx.intrinsic_sizes.0.borrow_mut().insert(id, sizes);
+    }
+    sizes
+}
+
+/// Text is as wide as its longest word at its narrowest, unless it doesn't wrap, and as
+/// wide as its one line at its widest.
+fn text_content_sizes(text: &str, style: &Style, ctx: &LayoutContext) -> IntrinsicSizes {
+    let font = FontSpec::from_style(style);
+    let measurer = ctx.text_measurer.as_ref();
+    let line = measurer.measure_unwrapped(text, &font);
+    let min_content_width = if style.wraps_text() {
+        text.split_whitespace()
+            .map(|word| measurer.measure_unwrapped(word, &font).width)
+            .fold(0.0, f64::max)
+    } else {
+        line.width
+    };
+    IntrinsicSizes {
+        min_content_width,
+        max_content_width: line.width,
+        height: line.height,
+    }
+}
+
+/// A child's margin-box intrinsic sizes, with its style.
+struct Contribution {
+    sizes: IntrinsicSizes,
+    style: Style,
+}
+
+/// The children laid out as items, in `order`.
+fn child_contributions(
+    node: &Rc<RefCell<Node>>,
+    style: &Style,
+    ctx: &LayoutContext,
+) -> Vec<Contribution> {
+    let children = node.borrow().children.clone();
+    let mut contributions: Vec<Contribution> = children
+        .iter()
+        .filter(|child| {
+            let child = child.borrow();
+            !child.is_text_node() || child.text.as_deref().is_some_and(|t| !t.trim().is_empty())
+        })
+        .map(|child| (child, resolve_style(child, ctx, style)))
+        .filter(|(_, child_style)| child_style.resolved_display() != Display::None)
+        .map(|(child, child_style)| {
+            let mut sizes = intrinsic_sizes(child, &child_style, ctx);
+            let margin = child_style.margin.resolved();
+            let margin_width = length_px_or_zero(&margin.left) + length_px_or_zero(&margin.right);
+            let margin_height = length_px_or_zero(&margin.top) + length_px_or_zero(&margin.bottom);
+            sizes.min_content_width += margin_width;
+            sizes.max_content_width += margin_width;
+            sizes.height += margin_height;
+            Contribution {
+                sizes,
+                style: child_style,
+            }
+        })
+        .collect();
+    contributions.sort_by_key(|contribution| contribution.style.order.unwrap_or(0));
+    contributions
+}
+
+/// Flex items take the sizes their `flex-basis` sets in pixels on the main axis, and are
+/// side by side along it, with the gaps between them, unless they wrap: at its narrowest, a
+/// wrapping row has each item on a line of its own. Negative margins pull items together,
+/// but never make the content smaller than nothing.
+fn flex_content_sizes(style: &Style, children: &[Contribution]) -> IntrinsicSizes {
+    let direction = style.flex_direction.unwrap_or(FlexDirection::Row);
+    let wraps = matches!(
+        style.flex_wrap,
+        Some(FlexWrap::Wrap | FlexWrap::WrapReverse)
+    );
+    let gaps = children.len().saturating_sub(1) as f64;
+    let basis = |contribution: &Contribution| match contribution.style.flex_basis {
+        Some(Length::Px(basis)) => {
+            let margin = contribution.style.margin.resolved();
+            Some(match direction {
+                FlexDirection::Row | FlexDirection::RowReverse => {
+                    basis + length_px_or_zero(&margin.left) + length_px_or_zero(&margin.right)
+                }
+                FlexDirection::Column | FlexDirection::ColumnReverse => {
+                    basis + length_px_or_zero(&margin.top) + length_px_or_zero(&margin.bottom)
+                }
+            })
+        }
+        _ => None,
+    };
+
+    match direction {
+        FlexDirection::Row | FlexDirection::RowReverse => {
+            let gap = style.column_gap.as_ref().map_or(0.0, Length::to_px) * gaps;
+            let min = |c: &Contribution| basis(c).unwrap_or(c.sizes.min_content_width);
+            let max = |c: &Contribution| basis(c).unwrap_or(c.sizes.max_content_width);
+            IntrinsicSizes {
+                min_content_width: if wraps {
+                    children.iter().map(min).fold(0.0, f64::max)
+                } else {
+                    (children.iter().map(min).sum::<f64>() + gap).max(0.0)
+                },
+                max_content_width: (children.iter().map(max).sum::<f64>() + gap).max(0.0),
+                height: children.iter().map(|c| c.sizes.height).fold(0.0, f64::max),
+            }
+        }
+        FlexDirection::Column | FlexDirection::ColumnReverse => {
+            let gap = style.row_gap.as_ref().map_or(0.0, Length::to_px) * gaps;
+            IntrinsicSizes {
+                min_content_width: children
+                    .iter()
+                    .map(|c| c.sizes.min_content_width)
+                    .fold(0.0, f64::max),
+                max_content_width: children
+                    .iter()
+                    .map(|c| c.sizes.max_content_width)
+                    .fold(0.0, f64::max),
+                height: (children
+                    .iter()
+                    .map(|c| basis(c).unwrap_or(c.sizes.height))
+                    .sum::<f64>()
+                    + gap)
+                    .max(0.0),
+            }
+        }
+    }
+}
+
+/// Tracks in pixels keep their size; the others are as wide as the widest item in them
+/// that spans only them, and rows past the template as high as their tallest item.
+fn grid_content_sizes(style: &Style, children: &[Contribution]) -> IntrinsicSizes {
+    let tracks = template_columns(style);
+    let mut min_columns = vec![0.0_f64; tracks.len()];
+    let mut max_columns = vec![0.0_f64; tracks.len()];
+    let mut rows: Vec<f64> = Vec::new();
+    let mut placement = AutoPlacement::default();
+    for child in children {
+        let (row, column, span) = placement.place(&child.style, tracks.len());
+        if span == 1 {
+            min_columns[column] = min_columns[column].max(child.sizes.min_content_width);
+            max_columns[column] = max_columns[column].max(child.sizes.max_content_width);
+        }
+        if rows.len() <= row {
+            rows.resize(row + 1, 0.0);
+        }
+        rows[row] = rows[row].max(child.sizes.height);
+    }
+
+    let fixed = |track: &GridTrack| match track {
+        GridTrack::Length(Length::Px(px)) => Some(*px),
+        _ => None,
+    };
+    let column_gap = style.column_gap.as_ref().map_or(0.0, Length::to_px);
+    let width = |columns: &[f64]| {
+        let tracks_width: f64 = tracks
+            .iter()
+            .zip(columns)
+            .map(|(track, content)| fixed(track).unwrap_or(*content))
+            .sum();
+        tracks_width + column_gap * tracks.len().saturating_sub(1) as f64
+    };
+
+    let template_rows = style.grid_template_rows.as_deref().unwrap_or(&[]);
+    let row_count = rows.len().max(template_rows.len());
+    rows.resize(row_count, 0.0);
+    let rows_height: f64 = rows
+        .iter()
+        .enumerate()
+        .map(|(row, content)| template_rows.get(row).and_then(fixed).unwrap_or(*content))
+        .sum();
+    let row_gap = style.row_gap.as_ref().map_or(0.0, Length::to_px);
+
+    IntrinsicSizes {
+        min_content_width: width(&min_columns),
+        max_content_width: width(&max_columns),
+        height: rows_height + row_gap * row_count.saturating_sub(1) as f64,
+    }
+}
+
+fn padding_and_border(style: &Style) -> (f64, f64) {
+    let padding = style.padding.resolved();
+    let border = style.used_border_width();
+    (
+        padding.left.to_px() + padding.right.to_px() + border.left.to_px() + border.right.to_px(),
+        padding.top.to_px() + padding.bottom.to_px() + border.top.to_px() + border.bottom.to_px(),
+    )
+}
+
+/// The border-box size a length in pixels sets, as `box-sizing` says.
+pub(crate) fn specified_border_box(
+    specified: Option<&Length>,
+    padding_and_border: f64,
+    box_sizing: BoxSizing,
+) -> Option<f64> {
+    match specified {
+        Some(&Length::Px(px)) if px > 0.0 => Some(match box_sizing {
+            BoxSizing::ContentBox => px + padding_and_border,
+            BoxSizing::BorderBox => px,
+        }),
+        _ => None,
+    }
+}
+
+fn length_px_or_zero(length: &Length) -> f64 {
+    match length {
+        Length::Auto => 0.0,
+        _ => length.to_px(),
+    }
+}
+
+#[cfg(test)]
+mod intrinsic_tests;
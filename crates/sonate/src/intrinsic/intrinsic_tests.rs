@@ -0,0 +1,122 @@
+use super::{automatic_minimum_main_size, intrinsic_sizes, IntrinsicSizes};
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::LayoutContext;
+use crate::style::FlexDirection;
+use crate::Id;
+use std::collections::HashMap;
+
+// TestTextMeasurer: 10px font => 6px per char, 12px line height.
+const HTML: &str = r#"
+<style>
+  .text { font-size: 10px; }
+  .nowrap { white-space: nowrap; }
+  .box { width: 40px; height: 20px; }
+  .row { display: flex; flex-direction: row; column-gap: 10px; padding: 5px; }
+  .column { display: flex; flex-direction: column; row-gap: 4px; }
+  .wrap { flex-wrap: wrap; }
+  .grid { display: grid; grid-template-columns: 50px 1fr; column-gap: 10px; }
+  .hidden { overflow: hidden; }
+</style>
+<div id="doc">
+  <div id="words" class="text">one three</div>
+  <div id="line" class="text nowrap">one three</div>
+  <div id="row" class="row">
+    <div class="box"></div>
+    <div class="text">one three</div>
+  </div>
+  <div id="wrapping_row" class="row wrap">
+    <div class="box"></div>
+    <div class="text">one three</div>
+  </div>
+  <div id="column" class="column">
+    <div class="box"></div>
+    <div class="text">one three</div>
+  </div>
+  <div id="grid" class="grid">
+    <div class="text">one</div>
+    <div class="text">one three</div>
+    <div class="box"></div>
+  </div>
+  <div id="clipped" class="text hidden">one three</div>
+  <div id="empty"></div>
+</div>
+"#;
+
+fn sizes(ctx: &LayoutContext, ids: &HashMap<String, Id>, name: &str) -> IntrinsicSizes {
+    let node = ctx.document.get_node(ids[name]).unwrap();
+    let style = ctx.computed_style(&node.borrow());
+    intrinsic_sizes(&node, &style, ctx)
+}
+
+fn minimum_width(ctx: &LayoutContext, ids: &HashMap<String, Id>, name: &str) -> f64 {
+    let node = ctx.document.get_node(ids[name]).unwrap();
+    let style = ctx.computed_style(&node.borrow());
+    automatic_minimum_main_size(&node, &style, &FlexDirection::Row, ctx)
+}
+
+#[test]
+fn text_is_as_narrow_as_its_longest_word() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+
+    let expected = IntrinsicSizes {
+        min_content_width: 30.0,
+        max_content_width: 54.0,
+        height: 12.0,
+    };
+    assert_eq!(sizes(&ctx, &ids, "words"), expected);
+    // Text that doesn't wrap is as wide at its narrowest as at its widest.
+    let expected = IntrinsicSizes {
+        min_content_width: 54.0,
+        ..expected
+    };
+    assert_eq!(sizes(&ctx, &ids, "line"), expected);
+}
+
+#[test]
+fn rows_add_up_their_items_and_columns_stack_them() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+
+    // 40px and 54px of items, a 10px gap and 5px of padding on each side.
+    let expected = IntrinsicSizes {
+        min_content_width: 90.0,
+        max_content_width: 114.0,
+        height: 30.0,
+    };
+    assert_eq!(sizes(&ctx, &ids, "row"), expected);
+    // At its narrowest, each item is on a line of its own.
+    let expected = IntrinsicSizes {
+        min_content_width: 50.0,
+        ..expected
+    };
+    assert_eq!(sizes(&ctx, &ids, "wrapping_row"), expected);
+
+    let expected = IntrinsicSizes {
+        min_content_width: 40.0,
+        max_content_width: 54.0,
+        height: 36.0,
+    };
+    assert_eq!(sizes(&ctx, &ids, "column"), expected);
+}
+
+#[test]
+fn grid_columns_fit_their_widest_item() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+
+    // The 50px column keeps its size; the `1fr` one is as wide as its text.
+    let expected = IntrinsicSizes {
+        min_content_width: 90.0,
+        max_content_width: 114.0,
+        height: 32.0,
+    };
+    assert_eq!(sizes(&ctx, &ids, "grid"), expected);
+}
+
+#[test]
+fn flex_items_stop_shrinking_at_their_min_content_width() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+
+    assert_eq!(minimum_width(&ctx, &ids, "words"), 30.0);
+    assert_eq!(minimum_width(&ctx, &ids, "row"), 90.0);
+    assert_eq!(minimum_width(&ctx, &ids, "clipped"), 0.0);
+    assert_eq!(sizes(&ctx, &ids, "empty"), IntrinsicSizes::default());
+}
@@ -0,0 +1,181 @@
+use crate::{layout::RenderNode, resources::ResourceCaches, style::Style, Id};
+use skia_safe::Image;
+use std::{collections::HashMap, sync::Arc};
+
+/// A subtree rendered once into an offscreen image.
+pub(crate) struct CachedLayer {
+    pub image: Image,
+    /// Top-left of the image in logical pixels.
+    pub origin: (f32, f32),
+    /// Device pixels per logical pixel the image was rendered at.
+    pub scale: f32,
+    /// Subtree the image was rendered from. The root's own opacity is applied when
+    /// compositing, so it's ignored when checking whether the image is still valid.
+    content: RenderNode,
+    bytes: usize,
+    last_used: u64,
+}
+
+impl CachedLayer {
+    pub fn new(
+        image: Image,
+        origin: (f32, f32),
+        scale: f32,
+        bytes: usize,
+        content: &RenderNode,
+    ) -> Self {
+        Self {
+            image,
+            origin,
+            scale,
+            content: content.clone(),
+            bytes,
+            last_used: 0,
+        }
+    }
+
+    fn is_valid_for(&self, node: &RenderNode, scale: f32) -> bool {
+        self.scale == scale
+            && self.content.bounds == node.bounds
+            && self.content.text == node.text
+            && self.content.children == node.children
+            && (Arc::ptr_eq(&self.content.style, &node.style)
+                || Style {
+                    opacity: None,
+                    ..self.content.style.as_ref().clone()
+                } == Style {
+                    opacity: None,
+                    ..node.style.as_ref().clone()
+                })
+    }
+}
+
+/// Cache of promoted layers keyed by node id, charged to the budget of resource caches it
+/// shares with their images and the other windows' layers.
+///
+/// Layers that are not composited during a frame are demoted (dropped) at the end of it;
+/// when the budget is exceeded the least recently used layers are evicted first.
+pub(crate) struct LayerCache {
+    layers: HashMap<Id, CachedLayer>,
+    caches: ResourceCaches,
+    used_bytes: usize,
+    frame: u64,
+}
+
+impl LayerCache {
+    pub fn new(caches: ResourceCaches) -> Self {
+        Self {
+            layers: HashMap::new(),
+            caches,
+            used_bytes: 0,
+            frame: 0,
+        }
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Drop layers whose nodes were not promoted this frame.
+    pub fn end_frame(&mut self) {
+        let frame = self.frame;
+        let mut freed = 0;
+        self.layers.retain(|_, layer| {
+            let keep = layer.last_used == frame;
+            if !keep {
+                freed += layer.bytes;
+            }
+            keep
+        });
+        self.used_bytes -= freed;
+        self.caches.release_layer(freed);
+    }
+
+    /// The cached layer for `node`, if its content hasn't changed since it was rendered.
+    pub fn get(&mut self, node: &RenderNode, scale: f32) -> Option<&CachedLayer> {
+        let valid = self
+            .layers
+            .get(&node.id)
+            .is_some_and(|layer| layer.is_valid_for(node, scale));
+
+        if !valid {
+            self.remove(node.id);
+            return None;
+        }
+
+        let layer = self.layers.get_mut(&node.id)?;
+        layer.last_used = self.frame;
+        Some(layer)
+    }
+
+    /// Whether a layer of `bytes` can be cached at all.
+    pub fn fits(&self, bytes: usize) -> bool {
+        bytes <= self.caches.budget_bytes()
+    }
+
+    /// Store a freshly rendered layer, evicting unused images, then least recently used
+    /// layers to make room. Returns `false` (and drops the layer) if there's no room even
+    /// without this cache's other layers.
+    pub fn insert(&mut self, id: Id, mut layer: CachedLayer) -> bool {
+        self.remove(id);
+
+        if !self.fits(layer.bytes) {
+            return false;
+        }
+
+        while !self.caches.reserve_layer(layer.bytes) {
+            let Some(lru) = self
+                .layers
+                .iter()
+                .min_by_key(|(_, layer)| layer.last_used)
+                .map(|(id, _)| *id)
+            else {
+                return false;
+            };
+            self.remove(lru);
+        }
+
+        layer.last_used = self.frame;
+        self.used_bytes += layer.bytes;
+        self.layers.insert(id, layer);
+        true
+    }
+
+    pub fn layer(&self, id: Id) -> Option<&CachedLayer> {
+        self.layers.get(&id)
+    }
+
+    #[allow(unused)]
+    pub fn contains(&self, id: Id) -> bool {
+        self.layers.contains_key(&id)
+    }
+
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    #[allow(unused)]
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn remove(&mut self, id: Id) {
+        if let Some(layer) = self.layers.remove(&id) {
+            self.used_bytes -= layer.bytes;
+            self.caches.release_layer(layer.bytes);
+        }
+    }
+}
+
+impl Default for LayerCache {
+    fn default() -> Self {
+        Self::new(ResourceCaches::default())
+    }
+}
+
+impl Drop for LayerCache {
+    fn drop(&mut self) {
+        self.caches.release_layer(self.used_bytes);
+    }
+}
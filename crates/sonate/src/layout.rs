@@ -1,20 +1,38 @@
 use crate::{
-    flex_layout::FlexLayoutEngine,
-    style::{BoxSizing, Length, Style, StyleSheet},
-    text::{default_text_measurer, FontSpec, TextMeasurer},
-    Id,
+    animation::Transitions,
+    css_parser::parse_inline_style,
+    flex_layout::{image_item_size, FlexLayoutEngine},
+    grid_layout::GridLayoutEngine,
+    images::ImageLoader,
+    intrinsic::IntrinsicSizeCache,
+    media::MediaEnvironment,
+    resources::ImageHandle,
+    snapshot::Edges,
+    style::{BoxSizing, Display, InlineStyle, Length, Rgba, Style, StyleSheet},
+    style_matching::{ComputedStyle, Inherited, StyleCache, DEFAULT_FOCUS_ACCENT},
+    template::Templates,
+    text::{default_text_measurer, line_boxes, measure_clamped, FontSpec, LineStyle, TextMeasurer},
+    tooltip, DocumentErrorKind, EngineError, Id,
 };
 
 // Many layout tests do `use super::*;` and refer to `Selector::...`.
 // Keep this import in the module namespace for those tests.
 #[allow(unused_imports)]
 use crate::style::Selector;
-use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::Arc};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{hash_map::Entry, HashMap},
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
 
 #[derive(Default)]
 pub struct Layout {
     pub bounds: Rect,
     pub style: Arc<Style>,
+    /// The decoded image of the `src` attribute, once it's ready.
+    pub image: Option<ImageHandle>,
 }
 
 #[derive(Default, Debug, Clone, Copy)]
@@ -23,7 +41,8 @@ pub struct Size {
     pub height: f64,
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     pub x: f64,
     pub y: f64,
@@ -42,22 +61,74 @@ impl Rect {
         }
     }
 
+    /// Whether the point is in the rectangle, its right and bottom edges included: where
+    /// siblings share an edge, the one painted on top gets it.
     #[allow(unused)]
     pub fn contains_point(&self, x: f64, y: f64) -> bool {
         x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
     }
+
+    /// Smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let left = self.x.min(other.x);
+        let top = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
+    /// Whether the rectangles share some area, not just an edge.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// The rectangle grown by `by` on every side.
+    pub fn outset(&self, by: f64) -> Rect {
+        Rect::new(
+            self.x - by,
+            self.y - by,
+            self.width + 2.0 * by,
+            self.height + 2.0 * by,
+        )
+    }
+
+    /// The rectangle grown by `edges`, each side by its own; negative sides shrink it.
+    pub fn outset_by(&self, edges: &Edges) -> Rect {
+        Rect::new(
+            self.x - edges.left,
+            self.y - edges.top,
+            self.width + edges.left + edges.right,
+            self.height + edges.top + edges.bottom,
+        )
+    }
+
+    /// The rectangle shrunk by `edges`, no smaller than nothing.
+    pub fn inset_by(&self, edges: &Edges) -> Rect {
+        let width = (self.width - edges.left - edges.right).max(0.0);
+        let height = (self.height - edges.top - edges.bottom).max(0.0);
+        Rect::new(self.x + edges.left, self.y + edges.top, width, height)
+    }
 }
 
 #[derive(Default)]
 #[allow(unused)]
 pub struct Node {
     pub id: Id,
+    /// The element name, lowercase, which type selectors match. `None` for plain nodes.
+    pub tag: Option<String>,
     pub text: Option<String>,
     pub attributes: HashMap<String, String>,
     pub children: Vec<Rc<RefCell<Node>>>,
     pub parent: Option<Id>, // Add parent member
+    // set directly on the node with the `style` attribute
+    pub inline_style: Arc<InlineStyle>,
     // modified when layouting
     pub layout: Layout,
+    /// The resolved style before transitions, reused by later layouts while it's current.
+    pub(crate) computed_style: RefCell<Option<ComputedStyle>>,
 }
 
 impl Node {
@@ -82,6 +153,74 @@ pub struct Document {
     #[allow(unused)]
     root: Rc<RefCell<Node>>,
     nodes: HashMap<Id, Rc<RefCell<Node>>>,
+    /// Roots of secondary windows' trees, laid out along with `root`.
+    window_roots: Vec<Id>,
+}
+
+/// Why a node couldn't be put under a parent, or moved among its siblings.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetParentError {
+    SameNode,
+    ChildNotFound,
+    ParentNotFound,
+    Cycle,
+    /// The node to insert before, or to move, isn't a child of the parent.
+    NotAChild,
+}
+
+impl std::fmt::Display for SetParentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SetParentError::SameNode => "Parent and child cannot be the same",
+            SetParentError::ChildNotFound => "Child node not found",
+            SetParentError::ParentNotFound => "Parent node not found",
+            SetParentError::Cycle => "Parent is a descendant of the child",
+            SetParentError::NotAChild => "Node is not a child of the parent",
+        })
+    }
+}
+
+impl SetParentError {
+    /// The error of a change made to `child`.
+    pub(crate) fn for_node(self, child: Id) -> EngineError {
+        EngineError::Document {
+            kind: self.into(),
+            id: Some(child.as_u64()),
+        }
+    }
+}
+
+impl From<SetParentError> for DocumentErrorKind {
+    fn from(err: SetParentError) -> Self {
+        match err {
+            SetParentError::SameNode => DocumentErrorKind::SameNode,
+            SetParentError::ChildNotFound => DocumentErrorKind::ChildNotFound,
+            SetParentError::ParentNotFound => DocumentErrorKind::ParentNotFound,
+            SetParentError::Cycle => DocumentErrorKind::Cycle,
+            SetParentError::NotAChild => DocumentErrorKind::NotAChild,
+        }
+    }
+}
+
+/// A node was to be created with the id of another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdInUse(pub Id);
+
+impl std::fmt::Display for IdInUse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Node {} already exists", self.0.as_u64())
+    }
+}
+
+impl std::error::Error for IdInUse {}
+
+impl From<IdInUse> for EngineError {
+    fn from(err: IdInUse) -> Self {
+        EngineError::Document {
+            kind: DocumentErrorKind::IdInUse,
+            id: Some(err.0.as_u64()),
+        }
+    }
 }
 
 impl Document {
@@ -89,25 +228,80 @@ impl Document {
         let root = Rc::new(RefCell::new(Node::new(Id(0), None)));
         let mut nodes = HashMap::new();
         nodes.insert(Id(0), root.clone());
-        Self { root, nodes }
+        Self {
+            root,
+            nodes,
+            window_roots: Vec::new(),
+        }
+    }
+
+    /// Create a node, unless another has the id.
+    pub fn create_node(&mut self, id: Id, text: Option<String>) -> Result<Id, IdInUse> {
+        self.insert_node(Node::new(id, text))
+    }
+
+    /// Like `create_node`, for an element named `tag`, in any case.
+    pub fn create_element(
+        &mut self,
+        id: Id,
+        tag: &str,
+        text: Option<String>,
+    ) -> Result<Id, IdInUse> {
+        let mut node = Node::new(id, text);
+        node.tag = Some(tag.to_ascii_lowercase());
+        self.insert_node(node)
+    }
+
+    fn insert_node(&mut self, node: Node) -> Result<Id, IdInUse> {
+        let id = node.id;
+        match self.nodes.entry(id) {
+            Entry::Occupied(_) => Err(IdInUse(id)),
+            Entry::Vacant(entry) => {
+                entry.insert(Rc::new(RefCell::new(node)));
+                Ok(id)
+            }
+        }
+    }
+
+    /// Create the root of another window's tree.
+    pub fn create_window_root(&mut self, id: Id) {
+        if self.create_node(id, None).is_ok() {
+            self.window_roots.push(id);
+        }
+    }
+
+    pub fn window_roots(&self) -> &[Id] {
+        &self.window_roots
     }
 
-    pub fn create_node(&mut self, id: Id, text: Option<String>) -> Id {
-        let node = Rc::new(RefCell::new(Node::new(id, text)));
-        self.nodes.insert(id, node);
-        id
+    /// Detach the node and forget it along with its descendants.
+    pub fn remove_tree(&mut self, id: Id) {
+        let Some(node) = self.nodes.remove(&id) else {
+            return;
+        };
+        self.window_roots.retain(|root| *root != id);
+        if let Some(parent) = node.borrow().parent.and_then(|p| self.nodes.get(&p)) {
+            parent.borrow_mut().children.retain(|c| c.borrow().id != id);
+        }
+
+        let mut removed = node.borrow().children.clone();
+        while let Some(child) = removed.pop() {
+            let child = child.borrow();
+            self.nodes.remove(&child.id);
+            removed.extend(child.children.iter().cloned());
+        }
     }
 
-    pub fn set_parent(&mut self, parent_id: Id, child_id: Id) -> Result<(), &str> {
+    pub fn set_parent(&mut self, parent_id: Id, child_id: Id) -> Result<(), SetParentError> {
         // Check if the parent and child are the same
         if parent_id == child_id {
-            return Err("Parent and child cannot be the same");
+            return Err(SetParentError::SameNode);
         }
 
         let child = self
             .nodes
             .get(&child_id)
-            .ok_or("Child node not found")?
+            .ok_or(SetParentError::ChildNotFound)?
             .clone();
 
         // Check if the child is already a child of the parent
@@ -115,7 +309,19 @@ impl Document {
             return Ok(());
         }
 
-        let parent = self.nodes.get(&parent_id).ok_or("Parent node not found")?;
+        let parent = self
+            .nodes
+            .get(&parent_id)
+            .ok_or(SetParentError::ParentNotFound)?;
+
+        // A node can't become a descendant of itself.
+        let mut ancestor = parent.borrow().parent;
+        while let Some(id) = ancestor {
+            if id == child_id {
+                return Err(SetParentError::Cycle);
+            }
+            ancestor = self.nodes.get(&id).and_then(|node| node.borrow().parent);
+        }
 
         // Remove the child from its previous parent
         if let Some(old_parent_id) = child.borrow().parent {
@@ -133,9 +339,75 @@ impl Document {
         Ok(())
     }
 
+    /// Like `set_parent`, but put the child right before `reference_id` among the parent's
+    /// children instead of after the last one. Moves the child if it's already a child of the
+    /// parent.
+    pub fn insert_before(
+        &mut self,
+        parent_id: Id,
+        child_id: Id,
+        reference_id: Id,
+    ) -> Result<(), SetParentError> {
+        let parent = self
+            .nodes
+            .get(&parent_id)
+            .ok_or(SetParentError::ParentNotFound)?
+            .clone();
+        if child_index(&parent.borrow(), reference_id).is_none() {
+            return Err(SetParentError::NotAChild);
+        }
+        if child_id == reference_id {
+            return Ok(());
+        }
+        self.set_parent(parent_id, child_id)?;
+
+        let mut parent = parent.borrow_mut();
+        let from = child_index(&parent, child_id).unwrap();
+        let child = parent.children.remove(from);
+        let index = child_index(&parent, reference_id).unwrap();
+        parent.children.insert(index, child);
+        Ok(())
+    }
+
+    /// Move a child of the parent to `index` among its children, or after the last one if
+    /// `index` is past the end.
+    pub fn move_child(
+        &mut self,
+        parent_id: Id,
+        child_id: Id,
+        index: usize,
+    ) -> Result<(), SetParentError> {
+        if !self.nodes.contains_key(&child_id) {
+            return Err(SetParentError::ChildNotFound);
+        }
+        let parent = self
+            .nodes
+            .get(&parent_id)
+            .ok_or(SetParentError::ParentNotFound)?;
+        let mut parent = parent.borrow_mut();
+        let from = child_index(&parent, child_id).ok_or(SetParentError::NotAChild)?;
+        let child = parent.children.remove(from);
+        let index = index.min(parent.children.len());
+        parent.children.insert(index, child);
+        Ok(())
+    }
+
+    /// Replace the node's text; `None` makes it a plain container.
+    pub fn set_text(&mut self, node_id: Id, text: Option<String>) {
+        if let Some(node) = self.nodes.get(&node_id) {
+            node.borrow_mut().text = text;
+        }
+    }
+
     pub fn set_attribute(&mut self, node_id: Id, key: String, value: String) {
         if let Some(node) = self.nodes.get(&node_id) {
-            node.borrow_mut().attributes.insert(key, value);
+            let mut node = node.borrow_mut();
+            // Rules match on attributes.
+            node.computed_style.take();
+            if key == "style" {
+                node.inline_style = Arc::new(parse_inline_style(&value));
+            }
+            node.attributes.insert(key, value);
         }
     }
 
@@ -156,18 +428,91 @@ impl Document {
         self.root.clone()
     }
 
-    #[allow(unused)]
     pub fn get_node(&self, id: Id) -> Option<Rc<RefCell<Node>>> {
         self.nodes.get(&id).cloned()
     }
+
+    /// Ids of the node's children, in order. Empty for unknown nodes.
+    pub fn children(&self, id: Id) -> Vec<Id> {
+        self.nodes.get(&id).map_or_else(Vec::new, |node| {
+            node.borrow()
+                .children
+                .iter()
+                .map(|c| c.borrow().id)
+                .collect()
+        })
+    }
+
+    /// The id of the node's parent, `None` for unknown and detached nodes and for roots.
+    pub fn parent(&self, id: Id) -> Option<Id> {
+        self.nodes.get(&id)?.borrow().parent
+    }
+
+    /// Ids of the node's descendants, in document order: each node before its children.
+    pub fn descendants(&self, id: Id) -> Vec<Id> {
+        let mut descendants = Vec::new();
+        let Some(node) = self.nodes.get(&id) else {
+            return descendants;
+        };
+        let mut pending: Vec<_> = node.borrow().children.iter().rev().cloned().collect();
+        while let Some(node) = pending.pop() {
+            let node = node.borrow();
+            descendants.push(node.id);
+            pending.extend(node.children.iter().rev().cloned());
+        }
+        descendants
+    }
+
+    /// Whether `node` is `ancestor` or one of its descendants. `false` for unknown nodes.
+    pub fn contains(&self, ancestor: Id, node: Id) -> bool {
+        if !self.nodes.contains_key(&ancestor) || !self.nodes.contains_key(&node) {
+            return false;
+        }
+        let mut current = Some(node);
+        while let Some(id) = current {
+            if id == ancestor {
+                return true;
+            }
+            current = self.parent(id);
+        }
+        false
+    }
+
+    /// Ids of all nodes, attached to the tree or not, in no particular order.
+    pub fn node_ids(&self) -> Vec<Id> {
+        self.nodes.keys().copied().collect()
+    }
+}
+
+/// Where the node with `id` is among the children of `parent`.
+fn child_index(parent: &Node, id: Id) -> Option<usize> {
+    parent.children.iter().position(|c| c.borrow().id == id)
 }
 
 pub struct LayoutContext {
     pub document: Document,
     pub style_sheet: StyleSheet,
     flex_layout_engine: FlexLayoutEngine,
+    grid_layout_engine: GridLayoutEngine,
     pub text_measurer: Arc<dyn TextMeasurer>,
+    pub(crate) images: ImageLoader,
     viewport_size: Size,
+    /// Viewports of the secondary windows' roots.
+    window_viewports: HashMap<Id, Size>,
+    /// The media environment but for the viewport's width, which is each tree's own.
+    pub media: MediaEnvironment,
+    /// The node `:focus` matches, which gets a focus ring in `focus_accent` unless its style
+    /// sets another outline.
+    focused: Option<Id>,
+    focus_accent: Rgba,
+    style_cache: StyleCache,
+    transitions: Transitions,
+    /// The time transitions are shown at by the next layout pass.
+    frame_time: Instant,
+    pub(crate) templates: Templates,
+    /// Nodes sized and positioned so far, by layout passes and template skeletons alike.
+    laid_out_nodes: Cell<u64>,
+    pub(crate) intrinsic_sizes: IntrinsicSizeCache,
 }
 
 impl LayoutContext {
@@ -176,11 +521,23 @@ impl LayoutContext {
             document: Document::new(),
             style_sheet: StyleSheet::new(),
             flex_layout_engine: FlexLayoutEngine::new(),
+            grid_layout_engine: GridLayoutEngine::new(),
             text_measurer: default_text_measurer(),
+            images: ImageLoader::default(),
             viewport_size: Size {
                 width: 800.0,
                 height: 500.0,
             },
+            window_viewports: HashMap::new(),
+            media: MediaEnvironment::default(),
+            focused: None,
+            focus_accent: DEFAULT_FOCUS_ACCENT,
+            style_cache: StyleCache::default(),
+            transitions: Transitions::default(),
+            frame_time: Instant::now(),
+            templates: Templates::default(),
+            laid_out_nodes: Cell::new(0),
+            intrinsic_sizes: IntrinsicSizeCache::default(),
         }
     }
 
@@ -188,26 +545,230 @@ impl LayoutContext {
         self.viewport_size = Size { width, height };
     }
 
+    /// Set the viewport of the tree under `root`, the document's root or a window root.
+    pub fn set_root_viewport_size(&mut self, root: Id, width: f64, height: f64) {
+        if root == self.document.root_id() {
+            self.set_viewport_size(width, height);
+        } else {
+            self.window_viewports.insert(root, Size { width, height });
+        }
+    }
+
+    /// Forget the tree of a closed window.
+    pub fn remove_window_root(&mut self, root: Id) {
+        self.document.remove_tree(root);
+        self.window_viewports.remove(&root);
+    }
+
+    /// Running transitions end at once when motion gets reduced.
+    pub fn set_prefers_reduced_motion(&mut self, reduce: bool) {
+        self.media.prefers_reduced_motion = reduce;
+        if reduce {
+            self.transitions.finish_all();
+        }
+    }
+
+    /// The node `:focus` matches.
+    pub fn focused(&self) -> Option<Id> {
+        self.focused
+    }
+
+    /// Move focus to the node, or away from the one that has it. Both are restyled on the
+    /// next layout.
+    pub fn set_focus(&mut self, focused: Option<Id>) {
+        self.focused = focused;
+    }
+
+    pub fn set_focus_accent_color(&mut self, accent: Rgba) {
+        self.focus_accent = accent;
+    }
+
+    pub fn set_frame_time(&mut self, frame_time: Instant) {
+        self.frame_time = frame_time;
+    }
+
     pub fn layout(&mut self) {
         self.text_measurer.begin_layout_pass();
+        self.style_cache.begin_layout_pass();
+        self.intrinsic_sizes.begin_layout_pass();
+        self.transitions
+            .begin_pass(self.frame_time, self.media.prefers_reduced_motion);
         self.layout_node(self.document.root.clone(), 0.0, 0.0);
+        for root in self.document.window_roots.clone() {
+            if let Some(node) = self.document.get_node(root) {
+                self.layout_node(node, 0.0, 0.0);
+            }
+        }
+        self.transitions.end_pass();
+        self.style_cache.end_layout_pass_and_sweep();
         self.text_measurer.end_layout_pass_and_sweep();
     }
 
+    /// Whether the document is mid-transition, so another layout pass at a later frame time
+    /// would look different.
+    pub(crate) fn has_running_transitions(&self) -> bool {
+        self.transitions.is_running()
+    }
+
+    /// The node's style as shown at the frame time: the resolved style with running
+    /// transitions applied.
+    pub(crate) fn computed_style(&self, node: &Node) -> Style {
+        let mut style = self.resolve_style(node).as_ref().clone();
+        // Skeleton nodes aren't in the document and must not start transitions of real ones.
+        if !self.templates.is_building() {
+            self.transitions.apply(node.id, &mut style);
+        }
+        style
+    }
+
+    /// The node's inline style with the matching stylesheet rules applied.
+    pub(crate) fn resolve_style(&self, node: &Node) -> Arc<Style> {
+        let inherited = self.inherited(node);
+        let focus = (self.focused == Some(node.id)).then_some(self.focus_accent);
+        self.style_cache.resolve(
+            node,
+            &inherited,
+            &self.style_sheet,
+            &self.media_of(node),
+            focus,
+        )
+    }
+
+    /// The media environment of the tree the node is in. Its viewport's width is only
+    /// looked up when the stylesheet has rules for some, so resizing doesn't restyle otherwise.
+    pub(crate) fn media_of(&self, node: &Node) -> MediaEnvironment {
+        if !self.style_sheet.depends_on_viewport_width() {
+            return self.media.clone();
+        }
+        let root = self
+            .tree_root(node)
+            .map_or(node.id, |root| root.borrow().id);
+        self.media_in(root)
+    }
+
+    /// The media environment of the tree under `root`, the document's root or a window root.
+    fn media_in(&self, root: Id) -> MediaEnvironment {
+        let mut media = self.media.clone();
+        if self.style_sheet.depends_on_viewport_width() {
+            media.viewport_width = self.viewport_of(root).width;
+        }
+        media
+    }
+
+    /// What the node's style is resolved against from its parent and the root of its tree.
+    /// Only looked up when the stylesheet or the node's inline style depend on them.
+    fn inherited(&self, node: &Node) -> Inherited {
+        let mut inherited = Inherited::default();
+        if !self.style_sheet.depends_on_ancestors()
+            && !node.inline_style.has_ancestor_relative_lengths()
+        {
+            return inherited;
+        }
+        let root = self.tree_root(node);
+        let viewport = self.viewport_of(root.as_ref().map_or(node.id, |root| root.borrow().id));
+        inherited.lengths.viewport_width = viewport.width;
+        inherited.lengths.viewport_height = viewport.height;
+        if let Some(root) = root {
+            inherited.lengths.root_font_size = self.resolve_style(&root.borrow()).font_size_px();
+        }
+        if let Some(parent) = node.parent.and_then(|id| self.document.get_node(id)) {
+            let parent = self.resolve_style(&parent.borrow());
+            inherited.lengths.font_size = parent.font_size_px();
+            inherited.custom_properties = parent.custom_properties.clone();
+        }
+        inherited
+    }
+
+    /// The root of the tree the node is in, unless it's the node itself.
+    fn tree_root(&self, node: &Node) -> Option<Rc<RefCell<Node>>> {
+        node.parent?;
+        // Without windows, there's no other tree to look for.
+        if self.document.window_roots.is_empty() {
+            return Some(self.document.root_node());
+        }
+        let mut root = None;
+        let mut ancestor = node.parent;
+        while let Some(next) = ancestor.and_then(|id| self.document.get_node(id)) {
+            ancestor = next.borrow().parent;
+            root = Some(next);
+        }
+        root
+    }
+
+    /// The viewport of the tree under `root`, the document's root or a window root.
+    fn viewport_of(&self, root: Id) -> Size {
+        self.window_viewports
+            .get(&root)
+            .copied()
+            .unwrap_or(self.viewport_size)
+    }
+
+    /// What tooltips over the tree under `root` are painted with.
+    pub(crate) fn tooltip_style(&self, root: Id) -> Arc<Style> {
+        Arc::new(tooltip::resolve_style(
+            &self.style_sheet,
+            &self.media_in(root),
+        ))
+    }
+
+    /// Styles resolved without a cache hit so far.
+    pub(crate) fn style_cache_misses(&self) -> u64 {
+        self.style_cache.misses()
+    }
+
+    /// Styles resolved without the node's own being current so far.
+    #[cfg(test)]
+    pub(crate) fn style_cache_lookups(&self) -> u64 {
+        self.style_cache.lookups()
+    }
+
+    /// The rules and media environment styles are resolved against, which template
+    /// skeletons are only valid for.
+    pub(crate) fn style_generation(&self) -> (u64, MediaEnvironment) {
+        let media = self.media_in(self.document.root_id());
+        (self.style_sheet.generation(), media)
+    }
+
+    pub(crate) fn count_laid_out_node(&self) {
+        self.laid_out_nodes.set(self.laid_out_nodes.get() + 1);
+    }
+
+    /// Nodes sized and positioned so far.
+    pub(crate) fn laid_out_nodes(&self) -> u64 {
+        self.laid_out_nodes.get()
+    }
+
+    /// The decoded image of the node's `src` attribute, if it's ready; see [`crate::images`].
+    pub(crate) fn image_of(&self, node: &Node) -> Option<ImageHandle> {
+        self.images.image(node.attributes.get("src")?)
+    }
+
+    /// Lay out the children of `node`, which is already sized and positioned, with the
+    /// layout engine for its `display`.
+    pub(crate) fn layout_children(&self, node: Rc<RefCell<Node>>, style: &Style) {
+        match style.resolved_display() {
+            Display::Grid => self
+                .grid_layout_engine
+                .layout_grid_children(node, style, self),
+            Display::Flex | Display::None => self
+                .flex_layout_engine
+                .layout_flex_children(node, style, self),
+        }
+    }
+
     pub fn layout_node(&self, node: Rc<RefCell<Node>>, x: f64, y: f64) {
         // Get style for this node - merge existing style with CSS rules
-        let style = {
-            let node_borrow = node.borrow();
-            // Start with existing style as base (this preserves manually set properties like flex_wrap)
-            let mut style = node_borrow.layout.style.as_ref().clone();
-
-            crate::style_matching::apply_matching_rules(
-                &mut style,
-                &node_borrow.attributes,
-                &self.style_sheet,
-            );
-            style
-        };
+        // Start from the inline style (this preserves manually set properties like flex_wrap)
+        // rather than the last computed style, so rules that stop matching no longer apply.
+        let style = self.computed_style(&node.borrow());
+
+        if style.resolved_display() == Display::None {
+            let mut node_borrow = node.borrow_mut();
+            node_borrow.layout.bounds = Rect::default();
+            node_borrow.layout.style = Arc::new(style);
+            return;
+        }
+        self.count_laid_out_node();
 
         // Set position (margins will be applied by flex layout engine for flex items)
         {
@@ -218,6 +779,7 @@ impl LayoutContext {
 
         let is_leaf = node.borrow().children.is_empty();
         let is_text_node = node.borrow().is_text_node();
+        let image = self.image_of(&node.borrow());
 
         // Sonate stores `layout.bounds` as the element's border-box.
         // `box-sizing` determines whether CSS `width/height` refer to the content-box or border-box.
@@ -225,13 +787,13 @@ impl LayoutContext {
         let padding = style.padding.resolved();
         let padding_w = padding.left.to_px() + padding.right.to_px();
         let padding_h = padding.top.to_px() + padding.bottom.to_px();
-        let border = style.border_width.resolved();
+        let border = style.used_border_width();
         let border_w = border.left.to_px() + border.right.to_px();
         let border_h = border.top.to_px() + border.bottom.to_px();
 
         let resolve_border_box =
-            |specified: Option<Length>, fallback: f64, padding_sum: f64, border_sum: f64| -> f64 {
-                let Some(Length::Px(px)) = specified else {
+            |specified: Option<&Length>, fallback: f64, padding_sum: f64, border_sum: f64| -> f64 {
+                let Some(&Length::Px(px)) = specified else {
                     return fallback;
                 };
 
@@ -242,12 +804,17 @@ impl LayoutContext {
             };
 
         if is_leaf {
-            // Leaf node - use specified dimensions or defaults.
+            // Leaf node - use specified dimensions, else its padding and border.
             // If this is a text node, prefer intrinsic text sizing.
-            let mut fallback_width_border_box = 100.0;
-            let mut fallback_height_border_box = 30.0;
+            let mut fallback_width_border_box = padding_w + border_w;
+            let mut fallback_height_border_box = padding_h + border_h;
 
-            if is_text_node {
+            if let Some(image) = &image {
+                // Images size the node rather than its text.
+                let (image_width, image_height) = image_item_size(image, &style);
+                fallback_width_border_box = image_width.unwrap_or(fallback_width_border_box);
+                fallback_height_border_box = image_height.unwrap_or(fallback_height_border_box);
+            } else if is_text_node {
                 if let Some(text) = node.borrow().text.as_deref() {
                     let font = FontSpec::from_style(&style);
 
@@ -260,7 +827,9 @@ impl LayoutContext {
                     // Height: if not specified, try to wrap to a specified width (if any), else unwrapped.
                     if matches!(style.height, Some(Length::Auto)) {
                         let text_size = match style.width {
-                            Some(Length::Px(specified_width_px)) if specified_width_px > 0.0 => {
+                            Some(Length::Px(specified_width_px))
+                                if specified_width_px > 0.0 && style.wraps_text() =>
+                            {
                                 // Wrap within the content box width.
                                 let content_max_width = match resolved_box_sizing {
                                     BoxSizing::ContentBox => specified_width_px,
@@ -268,8 +837,9 @@ impl LayoutContext {
                                         (specified_width_px - padding_w - border_w).max(0.0)
                                     }
                                 };
-                                self.text_measurer
-                                    .measure_wrapped(text, &font, content_max_width)
+                                let measurer = self.text_measurer.as_ref();
+                                let clamp = style.resolved_line_clamp();
+                                measure_clamped(measurer, text, &font, content_max_width, clamp)
                             }
                             _ => self.text_measurer.measure_unwrapped(text, &font),
                         };
@@ -280,33 +850,31 @@ impl LayoutContext {
             }
 
             let mut node_borrow = node.borrow_mut();
-            node_borrow.layout.bounds.width =
-                resolve_border_box(style.width, fallback_width_border_box, padding_w, border_w);
+            node_borrow.layout.bounds.width = resolve_border_box(
+                style.width.as_ref(),
+                fallback_width_border_box,
+                padding_w,
+                border_w,
+            );
             node_borrow.layout.bounds.height = resolve_border_box(
-                style.height,
+                style.height.as_ref(),
                 fallback_height_border_box,
                 padding_h,
                 border_h,
             );
             node_borrow.layout.style = Arc::new(style);
+            node_borrow.layout.image = image;
         } else {
             // Container node - handle flexbox layout
             let is_root = node.borrow().parent.is_none();
-            let fallback_width = if is_root {
-                self.viewport_size.width
-            } else {
-                800.0
-            };
-            let fallback_height = if is_root {
-                self.viewport_size.height
-            } else {
-                500.0
-            };
+            let viewport = self.viewport_of(node.borrow().id);
+            let fallback_width = if is_root { viewport.width } else { 800.0 };
+            let fallback_height = if is_root { viewport.height } else { 500.0 };
 
             let container_width =
-                resolve_border_box(style.width, fallback_width, padding_w, border_w);
+                resolve_border_box(style.width.as_ref(), fallback_width, padding_w, border_w);
             let container_height =
-                resolve_border_box(style.height, fallback_height, padding_h, border_h);
+                resolve_border_box(style.height.as_ref(), fallback_height, padding_h, border_h);
 
             // Set container dimensions
             {
@@ -314,26 +882,124 @@ impl LayoutContext {
                 node_borrow.layout.bounds.width = container_width;
                 node_borrow.layout.bounds.height = container_height;
                 node_borrow.layout.style = Arc::new(style.clone());
+                node_borrow.layout.image = image;
             }
 
-            // Layout children using the dedicated flex layout engine
-            self.flex_layout_engine
-                .layout_flex_children(node.clone(), &style, self);
+            self.layout_children(node.clone(), &style);
         }
     }
 }
 
 /// Snapshot types safe to share across threads
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct RenderNode {
     pub id: Id,
+    /// The border box.
     pub bounds: Rect,
+    /// Resolved `margin`, around `bounds`; `auto` is 0.
+    pub margin: Edges,
+    /// Resolved `padding`, inside the border.
+    pub padding: Edges,
+    /// Resolved `border-width`, 0 where `border-style` is `none` or `hidden`.
+    pub border_width: Edges,
     pub style: Arc<Style>,
     pub text: Option<String>,
-    pub children: Vec<RenderNode>,
+    /// The `title` attribute, shown as a tooltip.
+    pub title: Option<String>,
+    /// The `role` attribute, for the accessibility tree.
+    pub role: Option<String>,
+    /// The `aria-label` attribute, the node's accessible name.
+    pub aria_label: Option<String>,
+    /// Resolved `z-index`; `auto` is 0.
+    pub z_index: i32,
+    /// Whether the node has `editable="true"` and takes focus and typing when clicked.
+    pub editable: bool,
+    /// The decoded image of the `src` attribute, painted in the content box as `object-fit`
+    /// says. `None` while it decodes, or if it failed to.
+    pub image: Option<ImageHandle>,
+    /// Whether `text-overflow: ellipsis` or `line-clamp` cut the text short, so an app may
+    /// want to show all of it, such as in a tooltip.
+    pub text_truncated: bool,
+    /// Union of `bounds` and the descendant bounds of visible children, unless this node
+    /// clips its overflow. Nothing outside it is painted or hit.
+    pub descendant_bounds: Rect,
+    /// Shared with the snapshots before, where the subtree didn't change.
+    pub children: Vec<Arc<RenderNode>>,
 }
 
 impl RenderNode {
+    /// The border box, which `bounds` is.
+    pub fn border_box(&self) -> Rect {
+        self.bounds
+    }
+
+    /// The border box inside the border, where the background is painted.
+    pub fn padding_box(&self) -> Rect {
+        self.bounds.inset_by(&self.border_width)
+    }
+
+    /// The padding box inside the padding, where children and text are laid out.
+    pub fn content_box(&self) -> Rect {
+        self.padding_box().inset_by(&self.padding)
+    }
+
+    /// Whether the point is in the border box, outside the corners `border-radius` rounds
+    /// off. Edges are included, as in [`Rect::contains_point`].
+    pub fn contains_point(&self, x: f64, y: f64) -> bool {
+        let bounds = self.border_box();
+        if !bounds.contains_point(x, y) {
+            return false;
+        }
+        let (left, top) = (bounds.x, bounds.y);
+        let (right, bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+        let [top_left, top_right, bottom_right, bottom_left] = self.corner_radii();
+        // Each corner's radii, where the box's edges meet there and which way is outward.
+        [
+            (top_left, (left, top), (-1.0, -1.0)),
+            (top_right, (right, top), (1.0, -1.0)),
+            (bottom_right, (right, bottom), (1.0, 1.0)),
+            (bottom_left, (left, bottom), (-1.0, 1.0)),
+        ]
+        .into_iter()
+        .all(|((rx, ry), (corner_x, corner_y), (sx, sy))| {
+            let (dx, dy) = (x - (corner_x - sx * rx), y - (corner_y - sy * ry));
+            if rx <= 0.0 || ry <= 0.0 || dx * sx <= 0.0 || dy * sy <= 0.0 {
+                return true;
+            }
+            (dx / rx).powi(2) + (dy / ry).powi(2) <= 1.0
+        })
+    }
+
+    /// The horizontal and vertical radius of each corner, from the top left clockwise.
+    /// Where two corners' radii add up to more than the side between them, all are scaled
+    /// down until they fit, as CSS specifies and the painter draws them.
+    fn corner_radii(&self) -> [(f64, f64); 4] {
+        let radius = &self.style.border_radius;
+        let radii = [
+            &radius.top_left,
+            &radius.top_right,
+            &radius.bottom_right,
+            &radius.bottom_left,
+        ]
+        .map(|corner| {
+            corner
+                .as_ref()
+                .map_or((0.0, 0.0), |r| (r.x.to_px().max(0.0), r.y.to_px().max(0.0)))
+        });
+        let [top_left, top_right, bottom_right, bottom_left] = radii;
+        let (width, height) = (self.bounds.width, self.bounds.height);
+        let scale = [
+            (width, top_left.0 + top_right.0),
+            (width, bottom_left.0 + bottom_right.0),
+            (height, top_left.1 + bottom_left.1),
+            (height, top_right.1 + bottom_right.1),
+        ]
+        .into_iter()
+        .filter(|&(_, sum)| sum > 0.0)
+        .fold(1.0_f64, |scale, (side, sum)| scale.min(side / sum));
+        radii.map(|(x, y)| (x * scale, y * scale))
+    }
+
     /// Find the element at the given position (x, y).
     ///
     /// Returns a `Vec<Id>` where the first element is the topmost element at the position,
@@ -343,42 +1009,270 @@ impl RenderNode {
         self.find_path_at_position(x, y).unwrap_or_default()
     }
 
+    /// The node with `id` and its ancestors up to this node, in the order
+    /// [`Self::find_element_at_position`] gives them. Empty if it isn't in this subtree.
+    pub(crate) fn path_to(&self, id: Id) -> Vec<Id> {
+        if self.id == id {
+            return vec![id];
+        }
+        for child in &self.children {
+            let mut path = child.path_to(id);
+            if !path.is_empty() {
+                path.push(self.id);
+                return path;
+            }
+        }
+        Vec::new()
+    }
+
+    /// The nodes of `path`, a path from this node as [`Self::find_element_at_position`]
+    /// gives, from this node down to the topmost. Stops where a node isn't a child of the
+    /// one before.
+    pub(crate) fn nodes_on_path(&self, path: &[Id]) -> Vec<&RenderNode> {
+        let mut nodes = vec![self];
+        for id in path.iter().rev().skip(1) {
+            let parent = nodes[nodes.len() - 1];
+            let Some(child) = parent.children.iter().find(|c| c.id == *id) else {
+                break;
+            };
+            nodes.push(child);
+        }
+        nodes
+    }
+
     fn find_path_at_position(&self, x: f64, y: f64) -> Option<Vec<Id>> {
-        if !self.bounds.contains_point(x, y) {
+        // Nothing in this subtree is painted outside its descendant bounds.
+        if !self.descendant_bounds.contains_point(x, y) {
+            return None;
+        }
+        self.hit_test(x, y, RenderNode::find_path_at_position)
+    }
+
+    /// Hit test this node, using `hit_child` for its children.
+    fn hit_test(
+        &self,
+        x: f64,
+        y: f64,
+        hit_child: fn(&RenderNode, f64, f64) -> Option<Vec<Id>>,
+    ) -> Option<Vec<Id>> {
+        let children = self.children.iter().map(|child| (&**child, ())).collect();
+        self.hit_test_among(x, y, children, |child, ()| hit_child(child, x, y))
+    }
+
+    /// Hit test this node, given the children that may be hit in document order, each with
+    /// what `hit_child` needs to hit test it.
+    ///
+    /// Children are tested topmost first, including where they overflow this element, unless
+    /// the element clips its overflow. Children with a negative z-index are painted behind
+    /// this element, so it covers them within its own bounds. Subtrees with `visibility:
+    /// hidden` or `pointer-events: none` aren't hit.
+    pub(crate) fn hit_test_among<'a, T>(
+        &self,
+        x: f64,
+        y: f64,
+        mut children: Vec<(&'a RenderNode, T)>,
+        mut hit_child: impl FnMut(&'a RenderNode, T) -> Option<Vec<Id>>,
+    ) -> Option<Vec<Id>> {
+        // Hidden subtrees are not painted, so they can't be hit either.
+        // Transparent (`opacity: 0`) elements are still hittable, like in browsers.
+        if self.style.is_hidden() || self.style.ignores_pointer_events() {
+            return None;
+        }
+
+        let in_bounds = self.bounds.contains_point(x, y);
+        if self.style.clips_overflow() && !in_bounds {
             return None;
         }
 
-        for child in self.children.iter().rev() {
-            if let Some(mut path) = child.find_path_at_position(x, y) {
+        // In paint order, as `children_in_paint_order` sorts them.
+        children.sort_by_key(|(child, _)| child.z_index);
+        let split = children.partition_point(|(child, _)| child.z_index < 0);
+        let in_front = children.split_off(split);
+        let mut hit_children = |children: Vec<(&'a RenderNode, T)>| {
+            children.into_iter().rev().find_map(|(child, data)| {
+                let mut path = hit_child(child, data)?;
                 path.push(self.id);
-                return Some(path);
-            }
+                Some(path)
+            })
+        };
+
+        if let Some(path) = hit_children(in_front) {
+            return Some(path);
         }
+        // Clipping is to the rectangle, but rounded-off corners show what's behind.
+        if in_bounds && self.contains_point(x, y) {
+            return Some(vec![self.id]);
+        }
+        hit_children(children)
+    }
 
-        Some(vec![self.id])
+    /// Reference hit test that visits every node, without the descendant bounds early-out.
+    #[cfg(test)]
+    pub(crate) fn find_element_at_position_exhaustive(&self, x: f64, y: f64) -> Vec<Id> {
+        fn find(node: &RenderNode, x: f64, y: f64) -> Option<Vec<Id>> {
+            node.hit_test(x, y, find)
+        }
+        find(self, x, y).unwrap_or_default()
+    }
+
+    /// Children sorted by (z-index, document order), split into those painted behind this
+    /// element's own background (negative z-index) and those painted on top of it.
+    pub(crate) fn children_in_paint_order(&self) -> (Vec<&RenderNode>, Vec<&RenderNode>) {
+        let mut children: Vec<&RenderNode> = self.children.iter().map(|child| &**child).collect();
+        children.sort_by_key(|child| child.z_index);
+        let split = children.partition_point(|child| child.z_index < 0);
+        let in_front = children.split_off(split);
+        (children, in_front)
     }
 }
 
+#[cfg(test)]
 pub fn build_render_tree(node: Rc<RefCell<Node>>) -> RenderNode {
+    let measurer = crate::text::TestTextMeasurer;
+    Arc::unwrap_or_clone(rebuild_render_tree(&node, None, &measurer))
+}
+
+/// Build the render tree of `node` after a layout, sharing the subtrees that are the same as
+/// in `previous`, the tree built after the layout before. `measurer` measures the lines of
+/// text that may be cut short.
+pub(crate) fn rebuild_render_tree(
+    node: &Rc<RefCell<Node>>,
+    previous: Option<&Arc<RenderNode>>,
+    measurer: &dyn TextMeasurer,
+) -> Arc<RenderNode> {
     let nb = node.borrow();
+    let previous = previous.filter(|previous| previous.id == nb.id);
     let mut children = Vec::with_capacity(nb.children.len());
-    for c in &nb.children {
-        children.push(build_render_tree(c.clone()));
+    // `display: none` subtrees aren't laid out, so their bounds are stale; leave them out.
+    let is_displayed =
+        |n: &Rc<RefCell<Node>>| n.borrow().layout.style.resolved_display() != Display::None;
+    if is_displayed(node) {
+        let previous_children = previous.map_or(&[][..], |previous| &previous.children);
+        // Children mostly keep their order, so look where the last match left off first.
+        let mut next = 0;
+        for c in nb.children.iter().filter(|c| is_displayed(c)) {
+            let id = c.borrow().id;
+            let found = match previous_children.get(next) {
+                Some(child) if child.id == id => Some(next),
+                _ => previous_children.iter().position(|child| child.id == id),
+            };
+            if let Some(index) = found {
+                next = index + 1;
+            }
+            children.push(rebuild_render_tree(
+                c,
+                found.map(|index| &previous_children[index]),
+                measurer,
+            ));
+        }
     }
-    RenderNode {
+
+    let bounds = nb.layout.bounds;
+    let style = &nb.layout.style;
+    let descendant_bounds = descendant_bounds(bounds, style, &children);
+    let margin = Edges::resolved(&style.margin);
+    let padding = Edges::resolved(&style.padding);
+    let border_width = Edges::from(&style.used_border_width());
+    let editable = nb.attributes.get("editable").is_some_and(|v| v == "true");
+    let title = nb.attributes.get("title");
+    let role = nb.attributes.get("role");
+    let aria_label = nb.attributes.get("aria-label");
+    if let Some(previous) = previous {
+        let same_children = previous.children.len() == children.len()
+            && previous
+                .children
+                .iter()
+                .zip(&children)
+                .all(|(old, new)| Arc::ptr_eq(old, new));
+        if same_children
+            && previous.bounds == bounds
+            && previous.descendant_bounds == descendant_bounds
+            && previous.text == nb.text
+            && previous.editable == editable
+            && previous.image == nb.layout.image
+            && previous.title.as_ref() == title
+            && previous.role.as_ref() == role
+            && previous.aria_label.as_ref() == aria_label
+            && (Arc::ptr_eq(&previous.style, style) || *previous.style == **style)
+        {
+            return Arc::clone(previous);
+        }
+    }
+    let content_width = bounds.inset_by(&border_width).inset_by(&padding).width;
+    let text_truncated = nb
+        .text
+        .as_deref()
+        .is_some_and(|text| is_truncated(measurer, text, style, content_width));
+    Arc::new(RenderNode {
         id: nb.id,
-        bounds: nb.layout.bounds,
-        style: nb.layout.style.clone(),
+        bounds,
+        margin,
+        padding,
+        border_width,
+        descendant_bounds,
+        style: style.clone(),
         text: nb.text.clone(),
+        title: title.cloned(),
+        role: role.cloned(),
+        aria_label: aria_label.cloned(),
+        z_index: style.resolved_z_index(),
+        editable,
+        image: nb.layout.image.clone(),
+        text_truncated,
         children,
+    })
+}
+
+/// Whether the lines of `text` in a content box `content_width` wide are cut short.
+fn is_truncated(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    style: &Style,
+    content_width: f64,
+) -> bool {
+    let line_style = LineStyle::from_style(style);
+    if !line_style.truncates() {
+        return false;
     }
+    let font = FontSpec::from_style(style);
+    line_boxes(measurer, text, &font, content_width, line_style)
+        .iter()
+        .any(|line| line.truncated)
+}
+
+/// See [`RenderNode::descendant_bounds`]. The node's own outline is part of them, but only
+/// hit testing stops at `bounds` anyway.
+pub(crate) fn descendant_bounds(bounds: Rect, style: &Style, children: &[Arc<RenderNode>]) -> Rect {
+    let own = outline_bounds(bounds, style).map_or(bounds, |outline| bounds.union(&outline));
+    if style.clips_overflow() {
+        return own;
+    }
+    children
+        .iter()
+        .filter(|child| !child.style.is_hidden())
+        .fold(own, |union, child| union.union(&child.descendant_bounds))
+}
+
+/// The outer edge of the outline `style` paints around the border box `bounds`, if any. An
+/// offset insets it at most to the middle of the box.
+pub(crate) fn outline_bounds(bounds: Rect, style: &Style) -> Option<Rect> {
+    let width = style.outline_width_px();
+    if width <= 0.0 {
+        return None;
+    }
+    let offset = style.outline_offset.as_ref().map_or(0.0, Length::to_px);
+    let offset = offset.max(-bounds.width.min(bounds.height) / 2.0);
+    Some(bounds.outset(offset + width))
 }
 
 #[cfg(test)]
 mod asserts;
 
 #[cfg(test)]
-mod test_html;
+pub(crate) mod test_html;
+
+#[cfg(test)]
+pub(crate) mod test_tree;
 
 #[cfg(test)]
 mod flex_layout_flow_tests;
@@ -409,3 +1303,27 @@ mod margin_tests;
 
 #[cfg(test)]
 mod tag_selector_tests;
+
+#[cfg(test)]
+mod media_query_tests;
+
+#[cfg(test)]
+mod display_none_tests;
+
+#[cfg(test)]
+mod render_tree_tests;
+
+#[cfg(test)]
+mod child_order_tests;
+
+#[cfg(test)]
+mod relative_length_tests;
+
+#[cfg(test)]
+mod grid_layout_tests;
+
+#[cfg(test)]
+mod aspect_ratio_tests;
+
+#[cfg(test)]
+mod flex_fixture_tests;
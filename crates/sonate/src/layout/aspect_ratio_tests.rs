@@ -0,0 +1,59 @@
+use crate::layout::{asserts::LayoutContextAsserts, test_html::load_html_test_example};
+
+use super::*;
+
+const HTML: &str = include_str!("./aspect_ratio_tests.html");
+
+#[test]
+fn items_take_their_height_from_their_width() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "row");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["row_1"], &Rect::new(0.0, 0.0, 100.0, 50.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["row_2"], &Rect::new(100.0, 0.0, 100.0, 50.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["row_3"], &Rect::new(200.0, 0.0, 100.0, 50.0));
+}
+
+#[test]
+fn the_ratio_caps_the_stretched_height() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "stretch");
+
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["stretch_ratio"],
+        &Rect::new(0.0, 0.0, 100.0, 50.0),
+    );
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["stretch_plain"],
+        &Rect::new(100.0, 0.0, 100.0, 200.0),
+    );
+}
+
+#[test]
+fn items_take_their_flex_base_size_from_their_height() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "from_height");
+
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["from_height_1"],
+        &Rect::new(0.0, 0.0, 120.0, 60.0),
+    );
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["from_height_2"],
+        &Rect::new(120.0, 0.0, 100.0, 0.0),
+    );
+}
+
+#[test]
+fn the_height_follows_the_flex_basis_and_the_flexed_width() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "basis");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["basis_1"], &Rect::new(0.0, 0.0, 200.0, 50.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["basis_2"], &Rect::new(200.0, 0.0, 200.0, 100.0));
+}
+
+#[test]
+fn grid_items_take_their_height_from_their_cell() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "cards");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["card_1"], &Rect::new(0.0, 0.0, 200.0, 112.5));
+    ctx.assert_node_bounds_eq(nodes_by_id["card_2"], &Rect::new(200.0, 0.0, 200.0, 112.5));
+    ctx.assert_node_bounds_eq(nodes_by_id["card_3"], &Rect::new(0.0, 112.5, 200.0, 112.5));
+}
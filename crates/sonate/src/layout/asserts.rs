@@ -1,3 +1,21 @@
+/// Hit test with the descendant bounds early-out, checked against the exhaustive traversal
+/// and the hit index.
+pub fn hit(tree: &super::RenderNode, x: f64, y: f64) -> Vec<super::Id> {
+    let result = tree.find_element_at_position(x, y);
+    assert_eq!(
+        result,
+        tree.find_element_at_position_exhaustive(x, y),
+        "traversals disagree at ({x}, {y})"
+    );
+    let index = crate::hit_index::HitIndex::new(tree);
+    assert_eq!(
+        result,
+        index.find_element_at_position(tree, x, y),
+        "the hit index disagrees at ({x}, {y})"
+    );
+    result
+}
+
 pub trait RectAsserts {
     fn assert_eq(&self, expected: &super::Rect);
 }
@@ -0,0 +1,149 @@
+use crate::layout::asserts::hit;
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{build_render_tree, RenderNode, SetParentError};
+use crate::Id;
+use std::collections::HashMap;
+
+const HTML: &str = r#"
+<style>
+  .row { display: flex; flex-direction: row; width: 300px; height: 50px; }
+  .item { width: 50px; height: 20px; }
+</style>
+<div id="lists">
+  <div id="list" class="row">
+    <div id="a" class="item"></div>
+    <div id="b" class="item"></div>
+    <div id="c" class="item"></div>
+  </div>
+  <div id="other" class="row">
+    <div id="d" class="item"><div id="d_child" class="item"></div></div>
+  </div>
+</div>
+"#;
+
+/// The children of `parent` in the render tree, by their names in `ids`.
+fn child_names(tree: &RenderNode, parent: Id, ids: &HashMap<String, Id>) -> Vec<String> {
+    let name = |id: Id| ids.iter().find(|(_, v)| **v == id).unwrap().0.clone();
+    find(tree, parent)
+        .unwrap()
+        .children
+        .iter()
+        .map(|child| name(child.id))
+        .collect()
+}
+
+fn find(node: &RenderNode, id: Id) -> Option<&RenderNode> {
+    if node.id == id {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| find(child, id))
+}
+
+#[test]
+fn insert_before_places_a_new_child_among_its_siblings() {
+    let (mut ctx, mut ids) = load_html_test_example(HTML, "lists");
+    let new = ctx.document.create_node(Id::from_u64(100), None).unwrap();
+    ctx.document
+        .set_attribute(new, "class".to_owned(), "item".to_owned());
+    ids.insert("new".to_owned(), new);
+
+    ctx.document
+        .insert_before(ids["list"], new, ids["b"])
+        .unwrap();
+    ctx.layout();
+
+    let tree = build_render_tree(ctx.document.root_node());
+    assert_eq!(
+        child_names(&tree, ids["list"], &ids),
+        ["a", "new", "b", "c"]
+    );
+    assert_eq!(find(&tree, new).unwrap().bounds.x, 50.0);
+    assert_eq!(find(&tree, ids["c"]).unwrap().bounds.x, 150.0);
+}
+
+#[test]
+fn insert_before_moves_an_existing_child_or_one_from_another_parent() {
+    let (mut ctx, ids) = load_html_test_example(HTML, "lists");
+
+    ctx.document
+        .insert_before(ids["list"], ids["c"], ids["a"])
+        .unwrap();
+    ctx.document
+        .insert_before(ids["list"], ids["d"], ids["b"])
+        .unwrap();
+    // Inserting a child before itself leaves it where it is.
+    ctx.document
+        .insert_before(ids["list"], ids["b"], ids["b"])
+        .unwrap();
+    ctx.layout();
+
+    let tree = build_render_tree(ctx.document.root_node());
+    assert_eq!(child_names(&tree, ids["list"], &ids), ["c", "a", "d", "b"]);
+    assert!(child_names(&tree, ids["other"], &ids).is_empty());
+    let d = ctx.document.get_node(ids["d"]).unwrap();
+    assert_eq!(d.borrow().parent, Some(ids["list"]));
+}
+
+#[test]
+fn move_child_reorders_layout_paint_and_hit_testing() {
+    let (mut ctx, ids) = load_html_test_example(HTML, "lists");
+
+    ctx.document.move_child(ids["list"], ids["a"], 2).unwrap();
+    ctx.layout();
+    let tree = build_render_tree(ctx.document.root_node());
+    assert_eq!(child_names(&tree, ids["list"], &ids), ["b", "c", "a"]);
+
+    let list = find(&tree, ids["list"]).unwrap();
+    let (behind, in_front) = list.children_in_paint_order();
+    assert!(behind.is_empty());
+    let painted: Vec<Id> = in_front.iter().map(|child| child.id).collect();
+    assert_eq!(painted, [ids["b"], ids["c"], ids["a"]]);
+    assert_eq!(hit(&tree, 10.0, 10.0)[0], ids["b"]);
+    assert_eq!(hit(&tree, 110.0, 10.0)[0], ids["a"]);
+
+    // Past the end moves to the end.
+    ctx.document.move_child(ids["list"], ids["b"], 10).unwrap();
+    ctx.document.move_child(ids["list"], ids["a"], 0).unwrap();
+    ctx.layout();
+    let tree = build_render_tree(ctx.document.root_node());
+    assert_eq!(child_names(&tree, ids["list"], &ids), ["a", "c", "b"]);
+}
+
+#[test]
+fn failed_reorders_leave_the_children_alone() {
+    let (mut ctx, ids) = load_html_test_example(HTML, "lists");
+    let list = ids["list"];
+    let missing = Id::from_u64(999);
+
+    let document = &mut ctx.document;
+    assert_eq!(
+        document.insert_before(list, ids["d"], ids["d_child"]),
+        Err(SetParentError::NotAChild)
+    );
+    assert_eq!(
+        document.insert_before(ids["d"], ids["other"], ids["d_child"]),
+        Err(SetParentError::Cycle)
+    );
+    assert_eq!(
+        document.insert_before(missing, ids["a"], ids["b"]),
+        Err(SetParentError::ParentNotFound)
+    );
+    assert_eq!(
+        document.insert_before(list, missing, ids["b"]),
+        Err(SetParentError::ChildNotFound)
+    );
+    assert_eq!(
+        document.move_child(list, ids["d"], 0),
+        Err(SetParentError::NotAChild)
+    );
+    assert_eq!(
+        document.move_child(list, missing, 0),
+        Err(SetParentError::ChildNotFound)
+    );
+
+    ctx.layout();
+    let tree = build_render_tree(ctx.document.root_node());
+    assert_eq!(child_names(&tree, list, &ids), ["a", "b", "c"]);
+    assert_eq!(child_names(&tree, ids["other"], &ids), ["d"]);
+    assert_eq!(child_names(&tree, ids["d"], &ids), ["d_child"]);
+}
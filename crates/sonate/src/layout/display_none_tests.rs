@@ -0,0 +1,70 @@
+use super::asserts::{hit, LayoutContextAsserts};
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{build_render_tree, Rect, RenderNode};
+use crate::Id;
+
+const HTML: &str = r#"
+<style>
+  .row { display: flex; flex-direction: row; width: 300px; height: 50px; column-gap: 10px; }
+  .item { width: 50px; height: 20px; }
+  .gone { display: none; }
+</style>
+<div id="three" class="row">
+  <div id="a" class="item"></div>
+  <div id="b" class="item gone"><div id="b_child" class="item"></div></div>
+  <div id="c" class="item"></div>
+</div>
+<div id="two" class="row">
+  <div id="a" class="item"></div>
+  <div id="c" class="item"></div>
+</div>
+"#;
+
+fn contains_id(node: &RenderNode, id: Id) -> bool {
+    node.id == id || node.children.iter().any(|c| contains_id(c, id))
+}
+
+#[test]
+fn display_none_item_lays_out_like_it_is_absent() {
+    let (with_hidden, three) = load_html_test_example(HTML, "three");
+    let (without, two) = load_html_test_example(HTML, "two");
+
+    for id in ["a", "c"] {
+        let node = without.document.get_node(two[id]).expect("node not found");
+        let expected = node.borrow().layout.bounds;
+        with_hidden.assert_node_bounds_eq(three[id], &expected);
+    }
+
+    // Sanity check: the gap is applied once, between the two remaining items.
+    with_hidden.assert_node_bounds_eq(three["c"], &Rect::new(60.0, 0.0, 50.0, 20.0));
+
+    let render_tree = build_render_tree(with_hidden.document.root_node());
+    assert!(!contains_id(&render_tree, three["b"]));
+    assert!(!contains_id(&render_tree, three["b_child"]));
+    let hit = hit(&render_tree, 75.0, 10.0);
+    assert_eq!(hit.first(), Some(&three["c"]));
+}
+
+#[test]
+fn toggling_display_none_restores_layout() {
+    let (mut ctx, ids) = load_html_test_example(HTML, "three");
+
+    ctx.document
+        .set_attribute(ids["b"], "class".to_owned(), "item".to_owned());
+    ctx.layout();
+    ctx.assert_node_bounds_eq(ids["b"], &Rect::new(60.0, 0.0, 50.0, 20.0));
+    ctx.assert_node_bounds_eq(ids["c"], &Rect::new(120.0, 0.0, 50.0, 20.0));
+    assert!(contains_id(
+        &build_render_tree(ctx.document.root_node()),
+        ids["b_child"]
+    ));
+
+    ctx.document
+        .set_attribute(ids["b"], "class".to_owned(), "item gone".to_owned());
+    ctx.layout();
+    ctx.assert_node_bounds_eq(ids["c"], &Rect::new(60.0, 0.0, 50.0, 20.0));
+    assert!(!contains_id(
+        &build_render_tree(ctx.document.root_node()),
+        ids["b"]
+    ));
+}
@@ -0,0 +1,121 @@
+//! Flex layout conformance fixtures. Each file in `tests/fixtures/flex` is markup with a
+//! `<style>`, whose `#fixture` element is laid out, and every element with a
+//! `data-expected="x y width height"` attribute must get those bounds.
+//!
+//! On `#fixture`, `data-tolerance` sets how many pixels off the bounds may be, a thousandth
+//! by default, and `data-expected-fail` says why the fixture fails for now. Such a fixture
+//! fails the run once it passes, so the fix takes the attribute off.
+
+use super::test_tree::format_rect;
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{LayoutContext, Rect};
+use crate::Id;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Relative to the crate, where tests run.
+const FIXTURES: &str = "tests/fixtures/flex";
+
+const DEFAULT_TOLERANCE: f64 = 0.001;
+
+#[test]
+fn flex_layout_matches_the_fixtures() {
+    let mut paths: Vec<PathBuf> = fs::read_dir(FIXTURES)
+        .expect("couldn't read the fixtures directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "html")
+        })
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no fixtures in {FIXTURES}");
+
+    let mut problems = Vec::new();
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let html = fs::read_to_string(path).expect("couldn't read the fixture");
+        let (ctx, nodes_by_id) = load_html_test_example(&html, "fixture");
+        let fixture = *nodes_by_id
+            .get("fixture")
+            .unwrap_or_else(|| panic!("{name} has no #fixture element"));
+
+        let tolerance = attribute(&ctx, fixture, "data-tolerance")
+            .map_or(DEFAULT_TOLERANCE, |tolerance| {
+                tolerance.parse().expect("data-tolerance is a number")
+            });
+        let mismatches = mismatches(&ctx, &nodes_by_id, tolerance, &name);
+        match attribute(&ctx, fixture, "data-expected-fail") {
+            Some(reason) if mismatches.is_empty() => problems.push(format!(
+                "{name} passes now; take off its data-expected-fail ({reason})"
+            )),
+            Some(_) => {}
+            None => problems.extend(
+                mismatches
+                    .iter()
+                    .map(|mismatch| format!("{name}: {mismatch}")),
+            ),
+        }
+    }
+
+    assert!(
+        problems.is_empty(),
+        "{} of {} flex fixtures fail:\n{}",
+        problems.len(),
+        paths.len(),
+        problems.join("\n")
+    );
+}
+
+fn attribute(ctx: &LayoutContext, id: Id, key: &str) -> Option<String> {
+    let node = ctx.document.get_node(id)?;
+    let value = node.borrow().attributes.get(key).cloned();
+    value
+}
+
+/// The nodes whose bounds aren't the ones they expect, in name order.
+fn mismatches(
+    ctx: &LayoutContext,
+    nodes_by_id: &HashMap<String, Id>,
+    tolerance: f64,
+    fixture: &str,
+) -> Vec<String> {
+    let mut names: Vec<&String> = nodes_by_id.keys().collect();
+    names.sort();
+
+    let close = |a: f64, b: f64| (a - b).abs() <= tolerance;
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let id = nodes_by_id[name];
+            let expected = parse_bounds(&attribute(ctx, id, "data-expected")?)
+                .unwrap_or_else(|| panic!("{fixture}: `{name}` expects \"x y width height\""));
+            let node = ctx.document.get_node(id).unwrap();
+            let actual = node.borrow().layout.bounds;
+            let matches = close(actual.x, expected.x)
+                && close(actual.y, expected.y)
+                && close(actual.width, expected.width)
+                && close(actual.height, expected.height);
+            (!matches).then(|| {
+                format!(
+                    "`{name}` is at {}, expected {}",
+                    format_rect(&actual),
+                    format_rect(&expected)
+                )
+            })
+        })
+        .collect()
+}
+
+fn parse_bounds(value: &str) -> Option<Rect> {
+    let numbers: Vec<f64> = value
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match numbers[..] {
+        [x, y, width, height] => Some(Rect::new(x, y, width, height)),
+        _ => None,
+    }
+}
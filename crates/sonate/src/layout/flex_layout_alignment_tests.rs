@@ -26,14 +26,14 @@ fn create_flex_container_with_alignment(
     width: Option<f64>,
     height: Option<f64>,
 ) -> Id {
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
 
     // Add a CSS rule for the flex container
     let class_name = format!("flex_container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction,
             justify_content,
             align_items,
@@ -42,6 +42,9 @@ fn create_flex_container_with_alignment(
             height: height.map(Length::Px),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -53,7 +56,8 @@ fn create_flex_container_with_alignment(
 fn create_flex_item(ctx: &mut LayoutContext, width: f64, height: f64) -> Id {
     let item_id = ctx
         .document
-        .create_node(next_test_id(), Some("item".to_string()));
+        .create_node(next_test_id(), Some("item".to_string()))
+        .unwrap();
 
     // Add a CSS rule for the flex item
     let class_name = format!("flex_item_{}", item_id.0);
@@ -64,6 +68,9 @@ fn create_flex_item(ctx: &mut LayoutContext, width: f64, height: f64) -> Id {
             height: Some(Length::Px(height)),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -391,9 +398,9 @@ fn test_align_content_flex_start() {
 
     // Add wrapping to the container
     let container_node = ctx.document.nodes.get(&container).unwrap();
-    let mut style = container_node.borrow().layout.style.as_ref().clone();
-    style.flex_wrap = Some(FlexWrap::Wrap);
-    container_node.borrow_mut().layout.style = Arc::new(style);
+    let mut style = container_node.borrow().inline_style.as_ref().clone();
+    style.declarations.flex_wrap = Some(FlexWrap::Wrap);
+    container_node.borrow_mut().inline_style = Arc::new(style);
 
     // Create items that will wrap to multiple lines
     let item1 = create_flex_item(&mut ctx, 100.0, 30.0);
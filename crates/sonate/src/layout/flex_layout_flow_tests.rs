@@ -21,20 +21,23 @@ fn create_flex_container(
     width: Option<f64>,
     height: Option<f64>,
 ) -> Id {
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
 
     // Add a CSS rule for the flex container
     let class_name = format!("flex_container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction,
             flex_wrap,
             width: width.map(Length::Px),
             height: height.map(Length::Px),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -52,7 +55,8 @@ fn create_flex_item(
 ) -> Id {
     let item_id = ctx
         .document
-        .create_node(next_test_id(), Some("item".to_string()));
+        .create_node(next_test_id(), Some("item".to_string()))
+        .unwrap();
 
     // Add a CSS rule for the flex item
     let class_name = format!("flex_item_{}", item_id.0);
@@ -65,6 +69,9 @@ fn create_flex_item(
             flex_shrink,
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
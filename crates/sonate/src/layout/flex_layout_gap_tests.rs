@@ -1,6 +1,8 @@
 use crate::style::{Display, FlexDirection, FlexWrap, JustifyContent, Length, Rule};
 
 use super::*;
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::test_tree::{item, lay_out, node};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
@@ -24,7 +26,7 @@ fn create_flex_container_with_gap(
     width: Option<f64>,
     height: Option<f64>,
 ) -> Id {
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
 
     // Add a CSS rule for the flex container
     let class_name = format!("flex_container_{}", container_id.0);
@@ -32,7 +34,7 @@ fn create_flex_container_with_gap(
 
     // Base declaration: individual row/column gaps.
     declarations.push(Style {
-        display: Display::Flex,
+        display: Some(Display::Flex),
         flex_direction,
         row_gap: row_gap.map(Length::Px),
         column_gap: column_gap.map(Length::Px),
@@ -55,6 +57,9 @@ fn create_flex_container_with_gap(
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations,
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -66,7 +71,8 @@ fn create_flex_container_with_gap(
 fn create_flex_item(ctx: &mut LayoutContext, width: f64, height: f64) -> Id {
     let item_id = ctx
         .document
-        .create_node(next_test_id(), Some("item".to_string()));
+        .create_node(next_test_id(), Some("item".to_string()))
+        .unwrap();
 
     // Add a CSS rule for the flex item
     let class_name = format!("flex_item_{}", item_id.0);
@@ -77,6 +83,9 @@ fn create_flex_item(ctx: &mut LayoutContext, width: f64, height: f64) -> Id {
             height: Some(Length::Px(height)),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -95,161 +104,59 @@ fn get_bounds(ctx: &LayoutContext, node_id: Id) -> (f64, f64, f64, f64) {
 
 #[test]
 fn test_column_gap_row_direction() {
-    let mut ctx = create_ctx();
-    let root = ctx.document.root_id();
-
-    // Create a flex container with column gap
-    let container = create_flex_container_with_gap(
-        &mut ctx,
-        Some(FlexDirection::Row),
-        None,
-        None,
-        Some(20.0), // 20px column gap
-        Some(300.0),
-        Some(100.0),
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-direction: row; column-gap: 20px")
+            .css("width: 300px; height: 100px")
+            .children([
+                item("item1", 50.0, 30.0),
+                item("item2", 60.0, 40.0),
+                item("item3", 70.0, 35.0),
+            ]),
     );
-    ctx.document.set_parent(root, container).unwrap();
-
-    // Create three flex items
-    let item1 = create_flex_item(&mut ctx, 50.0, 30.0);
-    let item2 = create_flex_item(&mut ctx, 60.0, 40.0);
-    let item3 = create_flex_item(&mut ctx, 70.0, 35.0);
-
-    ctx.document.set_parent(container, item1).unwrap();
-    ctx.document.set_parent(container, item2).unwrap();
-    ctx.document.set_parent(container, item3).unwrap();
-
-    // Run layout
-    ctx.layout();
-
-    // Verify positioning with column gaps
-    let (x1, y1, w1, h1) = get_bounds(&ctx, item1);
-    let (x2, y2, w2, h2) = get_bounds(&ctx, item2);
-    let (x3, y3, w3, h3) = get_bounds(&ctx, item3);
-
-    // Verify dimensions are preserved
-    assert_eq!(w1, 50.0);
-    assert_eq!(h1, 30.0);
-    assert_eq!(w2, 60.0);
-    assert_eq!(h2, 40.0);
-    assert_eq!(w3, 70.0);
-    assert_eq!(h3, 35.0);
-
-    // Verify horizontal positioning with 20px gaps
-    assert_eq!(x1, 0.0); // First item at container start
-    assert_eq!(x2, 70.0); // Second item after first + gap (0 + 50 + 20)
-    assert_eq!(x3, 150.0); // Third item after second + gap (70 + 60 + 20)
 
-    // All items should be on the same horizontal line
-    assert_eq!(y1, 0.0);
-    assert_eq!(y2, 0.0);
-    assert_eq!(y3, 0.0);
+    // Each item after the one before and a 20px gap, on the same horizontal line.
+    layout.assert_bounds("item1", 0.0, 0.0, 50.0, 30.0);
+    layout.assert_bounds("item2", 70.0, 0.0, 60.0, 40.0);
+    layout.assert_bounds("item3", 150.0, 0.0, 70.0, 35.0);
 }
 
 #[test]
 fn test_row_gap_column_direction() {
-    let mut ctx = create_ctx();
-    let root = ctx.document.root_id();
-
-    // Create a flex container with row gap
-    let container = create_flex_container_with_gap(
-        &mut ctx,
-        Some(FlexDirection::Column),
-        None,
-        Some(15.0), // 15px row gap
-        None,
-        Some(100.0),
-        Some(300.0),
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-direction: column; row-gap: 15px")
+            .css("width: 100px; height: 300px")
+            .children([
+                item("item1", 50.0, 30.0),
+                item("item2", 60.0, 40.0),
+                item("item3", 70.0, 35.0),
+            ]),
     );
-    ctx.document.set_parent(root, container).unwrap();
 
-    // Create three flex items
-    let item1 = create_flex_item(&mut ctx, 50.0, 30.0);
-    let item2 = create_flex_item(&mut ctx, 60.0, 40.0);
-    let item3 = create_flex_item(&mut ctx, 70.0, 35.0);
-
-    ctx.document.set_parent(container, item1).unwrap();
-    ctx.document.set_parent(container, item2).unwrap();
-    ctx.document.set_parent(container, item3).unwrap();
-
-    // Run layout
-    ctx.layout();
-
-    // Verify positioning with row gaps
-    let (x1, y1, w1, h1) = get_bounds(&ctx, item1);
-    let (x2, y2, w2, h2) = get_bounds(&ctx, item2);
-    let (x3, y3, w3, h3) = get_bounds(&ctx, item3);
-
-    // Verify dimensions are preserved
-    assert_eq!(w1, 50.0);
-    assert_eq!(h1, 30.0);
-    assert_eq!(w2, 60.0);
-    assert_eq!(h2, 40.0);
-    assert_eq!(w3, 70.0);
-    assert_eq!(h3, 35.0);
-
-    // Verify vertical positioning with 15px gaps
-    assert_eq!(y1, 0.0); // First item at container start
-    assert_eq!(y2, 45.0); // Second item after first + gap (0 + 30 + 15)
-    assert_eq!(y3, 100.0); // Third item after second + gap (45 + 40 + 15)
-
-    // All items should be aligned on the same vertical line
-    assert_eq!(x1, 0.0);
-    assert_eq!(x2, 0.0);
-    assert_eq!(x3, 0.0);
+    // Each item below the one before and a 15px gap, on the same vertical line.
+    layout.assert_bounds("item1", 0.0, 0.0, 50.0, 30.0);
+    layout.assert_bounds("item2", 0.0, 45.0, 60.0, 40.0);
+    layout.assert_bounds("item3", 0.0, 100.0, 70.0, 35.0);
 }
 
 #[test]
 fn test_gap_shorthand_row_direction() {
-    let mut ctx = create_ctx();
-    let root = ctx.document.root_id();
-
-    // Create a flex container with gap shorthand (applies to both row and column gaps)
-    let container = create_flex_container_with_gap(
-        &mut ctx,
-        Some(FlexDirection::Row),
-        Some(25.0), // 25px gap shorthand (both row and column)
-        None,
-        None,
-        Some(300.0),
-        Some(100.0),
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-direction: row; gap: 25px")
+            .css("width: 300px; height: 100px")
+            .children([
+                item("item1", 50.0, 30.0),
+                item("item2", 60.0, 40.0),
+                item("item3", 70.0, 35.0),
+            ]),
     );
-    ctx.document.set_parent(root, container).unwrap();
 
-    // Create three flex items
-    let item1 = create_flex_item(&mut ctx, 50.0, 30.0);
-    let item2 = create_flex_item(&mut ctx, 60.0, 40.0);
-    let item3 = create_flex_item(&mut ctx, 70.0, 35.0);
-
-    ctx.document.set_parent(container, item1).unwrap();
-    ctx.document.set_parent(container, item2).unwrap();
-    ctx.document.set_parent(container, item3).unwrap();
-
-    // Run layout
-    ctx.layout();
-
-    // Verify positioning with gaps from shorthand
-    let (x1, y1, w1, h1) = get_bounds(&ctx, item1);
-    let (x2, y2, w2, h2) = get_bounds(&ctx, item2);
-    let (x3, y3, w3, h3) = get_bounds(&ctx, item3);
-
-    // Verify dimensions are preserved
-    assert_eq!(w1, 50.0);
-    assert_eq!(h1, 30.0);
-    assert_eq!(w2, 60.0);
-    assert_eq!(h2, 40.0);
-    assert_eq!(w3, 70.0);
-    assert_eq!(h3, 35.0);
-
-    // Verify horizontal positioning with 25px gaps (gap shorthand acts as column-gap in row direction)
-    assert_eq!(x1, 0.0); // First item at container start
-    assert_eq!(x2, 75.0); // Second item after first + gap (0 + 50 + 25)
-    assert_eq!(x3, 160.0); // Third item after second + gap (75 + 60 + 25)
-
-    // All items should be on the same horizontal line
-    assert_eq!(y1, 0.0);
-    assert_eq!(y2, 0.0);
-    assert_eq!(y3, 0.0);
+    // The shorthand's column gap separates the items of a row.
+    layout.assert_bounds("item1", 0.0, 0.0, 50.0, 30.0);
+    layout.assert_bounds("item2", 75.0, 0.0, 60.0, 40.0);
+    layout.assert_bounds("item3", 160.0, 0.0, 70.0, 35.0);
 }
 
 #[test]
@@ -320,9 +227,9 @@ fn test_gap_with_wrapped_layout() {
 
     // Add wrapping to the container
     let container_node = ctx.document.nodes.get(&container).unwrap();
-    let mut style = container_node.borrow().layout.style.as_ref().clone();
-    style.flex_wrap = Some(FlexWrap::Wrap);
-    container_node.borrow_mut().layout.style = Arc::new(style);
+    let mut style = container_node.borrow().inline_style.as_ref().clone();
+    style.declarations.flex_wrap = Some(FlexWrap::Wrap);
+    container_node.borrow_mut().inline_style = Arc::new(style);
 
     // Create items that will wrap to multiple lines
     let item1 = create_flex_item(&mut ctx, 60.0, 30.0);
@@ -422,12 +329,12 @@ fn test_gap_with_justify_content_center() {
     let root = ctx.document.root_id();
 
     // Create a flex container with gap and justify-content: center
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
     let class_name = format!("flex_container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction: Some(FlexDirection::Row),
             justify_content: Some(JustifyContent::Center),
             column_gap: Some(Length::Px(20.0)),
@@ -435,6 +342,9 @@ fn test_gap_with_justify_content_center() {
             height: Some(Length::Px(100.0)),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
     ctx.document
         .set_attribute(container_id, "class".to_owned(), class_name);
@@ -478,12 +388,12 @@ fn test_gap_with_justify_content_space_between() {
     let root = ctx.document.root_id();
 
     // Create a flex container with gap and justify-content: space-between
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
     let class_name = format!("flex_container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction: Some(FlexDirection::Row),
             justify_content: Some(JustifyContent::SpaceBetween),
             column_gap: Some(Length::Px(10.0)),
@@ -491,6 +401,9 @@ fn test_gap_with_justify_content_space_between() {
             height: Some(Length::Px(100.0)),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
     ctx.document
         .set_attribute(container_id, "class".to_owned(), class_name);
@@ -534,3 +447,84 @@ fn test_gap_with_justify_content_space_between() {
     assert_eq!(y2, 0.0);
     assert_eq!(y3, 0.0);
 }
+
+// PERCENTAGE GAP TESTS
+
+const PERCENTAGE_GAPS: &str = r#"
+<div id="percent_gap" class="container" style="gap: 5%; height: 100px">
+  <div id="item1" class="item"></div>
+  <div id="item2" class="item"></div>
+  <div id="item3" class="item"></div>
+</div>
+<div id="percent_gap_wrap" class="container" style="flex-wrap: wrap; gap: 10%; height: 200px">
+  <div id="item1" class="item wide"></div>
+  <div id="item2" class="item wide"></div>
+  <div id="item3" class="item wide"></div>
+</div>
+<div id="oversized_gap" class="container" style="justify-content: center; column-gap: 200%; height: 100px">
+  <div id="item1" class="item"></div>
+  <div id="item2" class="item"></div>
+</div>
+<div id="negative_gap" class="container" style="column-gap: calc(10px - 10%); height: 100px">
+  <div id="item1" class="item"></div>
+  <div id="item2" class="item"></div>
+</div>
+<style>
+  .container { display: flex; width: 400px; }
+  .item { width: 50px; height: 30px; }
+  .wide { width: 120px; }
+</style>
+"#;
+
+fn percentage_gap_bounds(example_id: &str) -> Vec<(f64, f64, f64, f64)> {
+    let (ctx, ids) = load_html_test_example(PERCENTAGE_GAPS, example_id);
+    ["item1", "item2", "item3"]
+        .iter()
+        .filter_map(|item| ids.get(*item))
+        .map(|id| get_bounds(&ctx, *id))
+        .collect()
+}
+
+#[test]
+fn test_percentage_gap_resolves_against_container_width() {
+    // 5% of the 400px content width is a 20px gap.
+    assert_eq!(
+        percentage_gap_bounds("percent_gap"),
+        [
+            (0.0, 0.0, 50.0, 30.0),
+            (70.0, 0.0, 50.0, 30.0),
+            (140.0, 0.0, 50.0, 30.0),
+        ]
+    );
+}
+
+#[test]
+fn test_percentage_gap_affects_wrapping() {
+    // Three items and two 40px gaps need 440px, so the third wraps; the row gap is also
+    // 10% of the width, not of the height.
+    assert_eq!(
+        percentage_gap_bounds("percent_gap_wrap"),
+        [
+            (0.0, 0.0, 120.0, 30.0),
+            (160.0, 0.0, 120.0, 30.0),
+            (0.0, 70.0, 120.0, 30.0),
+        ]
+    );
+}
+
+#[test]
+fn test_oversized_percentage_gap_keeps_positions_finite() {
+    // The 800px gap overflows the container: there is no free space to center with.
+    assert_eq!(
+        percentage_gap_bounds("oversized_gap"),
+        [(0.0, 0.0, 50.0, 30.0), (850.0, 0.0, 50.0, 30.0)]
+    );
+}
+
+#[test]
+fn test_negative_percentage_gap_is_clamped_to_zero() {
+    assert_eq!(
+        percentage_gap_bounds("negative_gap"),
+        [(0.0, 0.0, 50.0, 30.0), (50.0, 0.0, 50.0, 30.0)]
+    );
+}
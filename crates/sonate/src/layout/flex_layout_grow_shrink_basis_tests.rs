@@ -1,6 +1,7 @@
 use crate::style::{Display, FlexDirection, Length, Rule};
 
 use super::*;
+use crate::layout::test_tree::{item, lay_out, node};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 fn next_test_id() -> Id {
@@ -20,19 +21,22 @@ fn create_flex_container(
     width: Option<f64>,
     height: Option<f64>,
 ) -> Id {
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
 
     // Add a CSS rule for the flex container
     let class_name = format!("flex_container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction,
             width: width.map(Length::Px),
             height: height.map(Length::Px),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -51,7 +55,8 @@ fn create_flex_item_with_flex(
 ) -> Id {
     let item_id = ctx
         .document
-        .create_node(next_test_id(), Some("item".to_string()));
+        .create_node(next_test_id(), Some("item".to_string()))
+        .unwrap();
 
     // Add a CSS rule for the flex item
     let class_name = format!("flex_item_{}", item_id.0);
@@ -65,6 +70,9 @@ fn create_flex_item_with_flex(
             flex_basis,
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -88,92 +96,37 @@ fn get_bounds(ctx: &LayoutContext, node_id: Id) -> (f64, f64, f64, f64) {
 
 #[test]
 fn test_flex_grow_basic() {
-    let mut ctx = create_ctx();
-    let root = ctx.document.root_id();
-
-    // Create a 300px wide container
-    let container =
-        create_flex_container(&mut ctx, Some(FlexDirection::Row), Some(300.0), Some(100.0));
-    ctx.document.set_parent(root, container).unwrap();
-
-    // Create items: one with flex-grow: 1, one without
-    let item1 = create_flex_item_with_flex(&mut ctx, Some(50.0), Some(30.0), Some(1.0), None, None);
-    let item2 = create_flex_item(&mut ctx, 50.0, 30.0);
-
-    ctx.document.set_parent(container, item1).unwrap();
-    ctx.document.set_parent(container, item2).unwrap();
-
-    // Run layout
-    ctx.layout();
-
-    // Verify positioning and sizing
-    let (x1, y1, w1, h1) = get_bounds(&ctx, item1);
-    let (x2, y2, w2, h2) = get_bounds(&ctx, item2);
-
-    // Item2 should keep its original width (50px)
-    assert_eq!(w2, 50.0);
-    assert_eq!(h2, 30.0);
-    assert_eq!(x2, 250.0); // After item1 which should have grown
-
-    // Item1 should grow to fill remaining space (300 - 100 = 200px available, 50px base + 200px growth)
-    assert_eq!(w1, 250.0);
-    assert_eq!(h1, 30.0);
-    assert_eq!(x1, 0.0);
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-direction: row; width: 300px; height: 100px")
+            .children([
+                item("item1", 50.0, 30.0).css("flex-grow: 1"),
+                item("item2", 50.0, 30.0),
+            ]),
+    );
 
-    // Both items should be on the same line
-    assert_eq!(y1, 0.0);
-    assert_eq!(y2, 0.0);
+    // Item1 grows into the 200px the items leave; item2 keeps its width.
+    layout.assert_bounds("item1", 0.0, 0.0, 250.0, 30.0);
+    layout.assert_bounds("item2", 250.0, 0.0, 50.0, 30.0);
 }
 
 #[test]
 fn test_flex_grow_multiple_items() {
-    let mut ctx = create_ctx();
-    let root = ctx.document.root_id();
-
-    // Create a 400px wide container
-    let container =
-        create_flex_container(&mut ctx, Some(FlexDirection::Row), Some(400.0), Some(100.0));
-    ctx.document.set_parent(root, container).unwrap();
-
-    // Create items with different flex-grow values
-    let item1 = create_flex_item_with_flex(&mut ctx, Some(50.0), Some(30.0), Some(1.0), None, None);
-    let item2 = create_flex_item_with_flex(&mut ctx, Some(50.0), Some(30.0), Some(2.0), None, None);
-    let item3 = create_flex_item(&mut ctx, 50.0, 30.0); // No flex-grow
-
-    ctx.document.set_parent(container, item1).unwrap();
-    ctx.document.set_parent(container, item2).unwrap();
-    ctx.document.set_parent(container, item3).unwrap();
-
-    // Run layout
-    ctx.layout();
-
-    // Verify positioning and sizing
-    let (x1, y1, w1, h1) = get_bounds(&ctx, item1);
-    let (x2, y2, w2, h2) = get_bounds(&ctx, item2);
-    let (x3, y3, w3, h3) = get_bounds(&ctx, item3);
-
-    // Item3 should keep its original width (50px)
-    assert_eq!(w3, 50.0);
-    assert_eq!(h3, 30.0);
-    assert_eq!(h1, 30.0);
-    assert_eq!(h2, 30.0);
-
-    // Available space: 400 - 150 = 250px
-    // Flex-grow ratio: 1:2 (total = 3)
-    // Item1 gets 250 * (1/3) = ~83.33px extra -> 50 + 83.33 = ~133.33px
-    // Item2 gets 250 * (2/3) = ~166.67px extra -> 50 + 166.67 = ~216.67px
-    assert!((w1 - 133.33).abs() < 0.1);
-    assert!((w2 - 216.67).abs() < 0.1);
-
-    // Verify positioning
-    assert_eq!(x1, 0.0);
-    assert!((x2 - w1).abs() < 0.1);
-    assert!((x3 - w1 - w2).abs() < 0.1);
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-direction: row; width: 400px; height: 100px")
+            .children([
+                item("item1", 50.0, 30.0).css("flex-grow: 1"),
+                item("item2", 50.0, 30.0).css("flex-grow: 2"),
+                item("item3", 50.0, 30.0),
+            ]),
+    );
 
-    // All items should be on the same line
-    assert_eq!(y1, 0.0);
-    assert_eq!(y2, 0.0);
-    assert_eq!(y3, 0.0);
+    // The 250px left is shared 1:2 between item1 and item2.
+    let grown = 250.0 / 3.0;
+    layout.assert_bounds("item1", 0.0, 0.0, 50.0 + grown, 30.0);
+    layout.assert_bounds("item2", 50.0 + grown, 0.0, 50.0 + 2.0 * grown, 30.0);
+    layout.assert_bounds("item3", 350.0, 0.0, 50.0, 30.0);
 }
 
 #[test]
@@ -1,3 +1,4 @@
+use crate::layout::test_tree::{item, lay_out, node};
 use crate::layout::{asserts::LayoutContextAsserts, test_html::load_html_test_example};
 
 use super::*;
@@ -95,3 +96,86 @@ fn case_container_border_box() {
     ctx.assert_node_bounds_eq(child2, &Rect::new(170.0, 20.0, 60.0, 40.0));
     ctx.assert_node_bounds_eq(child3, &Rect::new(320.0, 20.0, 60.0, 40.0));
 }
+
+#[test]
+fn case_percentage_margin() {
+    let (ctx, container, child1, child2, child3) = setup_margin_demo_ctx("case_percent");
+
+    // 10% of the container's 400px content width on either side of child2.
+    ctx.assert_node_bounds_eq(container, &Rect::new(0.0, 0.0, 400.0, 200.0));
+    ctx.assert_node_bounds_eq(child1, &Rect::new(0.0, 0.0, 60.0, 40.0));
+    ctx.assert_node_bounds_eq(child2, &Rect::new(100.0, 0.0, 60.0, 40.0));
+    ctx.assert_node_bounds_eq(child3, &Rect::new(200.0, 0.0, 60.0, 40.0));
+}
+
+#[test]
+fn case_percentage_margin_in_column() {
+    let (ctx, container, child1, child2, child3) = setup_margin_demo_ctx("case_percent_column");
+
+    // Still a percentage of the width, now on the cross axis.
+    ctx.assert_node_bounds_eq(container, &Rect::new(0.0, 0.0, 400.0, 200.0));
+    ctx.assert_node_bounds_eq(child1, &Rect::new(0.0, 0.0, 60.0, 40.0));
+    ctx.assert_node_bounds_eq(child2, &Rect::new(40.0, 40.0, 60.0, 40.0));
+    ctx.assert_node_bounds_eq(child3, &Rect::new(0.0, 80.0, 60.0, 40.0));
+}
+
+#[test]
+fn case_percentage_padding() {
+    let (ctx, container, child1, child2, child3) = setup_margin_demo_ctx("case_percent_padding");
+
+    // 20px on every side of child1, the vertical ones included.
+    ctx.assert_node_bounds_eq(container, &Rect::new(0.0, 0.0, 400.0, 200.0));
+    ctx.assert_node_bounds_eq(child1, &Rect::new(0.0, 0.0, 100.0, 80.0));
+    ctx.assert_node_bounds_eq(child2, &Rect::new(100.0, 0.0, 60.0, 40.0));
+    ctx.assert_node_bounds_eq(child3, &Rect::new(160.0, 0.0, 60.0, 40.0));
+}
+
+#[test]
+fn negative_margin_pulls_items_together() {
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; width: 300px; height: 200px; align-items: flex-start")
+            .child(node("row").css("display: flex").children([
+                item("child1", 100.0, 100.0),
+                item("child2", 100.0, 100.0).css("margin-left: -20px"),
+            ])),
+    );
+
+    // child2 overlaps the last 20px of child1, and the row sizes to what they span.
+    layout.assert_bounds("row", 0.0, 0.0, 180.0, 100.0);
+    layout.assert_bounds("child1", 0.0, 0.0, 100.0, 100.0);
+    layout.assert_bounds("child2", 80.0, 0.0, 100.0, 100.0);
+}
+
+#[test]
+fn negative_margin_keeps_item_on_the_line() {
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; flex-wrap: wrap; width: 250px; height: 200px")
+            .children([
+                item("child1", 100.0, 40.0),
+                item("child2", 100.0, 40.0),
+                item("child3", 70.0, 40.0).css("margin-left: -20px"),
+            ]),
+    );
+
+    // Its outer size is 50px, which fits in what's left of the first line.
+    layout.assert_bounds("child3", 180.0, 0.0, 70.0, 40.0);
+}
+
+#[test]
+fn negative_margin_larger_than_item() {
+    let layout = lay_out(
+        node("container")
+            .css("display: flex; width: 300px; height: 200px; align-items: flex-start")
+            .child(
+                node("row")
+                    .css("display: flex")
+                    .child(item("child1", 100.0, 100.0).css("margin-left: -150px")),
+            ),
+    );
+
+    // The item sticks out of the row, which is never narrower than nothing.
+    layout.assert_bounds("row", 0.0, 0.0, 0.0, 100.0);
+    layout.assert_bounds("child1", -150.0, 0.0, 100.0, 100.0);
+}
@@ -27,3 +27,32 @@ fn test_nested_flex_layout() {
     ctx.assert_node_bounds_eq(nested4, &Rect::new(0.0, 160.0, 60.0, 40.0));
     ctx.assert_node_bounds_eq(child2, &Rect::new(60.0, 0.0, 340.0, 200.0));
 }
+
+#[test]
+fn unsized_rows_in_a_column_size_to_their_children() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "unsized");
+
+    // 16px icon and 48px of text, an 8px gap and 4px of padding on each side.
+    ctx.assert_node_bounds_eq(nodes_by_id["toolbar"], &Rect::new(0.0, 0.0, 300.0, 24.0));
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["toolbar_icon"],
+        &Rect::new(4.0, 4.0, 16.0, 16.0),
+    );
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["toolbar_label"],
+        &Rect::new(28.0, 4.0, 48.0, 16.0),
+    );
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["compact_toolbar"],
+        &Rect::new(0.0, 24.0, 80.0, 24.0),
+    );
+}
+
+#[test]
+fn items_dont_shrink_below_their_longest_word() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "squeezed");
+
+    // Both would shrink to 25px; the first stops at "three", the clipped one doesn't.
+    ctx.assert_node_bounds_eq(nodes_by_id["squeezed_1"], &Rect::new(0.0, 0.0, 30.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["squeezed_2"], &Rect::new(30.0, 0.0, 25.0, 40.0));
+}
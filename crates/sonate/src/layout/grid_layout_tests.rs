@@ -0,0 +1,69 @@
+use crate::layout::{asserts::LayoutContextAsserts, test_html::load_html_test_example};
+
+use super::*;
+
+const HTML: &str = include_str!("./grid_layout_tests.html");
+
+#[test]
+fn fr_columns_share_the_width_and_items_wrap_to_the_next_row() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "fr_columns");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["fr_1"], &Rect::new(0.0, 0.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["fr_2"], &Rect::new(100.0, 0.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["fr_3"], &Rect::new(200.0, 0.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["fr_4"], &Rect::new(0.0, 40.0, 100.0, 40.0));
+}
+
+#[test]
+fn fixed_tracks_and_gaps_come_out_of_the_fr_share() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "mixed_tracks");
+
+    // 400px less 50px, 25% and two 10px gaps leaves 230px for the `1fr` column.
+    ctx.assert_node_bounds_eq(nodes_by_id["mixed_1"], &Rect::new(0.0, 0.0, 50.0, 30.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["mixed_2"], &Rect::new(60.0, 0.0, 230.0, 30.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["mixed_3"], &Rect::new(300.0, 0.0, 100.0, 30.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["mixed_4"], &Rect::new(0.0, 50.0, 50.0, 30.0));
+}
+
+#[test]
+fn spanning_items_start_a_new_row_when_they_dont_fit() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "spans");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["span_1"], &Rect::new(0.0, 0.0, 200.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["span_2"], &Rect::new(200.0, 0.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["span_3"], &Rect::new(0.0, 40.0, 300.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["span_4"], &Rect::new(0.0, 80.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["span_5"], &Rect::new(100.0, 80.0, 100.0, 40.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["span_6"], &Rect::new(0.0, 120.0, 200.0, 40.0));
+}
+
+#[test]
+fn items_without_sizes_fill_fixed_and_fr_rows() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "fr_rows");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["rows_1"], &Rect::new(0.0, 0.0, 95.0, 50.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["rows_2"], &Rect::new(105.0, 0.0, 95.0, 50.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["rows_3"], &Rect::new(0.0, 60.0, 95.0, 140.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["rows_4"], &Rect::new(105.0, 60.0, 95.0, 140.0));
+}
+
+#[test]
+fn grids_nest_in_flex_containers_and_flex_containers_in_grids() {
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "nested");
+
+    ctx.assert_node_bounds_eq(nodes_by_id["sidebar"], &Rect::new(0.0, 0.0, 100.0, 100.0));
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["grid_in_flex"],
+        &Rect::new(100.0, 0.0, 300.0, 100.0),
+    );
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["flex_in_grid"],
+        &Rect::new(100.0, 0.0, 150.0, 40.0),
+    );
+    ctx.assert_node_bounds_eq(nodes_by_id["dot_1"], &Rect::new(100.0, 0.0, 20.0, 10.0));
+    ctx.assert_node_bounds_eq(nodes_by_id["dot_2"], &Rect::new(120.0, 0.0, 20.0, 10.0));
+    ctx.assert_node_bounds_eq(
+        nodes_by_id["grid_cell"],
+        &Rect::new(250.0, 0.0, 150.0, 40.0),
+    );
+}
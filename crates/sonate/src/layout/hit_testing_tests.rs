@@ -1,4 +1,6 @@
+use super::asserts::hit;
 use super::*;
+use crate::style::{PointerEvents, ZIndex};
 use std::sync::atomic::{AtomicU64, Ordering};
 
 fn next_test_id() -> Id {
@@ -47,12 +49,12 @@ fn test_find_element_at_position_single_element() {
     // Test point inside root
     let tree = build_render_tree(ctx.document.root_node());
 
-    let result = tree.find_element_at_position(50.0, 50.0);
+    let result = hit(&tree, 50.0, 50.0);
     assert_eq!(result.len(), 1);
     assert_eq!(result[0], root_id);
 
     // Test point outside root
-    let result = tree.find_element_at_position(250.0, 50.0);
+    let result = hit(&tree, 250.0, 50.0);
     assert_eq!(result.len(), 0);
 }
 
@@ -64,13 +66,16 @@ fn test_find_element_at_position_nested_elements() {
     // Create child elements
     let child1_id = ctx
         .document
-        .create_node(next_test_id(), Some("child1".to_string()));
+        .create_node(next_test_id(), Some("child1".to_string()))
+        .unwrap();
     let child2_id = ctx
         .document
-        .create_node(next_test_id(), Some("child2".to_string()));
+        .create_node(next_test_id(), Some("child2".to_string()))
+        .unwrap();
     let grandchild_id = ctx
         .document
-        .create_node(next_test_id(), Some("grandchild".to_string()));
+        .create_node(next_test_id(), Some("grandchild".to_string()))
+        .unwrap();
 
     // Set up parent-child relationships
     ctx.document.set_parent(root_id, child1_id).unwrap();
@@ -125,31 +130,31 @@ fn test_find_element_at_position_nested_elements() {
     let tree = build_render_tree(ctx.document.root_node());
 
     // Test clicking on grandchild - should return [grandchild, child1, root]
-    let result = tree.find_element_at_position(40.0, 40.0);
+    let result = hit(&tree, 40.0, 40.0);
     assert_eq!(result.len(), 3);
     assert_eq!(result[0], grandchild_id);
     assert_eq!(result[1], child1_id);
     assert_eq!(result[2], root_id);
 
     // Test clicking on child1 but outside grandchild - should return [child1, root]
-    let result = tree.find_element_at_position(80.0, 80.0);
+    let result = hit(&tree, 80.0, 80.0);
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], child1_id);
     assert_eq!(result[1], root_id);
 
     // Test clicking on child2 - should return [child2, root]
-    let result = tree.find_element_at_position(150.0, 40.0);
+    let result = hit(&tree, 150.0, 40.0);
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], child2_id);
     assert_eq!(result[1], root_id);
 
     // Test clicking on root but outside children - should return [root]
-    let result = tree.find_element_at_position(5.0, 5.0);
+    let result = hit(&tree, 5.0, 5.0);
     assert_eq!(result.len(), 1);
     assert_eq!(result[0], root_id);
 
     // Test clicking outside all elements
-    let result = tree.find_element_at_position(250.0, 250.0);
+    let result = hit(&tree, 250.0, 250.0);
     assert_eq!(result.len(), 0);
 }
 
@@ -161,10 +166,12 @@ fn test_find_element_at_position_overlapping_siblings() {
     // Create two overlapping child elements
     let child1_id = ctx
         .document
-        .create_node(next_test_id(), Some("child1".to_string()));
+        .create_node(next_test_id(), Some("child1".to_string()))
+        .unwrap();
     let child2_id = ctx
         .document
-        .create_node(next_test_id(), Some("child2".to_string()));
+        .create_node(next_test_id(), Some("child2".to_string()))
+        .unwrap();
 
     // Set up parent-child relationships
     ctx.document.set_parent(root_id, child1_id).unwrap();
@@ -207,20 +214,437 @@ fn test_find_element_at_position_overlapping_siblings() {
     let tree = build_render_tree(ctx.document.root_node());
 
     // Test clicking in overlapping area - should hit child2 (last child, rendered on top)
-    let result = tree.find_element_at_position(80.0, 80.0);
+    let result = hit(&tree, 80.0, 80.0);
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], child2_id);
     assert_eq!(result[1], root_id);
 
     // Test clicking in child1 only area
-    let result = tree.find_element_at_position(30.0, 30.0);
+    let result = hit(&tree, 30.0, 30.0);
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], child1_id);
     assert_eq!(result[1], root_id);
 
     // Test clicking in child2 only area
-    let result = tree.find_element_at_position(140.0, 140.0);
+    let result = hit(&tree, 140.0, 140.0);
     assert_eq!(result.len(), 2);
     assert_eq!(result[0], child2_id);
     assert_eq!(result[1], root_id);
 }
+
+#[test]
+fn test_find_element_at_position_skips_hidden_elements() {
+    let mut ctx = LayoutContext::new();
+    let root_id = ctx.document.root_id();
+
+    let hidden_id = ctx
+        .document
+        .create_node(next_test_id(), Some("hidden".to_string()))
+        .unwrap();
+    let transparent_id = ctx
+        .document
+        .create_node(next_test_id(), Some("transparent".to_string()))
+        .unwrap();
+
+    ctx.document.set_parent(root_id, hidden_id).unwrap();
+    ctx.document.set_parent(root_id, transparent_id).unwrap();
+
+    {
+        let root = ctx.document.root_node();
+        root.borrow_mut().layout.bounds = Rect::new(0.0, 0.0, 200.0, 200.0);
+    }
+
+    {
+        let hidden = ctx.document.nodes.get(&hidden_id).unwrap();
+        let mut hidden_borrow = hidden.borrow_mut();
+        hidden_borrow.layout.bounds = Rect::new(10.0, 10.0, 50.0, 50.0);
+        hidden_borrow.layout.style = Arc::new(Style {
+            visibility: Some(crate::style::Visibility::Hidden),
+            ..Default::default()
+        });
+    }
+
+    {
+        let transparent = ctx.document.nodes.get(&transparent_id).unwrap();
+        let mut transparent_borrow = transparent.borrow_mut();
+        transparent_borrow.layout.bounds = Rect::new(100.0, 10.0, 50.0, 50.0);
+        transparent_borrow.layout.style = Arc::new(Style {
+            opacity: Some(0.0),
+            ..Default::default()
+        });
+    }
+
+    let tree = build_render_tree(ctx.document.root_node());
+
+    // The hidden element is skipped; the click falls through to the root.
+    let result = hit(&tree, 20.0, 20.0);
+    assert_eq!(result, vec![root_id]);
+
+    // Fully transparent elements are still hit.
+    let result = hit(&tree, 120.0, 20.0);
+    assert_eq!(result, vec![transparent_id, root_id]);
+}
+
+fn overlapping_boxes(ctx: &mut LayoutContext, z_indices: [Option<i32>; 2]) -> [Id; 2] {
+    let root_id = ctx.document.root_id();
+    {
+        let root = ctx.document.root_node();
+        root.borrow_mut().layout.bounds = Rect::new(0.0, 0.0, 200.0, 200.0);
+    }
+
+    let bounds = [
+        Rect::new(10.0, 10.0, 100.0, 100.0),
+        Rect::new(50.0, 50.0, 100.0, 100.0),
+    ];
+    let ids = [next_test_id(), next_test_id()];
+    for i in 0..2 {
+        ctx.document.create_node(ids[i], None).unwrap();
+        ctx.document.set_parent(root_id, ids[i]).unwrap();
+
+        let node = ctx.document.nodes.get(&ids[i]).unwrap();
+        let mut node_borrow = node.borrow_mut();
+        node_borrow.layout.bounds = bounds[i];
+        node_borrow.layout.style = Arc::new(Style {
+            z_index: z_indices[i].map(ZIndex::Integer),
+            ..Default::default()
+        });
+    }
+    ids
+}
+
+#[test]
+fn test_find_element_at_position_respects_z_index() {
+    let mut ctx = LayoutContext::new();
+    let root_id = ctx.document.root_id();
+    let [first, _second] = overlapping_boxes(&mut ctx, [Some(2), Some(1)]);
+
+    let tree = build_render_tree(ctx.document.root_node());
+
+    // The first box comes earlier in the document but is stacked on top.
+    let result = hit(&tree, 75.0, 75.0);
+    assert_eq!(result, vec![first, root_id]);
+}
+
+#[test]
+fn test_find_element_at_position_equal_z_index_uses_document_order() {
+    for z_indices in [[None, None], [Some(3), Some(3)]] {
+        let mut ctx = LayoutContext::new();
+        let root_id = ctx.document.root_id();
+        let [_first, second] = overlapping_boxes(&mut ctx, z_indices);
+
+        let tree = build_render_tree(ctx.document.root_node());
+
+        let result = hit(&tree, 75.0, 75.0);
+        assert_eq!(result, vec![second, root_id]);
+    }
+}
+
+#[test]
+fn test_negative_z_index_is_behind_parent() {
+    let mut ctx = LayoutContext::new();
+    let root_id = ctx.document.root_id();
+    let [first, second] = overlapping_boxes(&mut ctx, [Some(-1), None]);
+
+    let tree = build_render_tree(ctx.document.root_node());
+
+    let (behind, in_front) = tree.children_in_paint_order();
+    assert_eq!(behind.iter().map(|c| c.id).collect::<Vec<_>>(), [first]);
+    assert_eq!(in_front.iter().map(|c| c.id).collect::<Vec<_>>(), [second]);
+
+    // The parent covers its negative z-index child.
+    let result = hit(&tree, 20.0, 20.0);
+    assert_eq!(result, vec![root_id]);
+    let result = hit(&tree, 75.0, 75.0);
+    assert_eq!(result, vec![second, root_id]);
+}
+
+/// Root (0, 0, 300, 300) > parent (10, 10, 100, 100) > child sticking out to (150, 150, 50, 50).
+fn overflowing_child(parent_style: Style) -> (LayoutContext, Id, Id) {
+    let mut ctx = LayoutContext::new();
+    let root_id = ctx.document.root_id();
+    let parent_id = ctx.document.create_node(next_test_id(), None).unwrap();
+    let child_id = ctx.document.create_node(next_test_id(), None).unwrap();
+    ctx.document.set_parent(root_id, parent_id).unwrap();
+    ctx.document.set_parent(parent_id, child_id).unwrap();
+
+    ctx.document.root_node().borrow_mut().layout.bounds = Rect::new(0.0, 0.0, 300.0, 300.0);
+    {
+        let parent = ctx.document.nodes.get(&parent_id).unwrap();
+        let mut parent_borrow = parent.borrow_mut();
+        parent_borrow.layout.bounds = Rect::new(10.0, 10.0, 100.0, 100.0);
+        parent_borrow.layout.style = Arc::new(parent_style);
+    }
+    {
+        let child = ctx.document.nodes.get(&child_id).unwrap();
+        child.borrow_mut().layout.bounds = Rect::new(150.0, 150.0, 50.0, 50.0);
+    }
+
+    (ctx, parent_id, child_id)
+}
+
+#[test]
+fn test_overflowing_child_is_hit_outside_parent() {
+    let (ctx, parent_id, child_id) = overflowing_child(Style::default());
+    let root_id = ctx.document.root_id();
+    let tree = build_render_tree(ctx.document.root_node());
+
+    assert_eq!(
+        tree.children[0].descendant_bounds,
+        Rect::new(10.0, 10.0, 190.0, 190.0)
+    );
+
+    let result = hit(&tree, 175.0, 175.0);
+    assert_eq!(result, vec![child_id, parent_id, root_id]);
+
+    // Between the parent and the child only the root is hit.
+    let result = hit(&tree, 130.0, 130.0);
+    assert_eq!(result, vec![root_id]);
+}
+
+#[test]
+fn test_overflowing_child_is_not_hit_when_parent_clips() {
+    let (ctx, _parent_id, _child_id) = overflowing_child(Style {
+        overflow: Some(crate::style::Overflow::Hidden),
+        ..Default::default()
+    });
+    let root_id = ctx.document.root_id();
+    let tree = build_render_tree(ctx.document.root_node());
+
+    assert_eq!(
+        tree.children[0].descendant_bounds,
+        Rect::new(10.0, 10.0, 100.0, 100.0)
+    );
+
+    let result = hit(&tree, 175.0, 175.0);
+    assert_eq!(result, vec![root_id]);
+}
+
+#[test]
+fn test_negative_z_index_child_is_hit_where_it_overflows() {
+    let (ctx, parent_id, child_id) = overflowing_child(Style::default());
+    {
+        let child = ctx.document.nodes.get(&child_id).unwrap();
+        let mut child_borrow = child.borrow_mut();
+        child_borrow.layout.bounds = Rect::new(80.0, 80.0, 50.0, 50.0);
+        child_borrow.layout.style = Arc::new(Style {
+            z_index: Some(ZIndex::Integer(-1)),
+            ..Default::default()
+        });
+    }
+    let root_id = ctx.document.root_id();
+    let tree = build_render_tree(ctx.document.root_node());
+
+    // Covered by the parent inside its bounds, exposed outside of them.
+    let result = hit(&tree, 90.0, 90.0);
+    assert_eq!(result, vec![parent_id, root_id]);
+    let result = hit(&tree, 120.0, 120.0);
+    assert_eq!(result, vec![child_id, parent_id, root_id]);
+}
+
+fn node(id: u64, bounds: Rect, style: Style, children: Vec<RenderNode>) -> RenderNode {
+    let children: Vec<_> = children.into_iter().map(Arc::new).collect();
+    RenderNode {
+        id: Id::from_u64(id),
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::resolved(&style.padding),
+        border_width: Edges::from(&style.used_border_width()),
+        descendant_bounds: descendant_bounds(bounds, &style, &children),
+        z_index: style.resolved_z_index(),
+        style: Arc::new(style),
+        text: None,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children,
+    }
+}
+
+fn rounded(radius: f64) -> Style {
+    let corner = Some(crate::style::Radius {
+        x: Length::Px(radius),
+        y: Length::Px(radius),
+    });
+    Style {
+        border_radius: crate::style::BorderRadius {
+            top_left: corner.clone(),
+            top_right: corner.clone(),
+            bottom_right: corner.clone(),
+            bottom_left: corner,
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn rounded_off_corners_are_not_hit() {
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 200.0, 200.0),
+        Style::default(),
+        vec![node(
+            2,
+            Rect::new(10.0, 10.0, 100.0, 60.0),
+            rounded(20.0),
+            vec![],
+        )],
+    );
+    let (root, card) = (Id::from_u64(1), Id::from_u64(2));
+
+    // Outside the curve in each corner, the root below is hit.
+    for (x, y) in [(12.0, 12.0), (108.0, 12.0), (108.0, 68.0), (12.0, 68.0)] {
+        assert_eq!(hit(&tree, x, y), vec![root], "at ({x}, {y})");
+    }
+    // Inside the curve, and along the straight edges between the corners.
+    for (x, y) in [(18.0, 18.0), (102.0, 62.0), (60.0, 10.0), (10.0, 40.0)] {
+        assert_eq!(hit(&tree, x, y), vec![card, root], "at ({x}, {y})");
+    }
+}
+
+#[test]
+fn radii_larger_than_the_box_are_scaled_down() {
+    // A 100px radius on a 40px square rounds it into a circle of radius 20.
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 100.0, 100.0),
+        Style::default(),
+        vec![node(
+            2,
+            Rect::new(0.0, 0.0, 40.0, 40.0),
+            rounded(100.0),
+            vec![],
+        )],
+    );
+    let circle = &tree.children[0];
+    assert!(circle.contains_point(20.0, 1.0));
+    assert!(circle.contains_point(34.0, 34.0));
+    assert!(!circle.contains_point(35.0, 35.0));
+    assert!(!circle.contains_point(3.0, 3.0));
+}
+
+#[test]
+fn rounded_corners_show_children_behind_them() {
+    let behind = Style {
+        z_index: Some(ZIndex::Integer(-1)),
+        ..Default::default()
+    };
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 100.0, 100.0),
+        rounded(30.0),
+        vec![node(2, Rect::new(0.0, 0.0, 20.0, 20.0), behind, vec![])],
+    );
+
+    assert_eq!(hit(&tree, 2.0, 2.0), vec![Id::from_u64(2), Id::from_u64(1)]);
+    assert_eq!(hit(&tree, 18.0, 18.0), vec![Id::from_u64(1)]);
+}
+
+#[test]
+fn pointer_events_none_subtrees_are_skipped() {
+    let ignored = Style {
+        pointer_events: Some(PointerEvents::None),
+        ..Default::default()
+    };
+    let opted_back_in = Style {
+        pointer_events: Some(PointerEvents::Auto),
+        ..Default::default()
+    };
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 200.0, 200.0),
+        Style::default(),
+        vec![
+            node(
+                2,
+                Rect::new(10.0, 10.0, 100.0, 100.0),
+                Style::default(),
+                vec![],
+            ),
+            // On top of node 2, but the pointer goes through it and its child.
+            node(
+                3,
+                Rect::new(50.0, 50.0, 100.0, 100.0),
+                ignored,
+                vec![node(
+                    4,
+                    Rect::new(60.0, 60.0, 20.0, 20.0),
+                    opted_back_in,
+                    vec![],
+                )],
+            ),
+        ],
+    );
+    let root = Id::from_u64(1);
+
+    assert_eq!(hit(&tree, 70.0, 70.0), vec![Id::from_u64(2), root]);
+    assert_eq!(hit(&tree, 130.0, 130.0), vec![root]);
+}
+
+#[test]
+fn the_topmost_painted_sibling_wins() {
+    let z = |z_index| Style {
+        z_index: Some(ZIndex::Integer(z_index)),
+        ..Default::default()
+    };
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 200.0, 200.0),
+        Style::default(),
+        vec![
+            node(2, Rect::new(0.0, 0.0, 100.0, 100.0), z(2), vec![]),
+            node(3, Rect::new(0.0, 0.0, 100.0, 100.0), z(1), vec![]),
+            node(4, Rect::new(0.0, 0.0, 100.0, 100.0), z(1), vec![]),
+        ],
+    );
+
+    // z-index first, then document order, as `children_in_paint_order` paints them.
+    assert_eq!(
+        hit(&tree, 50.0, 50.0),
+        vec![Id::from_u64(2), Id::from_u64(1)]
+    );
+    let (_, in_front) = tree.children_in_paint_order();
+    assert_eq!(in_front.last().unwrap().id, Id::from_u64(2));
+
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 200.0, 200.0),
+        Style::default(),
+        vec![
+            node(3, Rect::new(0.0, 0.0, 100.0, 100.0), z(1), vec![]),
+            node(4, Rect::new(0.0, 0.0, 100.0, 100.0), z(1), vec![]),
+        ],
+    );
+    assert_eq!(
+        hit(&tree, 50.0, 50.0),
+        vec![Id::from_u64(4), Id::from_u64(1)]
+    );
+}
+
+#[test]
+fn right_and_bottom_edges_belong_to_the_element() {
+    let tree = node(
+        1,
+        Rect::new(0.0, 0.0, 200.0, 200.0),
+        Style::default(),
+        vec![
+            node(2, Rect::new(0.0, 0.0, 50.0, 50.0), Style::default(), vec![]),
+            node(
+                3,
+                Rect::new(50.0, 0.0, 50.0, 50.0),
+                Style::default(),
+                vec![],
+            ),
+        ],
+    );
+    let root = Id::from_u64(1);
+
+    assert_eq!(hit(&tree, 0.0, 0.0), vec![Id::from_u64(2), root]);
+    assert_eq!(hit(&tree, 25.0, 50.0), vec![Id::from_u64(2), root]);
+    assert_eq!(hit(&tree, 100.0, 50.0), vec![Id::from_u64(3), root]);
+    // The shared edge goes to the sibling painted on top, the later one.
+    assert_eq!(hit(&tree, 50.0, 25.0), vec![Id::from_u64(3), root]);
+    assert_eq!(hit(&tree, 100.5, 25.0), vec![root]);
+}
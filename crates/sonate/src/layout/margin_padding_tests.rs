@@ -21,37 +21,40 @@ fn create_container(
     margin: Option<Directional<Length>>,
     padding: Option<Directional<Length>>,
 ) -> Id {
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
 
     // Add a CSS rule for the container
     let class_name = format!("container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction: Some(FlexDirection::Row),
             width: width.map(Length::Px),
             height: height.map(Length::Px),
             margin: margin
                 .as_ref()
                 .map(|m| Directional {
-                    top: Some(m.top),
-                    right: Some(m.right),
-                    bottom: Some(m.bottom),
-                    left: Some(m.left),
+                    top: Some(m.top.clone()),
+                    right: Some(m.right.clone()),
+                    bottom: Some(m.bottom.clone()),
+                    left: Some(m.left.clone()),
                 })
                 .unwrap_or_default(),
             padding: padding
                 .as_ref()
                 .map(|p| Directional {
-                    top: Some(p.top),
-                    right: Some(p.right),
-                    bottom: Some(p.bottom),
-                    left: Some(p.left),
+                    top: Some(p.top.clone()),
+                    right: Some(p.right.clone()),
+                    bottom: Some(p.bottom.clone()),
+                    left: Some(p.left.clone()),
                 })
                 .unwrap_or_default(),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -69,7 +72,8 @@ fn create_item_with_spacing(
 ) -> Id {
     let item_id = ctx
         .document
-        .create_node(next_test_id(), Some("item".to_string()));
+        .create_node(next_test_id(), Some("item".to_string()))
+        .unwrap();
 
     // Add a CSS rule for the item
     let class_name = format!("item_{}", item_id.0);
@@ -81,23 +85,26 @@ fn create_item_with_spacing(
             margin: margin
                 .as_ref()
                 .map(|m| Directional {
-                    top: Some(m.top),
-                    right: Some(m.right),
-                    bottom: Some(m.bottom),
-                    left: Some(m.left),
+                    top: Some(m.top.clone()),
+                    right: Some(m.right.clone()),
+                    bottom: Some(m.bottom.clone()),
+                    left: Some(m.left.clone()),
                 })
                 .unwrap_or_default(),
             padding: padding
                 .as_ref()
                 .map(|p| Directional {
-                    top: Some(p.top),
-                    right: Some(p.right),
-                    bottom: Some(p.bottom),
-                    left: Some(p.left),
+                    top: Some(p.top.clone()),
+                    right: Some(p.right.clone()),
+                    bottom: Some(p.bottom.clone()),
+                    left: Some(p.left.clone()),
                 })
                 .unwrap_or_default(),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
 
     ctx.document
@@ -258,17 +265,20 @@ fn test_margin_column_direction() {
     let root = ctx.document.root_id();
 
     // Create a container with column direction
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
     let class_name = format!("container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction: Some(FlexDirection::Column),
             width: Some(Length::Px(200.0)),
             height: Some(Length::Px(400.0)),
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
     ctx.document
         .set_attribute(container_id, "class".to_owned(), class_name);
@@ -434,12 +444,12 @@ fn test_padding_column_direction() {
     let root = ctx.document.root_id();
 
     // Create a container with column direction and padding
-    let container_id = ctx.document.create_node(next_test_id(), None);
+    let container_id = ctx.document.create_node(next_test_id(), None).unwrap();
     let class_name = format!("container_{}", container_id.0);
     ctx.style_sheet.add_rule(Rule {
         selector: Selector::Class(class_name.clone()),
         declarations: vec![Style {
-            display: Display::Flex,
+            display: Some(Display::Flex),
             flex_direction: Some(FlexDirection::Column),
             width: Some(Length::Px(200.0)),
             height: Some(Length::Px(400.0)),
@@ -451,6 +461,9 @@ fn test_padding_column_direction() {
             },
             ..Default::default()
         }],
+        media: Vec::new(),
+        important: Vec::new(),
+        var_declarations: Vec::new(),
     });
     ctx.document
         .set_attribute(container_id, "class".to_owned(), class_name);
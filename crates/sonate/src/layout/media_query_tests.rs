@@ -0,0 +1,35 @@
+use crate::layout::test_html::load_html_test_example;
+use crate::style::Length;
+
+#[test]
+fn prefers_reduced_motion_rule_follows_runtime_toggle() {
+    const HTML: &str = r#"
+<style>
+  .box { width: 100px; height: 20px; }
+  @media (prefers-reduced-motion: reduce) {
+    .box { width: 40px; }
+  }
+</style>
+<div id="example">
+  <div id="x" class="box"></div>
+</div>
+"#;
+
+    let (mut ctx, nodes_by_id) = load_html_test_example(HTML, "example");
+    let x = nodes_by_id.get("x").copied().expect("missing node x");
+    let width = |ctx: &crate::layout::LayoutContext| {
+        let node = ctx.document.get_node(x).expect("node not found");
+        let width = node.borrow().layout.style.width.clone();
+        width
+    };
+
+    assert_eq!(width(&ctx), Some(Length::Px(100.0)));
+
+    ctx.set_prefers_reduced_motion(true);
+    ctx.layout();
+    assert_eq!(width(&ctx), Some(Length::Px(40.0)));
+
+    ctx.set_prefers_reduced_motion(false);
+    ctx.layout();
+    assert_eq!(width(&ctx), Some(Length::Px(100.0)));
+}
@@ -0,0 +1,48 @@
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::LayoutContext;
+use crate::Id;
+
+fn size(ctx: &LayoutContext, id: Id) -> (f64, f64) {
+    let node = ctx.document.get_node(id).expect("node not found");
+    let bounds = node.borrow().layout.bounds;
+    (bounds.width, bounds.height)
+}
+
+#[test]
+fn calc_percentages_are_of_the_containers_content_box() {
+    const HTML: &str = r#"
+<style>
+  .row { display: flex; width: 300px; height: 100px; padding: 0 5px; }
+  .item { width: calc(100% - 2 * 10px); height: calc((100% + 10px) / 2); }
+  .half { width: 50%; height: 10px; }
+</style>
+<div id="example" class="row">
+  <div id="item" class="item"></div>
+  <div id="half" class="half"></div>
+</div>
+"#;
+
+    let (ctx, nodes_by_id) = load_html_test_example(HTML, "example");
+    assert_eq!(size(&ctx, nodes_by_id["item"]), (280.0, 55.0));
+    assert_eq!(size(&ctx, nodes_by_id["half"]).0, 150.0);
+}
+
+#[test]
+fn viewport_units_follow_the_viewport_size() {
+    const HTML: &str = r#"
+<style>
+  .box { width: 50vw; height: calc(10vh + 1px); }
+</style>
+<div id="example">
+  <div id="x" class="box"></div>
+</div>
+"#;
+
+    let (mut ctx, nodes_by_id) = load_html_test_example(HTML, "example");
+    let x = nodes_by_id["x"];
+    assert_eq!(size(&ctx, x), (400.0, 51.0));
+
+    ctx.set_viewport_size(1000.0, 600.0);
+    ctx.layout();
+    assert_eq!(size(&ctx, x), (500.0, 61.0));
+}
@@ -0,0 +1,312 @@
+use crate::layout::{outline_bounds, rebuild_render_tree, LayoutContext, Rect, RenderNode};
+use crate::snapshot::Edges;
+use crate::style::Style;
+use crate::text::TestTextMeasurer;
+use crate::{css_parser, Id};
+use std::sync::Arc;
+use std::time::Instant;
+
+const CSS: &str = "
+.row { display: flex; flex-direction: row; height: 20px; }
+.cell { width: 10px; height: 10px; }
+.hot { background-color: red; }
+";
+
+/// A laid out document of `rows` rows of `cells` cells. Returns the cells, row by row.
+fn grid(rows: u64, cells: u64) -> (LayoutContext, Vec<Id>) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = css_parser::parse_css(CSS).unwrap();
+    let root = ctx.document.root_id();
+    let mut next_id = 2;
+    let mut create = |ctx: &mut LayoutContext, parent: Id, class: &str| {
+        let id = ctx
+            .document
+            .create_node(Id::from_u64(next_id), None)
+            .unwrap();
+        next_id += 1;
+        ctx.document.set_parent(parent, id).unwrap();
+        ctx.document
+            .set_attribute(id, "class".to_owned(), class.to_owned());
+        id
+    };
+    let mut all_cells = Vec::new();
+    for _ in 0..rows {
+        let row = create(&mut ctx, root, "row");
+        for _ in 0..cells {
+            all_cells.push(create(&mut ctx, row, "cell"));
+        }
+    }
+    ctx.layout();
+    (ctx, all_cells)
+}
+
+fn rebuild(ctx: &LayoutContext, previous: Option<&Arc<RenderNode>>) -> Arc<RenderNode> {
+    rebuild_render_tree(&ctx.document.root_node(), previous, &TestTextMeasurer)
+}
+
+#[test]
+fn unchanged_subtrees_are_shared_with_the_previous_tree() {
+    let (mut ctx, cells) = grid(3, 3);
+    let first = rebuild(&ctx, None);
+
+    ctx.document
+        .set_attribute(cells[4], "class".to_owned(), "cell hot".to_owned());
+    ctx.layout();
+    let second = rebuild(&ctx, Some(&first));
+    assert!(Arc::ptr_eq(&first.children[0], &second.children[0]));
+    assert!(Arc::ptr_eq(&first.children[2], &second.children[2]));
+    // The changed cell's row is rebuilt, around its siblings.
+    let (old_row, new_row) = (&first.children[1], &second.children[1]);
+    assert!(!Arc::ptr_eq(old_row, new_row));
+    assert!(Arc::ptr_eq(&old_row.children[0], &new_row.children[0]));
+    assert!(!Arc::ptr_eq(&old_row.children[1], &new_row.children[1]));
+    assert!(Arc::ptr_eq(&old_row.children[2], &new_row.children[2]));
+
+    // Laying out again without changes shares the whole tree.
+    ctx.layout();
+    let third = rebuild(&ctx, Some(&second));
+    assert!(Arc::ptr_eq(&second, &third));
+}
+
+#[test]
+fn reordered_children_keep_their_siblings_shared() {
+    let (mut ctx, cells) = grid(1, 4);
+    let first = rebuild(&ctx, None);
+
+    let row = first.children[0].id;
+    ctx.document.move_child(row, cells[1], 0).unwrap();
+    ctx.layout();
+    let second = rebuild(&ctx, Some(&first));
+    let (old_row, new_row) = (&first.children[0], &second.children[0]);
+    // The last two cells didn't move.
+    assert!(Arc::ptr_eq(&old_row.children[2], &new_row.children[2]));
+    assert!(Arc::ptr_eq(&old_row.children[3], &new_row.children[3]));
+    assert_eq!(new_row.children[0].id, cells[1]);
+}
+
+/// Building the snapshot of an unchanged 5,000 node document, from scratch and sharing the
+/// previous one. Run with `--release --ignored --nocapture` to see the timings; unoptimized,
+/// comparing styles takes about as long as copying them.
+#[ignore]
+#[test]
+fn sharing_unchanged_subtrees_is_cheaper_than_rebuilding_them() {
+    const FRAMES: u32 = 50;
+    let (ctx, cells) = grid(50, 99);
+    assert_eq!(cells.len() + 50, 5_000);
+
+    let started = Instant::now();
+    for _ in 0..FRAMES {
+        drop(rebuild(&ctx, None));
+    }
+    let from_scratch = started.elapsed() / FRAMES;
+
+    let mut previous = rebuild(&ctx, None);
+    let started = Instant::now();
+    for _ in 0..FRAMES {
+        previous = rebuild(&ctx, Some(&previous));
+    }
+    let shared = started.elapsed() / FRAMES;
+
+    println!("per frame: {from_scratch:?} from scratch, {shared:?} sharing");
+    assert!(shared < from_scratch);
+}
+
+#[test]
+fn outlines_are_drawn_outside_the_border_box() {
+    let bounds = Rect::new(10.0, 10.0, 40.0, 20.0);
+    let outline = |css: &str| {
+        let stylesheet = css_parser::parse_css(&format!(".a {{ {css} }}")).unwrap();
+        let mut style = Style::default();
+        for declaration in &stylesheet.rules[0].declarations {
+            style.merge(declaration);
+        }
+        outline_bounds(bounds, &style)
+    };
+
+    assert_eq!(
+        outline("outline: 2px solid"),
+        Some(Rect::new(8.0, 8.0, 44.0, 24.0))
+    );
+    assert_eq!(
+        outline("outline: 2px solid; outline-offset: 3px"),
+        Some(Rect::new(5.0, 5.0, 50.0, 30.0))
+    );
+    // Insetting stops at the middle of the box.
+    assert_eq!(
+        outline("outline: 1px solid; outline-offset: -50px"),
+        Some(Rect::new(19.0, 19.0, 22.0, 2.0))
+    );
+    assert_eq!(outline("outline: solid"), Some(bounds.outset(3.0)));
+    assert_eq!(outline("outline: 2px none"), None);
+    assert_eq!(outline("outline-width: 2px"), None);
+}
+
+#[test]
+fn outlines_extend_what_a_node_paints_but_not_its_layout() {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = css_parser::parse_css(
+        ".row { display: flex; width: 100px; height: 20px; }
+         .cell { width: 10px; height: 10px; outline: 4px solid; }",
+    )
+    .unwrap();
+    let root = ctx.document.root_id();
+    let row = ctx.document.create_node(Id::from_u64(2), None).unwrap();
+    ctx.document.set_parent(root, row).unwrap();
+    ctx.document
+        .set_attribute(row, "class".to_owned(), "row".to_owned());
+    for id in [3, 4] {
+        let cell = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+        ctx.document.set_parent(row, cell).unwrap();
+        ctx.document
+            .set_attribute(cell, "class".to_owned(), "cell".to_owned());
+    }
+    ctx.layout();
+    let tree = rebuild(&ctx, None);
+
+    let row = &tree.children[0];
+    assert_eq!(row.children[1].bounds, Rect::new(10.0, 0.0, 10.0, 10.0));
+    assert_eq!(
+        row.children[1].descendant_bounds,
+        Rect::new(6.0, -4.0, 18.0, 18.0)
+    );
+    assert_eq!(row.descendant_bounds, Rect::new(-4.0, -4.0, 104.0, 24.0));
+}
+
+#[test]
+fn nodes_carry_their_resolved_margin_and_padding() {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = css_parser::parse_css(
+        ".row { display: flex; width: 200px; height: 50px; padding: 4px 8px; }
+         .cell { width: 20px; height: 10px; margin: 1px 2px 3px; padding: 2em 0 0 5px; font-size: 2px; }
+         .plain { width: 20px; height: 10px; }",
+    )
+    .unwrap();
+    let root = ctx.document.root_id();
+    let row = ctx.document.create_node(Id::from_u64(2), None).unwrap();
+    ctx.document.set_parent(root, row).unwrap();
+    ctx.document
+        .set_attribute(row, "class".to_owned(), "row".to_owned());
+    for (id, class) in [(3, "cell"), (4, "plain")] {
+        let cell = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+        ctx.document.set_parent(row, cell).unwrap();
+        ctx.document
+            .set_attribute(cell, "class".to_owned(), class.to_owned());
+    }
+    ctx.layout();
+    let tree = rebuild(&ctx, None);
+    let edges = |top, right, bottom, left| Edges {
+        top,
+        right,
+        bottom,
+        left,
+    };
+
+    let row = &tree.children[0];
+    assert_eq!(row.padding, edges(4.0, 8.0, 4.0, 8.0));
+    assert_eq!(row.margin, Edges::default());
+    let (cell, plain) = (&row.children[0], &row.children[1]);
+    // Relative lengths are resolved like the bounds are.
+    assert_eq!(cell.margin, edges(1.0, 2.0, 3.0, 2.0));
+    assert_eq!(cell.padding, edges(4.0, 0.0, 0.0, 5.0));
+    assert_eq!(plain.margin, Edges::default());
+    assert_eq!(plain.padding, Edges::default());
+    // The margin edge and the content box around and in the border box.
+    assert_eq!(
+        cell.bounds.outset_by(&cell.margin),
+        Rect::new(
+            cell.bounds.x - 2.0,
+            cell.bounds.y - 1.0,
+            cell.bounds.width + 4.0,
+            cell.bounds.height + 4.0
+        )
+    );
+    assert_eq!(
+        row.bounds.inset_by(&row.padding),
+        Rect::new(8.0, 4.0, row.bounds.width - 16.0, row.bounds.height - 8.0)
+    );
+}
+
+#[test]
+fn new_margins_and_padding_rebuild_the_node() {
+    let (mut ctx, cells) = grid(1, 2);
+    let first = rebuild(&ctx, None);
+
+    ctx.style_sheet =
+        css_parser::parse_css(&format!("{CSS} .hot {{ padding-left: 3px; }}")).unwrap();
+    ctx.document
+        .set_attribute(cells[1], "class".to_owned(), "cell hot".to_owned());
+    ctx.layout();
+    let second = rebuild(&ctx, Some(&first));
+    let (old_row, new_row) = (&first.children[0], &second.children[0]);
+    assert!(Arc::ptr_eq(&old_row.children[0], &new_row.children[0]));
+    assert_eq!(old_row.children[1].padding.left, 0.0);
+    assert_eq!(new_row.children[1].padding.left, 3.0);
+}
+
+#[test]
+fn nodes_tell_their_border_padding_and_content_boxes_apart() {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = css_parser::parse_css(
+        ".row { display: flex; border: 4px solid; padding: 2px; }
+         .cell { width: 30px; height: 10px; margin: 1px; border: 3px solid; padding: 5px 2px; }
+         .borderless { width: 30px; height: 10px; border-width: 3px; border-style: none; }",
+    )
+    .unwrap();
+    let root = ctx.document.root_id();
+    let row = ctx.document.create_node(Id::from_u64(2), None).unwrap();
+    ctx.document.set_parent(root, row).unwrap();
+    ctx.document
+        .set_attribute(row, "class".to_owned(), "row".to_owned());
+    for (id, class) in [(3, "cell"), (4, "borderless")] {
+        let cell = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+        ctx.document.set_parent(row, cell).unwrap();
+        ctx.document
+            .set_attribute(cell, "class".to_owned(), class.to_owned());
+    }
+    ctx.layout();
+    let tree = rebuild(&ctx, None);
+
+    let row = &tree.children[0];
+    let (cell, borderless) = (&row.children[0], &row.children[1]);
+    // Children are laid out in the row's content box, inside its border and padding.
+    assert_eq!(row.content_box().x, 6.0);
+    assert_eq!(cell.border_box(), Rect::new(7.0, 7.0, 40.0, 26.0));
+    assert_eq!(cell.border_width.left, 3.0);
+    assert_eq!(cell.padding_box(), Rect::new(10.0, 10.0, 34.0, 20.0));
+    assert_eq!(cell.content_box(), Rect::new(12.0, 15.0, 30.0, 10.0));
+
+    // A border that isn't drawn takes no room.
+    assert_eq!(borderless.border_width, Edges::default());
+    assert_eq!(borderless.border_box().width, 30.0);
+    assert_eq!(borderless.content_box(), borderless.border_box());
+}
+
+#[test]
+fn render_nodes_tell_whether_their_text_was_cut_short() {
+    // TestTextMeasurer: 10px font => 6px per char, 12px line height.
+    let html = r#"
+    <style>
+      .list { display: flex; flex-direction: column; width: 300px; }
+      .row { width: 60px; height: auto; font-size: 10px; }
+      .ellipsis { white-space: nowrap; text-overflow: ellipsis; }
+      .clamped { line-clamp: 1; }
+    </style>
+    <div id="list" class="list">
+      <div id="short" class="row ellipsis">short</div>
+      <div id="long" class="row ellipsis">much longer text</div>
+      <div id="clipped" class="row">much longer text</div>
+      <div id="clamped" class="row clamped">one two three four</div>
+    </div>
+    "#;
+    let (ctx, ids) = crate::layout::test_html::load_html_test_example(html, "list");
+    let tree = crate::layout::build_render_tree(ctx.document.root_node());
+    let node = |id: &str| crate::selection::find_node(&tree, ids[id]).unwrap().clone();
+
+    assert!(!node("short").text_truncated);
+    assert!(node("long").text_truncated);
+    assert!(!node("clipped").text_truncated);
+    let clamped = node("clamped");
+    assert!(clamped.text_truncated);
+    // Only as high as the one line it shows.
+    assert_eq!(clamped.bounds.height, 12.0);
+}
@@ -1,6 +1,9 @@
+use crate::css_parser::parse_css;
 use crate::layout::test_html::load_html_test_example;
+use crate::layout::LayoutContext;
 use crate::style::Length;
 use crate::style::Selector;
+use crate::Id;
 
 #[test]
 fn tag_selectors_apply_to_elements() {
@@ -25,10 +28,8 @@ fn tag_selectors_apply_to_elements() {
     );
 
     let node = ctx.document.get_node(x).expect("node not found");
-    assert_eq!(
-        node.borrow().attributes.get("tag").map(|s| s.as_str()),
-        Some("input")
-    );
+    assert_eq!(node.borrow().tag.as_deref(), Some("input"));
+    assert!(!node.borrow().attributes.contains_key("tag"));
     let style = node.borrow().layout.style.clone();
 
     assert_eq!(style.border_width.top, Some(Length::Px(2.0)));
@@ -36,3 +37,46 @@ fn tag_selectors_apply_to_elements() {
     assert_eq!(style.border_width.bottom, Some(Length::Px(2.0)));
     assert_eq!(style.border_width.left, Some(Length::Px(2.0)));
 }
+
+/// A laid out document with `css`, of a `BUTTON` element and an untagged node.
+fn element_and_node(css: &str) -> (LayoutContext, Id, Id) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = parse_css(css).unwrap();
+    let root = ctx.document.root_id();
+    let button = ctx
+        .document
+        .create_element(Id::from_u64(2), "BUTTON", Some("OK".to_owned()))
+        .unwrap();
+    let node = ctx.document.create_node(Id::from_u64(3), None).unwrap();
+    ctx.document.set_parent(root, button).unwrap();
+    ctx.document.set_parent(root, node).unwrap();
+    ctx.layout();
+    (ctx, button, node)
+}
+
+fn height(ctx: &LayoutContext, id: Id) -> Option<Length> {
+    let node = ctx.document.get_node(id).unwrap();
+    let height = node.borrow().layout.style.height.clone();
+    height
+}
+
+#[test]
+fn type_selectors_match_elements_created_with_the_tag() {
+    let (ctx, button, node) = element_and_node("button { height: 32px; }");
+    assert_eq!(height(&ctx, button), Some(Length::Px(32.0)));
+    assert_eq!(height(&ctx, node), None);
+
+    // An attribute named `tag` isn't the element's type.
+    let (mut ctx, _, node) = element_and_node("button { height: 32px; }");
+    ctx.document
+        .set_attribute(node, "tag".to_owned(), "button".to_owned());
+    ctx.layout();
+    assert_eq!(height(&ctx, node), None);
+}
+
+#[test]
+fn the_universal_selector_matches_every_node() {
+    let (ctx, button, node) = element_and_node("* { height: 5px; } button { height: 32px; }");
+    assert_eq!(height(&ctx, button), Some(Length::Px(32.0)));
+    assert_eq!(height(&ctx, node), Some(Length::Px(5.0)));
+}
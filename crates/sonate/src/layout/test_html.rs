@@ -76,16 +76,13 @@ impl Reader {
                 .and_then(|child| child.text())
                 .map(|s| s.to_owned());
 
-            let node = self.ctx.document.create_node(id, text);
+            let node = self
+                .ctx
+                .document
+                .create_element(id, &element.name, text)
+                .unwrap();
             self.ctx.document.set_parent(parent, node).unwrap();
 
-            // Store element tag for tag selector matching.
-            self.ctx.document.set_attribute(
-                node,
-                "tag".to_owned(),
-                element.name.to_ascii_lowercase(),
-            );
-
             // get id
             if let Some(id_attr) = &element.id {
                 self.nodes_by_id.insert(id_attr.to_owned(), node);
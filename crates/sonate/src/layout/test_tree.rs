@@ -0,0 +1,255 @@
+//! A builder for layout tests: a tree of named nodes with inline styles, laid out in a
+//! viewport, whose bounds tests check by name. A mismatch prints the whole laid out tree.
+//!
+//! ```ignore
+//! let layout = lay_out(
+//!     node("row")
+//!         .css("display: flex; column-gap: 10px; width: 300px; height: 100px")
+//!         .children([item("a", 50.0, 30.0), item("b", 60.0, 30.0)]),
+//! );
+//! layout.assert_bounds("b", 60.0, 0.0, 60.0, 30.0);
+//! ```
+
+use super::{LayoutContext, Node, Rect};
+use crate::css_parser;
+use crate::Id;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+/// A `div` to lay out, named for the assertions.
+pub struct TestNode {
+    name: String,
+    css: String,
+    class: Option<String>,
+    text: Option<String>,
+    children: Vec<TestNode>,
+}
+
+/// A node called `name`, without style or children yet.
+pub fn node(name: &str) -> TestNode {
+    TestNode {
+        name: name.to_owned(),
+        css: String::new(),
+        class: None,
+        text: None,
+        children: Vec::new(),
+    }
+}
+
+/// A node called `name` sized in pixels.
+pub fn item(name: &str, width: f64, height: f64) -> TestNode {
+    node(name).css(&format!("width: {width}px; height: {height}px"))
+}
+
+impl TestNode {
+    /// Add declarations to the node's inline style.
+    pub fn css(mut self, css: &str) -> Self {
+        self.css.push_str(css);
+        self.css.push(';');
+        self
+    }
+
+    /// Set the `class` attribute, for rules of the tree's stylesheet.
+    pub fn class(mut self, class: &str) -> Self {
+        self.class = Some(class.to_owned());
+        self
+    }
+
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = Some(text.to_owned());
+        self
+    }
+
+    pub fn child(mut self, child: TestNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn children(mut self, children: impl IntoIterator<Item = TestNode>) -> Self {
+        self.children.extend(children);
+        self
+    }
+}
+
+/// The nodes under the document root, the stylesheet and the viewport to lay them out in.
+pub struct TestTree {
+    viewport: (f64, f64),
+    stylesheet: String,
+    roots: Vec<TestNode>,
+}
+
+impl Default for TestTree {
+    fn default() -> Self {
+        Self {
+            viewport: (800.0, 500.0),
+            stylesheet: String::new(),
+            roots: Vec::new(),
+        }
+    }
+}
+
+impl TestTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lay out in `width` by `height` pixels rather than 800 by 500.
+    pub fn viewport(mut self, width: f64, height: f64) -> Self {
+        self.viewport = (width, height);
+        self
+    }
+
+    /// Add the rules of `css`, which the nodes' classes and tags can match.
+    pub fn stylesheet(mut self, css: &str) -> Self {
+        self.stylesheet.push_str(css);
+        self.stylesheet.push('\n');
+        self
+    }
+
+    /// Add a child of the document root, after those added before it.
+    pub fn root(mut self, node: TestNode) -> Self {
+        self.roots.push(node);
+        self
+    }
+
+    pub fn lay_out(self) -> TestLayout {
+        let mut ctx = LayoutContext::new();
+        for rule in css_parser::parse_css(&self.stylesheet)
+            .expect("expected to load stylesheet")
+            .rules
+        {
+            ctx.style_sheet.add_rule(rule);
+        }
+        ctx.set_viewport_size(self.viewport.0, self.viewport.1);
+
+        let mut layout = TestLayout {
+            ctx,
+            ids: HashMap::new(),
+            names: HashMap::new(),
+        };
+        let root = layout.ctx.document.root_id();
+        for node in &self.roots {
+            layout.create(node, root);
+        }
+        layout.ctx.layout();
+        layout
+    }
+}
+
+/// Lay out `root` as the only child of the document root, in an 800 by 500 viewport.
+pub fn lay_out(root: TestNode) -> TestLayout {
+    TestTree::new().root(root).lay_out()
+}
+
+/// A laid out tree. Its context is there to change the document and lay it out again.
+pub struct TestLayout {
+    pub ctx: LayoutContext,
+    ids: HashMap<String, Id>,
+    names: HashMap<Id, String>,
+}
+
+impl TestLayout {
+    fn create(&mut self, node: &TestNode, parent: Id) {
+        let id = Id::from_u64(self.ids.len() as u64 + 1);
+        assert!(
+            self.ids.insert(node.name.clone(), id).is_none(),
+            "two nodes are called `{}`",
+            node.name
+        );
+        self.names.insert(id, node.name.clone());
+
+        let document = &mut self.ctx.document;
+        document
+            .create_element(id, "div", node.text.clone())
+            .unwrap();
+        document.set_parent(parent, id).unwrap();
+        if !node.css.is_empty() {
+            document.set_attribute(id, "style".to_owned(), node.css.clone());
+        }
+        if let Some(class) = &node.class {
+            document.set_attribute(id, "class".to_owned(), class.clone());
+        }
+        for child in &node.children {
+            self.create(child, id);
+        }
+    }
+
+    /// The id of the node called `name`.
+    pub fn id(&self, name: &str) -> Id {
+        *self
+            .ids
+            .get(name)
+            .unwrap_or_else(|| panic!("no node is called `{name}`\n\n{}", self.dump(None)))
+    }
+
+    /// The border box of the node called `name`.
+    pub fn bounds(&self, name: &str) -> Rect {
+        let node = self.ctx.document.get_node(self.id(name)).unwrap();
+        let bounds = node.borrow().layout.bounds;
+        bounds
+    }
+
+    /// Check the border box of the node called `name`, to within a thousandth of a pixel.
+    pub fn assert_bounds(&self, name: &str, x: f64, y: f64, width: f64, height: f64) {
+        let expected = Rect::new(x, y, width, height);
+        let actual = self.bounds(name);
+        let close = |a: f64, b: f64| (a - b).abs() < 0.001;
+        if !(close(actual.x, x)
+            && close(actual.y, y)
+            && close(actual.width, width)
+            && close(actual.height, height))
+        {
+            panic!(
+                "`{name}` is at {}, expected {}\n\n{}",
+                format_rect(&actual),
+                format_rect(&expected),
+                self.dump(Some((self.id(name), expected)))
+            );
+        }
+    }
+
+    /// The laid out tree, a node per line, with what `mismatch` expected of its node.
+    fn dump(&self, mismatch: Option<(Id, Rect)>) -> String {
+        let mut out = String::new();
+        self.dump_node(&self.ctx.document.root_node(), 0, mismatch, &mut out);
+        out
+    }
+
+    fn dump_node(
+        &self,
+        node: &Rc<RefCell<Node>>,
+        depth: usize,
+        mismatch: Option<(Id, Rect)>,
+        out: &mut String,
+    ) {
+        let node = node.borrow();
+        let name = self.names.get(&node.id).map_or("(root)", String::as_str);
+        let _ = write!(
+            out,
+            "{:indent$}{name} {}",
+            "",
+            format_rect(&node.layout.bounds),
+            indent = depth * 2
+        );
+        if let Some(text) = &node.text {
+            let _ = write!(out, " {text:?}");
+        }
+        if let Some((_, expected)) = mismatch.filter(|(id, _)| *id == node.id) {
+            let _ = write!(out, "  <- expected {}", format_rect(&expected));
+        }
+        out.push('\n');
+        for child in &node.children {
+            self.dump_node(child, depth + 1, mismatch, out);
+        }
+    }
+}
+
+/// `(x, y) widthxheight`, as failures print bounds.
+pub fn format_rect(rect: &Rect) -> String {
+    format!("({}, {}) {}x{}", rect.x, rect.y, rect.width, rect.height)
+}
+
+#[cfg(test)]
+mod test_tree_tests;
@@ -0,0 +1,30 @@
+use super::{node, TestTree};
+
+#[test]
+fn classes_match_the_stylesheet_and_lengths_resolve_in_the_viewport() {
+    let layout = TestTree::new()
+        .viewport(200.0, 100.0)
+        .stylesheet(".column { display: flex; flex-direction: column; height: 50px; }")
+        .root(
+            node("list")
+                .class("column")
+                .css("width: 50vw")
+                .child(node("label").text("item")),
+        )
+        .lay_out();
+
+    layout.assert_bounds("list", 0.0, 0.0, 100.0, 50.0);
+    // TestTextMeasurer: 16px font => 9.6px per char, 19.2px line height.
+    layout.assert_bounds("label", 0.0, 0.0, 100.0, 19.2);
+}
+
+#[test]
+#[should_panic(expected = "`b` is at (100, 0) 20x10, expected (100, 0) 30x10")]
+fn mismatches_name_the_node() {
+    let layout = TestTree::new()
+        .root(node("a").css("display: flex; width: 100px; height: 10px"))
+        .root(node("b").css("width: 20px; height: 10px"))
+        .lay_out();
+
+    layout.assert_bounds("b", 100.0, 0.0, 30.0, 10.0);
+}
@@ -1,30 +1,131 @@
+mod accessibility;
+mod animation;
+#[cfg(feature = "async")]
+mod async_engine;
 mod backend;
+mod callbacks;
+mod clipboard;
 mod commands;
 mod css_parser;
+mod damage;
+mod debug_overlay;
+mod diagnostics;
+mod editing;
+mod external;
 mod flex_layout;
+mod grid_layout;
+mod health;
+mod hit_index;
+mod idle;
+mod images;
+mod intrinsic;
+mod layer_cache;
 mod layout;
+mod markup;
+mod media;
 mod painter;
+mod pointer_capture;
+mod progress;
+mod query;
+mod resources;
+mod selection;
+mod snapshot;
+mod stats;
 mod style;
 mod style_matching;
+mod template;
 mod text;
+mod text_extraction;
+mod threading;
+mod tooltip;
 mod windowing;
 
+use callbacks::{CallbackRegistry, Callbacks};
 use commands::Command;
+use damage::{Overlay, PaintedWindow};
+use diagnostics::Diagnostics;
+use editing::{EditKey, Editing};
+use health::Health;
+use hit_index::IndexedTree;
+use idle::{IdleWarmup, WarmupCounters};
+use layer_cache::LayerCache;
 use layout::RenderNode;
+use markup::MarkupNode;
 use painter::Painter;
+use pointer_capture::PointerCapture;
+use query::QuerySelector;
+use selection::Selection;
+use stats::StatsRecorder;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 use std::sync::{
-    mpsc::{channel, Receiver, Sender},
+    mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError},
     Arc, RwLock,
 };
 use std::thread;
+use std::time::{Duration, Instant};
+use text::{SkiaTextMeasurer, TextMeasurer};
+use threading::DataThread;
+use tooltip::Tooltips;
 
-use crate::windowing::WindowMessageSender;
+pub use accessibility::AccessNode;
+#[cfg(feature = "async")]
+pub use async_engine::{Acknowledged, AsyncEngine};
+pub use backend::external::FrameHandle;
+pub use backend::pdf::{PageSize, PdfOptions};
+pub use backend::{BackendType, GpuMemoryStats, VsyncMode};
+pub use callbacks::{
+    Event, EventKind, EventListener, EventStatus, KeyHandler, KeyPress, ListenerId, PointerHandler,
+};
+pub use commands::DEFAULT_COMMAND_CAPACITY;
+pub use diagnostics::{Diagnostic, DiagnosticCode, Severity};
+pub use external::PresentMode;
+pub use health::{EngineErrorHandler, EngineHealth};
+pub use hit_index::HitIndexStats;
+pub use idle::{WarmupStats, DEFAULT_IDLE_WARMUP_DELAY};
+pub use layout::{IdInUse, Rect, SetParentError};
+pub use markup::{escape_markup, MarkupError, MarkupErrorKind};
+pub use pointer_capture::{Drag, DragPhase};
+pub use resources::{CacheStats, ImageHandle, ResourceCaches, DEFAULT_CACHE_BUDGET_BYTES};
+pub use snapshot::{BorderInfo, Corners, Edges, RenderSnapshot};
+pub use sonate_error::{
+    CssErrorKind, DocumentErrorKind, EngineError, ErrorCode, SourceLocation, WindowErrorKind,
+    WorkerErrorKind,
+};
+pub use stats::{EngineStats, STATS_SAMPLES};
+pub use style::{Cursor, Rgba, UserSelect};
+pub use template::{Fragment, TemplateId, TemplateStats};
+pub use text_extraction::{TextGranularity, TextRun};
+pub use threading::ThreadAffinity;
+pub use tooltip::TOOLTIP_CLASS;
+
+pub use crate::windowing::{FrameStats, FullscreenMode, PointerInput, WindowId, WindowOptions};
+use crate::windowing::{WindowMessage, WindowMessageSender};
 
 #[derive(Clone, Copy, Default, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
 pub struct Id(u64);
 
 impl Id {
+    /// Ids from here on, those with the high bit set, are the engine's: it generates them
+    /// for [`Engine::create_node_auto`] and markup, and window roots have them. Ids the host
+    /// picks must be below, so that both never collide.
+    pub const GENERATED_BASE: u64 = 1 << 63;
+
+    /// Whether the id is one of the engine's, see [`Id::GENERATED_BASE`].
+    pub fn is_generated(&self) -> bool {
+        self.0 >= Self::GENERATED_BASE
+    }
+
     pub fn value(&self) -> u64 {
         self.0
     }
@@ -40,122 +141,1531 @@ impl Id {
 
 #[derive(Clone)]
 pub struct Engine {
-    sender: Sender<Command>,
-    snapshot: Arc<RwLock<Option<RenderNode>>>,
+    sender: SyncSender<Command>,
+    snapshot: Arc<RwLock<Option<Arc<IndexedTree>>>>,
+    /// Snapshots of the secondary windows by root.
+    window_snapshots: Arc<RwLock<HashMap<Id, Arc<IndexedTree>>>>,
+    /// Secondary windows that are open, or will open when `run` starts.
+    windows: Arc<Mutex<Vec<(WindowId, WindowOptions)>>>,
+    next_window: Arc<AtomicU64>,
     root_id: Id,
     running: Arc<Mutex<()>>,
     message_sender: WindowMessageSender,
+    reduced_motion_override: Arc<Mutex<Option<bool>>>,
+    resources: Arc<resources::CacheAttachment>,
+    diagnostics: Arc<Diagnostics>,
+    /// The next id to generate, see [`Id::GENERATED_BASE`].
+    next_generated_id: Arc<AtomicU64>,
+    /// Fragments of the defined templates, by `TemplateId`.
+    templates: Arc<Mutex<Vec<Fragment>>>,
+    warmup_counters: Arc<WarmupCounters>,
+    gpu_memory: windowing::GpuMemoryReport,
+    backend: windowing::BackendReport,
+    selection: Arc<Mutex<Option<Selection>>>,
+    /// The editable node with focus, if any.
+    editing: Arc<Mutex<Option<Editing>>>,
+    /// Where the pointer rests, for the tooltip of the element there.
+    tooltips: Arc<Mutex<Tooltips>>,
+    /// The node pointer moves and releases go to, if any.
+    pointer_capture: Arc<Mutex<PointerCapture>>,
+    affinity: ThreadAffinity,
+    #[cfg_attr(not(feature = "async"), allow(unused))]
+    progress: Arc<progress::Progress>,
+    /// Reaches the host's handlers of pointer and key events while it runs.
+    callbacks: CallbackRegistry,
+    health: Arc<Health>,
+    data_thread: Arc<DataThread>,
+    stats: Arc<StatsRecorder>,
 }
 
-#[derive(Default)]
-pub struct Params {
-    pub on_click: Option<Box<dyn Fn(f64, f64, Vec<Id>)>>,
+#[derive(Clone)]
+pub struct EngineOptions {
+    /// Image and font caches shared with other engines. When `None` the engine creates
+    /// its own caches with [`DEFAULT_CACHE_BUDGET_BYTES`].
+    pub shared_caches: Option<ResourceCaches>,
+    /// How long the engine waits without changes before it resolves styles and measures
+    /// text of content that isn't displayed yet, so revealing it later is cheaper. `None`
+    /// disables this. Defaults to [`DEFAULT_IDLE_WARMUP_DELAY`].
+    pub idle_warmup_delay: Option<Duration>,
+    /// Check in release builds too that [`Engine::run`] is called on the thread that created
+    /// the engine; debug builds always check. The window's event loop and the `Params`
+    /// callbacks belong to that thread. Every other method sends a command to the data
+    /// thread or reads the published snapshot, and works from any thread.
+    pub strict_threading: bool,
+    /// Called on the data thread when it catches a panic, with what [`Engine::health`] says
+    /// after it. Changes the handler makes are applied next, without waiting for room in
+    /// the queue; methods that wait for an answer, such as [`Engine::children`], would wait
+    /// forever there.
+    pub on_engine_error: Option<EngineErrorHandler>,
+    /// How many changes may wait for the data thread, at least one. Methods that send more
+    /// wait for it to make room, except [`Engine::try_set_attribute_nonblocking`], which
+    /// fails instead. Defaults to [`DEFAULT_COMMAND_CAPACITY`].
+    pub command_capacity: usize,
 }
 
-#[derive(Debug)]
-pub enum Error {
-    AlreadyRunning,
-    UnknownError(String),
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            shared_caches: None,
+            idle_warmup_delay: Some(DEFAULT_IDLE_WARMUP_DELAY),
+            strict_threading: false,
+            on_engine_error: None,
+            command_capacity: DEFAULT_COMMAND_CAPACITY,
+        }
+    }
+}
+
+/// What [`Engine::run`] starts with. The click, hover and key handlers given here are
+/// registered as if by [`Engine::set_on_click`], [`Engine::set_on_hover`] and
+/// [`Engine::set_on_key`]; `None` keeps what was set before. They are dropped when the run
+/// ends, and called for the events no listener stopped, see [`Engine::add_event_listener`].
+#[derive(Default)]
+pub struct Params {
+    pub on_click: Option<PointerHandler>,
+    /// Called when the pointer moves over a window, with where it is and the nodes under
+    /// it, innermost first.
+    pub on_hover: Option<PointerHandler>,
+    /// Called for key presses that no editable node with focus took. Returning `true` marks
+    /// the key as handled, so the engine does nothing else with it.
+    pub on_key: Option<KeyHandler>,
+    /// Called before each frame is drawn with the time since [`Engine::run`] started, for
+    /// animating from code. The window redraws continuously while this is set, and only
+    /// when something changed otherwise. Engine methods may be called from it; their
+    /// changes are laid out right away and drawn in the following frame.
+    pub on_frame: Option<Box<dyn FnMut(Duration)>>,
+    /// Called when the user asks to close a window. Returning `false` keeps it open, e.g.
+    /// to ask about unsaved changes first; [`Engine::quit`] still closes it.
+    pub on_close_requested: Option<Box<dyn FnMut() -> bool>>,
+    /// Called once, after the first frame has been presented.
+    pub on_ready: Option<Box<dyn FnOnce()>>,
+    /// Called with the selected text when the user copies it, after it was put on the
+    /// system clipboard where the engine can do that; see [`Engine::get_selection`].
+    pub on_copy: Option<Box<dyn FnMut(String)>>,
+    /// Called with the new value of a node with `editable="true"` after each edit the user
+    /// makes by typing into it.
+    pub on_input: Option<Box<dyn Fn(Id, String)>>,
+    /// Called with the inner size of a window in logical pixels when it opens, and each time
+    /// its size or scale factor changes.
+    pub on_resize: Option<Box<dyn FnMut(f64, f64)>>,
+    /// Whether `run` presents to windows or leaves that to the host.
+    pub present_mode: PresentMode,
+    /// The backend to render windows with. When `None`, the one the `SONATE_BACKEND`
+    /// environment variable names, see [`BackendType::from_name`], else the platform's
+    /// default. Should it fail to start, the platform's other backends are tried in turn;
+    /// [`Engine::backend`] tells which one did.
+    pub backend: Option<BackendType>,
+    /// Whether windows wait for the display's refresh to present frames. Backends that
+    /// can't present as asked fall back to one they can, see [`VsyncMode`].
+    pub vsync: VsyncMode,
+    /// Called about once a second with how the primary window's frames were paced, to
+    /// check that it keeps up with the display. Not called with [`PresentMode::External`],
+    /// where the host presents frames.
+    pub on_frame_stats: Option<Box<dyn FnMut(FrameStats)>>,
+    /// Whether windows open with the layout debugging overlay painted over their content:
+    /// each node's border box outlined, its margin tinted orange and its padding green, and
+    /// the style of the node under the pointer shown with its content tinted blue. F12
+    /// toggles it, unless [`Params::on_key`] handles the key.
+    pub debug_overlay: bool,
+    /// Whether the primary window paints what [`Engine::stats`] says in its bottom left
+    /// corner, updated with each frame.
+    pub show_stats: bool,
+    /// Called with the node that has the pointer captured and how far the pointer is from
+    /// where the capture began as it's dragged, for the drags no [`EventKind::Drag`]
+    /// listener stopped; see [`Engine::set_pointer_capture`].
+    pub on_drag: Option<Box<dyn FnMut(Id, f64, f64, DragPhase)>>,
+    /// How the primary window opens: its size, title, monitor, position and whether it
+    /// covers the monitor. Not used with [`PresentMode::External`].
+    pub window: WindowOptions,
 }
 
 impl Engine {
     /// Create a new CSS engine instance
     pub fn new() -> Self {
-        let (tx, rx): (Sender<Command>, Receiver<Command>) = channel();
-        let snapshot: Arc<RwLock<Option<RenderNode>>> = Arc::new(RwLock::new(None));
-        let snapshot_for_thread = Arc::clone(&snapshot);
+        Self::with_options(EngineOptions::default())
+    }
+
+    pub fn with_options(options: EngineOptions) -> Self {
+        let capacity = options.command_capacity.max(1);
+        let (tx, rx): (SyncSender<Command>, Receiver<Command>) = sync_channel(capacity);
+        let snapshots = commands::Snapshots::default();
+        let snapshot_for_thread = snapshots.clone();
         let message_sender = WindowMessageSender::new();
         let message_sender_for_thread = message_sender.clone();
+        let caches = options.shared_caches.unwrap_or_default();
+        let fonts = caches.fonts();
+        let resources = caches.attach(tx.clone());
+        let resources_for_thread = Arc::downgrade(&resources);
+        let diagnostics = Arc::new(Diagnostics::default());
+        let diagnostics_for_thread = Arc::clone(&diagnostics);
+        let warmup_counters = Arc::new(WarmupCounters::default());
+        let idle = IdleWarmup::new(
+            options.idle_warmup_delay,
+            Arc::clone(&warmup_counters),
+            Instant::now(),
+        );
+        let progress = Arc::new(progress::Progress::default());
+        let progress_for_thread = Arc::clone(&progress);
+        let health = Arc::new(Health::new(options.on_engine_error));
+        let health_for_thread = Arc::clone(&health);
+        let stats = Arc::new(StatsRecorder::default());
+        let stats_for_thread = Arc::clone(&stats);
 
         // Spawn thread to handle the commands without blocking the main thread
-        thread::spawn(move || {
-            commands::handle_commands(rx, snapshot_for_thread, message_sender_for_thread)
+        let data_thread = thread::spawn(move || {
+            // Commands that panic are dropped; this catches the panics outside of them.
+            let handled = panic::catch_unwind(AssertUnwindSafe(|| {
+                commands::handle_commands(
+                    rx,
+                    snapshot_for_thread,
+                    message_sender_for_thread,
+                    fonts,
+                    resources_for_thread,
+                    diagnostics_for_thread,
+                    idle,
+                    Arc::clone(&progress_for_thread),
+                    Arc::clone(&health_for_thread),
+                    capacity,
+                    stats_for_thread,
+                )
+            }));
+            if let Err(payload) = handled {
+                health_for_thread.stopped(&*payload);
+                progress_for_thread.close();
+            }
         });
 
+        let _ = tx.send(Command::SetReducedMotion(
+            accessibility::system_prefers_reduced_motion(),
+        ));
+
         Self {
-            sender: tx,
-            snapshot,
+            sender: tx.clone(),
+            snapshot: snapshots.primary,
+            window_snapshots: snapshots.windows,
+            windows: Arc::new(Mutex::new(Vec::new())),
+            next_window: Arc::new(AtomicU64::new(1)),
             root_id: Id::from_u64(0),
             running: Arc::new(Mutex::new(())),
             message_sender,
+            reduced_motion_override: Arc::new(Mutex::new(None)),
+            resources,
+            diagnostics,
+            next_generated_id: Arc::new(AtomicU64::new(Id::GENERATED_BASE)),
+            templates: Arc::new(Mutex::new(Vec::new())),
+            warmup_counters,
+            gpu_memory: Arc::new(Mutex::new(None)),
+            backend: Arc::new(Mutex::new(None)),
+            selection: Arc::new(Mutex::new(None)),
+            editing: Arc::new(Mutex::new(None)),
+            tooltips: Arc::new(Mutex::new(Tooltips::default())),
+            pointer_capture: Arc::new(Mutex::new(PointerCapture::default())),
+            affinity: ThreadAffinity::current(options.strict_threading),
+            progress,
+            callbacks: CallbackRegistry::new(),
+            health,
+            data_thread: Arc::new(DataThread::new(tx, data_thread)),
+            stats,
         }
     }
 
-    // Run the event loop
-    pub fn run(&self, params: Params) -> Result<(), Error> {
+    /// Run the event loop. Fails with [`EngineError::WrongThread`] when checked, see
+    /// [`EngineOptions::strict_threading`], and called on another thread than the one that
+    /// created the engine, and with [`EngineError::ShuttingDown`] after [`Engine::shutdown`].
+    pub fn run(&self, params: Params) -> Result<(), EngineError> {
+        self.affinity.check()?;
         // only allow running once
-        let _lock = self.running.try_lock().map_err(|_| Error::AlreadyRunning)?;
+        let _lock = self
+            .running
+            .try_lock()
+            .map_err(|_| EngineError::Reentrancy)?;
+        if self.data_thread.is_shut_down() {
+            return Err(EngineError::ShuttingDown);
+        }
 
+        let Params {
+            on_click,
+            on_hover,
+            on_key,
+            on_frame,
+            on_close_requested,
+            on_ready,
+            mut on_copy,
+            on_input,
+            mut on_resize,
+            present_mode,
+            backend,
+            vsync,
+            on_frame_stats,
+            debug_overlay,
+            show_stats,
+            on_drag,
+            window: primary_window,
+        } = params;
+        let callbacks = self.callbacks.start();
+        if on_click.is_some() {
+            callbacks.set_on_click(on_click);
+        }
+        if on_hover.is_some() {
+            callbacks.set_on_hover(on_hover);
+        }
+        if on_key.is_some() {
+            callbacks.set_on_key(on_key);
+        }
+        let callbacks1 = callbacks.clone();
+        let callbacks2 = callbacks.clone();
+        let callbacks3 = callbacks.clone();
+        let callbacks4 = callbacks;
         let this1 = self.clone();
         let this2 = self.clone();
+        let this3 = self.clone();
+        let this4 = self.clone();
+        let this5 = self.clone();
+        let this6 = self.clone();
+        let this7 = self.clone();
+        let this8 = self.clone();
+        let this9 = self.clone();
+        let this10 = self.clone();
+        let this11 = self.clone();
+        let this12 = self.clone();
+        let on_drag = Rc::new(RefCell::new(on_drag));
+        let on_release_drag = Rc::clone(&on_drag);
         let resize_sender = self.sender.clone();
+        let frame_sender = self.sender.clone();
+
+        // Layers hold images of a window's GPU context, so each window caches its own, all
+        // charged to the budget of the resource caches until the window closes.
+        let caches = self.resources.caches().clone();
+        let layer_caches: Rc<RefCell<HashMap<WindowId, LayerCache>>> = Rc::default();
+        let closed_layer_caches = Rc::clone(&layer_caches);
+        // What each window's last frames changed, to repaint only that.
+        let mut painted: HashMap<WindowId, PaintedWindow> = HashMap::new();
+        let fonts = self.resources.caches().fonts();
 
         let mut params = windowing::Params {
-            on_draw: Box::new(move |canvas| {
-                if let Some(snapshot) = this1.get_current_snapshot() {
-                    let mut painter = Painter::new(canvas);
-                    painter.paint(&snapshot);
+            on_draw: Box::new(move |window, canvas, buffer_age, debug_overlay| {
+                let snapshot = this1.get_window_snapshot(window)?;
+                let now = Instant::now();
+                let mut layer_caches = layer_caches.borrow_mut();
+                let layer_cache = layer_caches
+                    .entry(window)
+                    .or_insert_with(|| LayerCache::new(caches.clone()));
+                let primary = window == WindowId::PRIMARY;
+                let stats = (show_stats && primary).then(|| {
+                    let measurer = SkiaTextMeasurer::with_fonts(fonts.clone());
+                    stats::panel(&this1.stats(), &measurer, snapshot.tree.bounds)
+                });
+                let overlay = Overlay {
+                    selection: this1.selection_in(window),
+                    editing: this1.edited_text_in(window, now),
+                    tooltip: this1.tooltip_in(window, &snapshot, now),
+                    debug: debug_overlay,
+                    stats,
+                    scale: canvas.local_to_device_as_3x3().scale_x(),
+                };
+                let region = painted.entry(window).or_default().frame(
+                    &snapshot,
+                    overlay.clone(),
+                    buffer_age,
+                );
+                let mut painter = Painter::with_layer_cache(canvas, layer_cache, fonts.clone())
+                    .with_selection(overlay.selection)
+                    .with_editing(overlay.editing)
+                    .with_tooltip(overlay.tooltip)
+                    .with_debug_overlay(overlay.debug)
+                    .with_stats(overlay.stats)
+                    .with_clip(region);
+                let started = Instant::now();
+                painter.paint(&snapshot.tree);
+                if primary {
+                    this1.stats.record_frame(started, started.elapsed());
                 }
+                region
             }),
-            on_click: Box::new(move |x, y| {
-                if let Some(snapshot) = this2.get_current_snapshot() {
-                    let elements = snapshot.find_element_at_position(x, y);
-
-                    if let Some(ref on_click) = params.on_click {
-                        on_click(x, y, elements);
+            on_click: Box::new(move |window, x, y| {
+                if this2.tooltips.lock().unwrap().pressed(Instant::now()) {
+                    this2.message_sender.send(WindowMessage::Redraw);
+                }
+                if let Some(snapshot) = this2.get_window_snapshot(window) {
+                    this2.start_selection(window, &snapshot, x, y);
+                    this2.focus_at(window, &snapshot, x, y);
+                    let path = snapshot.find_element_at_position(x, y);
+                    let position = Some((x, y));
+                    callbacks1.dispatch(EventKind::MouseDown, &path, position, None);
+                    // After the listeners, which may capture the pointer themselves.
+                    let draggable = callbacks1.first_listening(EventKind::Drag, &path);
+                    let mut capture = this2.pointer_capture.lock().unwrap();
+                    capture.pressed(window, (x, y), draggable);
+                    drop(capture);
+                    let status = callbacks1.dispatch(EventKind::Click, &path, position, None);
+                    if let (EventStatus::Continue, Some(on_click)) = (status, callbacks1.on_click())
+                    {
+                        on_click(x, y, path);
+                    }
+                }
+            }),
+            on_resize: Box::new(move |window, width, height| {
+                let _ =
+                    resize_sender.send(Command::SetViewportSize(window.root_id(), width, height));
+                if let Some(on_resize) = on_resize.as_mut() {
+                    on_resize(width, height);
+                }
+            }),
+            on_pointer_move: Box::new(move |window, x, y| {
+                let Some(snapshot) = this9.get_window_snapshot(window) else {
+                    return Cursor::default();
+                };
+                let mut capture = this9.pointer_capture.lock().unwrap();
+                let dragged = capture.moved(window, (x, y), &snapshot.tree);
+                let path = match capture.captured(window) {
+                    Some(node) => snapshot.tree.path_to(node),
+                    None => snapshot.find_element_at_position(x, y),
+                };
+                drop(capture);
+                let cursor = windowing::cursor_on_path(&snapshot.tree, &path);
+                let titled = tooltip::title_on_path(&snapshot.tree, &path);
+                let mut tooltips = this9.tooltips.lock().unwrap();
+                if tooltips.pointer_moved(window, (x, y), titled, Instant::now()) {
+                    this9.message_sender.send(WindowMessage::Redraw);
+                }
+                drop(tooltips);
+                let status = callbacks2.dispatch(EventKind::Hover, &path, Some((x, y)), None);
+                if let (EventStatus::Continue, Some(on_hover)) = (status, callbacks2.on_hover()) {
+                    on_hover(x, y, path);
+                }
+                if let Some((node, drag)) = dragged {
+                    let tree = &snapshot.tree;
+                    Self::report_drag(&callbacks2, tree, node, (x, y), drag, &on_drag);
+                }
+                cursor
+            }),
+            on_release: Box::new(move |window, x, y| {
+                let mut capture = this11.pointer_capture.lock().unwrap();
+                let captured = capture.captured(window);
+                let dragged = capture.released(window, (x, y));
+                drop(capture);
+                if let Some(snapshot) = this11.get_window_snapshot(window) {
+                    let path = match captured {
+                        Some(node) => snapshot.tree.path_to(node),
+                        None => snapshot.find_element_at_position(x, y),
+                    };
+                    callbacks3.dispatch(EventKind::MouseUp, &path, Some((x, y)), None);
+                    if let Some((node, drag)) = dragged {
+                        let tree = &snapshot.tree;
+                        let on_drag = &on_release_drag;
+                        Self::report_drag(&callbacks3, tree, node, (x, y), drag, on_drag);
                     }
                 }
             }),
-            on_resize: Box::new(move |width, height| {
-                let _ = resize_sender.send(Command::SetViewportSize(width, height));
+            on_drag: Box::new(move |window, x, _y| this5.extend_selection(window, x)),
+            on_copy: Box::new(move |window| {
+                let Some(text) = this6.selected_text_in(window) else {
+                    return;
+                };
+                clipboard::set_text(&text);
+                if let Some(on_copy) = on_copy.as_mut() {
+                    on_copy(text);
+                }
+            }),
+            on_key: Box::new(move |window, key| {
+                let Some(edited) = this7.edit(window, &key) else {
+                    return false;
+                };
+                if let (Some((node, value)), Some(on_input)) = (edited, on_input.as_ref()) {
+                    on_input(node, value);
+                }
+                true
+            }),
+            on_key_press: Box::new(move |window, press| {
+                let path = this12.key_target_path(window);
+                let status = callbacks4.dispatch(EventKind::KeyDown, &path, None, Some(&press));
+                status == EventStatus::StopPropagation
+                    || callbacks4.on_key().is_some_and(|on_key| on_key(&press))
+            }),
+            on_pointer_leave: Box::new(move |_window| {
+                if this10.tooltips.lock().unwrap().pointer_left(Instant::now()) {
+                    this10.message_sender.send(WindowMessage::Redraw);
+                }
+            }),
+            next_redraw: Box::new(move |window| {
+                let now = Instant::now();
+                let editing = this8.editing.lock().unwrap();
+                let blink = editing
+                    .as_ref()
+                    .filter(|editing| editing.window == window)
+                    .map(|editing| editing.next_blink(now));
+                let tooltip = this8.tooltips.lock().unwrap().next_change(window, now);
+                blink.into_iter().chain(tooltip).min()
+            }),
+            on_frame: on_frame.map(|mut on_frame| -> Box<dyn FnMut(Duration)> {
+                Box::new(move |elapsed| {
+                    on_frame(elapsed);
+                    let _ = frame_sender.send(Command::Frame);
+                })
             }),
+            on_close_requested,
+            on_ready,
+            vsync,
+            debug_overlay,
+            on_frame_stats,
+            on_window_closed: Box::new(move |window| {
+                closed_layer_caches.borrow_mut().remove(&window);
+                this3.forget_window(window);
+            }),
+            windows: Box::new(move || this4.windows.lock().unwrap().clone()),
+            primary_window,
         };
 
-        windowing::run(&mut params, self.message_sender.clone())
-            .map_err(|err| Error::UnknownError(err.to_string()))?;
+        if present_mode == PresentMode::External {
+            external::attach(self.presenter_key(), params, Arc::clone(&self.gpu_memory));
+            return Ok(());
+        }
 
+        let result = windowing::run(
+            &mut params,
+            backend,
+            self.message_sender.clone(),
+            Arc::clone(&self.gpu_memory),
+            Arc::clone(&self.backend),
+        );
+        self.callbacks.stop();
+        result.map_err(|err| EngineError::window(WindowErrorKind::EventLoop, err))
+    }
+
+    /// Replace the handler of clicks, set by [`Params::on_click`], from the next click on;
+    /// `None` stops calling one. Set before [`Engine::run`], it's kept for the next run on
+    /// this thread; handlers are dropped when the run ends. Like the `Params` callbacks, they
+    /// belong to the thread that runs the engine, so this fails with
+    /// [`EngineError::WrongThread`] on another one where that's checked, see
+    /// [`EngineOptions::strict_threading`]. A handler may replace handlers.
+    pub fn set_on_click(&self, on_click: Option<PointerHandler>) -> Result<(), EngineError> {
+        self.affinity.check()?;
+        self.callbacks.current().set_on_click(on_click);
         Ok(())
     }
 
+    /// Replace the handler of pointer moves, set by [`Params::on_hover`], like
+    /// [`Engine::set_on_click`] does for clicks.
+    pub fn set_on_hover(&self, on_hover: Option<PointerHandler>) -> Result<(), EngineError> {
+        self.affinity.check()?;
+        self.callbacks.current().set_on_hover(on_hover);
+        Ok(())
+    }
+
+    /// Replace the handler of key presses, set by [`Params::on_key`], like
+    /// [`Engine::set_on_click`] does for clicks.
+    pub fn set_on_key(&self, on_key: Option<KeyHandler>) -> Result<(), EngineError> {
+        self.affinity.check()?;
+        self.callbacks.current().set_on_key(on_key);
+        Ok(())
+    }
+
+    /// Call `listener` for each event of `kind` whose target is the node or a node in it,
+    /// from the topmost node under the pointer up through its ancestors, until a listener
+    /// returns [`EventStatus::StopPropagation`]. Events no listener stopped then reach the
+    /// handler of their kind, set by [`Engine::set_on_click`] and the like. Listeners
+    /// belong to the run like handlers do, see [`Engine::set_on_click`], and stay until
+    /// removed or it ends, even when the node goes.
+    pub fn add_event_listener(
+        &self,
+        id: Id,
+        kind: EventKind,
+        listener: EventListener,
+    ) -> Result<ListenerId, EngineError> {
+        self.affinity.check()?;
+        Ok(self.callbacks.current().add_listener(id, kind, listener))
+    }
+
+    /// Stop calling a listener [`Engine::add_event_listener`] added. Returns whether it was
+    /// still registered.
+    pub fn remove_event_listener(&self, listener: ListenerId) -> Result<bool, EngineError> {
+        self.affinity.check()?;
+        Ok(self.callbacks.current().remove_listener(listener))
+    }
+
+    /// Send the pointer's moves and the button's release to the node wherever the pointer
+    /// is, as events targeting it, until the button goes up or
+    /// [`Engine::release_pointer_capture`]. As the pointer moves the node is dragged, see
+    /// [`Params::on_drag`], measured from where the button went down when called from a
+    /// [`EventKind::MouseDown`] listener, else from where the pointer moves first. The button
+    /// going down over a node with an [`EventKind::Drag`] listener captures the pointer for it
+    /// anyway. The node leaving the window's tree, or getting `display: none`, releases the
+    /// capture and ends its drag. Returns whether a window shows the node as of the latest
+    /// layout, which it must to capture the pointer.
+    pub fn set_pointer_capture(&self, id: Id) -> bool {
+        let mut windows = std::iter::once(WindowId::PRIMARY).chain(self.windows());
+        let shown_in = windows.find(|&window| {
+            self.get_window_snapshot(window)
+                .is_some_and(|snapshot| !snapshot.tree.path_to(id).is_empty())
+        });
+        let Some(window) = shown_in else {
+            return false;
+        };
+        self.pointer_capture.lock().unwrap().capture(window, id);
+        true
+    }
+
+    /// Release the pointer a node captured, without ending its drag. Returns whether one had
+    /// it.
+    pub fn release_pointer_capture(&self) -> bool {
+        self.pointer_capture.lock().unwrap().release()
+    }
+
+    /// Call the drag listeners of `node` and its ancestors in `tree`, and `on_drag` unless
+    /// one of them stopped the drag.
+    fn report_drag(
+        callbacks: &Callbacks,
+        tree: &RenderNode,
+        node: Id,
+        position: (f64, f64),
+        drag: Drag,
+        on_drag: &RefCell<Option<Box<dyn FnMut(Id, f64, f64, DragPhase)>>>,
+    ) {
+        let path = tree.path_to(node);
+        if callbacks.dispatch_drag(&path, position, drag) == EventStatus::Continue {
+            if let Some(on_drag) = on_drag.borrow_mut().as_mut() {
+                on_drag(node, drag.dx, drag.dy, drag.phase);
+            }
+        }
+    }
+
+    /// Stop the data thread once it has handled the changes sent before, and wait until it
+    /// exited. The engine takes no changes afterwards: the `try_` methods fail with
+    /// [`EngineError::ShuttingDown`], the others panic, and the snapshot stays as it was.
+    /// Dropping the last clone of the engine does the same.
+    ///
+    /// Fails with [`EngineError::Reentrancy`] while [`Engine::run`] or a headless layout is
+    /// running, as they need the thread; [`Engine::quit`] ends `run` first. Fails with
+    /// [`EngineError::ShuttingDown`] when shut down already. Called from
+    /// [`EngineOptions::on_engine_error`], the thread exits after the handler returns.
+    pub fn shutdown(&self) -> Result<(), EngineError> {
+        let _lock = self
+            .running
+            .try_lock()
+            .map_err(|_| EngineError::Reentrancy)?;
+        self.data_thread.shut_down()
+    }
+
+    /// Close every window opened by [`Engine::run`] from any thread, making `run` return
+    /// `Ok`. Does nothing while no window is open.
+    ///
+    /// With [`PresentMode::External`], called on the thread that called `run`, this stops
+    /// presenting and releases the offscreen target instead.
+    pub fn quit(&self) {
+        if external::detach(self.presenter_key()) {
+            self.callbacks.stop();
+            return;
+        }
+        self.message_sender.send(WindowMessage::Quit);
+    }
+
+    /// Render a frame of the primary window into an offscreen texture of the graphics
+    /// context current on this thread, for the host to composite and present. The engine
+    /// must run with [`PresentMode::External`] on this thread.
+    ///
+    /// The texture stays valid until the next call. Rendering changes the context's state,
+    /// so the host sets up what it draws with afterwards. Fails with
+    /// [`EngineError::RenderFailed`] when no target can be created, e.g. without a current
+    /// context or on platforms other than Linux, where only OpenGL is supported so far.
+    pub fn render_frame(&self) -> Result<FrameHandle, EngineError> {
+        external::render_frame(self.presenter_key())
+    }
+
+    /// Set the size of frames from [`Engine::render_frame`] in logical pixels, and how many
+    /// device pixels make one. Frames are 800 by 800 at a scale of 1 until this is called.
+    /// Does nothing unless the engine runs with [`PresentMode::External`] on this thread.
+    pub fn set_frame_size(&self, width: f64, height: f64, scale_factor: f64) {
+        external::set_frame_size(self.presenter_key(), width, height, scale_factor);
+    }
+
+    /// Handle pointer input on a window as if the user gave it, in the window's logical
+    /// pixels. This is how input reaches the engine with [`PresentMode::External`], called
+    /// on the thread that called `run`; input for windows that aren't open is dropped.
+    pub fn inject_input(&self, window: WindowId, input: PointerInput) {
+        if window == WindowId::PRIMARY && external::inject_input(self.presenter_key(), input) {
+            return;
+        }
+        self.message_sender
+            .send(WindowMessage::Input(window, input));
+    }
+
+    /// Make the primary window cover its monitor without decorations, or a window again
+    /// with the size it had before. The swapchain follows the size the window gets. Does
+    /// nothing unless [`Engine::run`] opened the window; see [`Params::window`] for opening
+    /// it that way, or in [`FullscreenMode::Exclusive`].
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        let mode = fullscreen.then_some(FullscreenMode::Borderless);
+        self.message_sender
+            .send(WindowMessage::SetFullscreen(WindowId::PRIMARY, mode));
+    }
+
+    /// Move the primary window's outer top left corner to `x`, `y` on the desktop, in
+    /// physical pixels. Does nothing unless [`Engine::run`] opened the window, or where the
+    /// platform doesn't let windows place themselves, as on Wayland.
+    pub fn set_window_position(&self, x: i32, y: i32) {
+        self.message_sender
+            .send(WindowMessage::SetPosition(WindowId::PRIMARY, (x, y)));
+    }
+
+    /// Identifies the engine, and its clones, to the thread that presents it externally.
+    fn presenter_key(&self) -> usize {
+        Arc::as_ptr(&self.running) as usize
+    }
+
+    /// Open another window, right away if [`Engine::run`] is running and otherwise when it
+    /// starts. The window shows the tree under [`WindowId::root_id`], styled by the engine's
+    /// stylesheets; nodes are attached to it with [`Engine::set_parent`] as usual.
+    pub fn create_window(&self, options: WindowOptions) -> WindowId {
+        let window = WindowId::secondary(self.next_window.fetch_add(1, Ordering::Relaxed));
+        self.send(Command::CreateWindow(window.root_id()))
+            .expect("data thread down");
+        self.windows.lock().unwrap().push((window, options.clone()));
+        self.message_sender
+            .send(WindowMessage::OpenWindow(window, options));
+        window
+    }
+
+    /// Close a window. The tree of a secondary window is destroyed along with it; closing
+    /// the last open window ends [`Engine::run`].
+    pub fn close_window(&self, window: WindowId) {
+        self.message_sender.send(WindowMessage::CloseWindow(window));
+        // Also when no event loop runs, so the window doesn't open once one does.
+        self.forget_window(window);
+    }
+
+    /// The secondary windows that are open, or will open once [`Engine::run`] starts.
+    pub fn windows(&self) -> Vec<WindowId> {
+        let windows = self.windows.lock().unwrap();
+        windows.iter().map(|(window, _)| *window).collect()
+    }
+
+    /// Destroy the tree of a secondary window that closed.
+    fn forget_window(&self, window: WindowId) {
+        let mut windows = self.windows.lock().unwrap();
+        let count = windows.len();
+        windows.retain(|(open, _)| *open != window);
+        if windows.len() < count {
+            let _ = self.send(Command::CloseWindow(window.root_id()));
+        }
+    }
+
     /// Add a CSS stylesheet
     pub fn add_stylesheet(&self, css_content: &str) {
-        let _ = self
-            .sender
-            .send(Command::AddStylesheet(css_content.to_string()))
+        self.try_add_stylesheet(css_content)
             .expect("data thread down");
     }
 
+    /// Like [`Engine::add_stylesheet`], but fails with [`EngineError::ShuttingDown`] instead of
+    /// panicking.
+    pub fn try_add_stylesheet(&self, css_content: &str) -> Result<(), EngineError> {
+        self.send(Command::AddStylesheet(css_content.to_string()))
+    }
+
     /// Create a new document node with optional text content
     pub fn create_node(&self, id: Id, text: Option<String>) -> Id {
-        self.sender
-            .send(Command::CreateNode(id, text))
-            .expect("data thread down");
-        id
+        self.try_create_node(id, text).expect("data thread down")
+    }
+
+    /// Like [`Engine::create_node`], but fails with [`EngineError::ShuttingDown`] instead of
+    /// panicking. A node that already exists is reported as a diagnostic and kept as it is.
+    pub fn try_create_node(&self, id: Id, text: Option<String>) -> Result<Id, EngineError> {
+        self.send(Command::CreateNode(id, text, None))?;
+        Ok(id)
+    }
+
+    /// Create a node with an id the engine generates, which no other node has or gets.
+    /// Ids from [`Engine::create_node_with_id`] can be mixed with these freely.
+    pub fn create_node_auto(&self) -> Id {
+        self.create_node(self.generate_id(), None)
+    }
+
+    /// Create a node with an id the host picked, and wait until it was. Fails with
+    /// [`DocumentErrorKind::IdInUse`] if another node has the id or if it's one the engine
+    /// generates, see [`Id::GENERATED_BASE`].
+    pub fn create_node_with_id(&self, id: Id) -> Result<Id, EngineError> {
+        if id.is_generated() {
+            return Err(IdInUse(id).into());
+        }
+        let (reply_tx, reply_rx) = channel();
+        self.send(Command::CreateNode(id, None, Some(reply_tx)))?;
+        let result = reply_rx.recv().map_err(|_| EngineError::ShuttingDown)?;
+        result.map_err(EngineError::from)?;
+        Ok(id)
+    }
+
+    fn generate_id(&self) -> Id {
+        Id::from_u64(self.next_generated_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Create an element named `tag`, which type selectors like `button { … }` match, in any
+    /// case. Nodes from [`Engine::create_node`] have no tag.
+    pub fn create_element(&self, id: Id, tag: &str, text: Option<String>) -> Id {
+        self.try_create_element(id, tag, text)
+            .expect("data thread down")
+    }
+
+    /// Like [`Engine::create_element`], but fails with [`EngineError::ShuttingDown`] instead
+    /// of panicking.
+    pub fn try_create_element(
+        &self,
+        id: Id,
+        tag: &str,
+        text: Option<String>,
+    ) -> Result<Id, EngineError> {
+        self.send(Command::CreateElement(id, tag.to_owned(), text))?;
+        Ok(id)
     }
 
     /// Set a parent-child relationship between nodes
     pub fn set_parent(&self, parent_id: Id, child_id: Id) {
-        self.sender
-            .send(Command::SetParent(parent_id, child_id))
+        self.try_set_parent(parent_id, child_id)
             .expect("data thread down");
     }
 
-    /// Set an attribute on a node
+    /// Like [`Engine::set_parent`], but fails with [`EngineError::ShuttingDown`] instead of
+    /// panicking. A parent or child that doesn't exist, or a cycle, is reported as a
+    /// diagnostic and the change ignored.
+    pub fn try_set_parent(&self, parent_id: Id, child_id: Id) -> Result<(), EngineError> {
+        self.send(Command::SetParent(parent_id, child_id))
+    }
+
+    /// Put `child_id` under `parent_id` right before `reference_id`, which must be a child of
+    /// the parent, moving it there if it's already a child. The child is laid out, painted
+    /// and hit tested in its new place among its siblings.
+    pub fn insert_before(
+        &self,
+        parent_id: Id,
+        child_id: Id,
+        reference_id: Id,
+    ) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Command::InsertBefore(
+            parent_id,
+            child_id,
+            reference_id,
+            reply_tx,
+        ))?;
+        let result = reply_rx.recv().map_err(|_| EngineError::ShuttingDown)?;
+        result.map_err(|err| err.for_node(child_id))
+    }
+
+    /// Move `child_id`, a child of `parent_id`, to `new_index` among the parent's children, or
+    /// after the last one if `new_index` is past the end.
+    pub fn move_child(
+        &self,
+        parent_id: Id,
+        child_id: Id,
+        new_index: usize,
+    ) -> Result<(), EngineError> {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Command::MoveChild(parent_id, child_id, new_index, reply_tx))?;
+        let result = reply_rx.recv().map_err(|_| EngineError::ShuttingDown)?;
+        result.map_err(|err| err.for_node(child_id))
+    }
+
+    /// Replace the text of a node, making it a text node if it wasn't; `None` makes it a
+    /// plain container that's sized like one. Also replaces the value being edited if the
+    /// node has focus.
+    pub fn set_text(&self, node_id: Id, text: Option<String>) {
+        self.try_set_text(node_id, text).expect("data thread down");
+    }
+
+    /// Like [`Engine::set_text`], but fails with [`EngineError::ShuttingDown`] instead of
+    /// panicking.
+    pub fn try_set_text(&self, node_id: Id, text: Option<String>) -> Result<(), EngineError> {
+        if let Some(editing) = self.editing.lock().unwrap().as_mut() {
+            if editing.node == node_id {
+                editing.set_text(text.clone().unwrap_or_default());
+            }
+        }
+        self.send(Command::SetText(node_id, text))
+    }
+
+    /// Set an attribute on a node. The `style` attribute sets its inline style, declarations
+    /// like `width: 10px; color: red`, which override those of the rules.
     pub fn set_attribute(&self, node_id: Id, key: String, value: String) {
+        self.try_set_attribute(node_id, key, value)
+            .expect("data thread down");
+    }
+
+    /// Like [`Engine::set_attribute`], but fails with [`EngineError::ShuttingDown`] instead of
+    /// panicking.
+    pub fn try_set_attribute(
+        &self,
+        node_id: Id,
+        key: String,
+        value: String,
+    ) -> Result<(), EngineError> {
+        self.send(Command::SetAttribute(node_id, key, value))
+    }
+
+    /// Like [`Engine::try_set_attribute`], but fails with [`EngineError::Busy`] instead of
+    /// waiting when [`EngineOptions::command_capacity`] changes are queued already, for
+    /// hosts that would rather drop an update, e.g. of an animation, than wait. Updates of
+    /// the same attribute that queue up are applied as the last one.
+    pub fn try_set_attribute_nonblocking(
+        &self,
+        node_id: Id,
+        key: String,
+        value: String,
+    ) -> Result<(), EngineError> {
         self.sender
-            .send(Command::SetAttribute(node_id, key, value))
+            .try_send(Command::SetAttribute(node_id, key, value))
+            .map_err(|err| match err {
+                TrySendError::Full(_) => EngineError::Busy,
+                TrySendError::Disconnected(_) => EngineError::ShuttingDown,
+            })
+    }
+
+    /// Hand a command to the data thread, once it has room for it. On the data thread itself,
+    /// e.g. in [`EngineOptions::on_engine_error`], it's set aside for the thread to take
+    /// next instead, as waiting for room would wait for itself. Methods that wait for an
+    /// answer still can't be called there.
+    fn send(&self, command: Command) -> Result<(), EngineError> {
+        self.data_thread.send(command)
+    }
+
+    /// Create nodes under the root from XML-like markup such as
+    /// `<div class="row"><span id="title">Hello</span><img src="logo.png"/></div>`, and return
+    /// the ids of the top-level ones. See [`Engine::load_markup_into`].
+    pub fn load_markup(&self, markup: &str) -> Result<Vec<Id>, MarkupError> {
+        self.load_markup_into(self.root_id, markup)
+    }
+
+    /// Create nodes under `parent` from markup and return the ids of the top-level ones.
+    ///
+    /// Each element becomes an element named by its tag, as [`Engine::create_element`]
+    /// makes, with every attribute copied as is; a `style` attribute sets its inline style.
+    /// Elements may close themselves, like `<img/>`. An element containing only text becomes
+    /// a text node; text next to elements becomes extra text nodes. Text is trimmed with
+    /// whitespace collapsed, and whitespace-only text is dropped. Values may use the five
+    /// XML entities; see [`escape_markup`].
+    ///
+    /// Nothing is created when the markup is malformed. Ids are generated in document order,
+    /// see [`Id::GENERATED_BASE`].
+    pub fn load_markup_into(&self, parent: Id, markup: &str) -> Result<Vec<Id>, MarkupError> {
+        let nodes = markup::parse_markup(markup)?;
+        Ok(nodes
+            .iter()
+            .map(|node| self.create_markup_node(parent, node))
+            .collect())
+    }
+
+    /// The document as markup that [`Engine::load_markup`] loads back: its elements with
+    /// their tag, their attributes in order of name and their text, one a line and indented
+    /// by depth, as of the commands sent so far. The same document always gives the same
+    /// markup. Nodes created without a tag are `<node>` elements, or bare text if they have
+    /// text and no children.
+    pub fn dump_document(&self) -> String {
+        self.ask(Command::DumpDocument)
+    }
+
+    /// Define a fragment to instantiate many times, such as the row of a long list.
+    ///
+    /// Instances reuse the layout of the fragment at its assumed width: when an instance is
+    /// laid out at that size, only the text of its slots is measured, and the rest of its
+    /// subtree is copied from the template. An instance whose slots' text takes another size,
+    /// or that is laid out at another width, is laid out in full and looks the same. See
+    /// [`Engine::template_stats`].
+    pub fn define_template(&self, fragment: &Fragment) -> TemplateId {
+        let mut templates = self.templates.lock().unwrap();
+        let id = TemplateId(templates.len());
+        templates.push(fragment.clone());
+        self.send(Command::DefineTemplate(id, fragment.clone()))
+            .expect("data thread down");
+        id
+    }
+
+    /// Create nodes under `parent` from a template, like [`Engine::load_markup_into`] does
+    /// from markup, and return the ids of the top-level ones. `substitutions` pairs slot
+    /// names with the text to show in them instead of the fragment's.
+    ///
+    /// Changing an instance's nodes later is fine; the instance is then laid out like any
+    /// other subtree. Returns `None` for a template of another engine.
+    pub fn instantiate_template(
+        &self,
+        template: TemplateId,
+        parent: Id,
+        substitutions: &[(&str, &str)],
+    ) -> Option<Vec<Id>> {
+        let nodes = self
+            .templates
+            .lock()
+            .unwrap()
+            .get(template.0)?
+            .substituted(substitutions);
+        let ids: Vec<Id> = nodes
+            .iter()
+            .map(|node| self.create_markup_node(parent, node))
+            .collect();
+        self.send(Command::AddTemplateInstances(template, ids.clone()))
+            .expect("data thread down");
+        Some(ids)
+    }
+
+    /// How often template instances reused their template's layout, as of the commands
+    /// sent so far.
+    pub fn template_stats(&self) -> TemplateStats {
+        let (reply_tx, reply_rx) = channel();
+        self.send(Command::TemplateStats(reply_tx))
+            .expect("data thread down");
+        reply_rx.recv().expect("data thread down")
+    }
+
+    /// Ids of the node's children, in order, as of the commands sent so far. Empty for
+    /// unknown nodes.
+    pub fn children(&self, id: Id) -> Vec<Id> {
+        self.ask(|reply| Command::Children(id, reply))
+    }
+
+    /// The node's parent, `None` for unknown and detached nodes and for roots.
+    pub fn parent(&self, id: Id) -> Option<Id> {
+        self.ask(|reply| Command::Parent(id, reply))
+    }
+
+    /// Ids of the node's descendants in document order, each node before its children.
+    /// Empty for unknown nodes.
+    pub fn descendants(&self, id: Id) -> Vec<Id> {
+        self.ask(|reply| Command::Descendants(id, reply))
+    }
+
+    /// Whether `node` is `ancestor` or one of its descendants. `false` if either is unknown.
+    pub fn contains(&self, ancestor: Id, node: Id) -> bool {
+        self.ask(|reply| Command::Contains(ancestor, node, reply))
+    }
+
+    /// Ids of the nodes `selector` matches, in document order, each node before its
+    /// children: the document's, then those of secondary windows. Takes the selectors
+    /// stylesheets do, like `button.primary` or `[data-state="open"]`, and descendant
+    /// combinators, like `.list .item`. A selector that isn't one matches nothing, and is
+    /// reported as a diagnostic.
+    pub fn query(&self, selector: &str) -> Vec<Id> {
+        self.query_up_to(selector, usize::MAX)
+    }
+
+    /// The first node [`Engine::query`] would find.
+    pub fn query_one(&self, selector: &str) -> Option<Id> {
+        self.query_up_to(selector, 1).pop()
+    }
+
+    fn query_up_to(&self, selector: &str, limit: usize) -> Vec<Id> {
+        let Some(parsed) = QuerySelector::parse(selector) else {
+            self.diagnostics
+                .report(DiagnosticCode::CssSyntaxError, None, None, || {
+                    format!("query({selector:?}): not a selector")
+                });
+            return Vec::new();
+        };
+        self.ask(|reply| Command::Query(parsed, limit, reply))
+    }
+
+    fn ask<T>(&self, command: impl FnOnce(Sender<T>) -> Command) -> T {
+        let (reply_tx, reply_rx) = channel();
+        self.send(command(reply_tx)).expect("data thread down");
+        reply_rx.recv().expect("data thread down")
+    }
+
+    fn create_markup_node(&self, parent: Id, node: &MarkupNode) -> Id {
+        let id = self.generate_id();
+
+        match node {
+            MarkupNode::Element {
+                tag,
+                attributes,
+                text,
+                children,
+            } => {
+                self.create_element(id, tag, text.clone());
+                self.set_parent(parent, id);
+                for (key, value) in attributes {
+                    self.set_attribute(id, key.clone(), value.clone());
+                }
+                for child in children {
+                    self.create_markup_node(id, child);
+                }
+            }
+            MarkupNode::Text(text) => {
+                self.create_node(id, Some(text.clone()));
+                self.set_parent(parent, id);
+            }
+        }
+
+        id
+    }
+
+    /// Whether reduced motion is in effect: the override from
+    /// [`Engine::set_reduced_motion`] if set, otherwise the platform preference.
+    pub fn prefers_reduced_motion(&self) -> bool {
+        self.reduced_motion_override
+            .lock()
+            .unwrap()
+            .unwrap_or_else(accessibility::system_prefers_reduced_motion)
+    }
+
+    /// Override the platform reduced-motion preference; `None` follows the platform again.
+    /// Re-evaluates `@media (prefers-reduced-motion)` rules on the next layout.
+    pub fn set_reduced_motion(&self, reduce: Option<bool>) {
+        *self.reduced_motion_override.lock().unwrap() = reduce;
+        self.send(Command::SetReducedMotion(self.prefers_reduced_motion()))
             .expect("data thread down");
     }
 
+    /// The color of the outline the focused node gets unless its style sets another, 2px wide
+    /// outside its border box; `rgb(0, 95, 204)` by default. Rules for `:focus` with
+    /// `outline: none` remove it.
+    pub fn set_focus_accent_color(&self, accent: Rgba) {
+        self.send(Command::SetFocusAccentColor(accent))
+            .expect("data thread down");
+    }
+
+    /// Make a TrueType/OpenType font available to this engine and every engine sharing its
+    /// caches, under `family` or the name stored in the font. Registered families are
+    /// resolved before installed ones, and registering a family again replaces its font. Text
+    /// is re-measured on the next layout of each of them. Returns the registered family name.
+    pub fn register_font(&self, family: Option<&str>, data: &[u8]) -> Result<String, EngineError> {
+        self.resources.register_font(family, data).ok_or_else(|| {
+            self.diagnostics
+                .report(DiagnosticCode::InvalidFontData, None, None, || {
+                    format!("font data ({} bytes) couldn't be loaded", data.len())
+                });
+            EngineError::InvalidFont
+        })
+    }
+
+    /// [`Engine::register_font`] with the font file at `path`.
+    pub fn register_font_file(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        family: Option<&str>,
+    ) -> Result<String, EngineError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path).map_err(|err| {
+            self.diagnostics
+                .report(DiagnosticCode::InvalidFontData, None, None, || {
+                    format!("font file `{}` couldn't be read: {err}", path.display())
+                });
+            EngineError::Io(err)
+        })?;
+        self.register_font(family, &data)
+    }
+
+    /// Get a decoded image from the resource caches, calling `load` for the encoded bytes
+    /// only if no engine sharing the caches has decoded `key` yet.
+    pub fn load_image(
+        &self,
+        key: &str,
+        load: impl FnOnce() -> Option<Vec<u8>>,
+    ) -> Option<ImageHandle> {
+        self.resources
+            .image(key, load)
+            .map_err(|err| match err {
+                resources::ImageError::Load => {
+                    self.diagnostics
+                        .report(DiagnosticCode::ImageLoadFailed, None, None, || {
+                            format!("no data for image `{key}`")
+                        })
+                }
+                resources::ImageError::Decode => {
+                    self.diagnostics
+                        .report(DiagnosticCode::ImageDecodeFailed, None, None, || {
+                            format!("image `{key}` couldn't be decoded")
+                        })
+                }
+            })
+            .ok()
+    }
+
+    /// Receive problems the engine detects from now on: stylesheet errors, mutations of
+    /// unknown nodes, invalid parents, images and fonts that fail to load. Repeats of the same
+    /// problem are rate-limited. Diagnostics are only built while a receiver is alive, and
+    /// dropping the receiver unsubscribes it.
+    pub fn subscribe_diagnostics(&self) -> Receiver<Diagnostic> {
+        self.diagnostics.subscribe()
+    }
+
+    /// Whether the data thread still applies changes, or caught panics to do so, or stopped.
+    /// See also [`EngineOptions::on_engine_error`].
+    pub fn health(&self) -> EngineHealth {
+        self.health.get()
+    }
+
+    /// How long the last [`STATS_SAMPLES`] layout passes took, and painting the last frames
+    /// of the primary window, with the frames per second they came at. Layout passes are
+    /// timed on the data thread, headless ones too; frames only while [`Engine::run`] draws.
+    pub fn stats(&self) -> EngineStats {
+        self.stats.stats()
+    }
+
+    /// Cache usage attributed to this engine.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.resources.stats()
+    }
+
+    /// Work done ahead of layout while the engine was idle; see
+    /// [`EngineOptions::idle_warmup_delay`].
+    pub fn warmup_stats(&self) -> WarmupStats {
+        self.warmup_counters.stats()
+    }
+
+    /// GPU memory use of the window opened by [`Engine::run`] as of its last frame or
+    /// resize, or `None` while no window is open.
+    pub fn gpu_memory_stats(&self) -> Option<GpuMemoryStats> {
+        *self.gpu_memory.lock().unwrap()
+    }
+
+    /// The backend the primary window of [`Engine::run`] renders with, see
+    /// [`Params::backend`]. `None` until it opened, and with [`PresentMode::External`].
+    pub fn backend(&self) -> Option<BackendType> {
+        *self.backend.lock().unwrap()
+    }
+
+    /// The text node the user selected text in by dragging across it, and the selected
+    /// characters, as indices of `char`s in its text. `None` while nothing is selected.
+    ///
+    /// Dragging selects within the node pressed on; it must be selectable, see
+    /// [`UserSelect`]. Pressing anywhere else clears the selection.
+    pub fn get_selection(&self) -> Option<(Id, Range<usize>)> {
+        let selection = (*self.selection.lock().unwrap())?;
+        let range = selection.range();
+        (!range.is_empty()).then_some((selection.node, range))
+    }
+
+    /// The selected text as of the latest layout, if any; see [`Engine::get_selection`].
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = (*self.selection.lock().unwrap())?;
+        self.selected_text_in(selection.window)
+    }
+
+    /// Start selecting at the point if it's on selectable text, and clear any selection.
+    pub(crate) fn start_selection(&self, window: WindowId, snapshot: &RenderNode, x: f64, y: f64) {
+        let selection = selection::selectable_text_at(snapshot, x, y).map(|node| {
+            let index = self.char_index_at(node, x);
+            Selection {
+                window,
+                node: node.id,
+                anchor: index,
+                focus: index,
+            }
+        });
+
+        let mut current = self.selection.lock().unwrap();
+        if *current != selection {
+            let had_range = current.is_some_and(|current| !current.range().is_empty());
+            *current = selection;
+            if had_range {
+                self.message_sender.send(WindowMessage::Redraw);
+            }
+        }
+    }
+
+    /// Move the end of the selection being dragged in the window to `x`.
+    pub(crate) fn extend_selection(&self, window: WindowId, x: f64) {
+        let Some(mut selection) = *self.selection.lock().unwrap() else {
+            return;
+        };
+        let Some(snapshot) = self.get_window_snapshot(window) else {
+            return;
+        };
+        let Some(node) = selection::find_node(&snapshot, selection.node) else {
+            return;
+        };
+        if selection.window != window {
+            return;
+        }
+
+        selection.focus = self.char_index_at(node, x);
+        let mut current = self.selection.lock().unwrap();
+        if *current != Some(selection) {
+            *current = Some(selection);
+            self.message_sender.send(WindowMessage::Redraw);
+        }
+    }
+
+    /// Focus the editable node at the point, with the caret at the character boundary
+    /// nearest to it, or take focus away from the one that has it.
+    pub(crate) fn focus_at(&self, window: WindowId, snapshot: &IndexedTree, x: f64, y: f64) {
+        let path = snapshot.find_element_at_position(x, y);
+        let node = path
+            .iter()
+            .find_map(|id| selection::find_node(snapshot, *id).filter(|node| node.editable));
+
+        let mut editing = self.editing.lock().unwrap();
+        let now = Instant::now();
+        let was_focused = editing.as_ref().map(|editing| editing.node);
+        *editing = match (editing.take(), node) {
+            (_, None) => None,
+            // Keep the value typed since the snapshot was laid out.
+            (Some(mut focused), Some(node))
+                if focused.window == window && focused.node == node.id =>
+            {
+                focused.caret = self
+                    .char_index_at(node, x)
+                    .min(focused.text.chars().count());
+                focused.moved_at = now;
+                Some(focused)
+            }
+            (_, Some(node)) => {
+                let text = node.text.clone().unwrap_or_default();
+                let caret = self.char_index_at(node, x);
+                Some(Editing::new(window, node.id, text, caret, now))
+            }
+        };
+        let focused = editing.as_ref().map(|editing| editing.node);
+        if focused != was_focused {
+            let _ = self.send(Command::SetFocus(focused));
+        }
+        self.message_sender.send(WindowMessage::Redraw);
+    }
+
+    /// Where a key pressed in the window goes: the focused node and its ancestors, or the
+    /// window's root without one.
+    fn key_target_path(&self, window: WindowId) -> Vec<Id> {
+        let focused = self
+            .editing
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|editing| editing.window == window)
+            .map(|editing| editing.node);
+        let path = self
+            .get_window_snapshot(window)
+            .zip(focused)
+            .map(|(snapshot, focused)| snapshot.tree.path_to(focused))
+            .unwrap_or_default();
+        if path.is_empty() {
+            vec![window.root_id()]
+        } else {
+            path
+        }
+    }
+
+    /// Apply a key to the focused editable node in the window. `None` if there is none, and
+    /// otherwise the node and its new value if the key changed it.
+    pub(crate) fn edit(&self, window: WindowId, key: &EditKey) -> Option<Option<(Id, String)>> {
+        let mut editing = self.editing.lock().unwrap();
+        let editing = editing
+            .as_mut()
+            .filter(|editing| editing.window == window)?;
+        if !editing.apply(key, Instant::now()) {
+            return Some(None);
+        }
+
+        let _ = self.send(Command::SetText(editing.node, Some(editing.text.clone())));
+        Some(Some((editing.node, editing.text.clone())))
+    }
+
+    /// What to paint for the focused editable node if it's in the window.
+    fn edited_text_in(&self, window: WindowId, now: Instant) -> Option<editing::EditedText> {
+        let editing = self.editing.lock().unwrap();
+        let editing = editing
+            .as_ref()
+            .filter(|editing| editing.window == window)?;
+        Some(editing.painted(now))
+    }
+
+    /// The tooltip to paint over the window's tree, if one is shown.
+    fn tooltip_in(
+        &self,
+        window: WindowId,
+        snapshot: &IndexedTree,
+        now: Instant,
+    ) -> Option<tooltip::Tooltip> {
+        let tooltips = self.tooltips.lock().unwrap();
+        let (title, position) = tooltips.shown(window, now)?;
+        let style = snapshot.tooltip_style.clone();
+        let measurer = SkiaTextMeasurer::with_fonts(self.resources.caches().fonts());
+        let size = measurer.measure_unwrapped(title, &text::FontSpec::from_style(&style));
+        let text_size = (size.width, size.height);
+        let bounds = snapshot.tree.bounds;
+        let tooltip = tooltip::Tooltip::place(title.to_owned(), text_size, style, position, bounds);
+        Some(tooltip)
+    }
+
+    /// The character boundary of the node's text nearest to `x`, as painted.
+    fn char_index_at(&self, node: &RenderNode, x: f64) -> usize {
+        let measurer = SkiaTextMeasurer::with_fonts(self.resources.caches().fonts());
+        let font = text::FontSpec::from_style(&node.style);
+        let text = node.text.as_deref().unwrap_or_default();
+        let origin = selection::text_origin_x(&measurer, node, text);
+        selection::char_index_at(&measurer, text, &font, x - origin)
+    }
+
+    /// The selection to highlight in the window.
+    fn selection_in(&self, window: WindowId) -> Option<(Id, Range<usize>)> {
+        self.get_selection().filter(|_| {
+            self.selection
+                .lock()
+                .unwrap()
+                .is_some_and(|s| s.window == window)
+        })
+    }
+
+    fn selected_text_in(&self, window: WindowId) -> Option<String> {
+        let selection = (*self.selection.lock().unwrap()).filter(|s| s.window == window)?;
+        let snapshot = self.get_window_snapshot(window)?;
+        let node = selection::find_node(&snapshot, selection.node)?;
+        let text = selection.text_of(node.text.as_deref()?);
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Visible text of the document, or of the subtree under `root`, in reading order; one
+    /// run per text node. Reflects the latest layout snapshot.
+    pub fn extract_text(&self, root: Option<Id>) -> Vec<TextRun> {
+        self.extract_text_with(root, TextGranularity::Node)
+    }
+
+    /// Like [`Engine::extract_text`], with runs split according to `granularity`.
+    pub fn extract_text_with(
+        &self,
+        root: Option<Id>,
+        granularity: TextGranularity,
+    ) -> Vec<TextRun> {
+        let Some(snapshot) = self.get_current_snapshot() else {
+            return Vec::new();
+        };
+
+        let measurer = SkiaTextMeasurer::with_fonts(self.resources.caches().fonts());
+        text_extraction::extract_text(&snapshot, root, granularity, &measurer)
+    }
+
+    /// All visible text of the document, with a newline between text nodes.
+    pub fn extract_plain_text(&self) -> String {
+        text_extraction::plain_text(&self.extract_text(None))
+    }
+
     /// Get the root node ID of the document
     pub fn root_id(&self) -> Id {
         self.root_id
     }
 
-    /// Get a cloned copy of the current render snapshot for drawing
-    fn get_current_snapshot(&self) -> Option<RenderNode> {
+    /// Lay out the document for a viewport of the given size without a window and return the
+    /// result, which also becomes the current snapshot. Every change made before the call is
+    /// included. Only CPU text measurement is used, so this works without a GPU or display.
+    ///
+    /// The window owns the viewport while [`Engine::run`] is active, so this fails with
+    /// [`EngineError::Reentrancy`] then; `run` fails the same way during a headless layout.
+    pub fn layout_headless(
+        &self,
+        viewport_width: f64,
+        viewport_height: f64,
+    ) -> Result<RenderSnapshot, EngineError> {
+        let snapshot = self.layout_synchronously(viewport_width, viewport_height)?;
+        Ok(RenderSnapshot::from(&*snapshot))
+    }
+
+    /// Lay out at the viewport size on the data thread and wait for the result.
+    fn layout_synchronously(
+        &self,
+        viewport_width: f64,
+        viewport_height: f64,
+    ) -> Result<Arc<RenderNode>, EngineError> {
+        let _lock = self
+            .running
+            .try_lock()
+            .map_err(|_| EngineError::Reentrancy)?;
+
+        let (reply_tx, reply_rx) = channel();
+        self.send(Command::LayoutHeadless(
+            viewport_width.max(0.0),
+            viewport_height.max(0.0),
+            reply_tx,
+        ))?;
+        reply_rx.recv().map_err(|_| EngineError::ShuttingDown)
+    }
+
+    /// Paint the current snapshot into a `width` by `height` image, in logical pixels, and
+    /// encode it as PNG. The image is `scale_factor` times larger in each dimension, so 2.0
+    /// renders like a 2x display. Uses a software rasterizer: no window or GPU is needed.
+    ///
+    /// Pair with [`Engine::layout_headless`] to render a document at a known viewport size.
+    pub fn render_to_png(
+        &self,
+        width: f64,
+        height: f64,
+        scale_factor: f64,
+    ) -> Result<Vec<u8>, EngineError> {
+        let mut target = backend::raster::RasterBackend::new(width, height, scale_factor)
+            .ok_or(EngineError::RenderFailed)?;
+
+        let snapshot = self.get_current_snapshot();
+        let fonts = self.resources.caches().fonts();
+        target.render(|canvas| match &snapshot {
+            Some(snapshot) => Painter::with_fonts(canvas, fonts)
+                .with_selection(self.selection_in(WindowId::PRIMARY))
+                .paint(snapshot),
+            None => {
+                canvas.clear(skia_safe::Color::WHITE);
+            }
+        });
+
+        target.encode_png().ok_or(EngineError::RenderFailed)
+    }
+
+    /// Print the document to PDF. It is laid out at the width of a page's content area, and
+    /// as much of it as fits on a page goes on each page; nodes straddling a page boundary are
+    /// clipped and appear on both pages. Text stays text, so it can be selected and searched.
+    ///
+    /// Like [`Engine::layout_headless`], this can't be used while the window is running, and
+    /// later rendering uses the page layout until the next layout.
+    pub fn export_pdf(&self, options: PdfOptions) -> Result<Vec<u8>, EngineError> {
+        let (width, height) = options.content_size().ok_or(EngineError::RenderFailed)?;
+        let snapshot = self.layout_synchronously(width, height)?;
+
+        let bounds = snapshot.descendant_bounds;
+        let pages = backend::pdf::page_count(bounds.y + bounds.height, height);
+        let fonts = self.resources.caches().fonts();
+        Ok(backend::pdf::render_pdf(&snapshot, fonts, &options, pages))
+    }
+
+    /// The document as of the latest layout, or `None` before the first layout.
+    pub fn snapshot(&self) -> Option<RenderSnapshot> {
+        self.snapshot
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|snapshot| RenderSnapshot::from(&*snapshot.tree))
+    }
+
+    /// Size of the index hit testing the primary window's latest snapshot goes through, or
+    /// `None` before the first layout. The index is rebuilt with every snapshot.
+    pub fn hit_index_stats(&self) -> Option<HitIndexStats> {
+        let snapshot = self.get_current_snapshot()?;
+        Some(snapshot.hit_index.stats())
+    }
+
+    /// A window's tree as of the latest layout, or `None` before its first layout or after
+    /// it closed.
+    pub fn window_snapshot(&self, window: WindowId) -> Option<RenderSnapshot> {
+        self.get_window_snapshot(window)
+            .map(|snapshot| RenderSnapshot::from(&*snapshot.tree))
+    }
+
+    /// What assistive technology sees of the primary window as of the latest layout, see
+    /// [`AccessNode`]. Before the first layout, only an empty root.
+    pub fn accessibility_tree(&self) -> AccessNode {
+        match self.get_current_snapshot() {
+            Some(snapshot) => accessibility::accessibility_tree(&snapshot.tree),
+            None => AccessNode {
+                id: self.root_id,
+                role: None,
+                name: None,
+                bounds: Rect::default(),
+                children: Vec::new(),
+            },
+        }
+    }
+
+    /// The current render snapshot for drawing
+    fn get_current_snapshot(&self) -> Option<Arc<IndexedTree>> {
         self.snapshot.read().unwrap().as_ref().cloned()
     }
+
+    fn get_window_snapshot(&self, window: WindowId) -> Option<Arc<IndexedTree>> {
+        if window == WindowId::PRIMARY {
+            return self.get_current_snapshot();
+        }
+        let snapshots = self.window_snapshots.read().unwrap();
+        snapshots.get(&window.root_id()).cloned()
+    }
 }
 
 impl Default for Engine {
@@ -163,3 +1673,15 @@ impl Default for Engine {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod headless_tests;
+
+#[cfg(test)]
+mod multi_window_tests;
+
+#[cfg(test)]
+mod pdf_tests;
+
+#[cfg(test)]
+mod render_tests;
@@ -0,0 +1,424 @@
+use crate::layout::{Document, Node};
+use crate::{EngineError, SourceLocation};
+use std::fmt;
+
+/// Where and why markup couldn't be parsed. Lines and columns start at 1; columns count
+/// characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MarkupError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: MarkupErrorKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MarkupErrorKind {
+    UnexpectedEnd,
+    UnexpectedChar(char),
+    /// A closing tag that doesn't match the open element, or has no open element.
+    MismatchedClosingTag {
+        expected: Option<String>,
+        found: String,
+    },
+    DuplicateAttribute(String),
+    /// Only `&amp;`, `&lt;`, `&gt;`, `&quot;` and `&apos;` are supported.
+    UnknownEntity(String),
+}
+
+impl fmt::Display for MarkupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            MarkupErrorKind::UnexpectedEnd => f.write_str("unexpected end of markup"),
+            MarkupErrorKind::UnexpectedChar(c) => write!(f, "unexpected {c:?}"),
+            MarkupErrorKind::MismatchedClosingTag {
+                expected: Some(expected),
+                found,
+            } => write!(f, "expected </{expected}>, found </{found}>"),
+            MarkupErrorKind::MismatchedClosingTag {
+                expected: None,
+                found,
+            } => write!(f, "</{found}> closes no element"),
+            MarkupErrorKind::DuplicateAttribute(name) => write!(f, "duplicate attribute {name}"),
+            MarkupErrorKind::UnknownEntity(entity) => write!(f, "unknown entity &{entity};"),
+        }
+    }
+}
+
+impl std::error::Error for MarkupError {}
+
+impl From<MarkupError> for EngineError {
+    fn from(err: MarkupError) -> Self {
+        let location = SourceLocation {
+            line: err.line as u32,
+            column: err.column as u32,
+        };
+        EngineError::Markup {
+            location,
+            source: Box::new(err),
+        }
+    }
+}
+
+/// Escape text for use in markup content or a quoted attribute value.
+pub fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The markup of the nodes under the document root, which loads back into the same tree:
+/// one node a line, indented by depth, with attributes in order of name.
+///
+/// Nodes created without a tag are written as `<node>` elements, or as bare text if they
+/// have text and no children. Markup can't tell the text of a node from text next to its
+/// children: the text of a node with children loads back as a text node before them, and a
+/// lone text node as the text of its parent.
+pub(crate) fn write_markup(document: &Document) -> String {
+    let mut markup = String::new();
+    for child in &document.root_node().borrow().children {
+        write_node(&mut markup, &child.borrow(), 0);
+    }
+    markup
+}
+
+fn write_node(markup: &mut String, node: &Node, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let text = node.text.as_deref().map(escape_markup);
+    let bare = text.is_some() && node.children.is_empty();
+    let Some(tag) = node.tag.as_deref().or((!bare).then_some("node")) else {
+        // A bare text node.
+        markup.push_str(&format!("{indent}{}\n", text.unwrap_or_default()));
+        return;
+    };
+
+    markup.push_str(&format!("{indent}<{tag}"));
+    let mut attributes: Vec<_> = node.attributes.iter().collect();
+    attributes.sort();
+    for (name, value) in attributes {
+        markup.push_str(&format!(" {name}=\"{}\"", escape_markup(value)));
+    }
+    match (text, node.children.is_empty()) {
+        (None, true) => markup.push_str("/>\n"),
+        (Some(text), true) => markup.push_str(&format!(">{text}</{tag}>\n")),
+        (text, false) => {
+            markup.push_str(">\n");
+            if let Some(text) = text {
+                markup.push_str(&format!("{indent}  {text}\n"));
+            }
+            for child in &node.children {
+                write_node(markup, &child.borrow(), depth + 1);
+            }
+            markup.push_str(&format!("{indent}</{tag}>\n"));
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum MarkupNode {
+    Element {
+        tag: String,
+        /// In source order.
+        attributes: Vec<(String, String)>,
+        /// Set when the element's only content is text; `children` is empty then.
+        text: Option<String>,
+        children: Vec<MarkupNode>,
+    },
+    /// Text next to elements, which becomes a node of its own.
+    Text(String),
+}
+
+/// Parse a sequence of elements and text.
+///
+/// Text is trimmed and runs of whitespace collapse to one space. Whitespace-only text is
+/// dropped, like whitespace between flex items.
+pub(crate) fn parse_markup(markup: &str) -> Result<Vec<MarkupNode>, MarkupError> {
+    let mut parser = Parser {
+        chars: markup.chars().collect(),
+        pos: 0,
+        line: 1,
+        column: 1,
+    };
+
+    parser.parse_content(None)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn starts_with(&self, s: &str) -> bool {
+        s.chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.pos + i) == Some(&c))
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, kind: MarkupErrorKind) -> MarkupError {
+        MarkupError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+
+    /// Error for the next character, or for the end of input.
+    fn unexpected(&self) -> MarkupError {
+        match self.peek() {
+            Some(c) => self.error(MarkupErrorKind::UnexpectedChar(c)),
+            None => self.error(MarkupErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), MarkupError> {
+        if self.peek() == Some(expected) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected())
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Content up to the closing tag of `parent`, or to the end for the top level.
+    fn parse_content(&mut self, parent: Option<&str>) -> Result<Vec<MarkupNode>, MarkupError> {
+        let mut nodes = Vec::new();
+        let mut text = String::new();
+
+        loop {
+            match self.peek() {
+                None => {
+                    if parent.is_some() {
+                        return Err(self.unexpected());
+                    }
+                    push_text(&mut nodes, &mut text);
+                    return Ok(nodes);
+                }
+                Some('<') if self.starts_with("<!--") => self.skip_comment()?,
+                Some('<') if self.starts_with("</") => {
+                    let (line, column) = (self.line, self.column);
+                    self.bump();
+                    self.bump();
+                    let name = self.parse_name()?;
+                    self.skip_whitespace();
+                    self.expect('>')?;
+
+                    if parent != Some(name.as_str()) {
+                        return Err(MarkupError {
+                            line,
+                            column,
+                            kind: MarkupErrorKind::MismatchedClosingTag {
+                                expected: parent.map(str::to_owned),
+                                found: name,
+                            },
+                        });
+                    }
+                    push_text(&mut nodes, &mut text);
+                    return Ok(nodes);
+                }
+                Some('<') => {
+                    push_text(&mut nodes, &mut text);
+                    nodes.push(self.parse_element()?);
+                }
+                Some('&') => text.push(self.parse_entity()?),
+                Some(c) => {
+                    self.bump();
+                    text.push(c);
+                }
+            }
+        }
+    }
+
+    fn skip_comment(&mut self) -> Result<(), MarkupError> {
+        for _ in 0..4 {
+            self.bump();
+        }
+        while !self.starts_with("-->") {
+            self.bump().ok_or_else(|| self.unexpected())?;
+        }
+        for _ in 0..3 {
+            self.bump();
+        }
+        Ok(())
+    }
+
+    fn parse_element(&mut self) -> Result<MarkupNode, MarkupError> {
+        self.expect('<')?;
+        let tag = self.parse_name()?;
+        let mut attributes: Vec<(String, String)> = Vec::new();
+
+        loop {
+            let had_whitespace = self.peek().is_some_and(char::is_whitespace);
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some('/') => {
+                    self.bump();
+                    self.expect('>')?;
+                    return Ok(MarkupNode::Element {
+                        tag,
+                        attributes,
+                        text: None,
+                        children: Vec::new(),
+                    });
+                }
+                Some('>') => {
+                    self.bump();
+                    break;
+                }
+                Some(_) if had_whitespace => {
+                    let (line, column) = (self.line, self.column);
+                    let name = self.parse_name()?;
+                    self.skip_whitespace();
+                    self.expect('=')?;
+                    self.skip_whitespace();
+                    let value = self.parse_attribute_value()?;
+
+                    if attributes.iter().any(|(existing, _)| *existing == name) {
+                        return Err(MarkupError {
+                            line,
+                            column,
+                            kind: MarkupErrorKind::DuplicateAttribute(name),
+                        });
+                    }
+                    attributes.push((name, value));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+
+        let mut children = self.parse_content(Some(&tag))?;
+        let text = match children.as_slice() {
+            [MarkupNode::Text(_)] => match children.pop() {
+                Some(MarkupNode::Text(text)) => Some(text),
+                _ => unreachable!(),
+            },
+            _ => None,
+        };
+
+        Ok(MarkupNode::Element {
+            tag,
+            attributes,
+            text,
+            children,
+        })
+    }
+
+    fn parse_name(&mut self) -> Result<String, MarkupError> {
+        let mut name = String::new();
+        match self.peek() {
+            Some(c) if c.is_alphabetic() || c == '_' => name.push(c),
+            _ => return Err(self.unexpected()),
+        }
+        self.bump();
+
+        while let Some(c) = self
+            .peek()
+            .filter(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+        {
+            self.bump();
+            name.push(c);
+        }
+        Ok(name)
+    }
+
+    fn parse_attribute_value(&mut self) -> Result<String, MarkupError> {
+        let quote = match self.peek() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return Err(self.unexpected()),
+        };
+        self.bump();
+
+        let mut value = String::new();
+        loop {
+            match self.peek() {
+                Some(c) if c == quote => {
+                    self.bump();
+                    return Ok(value);
+                }
+                Some('&') => value.push(self.parse_entity()?),
+                Some('<') | None => return Err(self.unexpected()),
+                Some(c) => {
+                    self.bump();
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    fn parse_entity(&mut self) -> Result<char, MarkupError> {
+        let (line, column) = (self.line, self.column);
+        self.expect('&')?;
+
+        let mut name = String::new();
+        while let Some(c) = self.peek().filter(|c| *c != ';') {
+            if c.is_whitespace() || c == '<' || c == '&' || name.len() > 8 {
+                break;
+            }
+            self.bump();
+            name.push(c);
+        }
+        let terminated = self.peek() == Some(';');
+
+        let c = match name.as_str() {
+            "amp" if terminated => '&',
+            "lt" if terminated => '<',
+            "gt" if terminated => '>',
+            "quot" if terminated => '"',
+            "apos" if terminated => '\'',
+            _ => {
+                return Err(MarkupError {
+                    line,
+                    column,
+                    kind: MarkupErrorKind::UnknownEntity(name),
+                })
+            }
+        };
+        self.bump();
+        Ok(c)
+    }
+}
+
+fn push_text(nodes: &mut Vec<MarkupNode>, text: &mut String) {
+    let collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if !collapsed.is_empty() {
+        nodes.push(MarkupNode::Text(collapsed));
+    }
+    text.clear();
+}
+
+#[cfg(test)]
+mod markup_tests;
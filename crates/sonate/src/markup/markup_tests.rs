@@ -0,0 +1,282 @@
+use super::{escape_markup, parse_markup, MarkupErrorKind, MarkupNode};
+use crate::{Engine, EngineError, ErrorCode, Id, SourceLocation};
+
+const STYLESHEET: &str = r#"
+    .row { display: flex; flex-direction: row; gap: 4px; }
+    .box { width: 30px; height: 20px; }
+    span { font-size: 20px; }
+"#;
+
+#[test]
+fn nested_markup_matches_imperative_construction() {
+    let markup = r#"
+        <div class="row" id="toolbar">
+            <div class="box"/>
+            <span>Hello,   world</span>
+            <div>
+                Before <b>bold</b> after
+            </div>
+        </div>
+    "#;
+
+    let from_markup = Engine::new();
+    from_markup.add_stylesheet(STYLESHEET);
+    let top_level = from_markup.load_markup(markup).unwrap();
+    assert_eq!(top_level, vec![Id::from_u64(Id::GENERATED_BASE)]);
+
+    // Ids are handed out in document order.
+    let id = |n: u64| Id::from_u64(Id::GENERATED_BASE + n);
+    let imperative = Engine::new();
+    imperative.add_stylesheet(STYLESHEET);
+    let element = |node: Id, parent: Id, tag: &str, text: Option<&str>| {
+        imperative.create_element(node, tag, text.map(str::to_owned));
+        imperative.set_parent(parent, node);
+    };
+    element(id(0), imperative.root_id(), "div", None);
+    imperative.set_attribute(id(0), "class".to_owned(), "row".to_owned());
+    imperative.set_attribute(id(0), "id".to_owned(), "toolbar".to_owned());
+    element(id(1), id(0), "div", None);
+    imperative.set_attribute(id(1), "class".to_owned(), "box".to_owned());
+    element(id(2), id(0), "span", Some("Hello, world"));
+    element(id(3), id(0), "div", None);
+    imperative.create_node(id(4), Some("Before".to_owned()));
+    imperative.set_parent(id(3), id(4));
+    element(id(5), id(3), "b", Some("bold"));
+    imperative.create_node(id(6), Some("after".to_owned()));
+    imperative.set_parent(id(3), id(6));
+
+    let expected = imperative.layout_headless(300.0, 200.0).unwrap();
+    let actual = from_markup.layout_headless(300.0, 200.0).unwrap();
+    assert_eq!(actual, expected);
+    #[cfg(feature = "serde")]
+    assert_eq!(actual.to_json(), expected.to_json());
+
+    assert_eq!(actual.find(id(1)).unwrap().bounds.width, 30.0);
+    assert_eq!(
+        actual.find(id(2)).unwrap().text.as_deref(),
+        Some("Hello, world")
+    );
+}
+
+#[test]
+fn markup_can_be_loaded_under_a_parent() {
+    let engine = Engine::new();
+    let container = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), container);
+
+    let ids = engine
+        .load_markup_into(container, "<a/> text <b/>")
+        .unwrap();
+    assert_eq!(ids.len(), 3);
+
+    let snapshot = engine.layout_headless(100.0, 100.0).unwrap();
+    let children: Vec<_> = snapshot
+        .find(container)
+        .unwrap()
+        .children
+        .iter()
+        .map(|child| child.id)
+        .collect();
+    assert_eq!(children, ids);
+    assert_eq!(snapshot.find(ids[1]).unwrap().text.as_deref(), Some("text"));
+}
+
+#[test]
+fn malformed_markup_reports_its_location() {
+    let error = parse_markup("<div>\n  <span>text</div>").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 13));
+    assert_eq!(
+        error.kind,
+        MarkupErrorKind::MismatchedClosingTag {
+            expected: Some("span".to_owned()),
+            found: "div".to_owned(),
+        }
+    );
+    assert_eq!(
+        error.to_string(),
+        "line 2, column 13: expected </span>, found </div>"
+    );
+
+    let error = parse_markup("<a>\n<b c=d/></a>").unwrap_err();
+    assert_eq!((error.line, error.column), (2, 6));
+    assert_eq!(error.kind, MarkupErrorKind::UnexpectedChar('d'));
+
+    let error = parse_markup("<a>&nbsp;</a>").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 4));
+    assert_eq!(
+        error.kind,
+        MarkupErrorKind::UnknownEntity("nbsp".to_owned())
+    );
+
+    let error = parse_markup("<a x='1' x='2'/>").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 10));
+
+    let error = parse_markup("<a><b></b>").unwrap_err();
+    assert_eq!(error.kind, MarkupErrorKind::UnexpectedEnd);
+
+    // Nothing is created from malformed markup.
+    let engine = Engine::new();
+    assert!(engine.load_markup("<a/><b>").is_err());
+    let snapshot = engine.layout_headless(100.0, 100.0).unwrap();
+    assert!(snapshot.children.is_empty());
+}
+
+#[test]
+fn loaded_trees_can_be_walked() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".list { width: 200px; }");
+    let markup = r#"
+        <ul class="list" id="todo" style="width: 120px; padding: 2px">
+            <li class="item done" data-due="mañana">Café &amp; croissants</li>
+            <li class="item"><input type="checkbox"/>Write &lt;tests&gt;</li>
+        </ul>
+    "#;
+    let top_level = engine.load_markup(markup).unwrap();
+    assert_eq!(engine.query("#todo"), top_level);
+
+    let list = top_level[0];
+    assert_eq!(engine.parent(list), Some(engine.root_id()));
+    let items = engine.children(list);
+    assert_eq!(items, engine.query(".list .item"));
+    assert_eq!(engine.query(".item.done"), items[..1]);
+    assert_eq!(engine.query("[data-due=\"mañana\"]"), items[..1]);
+    let (checkbox, label) = match engine.children(items[1])[..] {
+        [checkbox, label] => (checkbox, label),
+        ref children => panic!("{children:?}"),
+    };
+    assert_eq!(engine.query("input[type=checkbox]"), vec![checkbox]);
+    assert!(engine.children(checkbox).is_empty());
+    assert_eq!(
+        engine.descendants(list),
+        vec![items[0], items[1], checkbox, label]
+    );
+
+    let snapshot = engine.layout_headless(300.0, 200.0).unwrap();
+    let text = |id| snapshot.find(id).unwrap().text.clone();
+    assert_eq!(text(items[0]).as_deref(), Some("Café & croissants"));
+    assert_eq!(text(label).as_deref(), Some("Write <tests>"));
+    // The style attribute is the list's inline style, which beats the rule: a 120px content
+    // box, padded.
+    let list = snapshot.find(list).unwrap();
+    assert_eq!(list.bounds.width, 124.0);
+    assert_eq!(list.children[0].bounds.x, 2.0);
+}
+
+#[test]
+fn markup_errors_are_engine_errors() {
+    let engine = Engine::new();
+    let error: EngineError = engine.load_markup("<a>\n  <b></a>").unwrap_err().into();
+    assert_eq!(error.code(), ErrorCode::MarkupSyntax);
+    assert!(matches!(
+        error,
+        EngineError::Markup {
+            location: SourceLocation { line: 2, column: 6 },
+            ..
+        }
+    ));
+    assert_eq!(
+        error.to_string(),
+        "markup-syntax: malformed markup at line 2, column 6: expected </b>, found </a>"
+    );
+}
+
+#[test]
+fn documents_dump_to_markup_in_a_stable_form() {
+    let engine = Engine::new();
+    engine
+        .load_markup(
+            r#"<ul id="todo" class='list' data-filter='"all" &amp; more'><li>a &lt; b</li></ul>"#,
+        )
+        .unwrap();
+    let container = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), container);
+    engine.set_attribute(container, "role".to_owned(), "status".to_owned());
+    let text = engine.create_node(Id::from_u64(2), Some("Saved".to_owned()));
+    engine.set_parent(container, text);
+    engine.create_node(Id::from_u64(3), Some("Not in the tree".to_owned()));
+
+    let expected = r#"<ul class="list" data-filter="&quot;all&quot; &amp; more" id="todo">
+  <li>a &lt; b</li>
+</ul>
+<node role="status">
+  Saved
+</node>
+"#;
+    assert_eq!(engine.dump_document(), expected);
+    assert_eq!(engine.dump_document(), expected);
+    assert_eq!(Engine::new().dump_document(), "");
+}
+
+#[test]
+fn dumped_documents_load_back_into_the_same_tree() {
+    let markup = r#"
+        <div class="row" id="toolbar" style="gap: 4px">
+            <div class="box"/>
+            <span title="Say &quot;hi&quot;">Hello,   world</span>
+            <section>
+                Before <b>bold</b> after
+                <ul><li data-n="1">Café &amp; crème</li><li data-n='2'/></ul>
+            </section>
+        </div>
+        <p>Last &gt; first</p>
+    "#;
+    let loaded = Engine::new();
+    loaded.add_stylesheet(STYLESHEET);
+    loaded.load_markup(markup).unwrap();
+    let dumped = loaded.dump_document();
+
+    let reloaded = Engine::new();
+    reloaded.add_stylesheet(STYLESHEET);
+    reloaded.load_markup(&dumped).unwrap();
+    assert_eq!(reloaded.dump_document(), dumped);
+    // Fresh engines generate the same ids, so the trees compare node for node.
+    assert_eq!(
+        reloaded.layout_headless(300.0, 200.0).unwrap(),
+        loaded.layout_headless(300.0, 200.0).unwrap()
+    );
+    assert_eq!(reloaded.query("[data-n]"), loaded.query("li[data-n]"));
+}
+
+#[test]
+fn escaped_attribute_values_round_trip() {
+    for value in [
+        r#"a < b && c > "d""#,
+        "it's",
+        "&amp; stays escaped once",
+        "  spaces  kept ",
+    ] {
+        for quote in ['"', '\''] {
+            let markup = format!("<a title={quote}{}{quote}/>", escape_markup(value));
+            let nodes = parse_markup(&markup).unwrap();
+            let [MarkupNode::Element { attributes, .. }] = nodes.as_slice() else {
+                panic!("expected one element, got {nodes:?}");
+            };
+            assert_eq!(attributes, &[("title".to_owned(), value.to_owned())]);
+        }
+    }
+
+    let nodes = parse_markup(&format!("<p>{}</p>", escape_markup("<b> & co"))).unwrap();
+    assert!(matches!(
+        nodes.as_slice(),
+        [MarkupNode::Element { text: Some(text), .. }] if text == "<b> & co"
+    ));
+}
+
+#[test]
+fn whitespace_only_text_and_comments_are_dropped() {
+    let nodes = parse_markup("\n  <a>\n    <!-- note -->\n    <b/>\n  </a>\n").unwrap();
+    assert_eq!(
+        nodes,
+        vec![MarkupNode::Element {
+            tag: "a".to_owned(),
+            attributes: vec![],
+            text: None,
+            children: vec![MarkupNode::Element {
+                tag: "b".to_owned(),
+                attributes: vec![],
+                text: None,
+                children: vec![],
+            }],
+        }]
+    );
+}
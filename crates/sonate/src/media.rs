@@ -0,0 +1,75 @@
+use std::hash::{Hash, Hasher};
+
+/// The environment `@media` rules are evaluated against.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaEnvironment {
+    pub prefers_reduced_motion: bool,
+    /// The width of the viewport, in pixels.
+    pub viewport_width: f64,
+}
+
+// Viewport sizes are finite.
+impl Eq for MediaEnvironment {}
+
+impl Hash for MediaEnvironment {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.prefers_reduced_motion.hash(state);
+        self.viewport_width.to_bits().hash(state);
+    }
+}
+
+/// A single `<media-feature>` or media type test.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaCondition {
+    /// Never matches. Used for media types other than `all`/`screen` and for
+    /// unknown features, which the spec evaluates as `not all`.
+    Never,
+    /// `(prefers-reduced-motion: reduce)` is `true`, `no-preference` is `false`.
+    PrefersReducedMotion(bool),
+    /// `(min-width: …)`, in pixels.
+    MinWidth(f64),
+    /// `(max-width: …)`, in pixels.
+    MaxWidth(f64),
+}
+
+impl MediaCondition {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        match self {
+            MediaCondition::Never => false,
+            MediaCondition::PrefersReducedMotion(reduce) => env.prefers_reduced_motion == *reduce,
+            MediaCondition::MinWidth(width) => env.viewport_width >= *width,
+            MediaCondition::MaxWidth(width) => env.viewport_width <= *width,
+        }
+    }
+
+    fn depends_on_width(&self) -> bool {
+        matches!(
+            self,
+            MediaCondition::MinWidth(_) | MediaCondition::MaxWidth(_)
+        )
+    }
+}
+
+/// A comma-separated `<media-query-list>`.
+///
+/// Each entry is a list of conditions joined by `and`; the list matches if any entry matches.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaQuery {
+    pub queries: Vec<Vec<MediaCondition>>,
+}
+
+impl MediaQuery {
+    pub fn matches(&self, env: &MediaEnvironment) -> bool {
+        self.queries
+            .iter()
+            .any(|conditions| conditions.iter().all(|c| c.matches(env)))
+    }
+
+    /// Whether the query tests the viewport's width.
+    pub fn depends_on_width(&self) -> bool {
+        self.queries
+            .iter()
+            .flatten()
+            .any(MediaCondition::depends_on_width)
+    }
+}
@@ -0,0 +1,94 @@
+use crate::commands::Command;
+use crate::{Engine, Id, WindowId, WindowOptions};
+
+fn build_windows(engine: &Engine) -> (WindowId, Id, Id) {
+    engine.add_stylesheet(
+        r#"
+        .box { width: 50px; height: 20px; }
+        .wide { width: 120px; height: 20px; }
+    "#,
+    );
+
+    let primary_item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), primary_item);
+    engine.set_attribute(primary_item, "class".to_owned(), "box".to_owned());
+
+    let window = engine.create_window(WindowOptions::default());
+    let window_item = engine.create_node(Id::from_u64(2), None);
+    engine.set_parent(window.root_id(), window_item);
+    engine.set_attribute(window_item, "class".to_owned(), "wide".to_owned());
+    // Stands in for the window reporting its size once open.
+    engine
+        .sender
+        .send(Command::SetViewportSize(window.root_id(), 300.0, 150.0))
+        .unwrap();
+
+    (window, primary_item, window_item)
+}
+
+#[test]
+fn windows_have_their_own_tree_and_viewport() {
+    let engine = Engine::new();
+    let (window, primary_item, window_item) = build_windows(&engine);
+    assert_ne!(window, WindowId::PRIMARY);
+    assert_eq!(WindowId::PRIMARY.root_id(), engine.root_id());
+    assert_eq!(engine.windows(), vec![window]);
+
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let primary = engine.window_snapshot(WindowId::PRIMARY).unwrap();
+    assert_eq!(
+        (primary.bounds.width, primary.bounds.height),
+        (200.0, 100.0)
+    );
+    assert!(primary.find(window_item).is_none());
+
+    let secondary = engine.window_snapshot(window).unwrap();
+    assert_eq!(secondary.id, window.root_id());
+    assert_eq!(
+        (secondary.bounds.width, secondary.bounds.height),
+        (300.0, 150.0)
+    );
+    assert!(secondary.find(primary_item).is_none());
+    // Styled by the shared stylesheet.
+    assert_eq!(secondary.find(window_item).unwrap().bounds.width, 120.0);
+}
+
+#[test]
+fn clicks_hit_the_window_they_happen_in() {
+    let engine = Engine::new();
+    let (window, primary_item, window_item) = build_windows(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let hit = |window| {
+        engine
+            .get_window_snapshot(window)
+            .unwrap()
+            .find_element_at_position(10.0, 10.0)
+    };
+    assert!(hit(WindowId::PRIMARY).contains(&primary_item));
+    assert!(!hit(WindowId::PRIMARY).contains(&window_item));
+    assert!(hit(window).contains(&window_item));
+    assert!(!hit(window).contains(&primary_item));
+}
+
+#[test]
+fn closing_a_window_destroys_only_its_tree() {
+    let engine = Engine::new();
+    let (window, primary_item, window_item) = build_windows(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    engine.close_window(window);
+    assert!(engine.windows().is_empty());
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    assert!(engine.window_snapshot(window).is_none());
+    let primary = engine.window_snapshot(WindowId::PRIMARY).unwrap();
+    assert!(primary.find(primary_item).is_some());
+
+    // The window's nodes are gone: attaching one to the primary tree fails.
+    engine.set_parent(engine.root_id(), window_item);
+    engine.layout_headless(200.0, 100.0).unwrap();
+    let primary = engine.window_snapshot(WindowId::PRIMARY).unwrap();
+    assert!(primary.find(window_item).is_none());
+}
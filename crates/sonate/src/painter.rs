@@ -1,120 +1,380 @@
 use crate::{
-    layout::RenderNode,
-    style::{BorderStyle, Length, Rgba},
-    text::{FontSpec, SkiaTextMeasurer},
+    damage::paint_extent,
+    debug_overlay::{self, DebugOverlay},
+    editing::EditedText,
+    images::object_fit_rect,
+    layer_cache::{CachedLayer, LayerCache},
+    layout::{outline_bounds, RenderNode},
+    selection::{find_node, x_of_char_index},
+    style::{Length, Rgba},
+    text::{line_boxes, shaping, FontProvider, FontSpec, LineStyle, SkiaTextMeasurer},
+    tooltip::Tooltip,
+    Id,
+};
+use skia_safe::{
+    surfaces, Canvas, ClipOp, Color, Color4f, FilterMode, MipmapMode, Paint, RRect, Rect,
+    SamplingOptions,
+};
+use std::ops::Range;
+
+/// Drawn behind selected text.
+const SELECTION_COLOR: Rgba = Rgba {
+    r: 179,
+    g: 215,
+    b: 255,
+    a: 255,
 };
-use skia_safe::{Canvas, Color, Color4f, Paint, RRect, Rect};
 
 pub struct Painter<'a> {
     canvas: &'a Canvas,
+    layers: Option<&'a mut LayerCache>,
+    fonts: FontProvider,
+    painted_nodes: usize,
+    /// Characters of a text node to highlight.
+    selection: Option<(Id, Range<usize>)>,
+    /// The value of the focused editable node, painted instead of its text.
+    editing: Option<EditedText>,
+    /// Painted over the tree.
+    tooltip: Option<Tooltip>,
+    /// The region to repaint, keeping the rest of the canvas; `None` repaints all of it.
+    clip: Option<crate::layout::Rect>,
+    /// Painted over everything, the tooltip too.
+    debug_overlay: Option<DebugOverlay>,
+    /// The panel of [`crate::Params::show_stats`], painted over the debug overlay.
+    stats: Option<RenderNode>,
 }
 
 impl<'a> Painter<'a> {
     pub fn new(canvas: &'a Canvas) -> Self {
-        Self { canvas }
+        Self {
+            canvas,
+            layers: None,
+            fonts: FontProvider::default(),
+            painted_nodes: 0,
+            selection: None,
+            editing: None,
+            tooltip: None,
+            clip: None,
+            debug_overlay: None,
+            stats: None,
+        }
+    }
+
+    /// A painter that draws text with the engine's fonts instead of the defaults.
+    pub(crate) fn with_fonts(canvas: &'a Canvas, fonts: FontProvider) -> Self {
+        Self {
+            fonts,
+            ..Self::new(canvas)
+        }
+    }
+
+    /// A painter that renders promoted subtrees (see [`crate::style::Style::promotes_layer`])
+    /// once into `layers` and composites the cached image on later frames.
+    pub(crate) fn with_layer_cache(
+        canvas: &'a Canvas,
+        layers: &'a mut LayerCache,
+        fonts: FontProvider,
+    ) -> Self {
+        Self {
+            canvas,
+            layers: Some(layers),
+            fonts,
+            painted_nodes: 0,
+            selection: None,
+            editing: None,
+            tooltip: None,
+            clip: None,
+            debug_overlay: None,
+            stats: None,
+        }
+    }
+
+    /// Highlight the given characters of a text node.
+    pub(crate) fn with_selection(self, selection: Option<(Id, Range<usize>)>) -> Self {
+        Self { selection, ..self }
+    }
+
+    /// Paint the value being edited in its node, and the caret.
+    pub(crate) fn with_editing(self, editing: Option<EditedText>) -> Self {
+        Self { editing, ..self }
+    }
+
+    /// Paint the tooltip over everything else.
+    pub(crate) fn with_tooltip(self, tooltip: Option<Tooltip>) -> Self {
+        Self { tooltip, ..self }
+    }
+
+    /// Repaint only `clip`, over a canvas holding an earlier frame of the tree.
+    pub(crate) fn with_clip(self, clip: Option<crate::layout::Rect>) -> Self {
+        Self { clip, ..self }
+    }
+
+    /// Paint the layout debugging overlay over everything, see [`crate::debug_overlay`].
+    pub(crate) fn with_debug_overlay(self, debug_overlay: Option<DebugOverlay>) -> Self {
+        Self {
+            debug_overlay,
+            ..self
+        }
+    }
+
+    /// Paint the panel of [`crate::Params::show_stats`] over everything.
+    pub(crate) fn with_stats(self, stats: Option<RenderNode>) -> Self {
+        Self { stats, ..self }
     }
 
     pub fn paint(&mut self, root: &RenderNode) {
+        if let Some(layers) = self.layers.as_deref_mut() {
+            layers.begin_frame();
+        }
+
+        let Some(clip) = self.clip else {
+            self.canvas.clear(Color::WHITE);
+            self.paint_node(root);
+            self.paint_tooltip();
+            self.paint_debug_overlay(root);
+            self.paint_stats();
+            if let Some(layers) = self.layers.as_deref_mut() {
+                layers.end_frame();
+            }
+            return;
+        };
+
+        self.canvas.save();
+        self.canvas.clip_rect(
+            Rect::from_xywh(
+                clip.x as f32,
+                clip.y as f32,
+                clip.width as f32,
+                clip.height as f32,
+            ),
+            None,
+            None,
+        );
         self.canvas.clear(Color::WHITE);
         self.paint_node(root);
+        self.paint_tooltip();
+        self.paint_debug_overlay(root);
+        self.paint_stats();
+        self.canvas.restore();
+        // Layers outside the clip weren't used, but are still on screen: they are evicted
+        // after the next full frame if they are gone by then.
+    }
+
+    fn paint_tooltip(&mut self) {
+        if let Some(tooltip) = self.tooltip.take() {
+            self.paint_node(&tooltip.node());
+            self.tooltip = Some(tooltip);
+        }
+    }
+
+    fn paint_stats(&mut self) {
+        if let Some(panel) = self.stats.take() {
+            self.paint_node(&panel);
+            self.stats = Some(panel);
+        }
+    }
+
+    fn paint_debug_overlay(&mut self, root: &RenderNode) {
+        let Some(overlay) = self.debug_overlay else {
+            return;
+        };
+        self.paint_boxes(root);
+
+        let Some((x, y)) = overlay.pointer else {
+            return;
+        };
+        let path = root.find_element_at_position(x, y);
+        let Some(hovered) = path.first().and_then(|&id| find_node(root, id)) else {
+            return;
+        };
+        let content = hovered.content_box();
+        let paint = Paint::new(debug_overlay::CONTENT_TINT.to_color4f(), None);
+        self.canvas.draw_rect(skia_rect(&content), &paint);
+
+        let measurer = SkiaTextMeasurer::with_fonts(self.fonts.clone());
+        let lines = debug_overlay::style_lines(hovered);
+        let panel = debug_overlay::panel(lines, &measurer, x, root.bounds);
+        self.paint_node(&panel);
+    }
+
+    /// The tinted margin and padding of `node` and its visible descendants, and their
+    /// border boxes outlined.
+    fn paint_boxes(&mut self, node: &RenderNode) {
+        if node.style.is_hidden() {
+            return;
+        }
+        for (outer, inner, tint) in debug_overlay::tinted_areas(node) {
+            let paint = Paint::new(tint.to_color4f(), None);
+            fill_between(self.canvas, &outer, &inner, &paint);
+        }
+        let mut paint = Paint::new(debug_overlay::OUTLINE_COLOR.to_color4f(), None);
+        paint.set_style(skia_safe::paint::Style::Stroke);
+        paint.set_stroke_width(1.0);
+        self.canvas
+            .draw_rect(skia_rect(&node.bounds.outset(-0.5)), &paint);
+        for child in &node.children {
+            self.paint_boxes(child);
+        }
+    }
+
+    /// Number of nodes whose contents were drawn, not counting composited cached layers.
+    #[allow(unused)]
+    pub(crate) fn painted_nodes(&self) -> usize {
+        self.painted_nodes
     }
 
     fn paint_node(&mut self, node: &RenderNode) {
-        // Draw the node's background color if it has one
         let style = &node.style;
 
-        let client_rect = Rect::new(
-            node.bounds.x as f32,
-            node.bounds.y as f32,
-            (node.bounds.x + node.bounds.width) as f32,
-            (node.bounds.y + node.bounds.height) as f32,
-        );
+        // `visibility: hidden` keeps the layout box but paints nothing for the subtree.
+        if style.is_hidden() {
+            return;
+        }
+
+        if let Some(clip) = &self.clip {
+            if !paint_extent(node).intersects(clip) {
+                return;
+            }
+        }
+
+        if style.promotes_layer()
+            && self.layers.is_some()
+            && !self.has_overlay_in(node)
+            && self.composite_layer(node)
+        {
+            return;
+        }
 
-        let client_rrect = if style.border_radius.is_empty() {
-            RRect::new_rect_xy(client_rect, 0.0, 0.0)
+        // Composite the element and its descendants as a single group when translucent.
+        let opacity = style.resolved_opacity();
+        let layer = if opacity < 1.0 {
+            Some(self.canvas.save_layer_alpha_f(None, opacity as f32))
         } else {
-            let tl = style
-                .border_radius
-                .top_left
-                .as_ref()
-                .map(|r| (r.x.to_px() as f32, r.y.to_px() as f32));
-            let tr = style
-                .border_radius
-                .top_right
-                .as_ref()
-                .map(|r| (r.x.to_px() as f32, r.y.to_px() as f32));
-            let br = style
-                .border_radius
-                .bottom_right
-                .as_ref()
-                .map(|r| (r.x.to_px() as f32, r.y.to_px() as f32));
-            let bl = style
-                .border_radius
-                .bottom_left
-                .as_ref()
-                .map(|r| (r.x.to_px() as f32, r.y.to_px() as f32));
-
-            RRect::new_rect_radii(
-                client_rect,
-                &[
-                    skia_safe::Vector::new(
-                        tl.map(|v| v.0).unwrap_or(0.0),
-                        tl.map(|v| v.1).unwrap_or(0.0),
-                    ),
-                    skia_safe::Vector::new(
-                        tr.map(|v| v.0).unwrap_or(0.0),
-                        tr.map(|v| v.1).unwrap_or(0.0),
-                    ),
-                    skia_safe::Vector::new(
-                        br.map(|v| v.0).unwrap_or(0.0),
-                        br.map(|v| v.1).unwrap_or(0.0),
-                    ),
-                    skia_safe::Vector::new(
-                        bl.map(|v| v.0).unwrap_or(0.0),
-                        bl.map(|v| v.1).unwrap_or(0.0),
-                    ),
-                ],
-            )
+            None
         };
 
-        if let Some(background_color) = &style.background_color {
-            let paint = Paint::new(background_color.to_color4f(), None);
+        self.paint_node_contents(node);
 
-            self.canvas.draw_rrect(client_rrect, &paint);
+        if let Some(count) = layer {
+            self.canvas.restore_to_count(count);
         }
+    }
 
-        let border_is_hidden = matches!(
-            style.border_style.top,
-            Some(BorderStyle::None) | Some(BorderStyle::Hidden)
-        );
+    /// Draw `node` from its cached layer, rendering the layer first if needed.
+    /// Returns `false` if the layer can't be cached, in which case the node is painted directly.
+    fn composite_layer(&mut self, node: &RenderNode) -> bool {
+        let scale = self.canvas.local_to_device_as_3x3().scale_x();
+        let scale = if scale > 0.0 { scale } else { 1.0 };
 
-        if !border_is_hidden {
-            let border_width = style.border_width.resolved();
-            let stroke_width_px = border_width
-                .top
-                .to_px()
-                .max(border_width.right.to_px())
-                .max(border_width.bottom.to_px())
-                .max(border_width.left.to_px());
-
-            if stroke_width_px > 0.0 {
-                let color = style.border_color.top.unwrap_or(Rgba {
-                    r: 0,
-                    g: 0,
-                    b: 0,
-                    a: 255,
-                });
-
-                let mut paint = Paint::new(color.to_color4f(), None);
-                paint.set_style(skia_safe::paint::Style::Stroke);
-                paint.set_stroke_width(stroke_width_px as f32);
-                paint.set_anti_alias(true);
-                self.canvas.draw_rrect(client_rrect, &paint);
+        let layers = self.layers.as_deref_mut().expect("layer cache");
+        if layers.get(node, scale).is_none() {
+            let rect = LayerRect::for_subtree(node, scale);
+            // Too large for the budget: paint directly instead.
+            if !layers.fits(rect.bytes()) {
+                return false;
             }
+            let Some(layer) = render_layer(
+                self.canvas,
+                node,
+                rect,
+                &self.fonts,
+                &mut self.painted_nodes,
+            ) else {
+                return false;
+            };
+            layers.insert(node.id, layer);
+        }
+
+        let layer = layers.layer(node.id).expect("layer just cached");
+        let mut paint = Paint::default();
+        paint.set_alpha_f(node.style.resolved_opacity() as f32);
+
+        self.canvas.save();
+        self.canvas.translate(layer.origin);
+        self.canvas.scale((1.0 / layer.scale, 1.0 / layer.scale));
+        self.canvas
+            .draw_image(&layer.image, (0.0, 0.0), Some(&paint));
+        self.canvas.restore();
+        true
+    }
+
+    fn paint_node_contents(&mut self, node: &RenderNode) {
+        self.painted_nodes += 1;
+
+        let style = &node.style;
+
+        let border_box = skia_rect(&node.border_box());
+        let clip = style.clips_overflow().then_some(border_box);
+
+        // Negative z-index children go behind this element's background.
+        let (behind, in_front) = node.children_in_paint_order();
+        self.paint_children(behind, clip);
+
+        // The background fills the padding box, and the border the rest of the border box;
+        // inside the border, corners are rounded by their radius less the border's width.
+        let border = &node.border_width;
+        let radii = corner_radii(style);
+        let inner_radii = [
+            (radii[0], border.left, border.top),
+            (radii[1], border.right, border.top),
+            (radii[2], border.right, border.bottom),
+            (radii[3], border.left, border.bottom),
+        ]
+        .map(|(radius, x, y)| {
+            skia_safe::Vector::new(
+                (radius.x - x as f32).max(0.0),
+                (radius.y - y as f32).max(0.0),
+            )
+        });
+        let outer = RRect::new_rect_radii(border_box, &radii);
+        let inner = RRect::new_rect_radii(skia_rect(&node.padding_box()), &inner_radii);
+
+        if let Some(background_color) = &style.background_color {
+            let paint = Paint::new(background_color.to_color4f(), None);
+            self.canvas.draw_rrect(inner, &paint);
+        }
+
+        let has_border = [border.top, border.right, border.bottom, border.left]
+            .iter()
+            .any(|&width| width > 0.0);
+        if has_border {
+            let color = style.border_color.top.unwrap_or(Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            });
+            let mut paint = Paint::new(color.to_color4f(), None);
+            paint.set_anti_alias(true);
+            self.canvas.draw_drrect(outer, inner, &paint);
+        }
+
+        // The image fills the content box as `object-fit` says, clipped to it.
+        if let Some(image) = &node.image {
+            let content_box = node.content_box();
+            let size = (image.width() as f64, image.height() as f64);
+            let fit = style.object_fit.unwrap_or_default();
+            let destination = object_fit_rect(fit, size, content_box);
+            let sampling = SamplingOptions::new(FilterMode::Linear, MipmapMode::None);
+            self.canvas.save();
+            self.canvas
+                .clip_rect(skia_rect(&content_box), ClipOp::Intersect, true);
+            self.canvas.draw_image_rect_with_sampling_options(
+                image.image(),
+                None,
+                skia_rect(&destination),
+                sampling,
+                &Paint::default(),
+            );
+            self.canvas.restore();
         }
 
         // Draw the node's text if it has any
-        if let Some(text) = &node.text {
+        let edited = self
+            .editing
+            .as_ref()
+            .filter(|edited| edited.node == node.id);
+        if let Some(text) = edited.map(|edited| &edited.text).or(node.text.as_ref()) {
             let text_color = style.color.unwrap_or(Rgba {
                 r: 0,
                 g: 0,
@@ -125,24 +385,253 @@ impl<'a> Painter<'a> {
             let mut paint = Paint::new(text_color.to_color4f(), None);
             paint.set_anti_alias(true);
 
-            let padding = style.padding.resolved();
-            let x = (node.bounds.x + padding.left.to_px()) as f32;
-
+            let content = node.content_box();
             let font_spec = FontSpec::from_style(style);
-            let font = SkiaTextMeasurer::make_font(&font_spec);
+            let font = self.fonts.make_font(&font_spec);
             let (_scale, metrics) = font.metrics();
-            let baseline_y =
-                (node.bounds.y + padding.top.to_px() + (-metrics.ascent as f64)) as f32;
+            let measurer = SkiaTextMeasurer::with_fonts(self.fonts.clone());
+            let lines = line_boxes(
+                &measurer,
+                text,
+                &font_spec,
+                content.width,
+                LineStyle::from_style(style),
+            );
+            let selected = self
+                .selection
+                .as_ref()
+                .filter(|(id, _)| *id == node.id)
+                .map(|(_, range)| range.clone());
+            let caret = edited.and_then(|edited| edited.caret);
+            // The caret goes on the last line starting at or before it.
+            let caret_line = caret.and_then(|caret| lines.iter().rposition(|l| l.start <= caret));
+
+            for (index, line) in lines.iter().enumerate() {
+                let x = (content.x + line.x) as f32;
+                let top = (content.y + line.y) as f32;
+                let bottom = top + line.height as f32;
+                let x_of = |char_index: usize| {
+                    let index = char_index - line.start;
+                    x_of_char_index(&measurer, &line.text, &font_spec, index) as f32
+                };
+
+                // The part of the selection on this line.
+                if let Some(range) = &selected {
+                    let end = line.start + line.text.chars().count();
+                    let (from, to) = (range.start.max(line.start), range.end.min(end));
+                    if from < to {
+                        let (start, end) = (x_of(from), x_of(to));
+                        // Right-to-left text ends left of where it starts.
+                        let highlight =
+                            Rect::new(x + start.min(end), top, x + start.max(end), bottom);
+                        let paint = Paint::new(SELECTION_COLOR.to_color4f(), None);
+                        self.canvas.draw_rect(highlight, &paint);
+                    }
+                }
+
+                let baseline_y = top + font_spec.baseline(&metrics) as f32;
+                let shaped = shaping::shape_line(
+                    line.text.trim_end(),
+                    &font,
+                    font_spec.direction,
+                    font_spec.letter_spacing_px as f64,
+                );
+                for run in &shaped.runs {
+                    let origin = (x + run.x as f32, baseline_y);
+                    self.canvas.draw_glyphs_at(
+                        &run.glyphs,
+                        &run.positions[..],
+                        origin,
+                        &run.font,
+                        &paint,
+                    );
+                }
 
-            self.canvas.draw_str(text, (x, baseline_y), &font, &paint);
+                if let Some(caret) = caret.filter(|_| caret_line == Some(index)) {
+                    let offset = x_of(caret);
+                    let caret = Rect::new(x + offset, top, x + offset + 1.0, bottom);
+                    self.canvas.draw_rect(caret, &paint);
+                }
+            }
         }
         // Recursively paint the children
-        for child in &node.children {
+        self.paint_children(in_front, clip);
+        self.paint_outline(node);
+    }
+
+    /// The outline, over the node's contents and unclipped by its `overflow`.
+    fn paint_outline(&mut self, node: &RenderNode) {
+        let style = &node.style;
+        let Some(outline) = outline_bounds(node.bounds, style) else {
+            return;
+        };
+        let width = style.outline_width_px();
+        let color = style.outline_color.or(style.color).unwrap_or(Rgba {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        });
+        // The stroke is centered on the path, half of it inside the outer edge.
+        let path = outline.outset(-width / 2.0);
+        let mut paint = Paint::new(color.to_color4f(), None);
+        paint.set_style(skia_safe::paint::Style::Stroke);
+        paint.set_stroke_width(width as f32);
+        paint.set_anti_alias(true);
+        self.canvas.draw_rect(
+            Rect::from_xywh(
+                path.x as f32,
+                path.y as f32,
+                path.width as f32,
+                path.height as f32,
+            ),
+            &paint,
+        );
+    }
+
+    /// Whether the highlighted or edited node is in the subtree, which a cached layer
+    /// wouldn't show.
+    fn has_overlay_in(&self, node: &RenderNode) -> bool {
+        let selected = self.selection.as_ref().map(|(id, _)| *id);
+        let edited = self.editing.as_ref().map(|edited| edited.node);
+        selected
+            .into_iter()
+            .chain(edited)
+            .any(|id| find_node(node, id).is_some())
+    }
+
+    fn paint_children(&mut self, children: Vec<&RenderNode>, clip: Option<Rect>) {
+        if children.is_empty() {
+            return;
+        }
+
+        if let Some(clip) = clip {
+            self.canvas.save();
+            self.canvas.clip_rect(clip, None, None);
+        }
+        for child in children {
             self.paint_node(child);
         }
+        if clip.is_some() {
+            self.canvas.restore();
+        }
     }
 }
 
+fn skia_rect(rect: &crate::layout::Rect) -> Rect {
+    Rect::from_xywh(
+        rect.x as f32,
+        rect.y as f32,
+        rect.width as f32,
+        rect.height as f32,
+    )
+}
+
+/// The horizontal and vertical radius of each corner, from the top left clockwise.
+fn corner_radii(style: &crate::style::Style) -> [skia_safe::Vector; 4] {
+    let radius = &style.border_radius;
+    [
+        &radius.top_left,
+        &radius.top_right,
+        &radius.bottom_right,
+        &radius.bottom_left,
+    ]
+    .map(|corner| {
+        let (x, y) = corner
+            .as_ref()
+            .map_or((0.0, 0.0), |r| (r.x.to_px() as f32, r.y.to_px() as f32));
+        skia_safe::Vector::new(x, y)
+    })
+}
+
+/// Fill the area inside `outer` and outside `inner`, which is inside it; nothing where
+/// `inner` reaches past `outer`.
+fn fill_between(
+    canvas: &Canvas,
+    outer: &crate::layout::Rect,
+    inner: &crate::layout::Rect,
+    paint: &Paint,
+) {
+    let (left, top) = (outer.x, outer.y);
+    let (right, bottom) = (outer.x + outer.width, outer.y + outer.height);
+    let (inner_right, inner_bottom) = (inner.x + inner.width, inner.y + inner.height);
+    let strips = [
+        (left, top, right, inner.y),
+        (left, inner_bottom, right, bottom),
+        (left, inner.y, inner.x, inner_bottom),
+        (inner_right, inner.y, right, inner_bottom),
+    ];
+    for (left, top, right, bottom) in strips {
+        if right > left && bottom > top {
+            let strip = Rect::new(left as f32, top as f32, right as f32, bottom as f32);
+            canvas.draw_rect(strip, paint);
+        }
+    }
+}
+
+/// Device-pixel rectangle a layer is rendered into.
+#[derive(Clone, Copy)]
+struct LayerRect {
+    /// Top-left in logical pixels.
+    left: f32,
+    top: f32,
+    width: i32,
+    height: i32,
+    scale: f32,
+}
+
+impl LayerRect {
+    fn for_subtree(node: &RenderNode, scale: f32) -> Self {
+        let b = &node.descendant_bounds;
+        let (left, top, right, bottom) = (b.x, b.y, b.x + b.width, b.y + b.height);
+        let (left, top) = (left.floor() as f32, top.floor() as f32);
+        Self {
+            left,
+            top,
+            width: ((right.ceil() as f32 - left) * scale).ceil().max(1.0) as i32,
+            height: ((bottom.ceil() as f32 - top) * scale).ceil().max(1.0) as i32,
+            scale,
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.width as usize * self.height as usize * 4
+    }
+}
+
+/// Render `node` and its descendants, without the node's own opacity, into an offscreen image.
+fn render_layer(
+    canvas: &Canvas,
+    node: &RenderNode,
+    rect: LayerRect,
+    fonts: &FontProvider,
+    painted_nodes: &mut usize,
+) -> Option<CachedLayer> {
+    let info = canvas
+        .image_info()
+        .with_dimensions((rect.width, rect.height));
+    let mut surface = canvas
+        .new_surface(&info, None)
+        .or_else(|| surfaces::raster_n32_premul((rect.width, rect.height)))?;
+
+    let layer_canvas = surface.canvas();
+    layer_canvas.clear(Color::TRANSPARENT);
+    layer_canvas.scale((rect.scale, rect.scale));
+    layer_canvas.translate((-rect.left, -rect.top));
+
+    let mut painter = Painter::with_fonts(layer_canvas, fonts.clone());
+    painter.paint_node_contents(node);
+    *painted_nodes += painter.painted_nodes;
+
+    Some(CachedLayer::new(
+        surface.image_snapshot(),
+        (rect.left, rect.top),
+        rect.scale,
+        rect.bytes(),
+        node,
+    ))
+}
+
 // Helper method to convert Length to pixels
 #[allow(unused)]
 trait ToPx {
@@ -173,3 +662,6 @@ impl ToColor4f for Rgba {
         )
     }
 }
+
+#[cfg(test)]
+mod layer_tests;
@@ -0,0 +1,258 @@
+use super::Painter;
+use crate::layer_cache::LayerCache;
+use crate::layout::{descendant_bounds, Rect, RenderNode};
+use crate::resources::ResourceCaches;
+use crate::snapshot::Edges;
+use crate::style::{Rgba, Style, WillChange};
+use crate::text::FontProvider;
+use crate::Id;
+use skia_safe::{surfaces, ImageInfo, Surface};
+use std::sync::Arc;
+
+const SIZE: i32 = 200;
+const STATIC_NODES: usize = 1 + 36;
+const SPINNER_NODES: usize = 1 + 8;
+
+fn node(id: u64, bounds: Rect, style: Style, children: Vec<RenderNode>) -> RenderNode {
+    let children: Vec<_> = children.into_iter().map(Arc::new).collect();
+    RenderNode {
+        id: Id::from_u64(id),
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::resolved(&style.padding),
+        border_width: Edges::from(&style.used_border_width()),
+        descendant_bounds: descendant_bounds(bounds, &style, &children),
+        z_index: style.resolved_z_index(),
+        style: Arc::new(style),
+        text: None,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children,
+    }
+}
+
+fn filled(r: u8, g: u8, b: u8) -> Style {
+    Style {
+        background_color: Some(Rgba { r, g, b, a: 255 }),
+        ..Default::default()
+    }
+}
+
+fn spinner(opacity: f64, segment_color: Rgba) -> RenderNode {
+    let segments = (0..8)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::FRAC_PI_4;
+            let bounds = Rect::new(
+                95.0 + angle.cos() * 30.0,
+                95.0 + angle.sin() * 30.0,
+                10.0,
+                10.0,
+            );
+            node(
+                200 + i,
+                bounds,
+                Style {
+                    background_color: Some(segment_color),
+                    ..Default::default()
+                },
+                vec![],
+            )
+        })
+        .collect();
+
+    node(
+        100,
+        Rect::new(60.0, 60.0, 80.0, 80.0),
+        Style {
+            opacity: Some(opacity),
+            will_change: Some(WillChange {
+                transform: false,
+                opacity: true,
+            }),
+            ..filled(240, 240, 255)
+        },
+        segments,
+    )
+}
+
+/// A 6x6 grid of static cells with a spinner on top.
+fn document(spinner: RenderNode) -> RenderNode {
+    let mut children: Vec<RenderNode> = (0..36)
+        .map(|i| {
+            let (col, row) = ((i % 6) as f64, (i / 6) as f64);
+            let shade = (i * 7) as u8;
+            node(
+                10 + i,
+                Rect::new(col * 33.0, row * 33.0, 30.0, 30.0),
+                filled(shade, 128, 255 - shade),
+                vec![],
+            )
+        })
+        .collect();
+    children.push(spinner);
+
+    node(
+        1,
+        Rect::new(0.0, 0.0, SIZE as f64, SIZE as f64),
+        Style::default(),
+        children,
+    )
+}
+
+fn segment_color() -> Rgba {
+    Rgba {
+        r: 200,
+        g: 30,
+        b: 30,
+        a: 255,
+    }
+}
+
+fn paint_frame(surface: &mut Surface, layers: &mut LayerCache, root: &RenderNode) -> usize {
+    let mut painter = Painter::with_layer_cache(surface.canvas(), layers, FontProvider::default());
+    painter.paint(root);
+    painter.painted_nodes()
+}
+
+fn read_pixels(surface: &mut Surface) -> Vec<u8> {
+    let info = ImageInfo::new_n32_premul((SIZE, SIZE), None);
+    let row_bytes = SIZE as usize * 4;
+    let mut pixels = vec![0u8; row_bytes * SIZE as usize];
+    assert!(surface.read_pixels(&info, &mut pixels, row_bytes, (0, 0)));
+    pixels
+}
+
+#[test]
+fn animating_promoted_subtree_only_composites_after_first_frame() {
+    let mut surface = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    let mut layers = LayerCache::default();
+
+    let first = paint_frame(
+        &mut surface,
+        &mut layers,
+        &document(spinner(1.0, segment_color())),
+    );
+    assert_eq!(first, STATIC_NODES + SPINNER_NODES);
+    assert!(layers.contains(Id::from_u64(100)));
+
+    for opacity in [0.8, 0.6, 0.4, 0.2, 0.0, 0.5] {
+        let painted = paint_frame(
+            &mut surface,
+            &mut layers,
+            &document(spinner(opacity, segment_color())),
+        );
+        assert_eq!(
+            painted, STATIC_NODES,
+            "spinner subtree was repainted at opacity {}",
+            opacity
+        );
+    }
+}
+
+#[test]
+fn layer_is_rerendered_when_subtree_content_changes() {
+    let mut surface = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    let mut layers = LayerCache::default();
+
+    paint_frame(
+        &mut surface,
+        &mut layers,
+        &document(spinner(1.0, segment_color())),
+    );
+
+    let recolored = Rgba {
+        r: 30,
+        g: 200,
+        b: 30,
+        a: 255,
+    };
+    let painted = paint_frame(
+        &mut surface,
+        &mut layers,
+        &document(spinner(0.5, recolored)),
+    );
+    assert_eq!(painted, STATIC_NODES + SPINNER_NODES);
+
+    let painted = paint_frame(
+        &mut surface,
+        &mut layers,
+        &document(spinner(0.7, recolored)),
+    );
+    assert_eq!(painted, STATIC_NODES);
+}
+
+#[test]
+fn layer_is_demoted_when_node_stops_being_promoted() {
+    let mut surface = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    let mut layers = LayerCache::default();
+
+    paint_frame(
+        &mut surface,
+        &mut layers,
+        &document(spinner(1.0, segment_color())),
+    );
+    assert_eq!(layers.len(), 1);
+
+    let mut demoted = spinner(1.0, segment_color());
+    demoted.style = Arc::new(Style {
+        will_change: None,
+        ..demoted.style.as_ref().clone()
+    });
+    let painted = paint_frame(&mut surface, &mut layers, &document(demoted));
+
+    assert_eq!(painted, STATIC_NODES + SPINNER_NODES);
+    assert_eq!(layers.len(), 0);
+    assert_eq!(layers.used_bytes(), 0);
+}
+
+#[test]
+fn layers_respect_byte_budget() {
+    let mut surface = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    // The spinner layer is 80x80 pixels.
+    let layer_bytes = 80 * 80 * 4;
+
+    let mut layers = LayerCache::new(ResourceCaches::new(layer_bytes - 1));
+    let root = document(spinner(0.5, segment_color()));
+    for _ in 0..3 {
+        let painted = paint_frame(&mut surface, &mut layers, &root);
+        assert_eq!(painted, STATIC_NODES + SPINNER_NODES);
+    }
+    assert_eq!(layers.len(), 0);
+
+    let mut layers = LayerCache::new(ResourceCaches::new(layer_bytes));
+    paint_frame(&mut surface, &mut layers, &root);
+    assert_eq!(layers.used_bytes(), layer_bytes);
+}
+
+#[test]
+fn composited_output_matches_direct_paint() {
+    let mut direct = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    let mut composited = surfaces::raster_n32_premul((SIZE, SIZE)).expect("raster surface");
+    let mut layers = LayerCache::default();
+
+    for opacity in [1.0, 0.75, 0.3, 0.0] {
+        let root = document(spinner(opacity, segment_color()));
+
+        Painter::new(direct.canvas()).paint(&root);
+        paint_frame(&mut composited, &mut layers, &root);
+
+        let expected = read_pixels(&mut direct);
+        let actual = read_pixels(&mut composited);
+        let max_diff = expected
+            .iter()
+            .zip(&actual)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap_or(0);
+        assert!(
+            max_diff <= 2,
+            "composited output differs by {} at opacity {}",
+            max_diff,
+            opacity
+        );
+    }
+}
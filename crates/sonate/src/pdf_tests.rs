@@ -0,0 +1,199 @@
+use crate::backend::pdf::{page_count, paint_page};
+use crate::backend::raster::RasterBackend;
+use crate::render_tests::{decode, demo_flex_container, differing_pixels, MAX_DIFFERING_PIXELS};
+use crate::{Edges, Engine, EngineError, Id, PageSize, PdfOptions};
+
+const NO_MARGINS: Edges = Edges {
+    top: 0.0,
+    right: 0.0,
+    bottom: 0.0,
+    left: 0.0,
+};
+
+/// A PDF page as found in the file.
+#[derive(Debug, PartialEq)]
+struct Page {
+    media_box: [f64; 4],
+}
+
+/// The pages of a PDF, read from the page objects' dictionaries. Page dictionaries are
+/// never compressed, so this works without a PDF library; content streams are skipped.
+fn parse_pages(pdf: &[u8]) -> Vec<Page> {
+    let text = String::from_utf8_lossy(pdf);
+    assert!(text.starts_with("%PDF-"), "missing PDF header");
+    assert!(text.trim_end().ends_with("%%EOF"), "missing PDF trailer");
+
+    let mut pages = Vec::new();
+    for object in text.split("endobj") {
+        let dictionary = object.split("stream").next().unwrap_or_default();
+        let is_page = dictionary
+            .match_indices("/Type /Page")
+            .any(|(i, m)| !dictionary[i + m.len()..].starts_with('s'));
+        if !is_page {
+            continue;
+        }
+
+        let media_box = dictionary
+            .split_once("/MediaBox [")
+            .and_then(|(_, rest)| rest.split_once(']'))
+            .map(|(numbers, _)| {
+                numbers
+                    .split_whitespace()
+                    .map(|n| n.parse::<f64>().expect("numeric media box"))
+                    .collect::<Vec<_>>()
+            })
+            .expect("page without media box");
+        pages.push(Page {
+            media_box: media_box.try_into().expect("four media box numbers"),
+        });
+    }
+    pages
+}
+
+/// `count` stacked boxes of 100 by 400 px.
+fn tall_document(engine: &Engine, count: u64) {
+    engine.add_stylesheet(
+        r#"
+        .column { display: flex; flex-direction: column; }
+        .tall { width: 100px; height: 400px; background-color: blue; }
+    "#,
+    );
+    engine.set_attribute(engine.root_id(), "class".to_owned(), "column".to_owned());
+    for n in 1..=count {
+        let node = engine.create_node(Id::from_u64(n), None);
+        engine.set_parent(engine.root_id(), node);
+        engine.set_attribute(node, "class".to_owned(), "tall".to_owned());
+    }
+}
+
+#[test]
+fn tall_document_spans_pages() {
+    let engine = Engine::new();
+    tall_document(&engine, 5);
+
+    // Letter with 0.5 in margins leaves 720 pt, or 960 px, of height per page.
+    let options = PdfOptions {
+        page_size: PageSize::LETTER,
+        ..Default::default()
+    };
+    assert_eq!(options.content_size(), Some((720.0, 960.0)));
+
+    let pages = parse_pages(&engine.export_pdf(options).unwrap());
+    assert_eq!(pages.len(), 3, "2000 px over pages of 960 px");
+    for page in &pages {
+        assert_eq!(page.media_box, [0.0, 0.0, 612.0, 792.0]);
+    }
+
+    // The document was laid out at the content width.
+    let snapshot = engine.snapshot().unwrap();
+    assert_eq!(snapshot.bounds.width, 720.0);
+}
+
+#[test]
+fn page_count_rounds_up_and_is_at_least_one() {
+    assert_eq!(page_count(0.0, 100.0), 1);
+    assert_eq!(page_count(100.0, 100.0), 1);
+    assert_eq!(page_count(100.000001, 100.0), 1);
+    assert_eq!(page_count(100.5, 100.0), 2);
+    assert_eq!(page_count(f64::NAN, 100.0), 1);
+}
+
+#[test]
+fn empty_document_prints_one_page_of_the_requested_size() {
+    let engine = Engine::new();
+    let options = PdfOptions {
+        page_size: PageSize {
+            width: 300.0,
+            height: 200.5,
+        },
+        margins: NO_MARGINS,
+        scale: 1.0,
+    };
+
+    let pages = parse_pages(&engine.export_pdf(options).unwrap());
+    assert_eq!(
+        pages,
+        vec![Page {
+            media_box: [0.0, 0.0, 300.0, 200.5]
+        }]
+    );
+}
+
+#[test]
+fn margins_without_room_for_content_are_an_error() {
+    let engine = Engine::new();
+    let options = PdfOptions {
+        page_size: PageSize {
+            width: 100.0,
+            height: 100.0,
+        },
+        margins: Edges {
+            top: 50.0,
+            right: 50.0,
+            bottom: 50.0,
+            left: 50.0,
+        },
+        ..Default::default()
+    };
+    assert!(matches!(
+        engine.export_pdf(options),
+        Err(EngineError::RenderFailed)
+    ));
+}
+
+#[test]
+fn single_page_matches_offscreen_raster() {
+    let engine = Engine::new();
+    demo_flex_container(&engine);
+    let options = PdfOptions {
+        page_size: PageSize {
+            width: 200.0,
+            height: 100.0,
+        },
+        margins: NO_MARGINS,
+        scale: 1.0,
+    };
+    assert_eq!(parse_pages(&engine.export_pdf(options).unwrap()).len(), 1);
+
+    // PDFs can't be rasterized here, so paint the page to pixels the way the PDF is painted.
+    let root = engine.get_current_snapshot().unwrap();
+    let fonts = engine.resources.caches().fonts();
+    let mut page = RasterBackend::new(200.0, 100.0, 1.0).unwrap();
+    page.render(|canvas| paint_page(canvas, &root, fonts, &options, 0));
+    let page = decode(&page.encode_png().unwrap());
+
+    let raster = decode(&engine.render_to_png(200.0, 100.0, 1.0).unwrap());
+    assert_eq!((page.width, page.height), (raster.width, raster.height));
+    let allowed = (raster.width * raster.height) as f64 * MAX_DIFFERING_PIXELS;
+    assert!(differing_pixels(&raster, &page) as f64 <= allowed);
+}
+
+#[test]
+fn straddling_node_is_split_across_pages() {
+    let engine = Engine::new();
+    tall_document(&engine, 1);
+    // 300 px of content per page: the 400 px box fills page 0 and a third of page 1.
+    let options = PdfOptions {
+        page_size: PageSize {
+            width: 150.0,
+            height: 300.0,
+        },
+        margins: NO_MARGINS,
+        scale: 1.0,
+    };
+    assert_eq!(parse_pages(&engine.export_pdf(options).unwrap()).len(), 2);
+
+    let root = engine.get_current_snapshot().unwrap();
+    let fonts = engine.resources.caches().fonts();
+    let mut page = RasterBackend::new(150.0, 300.0, 1.0).unwrap();
+    page.render(|canvas| paint_page(canvas, &root, fonts, &options, 1));
+    let page = decode(&page.encode_png().unwrap());
+
+    let pixel = |x: i32, y: i32| {
+        let i = ((y * page.width + x) * 4) as usize;
+        page.rgba[i..i + 4].to_vec()
+    };
+    assert_eq!(pixel(50, 50), vec![0, 0, 255, 255]);
+    assert_eq!(pixel(50, 150), vec![255, 255, 255, 255]);
+    assert_eq!(pixel(120, 50), vec![255, 255, 255, 255]);
+}
@@ -0,0 +1,144 @@
+//! Pointer capture: while a node has it, the pointer's moves and the button's release go to
+//! that node wherever the pointer is, and report a drag of it.
+//!
+//! The button going down over a node with an [`crate::EventKind::Drag`] listener captures
+//! the pointer for it until the button goes up; [`crate::Engine::set_pointer_capture`]
+//! captures it for any node until then, or until
+//! [`crate::Engine::release_pointer_capture`].
+
+use crate::layout::RenderNode;
+use crate::{Id, WindowId};
+
+/// Which part of a drag [`crate::Params::on_drag`] and drag listeners are called for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragPhase {
+    /// The pointer moved for the first time since it was captured.
+    Start,
+    Move,
+    /// The button went up, or the node was removed. Only follows a [`DragPhase::Start`].
+    End,
+}
+
+/// A drag of the node that has the pointer captured, as of a pointer move or release.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drag {
+    /// How far the pointer is from where the capture began, in logical pixels.
+    pub dx: f64,
+    pub dy: f64,
+    pub phase: DragPhase,
+}
+
+/// Which node has the pointer captured, if any, and how far it was dragged.
+#[derive(Default)]
+pub(crate) struct PointerCapture {
+    capture: Option<Capture>,
+}
+
+struct Capture {
+    window: WindowId,
+    node: Id,
+    /// Where the pointer was when the capture began, which drags are measured from. `None`
+    /// for a capture set between events, until the pointer is next seen.
+    origin: Option<(f64, f64)>,
+    /// Whether the drag started, so it gets an end.
+    started: bool,
+    /// Where the last drag was, which the end of one for a removed node repeats.
+    delta: (f64, f64),
+}
+
+impl PointerCapture {
+    /// Capture the pointer for `node` in `window`, where it isn't seen yet, ending the
+    /// capture it had without reporting that drag's end.
+    pub fn capture(&mut self, window: WindowId, node: Id) {
+        self.capture = Some(Capture {
+            window,
+            node,
+            origin: None,
+            started: false,
+            delta: (0.0, 0.0),
+        });
+    }
+
+    /// The button went down at `position` in the window, over `draggable`, the nearest node
+    /// under it with a drag listener. A capture that listeners set for the press measures
+    /// its drag from there; otherwise `draggable` captures the pointer.
+    pub fn pressed(&mut self, window: WindowId, position: (f64, f64), draggable: Option<Id>) {
+        if let Some(capture) = self.capture.as_mut() {
+            if capture.window == window && capture.origin.is_none() {
+                capture.origin = Some(position);
+                return;
+            }
+        }
+        if let Some(node) = draggable {
+            self.capture(window, node);
+            self.pressed(window, position, None);
+        }
+    }
+
+    /// The node that has the pointer captured in the window, which its moves and the
+    /// release go to.
+    pub fn captured(&self, window: WindowId) -> Option<Id> {
+        self.capture
+            .as_ref()
+            .filter(|capture| capture.window == window)
+            .map(|capture| capture.node)
+    }
+
+    /// The pointer moved to `position` in the window, whose latest tree is `tree`. Returns
+    /// the node that has it captured and its drag, if one started or goes on. A node that
+    /// is no longer in the tree loses the capture, and its drag ends where it last was.
+    pub fn moved(
+        &mut self,
+        window: WindowId,
+        position: (f64, f64),
+        tree: &RenderNode,
+    ) -> Option<(Id, Drag)> {
+        let capture = self
+            .capture
+            .as_mut()
+            .filter(|capture| capture.window == window)?;
+        if tree.path_to(capture.node).is_empty() {
+            let capture = self.capture.take()?;
+            let (dx, dy) = capture.delta;
+            let phase = DragPhase::End;
+            return capture
+                .started
+                .then_some((capture.node, Drag { dx, dy, phase }));
+        }
+        let Some(origin) = capture.origin else {
+            capture.origin = Some(position);
+            return None;
+        };
+        let phase = if capture.started {
+            DragPhase::Move
+        } else {
+            DragPhase::Start
+        };
+        capture.started = true;
+        capture.delta = (position.0 - origin.0, position.1 - origin.1);
+        let (dx, dy) = capture.delta;
+        Some((capture.node, Drag { dx, dy, phase }))
+    }
+
+    /// The button went up at `position` in the window, which releases the capture there.
+    /// Returns the node that had it and the end of its drag, if one started.
+    pub fn released(&mut self, window: WindowId, position: (f64, f64)) -> Option<(Id, Drag)> {
+        let capture = self.capture.take_if(|capture| capture.window == window)?;
+        let origin = capture.origin.filter(|_| capture.started)?;
+        let drag = Drag {
+            dx: position.0 - origin.0,
+            dy: position.1 - origin.1,
+            phase: DragPhase::End,
+        };
+        Some((capture.node, drag))
+    }
+
+    /// Release the capture, wherever it is, without reporting the end of its drag. Returns
+    /// whether a node had it.
+    pub fn release(&mut self) -> bool {
+        self.capture.take().is_some()
+    }
+}
+
+#[cfg(test)]
+mod pointer_capture_tests;
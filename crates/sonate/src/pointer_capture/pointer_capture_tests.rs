@@ -0,0 +1,144 @@
+use super::{Drag, DragPhase, PointerCapture};
+use crate::layout::{Rect, RenderNode};
+use crate::snapshot::Edges;
+use crate::style::Style;
+use crate::{Id, WindowId};
+use std::sync::Arc;
+
+const WINDOW: WindowId = WindowId::PRIMARY;
+
+fn node(id: u64, children: Vec<RenderNode>) -> RenderNode {
+    let bounds = Rect::new(0.0, 0.0, 100.0, 100.0);
+    RenderNode {
+        id: Id::from_u64(id),
+        bounds,
+        margin: Edges::default(),
+        padding: Edges::default(),
+        border_width: Edges::default(),
+        descendant_bounds: bounds,
+        z_index: 0,
+        style: Arc::new(Style::default()),
+        text: None,
+        title: None,
+        role: None,
+        aria_label: None,
+        editable: false,
+        image: None,
+        text_truncated: false,
+        children: children.into_iter().map(Arc::new).collect(),
+    }
+}
+
+/// A root with the node 1 in it.
+fn tree() -> RenderNode {
+    node(0, vec![node(1, vec![])])
+}
+
+fn drag(dx: f64, dy: f64, phase: DragPhase) -> Option<(Id, Drag)> {
+    Some((Id::from_u64(1), Drag { dx, dy, phase }))
+}
+
+#[test]
+fn drags_start_move_and_end_measured_from_the_press() {
+    let tree = tree();
+    let mut capture = PointerCapture::default();
+
+    capture.pressed(WINDOW, (10.0, 10.0), Some(Id::from_u64(1)));
+    assert_eq!(capture.captured(WINDOW), Some(Id::from_u64(1)));
+    assert_eq!(
+        capture.moved(WINDOW, (15.0, 12.0), &tree),
+        drag(5.0, 2.0, DragPhase::Start)
+    );
+    // Wherever the pointer goes, outside the node's bounds too.
+    assert_eq!(
+        capture.moved(WINDOW, (250.0, -20.0), &tree),
+        drag(240.0, -30.0, DragPhase::Move)
+    );
+    assert_eq!(
+        capture.released(WINDOW, (260.0, -20.0)),
+        drag(250.0, -30.0, DragPhase::End)
+    );
+    assert_eq!(capture.captured(WINDOW), None);
+    assert_eq!(capture.moved(WINDOW, (0.0, 0.0), &tree), None);
+}
+
+#[test]
+fn pressing_without_a_drag_listener_captures_nothing() {
+    let tree = tree();
+    let mut capture = PointerCapture::default();
+
+    capture.pressed(WINDOW, (10.0, 10.0), None);
+    assert_eq!(capture.captured(WINDOW), None);
+    assert_eq!(capture.moved(WINDOW, (20.0, 10.0), &tree), None);
+    assert_eq!(capture.released(WINDOW, (20.0, 10.0)), None);
+}
+
+#[test]
+fn releasing_without_moving_ends_no_drag() {
+    let mut capture = PointerCapture::default();
+
+    capture.pressed(WINDOW, (10.0, 10.0), Some(Id::from_u64(1)));
+    assert_eq!(capture.released(WINDOW, (10.0, 10.0)), None);
+    assert_eq!(capture.captured(WINDOW), None);
+}
+
+#[test]
+fn captures_set_during_the_press_are_measured_from_it() {
+    let tree = tree();
+    let mut capture = PointerCapture::default();
+
+    // As a mouse down listener would, before the press reaches the capture.
+    capture.capture(WINDOW, Id::from_u64(1));
+    capture.pressed(WINDOW, (10.0, 10.0), None);
+    assert_eq!(
+        capture.moved(WINDOW, (12.0, 10.0), &tree),
+        drag(2.0, 0.0, DragPhase::Start)
+    );
+}
+
+#[test]
+fn captures_set_between_events_are_measured_from_the_next_move() {
+    let tree = tree();
+    let mut capture = PointerCapture::default();
+
+    capture.capture(WINDOW, Id::from_u64(1));
+    assert_eq!(capture.moved(WINDOW, (40.0, 40.0), &tree), None);
+    assert_eq!(
+        capture.moved(WINDOW, (45.0, 30.0), &tree),
+        drag(5.0, -10.0, DragPhase::Start)
+    );
+    assert!(capture.release());
+    assert!(!capture.release());
+    assert_eq!(capture.moved(WINDOW, (50.0, 30.0), &tree), None);
+}
+
+#[test]
+fn removing_the_node_ends_its_drag() {
+    let mut capture = PointerCapture::default();
+
+    capture.pressed(WINDOW, (10.0, 10.0), Some(Id::from_u64(1)));
+    assert_eq!(
+        capture.moved(WINDOW, (20.0, 20.0), &tree()),
+        drag(10.0, 10.0, DragPhase::Start)
+    );
+    let removed = node(0, vec![]);
+    assert_eq!(
+        capture.moved(WINDOW, (30.0, 30.0), &removed),
+        drag(10.0, 10.0, DragPhase::End)
+    );
+    assert_eq!(capture.captured(WINDOW), None);
+    assert_eq!(capture.released(WINDOW, (30.0, 30.0)), None);
+}
+
+#[test]
+fn captures_belong_to_their_window() {
+    let tree = tree();
+    let other = WindowId::secondary(1);
+    let mut capture = PointerCapture::default();
+
+    capture.pressed(WINDOW, (10.0, 10.0), Some(Id::from_u64(1)));
+    assert_eq!(capture.captured(other), None);
+    assert_eq!(capture.moved(other, (20.0, 20.0), &tree), None);
+    assert_eq!(capture.released(other, (20.0, 20.0)), None);
+    assert_eq!(capture.captured(WINDOW), Some(Id::from_u64(1)));
+}
@@ -0,0 +1,92 @@
+// Only awaited through `AsyncEngine`; the data thread reports progress regardless.
+#![cfg_attr(not(feature = "async"), allow(dead_code))]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::task::Waker;
+
+/// How far the data thread got with acknowledgment markers, which are numbered commands sent
+/// after the commands they acknowledge. The data thread publishes its progress only when it
+/// runs out of commands, so a burst of commands wakes waiters once rather than per command.
+#[derive(Default)]
+pub(crate) struct Progress {
+    next_sequence: AtomicU64,
+    state: Mutex<ProgressState>,
+}
+
+#[derive(Default)]
+pub(crate) struct ProgressState {
+    /// The highest marker handled, so every command sent before it has been applied.
+    pub applied: u64,
+    /// The highest marker whose preceding commands are in the published snapshot.
+    pub laid_out: u64,
+    /// Set when the data thread stopped; nothing advances anymore.
+    pub closed: bool,
+    pub waiters: Vec<(Goal, Waker)>,
+    /// How many times published progress woke waiters.
+    pub wakeups: u64,
+}
+
+/// What a waiter waits for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Goal {
+    Applied(u64),
+    LaidOut(u64),
+}
+
+impl ProgressState {
+    pub fn reached(&self, goal: Goal) -> bool {
+        self.closed
+            || match goal {
+                Goal::Applied(sequence) => self.applied >= sequence,
+                Goal::LaidOut(sequence) => self.laid_out >= sequence,
+            }
+    }
+}
+
+impl Progress {
+    /// Number the next marker. Take the number after sending the commands it acknowledges
+    /// and send the marker after taking it, so that markers handled out of order still
+    /// only pass commands that were applied.
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    /// Record the data thread's progress and wake the waiters it satisfies.
+    pub fn publish(&self, applied: u64, laid_out: u64) {
+        let mut state = self.state.lock().unwrap();
+        if (state.applied, state.laid_out) == (applied, laid_out) {
+            return;
+        }
+        state.applied = applied;
+        state.laid_out = laid_out;
+        let reached = take_reached(&mut state);
+        drop(state);
+        reached.into_iter().for_each(Waker::wake);
+    }
+
+    /// Resolve every waiter, as the data thread stopped.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        let reached = take_reached(&mut state);
+        drop(state);
+        reached.into_iter().for_each(Waker::wake);
+    }
+
+    pub fn state(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        self.state.lock().unwrap()
+    }
+}
+
+/// Remove the waiters whose goal was reached, to be woken once the lock is released.
+fn take_reached(state: &mut ProgressState) -> Vec<Waker> {
+    let (reached, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut state.waiters)
+        .into_iter()
+        .partition(|(goal, _)| state.reached(*goal));
+    state.waiters = waiting;
+    if !reached.is_empty() {
+        state.wakeups += 1;
+    }
+    reached.into_iter().map(|(_, waker)| waker).collect()
+}
@@ -0,0 +1,80 @@
+//! Finding nodes by selector, like `querySelectorAll` does in browsers.
+
+use crate::css_parser::parse_selector_chain;
+use crate::layout::{Document, Node};
+use crate::style::Selector;
+use crate::Id;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Compound selectors, each matching a descendant of a node the one before matches, like
+/// `.list .item`: the selectors the stylesheet supports, and descendant combinators.
+#[derive(Debug)]
+pub(crate) struct QuerySelector {
+    compounds: Vec<Selector>,
+}
+
+impl QuerySelector {
+    /// `None` if `text` isn't a selector.
+    pub fn parse(text: &str) -> Option<Self> {
+        let compounds = parse_selector_chain(text)?;
+        Some(Self { compounds })
+    }
+
+    /// Ids of the nodes in the document and the trees of secondary windows that match, in
+    /// document order, each node before its children. At most `limit` of them.
+    pub fn matching(&self, document: &Document, focused: Option<Id>, limit: usize) -> Vec<Id> {
+        let mut found = Vec::new();
+        let roots = std::iter::once(document.root_id()).chain(document.window_roots().to_vec());
+        let mut pending: Vec<_> = roots.rev().filter_map(|id| document.get_node(id)).collect();
+        while let Some(node) = pending.pop() {
+            if found.len() == limit {
+                break;
+            }
+            let node = node.borrow();
+            if self.matches(&node, document, focused) {
+                found.push(node.id);
+            }
+            pending.extend(node.children.iter().rev().cloned());
+        }
+        found
+    }
+
+    fn matches(&self, node: &Node, document: &Document, focused: Option<Id>) -> bool {
+        let (last, ancestors) = self.compounds.split_last().unwrap();
+        if !matches(last, node, focused) {
+            return false;
+        }
+        // With only descendant combinators, matching each compound to the nearest ancestor
+        // it can match never misses a match further up.
+        let mut remaining = ancestors.iter().rev();
+        let mut wanted = remaining.next();
+        let mut ancestor = ancestor_of(node, document);
+        while let Some(compound) = wanted {
+            let Some(candidate) = ancestor else {
+                return false;
+            };
+            let candidate = candidate.borrow();
+            if matches(compound, &candidate, focused) {
+                wanted = remaining.next();
+            }
+            ancestor = ancestor_of(&candidate, document);
+        }
+        true
+    }
+}
+
+fn matches(selector: &Selector, node: &Node, focused: Option<Id>) -> bool {
+    selector.matches(
+        node.tag.as_deref(),
+        &node.attributes,
+        focused == Some(node.id),
+    )
+}
+
+fn ancestor_of(node: &Node, document: &Document) -> Option<Rc<RefCell<Node>>> {
+    document.get_node(node.parent?)
+}
+
+#[cfg(test)]
+mod query_tests;
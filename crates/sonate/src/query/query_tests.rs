@@ -0,0 +1,112 @@
+use super::QuerySelector;
+use crate::{Engine, Id};
+
+/// A list of items, some done, in a panel, with a stray item outside of it:
+///
+/// ```text
+/// 1 div.panel#main
+///   2 ul.list
+///     3 li.item
+///     4 li.item.done[data-state="done"]
+///       5 span.item-label
+///   6 div.footer.list
+///     7 li.item
+/// 8 li.item
+/// ```
+fn list() -> Engine {
+    let engine = Engine::new();
+    let nodes = [
+        (1, 0, "div", "panel", Some(("id", "main"))),
+        (2, 1, "ul", "list", None),
+        (3, 2, "li", "item", None),
+        (4, 2, "li", "item done", Some(("data-state", "done"))),
+        (5, 4, "span", "item-label", None),
+        (6, 1, "div", "footer list", None),
+        (7, 6, "li", "item", None),
+        (8, 0, "li", "item", None),
+    ];
+    for (id, parent, tag, class, attribute) in nodes {
+        let id = engine.create_element(Id::from_u64(id), tag, None);
+        engine.set_parent(Id::from_u64(parent), id);
+        engine.set_attribute(id, "class".to_owned(), class.to_owned());
+        if let Some((key, value)) = attribute {
+            engine.set_attribute(id, key.to_owned(), value.to_owned());
+        }
+    }
+    engine
+}
+
+fn ids(ids: &[u64]) -> Vec<Id> {
+    ids.iter().copied().map(Id::from_u64).collect()
+}
+
+#[test]
+fn simple_selectors_find_nodes_in_document_order() {
+    let engine = list();
+    assert_eq!(engine.query(".item"), ids(&[3, 4, 7, 8]));
+    assert_eq!(engine.query("#main"), ids(&[1]));
+    assert_eq!(engine.query("DIV"), ids(&[1, 6]));
+    assert_eq!(engine.query("[data-state=done]"), ids(&[4]));
+    // The class is matched as a whole.
+    assert_eq!(engine.query(".item-label"), ids(&[5]));
+}
+
+#[test]
+fn nodes_with_several_classes_match_each() {
+    let engine = list();
+    assert_eq!(engine.query(".done"), ids(&[4]));
+    assert_eq!(engine.query(".item.done"), ids(&[4]));
+    assert_eq!(engine.query("li.done.item"), ids(&[4]));
+    assert_eq!(engine.query(".list"), ids(&[2, 6]));
+    assert_eq!(engine.query("div.list"), ids(&[6]));
+}
+
+#[test]
+fn descendant_selectors_match_at_any_depth() {
+    let engine = list();
+    assert_eq!(engine.query(".panel .item"), ids(&[3, 4, 7]));
+    assert_eq!(engine.query(".panel .list .item"), ids(&[3, 4, 7]));
+    assert_eq!(engine.query("ul .item"), ids(&[3, 4]));
+    assert_eq!(engine.query("#main .done span"), ids(&[5]));
+    assert_eq!(engine.query("  .list   .done  "), ids(&[4]));
+    // Ancestors are matched in order, and a node isn't its own ancestor.
+    assert_eq!(engine.query(".item .list"), ids(&[]));
+    assert_eq!(engine.query(".done .done"), ids(&[]));
+}
+
+#[test]
+fn selectors_that_match_nothing_find_nothing() {
+    let engine = list();
+    assert!(engine.query(".missing").is_empty());
+    assert!(engine.query(".footer .done").is_empty());
+    assert_eq!(engine.query_one(".missing"), None);
+    // Neither do ones that aren't selectors.
+    assert!(engine.query("").is_empty());
+    assert!(engine.query(".item >").is_empty());
+    assert!(engine.query("{").is_empty());
+}
+
+#[test]
+fn query_one_finds_the_first_match() {
+    let engine = list();
+    assert_eq!(engine.query_one(".item"), Some(Id::from_u64(3)));
+    assert_eq!(engine.query_one(".footer .item"), Some(Id::from_u64(7)));
+}
+
+#[test]
+fn queries_see_the_changes_sent_before_them() {
+    let engine = list();
+    engine.set_attribute(Id::from_u64(3), "class".to_owned(), "item done".to_owned());
+    engine.set_parent(Id::from_u64(2), Id::from_u64(8));
+    assert_eq!(engine.query(".done"), ids(&[3, 4]));
+    assert_eq!(engine.query(".list .item"), ids(&[3, 4, 8, 7]));
+}
+
+#[test]
+fn only_selectors_parse() {
+    assert!(QuerySelector::parse("*").is_some());
+    assert!(QuerySelector::parse("a b c").is_some());
+    assert!(QuerySelector::parse("a > b").is_none());
+    assert!(QuerySelector::parse("a, b").is_none());
+    assert!(QuerySelector::parse(".a:hover").is_none());
+}
@@ -0,0 +1,222 @@
+use crate::backend::raster::RasterBackend;
+use crate::backend::ResizeStress;
+use crate::painter::Painter;
+use crate::{Engine, EngineError, GpuMemoryStats, Id};
+use skia_safe::{image::CachingHint, AlphaType, ColorType, Data, Image, ImageInfo};
+use std::path::Path;
+
+/// Rendered at 2x; regenerate with `SONATE_BLESS=1 cargo test -p sonate render_tests`
+/// after an intended painter change, and review the image before committing it.
+const GOLDEN_FLEX_CONTAINER: &str = "src/render_tests/flex_container.png";
+
+/// A pixel differs if any channel is further off than this, which absorbs anti-aliasing
+/// differences between Skia versions and CPUs.
+const CHANNEL_TOLERANCE: u8 = 32;
+
+/// Share of pixels allowed to differ.
+pub(crate) const MAX_DIFFERING_PIXELS: f64 = 0.01;
+
+pub(crate) struct Pixels {
+    pub width: i32,
+    pub height: i32,
+    pub rgba: Vec<u8>,
+}
+
+pub(crate) fn decode(png: &[u8]) -> Pixels {
+    let image = Image::from_encoded(Data::new_copy(png)).expect("valid PNG");
+    let (width, height) = (image.width(), image.height());
+    let info = ImageInfo::new(
+        (width, height),
+        ColorType::RGBA8888,
+        AlphaType::Unpremul,
+        None,
+    );
+    let mut rgba = vec![0; info.min_row_bytes() * height as usize];
+    assert!(image.read_pixels(
+        &info,
+        &mut rgba,
+        info.min_row_bytes(),
+        (0, 0),
+        CachingHint::Allow
+    ));
+
+    Pixels {
+        width,
+        height,
+        rgba,
+    }
+}
+
+pub(crate) fn differing_pixels(expected: &Pixels, actual: &Pixels) -> usize {
+    expected
+        .rgba
+        .chunks_exact(4)
+        .zip(actual.rgba.chunks_exact(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+        })
+        .count()
+}
+
+fn assert_matches_golden(png: &[u8], golden: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(golden);
+    if std::env::var_os("SONATE_BLESS").is_some() {
+        std::fs::write(&path, png).expect("write golden image");
+    }
+
+    let expected = decode(&std::fs::read(&path).expect("read golden image"));
+    let actual = decode(png);
+    assert_eq!(
+        (actual.width, actual.height),
+        (expected.width, expected.height)
+    );
+
+    let differing = differing_pixels(&expected, &actual);
+    let allowed = (expected.width * expected.height) as f64 * MAX_DIFFERING_PIXELS;
+    assert!(
+        differing as f64 <= allowed,
+        "{differing} pixels differ from {golden}, at most {allowed} may"
+    );
+}
+
+/// The flex container from the rust_usage example, with sizes instead of text: glyphs depend
+/// on the fonts installed on the machine, so they don't belong in a golden image.
+pub(crate) fn demo_flex_container(engine: &Engine) {
+    engine.add_stylesheet(
+        r#"
+        .flex_container {
+            display: flex;
+            flex-direction: row;
+            gap: 10px;
+            padding: 10px;
+        }
+
+        .red_box {
+            background-color: #ff0000;
+            border-width: 2px;
+            border-color: black;
+            border-radius: 8px;
+            margin: 10px;
+            width: 60px;
+            height: 40px;
+        }
+
+        .green_box {
+            background-color: green;
+            border-radius: 12px 4px;
+            width: 80px;
+            height: 60px;
+        }
+    "#,
+    );
+
+    let root = engine.root_id();
+    let a = engine.create_node(Id::from_u64(1), None);
+    let b = engine.create_node(Id::from_u64(2), None);
+    engine.set_parent(root, a);
+    engine.set_parent(root, b);
+    engine.set_attribute(root, "class".to_owned(), "flex_container".to_owned());
+    engine.set_attribute(a, "class".to_owned(), "red_box".to_owned());
+    engine.set_attribute(b, "class".to_owned(), "green_box".to_owned());
+}
+
+#[test]
+fn flex_container_matches_golden_image() {
+    let engine = Engine::new();
+    demo_flex_container(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let png = engine.render_to_png(200.0, 100.0, 2.0).unwrap();
+    assert_matches_golden(&png, GOLDEN_FLEX_CONTAINER);
+}
+
+#[test]
+fn scale_factor_sets_device_size() {
+    let engine = Engine::new();
+    demo_flex_container(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let one_x = decode(&engine.render_to_png(200.0, 100.0, 1.0).unwrap());
+    assert_eq!((one_x.width, one_x.height), (200, 100));
+
+    let two_x = decode(&engine.render_to_png(200.0, 100.0, 2.0).unwrap());
+    assert_eq!((two_x.width, two_x.height), (400, 200));
+
+    // Same picture: the red box's background at (50, 40) is at (100, 80) at 2x.
+    let pixel = |pixels: &Pixels, x: i32, y: i32| {
+        let i = ((y * pixels.width + x) * 4) as usize;
+        pixels.rgba[i..i + 4].to_vec()
+    };
+    assert_eq!(pixel(&one_x, 50, 40), vec![255, 0, 0, 255]);
+    assert_eq!(pixel(&two_x, 100, 80), vec![255, 0, 0, 255]);
+}
+
+#[test]
+fn empty_size_is_an_error() {
+    let engine = Engine::new();
+    assert!(matches!(
+        engine.render_to_png(0.0, 100.0, 1.0),
+        Err(EngineError::RenderFailed)
+    ));
+}
+
+#[test]
+fn offscreen_target_survives_many_resizes() {
+    const RESIZES: usize = 500;
+    let engine = Engine::new();
+    demo_flex_container(&engine);
+    engine.layout_headless(200.0, 100.0).unwrap();
+    let root = engine.get_current_snapshot().unwrap();
+    let fonts = engine.resources.caches().fonts();
+
+    let mut target = RasterBackend::new(200.0, 100.0, 2.0).unwrap();
+    let mut stress = ResizeStress::default();
+    for n in 0..RESIZES {
+        let (width, height) = if n % 2 == 0 {
+            (120.0, 60.0)
+        } else {
+            (200.0, 100.0)
+        };
+        target.resize(width, height).unwrap();
+        target.render(|canvas| Painter::with_fonts(canvas, fonts.clone()).paint(&root));
+        stress.record(target.gpu_memory_stats()).unwrap();
+    }
+    assert_eq!(target.gpu_memory_stats().surfaces, 1);
+
+    // The last resize was back to the full size, and an empty size keeps it.
+    assert_eq!(target.resize(0.0, 100.0), None);
+    let pixels = decode(&target.encode_png().unwrap());
+    assert_eq!((pixels.width, pixels.height), (400, 200));
+}
+
+#[test]
+fn resize_stress_catches_growth() {
+    let stats = GpuMemoryStats {
+        cache_bytes: 1 << 20,
+        cache_resources: 10,
+        surfaces: 2,
+        swapchain_images: 2,
+    };
+    let mut stress = ResizeStress::default();
+    stress.record(stats).unwrap();
+    stress.record(stats).unwrap();
+
+    let leaked_surface = GpuMemoryStats {
+        surfaces: 3,
+        ..stats
+    };
+    assert!(stress.record(leaked_surface).is_err());
+
+    let bloated_cache = GpuMemoryStats {
+        cache_bytes: 64 << 20,
+        ..stats
+    };
+    assert!(stress.record(bloated_cache).is_err());
+}
+
+#[test]
+fn no_gpu_memory_without_a_window() {
+    assert_eq!(Engine::new().gpu_memory_stats(), None);
+}
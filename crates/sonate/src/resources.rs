@@ -0,0 +1,334 @@
+use crate::commands::Command;
+use crate::text::FontProvider;
+use parking_lot::Mutex;
+use skia_safe::{Data, Image};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Weak};
+
+/// Byte budget of the caches an engine creates for itself.
+pub const DEFAULT_CACHE_BUDGET_BYTES: usize = 256 * 1024 * 1024;
+
+/// Decoded images and registered fonts, shareable between engines. The windows' cached
+/// layers are charged to the same budget as the images.
+///
+/// Cloning is cheap and every clone refers to the same caches. Pass one instance to several
+/// engines through [`crate::EngineOptions::shared_caches`] so they decode each image once and
+/// see each other's registered fonts.
+#[derive(Clone)]
+pub struct ResourceCaches {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    budget_bytes: usize,
+    images: Mutex<ImageCache>,
+    fonts: FontProvider,
+    engines: Mutex<Vec<Weak<CacheAttachment>>>,
+    next_engine_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct ImageCache {
+    entries: HashMap<String, ImageEntry>,
+    used_bytes: usize,
+    /// Bytes of the layers the windows cache, which images are evicted to make room for.
+    layer_bytes: usize,
+    clock: u64,
+}
+
+struct ImageEntry {
+    image: ImageHandle,
+    /// Engines that loaded this image, for per-engine accounting.
+    users: HashSet<u64>,
+    last_used: u64,
+}
+
+/// A decoded image owned by the cache.
+///
+/// While any handle other than the cache's own is alive (e.g. in a snapshot), the image
+/// is not evicted.
+#[derive(Clone)]
+pub struct ImageHandle(Arc<DecodedImage>);
+
+struct DecodedImage {
+    image: Image,
+    bytes: usize,
+}
+
+impl ImageHandle {
+    pub fn width(&self) -> i32 {
+        self.0.image.width()
+    }
+
+    pub fn height(&self) -> i32 {
+        self.0.image.height()
+    }
+
+    pub(crate) fn image(&self) -> &Image {
+        &self.0.image
+    }
+
+    fn is_referenced_elsewhere(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+}
+
+/// Handles are equal when they share the decoded image.
+impl PartialEq for ImageHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Why an image couldn't be provided.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ImageError {
+    /// The loader returned no data.
+    Load,
+    /// The data isn't an image format Skia can decode.
+    Decode,
+}
+
+/// Cache usage, either for the whole cache or attributed to one engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub images: usize,
+    pub image_bytes: usize,
+    pub registered_fonts: usize,
+}
+
+impl ResourceCaches {
+    pub fn new(budget_bytes: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                budget_bytes,
+                images: Mutex::new(ImageCache::default()),
+                fonts: FontProvider::default(),
+                engines: Mutex::new(Vec::new()),
+                next_engine_id: AtomicU64::new(1),
+            }),
+        }
+    }
+
+    pub fn budget_bytes(&self) -> usize {
+        self.shared.budget_bytes
+    }
+
+    /// Totals across all attached engines.
+    pub fn stats(&self) -> CacheStats {
+        let images = self.shared.images.lock();
+        CacheStats {
+            images: images.entries.len(),
+            image_bytes: images.used_bytes,
+            registered_fonts: self.shared.fonts.registered_count(),
+        }
+    }
+
+    pub(crate) fn fonts(&self) -> FontProvider {
+        self.shared.fonts.clone()
+    }
+
+    /// Attach an engine. The engine's data thread is notified through `sender` when fonts change.
+    pub(crate) fn attach(&self, sender: SyncSender<Command>) -> Arc<CacheAttachment> {
+        let attachment = Arc::new(CacheAttachment {
+            id: self.shared.next_engine_id.fetch_add(1, Ordering::Relaxed),
+            sender: Mutex::new(sender),
+            fonts_changed: AtomicBool::new(false),
+            caches: self.clone(),
+        });
+
+        let mut engines = self.shared.engines.lock();
+        engines.retain(|engine| engine.strong_count() > 0);
+        engines.push(Arc::downgrade(&attachment));
+        attachment
+    }
+
+    fn image(
+        &self,
+        engine_id: u64,
+        key: &str,
+        load: impl FnOnce() -> Option<Vec<u8>>,
+    ) -> Result<ImageHandle, ImageError> {
+        {
+            let mut images = self.shared.images.lock();
+            images.clock += 1;
+            let clock = images.clock;
+            if let Some(entry) = images.entries.get_mut(key) {
+                entry.users.insert(engine_id);
+                entry.last_used = clock;
+                return Ok(entry.image.clone());
+            }
+        }
+
+        // Load and decode without holding the lock; other engines may use the cache meanwhile.
+        let data = load().ok_or(ImageError::Load)?;
+        let image = Image::from_encoded(Data::new_copy(&data)).ok_or(ImageError::Decode)?;
+        let bytes = image.width().max(0) as usize * image.height().max(0) as usize * 4;
+        let handle = ImageHandle(Arc::new(DecodedImage { image, bytes }));
+
+        let mut images = self.shared.images.lock();
+        if let Some(entry) = images.entries.get_mut(key) {
+            // Another engine decoded it at the same time; keep the first one.
+            entry.users.insert(engine_id);
+            return Ok(entry.image.clone());
+        }
+
+        let clock = images.clock;
+        images.used_bytes += bytes;
+        images.entries.insert(
+            key.to_owned(),
+            ImageEntry {
+                image: handle.clone(),
+                users: HashSet::from([engine_id]),
+                last_used: clock,
+            },
+        );
+        let budget_bytes = self.shared.budget_bytes.saturating_sub(images.layer_bytes);
+        images.evict_to(budget_bytes);
+
+        Ok(handle)
+    }
+
+    /// Charge a layer of `bytes` to the budget, evicting images to make room. Returns
+    /// `false`, charging nothing, if the images still in use and the other layers leave
+    /// too little.
+    pub(crate) fn reserve_layer(&self, bytes: usize) -> bool {
+        let mut images = self.shared.images.lock();
+        let layer_bytes = images.layer_bytes + bytes;
+        images.evict_to(self.shared.budget_bytes.saturating_sub(layer_bytes));
+        if images.used_bytes + layer_bytes > self.shared.budget_bytes {
+            return false;
+        }
+        images.layer_bytes = layer_bytes;
+        true
+    }
+
+    /// Give back what [`Self::reserve_layer`] charged for a layer that was dropped.
+    pub(crate) fn release_layer(&self, bytes: usize) {
+        self.shared.images.lock().layer_bytes -= bytes;
+    }
+
+    fn register_font(&self, family: Option<&str>, data: &[u8]) -> Option<String> {
+        let family = self.shared.fonts.register(family, data)?;
+
+        let engines: Vec<Arc<CacheAttachment>> = {
+            let mut engines = self.shared.engines.lock();
+            engines.retain(|engine| engine.strong_count() > 0);
+            engines.iter().filter_map(Weak::upgrade).collect()
+        };
+        // Without holding the lock, nor waiting for room: the flag is seen with the next
+        // batch of commands, which a full queue is sure to bring.
+        for engine in engines {
+            engine.fonts_changed.store(true, Ordering::Release);
+            let _ = engine.sender.lock().try_send(Command::FontsChanged);
+        }
+
+        Some(family)
+    }
+
+    fn engine_stats(&self, engine_id: u64) -> CacheStats {
+        let images = self.shared.images.lock();
+        let (count, bytes) = images
+            .entries
+            .values()
+            .filter(|entry| entry.users.contains(&engine_id))
+            .fold((0, 0), |(count, bytes), entry| {
+                (count + 1, bytes + entry.image.0.bytes)
+            });
+
+        CacheStats {
+            images: count,
+            image_bytes: bytes,
+            registered_fonts: self.shared.fonts.registered_count(),
+        }
+    }
+
+    fn detach(&self, engine_id: u64) {
+        let mut images = self.shared.images.lock();
+        for entry in images.entries.values_mut() {
+            entry.users.remove(&engine_id);
+        }
+    }
+}
+
+impl Default for ResourceCaches {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_BUDGET_BYTES)
+    }
+}
+
+impl ImageCache {
+    /// Evict least recently used images until under budget, skipping images that are
+    /// still referenced outside the cache.
+    fn evict_to(&mut self, budget_bytes: usize) {
+        while self.used_bytes > budget_bytes {
+            let Some(key) = self
+                .entries
+                .iter()
+                .filter(|(_, entry)| !entry.image.is_referenced_elsewhere())
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                // Everything left is in use.
+                break;
+            };
+
+            if let Some(entry) = self.entries.remove(&key) {
+                self.used_bytes -= entry.image.0.bytes;
+            }
+        }
+    }
+}
+
+/// One engine's view of a [`ResourceCaches`]. Dropped together with the last clone of the engine.
+pub(crate) struct CacheAttachment {
+    id: u64,
+    sender: Mutex<SyncSender<Command>>,
+    /// Set when a font is registered, until the engine's data thread takes it up.
+    fonts_changed: AtomicBool,
+    caches: ResourceCaches,
+}
+
+impl CacheAttachment {
+    pub fn caches(&self) -> &ResourceCaches {
+        &self.caches
+    }
+
+    pub fn image(
+        &self,
+        key: &str,
+        load: impl FnOnce() -> Option<Vec<u8>>,
+    ) -> Result<ImageHandle, ImageError> {
+        self.caches.image(self.id, key, load)
+    }
+
+    pub fn register_font(&self, family: Option<&str>, data: &[u8]) -> Option<String> {
+        self.caches.register_font(family, data)
+    }
+
+    /// Send the engine's data thread a command, from a thread other than the engine's own.
+    /// Returns whether the data thread still runs.
+    pub fn send(&self, command: Command) -> bool {
+        self.sender.lock().send(command).is_ok()
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.caches.engine_stats(self.id)
+    }
+
+    /// Whether fonts were registered since the last call.
+    pub fn take_fonts_changed(&self) -> bool {
+        self.fonts_changed.swap(false, Ordering::AcqRel)
+    }
+}
+
+impl Drop for CacheAttachment {
+    fn drop(&mut self) {
+        self.caches.detach(self.id);
+    }
+}
+
+#[cfg(test)]
+mod resource_cache_tests;
@@ -0,0 +1,268 @@
+use super::*;
+use crate::layout::RenderNode;
+use crate::style::Direction;
+use crate::text::FontSpec;
+use crate::{Engine, EngineHealth, EngineOptions, Id};
+use skia_safe::FontMgr;
+use std::cell::Cell;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// An 8x8 red PNG, 256 bytes once decoded.
+const PNG_8X8: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x08, 0x08, 0x02, 0x00, 0x00, 0x00, 0x4b, 0x6d, 0x29,
+    0xdc, 0x00, 0x00, 0x00, 0x12, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0xf8, 0xcf, 0xc0, 0x80,
+    0x15, 0x61, 0x17, 0x1d, 0xb4, 0x12, 0x00, 0x28, 0xff, 0x3f, 0xc1, 0x6e, 0xec, 0xdf, 0x61, 0x00,
+    0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+const PNG_8X8_BYTES: usize = 8 * 8 * 4;
+
+fn shared_engines(caches: &ResourceCaches) -> (Engine, Engine) {
+    let options = EngineOptions {
+        shared_caches: Some(caches.clone()),
+        ..Default::default()
+    };
+    (
+        Engine::with_options(options.clone()),
+        Engine::with_options(options),
+    )
+}
+
+#[test]
+fn engines_sharing_caches_decode_an_image_once() {
+    let caches = ResourceCaches::default();
+    let (a, b) = shared_engines(&caches);
+    let loads = Cell::new(0);
+    let load = || {
+        loads.set(loads.get() + 1);
+        Some(PNG_8X8.to_vec())
+    };
+
+    let from_a = a.load_image("logo.png", load).expect("decoded image");
+    let from_b = b.load_image("logo.png", load).expect("decoded image");
+
+    assert_eq!(loads.get(), 1);
+    assert_eq!((from_b.width(), from_b.height()), (8, 8));
+    assert!(Arc::ptr_eq(&from_a.0, &from_b.0));
+
+    assert_eq!(caches.stats().images, 1);
+    assert_eq!(a.cache_stats().image_bytes, PNG_8X8_BYTES);
+    assert_eq!(b.cache_stats().image_bytes, PNG_8X8_BYTES);
+}
+
+#[test]
+fn separate_engines_keep_separate_caches() {
+    let a = Engine::new();
+    let b = Engine::new();
+    let loads = Cell::new(0);
+    let load = || {
+        loads.set(loads.get() + 1);
+        Some(PNG_8X8.to_vec())
+    };
+
+    a.load_image("logo.png", load);
+    b.load_image("logo.png", load);
+
+    assert_eq!(loads.get(), 2);
+}
+
+#[test]
+fn eviction_skips_images_referenced_by_another_engine() {
+    let caches = ResourceCaches::new(2 * PNG_8X8_BYTES);
+    let (a, b) = shared_engines(&caches);
+    let load = || Some(PNG_8X8.to_vec());
+
+    // Engine A keeps its image alive, as a snapshot referencing it would.
+    let held_by_a = a.load_image("a.png", load).expect("decoded image");
+    drop(b.load_image("b1.png", load));
+    drop(b.load_image("b2.png", load));
+
+    assert_eq!(caches.stats().images, 2);
+    assert_eq!(caches.stats().image_bytes, 2 * PNG_8X8_BYTES);
+    assert_eq!(a.cache_stats().images, 1);
+    assert_eq!(b.cache_stats().images, 1);
+
+    // `a.png` is the least recently used entry but must survive; it's still cached.
+    let mut reloaded = false;
+    let again = a
+        .load_image("a.png", || {
+            reloaded = true;
+            None
+        })
+        .expect("still cached");
+    assert!(!reloaded);
+    assert!(Arc::ptr_eq(&again.0, &held_by_a.0));
+}
+
+#[test]
+fn layers_are_charged_to_the_budget_of_the_images() {
+    let caches = ResourceCaches::new(2 * PNG_8X8_BYTES);
+    let (a, _) = shared_engines(&caches);
+    let held = a.load_image("held.png", || Some(PNG_8X8.to_vec()));
+    drop(a.load_image("unused.png", || Some(PNG_8X8.to_vec())));
+
+    // The unused image makes room; the held one doesn't, nor do layers of other windows.
+    assert!(caches.reserve_layer(PNG_8X8_BYTES));
+    assert_eq!(caches.stats().images, 1);
+    assert!(!caches.reserve_layer(1));
+
+    caches.release_layer(PNG_8X8_BYTES);
+    drop(held);
+    assert!(caches.reserve_layer(2 * PNG_8X8_BYTES));
+    assert_eq!(caches.stats().images, 0);
+}
+
+#[test]
+fn dropping_an_engine_removes_its_accounting() {
+    let caches = ResourceCaches::default();
+    let (a, b) = shared_engines(&caches);
+    a.load_image("logo.png", || Some(PNG_8X8.to_vec()));
+
+    drop(a);
+
+    assert_eq!(caches.stats().images, 1);
+    assert_eq!(b.cache_stats().images, 0);
+}
+
+/// Font data of an installed family that measures differently from the fallback typeface.
+fn distinct_system_font(text: &str, fallback_family: &str) -> Option<(Vec<u8>, f64)> {
+    let fonts = FontProvider::default();
+    let width = |family: &str| {
+        let spec = FontSpec {
+            families: vec![family.to_owned()],
+            size_px: 20,
+            weight: 400,
+            italic: false,
+            direction: Direction::Ltr,
+            letter_spacing_px: 0,
+            line_height_px: None,
+        };
+        fonts.make_font(&spec).measure_str(text, None).0 as f64
+    };
+    let fallback_width = width(fallback_family);
+
+    let families: Vec<String> = FontMgr::default().family_names().collect();
+    families.into_iter().find_map(|family| {
+        let candidate_width = width(&family);
+        if (candidate_width - fallback_width).abs() < 0.5 {
+            return None;
+        }
+        let spec = FontSpec {
+            families: vec![family],
+            size_px: 20,
+            weight: 400,
+            italic: false,
+            direction: Direction::Ltr,
+            letter_spacing_px: 0,
+            line_height_px: None,
+        };
+        let (data, _) = fonts.make_font(&spec).typeface().to_font_data()?;
+        Some((data, candidate_width))
+    })
+}
+
+fn wait_for_node_width(engine: &Engine, id: Id, matches: impl Fn(f64) -> bool) -> Option<f64> {
+    fn find(node: &RenderNode, id: Id) -> Option<&RenderNode> {
+        if node.id == id {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find(child, id))
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        let width = engine
+            .get_current_snapshot()
+            .and_then(|snapshot| find(&snapshot, id).map(|node| node.bounds.width));
+        if let Some(width) = width.filter(|w| matches(*w)) {
+            return Some(width);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    None
+}
+
+#[test]
+fn font_registered_through_one_engine_relayouts_the_other() {
+    const FAMILY: &str = "Sonate Shared Test Font";
+    const TEXT: &str = "Hello shared fonts";
+
+    let Some((data, expected_width)) = distinct_system_font(TEXT, FAMILY) else {
+        eprintln!("skipping: no installed font measures differently from the fallback");
+        return;
+    };
+
+    let caches = ResourceCaches::default();
+    let (a, b) = shared_engines(&caches);
+
+    b.add_stylesheet(&format!(
+        ".label {{ width: auto; height: auto; font-family: {}; font-size: 20px; }}",
+        FAMILY
+    ));
+    let label = b.create_node(Id::from_u64(1), Some(TEXT.to_owned()));
+    b.set_attribute(label, "class".to_owned(), "label".to_owned());
+    b.set_parent(b.root_id(), label);
+
+    let before = wait_for_node_width(&b, label, |w| w > 0.0).expect("initial layout");
+    assert!((before - expected_width).abs() >= 0.5);
+
+    assert_eq!(
+        a.register_font(Some(FAMILY), &data).ok(),
+        Some(FAMILY.to_owned())
+    );
+    assert_eq!(b.cache_stats().registered_fonts, 1);
+
+    let after = wait_for_node_width(&b, label, |w| (w - before).abs() >= 0.5)
+        .expect("engine B relayouts after the font is registered");
+    assert!((after - expected_width).abs() < 0.5);
+}
+
+#[test]
+fn an_engine_whose_queue_is_full_measures_with_a_font_registered_meanwhile() {
+    const FAMILY: &str = "Sonate Busy Test Font";
+    const TEXT: &str = "Hello busy fonts";
+
+    let Some((data, expected_width)) = distinct_system_font(TEXT, FAMILY) else {
+        eprintln!("skipping: no installed font measures differently from the fallback");
+        return;
+    };
+
+    let caches = ResourceCaches::default();
+    let (blocked, blocked_receiver) = mpsc::channel();
+    let (release, release_receiver) = mpsc::channel::<()>();
+    let release_receiver = Mutex::new(release_receiver);
+    let busy = Engine::with_options(EngineOptions {
+        shared_caches: Some(caches.clone()),
+        command_capacity: 1,
+        // Keeps the data thread from taking commands until released.
+        on_engine_error: Some(Arc::new(move |_: &EngineHealth| {
+            let _ = blocked.send(());
+            let _ = release_receiver.lock().recv();
+        })),
+        ..Default::default()
+    });
+
+    busy.add_stylesheet(&format!(
+        ".label {{ width: auto; height: auto; font-family: {}; font-size: 20px; }}",
+        FAMILY
+    ));
+    let label = busy.create_node(Id::from_u64(1), Some(TEXT.to_owned()));
+    busy.set_attribute(label, "class".to_owned(), "label".to_owned());
+    busy.set_parent(busy.root_id(), label);
+    let before = wait_for_node_width(&busy, label, |w| w > 0.0).expect("initial layout");
+    assert!((before - expected_width).abs() >= 0.5);
+
+    busy.send(Command::Panic).unwrap();
+    blocked_receiver
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    // Fills the queue, so the font's `FontsChanged` finds no room.
+    busy.set_attribute(label, "title".to_owned(), "busy".to_owned());
+    assert!(caches.register_font(Some(FAMILY), &data).is_some());
+    release.send(()).unwrap();
+
+    let after = wait_for_node_width(&busy, label, |w| (w - before).abs() >= 0.5)
+        .expect("the busy engine relayouts after the font is registered");
+    assert!((after - expected_width).abs() < 0.5);
+}
@@ -0,0 +1,104 @@
+use crate::layout::RenderNode;
+use crate::style::UserSelect;
+use crate::text::{line_boxes, FontSpec, LineStyle, TextMeasurer};
+use crate::{Id, WindowId};
+use std::ops::Range;
+
+/// Characters of one text node the user dragged across.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Selection {
+    pub window: WindowId,
+    pub node: Id,
+    /// Where the drag started, in characters.
+    pub anchor: usize,
+    /// Where the drag is now, in characters.
+    pub focus: usize,
+}
+
+impl Selection {
+    pub fn range(&self) -> Range<usize> {
+        self.anchor.min(self.focus)..self.anchor.max(self.focus)
+    }
+
+    /// The selected part of `text`, which may have changed since the selection was made.
+    pub fn text_of(&self, text: &str) -> String {
+        let range = self.range();
+        text.chars().skip(range.start).take(range.len()).collect()
+    }
+}
+
+/// The topmost node at the point if it has text that can be selected: its `user-select` is
+/// `text`, or it has none and its nearest ancestor with one says `text`.
+pub(crate) fn selectable_text_at(root: &RenderNode, x: f64, y: f64) -> Option<&RenderNode> {
+    let path = root.find_element_at_position(x, y);
+    let nodes = root.nodes_on_path(&path);
+    if nodes.len() < path.len() {
+        return None;
+    }
+
+    let node = *nodes.last()?;
+    node.text.as_ref()?;
+    let user_select = nodes.iter().rev().find_map(|node| node.style.user_select);
+    (user_select == Some(UserSelect::Text)).then_some(node)
+}
+
+/// The node with the id in the subtree, if it's there.
+pub(crate) fn find_node(node: &RenderNode, id: Id) -> Option<&RenderNode> {
+    if node.id == id {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| find_node(child, id))
+}
+
+/// Where the painter starts the node's first line of text, horizontally, as its
+/// `text-align` places it.
+pub(crate) fn text_origin_x(measurer: &dyn TextMeasurer, node: &RenderNode, text: &str) -> f64 {
+    let style = &node.style;
+    let content = node.content_box();
+    let lines = line_boxes(
+        measurer,
+        text,
+        &FontSpec::from_style(style),
+        content.width,
+        LineStyle::from_style(style),
+    );
+    content.x + lines.first().map_or(0.0, |line| line.x)
+}
+
+/// The caret stop nearest to `x`, measured from where the text starts, as a character
+/// index: 0 before the middle of the first cluster, the character count past the middle of
+/// the last. Never inside a cluster, such as between a letter and its combining marks.
+pub(crate) fn char_index_at(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    x: f64,
+) -> usize {
+    let mut nearest = (0, f64::INFINITY);
+    for (index, stop_x) in measurer.caret_stops(text, font) {
+        let distance = (x - stop_x).abs();
+        if distance <= nearest.1 {
+            nearest = (index, distance);
+        }
+    }
+    nearest.0
+}
+
+/// How far from where the text starts the caret before the `index`th character is; inside a
+/// cluster, that's before the cluster. Indices past the end are at the end.
+pub(crate) fn x_of_char_index(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    index: usize,
+) -> f64 {
+    measurer
+        .caret_stops(text, font)
+        .into_iter()
+        .take_while(|&(stop, _)| stop <= index)
+        .last()
+        .map_or(0.0, |(_, x)| x)
+}
+
+#[cfg(test)]
+mod selection_tests;
@@ -0,0 +1,156 @@
+use super::{char_index_at, find_node, selectable_text_at, x_of_char_index, Selection};
+use crate::layout::RenderNode;
+use crate::style::Direction;
+use crate::text::{FontSpec, SkiaTextMeasurer, TestTextMeasurer, TextMeasurer};
+use crate::{Engine, Id, WindowId};
+use std::sync::Arc;
+
+/// 10px text: the test measurer makes every character 6px wide.
+fn font() -> FontSpec {
+    FontSpec {
+        families: vec!["Arial".to_owned()],
+        size_px: 10,
+        weight: 400,
+        italic: false,
+        direction: Direction::Ltr,
+        letter_spacing_px: 0,
+        line_height_px: None,
+    }
+}
+
+#[test]
+fn x_positions_map_to_the_nearest_character_boundary() {
+    let measurer = TestTextMeasurer;
+    let text = "Hello";
+
+    assert_eq!(char_index_at(&measurer, text, &font(), -5.0), 0);
+    assert_eq!(char_index_at(&measurer, text, &font(), 2.9), 0);
+    assert_eq!(char_index_at(&measurer, text, &font(), 3.0), 1);
+    assert_eq!(char_index_at(&measurer, text, &font(), 8.0), 1);
+    assert_eq!(char_index_at(&measurer, text, &font(), 9.5), 2);
+    assert_eq!(char_index_at(&measurer, text, &font(), 27.0), 5);
+    assert_eq!(char_index_at(&measurer, text, &font(), 500.0), 5);
+    assert_eq!(char_index_at(&measurer, "", &font(), 10.0), 0);
+}
+
+#[test]
+fn indices_count_characters_rather_than_bytes() {
+    let measurer = TestTextMeasurer;
+    let text = "héllo wörld";
+
+    assert_eq!(char_index_at(&measurer, text, &font(), 13.0), 2);
+    assert_eq!(char_index_at(&measurer, text, &font(), 500.0), 11);
+    assert_eq!(x_of_char_index(&measurer, text, &font(), 2), 12.0);
+    assert_eq!(x_of_char_index(&measurer, text, &font(), 11), 66.0);
+    assert_eq!(x_of_char_index(&measurer, text, &font(), 99), 66.0);
+    assert_eq!(x_of_char_index(&measurer, text, &font(), 0), 0.0);
+
+    let selection = Selection {
+        window: WindowId::PRIMARY,
+        node: Id::from_u64(1),
+        anchor: 9,
+        focus: 1,
+    };
+    assert_eq!(selection.range(), 1..9);
+    assert_eq!(selection.text_of(text), "éllo wör");
+}
+
+#[test]
+fn hit_testing_round_trips_on_cluster_boundaries() {
+    let measurer = SkiaTextMeasurer::new();
+    // Clusters of a letter and its combining marks start at characters 0, 2 and 5.
+    let text = "e\u{301}a\u{301}\u{302}b";
+    let stops: Vec<usize> = measurer
+        .caret_stops(text, &font())
+        .into_iter()
+        .map(|(index, _)| index)
+        .collect();
+    assert_eq!(stops, [0, 2, 5, 6]);
+
+    for index in stops {
+        let x = x_of_char_index(&measurer, text, &font(), index);
+        assert_eq!(char_index_at(&measurer, text, &font(), x), index);
+    }
+    // Inside a cluster is before it.
+    assert_eq!(
+        x_of_char_index(&measurer, text, &font(), 3),
+        x_of_char_index(&measurer, text, &font(), 2)
+    );
+    let end = x_of_char_index(&measurer, text, &font(), 6);
+    for step in 0..=20 {
+        let x = end * step as f64 / 20.0;
+        let index = char_index_at(&measurer, text, &font(), x);
+        assert!([0, 2, 5, 6].contains(&index), "{index} at {x}");
+    }
+}
+
+/// A text node in a container, with the container's and the text's `user-select`.
+fn document(container: &str, text: &str) -> (Engine, Id, Id) {
+    let engine = Engine::new();
+    engine.add_stylesheet(&format!(
+        ".box {{ {container} }} .text {{ padding-left: 4px; {text} }}"
+    ));
+    let container = engine.create_node(Id::from_u64(1), None);
+    let text = engine.create_node(Id::from_u64(2), Some("Select me".to_owned()));
+    engine.set_parent(engine.root_id(), container);
+    engine.set_parent(container, text);
+    engine.set_attribute(container, "class".to_owned(), "box".to_owned());
+    engine.set_attribute(text, "class".to_owned(), "text".to_owned());
+    engine.layout_headless(400.0, 200.0).unwrap();
+    (engine, container, text)
+}
+
+fn snapshot(engine: &Engine) -> Arc<RenderNode> {
+    engine
+        .get_window_snapshot(WindowId::PRIMARY)
+        .unwrap()
+        .tree
+        .clone()
+}
+
+#[test]
+fn text_is_selectable_where_the_nearest_user_select_says_so() {
+    let selectable = |container, text| {
+        let (engine, _, text_id) = document(container, text);
+        let snapshot = snapshot(&engine);
+        selectable_text_at(&snapshot, 10.0, 10.0).map(|node| node.id == text_id)
+    };
+
+    assert_eq!(selectable("", ""), None);
+    assert_eq!(selectable("user-select: text;", ""), Some(true));
+    assert_eq!(selectable("", "user-select: text;"), Some(true));
+    assert_eq!(selectable("user-select: text;", "user-select: none;"), None);
+    assert_eq!(
+        selectable("user-select: none;", "user-select: text;"),
+        Some(true)
+    );
+}
+
+#[test]
+fn dragging_selects_within_the_pressed_node() {
+    let (engine, container, text) = document("user-select: text;", "");
+    let snapshot = snapshot(&engine);
+    let bounds = find_node(&snapshot, text).unwrap().bounds;
+    let y = bounds.y + 5.0;
+
+    // Pressed in the padding, before the first character, and dragged past the end.
+    engine.start_selection(WindowId::PRIMARY, &snapshot, bounds.x + 1.0, y);
+    assert_eq!(engine.get_selection(), None);
+    engine.extend_selection(WindowId::PRIMARY, bounds.x + 1000.0);
+    assert_eq!(engine.get_selection(), Some((text, 0..9)));
+    assert_eq!(engine.selected_text().as_deref(), Some("Select me"));
+
+    // Dragging back to the start leaves nothing selected.
+    engine.extend_selection(WindowId::PRIMARY, bounds.x - 50.0);
+    assert_eq!(engine.get_selection(), None);
+    assert_eq!(engine.selected_text(), None);
+
+    // Pressing outside of text clears the selection.
+    engine.extend_selection(WindowId::PRIMARY, bounds.x + 1000.0);
+    let container_bounds = find_node(&snapshot, container).unwrap().bounds;
+    let below = container_bounds.y + container_bounds.height + 20.0;
+    engine.start_selection(WindowId::PRIMARY, &snapshot, 10.0, below);
+    assert_eq!(engine.get_selection(), None);
+    engine.extend_selection(WindowId::PRIMARY, bounds.x + 1000.0);
+    assert_eq!(engine.get_selection(), None);
+}
@@ -0,0 +1,160 @@
+use crate::{
+    layout::{Rect, RenderNode},
+    style::{Directional, Length, Radius, Rgba},
+    Id,
+};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// An immutable copy of the laid out document, as returned by [`crate::Engine::snapshot`].
+///
+/// The shape is stable: fields are only ever added. All lengths are in logical pixels and
+/// bounds are border boxes in document coordinates. With the `serde` feature every type
+/// here implements `Serialize` and `Deserialize`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RenderSnapshot {
+    pub id: Id,
+    pub bounds: Rect,
+    /// Resolved `margin`, around the border box; `auto` is 0.
+    pub margin: Edges,
+    /// Resolved `padding`, between the border and the content box.
+    pub padding: Edges,
+    pub background_color: Option<Rgba>,
+    pub border: BorderInfo,
+    pub text: Option<String>,
+    /// The `role` attribute.
+    pub role: Option<String>,
+    /// The `aria-label` attribute.
+    pub aria_label: Option<String>,
+    /// `false` for `visibility: hidden`; the subtree isn't painted or hit.
+    pub visible: bool,
+    /// Resolved `z-index`; `auto` is 0. Siblings paint in (z-index, document order), and
+    /// negative ones behind their parent.
+    pub z_index: i32,
+    /// In document order. `display: none` children are left out.
+    pub children: Vec<RenderSnapshot>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BorderInfo {
+    /// Zero on every side when `border-style` is `none` or `hidden`.
+    pub width: Edges,
+    pub color: Option<Rgba>,
+    pub radius: Corners,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Edges {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Edges {
+    /// The sides of a resolved `margin` or `padding`, unset ones 0.
+    pub(crate) fn resolved(lengths: &Directional<Option<Length>>) -> Self {
+        Self::from(&lengths.resolved())
+    }
+}
+
+impl From<&Directional<Length>> for Edges {
+    fn from(lengths: &Directional<Length>) -> Self {
+        Self {
+            top: lengths.top.to_px(),
+            right: lengths.right.to_px(),
+            bottom: lengths.bottom.to_px(),
+            left: lengths.left.to_px(),
+        }
+    }
+}
+
+/// Horizontal and vertical radius of each corner.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Corners {
+    pub top_left: [f64; 2],
+    pub top_right: [f64; 2],
+    pub bottom_right: [f64; 2],
+    pub bottom_left: [f64; 2],
+}
+
+impl RenderSnapshot {
+    /// The node with `id` in this subtree, if any.
+    pub fn find(&self, id: Id) -> Option<&RenderSnapshot> {
+        if self.id == id {
+            return Some(self);
+        }
+        self.children.iter().find_map(|child| child.find(id))
+    }
+
+    /// The border box, which `bounds` is.
+    pub fn border_box(&self) -> Rect {
+        self.bounds
+    }
+
+    /// The border box inside the border, where the background is painted.
+    pub fn padding_box(&self) -> Rect {
+        self.bounds.inset_by(&self.border.width)
+    }
+
+    /// The padding box inside the padding, where children and text are laid out.
+    pub fn content_box(&self) -> Rect {
+        self.padding_box().inset_by(&self.padding)
+    }
+
+    /// Pretty-printed JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("snapshot is always serializable")
+    }
+}
+
+impl From<&RenderNode> for RenderSnapshot {
+    fn from(node: &RenderNode) -> Self {
+        let style = &node.style;
+        let radius = |radius: &Option<Radius>| {
+            radius
+                .as_ref()
+                .map_or([0.0, 0.0], |r| [r.x.to_px(), r.y.to_px()])
+        };
+
+        Self {
+            id: node.id,
+            bounds: node.bounds,
+            margin: node.margin,
+            padding: node.padding,
+            background_color: style.background_color,
+            border: BorderInfo {
+                width: node.border_width,
+                color: style.border_color.top,
+                radius: Corners {
+                    top_left: radius(&style.border_radius.top_left),
+                    top_right: radius(&style.border_radius.top_right),
+                    bottom_right: radius(&style.border_radius.bottom_right),
+                    bottom_left: radius(&style.border_radius.bottom_left),
+                },
+            },
+            text: node.text.clone(),
+            role: node.role.clone(),
+            aria_label: node.aria_label.clone(),
+            visible: !style.is_hidden(),
+            z_index: node.z_index,
+            children: node
+                .children
+                .iter()
+                .map(|child| (&**child).into())
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod snapshot_tests;
+
+#[cfg(all(test, feature = "serde"))]
+mod golden_tests;
@@ -0,0 +1,213 @@
+//! Golden layouts. Each `tests/golden/*.html` scenario is markup with a `<style>`, whose
+//! `#scenario` element is laid out, and the whole snapshot must match the JSON file of the
+//! same name, so refactors of the layout engines can't move anything unnoticed. Lengths may
+//! be off by their field's tolerance in `TOLERANCES`; every other field must be equal.
+//!
+//! After a change that moves things on purpose, record the snapshots again with
+//! `UPDATE_GOLDENS=1 cargo test -p sonate --features serde golden`, and review the diff of
+//! the files.
+
+use super::{Corners, Edges, RenderSnapshot};
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{build_render_tree, Rect};
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+/// Relative to the crate, where tests run.
+const GOLDEN: &str = "tests/golden";
+
+/// How many pixels off the lengths in each field may be, by the start of its name.
+const TOLERANCES: &[(&str, f64)] = &[
+    ("bounds", 0.01),
+    ("margin", 0.001),
+    ("padding", 0.001),
+    ("border.width", 0.001),
+    ("border.radius", 0.001),
+];
+
+#[test]
+fn layouts_match_the_goldens() {
+    let update = std::env::var_os("UPDATE_GOLDENS").is_some_and(|value| value == "1");
+    let mut scenarios: Vec<PathBuf> = fs::read_dir(GOLDEN)
+        .expect("couldn't read the goldens directory")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "html")
+        })
+        .collect();
+    scenarios.sort();
+    assert!(!scenarios.is_empty(), "no scenarios in {GOLDEN}");
+
+    let mut differences = Vec::new();
+    for path in &scenarios {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let html = fs::read_to_string(path).expect("couldn't read the scenario");
+        let (ctx, nodes_by_id) = load_html_test_example(&html, "scenario");
+        assert!(
+            nodes_by_id.contains_key("scenario"),
+            "{name} has no #scenario element"
+        );
+        let actual = RenderSnapshot::from(&build_render_tree(ctx.document.root_node()));
+
+        let golden = path.with_extension("json");
+        if update {
+            fs::write(&golden, actual.to_json() + "\n").expect("couldn't write the golden");
+            continue;
+        }
+        let Ok(json) = fs::read_to_string(&golden) else {
+            differences.push(format!("{name}: no {}", golden.display()));
+            continue;
+        };
+        let expected: RenderSnapshot =
+            serde_json::from_str(&json).unwrap_or_else(|err| panic!("{name}.json: {err}"));
+        let mut diff = Diff::default();
+        diff.node(&expected, &actual, "");
+        differences.extend(diff.lines.iter().map(|line| format!("{name}: {line}")));
+    }
+
+    assert!(
+        differences.is_empty(),
+        "{} differences from the goldens; run with UPDATE_GOLDENS=1 if they are meant:\n{}",
+        differences.len(),
+        differences.join("\n")
+    );
+}
+
+/// The fields that differ, a line each with the path to their node.
+#[derive(Default)]
+struct Diff {
+    lines: Vec<String>,
+}
+
+impl Diff {
+    fn node(&mut self, expected: &RenderSnapshot, actual: &RenderSnapshot, parents: &str) {
+        let node = format!("{parents}{}", expected.id.as_u64());
+        if expected.id != actual.id {
+            // The trees are shaped differently from here on.
+            self.equal(&node, "id", &expected.id.as_u64(), &actual.id.as_u64());
+            return;
+        }
+        self.rect(&node, "bounds", &expected.bounds, &actual.bounds);
+        self.edges(&node, "margin", &expected.margin, &actual.margin);
+        self.edges(&node, "padding", &expected.padding, &actual.padding);
+        let (border, actual_border) = (&expected.border, &actual.border);
+        self.edges(&node, "border.width", &border.width, &actual_border.width);
+        self.equal(&node, "border.color", &border.color, &actual_border.color);
+        self.corners(
+            &node,
+            "border.radius",
+            &border.radius,
+            &actual_border.radius,
+        );
+        self.equal(
+            &node,
+            "background_color",
+            &expected.background_color,
+            &actual.background_color,
+        );
+        self.equal(&node, "text", &expected.text, &actual.text);
+        self.equal(&node, "role", &expected.role, &actual.role);
+        self.equal(
+            &node,
+            "aria_label",
+            &expected.aria_label,
+            &actual.aria_label,
+        );
+        self.equal(&node, "visible", &expected.visible, &actual.visible);
+        self.equal(&node, "z_index", &expected.z_index, &actual.z_index);
+
+        let children = |snapshot: &RenderSnapshot| snapshot.children.len();
+        self.equal(&node, "children", &children(expected), &children(actual));
+        let parents = format!("{node} > ");
+        for (expected, actual) in expected.children.iter().zip(&actual.children) {
+            self.node(expected, actual, &parents);
+        }
+    }
+
+    fn equal<T: PartialEq + Debug>(&mut self, node: &str, field: &str, expected: &T, actual: &T) {
+        if expected != actual {
+            self.lines.push(format!(
+                "node {node}, {field}: expected {expected:?}, actual {actual:?}"
+            ));
+        }
+    }
+
+    fn length(&mut self, node: &str, field: &str, expected: f64, actual: f64) {
+        let tolerance = TOLERANCES
+            .iter()
+            .find(|(prefix, _)| field.starts_with(prefix))
+            .map_or(0.0, |&(_, tolerance)| tolerance);
+        if (expected - actual).abs() > tolerance {
+            self.lines.push(format!(
+                "node {node}, {field}: expected {expected}, actual {actual}"
+            ));
+        }
+    }
+
+    fn rect(&mut self, node: &str, field: &str, expected: &Rect, actual: &Rect) {
+        let lengths = [
+            ("x", expected.x, actual.x),
+            ("y", expected.y, actual.y),
+            ("width", expected.width, actual.width),
+            ("height", expected.height, actual.height),
+        ];
+        for (length, expected, actual) in lengths {
+            self.length(node, &format!("{field}.{length}"), expected, actual);
+        }
+    }
+
+    fn edges(&mut self, node: &str, field: &str, expected: &Edges, actual: &Edges) {
+        let sides = [
+            ("top", expected.top, actual.top),
+            ("right", expected.right, actual.right),
+            ("bottom", expected.bottom, actual.bottom),
+            ("left", expected.left, actual.left),
+        ];
+        for (side, expected, actual) in sides {
+            self.length(node, &format!("{field}.{side}"), expected, actual);
+        }
+    }
+
+    fn corners(&mut self, node: &str, field: &str, expected: &Corners, actual: &Corners) {
+        let corners = [
+            ("top_left", expected.top_left, actual.top_left),
+            ("top_right", expected.top_right, actual.top_right),
+            ("bottom_right", expected.bottom_right, actual.bottom_right),
+            ("bottom_left", expected.bottom_left, actual.bottom_left),
+        ];
+        for (corner, [expected_x, expected_y], [actual_x, actual_y]) in corners {
+            self.length(node, &format!("{field}.{corner}.x"), expected_x, actual_x);
+            self.length(node, &format!("{field}.{corner}.y"), expected_y, actual_y);
+        }
+    }
+}
+
+#[test]
+fn differences_name_the_node_and_field() {
+    let (ctx, _) = load_html_test_example(
+        r#"<style>.row { display: flex; width: 100px; height: 20px; }</style>
+<div id="row" class="row"><div></div></div>"#,
+        "row",
+    );
+    let expected = RenderSnapshot::from(&build_render_tree(ctx.document.root_node()));
+    let mut actual = expected.clone();
+    actual.children[0].bounds.width = 100.005;
+    actual.children[0].children[0].bounds.x = 3.0;
+    actual.children[0].children[0].visible = false;
+    actual.children[0]
+        .children
+        .push(expected.children[0].clone());
+
+    let mut diff = Diff::default();
+    diff.node(&expected, &actual, "");
+    assert_eq!(
+        diff.lines,
+        [
+            "node 0 > 1, children: expected 1, actual 2",
+            "node 0 > 1 > 2, bounds.x: expected 0, actual 3",
+            "node 0 > 1 > 2, visible: expected true, actual false",
+        ]
+    );
+}
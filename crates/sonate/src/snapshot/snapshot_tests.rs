@@ -0,0 +1,90 @@
+use super::RenderSnapshot;
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{build_render_tree, Rect};
+use crate::style::Rgba;
+
+const HTML: &str = r#"
+<style>
+  .row { display: flex; flex-direction: row; width: 200px; height: 50px; }
+  .box { width: 40px; height: 20px; background-color: #ff0000; border: 2px solid black; border-radius: 4px; }
+  .hidden { visibility: hidden; }
+  .top { z-index: 2; }
+</style>
+<div id="row" class="row">
+  <div id="a" class="box top" role="note" aria-label="Greeting">hello</div>
+  <div id="b" class="box hidden"></div>
+</div>
+"#;
+
+fn snapshot() -> (RenderSnapshot, std::collections::HashMap<String, crate::Id>) {
+    let (ctx, ids) = load_html_test_example(HTML, "row");
+    let render_tree = build_render_tree(ctx.document.root_node());
+    (RenderSnapshot::from(&render_tree), ids)
+}
+
+#[test]
+fn snapshot_carries_layout_and_paint_info() {
+    let (snapshot, ids) = snapshot();
+
+    let a = snapshot.find(ids["a"]).expect("a in snapshot");
+    assert_eq!(a.bounds, Rect::new(0.0, 0.0, 44.0, 24.0));
+    assert_eq!(
+        a.background_color,
+        Some(Rgba {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255
+        })
+    );
+    assert_eq!(a.border.width.left, 2.0);
+    assert_eq!(a.border.radius.top_left, [4.0, 4.0]);
+    assert_eq!(a.text.as_deref(), Some("hello"));
+    assert_eq!(a.role.as_deref(), Some("note"));
+    assert_eq!(a.aria_label.as_deref(), Some("Greeting"));
+    assert_eq!(a.z_index, 2);
+    assert!(a.visible);
+
+    let b = snapshot.find(ids["b"]).expect("b in snapshot");
+    assert!(!b.visible);
+
+    assert!(snapshot.find(crate::Id::from_u64(999)).is_none());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshot_json_round_trip() {
+    let (snapshot, _) = snapshot();
+
+    let json = snapshot.to_json();
+    let parsed: RenderSnapshot = serde_json::from_str(&json).expect("valid snapshot json");
+    assert_eq!(parsed, snapshot);
+
+    // Ids serialize as plain numbers.
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["id"], serde_json::json!(0));
+}
+
+#[test]
+fn snapshots_tell_the_border_padding_and_content_boxes_apart() {
+    let (ctx, ids) = load_html_test_example(
+        r#"
+<style>
+  .row { display: flex; flex-direction: row; width: 200px; height: 60px; }
+  .box { width: 40px; height: 20px; margin: 5px 6px; border: 2px solid black; padding: 3px 4px; }
+</style>
+<div id="row" class="row"><div id="a" class="box"></div></div>
+"#,
+        "row",
+    );
+    let snapshot = RenderSnapshot::from(&build_render_tree(ctx.document.root_node()));
+
+    let a = snapshot.find(ids["a"]).unwrap();
+    assert_eq!(a.margin.left, 6.0);
+    assert_eq!(a.margin.top, 5.0);
+    assert_eq!(a.padding.left, 4.0);
+    assert_eq!(a.padding.top, 3.0);
+    assert_eq!(a.border_box(), Rect::new(6.0, 5.0, 52.0, 30.0));
+    assert_eq!(a.padding_box(), Rect::new(8.0, 7.0, 48.0, 26.0));
+    assert_eq!(a.content_box(), Rect::new(12.0, 10.0, 40.0, 20.0));
+}
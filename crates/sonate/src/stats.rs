@@ -0,0 +1,159 @@
+//! Timings of the engine's latest work, cheap enough to keep recording: the data thread
+//! records each layout pass, and the primary window each frame it paints. See
+//! [`crate::Engine::stats`], and [`crate::Params::show_stats`] for seeing them on screen.
+
+use crate::debug_overlay;
+use crate::layout::{Rect, RenderNode};
+use crate::text::TextMeasurer;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How many of the latest layout passes and frames [`EngineStats`] are made from.
+pub const STATS_SAMPLES: usize = 120;
+
+/// What the latest layout passes and frames of the primary window took, see
+/// [`crate::Engine::stats`]. Zero for what didn't happen yet.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EngineStats {
+    pub mean_layout_ms: f64,
+    pub max_layout_ms: f64,
+    /// Painting a frame onto the window's canvas, without presenting it.
+    pub mean_paint_ms: f64,
+    pub max_paint_ms: f64,
+    /// Frames per second, from the time between the frames.
+    pub fps: f64,
+    /// The nodes the last layout pass sized and positioned.
+    pub layout_nodes: u64,
+}
+
+impl EngineStats {
+    /// The lines [`crate::Params::show_stats`] paints.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        vec![
+            format!(
+                "layout {:.1} ms, max {:.1}",
+                self.mean_layout_ms, self.max_layout_ms
+            ),
+            format!(
+                "paint {:.1} ms, max {:.1}",
+                self.mean_paint_ms, self.max_paint_ms
+            ),
+            format!("{:.0} fps", self.fps),
+            format!("{} nodes", self.layout_nodes),
+        ]
+    }
+}
+
+/// The latest `N` samples; each one past `N` replaces the oldest.
+pub(crate) struct Samples<const N: usize> {
+    values: [f64; N],
+    /// Where the next sample goes.
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for Samples<N> {
+    fn default() -> Self {
+        Self {
+            values: [0.0; N],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Samples<N> {
+    pub fn push(&mut self, value: f64) {
+        self.values[self.next] = value;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    /// The samples kept, oldest first.
+    fn kept(&self) -> impl Iterator<Item = f64> + '_ {
+        let start = if self.len < N { 0 } else { self.next };
+        (0..self.len).map(move |i| self.values[(start + i) % N])
+    }
+
+    /// 0 without samples.
+    pub fn mean(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.kept().sum::<f64>() / self.len as f64
+    }
+
+    /// 0 without samples.
+    pub fn max(&self) -> f64 {
+        self.kept().fold(0.0, f64::max)
+    }
+}
+
+/// [`EngineStats`] as recorded by the data thread and the window.
+#[derive(Default)]
+pub(crate) struct StatsRecorder {
+    recorded: Mutex<Recorded>,
+}
+
+#[derive(Default)]
+struct Recorded {
+    layout_ms: Samples<STATS_SAMPLES>,
+    layout_nodes: u64,
+    paint_ms: Samples<STATS_SAMPLES>,
+    frame_interval_ms: Samples<STATS_SAMPLES>,
+    /// When the last frame's painting started.
+    last_frame: Option<Instant>,
+}
+
+impl StatsRecorder {
+    pub fn record_layout(&self, duration: Duration, nodes: u64) {
+        let mut recorded = self.recorded.lock().unwrap();
+        recorded.layout_ms.push(millis(duration));
+        recorded.layout_nodes = nodes;
+    }
+
+    /// A frame of the primary window whose painting started at `started` and took
+    /// `duration`.
+    pub fn record_frame(&self, started: Instant, duration: Duration) {
+        let mut recorded = self.recorded.lock().unwrap();
+        recorded.paint_ms.push(millis(duration));
+        if let Some(last_frame) = recorded.last_frame.replace(started) {
+            let interval = started.saturating_duration_since(last_frame);
+            recorded.frame_interval_ms.push(millis(interval));
+        }
+    }
+
+    pub fn stats(&self) -> EngineStats {
+        let recorded = self.recorded.lock().unwrap();
+        let frame_interval_ms = recorded.frame_interval_ms.mean();
+        EngineStats {
+            mean_layout_ms: recorded.layout_ms.mean(),
+            max_layout_ms: recorded.layout_ms.max(),
+            mean_paint_ms: recorded.paint_ms.mean(),
+            max_paint_ms: recorded.paint_ms.max(),
+            fps: if frame_interval_ms > 0.0 {
+                1000.0 / frame_interval_ms
+            } else {
+                0.0
+            },
+            layout_nodes: recorded.layout_nodes,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / 1_000_000.0
+}
+
+/// The panel showing `stats`, in the bottom left corner of `window`.
+pub(crate) fn panel(stats: &EngineStats, measurer: &dyn TextMeasurer, window: Rect) -> RenderNode {
+    debug_overlay::panel_at(stats.lines(), measurer, |_, height| {
+        (
+            window.x + debug_overlay::PANEL_PADDING,
+            window.y + window.height - height - debug_overlay::PANEL_PADDING,
+        )
+    })
+}
+
+#[cfg(test)]
+mod stats_tests;
@@ -0,0 +1,75 @@
+use super::{EngineStats, Samples, StatsRecorder};
+use crate::{Engine, Id};
+use std::time::{Duration, Instant};
+
+#[test]
+fn samples_without_any_are_zero() {
+    let samples = Samples::<4>::default();
+    assert_eq!(samples.mean(), 0.0);
+    assert_eq!(samples.max(), 0.0);
+}
+
+#[test]
+fn samples_keep_the_latest_once_full() {
+    let mut samples = Samples::<4>::default();
+    samples.push(2.0);
+    samples.push(4.0);
+    assert_eq!(samples.kept().collect::<Vec<_>>(), [2.0, 4.0]);
+    assert_eq!(samples.mean(), 3.0);
+
+    for value in [6.0, 8.0, 10.0, 1.0] {
+        samples.push(value);
+    }
+    assert_eq!(samples.kept().collect::<Vec<_>>(), [6.0, 8.0, 10.0, 1.0]);
+    assert_eq!(samples.mean(), 6.25);
+    assert_eq!(samples.max(), 10.0);
+}
+
+#[test]
+fn the_recorder_aggregates_layouts_and_frames() {
+    let recorder = StatsRecorder::default();
+    recorder.record_layout(Duration::from_millis(2), 10);
+    recorder.record_layout(Duration::from_millis(6), 12);
+    let start = Instant::now();
+    for frame in 0..3 {
+        let started = start + Duration::from_millis(20 * frame);
+        recorder.record_frame(started, Duration::from_millis(3 + frame));
+    }
+
+    assert_eq!(
+        recorder.stats(),
+        EngineStats {
+            mean_layout_ms: 4.0,
+            max_layout_ms: 6.0,
+            mean_paint_ms: 4.0,
+            max_paint_ms: 5.0,
+            fps: 50.0,
+            layout_nodes: 12,
+        }
+    );
+}
+
+#[test]
+fn a_single_frame_has_no_rate() {
+    let recorder = StatsRecorder::default();
+    recorder.record_frame(Instant::now(), Duration::from_millis(3));
+    let stats = recorder.stats();
+    assert_eq!(stats.fps, 0.0);
+    assert_eq!(stats.max_paint_ms, 3.0);
+}
+
+#[test]
+fn layout_passes_of_the_engine_are_recorded() {
+    let engine = Engine::new();
+    let root = engine.root_id();
+    for id in 1..=3 {
+        engine.create_node(Id::from_u64(id), None);
+        engine.set_parent(root, Id::from_u64(id));
+    }
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    let stats = engine.stats();
+    assert!(stats.layout_nodes >= 4, "{stats:?}");
+    assert!(stats.max_layout_ms > 0.0);
+    assert_eq!(stats.fps, 0.0);
+}
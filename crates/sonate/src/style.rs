@@ -1,6 +1,13 @@
+use crate::media::MediaQuery;
+use crate::text::DEFAULT_FONT_SIZE_PX;
 use sonate_macros::MergeProperties;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rgba {
     pub r: u8,
     pub g: u8,
@@ -8,33 +15,173 @@ pub struct Rgba {
     pub a: u8,
 }
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
+#[derive(Clone, Default, Debug, PartialEq)]
 #[allow(unused)]
 pub enum Length {
     #[default]
     Auto,
     Px(f64),
     Em(f64),
+    /// Relative to the document root's font size.
+    Rem(f64),
     Percent(f64),
+    /// Relative to the viewport's width.
+    Vw(f64),
+    /// Relative to the viewport's height.
+    Vh(f64),
+    /// A `calc()` expression. Once computed, only those with percentages are left.
+    Calc(Arc<Calc>),
+}
+
+/// The expression of a `calc()`, over numbers and lengths. Products have a number on
+/// either side and quotients on the right, as the parser checks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Calc {
+    Number(f64),
+    Length(Length),
+    Sum(Box<Calc>, Box<Calc>),
+    Difference(Box<Calc>, Box<Calc>),
+    Product(Box<Calc>, Box<Calc>),
+    Quotient(Box<Calc>, Box<Calc>),
+}
+
+/// What relative lengths are resolved against when styles are computed, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LengthContext {
+    /// What `em` is relative to.
+    pub font_size: f64,
+    /// What `rem` is relative to.
+    pub root_font_size: f64,
+    pub viewport_width: f64,
+    pub viewport_height: f64,
 }
 
 impl Length {
+    /// The length in pixels, once resolved against a [`LengthContext`]: it is for computed
+    /// styles, where only percentages are left relative. Those count as 0.
     pub fn to_px(&self) -> f64 {
+        debug_assert!(
+            !matches!(
+                self,
+                Length::Em(_) | Length::Rem(_) | Length::Vw(_) | Length::Vh(_)
+            ),
+            "{self:?} wasn't resolved"
+        );
         match self {
             Length::Px(value) => *value,
             Length::Auto => 0.0,
-            Length::Em(_) => 0.0,      // TODO: Implement em conversion
+            Length::Em(_) | Length::Rem(_) | Length::Vw(_) | Length::Vh(_) => 0.0,
             Length::Percent(_) => 0.0, // TODO: Implement percentage conversion
+            Length::Calc(calc) => calc.evaluate(0.0),
+        }
+    }
+
+    /// The length with what is relative to the `context` in pixels, and `calc()` evaluated
+    /// unless it has percentages.
+    pub fn resolve(&self, context: &LengthContext) -> Length {
+        match self {
+            Length::Em(value) => Length::Px(value * context.font_size),
+            Length::Rem(value) => Length::Px(value * context.root_font_size),
+            Length::Vw(value) => Length::Px(value * context.viewport_width / 100.0),
+            Length::Vh(value) => Length::Px(value * context.viewport_height / 100.0),
+            Length::Calc(calc) => {
+                let calc = calc.resolve(context);
+                if calc.any_length(&|length| matches!(length, Length::Percent(_))) {
+                    Length::Calc(Arc::new(calc))
+                } else {
+                    Length::Px(calc.evaluate(0.0))
+                }
+            }
+            length => length.clone(),
+        }
+    }
+
+    /// The length with percentages of `basis` in pixels.
+    pub fn resolve_percentage(&self, basis: f64) -> Length {
+        match self {
+            Length::Percent(percent) => Length::Px(basis * percent / 100.0),
+            Length::Calc(calc) => Length::Px(calc.evaluate(basis)),
+            length => length.clone(),
+        }
+    }
+
+    /// Whether it's relative to the root's font size or the viewport, which depend on the
+    /// tree the node is in.
+    fn depends_on_tree(&self) -> bool {
+        match self {
+            Length::Rem(_) | Length::Vw(_) | Length::Vh(_) => true,
+            Length::Calc(calc) => calc.any_length(&Length::depends_on_tree),
+            _ => false,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl Calc {
+    /// The expression with its lengths resolved against the `context`.
+    fn resolve(&self, context: &LengthContext) -> Calc {
+        let resolve = |calc: &Calc| Box::new(calc.resolve(context));
+        match self {
+            Calc::Number(number) => Calc::Number(*number),
+            Calc::Length(length) => Calc::Length(length.resolve(context)),
+            Calc::Sum(a, b) => Calc::Sum(resolve(a), resolve(b)),
+            Calc::Difference(a, b) => Calc::Difference(resolve(a), resolve(b)),
+            Calc::Product(a, b) => Calc::Product(resolve(a), resolve(b)),
+            Calc::Quotient(a, b) => Calc::Quotient(resolve(a), resolve(b)),
+        }
+    }
+
+    /// The value in pixels, with percentages of `basis`. Division by zero gives 0.
+    pub fn evaluate(&self, basis: f64) -> f64 {
+        let value = match self {
+            Calc::Number(number) => *number,
+            Calc::Length(length) => length.resolve_percentage(basis).to_px(),
+            Calc::Sum(a, b) => a.evaluate(basis) + b.evaluate(basis),
+            Calc::Difference(a, b) => a.evaluate(basis) - b.evaluate(basis),
+            Calc::Product(a, b) => a.evaluate(basis) * b.evaluate(basis),
+            Calc::Quotient(a, b) => a.evaluate(basis) / b.evaluate(basis),
+        };
+        if value.is_finite() {
+            value
+        } else {
+            0.0
+        }
+    }
+
+    /// Whether the expression is a number rather than a length.
+    pub fn is_number(&self) -> bool {
+        match self {
+            Calc::Number(_) => true,
+            Calc::Length(_) => false,
+            Calc::Sum(a, _) | Calc::Difference(a, _) => a.is_number(),
+            Calc::Product(a, b) => a.is_number() && b.is_number(),
+            Calc::Quotient(a, _) => a.is_number(),
+        }
+    }
+
+    fn any_length(&self, predicate: &dyn Fn(&Length) -> bool) -> bool {
+        match self {
+            Calc::Number(_) => false,
+            Calc::Length(length) => predicate(length),
+            Calc::Sum(a, b)
+            | Calc::Difference(a, b)
+            | Calc::Product(a, b)
+            | Calc::Quotient(a, b) => a.any_length(predicate) || b.any_length(predicate),
+        }
+    }
+}
+
+/// Per-side values. `Directional<Option<T>>` merges side by side, so a declaration that
+/// sets one side leaves the others alone.
+#[derive(Clone, Debug, PartialEq, MergeProperties)]
 #[allow(unused)]
 pub struct Directional<T> {
+    #[merge_if_set]
     pub top: T,
+    #[merge_if_set]
     pub right: T,
+    #[merge_if_set]
     pub bottom: T,
+    #[merge_if_set]
     pub left: T,
 }
 
@@ -74,26 +221,6 @@ impl<T> Default for Directional<Option<T>> {
     }
 }
 
-impl<T> Directional<Option<T>>
-where
-    T: Clone,
-{
-    pub fn merge(&mut self, other: &Self) {
-        if let Some(value) = &other.top {
-            self.top = Some(value.clone());
-        }
-        if let Some(value) = &other.right {
-            self.right = Some(value.clone());
-        }
-        if let Some(value) = &other.bottom {
-            self.bottom = Some(value.clone());
-        }
-        if let Some(value) = &other.left {
-            self.left = Some(value.clone());
-        }
-    }
-}
-
 impl Directional<Option<Length>> {
     pub fn resolved(&self) -> Directional<Length> {
         Directional::<Length> {
@@ -105,13 +232,20 @@ impl Directional<Option<Length>> {
     }
 }
 
-#[derive(Clone, Copy, Default, Debug, PartialEq)]
+/// Properties hinted by `will-change`. Only the ones that affect painting are tracked.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct WillChange {
+    pub transform: bool,
+    pub opacity: bool,
+}
+
+#[derive(Clone, Default, Debug, PartialEq)]
 pub struct Radius {
     pub x: Length,
     pub y: Length,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, PartialEq, MergeProperties)]
 pub struct BorderRadius {
     pub top_left: Option<Radius>,
     pub top_right: Option<Radius>,
@@ -120,21 +254,6 @@ pub struct BorderRadius {
 }
 
 impl BorderRadius {
-    pub fn merge(&mut self, other: &Self) {
-        if let Some(v) = &other.top_left {
-            self.top_left = Some(v.clone());
-        }
-        if let Some(v) = &other.top_right {
-            self.top_right = Some(v.clone());
-        }
-        if let Some(v) = &other.bottom_right {
-            self.bottom_right = Some(v.clone());
-        }
-        if let Some(v) = &other.bottom_left {
-            self.bottom_left = Some(v.clone());
-        }
-    }
-
     pub fn is_empty(&self) -> bool {
         self.top_left.is_none()
             && self.top_right.is_none()
@@ -157,17 +276,21 @@ pub enum BorderStyle {
     Outset,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum Display {
     // Block,
     // Inline,
     // InlineBlock,
     #[default]
     Flex,
-    // Grid,
+    /// Children are placed in the rows and columns of `grid-template-columns` and
+    /// `grid-template-rows`, see [`crate::grid_layout`].
+    Grid,
+    /// Removed from layout and rendering, along with all descendants.
+    None,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum FlexDirection {
     #[default]
     Row,
@@ -176,7 +299,7 @@ pub enum FlexDirection {
     ColumnReverse,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum FlexWrap {
     #[default]
     NoWrap,
@@ -184,7 +307,7 @@ pub enum FlexWrap {
     WrapReverse,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum JustifyContent {
     #[default]
     FlexStart,
@@ -195,7 +318,7 @@ pub enum JustifyContent {
     SpaceEvenly,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum AlignItems {
     #[default]
     Stretch,
@@ -205,7 +328,7 @@ pub enum AlignItems {
     Baseline,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum AlignContent {
     #[default]
     Stretch,
@@ -217,7 +340,7 @@ pub enum AlignContent {
     SpaceEvenly,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum AlignSelf {
     #[default]
     Auto,
@@ -228,6 +351,236 @@ pub enum AlignSelf {
     Stretch,
 }
 
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    #[default]
+    Visible,
+    Hidden,
+}
+
+/// `user-select`. Text isn't selectable unless a node or an ancestor says `text`.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum UserSelect {
+    #[default]
+    None,
+    Text,
+}
+
+/// `font-style`. Not inherited.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    /// Drawn with the family's italic face, like `italic`; an angle after it is ignored.
+    Oblique,
+}
+
+/// A track of `grid-template-columns` or `grid-template-rows`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GridTrack {
+    /// Percentages are of the grid's content box.
+    Length(Length),
+    /// A share of the space the other tracks and the gaps leave.
+    Fr(f64),
+}
+
+/// `grid-column`: how many columns an item takes.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum GridColumn {
+    /// One column.
+    #[default]
+    Auto,
+    /// `span N`, at least one.
+    Span(u32),
+}
+
+/// `line-height`: how high each line of a text node is, its text centered in it. Not
+/// inherited.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub enum LineHeight {
+    /// As high as the font's ascent, descent and line gap.
+    #[default]
+    Normal,
+    /// Times the font size.
+    Number(f64),
+    /// Percentages are of the font size.
+    Length(Length),
+}
+
+impl LineHeight {
+    /// The height of a line in pixels for text `font_size` pixels high, or `None` for the
+    /// font's own. For computed styles, where the length is resolved.
+    pub fn to_px(&self, font_size: f64) -> Option<f64> {
+        match self {
+            LineHeight::Normal => None,
+            LineHeight::Number(number) => Some(number * font_size),
+            LineHeight::Length(length) => Some(length.to_px()),
+        }
+    }
+}
+
+/// `text-align`: where each line of a text node goes in its content box. Not inherited.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// `white-space`, as far as whether text wraps to its content box: whitespace is never
+/// collapsed, and newlines don't break lines. Not inherited.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum WhiteSpace {
+    #[default]
+    Normal,
+    /// All of the text on one line, overflowing the content box if need be.
+    Nowrap,
+}
+
+/// `text-overflow`: what shows where a line of text overflows its content box. Not inherited.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum TextOverflow {
+    /// Cut off at the edge of the box, if the node clips its overflow.
+    #[default]
+    Clip,
+    /// Cut short to fit the box, "…" ending it.
+    Ellipsis,
+}
+
+/// `object-fit`: how the image of a node with a `src` fills its content box, centered in it.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ObjectFit {
+    /// Stretched to the box, whatever its aspect ratio.
+    #[default]
+    Fill,
+    /// As large as fits in the box, keeping its aspect ratio.
+    Contain,
+    /// As small as covers the box, keeping its aspect ratio; clipped to the box.
+    Cover,
+}
+
+/// `pointer-events`. `none` takes a node and its descendants out of hit testing, so the
+/// pointer goes to whatever is painted below them; unlike CSS, descendants can't set `auto`
+/// to take it back.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum PointerEvents {
+    #[default]
+    Auto,
+    None,
+}
+
+/// `cursor`: the pointer's shape over a node. Nodes without one show their nearest
+/// ancestor's.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Cursor {
+    /// The platform's arrow, like `default`.
+    #[default]
+    Auto,
+    Default,
+    Pointer,
+    Text,
+    Move,
+    NotAllowed,
+    Grab,
+    Grabbing,
+    Crosshair,
+    Wait,
+    Progress,
+    Help,
+    ColResize,
+    RowResize,
+    EwResize,
+    NsResize,
+    ZoomIn,
+    ZoomOut,
+}
+
+/// `direction`: the base direction of a node's text, which runs of left-to-right and
+/// right-to-left text are ordered by. Text still starts at the left edge either way.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+/// `overflow`. Scrolling isn't supported, so `scroll` and `auto` clip like `hidden`.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+}
+
+/// `aspect-ratio`: width over height of the box `box-sizing` says.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum AspectRatio {
+    /// The box's content decides; also what ratios with a zero in them are.
+    #[default]
+    Auto,
+    /// Greater than 0.
+    Ratio(f64),
+}
+
+/// `line-clamp`: the most lines a text node takes, the last ending in "…" if there's more.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum LineClamp {
+    /// As many as the text needs.
+    #[default]
+    None,
+    /// At least one.
+    Lines(u32),
+}
+
+/// `z-index`: where a node stacks among its siblings, those with lower ones painted first.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ZIndex {
+    /// Stacks like 0, in document order.
+    #[default]
+    Auto,
+    Integer(i32),
+}
+
+/// A property named in `transition`. Only properties with numeric values can be transitioned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransitionProperty {
+    All,
+    Color,
+    BackgroundColor,
+    Width,
+    Height,
+    Opacity,
+}
+
+impl TransitionProperty {
+    pub fn covers(&self, property: TransitionProperty) -> bool {
+        *self == TransitionProperty::All || *self == property
+    }
+}
+
+/// `<easing-function>`; the keywords are shorthands for cubic Bézier curves.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum TimingFunction {
+    Linear,
+    #[default]
+    Ease,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    CubicBezier(f64, f64, f64, f64),
+}
+
+/// One entry of the `transition` list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition {
+    pub property: TransitionProperty,
+    pub duration: Duration,
+    pub timing_function: TimingFunction,
+    pub delay: Duration,
+}
+
 #[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub enum BoxSizing {
     #[default]
@@ -235,9 +588,10 @@ pub enum BoxSizing {
     BorderBox,
 }
 
-#[derive(Clone, Default, MergeProperties)]
+#[derive(Clone, Default, PartialEq, MergeProperties)]
 pub struct Style {
-    pub display: Display,
+    /// `flex` unless set, see [`Style::resolved_display`].
+    pub display: Option<Display>,
     pub color: Option<Rgba>,
     pub background_color: Option<Rgba>,
     #[merge_by_method_call]
@@ -255,10 +609,49 @@ pub struct Style {
     pub padding: Directional<Option<Length>>,
     pub width: Option<Length>,
     pub height: Option<Length>,
+    pub aspect_ratio: Option<AspectRatio>,
+    pub object_fit: Option<ObjectFit>,
+
+    // Outline, painted outside the border box without taking space
+    /// `medium` (3px) when the style is set but not the width.
+    pub outline_width: Option<Length>,
+    /// `none` unless set, which paints no outline.
+    pub outline_style: Option<BorderStyle>,
+    /// The text color unless set.
+    pub outline_color: Option<Rgba>,
+    /// How far outside the border box the outline starts; negative insets it.
+    pub outline_offset: Option<Length>,
+
+    // Visual effects
+    pub opacity: Option<f64>,
+    pub visibility: Option<Visibility>,
+    pub overflow: Option<Overflow>,
+    pub will_change: Option<WillChange>,
+    pub z_index: Option<ZIndex>,
+    /// `transition`; empty for `none`. When several entries name a property, the last wins.
+    pub transition: Option<Vec<Transition>>,
 
     // Text / font properties
-    pub font_family: Option<String>,
+    /// In order of preference, generic families such as `sans-serif` by name. Not inherited.
+    pub font_family: Option<Vec<String>>,
     pub font_size: Option<Length>,
+    /// From 1 to 1000; `normal` is 400 and `bold` 700. Not inherited.
+    pub font_weight: Option<u16>,
+    pub font_style: Option<FontStyle>,
+    pub line_height: Option<LineHeight>,
+    /// Added after each character; `normal` is 0. Not inherited.
+    pub letter_spacing: Option<Length>,
+    pub text_align: Option<TextAlign>,
+    pub white_space: Option<WhiteSpace>,
+    pub text_overflow: Option<TextOverflow>,
+    pub line_clamp: Option<LineClamp>,
+    /// Not inherited, but nodes without it are selectable if their nearest ancestor with
+    /// one is.
+    pub user_select: Option<UserSelect>,
+    /// Not inherited either, see [`Cursor`].
+    pub cursor: Option<Cursor>,
+    pub pointer_events: Option<PointerEvents>,
+    pub direction: Option<Direction>,
 
     // Flexbox container properties
     pub flex_direction: Option<FlexDirection>,
@@ -275,29 +668,658 @@ pub struct Style {
     pub flex_basis: Option<Length>,
     pub align_self: Option<AlignSelf>,
     pub order: Option<i32>,
+
+    // Grid container properties
+    /// Empty for `none`, one column.
+    pub grid_template_columns: Option<Vec<GridTrack>>,
+    /// Empty for `none`. Rows past these are as high as their tallest item.
+    pub grid_template_rows: Option<Vec<GridTrack>>,
+
+    // Grid item properties
+    pub grid_column: Option<GridColumn>,
+
+    /// `--name: value`, inherited by descendants.
+    #[merge_by_method_call]
+    pub custom_properties: CustomProperties,
+}
+
+/// Custom properties by name, with their values as written.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CustomProperties(Option<Arc<BTreeMap<String, String>>>);
+
+impl CustomProperties {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.as_ref()?.get(name).map(String::as_str)
+    }
+
+    pub fn set(&mut self, name: String, value: String) {
+        Arc::make_mut(self.0.get_or_insert_with(Default::default)).insert(name, value);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0
+            .as_ref()
+            .is_none_or(|properties| properties.is_empty())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .flat_map(|properties| properties.iter())
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if self.is_empty() {
+            self.0.clone_from(&other.0);
+            return;
+        }
+        for (name, value) in other.iter() {
+            self.set(name.to_owned(), value.to_owned());
+        }
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for CustomProperties {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(iter: I) -> Self {
+        let properties: BTreeMap<_, _> = iter
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        Self((!properties.is_empty()).then(|| Arc::new(properties)))
+    }
+}
+
+impl Style {
+    /// Resolved `opacity`, clamped to `[0, 1]`.
+    pub fn resolved_opacity(&self) -> f64 {
+        self.opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+    }
+
+    /// Resolved `display`, `flex` unless set.
+    pub fn resolved_display(&self) -> Display {
+        self.display.unwrap_or_default()
+    }
+
+    /// How many columns `grid-column` spans, 1 for `auto`.
+    pub fn resolved_grid_column_span(&self) -> u32 {
+        match self.grid_column.unwrap_or_default() {
+            GridColumn::Auto => 1,
+            GridColumn::Span(span) => span,
+        }
+    }
+
+    /// Resolved `aspect-ratio`, width over height; `None` for `auto`.
+    pub fn resolved_aspect_ratio(&self) -> Option<f64> {
+        match self.aspect_ratio.unwrap_or_default() {
+            AspectRatio::Auto => None,
+            AspectRatio::Ratio(ratio) => Some(ratio),
+        }
+    }
+
+    /// Resolved `line-clamp`, the most lines text takes; `None` for `none`.
+    pub fn resolved_line_clamp(&self) -> Option<u32> {
+        match self.line_clamp.unwrap_or_default() {
+            LineClamp::None => None,
+            LineClamp::Lines(lines) => Some(lines),
+        }
+    }
+
+    /// Resolved `z-index`, 0 for `auto`.
+    pub fn resolved_z_index(&self) -> i32 {
+        match self.z_index.unwrap_or_default() {
+            ZIndex::Auto => 0,
+            ZIndex::Integer(z_index) => z_index,
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        matches!(self.visibility, Some(Visibility::Hidden))
+    }
+
+    /// Whether text breaks into lines to fit the content box, unless `white-space: nowrap`.
+    pub fn wraps_text(&self) -> bool {
+        !matches!(self.white_space, Some(WhiteSpace::Nowrap))
+    }
+
+    /// Whether `pointer-events: none` takes the node and its subtree out of hit testing.
+    pub fn ignores_pointer_events(&self) -> bool {
+        matches!(self.pointer_events, Some(PointerEvents::None))
+    }
+
+    /// The resolved `border-width`, 0 on every side when `border-style` is `none` or
+    /// `hidden`: what layout makes room for between the padding and the margin.
+    pub fn used_border_width(&self) -> Directional<Length> {
+        match self.border_style.top {
+            Some(BorderStyle::None) | Some(BorderStyle::Hidden) => Directional::default(),
+            _ => self.border_width.resolved(),
+        }
+    }
+
+    /// The width of the outline in pixels, 0 when `outline-style` paints none.
+    pub fn outline_width_px(&self) -> f64 {
+        match self.outline_style {
+            None | Some(BorderStyle::None) | Some(BorderStyle::Hidden) => 0.0,
+            Some(_) => self
+                .outline_width
+                .as_ref()
+                .map_or(3.0, Length::to_px)
+                .max(0.0),
+        }
+    }
+
+    /// Whether descendants are clipped to this element's border box.
+    pub fn clips_overflow(&self) -> bool {
+        matches!(self.overflow, Some(Overflow::Hidden))
+    }
+
+    /// The transition that applies when `property` changes, if any.
+    pub fn transition_for(&self, property: TransitionProperty) -> Option<&Transition> {
+        self.transition
+            .iter()
+            .flatten()
+            .rev()
+            .find(|transition| transition.property.covers(property))
+    }
+
+    /// Whether the subtree should be rendered into its own cached layer: `will-change` hints
+    /// at its opacity or transform, which a running opacity transition also does.
+    pub fn promotes_layer(&self) -> bool {
+        self.will_change
+            .is_some_and(|will_change| will_change.transform || will_change.opacity)
+    }
+
+    /// The font size text is drawn at, in pixels, once resolved.
+    pub fn font_size_px(&self) -> f64 {
+        match self.font_size {
+            Some(Length::Px(px)) if px > 0.0 => px,
+            _ => DEFAULT_FONT_SIZE_PX,
+        }
+    }
+
+    /// Whether lengths are relative to the font size of the parent or the document root, or
+    /// to the viewport: a font size other than in pixels, or any length in `rem`, `vw` or
+    /// `vh`.
+    pub fn has_ancestor_relative_lengths(&self) -> bool {
+        !matches!(self.font_size, None | Some(Length::Px(_) | Length::Auto))
+            || self.lengths().any(Length::depends_on_tree)
+            || matches!(&self.line_height, Some(LineHeight::Length(length)) if length.depends_on_tree())
+            || self.grid_track_lengths().any(Length::depends_on_tree)
+    }
+
+    /// Resolve relative lengths as computed values are: the font size against the `parent`'s,
+    /// percentages included, and the other lengths against the resulting font size.
+    pub fn resolve_relative_lengths(&mut self, parent: &LengthContext) {
+        self.font_size = self.font_size.as_ref().map(|font_size| {
+            font_size
+                .resolve(parent)
+                .resolve_percentage(parent.font_size)
+        });
+        let context = LengthContext {
+            font_size: self.font_size_px(),
+            ..*parent
+        };
+        for length in self.lengths_mut() {
+            *length = length.resolve(&context);
+        }
+        if let Some(LineHeight::Length(length)) = &mut self.line_height {
+            *length = length
+                .resolve(&context)
+                .resolve_percentage(context.font_size);
+        }
+        for length in self.grid_track_lengths_mut() {
+            *length = length.resolve(&context);
+        }
+    }
+
+    /// Resolve percentages in the width and height against the containing block's content
+    /// box, and in the flex basis against its size on the main axis.
+    pub fn resolve_size_percentages(&mut self, width: f64, height: f64, main: f64) {
+        for (length, basis) in [
+            (&mut self.width, width),
+            (&mut self.height, height),
+            (&mut self.flex_basis, main),
+        ] {
+            if let Some(length) = length {
+                *length = length.resolve_percentage(basis);
+            }
+        }
+    }
+
+    /// Resolve percentages in the margins and padding against the containing block's
+    /// content width, which those on every side are relative to.
+    pub fn resolve_box_percentages(&mut self, width: f64) {
+        for sides in [&mut self.margin, &mut self.padding] {
+            for length in [
+                &mut sides.top,
+                &mut sides.right,
+                &mut sides.bottom,
+                &mut sides.left,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *length = length.resolve_percentage(width);
+            }
+        }
+    }
+
+    /// The lengths of the grid template tracks, which keep their percentages until layout.
+    fn grid_track_lengths(&self) -> impl Iterator<Item = &Length> {
+        [&self.grid_template_columns, &self.grid_template_rows]
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|track| match track {
+                GridTrack::Length(length) => Some(length),
+                GridTrack::Fr(_) => None,
+            })
+    }
+
+    fn grid_track_lengths_mut(&mut self) -> impl Iterator<Item = &mut Length> {
+        [
+            &mut self.grid_template_columns,
+            &mut self.grid_template_rows,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|track| match track {
+            GridTrack::Length(length) => Some(length),
+            GridTrack::Fr(_) => None,
+        })
+    }
+
+    /// The lengths other than the font size.
+    fn lengths(&self) -> impl Iterator<Item = &Length> {
+        let Style {
+            border_width,
+            border_radius,
+            margin,
+            padding,
+            width,
+            height,
+            row_gap,
+            column_gap,
+            flex_basis,
+            outline_width,
+            outline_offset,
+            letter_spacing,
+            ..
+        } = self;
+        let directional = [border_width, margin, padding]
+            .into_iter()
+            .flat_map(|sides| [&sides.top, &sides.right, &sides.bottom, &sides.left]);
+        let radii = [
+            &border_radius.top_left,
+            &border_radius.top_right,
+            &border_radius.bottom_right,
+            &border_radius.bottom_left,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|radius| [&radius.x, &radius.y]);
+        directional
+            .chain([
+                width,
+                height,
+                row_gap,
+                column_gap,
+                flex_basis,
+                outline_width,
+                outline_offset,
+                letter_spacing,
+            ])
+            .flatten()
+            .chain(radii)
+    }
+
+    fn lengths_mut(&mut self) -> impl Iterator<Item = &mut Length> {
+        let Style {
+            border_width,
+            border_radius,
+            margin,
+            padding,
+            width,
+            height,
+            row_gap,
+            column_gap,
+            flex_basis,
+            outline_width,
+            outline_offset,
+            letter_spacing,
+            ..
+        } = self;
+        let directional = [border_width, margin, padding]
+            .into_iter()
+            .flat_map(|sides| {
+                [
+                    &mut sides.top,
+                    &mut sides.right,
+                    &mut sides.bottom,
+                    &mut sides.left,
+                ]
+            });
+        let radii = [
+            &mut border_radius.top_left,
+            &mut border_radius.top_right,
+            &mut border_radius.bottom_right,
+            &mut border_radius.bottom_left,
+        ]
+        .into_iter()
+        .flatten()
+        .flat_map(|radius| [&mut radius.x, &mut radius.y]);
+        directional
+            .chain([
+                width,
+                height,
+                row_gap,
+                column_gap,
+                flex_basis,
+                outline_width,
+                outline_offset,
+                letter_spacing,
+            ])
+            .flatten()
+            .chain(radii)
+    }
 }
 
+/// Rules in source order. Add them with [`StyleSheet::add_rule`], which keeps the index by
+/// selector that matching goes through.
 pub struct StyleSheet {
     pub rules: Vec<Rule>,
+    /// Indices into `rules` by the id, class or tag their selector requires, ascending.
+    by_id: HashMap<String, Vec<usize>>,
+    by_class: HashMap<String, Vec<usize>>,
+    by_tag: HashMap<String, Vec<usize>>,
+    /// Indices of the rules whose selector requires none, only attributes.
+    unindexed: Vec<usize>,
+    /// The attributes selectors test, other than `class`, sorted; `id` among them.
+    attribute_names: Vec<String>,
+    /// Whether a rule declares custom properties, uses `var()` or has lengths relative to
+    /// the parent's or the root's font size, which makes styles depend on theirs.
+    depends_on_ancestors: bool,
+    /// Whether a rule is inside an `@media` that tests the viewport's width.
+    depends_on_viewport_width: bool,
+    generation: u64,
+}
+
+/// Unique across stylesheets, so one replaced by another is told apart.
+fn next_generation() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
 }
 
 impl StyleSheet {
     pub fn new() -> Self {
-        Self { rules: vec![] }
+        Self {
+            rules: vec![],
+            by_id: HashMap::new(),
+            by_class: HashMap::new(),
+            by_tag: HashMap::new(),
+            unindexed: Vec::new(),
+            attribute_names: Vec::new(),
+            depends_on_ancestors: false,
+            depends_on_viewport_width: false,
+            generation: next_generation(),
+        }
+    }
+
+    /// Changes whenever a rule is added, and differs between stylesheets, so styles
+    /// resolved against an earlier generation are stale.
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
     pub fn add_rule(&mut self, rule: Rule) {
+        self.generation = next_generation();
+        self.depends_on_ancestors |= !rule.var_declarations.is_empty()
+            || rule
+                .declarations
+                .iter()
+                .chain(&rule.important)
+                .any(|declaration| {
+                    !declaration.custom_properties.is_empty()
+                        || declaration.has_ancestor_relative_lengths()
+                });
+        self.depends_on_viewport_width |= rule.media.iter().any(MediaQuery::depends_on_width);
+        let simple = match &rule.selector {
+            Selector::Compound(parts) => parts.as_slice(),
+            simple => std::slice::from_ref(simple),
+        };
+        for part in simple {
+            let name = match part {
+                Selector::Attribute(attribute) => &attribute.name,
+                Selector::Id(_) => "id",
+                _ => continue,
+            };
+            if let Err(at) = self
+                .attribute_names
+                .binary_search_by(|n| n.as_str().cmp(name))
+            {
+                self.attribute_names.insert(at, name.to_owned());
+            }
+        }
+        // One required id, class or tag is enough to find the rule by.
+        let id = simple.iter().find_map(|part| match part {
+            Selector::Id(id) => Some(id),
+            _ => None,
+        });
+        let class = simple.iter().find_map(|part| match part {
+            Selector::Class(class) => Some(class),
+            _ => None,
+        });
+        let tag = simple.iter().find_map(|part| match part {
+            Selector::Tag(tag) => Some(tag),
+            _ => None,
+        });
+        let index = self.rules.len();
+        match (id, class, tag) {
+            (Some(id), _, _) => self.by_id.entry(id.clone()).or_default().push(index),
+            (None, Some(class), _) => self.by_class.entry(class.clone()).or_default().push(index),
+            (None, None, Some(tag)) => self.by_tag.entry(tag.clone()).or_default().push(index),
+            (None, None, None) => self.unindexed.push(index),
+        }
         self.rules.push(rule);
     }
+
+    /// The rules whose selector matches a node with the `tag` and `attributes`, and focus if
+    /// `focused`, regardless of their media conditions, in cascade order: by specificity, then
+    /// in source order.
+    pub fn matching_rules(
+        &self,
+        tag: Option<&str>,
+        attributes: &HashMap<String, String>,
+        focused: bool,
+    ) -> Vec<&Rule> {
+        let by_id = attributes.get("id").and_then(|id| self.by_id.get(id));
+        let by_tag = tag.and_then(|tag| self.by_tag.get(tag));
+        let by_class = attributes
+            .get("class")
+            .into_iter()
+            .flat_map(|classes| classes.split_whitespace())
+            .filter_map(|class| self.by_class.get(class));
+        let mut indices: Vec<usize> = by_id
+            .into_iter()
+            .chain(by_tag)
+            .chain(by_class)
+            .chain([&self.unindexed])
+            .flatten()
+            .copied()
+            .collect();
+        // A class can be listed twice, and the rule still applies once.
+        indices.sort_unstable();
+        indices.dedup();
+        let mut rules: Vec<&Rule> = indices
+            .into_iter()
+            .map(|index| &self.rules[index])
+            .filter(|rule| rule.selector.matches(tag, attributes, focused))
+            .collect();
+        // Stable, so rules of equal specificity stay in source order.
+        rules.sort_by_key(|rule| rule.selector.specificity());
+        rules
+    }
+
+    /// The attributes other than `class` that selectors test, sorted.
+    pub fn attribute_names(&self) -> &[String] {
+        &self.attribute_names
+    }
+
+    pub fn depends_on_ancestors(&self) -> bool {
+        self.depends_on_ancestors
+    }
+
+    pub fn depends_on_viewport_width(&self) -> bool {
+        self.depends_on_viewport_width
+    }
 }
 
 pub struct Rule {
     pub selector: Selector,
     pub declarations: Vec<Style>,
+    /// The declarations marked `!important`, which override all others.
+    pub important: Vec<Style>,
+    /// The declarations whose value uses `var()`, parsed once the custom properties of the
+    /// node they apply to are known.
+    pub var_declarations: Vec<VarDeclaration>,
+    /// Enclosing `@media` conditions, all of which must match. Empty for top-level rules.
+    pub media: Vec<MediaQuery>,
+}
+
+/// The declarations of a `style` attribute. They override those of the rules in their tier:
+/// `declarations` those of normal rules, and `important` all others.
+#[derive(Clone, Default, PartialEq)]
+pub struct InlineStyle {
+    pub declarations: Style,
+    pub important: Style,
+}
+
+impl InlineStyle {
+    pub fn has_ancestor_relative_lengths(&self) -> bool {
+        self.declarations.has_ancestor_relative_lengths()
+            || self.important.has_ancestor_relative_lengths()
+    }
+}
+
+/// `property: value` where the value refers to custom properties.
+#[derive(Debug, PartialEq)]
+pub struct VarDeclaration {
+    pub property: String,
+    pub value: String,
+    pub important: bool,
+    /// Where it goes among the declarations of its tier, [`Rule::declarations`] or
+    /// [`Rule::important`]: right before the one at this index.
+    pub index: usize,
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Selector {
+    /// `*`, which matches every node.
+    Universal,
+    /// An element name, which nodes created with a tag match.
     Tag(String),
+    /// `#name`, which matches the `id` attribute.
+    Id(String),
     Class(String),
+    Attribute(AttributeSelector),
+    /// `:focus`, which matches the node with focus.
+    Focus,
+    /// Simple selectors that must all match, like `.panel[data-state="open"]`.
+    Compound(Vec<Selector>),
+}
+
+impl Selector {
+    /// Whether a node with the `tag`, if any, and the `attributes`, its classes among them,
+    /// matches, given whether it has focus.
+    pub fn matches(
+        &self,
+        tag: Option<&str>,
+        attributes: &HashMap<String, String>,
+        focused: bool,
+    ) -> bool {
+        match self {
+            Selector::Universal => true,
+            Selector::Tag(name) => tag == Some(name.as_str()),
+            Selector::Id(id) => attributes.get("id") == Some(id),
+            Selector::Class(class) => attributes
+                .get("class")
+                .is_some_and(|classes| classes.split_whitespace().any(|c| c == class)),
+            Selector::Attribute(attribute) => attributes
+                .get(&attribute.name)
+                .is_some_and(|value| attribute.matches(value)),
+            Selector::Focus => focused,
+            Selector::Compound(parts) => parts
+                .iter()
+                .all(|part| part.matches(tag, attributes, focused)),
+        }
+    }
+
+    pub fn specificity(&self) -> Specificity {
+        let mut specificity = Specificity::default();
+        match self {
+            Selector::Universal => {}
+            Selector::Tag(_) => specificity.tags = 1,
+            Selector::Id(_) => specificity.ids = 1,
+            Selector::Class(_) | Selector::Attribute(_) | Selector::Focus => {
+                specificity.classes = 1
+            }
+            Selector::Compound(parts) => {
+                for part in parts.iter().map(Selector::specificity) {
+                    specificity.ids += part.ids;
+                    specificity.classes += part.classes;
+                    specificity.tags += part.tags;
+                }
+            }
+        }
+        specificity
+    }
+}
+
+/// How specific a selector is, compared by ids, then classes and attributes, then tags: of
+/// two matching rules, the more specific one wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Specificity {
+    pub ids: u32,
+    pub classes: u32,
+    pub tags: u32,
+}
+
+/// `[name]`, or `[name op "value"]`.
+#[derive(Debug, PartialEq)]
+pub struct AttributeSelector {
+    pub name: String,
+    /// `None` for `[name]`, which any value matches.
+    pub value: Option<(AttributeOperator, String)>,
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// `=`
+    Equals,
+    /// `^=`
+    Prefix,
+    /// `$=`
+    Suffix,
+    /// `*=`
+    Substring,
+}
+
+impl AttributeSelector {
+    fn matches(&self, value: &str) -> bool {
+        let Some((operator, expected)) = &self.value else {
+            return true;
+        };
+        match operator {
+            AttributeOperator::Equals => value == expected,
+            // Empty strings match nothing but `=`, as in browsers.
+            _ if expected.is_empty() => false,
+            AttributeOperator::Prefix => value.starts_with(expected.as_str()),
+            AttributeOperator::Suffix => value.ends_with(expected.as_str()),
+            AttributeOperator::Substring => value.contains(expected.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod merge_tests;
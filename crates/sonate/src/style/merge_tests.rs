@@ -0,0 +1,78 @@
+use super::*;
+
+#[test]
+fn test_merge_opacity_and_visibility() {
+    let mut style = Style {
+        opacity: Some(0.5),
+        ..Default::default()
+    };
+
+    // Unset fields in the later declaration leave earlier values alone.
+    style.merge(&Style {
+        visibility: Some(Visibility::Hidden),
+        ..Default::default()
+    });
+    assert_eq!(style.opacity, Some(0.5));
+    assert_eq!(style.visibility, Some(Visibility::Hidden));
+
+    // Set fields override.
+    style.merge(&Style {
+        opacity: Some(0.25),
+        visibility: Some(Visibility::Visible),
+        ..Default::default()
+    });
+    assert_eq!(style.opacity, Some(0.25));
+    assert_eq!(style.visibility, Some(Visibility::Visible));
+    assert!(!style.is_hidden());
+}
+
+#[test]
+fn test_resolved_opacity_defaults_to_opaque() {
+    assert_eq!(Style::default().resolved_opacity(), 1.0);
+
+    let style = Style {
+        opacity: Some(1.5),
+        ..Default::default()
+    };
+    assert_eq!(style.resolved_opacity(), 1.0);
+}
+
+#[test]
+fn test_merge_sides_one_by_one() {
+    let mut style = Style {
+        margin: Directional::set_all(Some(Length::Px(10.0))),
+        ..Default::default()
+    };
+
+    style.merge(&Style {
+        margin: Directional {
+            left: Some(Length::Px(5.0)),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+    assert_eq!(style.margin.top, Some(Length::Px(10.0)));
+    assert_eq!(style.margin.right, Some(Length::Px(10.0)));
+    assert_eq!(style.margin.bottom, Some(Length::Px(10.0)));
+    assert_eq!(style.margin.left, Some(Length::Px(5.0)));
+}
+
+#[test]
+fn test_merge_keeps_display_unless_set() {
+    let mut style = Style {
+        display: Some(Display::Grid),
+        ..Default::default()
+    };
+
+    style.merge(&Style {
+        width: Some(Length::Px(10.0)),
+        ..Default::default()
+    });
+    assert_eq!(style.resolved_display(), Display::Grid);
+
+    style.merge(&Style {
+        display: Some(Display::Flex),
+        ..Default::default()
+    });
+    assert_eq!(style.resolved_display(), Display::Flex);
+}
@@ -1,25 +1,340 @@
-use crate::style::{Selector, Style, StyleSheet};
+use crate::css_parser::{parse_declaration, resolve_custom_properties, substitute};
+use crate::layout::Node;
+use crate::media::MediaEnvironment;
+use crate::style::{
+    BorderStyle, CustomProperties, InlineStyle, Length, LengthContext, Rgba, Rule, Style,
+    StyleSheet, VarDeclaration,
+};
+use crate::text::DEFAULT_FONT_SIZE_PX;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
+/// The color of the focus ring until [`crate::Engine::set_focus_accent_color`] sets another.
+pub(crate) const DEFAULT_FOCUS_ACCENT: Rgba = Rgba {
+    r: 0,
+    g: 95,
+    b: 204,
+    a: 255,
+};
+
+/// The outline the node with focus gets unless its own style or the rules set another, as
+/// if a user agent stylesheet had `:focus { outline: 2px solid <accent> }`.
+fn focus_ring(accent: Rgba) -> Style {
+    Style {
+        outline_width: Some(Length::Px(2.0)),
+        outline_style: Some(BorderStyle::Solid),
+        outline_color: Some(accent),
+        ..Style::default()
+    }
+}
+
+/// What a node's style is resolved against from its ancestors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Inherited {
+    /// The parent's, which `var()` may refer to.
+    pub custom_properties: CustomProperties,
+    /// With the parent's font size, which an `em` font size is relative to, the root's and
+    /// the viewport of the tree.
+    pub lengths: LengthContext,
+}
+
+impl Default for Inherited {
+    fn default() -> Self {
+        Self {
+            custom_properties: CustomProperties::default(),
+            lengths: LengthContext {
+                font_size: DEFAULT_FONT_SIZE_PX,
+                root_font_size: DEFAULT_FONT_SIZE_PX,
+                viewport_width: 0.0,
+                viewport_height: 0.0,
+            },
+        }
+    }
+}
+
+// Lengths are resolved from finite sizes, never NaN.
+impl Eq for Inherited {}
+
+impl Hash for Inherited {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.custom_properties.hash(state);
+        let lengths = &self.lengths;
+        for size in [
+            lengths.font_size,
+            lengths.root_font_size,
+            lengths.viewport_width,
+            lengths.viewport_height,
+        ] {
+            size.to_bits().hash(state);
+        }
+    }
+}
+
+/// Apply the rules matching a node with the `tag` and `attributes`, and focus if `focused`,
+/// and its `inline` style to its `style`, with `var()` referring to its custom properties,
+/// the `inherited` ones of its parent then its own, and lengths relative to font sizes and
+/// the viewport resolved to pixels. Inline declarations override those of the rules in
+/// their tier.
+#[allow(clippy::too_many_arguments)]
 pub fn apply_matching_rules(
     style: &mut Style,
+    inline: &InlineStyle,
+    tag: Option<&str>,
     attributes: &HashMap<String, String>,
+    focused: bool,
+    inherited: &Inherited,
     style_sheet: &StyleSheet,
+    media: &MediaEnvironment,
 ) {
-    let tag_name = attributes.get("tag").map(|s| s.as_str());
-    let class_attr = attributes.get("class").map(|s| s.as_str());
-
-    for rule in &style_sheet.rules {
-        let matches = match &rule.selector {
-            Selector::Tag(tag) => tag_name.is_some_and(|t| t == tag.as_str()),
-            Selector::Class(class_name) => class_attr
-                .is_some_and(|classes| classes.split_whitespace().any(|c| c == class_name)),
-        };
+    let rules: Vec<_> = style_sheet
+        .matching_rules(tag, attributes, focused)
+        .into_iter()
+        .filter(|rule| rule.media.iter().all(|query| query.matches(media)))
+        .collect();
 
-        if matches {
-            for declaration in &rule.declarations {
-                style.merge(declaration);
+    // Custom properties come first, since a `var()` sees the ones declared after it too.
+    let mut custom_properties = inherited.custom_properties.clone();
+    custom_properties.merge(&style.custom_properties);
+    let normal = rules.iter().flat_map(|rule| &rule.declarations);
+    let important = rules.iter().flat_map(|rule| &rule.important);
+    for declaration in normal
+        .chain([&inline.declarations])
+        .chain(important)
+        .chain([&inline.important])
+    {
+        custom_properties.merge(&declaration.custom_properties);
+    }
+    let custom_properties = resolve_custom_properties(&custom_properties);
+
+    // `!important` declarations are a tier of their own, in the same order.
+    for (important, inline) in [(false, &inline.declarations), (true, &inline.important)] {
+        for rule in &rules {
+            merge_tier(style, rule, important, &custom_properties);
+        }
+        style.merge(inline);
+    }
+    style.custom_properties = custom_properties;
+    style.resolve_relative_lengths(&inherited.lengths);
+}
+
+/// Merge the rule's normal or important declarations, those with `var()` among them.
+fn merge_tier(
+    style: &mut Style,
+    rule: &Rule,
+    important: bool,
+    custom_properties: &CustomProperties,
+) {
+    let declarations = if important {
+        &rule.important
+    } else {
+        &rule.declarations
+    };
+    let mut var_declarations = rule
+        .var_declarations
+        .iter()
+        .filter(|declaration| declaration.important == important)
+        .peekable();
+    for (index, declaration) in declarations.iter().enumerate() {
+        while let Some(var) = var_declarations.next_if(|var| var.index == index) {
+            merge_var_declaration(style, var, custom_properties);
+        }
+        style.merge(declaration);
+    }
+    for var in var_declarations {
+        merge_var_declaration(style, var, custom_properties);
+    }
+}
+
+/// A declaration whose `var()` can't be substituted, or that is invalid once they are, is
+/// dropped. Browsers reset the property to its initial value instead, which is the same
+/// unless an earlier declaration set it.
+fn merge_var_declaration(
+    style: &mut Style,
+    declaration: &VarDeclaration,
+    custom_properties: &CustomProperties,
+) {
+    let value = substitute(&declaration.value, &mut |name| {
+        custom_properties.get(name).map(str::to_owned)
+    });
+    if let Some(parsed) = value.and_then(|value| parse_declaration(&declaration.property, &value)) {
+        style.merge(&parsed);
+    }
+}
+
+/// Styles resolved by [`apply_matching_rules`], keyed by everything rules match on, so nodes
+/// that didn't change since the last layout pass skip rule matching.
+///
+/// Like the text measurement cache, entries not used during a layout pass are swept at its
+/// end. Everything is dropped when rules are added. The media environment is part of the
+/// key, as windows of different sizes resolve the same nodes in their own.
+///
+/// In front of it, each node keeps the style it was last resolved to in a [`ComputedStyle`],
+/// so nodes that didn't change don't even build a key.
+#[derive(Default)]
+pub(crate) struct StyleCache {
+    state: RefCell<StyleCacheState>,
+}
+
+#[derive(Default)]
+struct StyleCacheState {
+    epoch: u64,
+    generation: u64,
+    entries: HashMap<StyleKey, StyleEntry>,
+    misses: u64,
+    /// Styles looked up here, because the node's own was stale.
+    lookups: u64,
+}
+
+/// A node's resolved style, valid while the stylesheet generation, the media environment,
+/// its focus, its inline style and what it inherits are the ones it was resolved against. Cleared when an attribute changes, which rules match on too.
+pub(crate) struct ComputedStyle {
+    generation: u64,
+    media: MediaEnvironment,
+    focus: Option<Rgba>,
+    inline_style: Arc<InlineStyle>,
+    inherited: Inherited,
+    style: Arc<Style>,
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct StyleKey {
+    inline_style: *const InlineStyle,
+    inherited: Inherited,
+    media: MediaEnvironment,
+    /// The accent of the focus ring, if the node has focus.
+    focus: Option<Rgba>,
+    tag: Option<String>,
+    class: Option<String>,
+    /// The values of [`StyleSheet::attribute_names`].
+    attributes: Vec<Option<String>>,
+}
+
+struct StyleEntry {
+    /// Keeps `StyleKey::inline_style` from being reused for another style.
+    _inline_style: Arc<InlineStyle>,
+    style: Arc<Style>,
+    last_used_epoch: u64,
+}
+
+impl StyleCache {
+    pub fn begin_layout_pass(&self) {
+        let mut state = self.state.borrow_mut();
+        state.epoch = state.epoch.wrapping_add(1);
+    }
+
+    pub fn end_layout_pass_and_sweep(&self) {
+        let mut state = self.state.borrow_mut();
+        let epoch = state.epoch;
+        state
+            .entries
+            .retain(|_, entry| entry.last_used_epoch == epoch);
+    }
+
+    /// The node's inline style with the matching rules applied, against what it
+    /// `inherited` from its ancestors. `focus` is the accent of the focus ring if the node
+    /// has focus.
+    pub fn resolve(
+        &self,
+        node: &Node,
+        inherited: &Inherited,
+        style_sheet: &StyleSheet,
+        media: &MediaEnvironment,
+        focus: Option<Rgba>,
+    ) -> Arc<Style> {
+        if let Some(computed) = node.computed_style.borrow().as_ref() {
+            if computed.generation == style_sheet.generation()
+                && computed.media == *media
+                && computed.focus == focus
+                && Arc::ptr_eq(&computed.inline_style, &node.inline_style)
+                && computed.inherited == *inherited
+            {
+                return computed.style.clone();
             }
         }
+
+        let style = self.lookup(node, inherited, style_sheet, media, focus);
+        *node.computed_style.borrow_mut() = Some(ComputedStyle {
+            generation: style_sheet.generation(),
+            media: media.clone(),
+            focus,
+            inline_style: node.inline_style.clone(),
+            inherited: inherited.clone(),
+            style: style.clone(),
+        });
+        style
+    }
+
+    fn lookup(
+        &self,
+        node: &Node,
+        inherited: &Inherited,
+        style_sheet: &StyleSheet,
+        media: &MediaEnvironment,
+        focus: Option<Rgba>,
+    ) -> Arc<Style> {
+        let mut state = self.state.borrow_mut();
+        if state.generation != style_sheet.generation() {
+            state.generation = style_sheet.generation();
+            state.entries.clear();
+        }
+        state.lookups += 1;
+
+        let key = StyleKey {
+            inline_style: Arc::as_ptr(&node.inline_style),
+            inherited: inherited.clone(),
+            media: media.clone(),
+            focus,
+            tag: node.tag.clone(),
+            class: node.attributes.get("class").cloned(),
+            attributes: style_sheet
+                .attribute_names()
+                .iter()
+                .map(|name| node.attributes.get(name).cloned())
+                .collect(),
+        };
+        let epoch = state.epoch;
+        if let Some(entry) = state.entries.get_mut(&key) {
+            entry.last_used_epoch = epoch;
+            return entry.style.clone();
+        }
+
+        state.misses += 1;
+        let mut style = focus.map_or_else(Style::default, focus_ring);
+        apply_matching_rules(
+            &mut style,
+            &node.inline_style,
+            node.tag.as_deref(),
+            &node.attributes,
+            focus.is_some(),
+            inherited,
+            style_sheet,
+            media,
+        );
+        let style = Arc::new(style);
+        state.entries.insert(
+            key,
+            StyleEntry {
+                _inline_style: node.inline_style.clone(),
+                style: style.clone(),
+                last_used_epoch: epoch,
+            },
+        );
+        style
+    }
+
+    /// Styles resolved without a cache hit so far.
+    pub fn misses(&self) -> u64 {
+        self.state.borrow().misses
+    }
+
+    /// Styles resolved without the node's own being current so far.
+    #[cfg(test)]
+    pub fn lookups(&self) -> u64 {
+        self.state.borrow().lookups
     }
 }
+
+#[cfg(test)]
+mod style_matching_tests;
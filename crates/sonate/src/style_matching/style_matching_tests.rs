@@ -0,0 +1,660 @@
+use super::{apply_matching_rules, Inherited, DEFAULT_FOCUS_ACCENT};
+use crate::css_parser::{parse_css, parse_inline_style};
+use crate::grid_layout::template_columns;
+use crate::layout::LayoutContext;
+use crate::media::MediaEnvironment;
+use crate::style::{Display, GridTrack, InlineStyle, Length, Rgba, Style, StyleSheet};
+use crate::Id;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+fn resolve(sheet: &StyleSheet, tag: &str, class: &str) -> Style {
+    let mut style = Style::default();
+    apply_matching_rules(
+        &mut style,
+        &InlineStyle::default(),
+        Some(tag),
+        &HashMap::from([("class".to_owned(), class.to_owned())]),
+        false,
+        &Inherited::default(),
+        sheet,
+        &MediaEnvironment::default(),
+    );
+    style
+}
+
+#[test]
+fn later_rules_override_earlier_ones_whatever_the_class_order() {
+    let sheet = parse_css(
+        ".a { width: 10px; height: 10px; }
+         .b { width: 20px; }
+         .a { height: 30px; }",
+    )
+    .unwrap();
+    let style = resolve(&sheet, "div", "b a");
+    assert_eq!(style.width, Some(Length::Px(20.0)));
+    assert_eq!(style.height, Some(Length::Px(30.0)));
+}
+
+#[test]
+fn class_rules_override_tag_rules_wherever_they_are() {
+    let sheet = parse_css(
+        "div { width: 10px; }
+         .a { width: 20px; height: 20px; }
+         div { height: 30px; }",
+    )
+    .unwrap();
+    let style = resolve(&sheet, "div", "a a");
+    assert_eq!(style.width, Some(Length::Px(20.0)));
+    assert_eq!(style.height, Some(Length::Px(20.0)));
+
+    let style = resolve(&sheet, "span", "a");
+    assert_eq!(style.height, Some(Length::Px(20.0)));
+    assert_eq!(resolve(&sheet, "span", "b").width, None);
+}
+
+/// The width of a `ul` with the class `list` and the inline style `inline`.
+fn width_with_inline_style(css: &str, inline: &str) -> Option<Length> {
+    let mut style = Style::default();
+    apply_matching_rules(
+        &mut style,
+        &parse_inline_style(inline),
+        Some("ul"),
+        &HashMap::from([("class".to_owned(), "list".to_owned())]),
+        false,
+        &Inherited::default(),
+        &parse_css(css).unwrap(),
+        &MediaEnvironment::default(),
+    );
+    style.width
+}
+
+#[test]
+fn inline_styles_override_rules_of_their_tier() {
+    let px = |px| Some(Length::Px(px));
+    let rule = ".list { width: 200px; }";
+    let important_rule = ".list { width: 200px !important; }";
+    assert_eq!(width_with_inline_style(rule, "width: 120px"), px(120.0));
+    assert_eq!(width_with_inline_style(rule, "height: 10px"), px(200.0));
+    assert_eq!(
+        width_with_inline_style(important_rule, "width: 120px"),
+        px(200.0)
+    );
+    assert_eq!(
+        width_with_inline_style(important_rule, "width: 120px !important"),
+        px(120.0)
+    );
+}
+
+#[test]
+fn a_later_side_overrides_only_that_side() {
+    let sides = |css: &str| {
+        let margin = resolve(&parse_css(css).unwrap(), "div", "a b").margin;
+        [margin.top, margin.right, margin.bottom, margin.left].map(|side| side.unwrap().to_px())
+    };
+    assert_eq!(
+        sides(".a { margin: 10px; } .b { margin-left: 5px; }"),
+        [10.0, 10.0, 10.0, 5.0]
+    );
+    assert_eq!(
+        sides(".b { margin-left: 5px; } .a { margin: 10px; }"),
+        [10.0; 4]
+    );
+}
+
+#[test]
+fn display_stays_unless_a_later_declaration_sets_it() {
+    let sheet = parse_css(".a { display: grid; width: 10px; } .b { height: 10px; }").unwrap();
+    assert_eq!(
+        resolve(&sheet, "div", "a b").resolved_display(),
+        Display::Grid
+    );
+
+    let sheet = parse_css(".a { display: grid; } .b { display: flex; }").unwrap();
+    assert_eq!(
+        resolve(&sheet, "div", "a b").resolved_display(),
+        Display::Flex
+    );
+}
+
+#[test]
+fn z_index_auto_resets_an_earlier_z_index() {
+    let sheet = parse_css(".a { z-index: 3; } .b { z-index: auto; }").unwrap();
+    assert_eq!(resolve(&sheet, "div", "a b").resolved_z_index(), 0);
+}
+
+#[test]
+fn line_clamp_none_resets_an_earlier_clamp() {
+    let sheet = parse_css(".a { line-clamp: 2; } .b { line-clamp: none; }").unwrap();
+    assert_eq!(resolve(&sheet, "div", "a b").resolved_line_clamp(), None);
+    let sheet = parse_css(".a { line-clamp: none; } .b { line-clamp: 2; }").unwrap();
+    assert_eq!(resolve(&sheet, "div", "a b").resolved_line_clamp(), Some(2));
+}
+
+#[test]
+fn aspect_ratio_auto_resets_an_earlier_ratio() {
+    let ratio = |css: &str| resolve(&parse_css(css).unwrap(), "div", "a b").resolved_aspect_ratio();
+    assert_eq!(
+        ratio(".a { aspect-ratio: 2; } .b { aspect-ratio: auto; }"),
+        None
+    );
+    // Degenerate ratios are `auto` too.
+    assert_eq!(
+        ratio(".a { aspect-ratio: 2; } .b { aspect-ratio: 0 / 1; }"),
+        None
+    );
+    assert_eq!(
+        ratio(".a { aspect-ratio: auto; } .b { aspect-ratio: 2; }"),
+        Some(2.0)
+    );
+}
+
+#[test]
+fn grid_keywords_reset_earlier_values() {
+    let sheet = parse_css(
+        ".a { grid-template-columns: 1fr 1fr; grid-template-rows: 10px; grid-column: span 2; }
+         .b { grid-template-columns: none; grid-template-rows: none; grid-column: auto; }",
+    )
+    .unwrap();
+    let style = resolve(&sheet, "div", "a b");
+    assert_eq!(template_columns(&style), [GridTrack::Fr(1.0)]);
+    assert_eq!(style.grid_template_rows, Some(Vec::new()));
+    assert_eq!(style.resolved_grid_column_span(), 1);
+}
+
+/// The width a `div` with the id `x` and the classes `a b` gets from `css`.
+fn cascaded_width(css: &str) -> Option<Length> {
+    let sheet = parse_css(css).unwrap();
+    let attributes = HashMap::from([
+        ("id".to_owned(), "x".to_owned()),
+        ("class".to_owned(), "a b".to_owned()),
+    ]);
+    let mut style = Style::default();
+    apply_matching_rules(
+        &mut style,
+        &InlineStyle::default(),
+        Some("div"),
+        &attributes,
+        false,
+        &Inherited::default(),
+        &sheet,
+        &MediaEnvironment::default(),
+    );
+    style.width
+}
+
+#[test]
+fn the_most_specific_rule_wins_then_the_last() {
+    let cases = [
+        (
+            "class over tag",
+            "div.a { width: 1px; } div { width: 2px; }",
+            1.0,
+        ),
+        (
+            "id over class",
+            "#x { width: 1px; } .a { width: 2px; }",
+            1.0,
+        ),
+        (
+            "id over many classes",
+            "#x { width: 1px; } .a.b[id] { width: 2px; }",
+            1.0,
+        ),
+        (
+            "later class over earlier class",
+            ".a { width: 1px; } .b { width: 2px; }",
+            2.0,
+        ),
+        (
+            "attribute ties with class",
+            ".a { width: 1px; } [id] { width: 2px; }",
+            2.0,
+        ),
+        (
+            "two classes over one",
+            ".a.b { width: 1px; } .b { width: 2px; }",
+            1.0,
+        ),
+        (
+            "tag over universal",
+            "div { width: 1px; } * { width: 2px; }",
+            1.0,
+        ),
+        (
+            "compound tag breaks a tie",
+            "div.a { width: 1px; } .b { width: 2px; }",
+            1.0,
+        ),
+        (
+            "important over id",
+            ".a { width: 1px !important; } #x { width: 2px; }",
+            1.0,
+        ),
+        (
+            "important over later important",
+            "#x { width: 1px !important; } .a { width: 2px !important; }",
+            1.0,
+        ),
+        (
+            "later important of equal specificity",
+            ".a { width: 1px !important; } .b { width: 2px !IMPORTANT; }",
+            2.0,
+        ),
+        (
+            "important tag over normal universal",
+            "* { width: 1px; } div { width: 2px ! important; }",
+            2.0,
+        ),
+    ];
+    for (case, css, width) in cases {
+        assert_eq!(
+            cascaded_width(css),
+            Some(Length::Px(width)),
+            "{case}: {css}"
+        );
+    }
+}
+
+#[test]
+fn a_thousand_rules_resolve_for_a_thousand_nodes_quickly() {
+    let css: String = (0..1_000)
+        .map(|i| format!(".c{i} {{ width: {i}px; }}\n"))
+        .collect();
+    let sheet = parse_css(&css).unwrap();
+    assert_eq!(sheet.rules.len(), 1_000);
+
+    let started = Instant::now();
+    for i in 0..1_000 {
+        let style = resolve(&sheet, "div", &format!("item c{i} other"));
+        assert_eq!(style.width, Some(Length::Px(i as f64)));
+    }
+    // Scanning every rule for every class takes far longer unoptimized.
+    assert!(started.elapsed() < Duration::from_millis(500));
+}
+
+const RED: Rgba = Rgba {
+    r: 255,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
+const BLUE: Rgba = Rgba {
+    r: 0,
+    g: 0,
+    b: 255,
+    a: 255,
+};
+
+/// A laid out document of three nodes of class `a`, with `.a` red and `.b` blue.
+fn document() -> (LayoutContext, Vec<Id>) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet =
+        parse_css(".a { background-color: red; } .b { background-color: blue; }").unwrap();
+    let root = ctx.document.root_id();
+    let nodes = (2..5)
+        .map(|id| {
+            let id = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+            ctx.document.set_parent(root, id).unwrap();
+            ctx.document
+                .set_attribute(id, "class".to_owned(), "a".to_owned());
+            id
+        })
+        .collect();
+    ctx.layout();
+    (ctx, nodes)
+}
+
+fn background(ctx: &LayoutContext, id: Id) -> Option<Rgba> {
+    let node = ctx.document.get_node(id).unwrap();
+    let color = node.borrow().layout.style.background_color;
+    color
+}
+
+#[test]
+fn unchanged_nodes_keep_their_style_across_layouts() {
+    let (mut ctx, _) = document();
+    let lookups = ctx.style_cache_lookups();
+    ctx.layout();
+    ctx.layout();
+    assert_eq!(ctx.style_cache_lookups(), lookups);
+}
+
+#[test]
+fn a_changed_class_restyles_its_node() {
+    let (mut ctx, nodes) = document();
+    let lookups = ctx.style_cache_lookups();
+    ctx.document
+        .set_attribute(nodes[1], "class".to_owned(), "b".to_owned());
+    ctx.layout();
+    assert_eq!(ctx.style_cache_lookups(), lookups + 1);
+    assert_eq!(background(&ctx, nodes[0]), Some(RED));
+    assert_eq!(background(&ctx, nodes[1]), Some(BLUE));
+
+    // Text doesn't matter to rules.
+    ctx.document.set_text(nodes[1], Some("text".to_owned()));
+    ctx.layout();
+    assert_eq!(ctx.style_cache_lookups(), lookups + 1);
+}
+
+#[test]
+fn added_and_replaced_stylesheets_restyle_every_node() {
+    let (mut ctx, nodes) = document();
+    for rule in parse_css(".a { background-color: blue; }").unwrap().rules {
+        ctx.style_sheet.add_rule(rule);
+    }
+    ctx.layout();
+    assert!(nodes.iter().all(|&id| background(&ctx, id) == Some(BLUE)));
+
+    // As many rules as before, yet others.
+    ctx.style_sheet = parse_css(
+        ".b { background-color: blue; } .a { background-color: red; } .c { width: 1px; }",
+    )
+    .unwrap();
+    ctx.layout();
+    assert!(nodes.iter().all(|&id| background(&ctx, id) == Some(RED)));
+}
+
+#[test]
+fn attribute_selectors_match_values_and_combine_with_classes() {
+    let sheet = parse_css(
+        "[data-state] { width: 1px; }
+         [data-state=\"open\"] { height: 1px; }
+         .panel[data-state^=\"cl\"] { height: 2px; }
+         [data-icon$=\".svg\"] { width: 3px; }
+         [data-icon*=arrow] { height: 3px; }",
+    )
+    .unwrap();
+    let resolve = |attributes: &[(&str, &str)]| {
+        let attributes = attributes
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let mut style = Style::default();
+        apply_matching_rules(
+            &mut style,
+            &InlineStyle::default(),
+            None,
+            &attributes,
+            false,
+            &Inherited::default(),
+            &sheet,
+            &MediaEnvironment::default(),
+        );
+        (style.width, style.height)
+    };
+    let px = |px| Some(Length::Px(px));
+
+    assert_eq!(resolve(&[]), (None, None));
+    assert_eq!(resolve(&[("data-state", "")]), (px(1.0), None));
+    assert_eq!(resolve(&[("data-state", "open")]), (px(1.0), px(1.0)));
+    // The class and the attribute must both match.
+    assert_eq!(resolve(&[("data-state", "closed")]), (px(1.0), None));
+    assert_eq!(
+        resolve(&[("data-state", "closed"), ("class", "panel")]),
+        (px(1.0), px(2.0))
+    );
+    assert_eq!(
+        resolve(&[("data-icon", "up-arrow.svg")]),
+        (px(3.0), px(3.0))
+    );
+    assert_eq!(resolve(&[("data-icon", "arrow.png")]), (None, px(3.0)));
+}
+
+#[test]
+fn toggling_an_attribute_restyles_on_the_next_layout() {
+    let (mut ctx, nodes) = document();
+    for rule in parse_css(".a[data-state=\"open\"] { background-color: blue; }")
+        .unwrap()
+        .rules
+    {
+        ctx.style_sheet.add_rule(rule);
+    }
+    let set_state = |ctx: &mut LayoutContext, id: Id, state: &str| {
+        ctx.document
+            .set_attribute(id, "data-state".to_owned(), state.to_owned());
+        ctx.layout();
+    };
+
+    set_state(&mut ctx, nodes[0], "open");
+    assert_eq!(background(&ctx, nodes[0]), Some(BLUE));
+    // Nodes of the same class that differ in the attribute don't share the style.
+    assert_eq!(background(&ctx, nodes[1]), Some(RED));
+
+    set_state(&mut ctx, nodes[0], "closed");
+    assert_eq!(background(&ctx, nodes[0]), Some(RED));
+    set_state(&mut ctx, nodes[0], "open");
+    assert_eq!(background(&ctx, nodes[0]), Some(BLUE));
+}
+
+const THEME: &str = "
+.theme { --primary: #3366ff; --r: 255; }
+.dark { --primary: rgb(0, 0, var(--r)); }
+.button { background-color: var(--primary); }
+.tinted { background-color: rgb(var(--r), 0, 0); }
+.fallback { background-color: var(--missing, rgb(0, 0, var(--r))); }
+.missing { background-color: red; background-color: var(--missing); }
+.cyclic { --a: var(--b); --b: var(--a); background-color: var(--a, blue); color: var(--b); }
+.override { background-color: var(--primary); background-color: red; }
+";
+
+/// A laid out document with the root of class `theme`, and one node of each of `classes`
+/// in a plain node under it.
+fn themed(classes: &[&str]) -> (LayoutContext, Vec<Id>) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = parse_css(THEME).unwrap();
+    let root = ctx.document.root_id();
+    ctx.document
+        .set_attribute(root, "class".to_owned(), "theme".to_owned());
+    let wrapper = ctx.document.create_node(Id::from_u64(2), None).unwrap();
+    ctx.document.set_parent(root, wrapper).unwrap();
+    let nodes = classes
+        .iter()
+        .zip(3..)
+        .map(|(class, id)| {
+            let id = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+            ctx.document.set_parent(wrapper, id).unwrap();
+            ctx.document
+                .set_attribute(id, "class".to_owned(), class.to_string());
+            id
+        })
+        .collect();
+    ctx.layout();
+    (ctx, nodes)
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Option<Rgba> {
+    Some(Rgba { r, g, b, a: 255 })
+}
+
+#[test]
+fn var_refers_to_custom_properties_of_ancestors() {
+    let (ctx, nodes) = themed(&["button", "tinted", "fallback"]);
+    assert_eq!(background(&ctx, nodes[0]), rgb(0x33, 0x66, 0xff));
+    assert_eq!(background(&ctx, nodes[1]), rgb(255, 0, 0));
+    assert_eq!(background(&ctx, nodes[2]), rgb(0, 0, 255));
+}
+
+#[test]
+fn unresolvable_var_drops_the_declaration() {
+    let (ctx, nodes) = themed(&["missing", "cyclic", "override"]);
+    // Earlier declarations still apply, where browsers would reset the property.
+    assert_eq!(background(&ctx, nodes[0]), Some(RED));
+    // Properties in a cycle are invalid: the fallback is used, or nothing.
+    assert_eq!(background(&ctx, nodes[1]), Some(BLUE));
+    let node = ctx.document.get_node(nodes[1]).unwrap();
+    assert_eq!(node.borrow().layout.style.color, None);
+    // Declarations with `var()` come in source order among the others.
+    assert_eq!(background(&ctx, nodes[2]), Some(RED));
+}
+
+#[test]
+fn changing_a_custom_property_on_the_root_restyles_what_uses_it() {
+    let (mut ctx, nodes) = themed(&["button", "tinted"]);
+    let root = ctx.document.root_id();
+    ctx.document
+        .set_attribute(root, "class".to_owned(), "theme dark".to_owned());
+    ctx.layout();
+    assert_eq!(background(&ctx, nodes[0]), rgb(0, 0, 255));
+    assert_eq!(background(&ctx, nodes[1]), rgb(255, 0, 0));
+
+    ctx.document
+        .set_attribute(root, "class".to_owned(), String::new());
+    ctx.layout();
+    assert_eq!(background(&ctx, nodes[0]), None);
+}
+
+const FONT_SIZES: &str = "
+.page { font-size: 20px; }
+.big { font-size: 1.5em; }
+.half { font-size: 50%; }
+.padded { padding: 1em 0.5em; }
+.sized { width: 2rem; height: 1em; }
+";
+
+/// A laid out document with the root of class `page`, and a chain of nodes under it, each
+/// the child of the one before, of `classes`.
+fn nested(classes: &[&str]) -> (LayoutContext, Vec<Id>) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = parse_css(FONT_SIZES).unwrap();
+    let mut parent = ctx.document.root_id();
+    ctx.document
+        .set_attribute(parent, "class".to_owned(), "page".to_owned());
+    let nodes = classes
+        .iter()
+        .zip(2..)
+        .map(|(class, id)| {
+            let id = ctx.document.create_node(Id::from_u64(id), None).unwrap();
+            ctx.document.set_parent(parent, id).unwrap();
+            ctx.document
+                .set_attribute(id, "class".to_owned(), class.to_string());
+            parent = id;
+            id
+        })
+        .collect();
+    ctx.layout();
+    (ctx, nodes)
+}
+
+fn px(px: f64) -> Option<Length> {
+    Some(Length::Px(px))
+}
+
+fn computed(ctx: &LayoutContext, id: Id) -> Arc<Style> {
+    ctx.document
+        .get_node(id)
+        .unwrap()
+        .borrow()
+        .layout
+        .style
+        .clone()
+}
+
+#[test]
+fn em_font_sizes_compound_through_ancestors() {
+    let (ctx, nodes) = nested(&["big", "big", "half", "plain"]);
+    let font_sizes: Vec<_> = nodes
+        .iter()
+        .map(|&id| computed(&ctx, id).font_size.clone())
+        .collect();
+    assert_eq!(
+        font_sizes,
+        vec![px(30.0), px(45.0), px(22.5), None],
+        "font sizes aren't inherited, only what em is relative to"
+    );
+}
+
+#[test]
+fn em_lengths_are_relative_to_the_elements_own_font_size() {
+    let (ctx, nodes) = nested(&["big", "big padded", "padded"]);
+    let padding = &computed(&ctx, nodes[1]).padding;
+    assert_eq!((&padding.top, &padding.left), (&px(45.0), &px(22.5)));
+    // Without a font size of its own, em is relative to the default one.
+    let padding = &computed(&ctx, nodes[2]).padding;
+    assert_eq!((&padding.top, &padding.left), (&px(12.0), &px(6.0)));
+}
+
+#[test]
+fn rem_is_relative_to_the_root_font_size() {
+    let (mut ctx, nodes) = nested(&["big", "big sized"]);
+    let style = computed(&ctx, nodes[1]);
+    assert_eq!((&style.width, &style.height), (&px(40.0), &px(45.0)));
+    let bounds = ctx
+        .document
+        .get_node(nodes[1])
+        .unwrap()
+        .borrow()
+        .layout
+        .bounds;
+    assert_eq!((bounds.width, bounds.height), (40.0, 45.0));
+
+    // Unstyled, the root has the default font size.
+    let root = ctx.document.root_id();
+    ctx.document
+        .set_attribute(root, "class".to_owned(), String::new());
+    ctx.layout();
+    let style = computed(&ctx, nodes[1]);
+    assert_eq!((&style.width, &style.height), (&px(24.0), &px(27.0)));
+}
+
+/// The focused node's outline and background in a document with a node of class `a`.
+fn focused_style(css: &str, focused: bool) -> (f64, Option<Rgba>, Option<Rgba>) {
+    let mut ctx = LayoutContext::new();
+    ctx.style_sheet = parse_css(css).unwrap();
+    let root = ctx.document.root_id();
+    let id = ctx.document.create_node(Id::from_u64(2), None).unwrap();
+    ctx.document.set_parent(root, id).unwrap();
+    ctx.document
+        .set_attribute(id, "class".to_owned(), "a".to_owned());
+    ctx.set_focus(focused.then_some(id));
+    let node = ctx.document.get_node(id).unwrap();
+    let style = ctx.resolve_style(&node.borrow());
+    (
+        style.outline_width_px(),
+        style.outline_color,
+        style.background_color,
+    )
+}
+
+#[test]
+fn the_focused_node_gets_a_focus_ring_and_its_rules() {
+    let css = ".a:focus { background-color: red; }";
+    assert_eq!(focused_style(css, false), (0.0, None, None));
+    assert_eq!(
+        focused_style(css, true),
+        (2.0, Some(DEFAULT_FOCUS_ACCENT), Some(RED))
+    );
+
+    // Rules for the outline override the ring's, `none` included.
+    let css = ":focus { outline-color: blue; } .b:focus { outline: none; }";
+    assert_eq!(focused_style(css, true), (2.0, Some(BLUE), None));
+    assert_eq!(
+        focused_style(".a:focus { outline: none; }", true),
+        (0.0, Some(DEFAULT_FOCUS_ACCENT), None)
+    );
+}
+
+#[test]
+fn moving_focus_restyles_both_nodes() {
+    let (mut ctx, nodes) = document();
+    let outline = |ctx: &LayoutContext, id: Id| {
+        let node = ctx.document.get_node(id).unwrap();
+        let width = node.borrow().layout.style.outline_width_px();
+        width
+    };
+    ctx.set_focus(Some(nodes[0]));
+    ctx.layout();
+    assert_eq!(outline(&ctx, nodes[0]), 2.0);
+
+    let lookups = ctx.style_cache_lookups();
+    ctx.set_focus(Some(nodes[1]));
+    ctx.set_focus_accent_color(BLUE);
+    ctx.layout();
+    assert_eq!(ctx.style_cache_lookups(), lookups + 2);
+    assert_eq!(outline(&ctx, nodes[0]), 0.0);
+    assert_eq!(outline(&ctx, nodes[1]), 2.0);
+    let node = ctx.document.get_node(nodes[1]).unwrap();
+    assert_eq!(node.borrow().layout.style.outline_color, Some(BLUE));
+}
@@ -0,0 +1,416 @@
+use crate::flex_layout::{base_sizes_for_item, text_item_size};
+use crate::layout::{Document, LayoutContext, Node, Rect, Size};
+use crate::markup::{parse_markup, MarkupError, MarkupNode};
+use crate::media::MediaEnvironment;
+use crate::style::{FlexDirection, Style};
+use crate::Id;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Markup to instantiate many times, such as the row of a list; see
+/// [`crate::Engine::define_template`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fragment {
+    nodes: Vec<MarkupNode>,
+    assumed_width: f64,
+}
+
+impl Fragment {
+    /// Parse `markup` the way [`crate::Engine::load_markup`] does. An element whose only
+    /// content is text and that has a `slot` attribute is a slot, whose text instances can
+    /// replace.
+    ///
+    /// `assumed_width` is the width the top-level elements are expected to be laid out at,
+    /// such as the width of the list they are rows of.
+    pub fn parse(markup: &str, assumed_width: f64) -> Result<Self, MarkupError> {
+        Ok(Self {
+            nodes: parse_markup(markup)?,
+            assumed_width,
+        })
+    }
+
+    /// The fragment's nodes with the text of each slot named in `substitutions` replaced.
+    pub(crate) fn substituted(&self, substitutions: &[(&str, &str)]) -> Vec<MarkupNode> {
+        let mut nodes = self.nodes.clone();
+        let mut pending: Vec<&mut MarkupNode> = nodes.iter_mut().collect();
+        while let Some(node) = pending.pop() {
+            let MarkupNode::Element {
+                attributes,
+                text,
+                children,
+                ..
+            } = node
+            else {
+                continue;
+            };
+            if let Some(slot) = slot_name(attributes) {
+                let substitution = substitutions.iter().find(|(name, _)| *name == slot);
+                if let (Some(text), Some((_, substitution))) = (text.as_mut(), substitution) {
+                    *text = substitution.to_string();
+                }
+            }
+            pending.extend(children.iter_mut());
+        }
+        nodes
+    }
+}
+
+fn slot_name(attributes: &[(String, String)]) -> Option<&str> {
+    attributes
+        .iter()
+        .find(|(key, _)| key == "slot")
+        .map(|(_, value)| value.as_str())
+}
+
+/// A template defined with [`crate::Engine::define_template`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TemplateId(pub(crate) usize);
+
+/// How template instances were laid out so far, see [`crate::Engine::template_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TemplateStats {
+    /// Instances laid out by patching their template's skeleton.
+    pub hits: u64,
+    /// Instances laid out in full because the skeleton didn't fit them.
+    pub fallbacks: u64,
+}
+
+impl TemplateStats {
+    /// The share of instance layouts that reused a skeleton, 0 when there were none.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.fallbacks;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// The data thread's templates and the instances that can reuse their layout.
+///
+/// A template lays out each top-level element of its fragment once, as a row of the
+/// assumed width, and keeps the result as a skeleton: the bounds and resolved style of
+/// every descendant. When layout reaches an instance whose root got the skeleton's size,
+/// it copies the skeleton into the instance's subtree instead of laying it out, after
+/// measuring the text of its slots. If a slot's text takes another size the instance is
+/// laid out in full. An instance whose nodes are changed afterwards becomes an ordinary
+/// subtree.
+#[derive(Default)]
+pub(crate) struct Templates {
+    templates: RefCell<HashMap<TemplateId, Template>>,
+    /// By the instance's root.
+    instances: HashMap<Id, Instance>,
+    /// The root of the instance each instance node belongs to.
+    instance_of: HashMap<Id, Id>,
+    /// Set while skeletons are laid out.
+    building: Cell<bool>,
+    hits: Cell<u64>,
+    fallbacks: Cell<u64>,
+}
+
+struct Template {
+    fragment: Fragment,
+    /// One per top-level node of the fragment, `None` for nodes without children. Dropped
+    /// when fonts change and rebuilt when next used.
+    skeletons: Option<Vec<Option<Skeleton>>>,
+    /// What the skeletons' styles were resolved against.
+    style_generation: (u64, MediaEnvironment),
+}
+
+struct Skeleton {
+    /// The root's border-box size the skeleton was laid out at.
+    size: Size,
+    /// The root's descendants in document order.
+    nodes: Vec<SkeletonNode>,
+    /// Transitions keep state per node, which patched nodes would miss.
+    has_transitions: bool,
+}
+
+struct SkeletonNode {
+    /// Relative to the root.
+    bounds: Rect,
+    style: Arc<Style>,
+    /// For slots, the size their text took; see [`text_item_size`].
+    text_size: Option<(Option<f64>, Option<f64>)>,
+}
+
+struct Instance {
+    template: TemplateId,
+    /// Which top-level node of the fragment the instance is.
+    index: usize,
+    nodes: Vec<Id>,
+}
+
+impl Templates {
+    pub fn define(&self, id: TemplateId, fragment: Fragment, ctx: &LayoutContext) {
+        let skeletons = self.build_skeletons(&fragment, ctx);
+        self.templates.borrow_mut().insert(
+            id,
+            Template {
+                fragment,
+                skeletons: Some(skeletons),
+                style_generation: ctx.style_generation(),
+            },
+        );
+    }
+
+    /// Register the top-level nodes created from a template, in the fragment's order.
+    pub fn add_instances(&mut self, template: TemplateId, roots: &[Id], document: &Document) {
+        if !self.templates.borrow().contains_key(&template) {
+            return;
+        }
+
+        for (index, &root) in roots.iter().enumerate() {
+            let Some(node) = document.get_node(root) else {
+                continue;
+            };
+            let nodes: Vec<Id> = descendants(&node)
+                .iter()
+                .map(|node| node.borrow().id)
+                .collect();
+            if nodes.is_empty() {
+                continue;
+            }
+
+            for &id in nodes.iter().chain([&root]) {
+                self.instance_of.insert(id, root);
+            }
+            self.instances.insert(
+                root,
+                Instance {
+                    template,
+                    index,
+                    nodes,
+                },
+            );
+        }
+    }
+
+    /// Turn the instance the node belongs to, if any, into an ordinary subtree, as the node
+    /// is about to change.
+    pub fn detach(&mut self, id: Id) {
+        let Some(root) = self.instance_of.get(&id).copied() else {
+            return;
+        };
+        if let Some(instance) = self.instances.remove(&root) {
+            for id in instance.nodes.iter().chain([&root]) {
+                self.instance_of.remove(id);
+            }
+        }
+    }
+
+    /// Drop the skeletons, as text measures differently now.
+    pub fn invalidate(&self) {
+        for template in self.templates.borrow_mut().values_mut() {
+            template.skeletons = None;
+        }
+    }
+
+    pub fn is_building(&self) -> bool {
+        self.building.get()
+    }
+
+    pub fn stats(&self) -> TemplateStats {
+        TemplateStats {
+            hits: self.hits.get(),
+            fallbacks: self.fallbacks.get(),
+        }
+    }
+
+    /// Lay out the subtree of a node that was sized and positioned by copying its
+    /// template's skeleton. Returns `false`, with nothing changed, if the node is no
+    /// instance or the skeleton doesn't fit it.
+    pub fn reuse_skeleton(&self, root: &Rc<RefCell<Node>>, ctx: &LayoutContext) -> bool {
+        if self.building.get() {
+            return false;
+        }
+        let Some(instance) = self.instances.get(&root.borrow().id) else {
+            return false;
+        };
+        self.rebuild_if_stale(instance.template, ctx);
+
+        let templates = self.templates.borrow();
+        let skeleton = templates
+            .get(&instance.template)
+            .and_then(|template| template.skeletons.as_ref())
+            .and_then(|skeletons| skeletons.get(instance.index)?.as_ref());
+        let Some(skeleton) = skeleton else {
+            return false;
+        };
+
+        let reused = patch(skeleton, root, ctx);
+        let counter = if reused { &self.hits } else { &self.fallbacks };
+        counter.set(counter.get() + 1);
+        reused
+    }
+
+    fn rebuild_if_stale(&self, id: TemplateId, ctx: &LayoutContext) {
+        let stale_fragment = {
+            let templates = self.templates.borrow();
+            let Some(template) = templates.get(&id) else {
+                return;
+            };
+            let fresh =
+                template.skeletons.is_some() && template.style_generation == ctx.style_generation();
+            (!fresh).then(|| template.fragment.clone())
+        };
+
+        if let Some(fragment) = stale_fragment {
+            self.define(id, fragment, ctx);
+        }
+    }
+
+    fn build_skeletons(&self, fragment: &Fragment, ctx: &LayoutContext) -> Vec<Option<Skeleton>> {
+        self.building.set(true);
+        let skeletons = fragment
+            .nodes
+            .iter()
+            .map(|node| build_skeleton(node, fragment.assumed_width, ctx))
+            .collect();
+        self.building.set(false);
+        skeletons
+    }
+}
+
+/// Lay out the node as a row of `width` on its own, like a child of a column.
+fn build_skeleton(node: &MarkupNode, width: f64, ctx: &LayoutContext) -> Option<Skeleton> {
+    let root = scratch_tree(node);
+    if root.borrow().children.is_empty() {
+        return None;
+    }
+
+    let style = ctx.computed_style(&root.borrow());
+    let (height, _) = base_sizes_for_item(&root, &style, &FlexDirection::Column, ctx);
+    {
+        let mut root_borrow = root.borrow_mut();
+        root_borrow.layout.bounds = Rect::new(0.0, 0.0, width, height);
+        root_borrow.layout.style = Arc::new(style.clone());
+    }
+    ctx.layout_children(root.clone(), &style);
+
+    let mut has_transitions = uses_transitions(&style);
+    let nodes = descendants(&root)
+        .iter()
+        .map(|node| {
+            let node = node.borrow();
+            let style = node.layout.style.clone();
+            has_transitions |= uses_transitions(&style);
+            let slot_text = node
+                .text
+                .as_deref()
+                .filter(|_| node.attributes.contains_key("slot"));
+            SkeletonNode {
+                bounds: node.layout.bounds,
+                text_size: slot_text.map(|text| text_item_size(text, &style, ctx)),
+                style,
+            }
+        })
+        .collect();
+
+    Some(Skeleton {
+        size: Size { width, height },
+        nodes,
+        has_transitions,
+    })
+}
+
+fn uses_transitions(style: &Style) -> bool {
+    style
+        .transition
+        .as_ref()
+        .is_some_and(|transitions| !transitions.is_empty())
+}
+
+/// Nodes like the ones markup creates, outside of the document.
+fn scratch_tree(node: &MarkupNode) -> Rc<RefCell<Node>> {
+    match node {
+        MarkupNode::Element {
+            tag,
+            attributes,
+            text,
+            children,
+        } => {
+            let mut scratch = Node::new(Id::default(), text.clone());
+            scratch.tag = Some(tag.to_ascii_lowercase());
+            scratch.attributes.extend(attributes.iter().cloned());
+            for child in children {
+                let child = scratch_tree(child);
+                child.borrow_mut().parent = Some(scratch.id);
+                scratch.add_child(child);
+            }
+            Rc::new(RefCell::new(scratch))
+        }
+        MarkupNode::Text(text) => {
+            Rc::new(RefCell::new(Node::new(Id::default(), Some(text.clone()))))
+        }
+    }
+}
+
+/// The node's descendants in document order.
+fn descendants(node: &Rc<RefCell<Node>>) -> Vec<Rc<RefCell<Node>>> {
+    let mut descendants = Vec::new();
+    let mut pending: Vec<_> = node.borrow().children.iter().rev().cloned().collect();
+    while let Some(node) = pending.pop() {
+        pending.extend(node.borrow().children.iter().rev().cloned());
+        descendants.push(node);
+    }
+    descendants
+}
+
+/// Copy the skeleton into the subtree of `root`, which layout already sized and positioned,
+/// if the size matches and every slot's text takes the size it did in the skeleton.
+fn patch(skeleton: &Skeleton, root: &Rc<RefCell<Node>>, ctx: &LayoutContext) -> bool {
+    let origin = root.borrow().layout.bounds;
+    // With custom properties or font-relative lengths, styles depend on the instance's
+    // ancestors, which the skeleton was resolved without. It was resolved in the document's
+    // viewport, too, which a window's may match other `@media` width rules than.
+    if skeleton.has_transitions
+        || ctx.style_sheet.depends_on_ancestors()
+        || ctx.media_of(&root.borrow()) != ctx.style_generation().1
+        || origin.width != skeleton.size.width
+        || origin.height != skeleton.size.height
+    {
+        return false;
+    }
+
+    let nodes = descendants(root);
+    if nodes.len() != skeleton.nodes.len() {
+        return false;
+    }
+    // Image nodes take the size of what they decode to, which skeletons don't know.
+    if std::iter::once(root)
+        .chain(&nodes)
+        .any(|node| node.borrow().attributes.contains_key("src"))
+    {
+        return false;
+    }
+    let slots_fit = nodes.iter().zip(&skeleton.nodes).all(|(node, skeleton)| {
+        let Some(expected) = skeleton.text_size else {
+            return true;
+        };
+        // Whitespace-only text isn't laid out at all.
+        let node = node.borrow();
+        let text = node.text.as_deref().filter(|text| !text.trim().is_empty());
+        text.is_some_and(|text| text_item_size(text, &skeleton.style, ctx) == expected)
+    });
+    if !slots_fit {
+        return false;
+    }
+
+    for (node, skeleton) in nodes.iter().zip(&skeleton.nodes) {
+        let mut node = node.borrow_mut();
+        node.layout.bounds = Rect {
+            x: origin.x + skeleton.bounds.x,
+            y: origin.y + skeleton.bounds.y,
+            ..skeleton.bounds
+        };
+        node.layout.style = skeleton.style.clone();
+    }
+    true
+}
+
+#[cfg(test)]
+mod template_tests;
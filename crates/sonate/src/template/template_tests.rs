@@ -0,0 +1,187 @@
+use super::{Fragment, TemplateId};
+use crate::css_parser::parse_css;
+use crate::layout::{build_render_tree, LayoutContext};
+use crate::markup::MarkupNode;
+use crate::{Engine, Id, RenderSnapshot};
+
+const CSS: &str = r#"
+    .list { flex-direction: column; width: 400px; }
+    .row { flex-direction: row; align-items: flex-start; column-gap: 8px; padding: 4px; }
+    .name { width: 60px; font-size: 10px; }
+    .count { font-size: 10px; }
+"#;
+
+const ROW: &str = r#"<div class="row"><span class="name" slot="name">Item 000</span><span class="count" slot="count">0</span><div class="icon"></div></div>"#;
+
+const ROWS: usize = 200;
+
+/// A document whose root is a 400px list, as wide as the rows are assumed to be.
+fn list_context() -> LayoutContext {
+    let mut ctx = LayoutContext::new();
+    for rule in parse_css(CSS).unwrap().rules {
+        ctx.style_sheet.add_rule(rule);
+    }
+    ctx.set_viewport_size(400.0, 10000.0);
+    let root = ctx.document.root_id();
+    ctx.document
+        .set_attribute(root, "class".to_owned(), "list".to_owned());
+    ctx
+}
+
+/// Create the nodes the way `Engine::load_markup_into` does, numbering them from `next_id`.
+fn create(ctx: &mut LayoutContext, parent: Id, node: &MarkupNode, next_id: &mut u64) -> Id {
+    let id = Id::from_u64(*next_id);
+    *next_id += 1;
+    match node {
+        MarkupNode::Element {
+            tag,
+            attributes,
+            text,
+            children,
+        } => {
+            ctx.document.create_element(id, tag, text.clone()).unwrap();
+            ctx.document.set_parent(parent, id).unwrap();
+            for (key, value) in attributes {
+                ctx.document.set_attribute(id, key.clone(), value.clone());
+            }
+            for child in children {
+                create(ctx, id, child, next_id);
+            }
+        }
+        MarkupNode::Text(text) => {
+            ctx.document.create_node(id, Some(text.clone())).unwrap();
+            ctx.document.set_parent(parent, id).unwrap();
+        }
+    }
+    id
+}
+
+/// The same rows laid out with and without the template, and the nodes each took.
+fn lay_out_rows(names: &[String]) -> ((LayoutContext, u64), (LayoutContext, u64)) {
+    let fragment = Fragment::parse(ROW, 400.0).unwrap();
+    let template = TemplateId(0);
+
+    let mut contexts = [list_context(), list_context()];
+    contexts[0]
+        .templates
+        .define(template, fragment.clone(), &contexts[0]);
+
+    let mut laid_out = [0, 0];
+    for (i, ctx) in contexts.iter_mut().enumerate() {
+        let mut next_id = 1;
+        let root = ctx.document.root_id();
+        for (n, name) in names.iter().enumerate() {
+            let count = (n % 10).to_string();
+            let nodes = fragment.substituted(&[("name", name), ("count", &count)]);
+            let ids: Vec<Id> = nodes
+                .iter()
+                .map(|node| create(ctx, root, node, &mut next_id))
+                .collect();
+            if i == 0 {
+                ctx.templates.add_instances(template, &ids, &ctx.document);
+            }
+        }
+
+        let before = ctx.laid_out_nodes();
+        ctx.layout();
+        laid_out[i] = ctx.laid_out_nodes() - before;
+    }
+
+    let [flyweight, full] = contexts;
+    ((flyweight, laid_out[0]), (full, laid_out[1]))
+}
+
+fn snapshot(ctx: &LayoutContext) -> RenderSnapshot {
+    RenderSnapshot::from(&build_render_tree(ctx.document.root_node()))
+}
+
+fn names(len: usize) -> Vec<String> {
+    (0..len).map(|n| format!("Item {n:03}")).collect()
+}
+
+#[test]
+fn instances_of_the_same_size_reuse_the_skeleton() {
+    let ((flyweight, flyweight_nodes), (full, full_nodes)) = lay_out_rows(&names(ROWS));
+
+    let stats = flyweight.templates.stats();
+    assert_eq!((stats.hits, stats.fallbacks), (ROWS as u64, 0));
+    assert_eq!(stats.hit_rate(), 1.0);
+    // Only the rows themselves are laid out; their three children come from the skeleton.
+    assert_eq!(full_nodes, 1 + 4 * ROWS as u64);
+    assert_eq!(flyweight_nodes, 1 + ROWS as u64);
+    assert_eq!(snapshot(&flyweight), snapshot(&full));
+}
+
+#[test]
+fn text_that_wraps_differently_falls_back() {
+    let mut names = names(ROWS);
+    names[7] = "A much longer name".to_owned();
+    let ((flyweight, _), (full, _)) = lay_out_rows(&names);
+
+    let stats = flyweight.templates.stats();
+    assert_eq!((stats.hits, stats.fallbacks), (ROWS as u64 - 1, 1));
+    let tree = snapshot(&flyweight);
+    assert_eq!(tree, snapshot(&full));
+    let (short, long) = (&tree.children[6].children[0], &tree.children[7].children[0]);
+    assert_eq!(long.bounds.height, 2.0 * short.bounds.height);
+}
+
+#[test]
+fn changed_instances_and_rules_are_laid_out_in_full() {
+    let ((mut flyweight, _), (mut full, _)) = lay_out_rows(&names(ROWS));
+    let changed = flyweight.document.root_node().borrow().children[3]
+        .borrow()
+        .children[2]
+        .borrow()
+        .id;
+
+    for ctx in [&mut flyweight, &mut full] {
+        ctx.templates.detach(changed);
+        ctx.document
+            .set_attribute(changed, "class".to_owned(), "name".to_owned());
+        for rule in parse_css(".icon { width: 24px; }").unwrap().rules {
+            ctx.style_sheet.add_rule(rule);
+        }
+        ctx.layout();
+    }
+
+    // The skeleton was rebuilt for the new rule; the changed row is an instance no more.
+    let stats = flyweight.templates.stats();
+    assert_eq!((stats.hits, stats.fallbacks), (2 * ROWS as u64 - 1, 0));
+    let tree = snapshot(&flyweight);
+    assert_eq!(tree, snapshot(&full));
+    assert_eq!(tree.children[0].children[2].bounds.width, 24.0);
+}
+
+#[test]
+fn engine_instances_match_loaded_markup() {
+    let fragment = Fragment::parse(ROW, 400.0).unwrap();
+    let (flyweight, full) = (Engine::new(), Engine::new());
+    let template = flyweight.define_template(&fragment);
+    for engine in [&flyweight, &full] {
+        engine.add_stylesheet(CSS);
+        let root = engine.root_id();
+        engine.set_attribute(root, "class".to_owned(), "list".to_owned());
+    }
+
+    for n in 0..20 {
+        let name = format!("Item {n:03}");
+        let substitutions = [("name", name.as_str()), ("count", "1")];
+        flyweight
+            .instantiate_template(template, flyweight.root_id(), &substitutions)
+            .unwrap();
+        let markup = ROW.replace("Item 000", &name).replace(">0<", ">1<");
+        full.load_markup(&markup).unwrap();
+    }
+
+    assert_eq!(
+        flyweight.layout_headless(400.0, 1000.0).unwrap(),
+        full.layout_headless(400.0, 1000.0).unwrap()
+    );
+    assert_eq!(flyweight.template_stats().hits, 20);
+    assert_eq!(full.template_stats().hit_rate(), 0.0);
+    assert_eq!(
+        Engine::new().instantiate_template(template, Id::from_u64(0), &[]),
+        None
+    );
+}
@@ -1,30 +1,114 @@
 use crate::{
     layout::Size,
-    style::{Length, Style},
+    style::{Direction, Length, Style, TextAlign, TextOverflow, WhiteSpace},
 };
 use parking_lot::RwLock;
-use skia_safe::{Font, FontMgr, FontStyle};
+use skia_safe::{
+    font_style::{Slant, Weight, Width},
+    Font, FontMetrics, FontMgr, FontStyle, Typeface,
+};
 use std::{collections::HashMap, sync::Arc};
 
+mod bidi;
+pub(crate) mod shaping;
+
+/// The size of text with no font size set.
+pub const DEFAULT_FONT_SIZE_PX: f64 = 12.0;
+
+/// Largest font size text is measured and drawn at; larger sizes are clamped.
+pub const MAX_FONT_SIZE_PX: f64 = 512.0;
+
+/// The family of text with no `font-family` set.
+pub const DEFAULT_FONT_FAMILY: &str = "Arial";
+
+/// The weight of text with no `font-weight` set, `normal`.
+pub const DEFAULT_FONT_WEIGHT: u16 = 400;
+
+/// Families that stand for whatever font the system has of that kind, rather than naming one.
+const GENERIC_FAMILIES: &[&str] = &[
+    "serif",
+    "sans-serif",
+    "monospace",
+    "cursive",
+    "fantasy",
+    "system-ui",
+    "ui-serif",
+    "ui-sans-serif",
+    "ui-monospace",
+    "emoji",
+    "math",
+];
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct FontSpec {
-    pub family: String,
+    /// In order of preference, see [`FontProvider::typeface`].
+    pub families: Vec<String>,
     pub size_px: u32,
+    pub weight: u16,
+    pub italic: bool,
+    /// The base direction the text is ordered in.
+    pub direction: Direction,
+    /// Added after each cluster, in whole pixels; may be negative.
+    pub letter_spacing_px: i32,
+    /// The height of each line, the text centered in it; `None` for the font's own.
+    pub line_height_px: Option<u32>,
 }
 
 impl FontSpec {
     pub fn from_style(style: &Style) -> Self {
-        let family = style
+        let families = style
             .font_family
             .clone()
-            .unwrap_or_else(|| "Arial".to_string());
+            .filter(|families| !families.is_empty())
+            .unwrap_or_else(|| vec![DEFAULT_FONT_FAMILY.to_owned()]);
 
         let size_px = match style.font_size {
-            Some(Length::Px(px)) if px > 0.0 => px.round().clamp(1.0, 512.0) as u32,
-            _ => 12,
+            Some(Length::Px(px)) if px > 0.0 => px.round().clamp(1.0, MAX_FONT_SIZE_PX) as u32,
+            _ => DEFAULT_FONT_SIZE_PX as u32,
+        };
+
+        let letter_spacing_px = match style.letter_spacing {
+            Some(Length::Px(px)) => px.round() as i32,
+            _ => 0,
         };
+        let line_height_px = style
+            .line_height
+            .as_ref()
+            .and_then(|line_height| line_height.to_px(size_px as f64))
+            .map(|px| px.round().max(0.0) as u32);
 
-        Self { family, size_px }
+        Self {
+            families,
+            size_px,
+            weight: style.font_weight.unwrap_or(DEFAULT_FONT_WEIGHT),
+            italic: style.font_style.unwrap_or_default() != crate::style::FontStyle::Normal,
+            direction: style.direction.unwrap_or_default(),
+            letter_spacing_px,
+            line_height_px,
+        }
+    }
+
+    /// How far the text's baseline is below the top of its line, for a font with `metrics`.
+    pub fn baseline(&self, metrics: &FontMetrics) -> f64 {
+        let ascent = -metrics.ascent as f64;
+        match self.line_height_px {
+            // Half of what the line has beyond the font's height goes above the text.
+            Some(line_height) => {
+                let font_height = (metrics.descent - metrics.ascent) as f64;
+                (line_height as f64 - font_height) / 2.0 + ascent
+            }
+            None => ascent,
+        }
+    }
+
+    /// The style the typeface is matched against within a family.
+    fn font_style(&self) -> FontStyle {
+        let slant = if self.italic {
+            Slant::Italic
+        } else {
+            Slant::Upright
+        };
+        FontStyle::new(Weight::from(self.weight as i32), Width::NORMAL, slant)
     }
 }
 
@@ -40,19 +124,141 @@ pub trait TextMeasurer: Send + Sync {
     /// during the pass.
     fn end_layout_pass_and_sweep(&self) {}
 
+    /// Drop all cached measurements, e.g. because the set of available fonts changed.
+    fn invalidate(&self) {}
+
+    /// Measurements that missed the cache so far, for implementations with caches.
+    fn measurement_count(&self) -> u64 {
+        0
+    }
+
     fn measure_unwrapped(&self, text: &str, font: &FontSpec) -> Size;
     fn measure_wrapped(&self, text: &str, font: &FontSpec, max_width_px: f64) -> Size;
+
+    /// Where a caret can go in `text` laid out as one line: before each cluster of characters
+    /// that's shaped as a unit, and at the end. As character indices in ascending order, with
+    /// their distance from where the text starts.
+    ///
+    /// By default every character is a cluster and text runs left to right.
+    fn caret_stops(&self, text: &str, font: &FontSpec) -> Vec<(usize, f64)> {
+        let mut stops = vec![(0, 0.0)];
+        for (index, (offset, c)) in text.char_indices().enumerate() {
+            let end = offset + c.len_utf8();
+            let width = self.measure_unwrapped(&text[..end], font).width;
+            stops.push((index + 1, width));
+        }
+        stops
+    }
+}
+
+/// Resolves font families to typefaces: fonts registered at runtime first, then system fonts.
+///
+/// Clones share the same registrations, and the typefaces resolved so far. Measuring and
+/// painting go through the same provider, so they use the same typeface.
+#[derive(Clone, Default)]
+pub(crate) struct FontProvider {
+    registered: Arc<RwLock<HashMap<String, Typeface>>>,
+    resolved: Arc<RwLock<HashMap<TypefaceKey, Typeface>>>,
+}
+
+/// What a typeface is resolved from: the families, lowercased, and the style.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct TypefaceKey {
+    families: Vec<String>,
+    weight: u16,
+    italic: bool,
+}
+
+impl FontProvider {
+    /// Register a font from TrueType/OpenType data. `family` overrides the name stored in the
+    /// font. Returns the family name the font is available under, or `None` if the data is invalid.
+    pub fn register(&self, family: Option<&str>, data: &[u8]) -> Option<String> {
+        let typeface = FontMgr::default().new_from_data(data, None)?;
+        let family = family
+            .map(str::to_owned)
+            .unwrap_or_else(|| typeface.family_name());
+
+        self.registered
+            .write()
+            .insert(family.to_ascii_lowercase(), typeface);
+        // Families that fell back past it before may resolve to it now.
+        self.resolved.write().clear();
+        Some(family)
+    }
+
+    pub fn registered_count(&self) -> usize {
+        self.registered.read().len()
+    }
+
+    /// How many family lists and styles have a typeface resolved for them.
+    pub fn resolved_count(&self) -> usize {
+        self.resolved.read().len()
+    }
+
+    /// The typeface for the first of the font's families that's registered or installed,
+    /// closest to its weight and slant; generic families such as `sans-serif` are always
+    /// available. Failing all of them, the system's default typeface.
+    ///
+    /// Characters the typeface has no glyph for are drawn from a system font that has one,
+    /// which the shaper finds with `FontMgr::match_family_style_character`.
+    pub fn typeface(&self, font: &FontSpec) -> Typeface {
+        let key = TypefaceKey {
+            families: font
+                .families
+                .iter()
+                .map(|family| family.to_ascii_lowercase())
+                .collect(),
+            weight: font.weight,
+            italic: font.italic,
+        };
+        if let Some(typeface) = self.resolved.read().get(&key) {
+            return typeface.clone();
+        }
+
+        let typeface = self.resolve(&key.families, font.font_style());
+        self.resolved.write().insert(key, typeface.clone());
+        typeface
+    }
+
+    fn resolve(&self, families: &[String], style: FontStyle) -> Typeface {
+        let font_mgr = FontMgr::default();
+        let registered = self.registered.read();
+        families
+            .iter()
+            .find_map(|family| {
+                if let Some(typeface) = registered.get(family) {
+                    return Some(typeface.clone());
+                }
+                if GENERIC_FAMILIES.contains(&family.as_str()) {
+                    // The system maps generic families to a font of its own, or its default.
+                    return font_mgr.legacy_make_typeface(Some(family.as_str()), style);
+                }
+                font_mgr.match_family_style(family, style)
+            })
+            .unwrap_or_else(|| {
+                // Fallback typeface.
+                font_mgr
+                    .legacy_make_typeface(None, style)
+                    .expect("Failed to load any typeface")
+            })
+    }
+
+    pub fn make_font(&self, font: &FontSpec) -> Font {
+        Font::new(self.typeface(font), font.size_px as f32)
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct SkiaTextMeasurer {
     cache: Arc<RwLock<CacheState>>,
+    fonts: FontProvider,
 }
 
 #[derive(Default)]
 struct CacheState {
     epoch: u64,
     map: HashMap<CacheKey, CacheEntry>,
+    misses: u64,
 }
 
 #[derive(Clone, Copy)]
@@ -64,67 +270,74 @@ struct CacheEntry {
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct CacheKey {
     text: String,
-    family: String,
-    size_px: u32,
+    font: FontSpec,
     max_width_px_rounded: u32,
 }
 
 impl SkiaTextMeasurer {
     #[allow(unused)]
     pub fn new() -> Self {
+        Self::with_fonts(FontProvider::default())
+    }
+
+    pub(crate) fn with_fonts(fonts: FontProvider) -> Self {
         Self {
             cache: Arc::new(RwLock::new(CacheState::default())),
+            fonts,
         }
     }
 
-    pub(crate) fn make_font(font: &FontSpec) -> Font {
-        let typeface = FontMgr::default()
-            .match_family(&font.family)
-            .match_style(FontStyle::normal())
-            .unwrap_or_else(|| {
-                // Fallback typeface.
-                FontMgr::default()
-                    .legacy_make_typeface(None, FontStyle::normal())
-                    .expect("Failed to load any typeface")
-            });
-
-        Font::new(typeface, font.size_px as f32)
-    }
-
-    fn measure_unwrapped_uncached(&self, text: &str, font: &FontSpec) -> Size {
-        let font = Self::make_font(font);
+    fn measure_unwrapped_uncached(&self, text: &str, spec: &FontSpec) -> Size {
+        let font = self.fonts.make_font(spec);
 
-        // `measure_str` gives us an advance width; height comes from font metrics.
-        let (advance_width, _bounds) = font.measure_str(text, None);
+        // The width is what the shaped glyphs advance; height comes from font metrics, unless
+        // the line height is set.
+        let line = shaping::shape_line(text, &font, spec.direction, spec.letter_spacing_px as f64);
 
         let (_scale, metrics) = font.metrics();
-        let height = (metrics.descent - metrics.ascent + metrics.leading) as f64;
+        let height = match spec.line_height_px {
+            Some(line_height) => line_height as f64,
+            None => (metrics.descent - metrics.ascent + metrics.leading) as f64,
+        };
 
         Size {
-            width: advance_width as f64,
+            width: line.width,
             height: height.max(0.0),
         }
     }
 
     fn measure_wrapped_uncached(&self, text: &str, font: &FontSpec, max_width_px: f64) -> Size {
-        // NOTE: Skia has a proper paragraph layout API, but Sonate doesn’t depend on it yet.
-        // This approximation is good enough to drive basic layout decisions.
         let max_width_px = max_width_px.max(0.0);
         if max_width_px == 0.0 {
             return Size::default();
         }
 
-        let unwrapped = self.measure_unwrapped_uncached(text, font);
-        if unwrapped.width <= max_width_px {
-            return unwrapped;
-        }
+        // The lines the painter draws, measured without the cache, which the caller holds.
+        let lines = line_boxes(
+            &Uncached(self),
+            text,
+            font,
+            max_width_px,
+            LineStyle::default(),
+        );
+        lines_size(&lines)
+    }
+}
 
-        // Naive wrapping: estimate number of lines by width ratio.
-        let lines = (unwrapped.width / max_width_px).ceil().max(1.0);
-        Size {
-            width: max_width_px,
-            height: unwrapped.height * lines,
-        }
+/// Measures like the measurer it wraps, without its cache.
+struct Uncached<'a>(&'a SkiaTextMeasurer);
+
+impl TextMeasurer for Uncached<'_> {
+    fn measure_unwrapped(&self, text: &str, font: &FontSpec) -> Size {
+        self.0.measure_unwrapped_uncached(text, font)
+    }
+
+    fn measure_wrapped(&self, text: &str, font: &FontSpec, max_width_px: f64) -> Size {
+        self.0.measure_wrapped_uncached(text, font, max_width_px)
+    }
+
+    fn caret_stops(&self, text: &str, font: &FontSpec) -> Vec<(usize, f64)> {
+        self.0.caret_stops(text, font)
     }
 }
 
@@ -140,11 +353,18 @@ impl TextMeasurer for SkiaTextMeasurer {
         state.map.retain(|_, entry| entry.last_used_epoch == epoch);
     }
 
+    fn invalidate(&self) {
+        self.cache.write().map.clear();
+    }
+
+    fn measurement_count(&self) -> u64 {
+        self.cache.read().misses
+    }
+
     fn measure_unwrapped(&self, text: &str, font: &FontSpec) -> Size {
         let key = CacheKey {
             text: text.to_string(),
-            family: font.family.clone(),
-            size_px: font.size_px,
+            font: font.clone(),
             max_width_px_rounded: 0,
         };
 
@@ -157,6 +377,7 @@ impl TextMeasurer for SkiaTextMeasurer {
         }
 
         // Cache miss.
+        state.misses += 1;
         let size = self.measure_unwrapped_uncached(text, font);
         state.map.insert(
             key,
@@ -171,8 +392,7 @@ impl TextMeasurer for SkiaTextMeasurer {
     fn measure_wrapped(&self, text: &str, font: &FontSpec, max_width_px: f64) -> Size {
         let key = CacheKey {
             text: text.to_string(),
-            family: font.family.clone(),
-            size_px: font.size_px,
+            font: font.clone(),
             max_width_px_rounded: max_width_px.round().clamp(0.0, 1_000_000.0) as u32,
         };
 
@@ -185,6 +405,7 @@ impl TextMeasurer for SkiaTextMeasurer {
         }
 
         // Cache miss.
+        state.misses += 1;
         let size = self.measure_wrapped_uncached(text, font, max_width_px);
         state.map.insert(
             key,
@@ -195,6 +416,262 @@ impl TextMeasurer for SkiaTextMeasurer {
         );
         size
     }
+
+    fn caret_stops(&self, text: &str, font: &FontSpec) -> Vec<(usize, f64)> {
+        let line = shaping::shape_line(
+            text,
+            &self.fonts.make_font(font),
+            font.direction,
+            font.letter_spacing_px as f64,
+        );
+        let offsets = char_offsets(text);
+        line.caret_stops(text)
+            .into_iter()
+            .filter_map(|(byte, x)| Some((offsets.binary_search(&byte).ok()?, x)))
+            .collect()
+    }
+}
+
+/// The byte each character of `text` starts at, and its length.
+fn char_offsets(text: &str) -> Vec<usize> {
+    text.char_indices()
+        .map(|(offset, _)| offset)
+        .chain([text.len()])
+        .collect()
+}
+
+/// `text` split into the clusters `measurer` shapes it in.
+fn clusters<'a>(measurer: &dyn TextMeasurer, text: &'a str, font: &FontSpec) -> Vec<&'a str> {
+    let offsets = char_offsets(text);
+    let mut starts: Vec<usize> = measurer
+        .caret_stops(text, font)
+        .into_iter()
+        .map(|(index, _)| offsets[index])
+        .collect();
+    starts.insert(0, 0);
+    starts.dedup();
+    starts.windows(2).map(|w| &text[w[0]..w[1]]).collect()
+}
+
+/// Greedy line breaking: split `text` into lines no wider than `max_width_px`, breaking after
+/// whitespace and, for words wider than a whole line, between the clusters they're shaped in.
+///
+/// Whitespace stays at the end of the line it follows, so the lines concatenate back to `text`.
+pub fn break_lines(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    max_width_px: f64,
+) -> Vec<String> {
+    let width = |s: &str| measurer.measure_unwrapped(s, font).width;
+    let fits = |s: &str| width(s.trim_end()) <= max_width_px;
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+
+    for word in text.split_inclusive(char::is_whitespace) {
+        if fits(&format!("{line}{word}")) {
+            line.push_str(word);
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+
+        if fits(word) {
+            line.push_str(word);
+            continue;
+        }
+
+        // The word doesn't fit on a line of its own; break it wherever it overflows, keeping
+        // clusters such as a letter and its combining marks together.
+        for cluster in clusters(measurer, word, font) {
+            let before = line.len();
+            line.push_str(cluster);
+            if !fits(&line) && before > 0 {
+                line.truncate(before);
+                lines.push(std::mem::replace(&mut line, cluster.to_string()));
+            }
+        }
+    }
+
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// What ends text cut short to fit its box.
+pub const ELLIPSIS: &str = "…";
+
+/// The longest start of `text` that fits in `max_width_px` with [`ELLIPSIS`] after it, or
+/// `None` if all of `text` fits. The cut is between the clusters `text` is shaped in, found
+/// by a binary search over their advances, and drops the whitespace before it. When not
+/// even the ellipsis fits, it's all there is, overflowing.
+pub fn truncate_with_ellipsis(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    max_width_px: f64,
+) -> Option<String> {
+    let width = |s: &str| measurer.measure_unwrapped(s, font).width;
+    if width(text.trim_end()) <= max_width_px {
+        return None;
+    }
+
+    let room = max_width_px - width(ELLIPSIS);
+    let stops = measurer.caret_stops(text, font);
+    // Right-to-left text starts at its right edge, so the advance is the distance from there.
+    let origin = stops.first().map_or(0.0, |&(_, x)| x);
+    let fitting = stops.partition_point(|&(_, x)| (x - origin).abs() <= room);
+    let end = fitting.checked_sub(1).map_or(0, |last| stops[last].0);
+    let kept: String = text.chars().take(end).collect();
+    Some(format!("{}{ELLIPSIS}", kept.trim_end()))
+}
+
+/// The properties of a text node that say how it's broken into lines, see [`line_boxes`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LineStyle {
+    pub white_space: WhiteSpace,
+    pub text_align: TextAlign,
+    pub text_overflow: TextOverflow,
+    pub line_clamp: Option<u32>,
+}
+
+impl LineStyle {
+    pub fn from_style(style: &Style) -> Self {
+        Self {
+            white_space: style.white_space.unwrap_or_default(),
+            text_align: style.text_align.unwrap_or_default(),
+            text_overflow: style.text_overflow.unwrap_or_default(),
+            line_clamp: style.resolved_line_clamp(),
+        }
+    }
+
+    /// Whether the lines may be cut short, which takes measuring them.
+    pub fn truncates(&self) -> bool {
+        self.text_overflow == TextOverflow::Ellipsis || self.line_clamp.is_some()
+    }
+}
+
+/// A line of a text node as it's laid out in its content box, see [`line_boxes`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineBox {
+    /// With the whitespace it ends with, so the lines concatenate back to the text, unless
+    /// it's `truncated`.
+    pub text: String,
+    /// The index of the character of the text the line starts with.
+    pub start: usize,
+    /// From the content box's left edge, where `text-align` puts the line.
+    pub x: f64,
+    /// From the content box's top edge.
+    pub y: f64,
+    /// The width of the line's text without the whitespace it ends with.
+    pub width: f64,
+    pub height: f64,
+    /// Whether the line was cut short and ends in [`ELLIPSIS`].
+    pub truncated: bool,
+}
+
+/// The lines `text` takes in a content box `content_width` wide: broken with
+/// [`break_lines`], or kept on one for `white-space: nowrap`, and stacked from the top, each
+/// placed as `text-align` says.
+///
+/// Past `line-clamp` lines, the rest of the text is cut short to end the last one. With
+/// `text-overflow: ellipsis`, so is the last line if it's wider than the box. Other lines
+/// wider than the box, such as a cluster that can't break, start at its left edge and
+/// overflow it on the right.
+pub fn line_boxes(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    content_width: f64,
+    line_style: LineStyle,
+) -> Vec<LineBox> {
+    let fits = measurer.measure_unwrapped(text.trim_end(), font).width <= content_width;
+    let mut lines = if line_style.white_space == WhiteSpace::Nowrap || fits {
+        vec![text.to_owned()]
+    } else {
+        break_lines(measurer, text, font, content_width)
+    };
+
+    let clamp = line_style
+        .line_clamp
+        .map_or(usize::MAX, |lines| lines.max(1) as usize);
+    let mut truncated = false;
+    if lines.len() > clamp {
+        let rest: String = lines.drain(clamp - 1..).collect();
+        let cut = truncate_with_ellipsis(measurer, &rest, font, content_width);
+        truncated = cut.is_some();
+        lines.push(cut.unwrap_or(rest));
+    } else if line_style.text_overflow == TextOverflow::Ellipsis {
+        if let Some(last) = lines.last_mut() {
+            if let Some(cut) = truncate_with_ellipsis(measurer, last, font, content_width) {
+                *last = cut;
+                truncated = true;
+            }
+        }
+    }
+    let count = lines.len();
+
+    let mut start = 0;
+    let mut y = 0.0;
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let size = measurer.measure_unwrapped(line.trim_end(), font);
+            let free = (content_width - size.width).max(0.0);
+            let x = match line_style.text_align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => free / 2.0,
+                TextAlign::Right => free,
+            };
+            let line_box = LineBox {
+                start,
+                x,
+                y,
+                width: size.width,
+                height: size.height,
+                truncated: truncated && index + 1 == count,
+                text: line,
+            };
+            start += line_box.text.chars().count();
+            y += size.height;
+            line_box
+        })
+        .collect()
+}
+
+/// The size of `text` wrapped within `max_width_px`, as [`TextMeasurer::measure_wrapped`]
+/// measures it, but no higher than `line_clamp` lines.
+pub fn measure_clamped(
+    measurer: &dyn TextMeasurer,
+    text: &str,
+    font: &FontSpec,
+    max_width_px: f64,
+    line_clamp: Option<u32>,
+) -> Size {
+    let size = measurer.measure_wrapped(text, font, max_width_px);
+    let Some(lines) = line_clamp else {
+        return size;
+    };
+    let line_height = measurer.measure_unwrapped(text, font).height;
+    Size {
+        height: size.height.min(line_height * lines.max(1) as f64),
+        ..size
+    }
+}
+
+/// The size of the lines of [`line_boxes`] from the content box's top left: as wide as the
+/// widest, as high as all of them.
+pub fn lines_size(lines: &[LineBox]) -> Size {
+    Size {
+        width: lines.iter().map(|line| line.width).fold(0.0, f64::max),
+        height: lines.last().map_or(0.0, |line| line.y + line.height),
+    }
 }
 
 #[cfg(test)]
@@ -207,8 +684,10 @@ impl TextMeasurer for TestTextMeasurer {
     fn measure_unwrapped(&self, text: &str, font: &FontSpec) -> Size {
         // Deterministic sizing for unit tests.
         let size = font.size_px as f64;
-        let char_w = (size * 0.6).max(1.0);
-        let line_h = (size * 1.2).max(1.0);
+        let char_w = (size * 0.6).max(1.0) + font.letter_spacing_px as f64;
+        let line_h = font
+            .line_height_px
+            .map_or((size * 1.2).max(1.0), |line_height| line_height as f64);
 
         Size {
             width: (text.chars().count() as f64) * char_w,
@@ -245,3 +724,6 @@ pub fn default_text_measurer() -> Arc<dyn TextMeasurer> {
         Arc::new(SkiaTextMeasurer::new())
     }
 }
+
+#[cfg(test)]
+mod text_tests;
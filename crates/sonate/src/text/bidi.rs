@@ -0,0 +1,258 @@
+//! Ordering of bidirectional text, after the Unicode Bidirectional Algorithm (UAX #9) for a
+//! single paragraph without explicit embeddings, overrides or isolates.
+
+use crate::style::Direction;
+use std::ops::Range;
+
+/// A stretch of text at one embedding level: even levels are laid out left to right, odd
+/// levels right to left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BidiRun {
+    /// In bytes.
+    pub range: Range<usize>,
+    pub level: u8,
+}
+
+impl BidiRun {
+    pub fn is_rtl(&self) -> bool {
+        self.level % 2 == 1
+    }
+}
+
+/// Bidirectional character types, named as in UAX #9, as far as the rules below tell them
+/// apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    /// Left to right.
+    L,
+    /// Right to left.
+    R,
+    /// Arabic letter.
+    Al,
+    /// European number.
+    En,
+    /// European number separator.
+    Es,
+    /// European number terminator.
+    Et,
+    /// Arabic number.
+    An,
+    /// Common number separator.
+    Cs,
+    /// Nonspacing mark, and boundary neutrals, which take the type of what they follow.
+    Nsm,
+    /// Whitespace.
+    Ws,
+    /// Other neutrals.
+    On,
+}
+
+/// The type of a character, by the block it's in. Exact for Latin, Hebrew and Arabic text
+/// and common punctuation, approximate elsewhere: other scripts are left to right.
+fn class_of(c: char) -> Class {
+    use Class::*;
+    match c {
+        '0'..='9' | '\u{B2}' | '\u{B3}' | '\u{B9}' | '\u{6F0}'..='\u{6F9}' => En,
+        '\u{FF10}'..='\u{FF19}' | '\u{2070}'..='\u{2079}' | '\u{2080}'..='\u{2089}' => En,
+        '+' | '-' | '\u{207A}' | '\u{207B}' | '\u{FF0B}' | '\u{FF0D}' => Es,
+        '#' | '$' | '%' | '\u{A2}'..='\u{A5}' | '\u{B0}' | '\u{B1}' | '\u{2030}'..='\u{2034}' => Et,
+        '\u{20A0}'..='\u{20CF}' | '\u{66A}' => Et,
+        ',' | '.' | '/' | ':' | '\u{A0}' | '\u{60C}' | '\u{202F}' | '\u{2044}' => Cs,
+        '\u{600}'..='\u{605}' | '\u{660}'..='\u{669}' | '\u{66B}' | '\u{66C}' => An,
+        '\u{300}'..='\u{36F}' | '\u{483}'..='\u{489}' | '\u{1AB0}'..='\u{1AFF}' => Nsm,
+        '\u{1DC0}'..='\u{1DFF}' | '\u{20D0}'..='\u{20FF}' | '\u{FE00}'..='\u{FE0F}' => Nsm,
+        '\u{FE20}'..='\u{FE2F}' | '\u{200B}'..='\u{200D}' | '\u{FEFF}' => Nsm,
+        '\u{591}'..='\u{5BD}' | '\u{5BF}' | '\u{5C1}' | '\u{5C2}' | '\u{5C4}' | '\u{5C5}' => Nsm,
+        '\u{5C7}' | '\u{610}'..='\u{61A}' | '\u{64B}'..='\u{65F}' | '\u{670}' => Nsm,
+        '\u{6D6}'..='\u{6DC}' | '\u{6DF}'..='\u{6E4}' | '\u{6E7}' | '\u{6E8}' => Nsm,
+        '\u{6EA}'..='\u{6ED}' => Nsm,
+        '\u{200E}' => L,
+        '\u{200F}' => R,
+        '\u{61C}' => Al,
+        '\u{590}'..='\u{5FF}' | '\u{7C0}'..='\u{85F}' | '\u{FB1D}'..='\u{FB4F}' => R,
+        '\u{10800}'..='\u{10FFF}' | '\u{1E800}'..='\u{1EDFF}' => R,
+        '\u{600}'..='\u{7BF}' | '\u{860}'..='\u{8FF}' | '\u{FB50}'..='\u{FDFF}' => Al,
+        '\u{FE70}'..='\u{FEFF}' | '\u{1EE00}'..='\u{1EEFF}' => Al,
+        c if c.is_whitespace() => Ws,
+        c if c.is_control() => Nsm,
+        c if c.is_ascii_punctuation() => On,
+        '\u{A1}'..='\u{BF}' if !matches!(c, '\u{AA}' | '\u{B5}' | '\u{BA}') => On,
+        '\u{D7}' | '\u{F7}' | '\u{2010}'..='\u{2BFF}' | '\u{3000}'..='\u{303F}' => On,
+        '\u{FE30}'..='\u{FE4F}' | '\u{FF01}'..='\u{FF0F}' | '\u{1F000}'..='\u{1FAFF}' => On,
+        _ => L,
+    }
+}
+
+/// Split `text` into runs at one level each, laid out with `direction` as the base
+/// direction. The runs are in visual order, left to right; each covers the text in logical
+/// order, which right-to-left runs are laid out reversed.
+pub(crate) fn visual_runs(text: &str, direction: Direction) -> Vec<BidiRun> {
+    let base = match direction {
+        Direction::Ltr => 0,
+        Direction::Rtl => 1,
+    };
+    let levels = resolve_levels(text, base);
+
+    let mut runs: Vec<BidiRun> = Vec::new();
+    for ((offset, c), level) in text.char_indices().zip(levels) {
+        match runs.last_mut() {
+            Some(run) if run.level == level => run.range.end = offset + c.len_utf8(),
+            _ => runs.push(BidiRun {
+                range: offset..offset + c.len_utf8(),
+                level,
+            }),
+        }
+    }
+
+    let levels: Vec<u8> = runs.iter().map(|run| run.level).collect();
+    let order = visual_order(&levels);
+    order.into_iter().map(|index| runs[index].clone()).collect()
+}
+
+/// The embedding level of each character of `text`.
+fn resolve_levels(text: &str, base: u8) -> Vec<u8> {
+    use Class::*;
+
+    let original: Vec<Class> = text.chars().map(class_of).collect();
+    let mut classes = original.clone();
+    let sos = if base.is_multiple_of(2) { L } else { R };
+
+    // W1: nonspacing marks take the type of what they follow.
+    let mut previous = sos;
+    for class in &mut classes {
+        if *class == Nsm {
+            *class = previous;
+        }
+        previous = *class;
+    }
+
+    // W2: European numbers after Arabic letters are Arabic numbers. W3: Arabic letters are
+    // right to left.
+    let mut strong = sos;
+    for class in &mut classes {
+        match *class {
+            En if strong == Al => *class = An,
+            L | R | Al => strong = *class,
+            _ => {}
+        }
+        if *class == Al {
+            *class = R;
+        }
+    }
+
+    // W4: a single separator between two numbers of the same kind joins them.
+    for i in 1..classes.len().saturating_sub(1) {
+        let (before, after) = (classes[i - 1], classes[i + 1]);
+        classes[i] = match (before, classes[i], after) {
+            (En, Es | Cs, En) => En,
+            (An, Cs, An) => An,
+            (_, class, _) => class,
+        };
+    }
+
+    // W5: terminators next to European numbers are European numbers.
+    let mut i = 0;
+    while i < classes.len() {
+        if classes[i] != Et {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < classes.len() && classes[i] == Et {
+            i += 1;
+        }
+        let touches_number =
+            (start > 0 && classes[start - 1] == En) || (i < classes.len() && classes[i] == En);
+        if touches_number {
+            classes[start..i].fill(En);
+        }
+    }
+
+    // W6: other separators and terminators are neutral. W7: European numbers take a
+    // preceding left-to-right direction.
+    let mut strong = sos;
+    for class in &mut classes {
+        match *class {
+            Es | Et | Cs => *class = On,
+            En if strong == L => *class = L,
+            L | R => strong = *class,
+            _ => {}
+        }
+    }
+
+    // N1: neutrals between text of the same direction take that direction, numbers counting
+    // as right to left. N2: other neutrals take the base direction.
+    let direction_of = |class: Class| match class {
+        L => Some(L),
+        R | En | An => Some(R),
+        _ => None,
+    };
+    let mut i = 0;
+    while i < classes.len() {
+        if direction_of(classes[i]).is_some() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < classes.len() && direction_of(classes[i]).is_none() {
+            i += 1;
+        }
+        let before = start
+            .checked_sub(1)
+            .map_or(Some(sos), |j| direction_of(classes[j]));
+        let after = classes.get(i).map_or(Some(sos), |c| direction_of(*c));
+        let resolved = match (before, after) {
+            (Some(before), Some(after)) if before == after => before,
+            _ => sos,
+        };
+        classes[start..i].fill(resolved);
+    }
+
+    // I1, I2: levels from the resolved types.
+    let mut levels: Vec<u8> = classes
+        .iter()
+        .map(|class| match (base % 2, class) {
+            (0, R) => base + 1,
+            (0, An | En) => base + 2,
+            (1, L | En | An) => base + 1,
+            _ => base,
+        })
+        .collect();
+
+    // L1: trailing whitespace is at the base level.
+    for (level, class) in levels.iter_mut().zip(&original).rev() {
+        if !matches!(class, Ws | Nsm) {
+            break;
+        }
+        *level = base;
+    }
+
+    levels
+}
+
+/// L2: the visual order of runs at the given levels, as indices into `levels`. From the
+/// highest level down to the lowest odd one, every sequence of runs at that level or higher
+/// is reversed.
+pub(crate) fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let Some(&highest) = levels.iter().max() else {
+        return order;
+    };
+    let lowest_odd = levels.iter().map(|level| level | 1).min().unwrap_or(1);
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+        while i < order.len() {
+            if levels[order[i]] < level {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < order.len() && levels[order[i]] >= level {
+                i += 1;
+            }
+            order[start..i].reverse();
+        }
+    }
+    order
+}
@@ -0,0 +1,210 @@
+//! Shaping a line of text into positioned glyphs, for measuring and painting alike.
+
+use super::bidi;
+use crate::style::Direction;
+use skia_safe::{
+    shaper::run_handler::{Buffer, RunHandler, RunInfo},
+    Font, FontMgr, GlyphId, Point, Shaper,
+};
+use std::ops::Range;
+
+thread_local! {
+    /// Shaping with HarfBuzz, falling back to system fonts for characters the font lacks.
+    static SHAPER: Shaper = {
+        skia_safe::icu::init();
+        Shaper::new(FontMgr::default())
+    };
+}
+
+/// A line of text as glyphs, in runs of one direction and font.
+pub(crate) struct ShapedLine {
+    /// In visual order, left to right.
+    pub runs: Vec<ShapedRun>,
+    pub width: f64,
+}
+
+pub(crate) struct ShapedRun {
+    /// The font the glyphs are from, which may be a fallback for the one asked for.
+    pub font: Font,
+    /// The bytes of the line's text the run covers.
+    pub range: Range<usize>,
+    pub rtl: bool,
+    /// Where the run starts, from the start of the line.
+    pub x: f64,
+    pub advance: f64,
+    /// In visual order.
+    pub glyphs: Vec<GlyphId>,
+    /// Where each glyph is drawn, from where the run starts.
+    pub positions: Vec<Point>,
+    /// Where the pen is at each glyph, before mark offsets, from where the run starts.
+    pen: Vec<f64>,
+    /// For each glyph, the byte of the line's text its cluster starts at. A cluster is the
+    /// smallest unit the shaper maps characters to glyphs in, e.g. a letter with its combining
+    /// marks or the letters of a ligature.
+    clusters: Vec<usize>,
+}
+
+impl ShapedRun {
+    /// The run's clusters in logical order: the byte each starts at, and its left and right
+    /// edges from where the run starts.
+    fn cluster_extents(&self) -> Vec<(usize, f64, f64)> {
+        let mut extents: Vec<(usize, f64, f64)> = Vec::new();
+        for (i, &cluster) in self.clusters.iter().enumerate() {
+            let left = self.pen[i];
+            let right = self.pen.get(i + 1).copied().unwrap_or(self.advance);
+            match extents.iter_mut().find(|extent| extent.0 == cluster) {
+                Some(extent) => {
+                    extent.1 = extent.1.min(left);
+                    extent.2 = extent.2.max(right);
+                }
+                None => extents.push((cluster, left, right)),
+            }
+        }
+        extents.sort_by_key(|extent| extent.0);
+        extents
+    }
+}
+
+impl ShapedLine {
+    /// Where a caret can go: at the start of each cluster, on the side text in its direction
+    /// starts on, and at the end of the text. As bytes of the text in ascending order, with
+    /// their distance from where the line starts.
+    pub fn caret_stops(&self, text: &str) -> Vec<(usize, f64)> {
+        let mut stops = Vec::new();
+        let mut end = None;
+        for run in &self.runs {
+            let extents = run.cluster_extents();
+            for &(start, left, right) in &extents {
+                let x = if run.rtl { right } else { left };
+                stops.push((start, run.x + x));
+            }
+            if run.range.end == text.len() {
+                end = extents.last().map(|&(_, left, right)| {
+                    (text.len(), run.x + if run.rtl { left } else { right })
+                });
+            }
+        }
+        stops.sort_by_key(|stop| stop.0);
+        stops.dedup_by_key(|stop| stop.0);
+        stops.push(end.unwrap_or((text.len(), self.width)));
+        stops.dedup_by_key(|stop| stop.0);
+        stops
+    }
+}
+
+/// Shape `text` as one line: split it into runs of one direction with `direction` as the
+/// base direction, shape each, and lay them out in visual order, `letter_spacing` pixels
+/// after each cluster.
+pub(crate) fn shape_line(
+    text: &str,
+    font: &Font,
+    direction: Direction,
+    letter_spacing: f64,
+) -> ShapedLine {
+    let mut line = ShapedLine {
+        runs: Vec::new(),
+        width: 0.0,
+    };
+    for bidi_run in bidi::visual_runs(text, direction) {
+        let range = bidi_run.range.clone();
+        let mut collector = RunCollector {
+            offset: range.start,
+            letter_spacing,
+            runs: Vec::new(),
+            offsets: Vec::new(),
+            clusters: Vec::new(),
+        };
+        SHAPER.with(|shaper| {
+            shaper.shape(
+                &text[range],
+                font,
+                !bidi_run.is_rtl(),
+                f32::MAX,
+                &mut collector,
+            )
+        });
+        // Runs come in visual order within the bidi run too.
+        for mut run in collector.runs {
+            run.x = line.width;
+            line.width += run.advance;
+            line.runs.push(run);
+        }
+    }
+    line
+}
+
+/// Collects the runs the shaper produces.
+struct RunCollector {
+    /// Where the shaped text starts in the line's text.
+    offset: usize,
+    letter_spacing: f64,
+    runs: Vec<ShapedRun>,
+    /// The shaper's mark offsets and clusters for the run being shaped.
+    offsets: Vec<Point>,
+    clusters: Vec<u32>,
+}
+
+impl RunHandler for RunCollector {
+    fn begin_line(&mut self) {}
+
+    fn run_info(&mut self, _info: &RunInfo) {}
+
+    fn commit_run_info(&mut self) {}
+
+    fn run_buffer(&mut self, info: &RunInfo) -> Buffer<'_> {
+        let count = info.glyph_count;
+        self.runs.push(ShapedRun {
+            font: info.font.clone(),
+            range: self.offset + info.utf8_range.start..self.offset + info.utf8_range.end,
+            rtl: info.bidi_level % 2 == 1,
+            x: 0.0,
+            advance: info.advance.x as f64,
+            glyphs: vec![0; count],
+            positions: vec![Point::default(); count],
+            pen: Vec::new(),
+            clusters: Vec::new(),
+        });
+        self.offsets = vec![Point::default(); count];
+        self.clusters = vec![0; count];
+
+        let run = self.runs.last_mut().unwrap();
+        Buffer {
+            glyphs: &mut run.glyphs,
+            positions: &mut run.positions,
+            offsets: Some(&mut self.offsets),
+            clusters: Some(&mut self.clusters),
+            point: Point::default(),
+        }
+    }
+
+    fn commit_run_buffer(&mut self, _info: &RunInfo) {
+        let Some(run) = self.runs.last_mut() else {
+            return;
+        };
+        run.pen = run.positions.iter().map(|p| p.x as f64).collect();
+        for (position, offset) in run.positions.iter_mut().zip(&self.offsets) {
+            *position += *offset;
+        }
+        run.clusters = self
+            .clusters
+            .iter()
+            .map(|&cluster| self.offset + cluster as usize)
+            .collect();
+
+        // Each cluster moves the ones right of it along by the spacing it's followed by,
+        // which is part of its extent.
+        if self.letter_spacing != 0.0 && !run.glyphs.is_empty() {
+            let mut shift = 0.0;
+            for i in 0..run.glyphs.len() {
+                if i > 0 && run.clusters[i] != run.clusters[i - 1] {
+                    shift += self.letter_spacing;
+                }
+                run.positions[i].x += shift as f32;
+                run.pen[i] += shift;
+            }
+            run.advance += shift + self.letter_spacing;
+        }
+    }
+
+    fn commit_line(&mut self) {}
+}
@@ -0,0 +1,436 @@
+use super::bidi::{visual_order, visual_runs};
+use super::{
+    break_lines, line_boxes, lines_size, measure_clamped, truncate_with_ellipsis, FontProvider,
+    FontSpec, LineBox, LineStyle, SkiaTextMeasurer, TestTextMeasurer, TextMeasurer,
+};
+use crate::style::{Direction, TextAlign, TextOverflow, WhiteSpace};
+use skia_safe::{FontMgr, FontStyle};
+
+fn font(direction: Direction) -> FontSpec {
+    FontSpec {
+        families: vec!["Arial".to_owned()],
+        size_px: 20,
+        weight: 400,
+        italic: false,
+        direction,
+        letter_spacing_px: 0,
+        line_height_px: None,
+    }
+}
+
+#[test]
+fn arabic_letters_are_shaped_in_context() {
+    let measurer = SkiaTextMeasurer::new();
+    let font = font(Direction::Rtl);
+    let text = "سلام عليكم";
+
+    // Each character on its own is shaped in its isolated form.
+    let isolated: f64 = text
+        .chars()
+        .map(|c| measurer.measure_unwrapped(&c.to_string(), &font).width)
+        .sum();
+    let joined = measurer.measure_unwrapped(text, &font).width;
+    assert!(joined > 0.0);
+    assert!((joined - isolated).abs() > 0.5, "{joined} vs {isolated}");
+}
+
+/// The text of each run of `text`, left to right, with its level.
+fn runs(text: &str, direction: Direction) -> Vec<(&str, u8)> {
+    visual_runs(text, direction)
+        .into_iter()
+        .map(|run| (&text[run.range], run.level))
+        .collect()
+}
+
+#[test]
+fn mixed_direction_text_is_ordered_by_the_base_direction() {
+    let text = "abc אבג def";
+    assert_eq!(
+        runs(text, Direction::Ltr),
+        [("abc ", 0), ("אבג", 1), (" def", 0)]
+    );
+    assert_eq!(
+        runs(text, Direction::Rtl),
+        [("def", 2), (" אבג ", 1), ("abc", 2)]
+    );
+
+    // Numbers keep their order inside right-to-left text, and marks stay with their letter.
+    assert_eq!(runs("אבג 123", Direction::Rtl), [("123", 2), ("אבג ", 1)]);
+    assert_eq!(
+        runs("abc \u{5D0}\u{5B8}\u{5D1}", Direction::Ltr),
+        [("abc ", 0), ("\u{5D0}\u{5B8}\u{5D1}", 1)]
+    );
+    assert_eq!(runs("", Direction::Rtl), []);
+}
+
+#[test]
+fn deeper_levels_are_reversed_within_shallower_ones() {
+    assert_eq!(visual_order(&[0, 1, 2, 1, 0]), [0, 3, 2, 1, 4]);
+    assert_eq!(visual_order(&[1, 2, 1]), [2, 1, 0]);
+    assert_eq!(visual_order(&[2, 2]), [0, 1]);
+}
+
+#[test]
+fn shaped_runs_are_laid_out_in_visual_order() {
+    let measurer = SkiaTextMeasurer::new();
+    let spec = font(Direction::Rtl);
+    let text = "abc אבג def";
+    let line =
+        super::shaping::shape_line(text, &measurer.fonts.make_font(&spec), spec.direction, 0.0);
+
+    let texts: Vec<&str> = line
+        .runs
+        .iter()
+        .map(|run| &text[run.range.clone()])
+        .collect();
+    assert_eq!(texts.concat(), "def אבג abc");
+    assert!(line.runs.windows(2).all(|w| w[0].x < w[1].x));
+    assert_eq!(line.width, measurer.measure_unwrapped(text, &spec).width);
+
+    // The caret before the first character of right-to-left text is at its right edge.
+    let stops = measurer.caret_stops("אבג", &spec);
+    let width = measurer.measure_unwrapped("אבג", &spec).width;
+    assert_eq!(stops.first(), Some(&(0, width)));
+    assert_eq!(stops.last(), Some(&(3, 0.0)));
+}
+
+#[test]
+fn words_break_between_clusters() {
+    let measurer = SkiaTextMeasurer::new();
+    let font = font(Direction::Ltr);
+    let cluster = "e\u{301}";
+    let width = measurer.measure_unwrapped(cluster, &font).width;
+
+    let lines = break_lines(&measurer, &cluster.repeat(3), &font, width + 0.5);
+    assert_eq!(lines, [cluster; 3]);
+}
+
+/// TestTextMeasurer: 20px font => 12px per char, 24px lines.
+fn lines_of(text: &str, width: f64, line_style: LineStyle) -> Vec<LineBox> {
+    line_boxes(
+        &TestTextMeasurer,
+        text,
+        &font(Direction::Ltr),
+        width,
+        line_style,
+    )
+}
+
+/// The lines' text and x offset.
+fn placed(width: f64, white_space: WhiteSpace, text_align: TextAlign) -> Vec<(String, f64)> {
+    let line_style = LineStyle {
+        white_space,
+        text_align,
+        ..Default::default()
+    };
+    lines_of("one two three", width, line_style)
+        .into_iter()
+        .map(|line| (line.text, line.x))
+        .collect()
+}
+
+#[test]
+fn lines_are_placed_as_text_align_says() {
+    let lines = |align| placed(100.0, WhiteSpace::Normal, align);
+    // "one two" is 84px wide and "three" 60px, in 100px.
+    let left = lines(TextAlign::Left);
+    assert_eq!(
+        left,
+        [("one two ".to_owned(), 0.0), ("three".to_owned(), 0.0)]
+    );
+    let center = lines(TextAlign::Center);
+    assert_eq!(
+        center,
+        [("one two ".to_owned(), 8.0), ("three".to_owned(), 20.0)]
+    );
+    let right = lines(TextAlign::Right);
+    assert_eq!(
+        right,
+        [("one two ".to_owned(), 16.0), ("three".to_owned(), 40.0)]
+    );
+
+    let boxes = lines_of("one two three", 100.0, LineStyle::default());
+    assert_eq!((boxes[1].start, boxes[1].y), (8, 24.0));
+    let size = lines_size(&boxes);
+    assert_eq!((size.width, size.height), (84.0, 48.0));
+}
+
+#[test]
+fn nowrap_keeps_text_on_one_line_as_wide_as_unwrapped() {
+    let font = font(Direction::Ltr);
+    let unwrapped = TestTextMeasurer.measure_unwrapped("one two three", &font);
+    for align in [TextAlign::Left, TextAlign::Center, TextAlign::Right] {
+        // Overflowing the box, the line starts at its left edge.
+        let lines = placed(100.0, WhiteSpace::Nowrap, align);
+        assert_eq!(lines, [("one two three".to_owned(), 0.0)]);
+    }
+    let nowrap = LineStyle {
+        white_space: WhiteSpace::Nowrap,
+        ..Default::default()
+    };
+    let boxes = lines_of("one two three", 100.0, nowrap);
+    assert_eq!(lines_size(&boxes).width, unwrapped.width);
+}
+
+#[test]
+fn unbreakable_text_overflows_narrow_boxes() {
+    let right = LineStyle {
+        text_align: TextAlign::Right,
+        ..Default::default()
+    };
+    for width in [0.0, 5.0, -10.0] {
+        let lines = lines_of("abc", width, right);
+        let placed: Vec<_> = lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.x))
+            .collect();
+        assert_eq!(placed, [("a", 0.0), ("b", 0.0), ("c", 0.0)]);
+    }
+    assert_eq!(lines_of("", 0.0, right).len(), 1);
+}
+
+#[test]
+fn truncation_keeps_the_longest_start_that_fits_with_an_ellipsis() {
+    let font = font(Direction::Ltr);
+    let truncate = |text, width| truncate_with_ellipsis(&TestTextMeasurer, text, &font, width);
+    // 12px per character, the ellipsis included.
+    assert_eq!(truncate("", 0.0), None);
+    assert_eq!(truncate("", 100.0), None);
+    // Exactly as wide as the box, or only overflowing it with trailing whitespace.
+    assert_eq!(truncate("abcde", 60.0), None);
+    assert_eq!(truncate("abcde  ", 60.0), None);
+    assert_eq!(truncate("abcdef", 60.0), Some("abcd…".to_owned()));
+    assert_eq!(truncate("abcdef", 59.0), Some("abc…".to_owned()));
+    // Whitespace before the cut goes.
+    assert_eq!(truncate("ab  cdef", 60.0), Some("ab…".to_owned()));
+    // Room for the ellipsis alone, and not even for that.
+    assert_eq!(truncate("abc", 12.0), Some("…".to_owned()));
+    assert_eq!(truncate("abc", 5.0), Some("…".to_owned()));
+    assert_eq!(truncate("abc", -5.0), Some("…".to_owned()));
+}
+
+#[test]
+fn ellipsis_ends_overflowing_and_clamped_lines() {
+    let ellipsis = |white_space, line_clamp| LineStyle {
+        white_space,
+        text_overflow: TextOverflow::Ellipsis,
+        line_clamp,
+        text_align: TextAlign::Right,
+    };
+    let texts = |lines: &[LineBox]| -> Vec<(String, bool)> {
+        (lines.iter())
+            .map(|line| (line.text.clone(), line.truncated))
+            .collect()
+    };
+
+    let lines = lines_of("one two three", 100.0, ellipsis(WhiteSpace::Nowrap, None));
+    assert_eq!(texts(&lines), [("one two…".to_owned(), true)]);
+    // Placed as the shorter text it is.
+    assert_eq!((lines[0].x, lines[0].width), (4.0, 96.0));
+
+    // Fitting text is left as it is.
+    let lines = lines_of("one two", 100.0, ellipsis(WhiteSpace::Nowrap, None));
+    assert_eq!(texts(&lines), [("one two".to_owned(), false)]);
+
+    // Wrapped, the lines fit, so there's nothing to cut short without a clamp.
+    let lines = lines_of(
+        "one two three four",
+        100.0,
+        ellipsis(WhiteSpace::Normal, None),
+    );
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| !line.truncated));
+
+    // The second line ends the rest of the text.
+    let clamped = lines_of(
+        "one two three four",
+        100.0,
+        ellipsis(WhiteSpace::Normal, Some(2)),
+    );
+    let expected = [
+        ("one two ".to_owned(), false),
+        ("three f…".to_owned(), true),
+    ];
+    assert_eq!(texts(&clamped), expected);
+    // Whatever `text-overflow` says.
+    let clip = LineStyle {
+        line_clamp: Some(2),
+        ..Default::default()
+    };
+    let clamped = lines_of("one two three four", 100.0, clip);
+    assert_eq!(texts(&clamped), expected);
+    let enough = LineStyle {
+        line_clamp: Some(3),
+        ..Default::default()
+    };
+    let clamped = lines_of("one two three four", 100.0, enough);
+    assert!(clamped.iter().all(|line| !line.truncated));
+}
+
+#[test]
+fn clamped_text_measures_no_higher_than_its_lines() {
+    let font = font(Direction::Ltr);
+    let text = "one two three four";
+    let measure = |clamp| measure_clamped(&TestTextMeasurer, text, &font, 100.0, clamp);
+    assert_eq!(measure(None).height, 72.0);
+    assert_eq!(measure(Some(2)).height, 48.0);
+    assert_eq!(measure(Some(5)).height, 72.0);
+}
+
+fn families(families: &[&str]) -> FontSpec {
+    FontSpec {
+        families: families.iter().map(|family| family.to_string()).collect(),
+        ..font(Direction::Ltr)
+    }
+}
+
+#[test]
+fn typefaces_are_resolved_once_per_family_list_and_style() {
+    let fonts = FontProvider::default();
+    let regular = families(&["Sonate Missing Family", "sans-serif"]);
+    let first = fonts.typeface(&regular);
+    assert_eq!(fonts.resolved_count(), 1);
+
+    // Names match whatever their case, and the size and direction don't matter.
+    let same = FontSpec {
+        size_px: 40,
+        direction: Direction::Rtl,
+        ..families(&["SONATE MISSING FAMILY", "Sans-Serif"])
+    };
+    assert_eq!(fonts.typeface(&same).unique_id(), first.unique_id());
+    assert_eq!(fonts.resolved_count(), 1);
+
+    // Clones share what's resolved; other weights and slants are resolved in their own right.
+    let bold = FontSpec {
+        weight: 700,
+        ..regular.clone()
+    };
+    fonts.clone().typeface(&bold);
+    let italic = FontSpec {
+        italic: true,
+        ..regular.clone()
+    };
+    fonts.typeface(&italic);
+    assert_eq!(fonts.resolved_count(), 3);
+    fonts.typeface(&bold);
+    assert_eq!(fonts.resolved_count(), 3);
+}
+
+#[test]
+fn families_fall_back_down_the_list() {
+    let fonts = FontProvider::default();
+    let chain = families(&["Sonate Missing Family", "Sonate Chain Test Font", "serif"]);
+    let before = fonts.typeface(&chain);
+
+    let Some((data, _)) = FontMgr::default()
+        .legacy_make_typeface(None, FontStyle::normal())
+        .and_then(|typeface| typeface.to_font_data())
+    else {
+        eprintln!("skipping: no system typeface to register");
+        return;
+    };
+    fonts
+        .register(Some("Sonate Chain Test Font"), &data)
+        .unwrap();
+    // What fell back past the family before is resolved again.
+    assert_eq!(fonts.resolved_count(), 0);
+
+    let registered = fonts.registered.read()["sonate chain test font"].clone();
+    let after = fonts.typeface(&chain);
+    assert_ne!(after.unique_id(), before.unique_id());
+    assert_eq!(after.unique_id(), registered.unique_id());
+    // Measuring and painting make their fonts from the same typeface.
+    assert_eq!(
+        fonts.make_font(&chain).typeface().unique_id(),
+        after.unique_id()
+    );
+}
+
+fn spaced(letter_spacing_px: i32, line_height_px: Option<u32>) -> FontSpec {
+    FontSpec {
+        letter_spacing_px,
+        line_height_px,
+        ..font(Direction::Ltr)
+    }
+}
+
+#[test]
+fn letter_spacing_follows_each_cluster() {
+    let measurer = SkiaTextMeasurer::new();
+    let (plain, wide) = (font(Direction::Ltr), spaced(3, None));
+    let width = measurer.measure_unwrapped("Hello", &plain).width;
+    assert!((measurer.measure_unwrapped("Hello", &wide).width - (width + 15.0)).abs() < 0.01);
+
+    // Carets move along with the characters before them.
+    let stops = measurer.caret_stops("Hello", &wide);
+    let plain_stops = measurer.caret_stops("Hello", &plain);
+    assert_eq!(stops.len(), 6);
+    for (&(index, x), &(_, plain_x)) in stops.iter().zip(&plain_stops) {
+        assert!((x - plain_x - 3.0 * index as f64).abs() < 0.01);
+    }
+}
+
+#[test]
+fn letter_spacing_moves_break_points() {
+    // "aaaa bbbb" is 108px wide in 12px characters, 126px in 14px ones.
+    let lines = |font: &FontSpec| -> Vec<String> {
+        line_boxes(
+            &TestTextMeasurer,
+            "aaaa bbbb cccc",
+            font,
+            110.0,
+            LineStyle::default(),
+        )
+        .into_iter()
+        .map(|line| line.text)
+        .collect()
+    };
+    assert_eq!(lines(&font(Direction::Ltr)), ["aaaa bbbb ", "cccc"]);
+    assert_eq!(lines(&spaced(2, None)), ["aaaa ", "bbbb ", "cccc"]);
+}
+
+#[test]
+fn line_height_sets_how_high_lines_are() {
+    let boxes = line_boxes(
+        &TestTextMeasurer,
+        "one two three",
+        &spaced(0, Some(30)),
+        100.0,
+        LineStyle::default(),
+    );
+    let rows: Vec<_> = boxes.iter().map(|line| (line.y, line.height)).collect();
+    assert_eq!(rows, [(0.0, 30.0), (30.0, 30.0)]);
+
+    // The text is centered in the line: its baseline goes down by half of what's added.
+    let measurer = SkiaTextMeasurer::new();
+    let tall = spaced(0, Some(40));
+    assert_eq!(measurer.measure_unwrapped("Hello", &tall).height, 40.0);
+    let (_, metrics) = measurer.fonts.make_font(&tall).metrics();
+    let added = 40.0 - (metrics.descent - metrics.ascent) as f64;
+    let normal = font(Direction::Ltr).baseline(&metrics);
+    assert!((tall.baseline(&metrics) - normal - added / 2.0).abs() < 0.01);
+}
+
+#[test]
+fn wrapped_text_is_as_high_as_its_lines() {
+    let font = spaced(2, Some(30));
+    let boxes = line_boxes(
+        &TestTextMeasurer,
+        "aaaa bbbb cccc",
+        &font,
+        110.0,
+        LineStyle::default(),
+    );
+    assert_eq!(boxes.len(), 3);
+    assert_eq!(lines_size(&boxes).height, 90.0);
+
+    let measurer = SkiaTextMeasurer::new();
+    let text = "one two three four five six";
+    let width = measurer.measure_unwrapped("one two", &font).width + 1.0;
+    let boxes = line_boxes(&measurer, text, &font, width, LineStyle::default());
+    assert!(boxes.len() > 1);
+    assert_eq!(
+        measurer.measure_wrapped(text, &font, width).height,
+        boxes.len() as f64 * 30.0
+    );
+}
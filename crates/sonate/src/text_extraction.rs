@@ -0,0 +1,121 @@
+use crate::{
+    layout::{Rect, RenderNode},
+    text::{line_boxes, FontSpec, LineStyle, TextMeasurer},
+    Id,
+};
+
+/// A piece of visible text with the box it occupies, in logical pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextRun {
+    pub id: Id,
+    pub text: String,
+    pub bounds: Rect,
+}
+
+/// How [`crate::Engine::extract_text_with`] splits text into runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextGranularity {
+    /// One run per text node, bounded by the node's border box.
+    #[default]
+    Node,
+    /// One run per wrapped line, bounded by the line box.
+    Line,
+}
+
+/// Collect the visible text under `root` (the whole snapshot if `None`) in reading order.
+///
+/// Siblings are visited in `order`, then document order, the way flex layout places them.
+/// `display: none` nodes are not in the snapshot, and `visibility: hidden` subtrees are skipped
+/// like the painter skips them. An unknown or hidden `root` yields no runs.
+pub(crate) fn extract_text(
+    snapshot: &RenderNode,
+    root: Option<Id>,
+    granularity: TextGranularity,
+    measurer: &dyn TextMeasurer,
+) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+
+    let root = match root {
+        Some(id) => find_visible(snapshot, id),
+        None => Some(snapshot),
+    };
+
+    if let Some(root) = root {
+        collect(root, granularity, measurer, &mut runs);
+    }
+
+    runs
+}
+
+/// Join node runs into one string, one line per text node.
+pub(crate) fn plain_text(runs: &[TextRun]) -> String {
+    runs.iter()
+        .map(|run| run.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn find_visible(node: &RenderNode, id: Id) -> Option<&RenderNode> {
+    if node.style.is_hidden() {
+        return None;
+    }
+    if node.id == id {
+        return Some(node);
+    }
+    node.children
+        .iter()
+        .find_map(|child| find_visible(child, id))
+}
+
+fn collect(
+    node: &RenderNode,
+    granularity: TextGranularity,
+    measurer: &dyn TextMeasurer,
+    runs: &mut Vec<TextRun>,
+) {
+    if node.style.is_hidden() {
+        return;
+    }
+
+    if let Some(text) = node.text.as_deref().filter(|text| !text.is_empty()) {
+        match granularity {
+            TextGranularity::Node => runs.push(TextRun {
+                id: node.id,
+                text: text.to_owned(),
+                bounds: node.bounds,
+            }),
+            TextGranularity::Line => line_runs(node, text, measurer, runs),
+        }
+    }
+
+    let mut children: Vec<&RenderNode> = node.children.iter().map(|child| &**child).collect();
+    children.sort_by_key(|child| child.style.order.unwrap_or(0));
+    for child in children {
+        collect(child, granularity, measurer, runs);
+    }
+}
+
+/// Split a text node into the lines the painter draws within its content box, each as wide
+/// as its text and placed as its `text-align` says. Text cut short ends in an ellipsis.
+fn line_runs(node: &RenderNode, text: &str, measurer: &dyn TextMeasurer, runs: &mut Vec<TextRun>) {
+    let style = &node.style;
+    let font = FontSpec::from_style(style);
+    let content = node.content_box();
+    let line_style = LineStyle::from_style(style);
+
+    for line in line_boxes(measurer, text, &font, content.width, line_style) {
+        runs.push(TextRun {
+            id: node.id,
+            bounds: Rect::new(
+                content.x + line.x,
+                content.y + line.y,
+                line.width,
+                line.height,
+            ),
+            text: line.text,
+        });
+    }
+}
+
+#[cfg(test)]
+mod text_extraction_tests;
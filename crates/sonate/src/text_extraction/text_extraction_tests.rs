@@ -0,0 +1,100 @@
+use super::{extract_text, plain_text, TextGranularity, TextRun};
+use crate::layout::test_html::load_html_test_example;
+use crate::layout::{build_render_tree, Rect};
+use crate::text::TestTextMeasurer;
+
+// TestTextMeasurer: 10px font => 6px per char, 12px line height.
+const HTML: &str = r#"
+<style>
+  .column { display: flex; flex-direction: column; width: 300px; }
+  .row { display: flex; flex-direction: row; }
+  .text { width: auto; height: auto; font-size: 10px; }
+  .first { order: -1; }
+  .hidden { visibility: hidden; }
+  .gone { display: none; }
+  .para { width: 60px; height: auto; font-size: 10px; padding: 5px; }
+</style>
+<div id="doc" class="column">
+  <div id="title" class="text">Title</div>
+  <div id="row" class="row">
+    <div id="second" class="text">second</div>
+    <div id="first" class="text first">first</div>
+  </div>
+  <div id="secret" class="hidden">
+    <div id="secret_text" class="text">secret</div>
+  </div>
+  <div id="unrendered" class="text gone">unrendered</div>
+  <div id="footer" class="text">Footer</div>
+</div>
+<div id="wrapped" class="column">
+  <div id="para" class="para">one two three four</div>
+</div>
+"#;
+
+fn texts(runs: &[TextRun]) -> Vec<&str> {
+    runs.iter().map(|run| run.text.as_str()).collect()
+}
+
+#[test]
+fn runs_follow_reading_order_and_skip_invisible_text() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+    let snapshot = build_render_tree(ctx.document.root_node());
+
+    let runs = extract_text(&snapshot, None, TextGranularity::Node, &TestTextMeasurer);
+
+    assert_eq!(texts(&runs), ["Title", "first", "second", "Footer"]);
+    assert_eq!(runs[1].id, ids["first"]);
+    assert_eq!(runs[1].bounds, Rect::new(0.0, 12.0, 30.0, 12.0));
+    assert_eq!(runs[2].id, ids["second"]);
+    assert_eq!(runs[2].bounds, Rect::new(30.0, 12.0, 36.0, 12.0));
+    let footer = ctx.document.get_node(ids["footer"]).unwrap();
+    assert_eq!(runs[3].bounds, footer.borrow().layout.bounds);
+    // The hidden subtree still takes up space.
+    assert!(runs[3].bounds.y > 24.0);
+
+    assert_eq!(plain_text(&runs), "Title\nfirst\nsecond\nFooter");
+}
+
+#[test]
+fn subtree_extraction() {
+    let (ctx, ids) = load_html_test_example(HTML, "doc");
+    let snapshot = build_render_tree(ctx.document.root_node());
+
+    let row = extract_text(
+        &snapshot,
+        Some(ids["row"]),
+        TextGranularity::Node,
+        &TestTextMeasurer,
+    );
+    assert_eq!(texts(&row), ["first", "second"]);
+
+    let in_hidden = extract_text(
+        &snapshot,
+        Some(ids["secret_text"]),
+        TextGranularity::Node,
+        &TestTextMeasurer,
+    );
+    assert!(in_hidden.is_empty());
+}
+
+#[test]
+fn line_granularity_splits_wrapped_paragraph() {
+    let (ctx, ids) = load_html_test_example(HTML, "wrapped");
+    let snapshot = build_render_tree(ctx.document.root_node());
+
+    let lines = extract_text(&snapshot, None, TextGranularity::Line, &TestTextMeasurer);
+
+    assert_eq!(texts(&lines), ["one two ", "three four"]);
+    assert!(lines.iter().all(|line| line.id == ids["para"]));
+    assert_eq!(
+        lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<String>(),
+        "one two three four"
+    );
+
+    // Lines stack from the content-box origin and are as wide as their text.
+    assert_eq!(lines[0].bounds, Rect::new(5.0, 5.0, 42.0, 12.0));
+    assert_eq!(lines[1].bounds, Rect::new(5.0, 17.0, 60.0, 12.0));
+}
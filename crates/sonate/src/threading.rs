@@ -0,0 +1,117 @@
+use crate::commands::Command;
+use crate::EngineError;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::sync::mpsc::SyncSender;
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle, ThreadId};
+
+/// The thread an object was created on, so that calls from other threads fail with
+/// [`EngineError::WrongThread`] instead of misbehaving later.
+///
+/// Checks are made in debug builds, and in release builds too when created `strict`.
+#[derive(Clone, Copy, Debug)]
+pub struct ThreadAffinity {
+    owner: ThreadId,
+    enforced: bool,
+}
+
+impl ThreadAffinity {
+    /// Owned by the calling thread.
+    pub fn current(strict: bool) -> Self {
+        Self {
+            owner: thread::current().id(),
+            enforced: strict || cfg!(debug_assertions),
+        }
+    }
+
+    pub fn owner(&self) -> ThreadId {
+        self.owner
+    }
+
+    /// `Err(EngineError::WrongThread)` if checks are made and this isn't the owning thread.
+    pub fn check(&self) -> Result<(), EngineError> {
+        let current = thread::current().id();
+        if self.enforced && current != self.owner {
+            return Err(EngineError::WrongThread {
+                owner: self.owner,
+                current,
+            });
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    /// The commands a data thread sent itself, which it takes before those in its queue.
+    static DEFERRED: RefCell<VecDeque<Command>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// The next command the data thread running on this thread sent itself, if any.
+pub(crate) fn take_deferred() -> Option<Command> {
+    DEFERRED.with(|deferred| deferred.borrow_mut().pop_front())
+}
+
+/// The thread that applies an engine's changes. It's stopped by [`crate::Engine::shutdown`],
+/// or once the last clone of the engine is dropped.
+pub(crate) struct DataThread {
+    sender: SyncSender<Command>,
+    id: ThreadId,
+    /// `None` once shut down.
+    handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DataThread {
+    pub fn new(sender: SyncSender<Command>, handle: JoinHandle<()>) -> Self {
+        Self {
+            sender,
+            id: handle.thread().id(),
+            handle: Mutex::new(Some(handle)),
+        }
+    }
+
+    /// Hand the thread a command, once it has room for it; on the thread itself, set it
+    /// aside for the thread to take next instead of waiting. Fails with
+    /// [`EngineError::ShuttingDown`] once the thread exited.
+    pub fn send(&self, command: Command) -> Result<(), EngineError> {
+        if thread::current().id() == self.id {
+            DEFERRED.with(|deferred| deferred.borrow_mut().push_back(command));
+            return Ok(());
+        }
+        self.sender
+            .send(command)
+            .map_err(|_| EngineError::ShuttingDown)
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.handle.lock().unwrap().is_none()
+    }
+
+    /// Stop the thread after the commands sent before, and wait until it exited; on the
+    /// thread itself it exits once the caller returns instead. Fails with
+    /// [`EngineError::ShuttingDown`] if it was shut down before.
+    pub fn shut_down(&self) -> Result<(), EngineError> {
+        let handle = self
+            .handle
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(EngineError::ShuttingDown)?;
+        // Fails when the thread stopped by panicking.
+        let _ = self.send(Command::Shutdown);
+        if self.id != thread::current().id() {
+            // The thread catches its panics, see `Engine::health`.
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for DataThread {
+    fn drop(&mut self) {
+        let _ = self.shut_down();
+    }
+}
+
+#[cfg(test)]
+mod threading_tests;
@@ -0,0 +1,118 @@
+use super::ThreadAffinity;
+use crate::commands::Command;
+use crate::{Engine, EngineError, EngineHealth, EngineOptions, Id, Params};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn other_threads_fail_with_both_thread_ids() {
+    let affinity = ThreadAffinity::current(true);
+    assert!(affinity.check().is_ok());
+
+    let (result, other) = thread::spawn(move || (affinity.check(), thread::current().id()))
+        .join()
+        .unwrap();
+    match result {
+        Err(EngineError::WrongThread { owner, current }) => {
+            assert_eq!(owner, thread::current().id());
+            assert_eq!(current, other);
+        }
+        result => panic!("expected WrongThread, got {result:?}"),
+    }
+}
+
+#[test]
+fn strict_engine_only_guards_the_event_loop() {
+    let engine = Engine::with_options(EngineOptions {
+        strict_threading: true,
+        ..Default::default()
+    });
+
+    let remote = engine.clone();
+    let (run, snapshot) = thread::spawn(move || {
+        let run = remote.run(Params::default());
+
+        let node = remote.create_node(Id::from_u64(1), Some("Label".to_owned()));
+        remote.set_parent(remote.root_id(), node);
+        (run, remote.layout_headless(200.0, 100.0))
+    })
+    .join()
+    .unwrap();
+
+    assert!(matches!(run, Err(EngineError::WrongThread { .. })));
+    assert!(snapshot.unwrap().find(Id::from_u64(1)).is_some());
+    assert!(engine.snapshot().unwrap().find(Id::from_u64(1)).is_some());
+}
+
+#[test]
+fn shutdown_waits_for_the_data_thread_to_exit() {
+    let engine = Engine::new();
+    engine.create_node(Id::from_u64(1), None);
+    engine.shutdown().unwrap();
+    // The thread had the other references.
+    assert!(engine.progress.state().closed);
+    assert_eq!(Arc::strong_count(&engine.health), 1);
+    assert!(matches!(
+        engine.try_create_node(Id::from_u64(2), None),
+        Err(EngineError::ShuttingDown)
+    ));
+    assert!(matches!(engine.shutdown(), Err(EngineError::ShuttingDown)));
+    assert!(matches!(
+        engine.run(Params::default()),
+        Err(EngineError::ShuttingDown)
+    ));
+}
+
+#[test]
+fn dropping_the_last_clone_shuts_the_data_thread_down() {
+    let engine = Engine::new();
+    let clone = engine.clone();
+    let health = Arc::clone(&engine.health);
+    drop(engine);
+    assert!(clone.try_create_node(Id::from_u64(1), None).is_ok());
+
+    drop(clone);
+    assert_eq!(Arc::strong_count(&health), 1);
+}
+
+#[test]
+fn shutdown_fails_while_the_engine_runs() {
+    let engine = Engine::new();
+    let running = engine.running.lock().unwrap();
+    assert!(matches!(engine.shutdown(), Err(EngineError::Reentrancy)));
+    assert!(!engine.data_thread.is_shut_down());
+
+    drop(running);
+    engine.shutdown().unwrap();
+}
+
+#[test]
+fn the_data_thread_sends_itself_changes_without_waiting_for_room() {
+    let slot: Arc<Mutex<Option<Engine>>> = Arc::default();
+    let handler_slot = Arc::clone(&slot);
+    let (handled, handled_receiver) = mpsc::channel();
+    let engine = Engine::with_options(EngineOptions {
+        command_capacity: 1,
+        on_engine_error: Some(Arc::new(move |_: &EngineHealth| {
+            let engine = handler_slot.lock().unwrap().clone().unwrap();
+            for id in 1..=3 {
+                let id = engine.create_node(Id::from_u64(id), None);
+                engine.set_parent(engine.root_id(), id);
+            }
+            let _ = handled.send(());
+        })),
+        ..Default::default()
+    });
+    *slot.lock().unwrap() = Some(engine.clone());
+
+    engine.send(Command::Panic).unwrap();
+    handled_receiver
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap();
+    let children = engine.children(engine.root_id());
+    assert_eq!(children, [1, 2, 3].map(Id::from_u64));
+
+    // The handler held a clone.
+    slot.lock().unwrap().take();
+}
@@ -0,0 +1,236 @@
+//! The built-in tooltip: the `title` of the element under the pointer, shown in a box near it
+//! once the pointer rested there for a while.
+//!
+//! Tooltips are painted over the tree like the caret, and aren't part of it: hit testing
+//! never finds them. Their look is [`DEFAULT_STYLE`] with the rules for the reserved
+//! [`TOOLTIP_CLASS`] applied.
+
+use crate::css_parser::parse_declaration;
+use crate::layout::{Rect, RenderNode};
+use crate::media::MediaEnvironment;
+use crate::snapshot::Edges;
+use crate::style::{InlineStyle, Style, StyleSheet};
+use crate::style_matching::{apply_matching_rules, Inherited};
+use crate::{Id, WindowId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long the pointer rests over an element before its tooltip shows.
+pub(crate) const TOOLTIP_DELAY: Duration = Duration::from_millis(700);
+
+/// Rules for elements of this class style tooltips instead; no element has to have it.
+pub const TOOLTIP_CLASS: &str = "sonate-tooltip";
+
+/// Declarations tooltips start from, before the rules for [`TOOLTIP_CLASS`].
+const DEFAULT_STYLE: [(&str, &str); 5] = [
+    ("background-color", "#ffffe1"),
+    ("border", "1px solid #767676"),
+    ("padding", "2px 6px"),
+    ("color", "black"),
+    ("font-size", "12px"),
+];
+
+/// How far below the pointer a tooltip's box starts, to clear the cursor's arrow.
+const POINTER_OFFSET: f64 = 20.0;
+
+/// The id the tooltip is painted with. Not a node's: window roots, the highest ids, end
+/// well below.
+const TOOLTIP_ID: Id = Id(u64::MAX);
+
+/// The style tooltips are painted with in a tree resolved against `media`.
+pub(crate) fn resolve_style(style_sheet: &StyleSheet, media: &MediaEnvironment) -> Style {
+    let mut style = Style::default();
+    for (property, value) in DEFAULT_STYLE {
+        if let Some(declaration) = parse_declaration(property, value) {
+            style.merge(&declaration);
+        }
+    }
+    let attributes = HashMap::from([("class".to_owned(), TOOLTIP_CLASS.to_owned())]);
+    let inherited = Inherited::default();
+    apply_matching_rules(
+        &mut style,
+        &InlineStyle::default(),
+        None,
+        &attributes,
+        false,
+        &inherited,
+        style_sheet,
+        media,
+    );
+    style
+}
+
+/// The title to show for the topmost element on `path`, a path from the tree's root as
+/// [`RenderNode::find_element_at_position`] gives: the nearest element on it with one, and
+/// its title.
+pub(crate) fn title_on_path(tree: &RenderNode, path: &[Id]) -> Option<(Id, String)> {
+    let nodes = tree.nodes_on_path(path);
+    let titled = nodes.iter().rev().find(|node| node.title.is_some())?;
+    Some((titled.id, titled.title.clone()?))
+}
+
+/// A tooltip as painted, see [`Tooltips::shown`].
+#[derive(Clone, PartialEq)]
+pub(crate) struct Tooltip {
+    pub text: String,
+    /// The border box, in the window's logical pixels.
+    pub bounds: Rect,
+    pub style: Arc<Style>,
+}
+
+impl Tooltip {
+    /// The tooltip for `text` taking `text_size` in `style`, near the pointer at `x`, `y`:
+    /// below and right of it, or above where there's no room below, and inside the window.
+    pub fn place(
+        text: String,
+        text_size: (f64, f64),
+        style: Arc<Style>,
+        (x, y): (f64, f64),
+        window: Rect,
+    ) -> Self {
+        let padding = style.padding.resolved();
+        let border = style.used_border_width();
+        let width = text_size.0
+            + padding.left.to_px()
+            + padding.right.to_px()
+            + border.left.to_px()
+            + border.right.to_px();
+        let height = text_size.1
+            + padding.top.to_px()
+            + padding.bottom.to_px()
+            + border.top.to_px()
+            + border.bottom.to_px();
+
+        let below = y + POINTER_OFFSET;
+        let top = if below + height <= window.y + window.height {
+            below
+        } else {
+            (y - height).max(window.y)
+        };
+        let left = x.min(window.x + window.width - width).max(window.x);
+        Self {
+            text,
+            bounds: Rect::new(left, top, width, height),
+            style,
+        }
+    }
+
+    /// A node painting the tooltip like any other.
+    pub fn node(&self) -> RenderNode {
+        RenderNode {
+            id: TOOLTIP_ID,
+            bounds: self.bounds,
+            margin: Edges::default(),
+            padding: Edges::resolved(&self.style.padding),
+            border_width: Edges::from(&self.style.used_border_width()),
+            descendant_bounds: self.bounds,
+            z_index: 0,
+            style: self.style.clone(),
+            text: Some(self.text.clone()),
+            title: None,
+            role: None,
+            aria_label: None,
+            editable: false,
+            image: None,
+            text_truncated: false,
+            children: Vec::new(),
+        }
+    }
+}
+
+/// Where the pointer rests, and whether the tooltip of the element there is due.
+#[derive(Default)]
+pub(crate) struct Tooltips {
+    hover: Option<Hover>,
+}
+
+struct Hover {
+    window: WindowId,
+    /// The element with the title.
+    node: Id,
+    title: String,
+    /// Where the pointer came to rest.
+    position: (f64, f64),
+    since: Instant,
+    /// Pressing hides the tooltip until the pointer leaves the element.
+    dismissed: bool,
+}
+
+impl Hover {
+    fn is_shown(&self, now: Instant) -> bool {
+        !self.dismissed && now >= self.since + TOOLTIP_DELAY
+    }
+}
+
+impl Tooltips {
+    /// The pointer moved to `position` in the window, over the element with a title that
+    /// [`title_on_path`] gave. Returns whether a shown tooltip went away.
+    ///
+    /// Moving over the same element waits for the pointer to rest again, but keeps a
+    /// tooltip that is shown where it is.
+    pub fn pointer_moved(
+        &mut self,
+        window: WindowId,
+        position: (f64, f64),
+        titled: Option<(Id, String)>,
+        now: Instant,
+    ) -> bool {
+        let Some((node, title)) = titled else {
+            return self.pointer_left(now);
+        };
+        if let Some(hover) = self.hover.as_mut() {
+            if hover.window == window && hover.node == node {
+                if !hover.is_shown(now) {
+                    hover.position = position;
+                    hover.since = now;
+                }
+                hover.title = title;
+                return false;
+            }
+        }
+        let hidden = self.pointer_left(now);
+        self.hover = Some(Hover {
+            window,
+            node,
+            title,
+            position,
+            since: now,
+            dismissed: false,
+        });
+        hidden
+    }
+
+    /// The pointer left the element it was over, or the window. Returns whether a shown
+    /// tooltip went away.
+    pub fn pointer_left(&mut self, now: Instant) -> bool {
+        self.hover.take().is_some_and(|hover| hover.is_shown(now))
+    }
+
+    /// The button went down. Returns whether a shown tooltip went away.
+    pub fn pressed(&mut self, now: Instant) -> bool {
+        let Some(hover) = self.hover.as_mut() else {
+            return false;
+        };
+        let shown = hover.is_shown(now);
+        hover.dismissed = true;
+        shown
+    }
+
+    /// The title to show in the window at `now`, and where the pointer rests.
+    pub fn shown(&self, window: WindowId, now: Instant) -> Option<(&str, (f64, f64))> {
+        let hover = self.hover.as_ref().filter(|hover| hover.window == window)?;
+        hover
+            .is_shown(now)
+            .then_some((hover.title.as_str(), hover.position))
+    }
+
+    /// When the tooltip due in the window shows, if it's not shown yet.
+    pub fn next_change(&self, window: WindowId, now: Instant) -> Option<Instant> {
+        let hover = self.hover.as_ref().filter(|hover| hover.window == window)?;
+        (!hover.dismissed && !hover.is_shown(now)).then_some(hover.since + TOOLTIP_DELAY)
+    }
+}
+
+#[cfg(test)]
+mod tooltip_tests;
@@ -0,0 +1,166 @@
+use super::{resolve_style, title_on_path, Tooltip, Tooltips, TOOLTIP_DELAY};
+use crate::css_parser::parse_css;
+use crate::layout::Rect;
+use crate::media::MediaEnvironment;
+use crate::style::{Length, Rgba};
+use crate::{Engine, Id, WindowId};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const WINDOW: WindowId = WindowId::PRIMARY;
+
+fn titled(id: u64, title: &str) -> Option<(Id, String)> {
+    Some((Id::from_u64(id), title.to_owned()))
+}
+
+fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+}
+
+#[test]
+fn tooltips_show_once_the_pointer_rests() {
+    let start = Instant::now();
+    let mut tooltips = Tooltips::default();
+
+    assert!(!tooltips.pointer_moved(WINDOW, (10.0, 10.0), titled(1, "Save"), start));
+    assert_eq!(tooltips.shown(WINDOW, start + ms(699)), None);
+    assert_eq!(
+        tooltips.next_change(WINDOW, start),
+        Some(start + TOOLTIP_DELAY)
+    );
+
+    // Moving over the element waits for the pointer to rest again.
+    let moved = start + ms(500);
+    assert!(!tooltips.pointer_moved(WINDOW, (12.0, 10.0), titled(1, "Save"), moved));
+    assert_eq!(tooltips.shown(WINDOW, start + ms(1000)), None);
+    let shown_at = moved + TOOLTIP_DELAY;
+    assert_eq!(tooltips.next_change(WINDOW, moved), Some(shown_at));
+    assert_eq!(
+        tooltips.shown(WINDOW, shown_at),
+        Some(("Save", (12.0, 10.0)))
+    );
+    assert_eq!(tooltips.next_change(WINDOW, shown_at), None);
+    // Other windows show nothing.
+    assert_eq!(tooltips.shown(WindowId::secondary(1), shown_at), None);
+
+    // Once shown, it stays where it is while the pointer moves over the element.
+    let later = shown_at + ms(100);
+    assert!(!tooltips.pointer_moved(WINDOW, (20.0, 15.0), titled(1, "Save"), later));
+    assert_eq!(tooltips.shown(WINDOW, later), Some(("Save", (12.0, 10.0))));
+}
+
+#[test]
+fn moving_away_hides_the_tooltip() {
+    let start = Instant::now();
+    let shown_at = start + TOOLTIP_DELAY;
+    let mut tooltips = Tooltips::default();
+    tooltips.pointer_moved(WINDOW, (10.0, 10.0), titled(1, "Save"), start);
+
+    // Onto another element with a title, whose tooltip waits for its own rest.
+    assert!(tooltips.pointer_moved(WINDOW, (50.0, 10.0), titled(2, "Open"), shown_at));
+    assert_eq!(tooltips.shown(WINDOW, shown_at + ms(1)), None);
+    let open_at = shown_at + TOOLTIP_DELAY;
+    assert_eq!(
+        tooltips.shown(WINDOW, open_at),
+        Some(("Open", (50.0, 10.0)))
+    );
+
+    // Onto nothing with a title.
+    assert!(tooltips.pointer_moved(WINDOW, (90.0, 90.0), None, open_at));
+    assert_eq!(tooltips.shown(WINDOW, open_at + ms(10_000)), None);
+    assert_eq!(tooltips.next_change(WINDOW, open_at), None);
+
+    // Out of the window, before anything showed.
+    tooltips.pointer_moved(WINDOW, (10.0, 10.0), titled(1, "Save"), open_at);
+    assert!(!tooltips.pointer_left(open_at + ms(100)));
+    assert_eq!(tooltips.shown(WINDOW, open_at + ms(10_000)), None);
+}
+
+#[test]
+fn pressing_dismisses_the_tooltip_until_the_pointer_leaves() {
+    let start = Instant::now();
+    let shown_at = start + TOOLTIP_DELAY;
+    let mut tooltips = Tooltips::default();
+    tooltips.pointer_moved(WINDOW, (10.0, 10.0), titled(1, "Save"), start);
+
+    assert!(tooltips.pressed(shown_at));
+    assert!(!tooltips.pointer_moved(WINDOW, (11.0, 10.0), titled(1, "Save"), shown_at));
+    assert_eq!(tooltips.shown(WINDOW, shown_at + ms(10_000)), None);
+    assert_eq!(tooltips.next_change(WINDOW, shown_at), None);
+
+    // Pressing before it showed keeps it from showing.
+    tooltips.pointer_moved(WINDOW, (50.0, 10.0), titled(2, "Open"), shown_at);
+    assert!(!tooltips.pressed(shown_at + ms(100)));
+    assert_eq!(tooltips.shown(WINDOW, shown_at + ms(10_000)), None);
+}
+
+#[test]
+fn tooltips_stay_inside_the_window() {
+    let style = Arc::new(resolve_style(
+        &parse_css("").unwrap(),
+        &MediaEnvironment::default(),
+    ));
+    let window = Rect::new(0.0, 0.0, 200.0, 100.0);
+    let place = |position| {
+        Tooltip::place(
+            "Save".to_owned(),
+            (30.0, 14.0),
+            style.clone(),
+            position,
+            window,
+        )
+        .bounds
+    };
+
+    // 6px of padding and a 1px border left and right, 2px and 1px above and below.
+    assert_eq!(place((10.0, 10.0)), Rect::new(10.0, 30.0, 44.0, 20.0));
+    // No room below or to the right.
+    assert_eq!(place((190.0, 90.0)), Rect::new(156.0, 70.0, 44.0, 20.0));
+}
+
+#[test]
+fn the_reserved_class_styles_tooltips() {
+    let default = resolve_style(&parse_css("").unwrap(), &MediaEnvironment::default());
+    assert_eq!(default.padding.resolved().left, Length::Px(6.0));
+
+    let stylesheet = parse_css(".sonate-tooltip { background-color: #000; color: #fff; }");
+    let style = resolve_style(&stylesheet.unwrap(), &MediaEnvironment::default());
+    let black = Rgba {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 255,
+    };
+    assert_eq!(style.background_color, Some(black));
+    // What the rules don't set keeps the default.
+    assert_eq!(style.padding, default.padding);
+}
+
+#[test]
+fn the_nearest_title_on_the_path_shows() {
+    let engine = Engine::new();
+    engine.add_stylesheet(
+        ".toolbar { display: flex; width: 200px; height: 40px; }
+         .button { width: 50px; height: 20px; }",
+    );
+    let root = engine.root_id();
+    let toolbar = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, toolbar);
+    engine.set_attribute(toolbar, "class".to_owned(), "toolbar".to_owned());
+    engine.set_attribute(toolbar, "title".to_owned(), "Tools".to_owned());
+    for (id, title) in [(2, Some("Save")), (3, None)] {
+        let button = engine.create_node(Id::from_u64(id), None);
+        engine.set_parent(toolbar, button);
+        engine.set_attribute(button, "class".to_owned(), "button".to_owned());
+        if let Some(title) = title {
+            engine.set_attribute(button, "title".to_owned(), title.to_owned());
+        }
+    }
+    engine.layout_headless(400.0, 100.0).unwrap();
+    let tree = engine.get_current_snapshot().unwrap();
+    let title_at = |x, y| title_on_path(&tree.tree, &tree.find_element_at_position(x, y));
+
+    assert_eq!(title_at(10.0, 10.0), titled(2, "Save"));
+    assert_eq!(title_at(60.0, 10.0), titled(1, "Tools"));
+    assert_eq!(title_at(300.0, 50.0), None);
+}
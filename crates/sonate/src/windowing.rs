@@ -1,16 +1,206 @@
-use crate::backend::{BackendType, RenderingBackend};
+use crate::backend::{BackendType, GpuMemoryStats, InputState, RenderingBackend, ResizeStress};
+use crate::callbacks::KeyPress;
+use crate::debug_overlay::DebugOverlay;
+use crate::editing::EditKey;
+use crate::layout::RenderNode;
+use crate::style::Cursor;
+use crate::Id;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use winit::event_loop::EventLoopProxy;
+use std::time::{Duration, Instant};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::monitor::MonitorHandle;
+use winit::window::{CursorIcon, Fullscreen, WindowAttributes};
 
 // Re-export types
 pub use crate::backend::Params;
 
+/// Root ids of secondary windows start here, above the ids of nodes created from markup.
+const WINDOW_ROOT_ID_BASE: u64 = u64::MAX - u32::MAX as u64;
+
+/// A window of an engine, see [`crate::Engine::create_window`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowId(u64);
+
+impl WindowId {
+    /// The window [`crate::Engine::run`] always opens, which shows the tree under
+    /// [`crate::Engine::root_id`].
+    pub const PRIMARY: WindowId = WindowId(0);
+
+    /// Number the `n`th secondary window, from 1.
+    pub(crate) fn secondary(n: u64) -> Self {
+        WindowId(n)
+    }
+
+    /// The root of the window's tree. Nodes attached under it are shown in the window.
+    pub fn root_id(self) -> Id {
+        match self {
+            WindowId::PRIMARY => Id::from_u64(0),
+            WindowId(n) => Id::from_u64(WINDOW_ROOT_ID_BASE + n),
+        }
+    }
+}
+
+/// How a window covers its monitor, see [`WindowOptions::fullscreen`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FullscreenMode {
+    /// A window without decorations the size of the monitor, which keeps its video mode.
+    #[default]
+    Borderless,
+    /// The monitor switches to its video mode with the most pixels, at the highest refresh
+    /// rate. Borderless where the platform can't switch, as on Wayland.
+    Exclusive,
+}
+
+/// How a window opens.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowOptions {
+    /// Defaults to a title naming the rendering backend.
+    pub title: Option<String>,
+    /// Initial size in logical pixels.
+    pub width: f64,
+    pub height: f64,
+    /// Open covering the monitor, see `monitor_index`; `width` and `height` are the size it
+    /// has after leaving fullscreen.
+    pub fullscreen: Option<FullscreenMode>,
+    /// The monitor to open on, in the order the platform lists them. The primary monitor
+    /// past the last one.
+    pub monitor_index: Option<usize>,
+    /// Where the window's outer top left corner goes, in physical pixels from the top left
+    /// of the monitor at `monitor_index`, or of the desktop without one. When `None`, the
+    /// platform places the window, at the monitor's top left if one is asked for.
+    pub position: Option<(i32, i32)>,
+}
+
+impl Default for WindowOptions {
+    fn default() -> Self {
+        Self {
+            title: None,
+            width: 800.0,
+            height: 800.0,
+            fullscreen: None,
+            monitor_index: None,
+            position: None,
+        }
+    }
+}
+
+impl WindowOptions {
+    pub(crate) fn title_or(&self, default: &str) -> String {
+        self.title.clone().unwrap_or_else(|| default.to_owned())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum WindowMessage {
     Redraw,
+    /// Exit the event loop, see [`crate::Engine::quit`].
+    Quit,
+    OpenWindow(WindowId, WindowOptions),
+    CloseWindow(WindowId),
+    /// Input for a window from [`crate::Engine::inject_input`].
+    Input(WindowId, PointerInput),
+    /// Make the window cover its monitor, or a window again for `None`, see
+    /// [`crate::Engine::set_fullscreen`].
+    SetFullscreen(WindowId, Option<FullscreenMode>),
+    /// Move the window's outer top left corner to the point on the desktop, in physical
+    /// pixels, see [`crate::Engine::set_window_position`].
+    SetPosition(WindowId, (i32, i32)),
 }
 
-pub struct WindowMessageSender(Arc<Mutex<Option<EventLoopProxy<WindowMessage>>>>);
+/// Pointer input given to the engine by the host instead of a window, in logical pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PointerInput {
+    /// The primary button went down at the point; this clicks there.
+    Press { x: f64, y: f64 },
+    /// The pointer moved to the point; this drags while the button is down.
+    Move { x: f64, y: f64 },
+    /// The primary button went up.
+    Release,
+}
+
+/// Update the input state for pointer input and call the callbacks it triggers.
+pub(crate) fn apply_pointer_input(
+    params: &mut Params,
+    input_state: &mut InputState,
+    window: WindowId,
+    input: PointerInput,
+) {
+    match input {
+        PointerInput::Press { x, y } => {
+            input_state.cursor_position = Some(winit::dpi::LogicalPosition::new(x, y));
+            input_state.pressed = true;
+            (params.on_click)(window, x, y);
+        }
+        PointerInput::Move { x, y } => {
+            input_state.cursor_position = Some(winit::dpi::LogicalPosition::new(x, y));
+            input_state.cursor = (params.on_pointer_move)(window, x, y);
+            if input_state.pressed {
+                (params.on_drag)(window, x, y);
+            }
+        }
+        PointerInput::Release => {
+            input_state.pressed = false;
+            if let Some(position) = input_state.cursor_position {
+                (params.on_release)(window, position.x, position.y);
+            }
+        }
+    }
+}
+
+/// What the debug overlay shows over a window's frame, while [`Params::debug_overlay`] is
+/// set.
+fn debug_overlay(params: &Params, input_state: &InputState) -> Option<DebugOverlay> {
+    params.debug_overlay.then(|| DebugOverlay {
+        pointer: input_state
+            .cursor_position
+            .map(|position| (position.x, position.y)),
+    })
+}
+
+/// The cursor for the topmost element on `path`, a path from the tree's root as
+/// [`RenderNode::find_element_at_position`] gives: the `cursor` of the nearest element on
+/// it that has one.
+pub(crate) fn cursor_on_path(tree: &RenderNode, path: &[Id]) -> Cursor {
+    tree.nodes_on_path(path)
+        .iter()
+        .rev()
+        .find_map(|node| node.style.cursor)
+        .unwrap_or_default()
+}
+
+/// The platform cursor showing `cursor`.
+pub(crate) fn cursor_icon(cursor: Cursor) -> CursorIcon {
+    match cursor {
+        Cursor::Auto | Cursor::Default => CursorIcon::Default,
+        Cursor::Pointer => CursorIcon::Pointer,
+        Cursor::Text => CursorIcon::Text,
+        Cursor::Move => CursorIcon::Move,
+        Cursor::NotAllowed => CursorIcon::NotAllowed,
+        Cursor::Grab => CursorIcon::Grab,
+        Cursor::Grabbing => CursorIcon::Grabbing,
+        Cursor::Crosshair => CursorIcon::Crosshair,
+        Cursor::Wait => CursorIcon::Wait,
+        Cursor::Progress => CursorIcon::Progress,
+        Cursor::Help => CursorIcon::Help,
+        Cursor::ColResize => CursorIcon::ColResize,
+        Cursor::RowResize => CursorIcon::RowResize,
+        Cursor::EwResize => CursorIcon::EwResize,
+        Cursor::NsResize => CursorIcon::NsResize,
+        Cursor::ZoomIn => CursorIcon::ZoomIn,
+        Cursor::ZoomOut => CursorIcon::ZoomOut,
+    }
+}
+
+/// Delivers a message to the running event loop.
+type WakeUp = Box<dyn Fn(WindowMessage) + Send>;
+
+/// Wakes the event loop from any thread, e.g. the data thread after publishing a new
+/// snapshot. Messages sent while no event loop runs are dropped; the loop draws the latest
+/// snapshot when it starts anyway.
+pub struct WindowMessageSender(Arc<Mutex<Option<WakeUp>>>);
 
 impl Clone for WindowMessageSender {
     fn clone(&self) -> Self {
@@ -24,108 +214,509 @@ impl WindowMessageSender {
     }
 
     pub(crate) fn set_proxy(&self, proxy: EventLoopProxy<WindowMessage>) {
-        *self.0.lock().unwrap() = Some(proxy);
+        self.set_receiver(move |message| {
+            let _ = proxy.send_event(message);
+        });
+    }
+
+    /// Deliver messages to `receive` instead of an event loop.
+    pub(crate) fn set_receiver(&self, receive: impl Fn(WindowMessage) + Send + 'static) {
+        *self.0.lock().unwrap() = Some(Box::new(receive));
+    }
+
+    /// Stop delivering messages, once the event loop has exited.
+    pub(crate) fn clear(&self) {
+        *self.0.lock().unwrap() = None;
     }
 
     pub fn send(&self, message: WindowMessage) {
-        if let Some(proxy) = self.0.lock().unwrap().as_ref() {
-            let _ = proxy.send_event(message);
+        if let Some(wake_up) = self.0.lock().unwrap().as_ref() {
+            wake_up(message);
         }
     }
 }
 
-/// Run the windowing system with the default backend for the current platform
-pub fn run(
-    params: &mut crate::backend::Params,
-    message_sender: WindowMessageSender,
-) -> anyhow::Result<()> {
-    run_with_backend(params, BackendType::default(), message_sender)
+/// Where the running window reports its [`GpuMemoryStats`]; `None` while no window is open.
+pub(crate) type GpuMemoryReport = Arc<Mutex<Option<GpuMemoryStats>>>;
+
+/// How the primary window's frames were paced over about a second, see
+/// [`crate::Params::on_frame_stats`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameStats {
+    /// Frames presented per second.
+    pub fps: f64,
+    /// Frames presented since the last stats.
+    pub frames: u32,
+    /// The longest time between two frames, which shows stutter an average hides.
+    pub max_frame_interval: Duration,
+    /// The average time spent drawing and presenting a frame.
+    pub mean_render_time: Duration,
 }
 
-/// Run the windowing system with a specific backend
-pub fn run_with_backend(
-    params: &mut crate::backend::Params,
-    backend_type: BackendType,
-    message_sender: WindowMessageSender,
-) -> anyhow::Result<()> {
-    println!(
-        "Starting windowing system with {} backend",
-        backend_type.name()
-    );
+/// Counts the frames of a window, to report [`FrameStats`] once per period.
+pub(crate) struct FrameCounter {
+    period: Duration,
+    /// When the current period started, at a frame.
+    started: Option<Instant>,
+    last_frame: Option<Instant>,
+    /// Frames since the period started, with their longest interval and rendering time.
+    frames: u32,
+    max_frame_interval: Duration,
+    render_time: Duration,
+}
+
+impl FrameCounter {
+    pub const PERIOD: Duration = Duration::from_secs(1);
 
-    match backend_type {
-        #[cfg(all(target_os = "windows"))]
-        BackendType::D3D12 => {
-            run_with_backend_impl::<crate::backend::d3d12::D3D12Backend>(params, message_sender)
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            started: None,
+            last_frame: None,
+            frames: 0,
+            max_frame_interval: Duration::ZERO,
+            render_time: Duration::ZERO,
         }
-        #[cfg(target_os = "macos")]
-        BackendType::Metal => {
-            run_with_backend_impl::<crate::backend::metal::MetalBackend>(params, message_sender)
+    }
+
+    /// Count a frame presented at `now` after `render_time` of drawing; returns the stats
+    /// once the period is over, and starts the next one at this frame.
+    pub fn record(&mut self, now: Instant, render_time: Duration) -> Option<FrameStats> {
+        let (Some(started), Some(last_frame)) = (self.started, self.last_frame) else {
+            self.started = Some(now);
+            self.last_frame = Some(now);
+            return None;
+        };
+        self.last_frame = Some(now);
+        self.frames += 1;
+        self.max_frame_interval = self.max_frame_interval.max(now - last_frame);
+        self.render_time += render_time;
+
+        let elapsed = now - started;
+        if elapsed < self.period {
+            return None;
         }
-        #[cfg(target_os = "linux")]
-        BackendType::OpenGL => {
-            run_with_backend_impl::<crate::backend::gl::OpenGlBackend>(params, message_sender)
+        let stats = FrameStats {
+            fps: self.frames as f64 / elapsed.as_secs_f64(),
+            frames: self.frames,
+            max_frame_interval: self.max_frame_interval,
+            mean_render_time: self.render_time / self.frames,
+        };
+        self.started = Some(now);
+        self.frames = 0;
+        self.max_frame_interval = Duration::ZERO;
+        self.render_time = Duration::ZERO;
+        Some(stats)
+    }
+}
+
+/// Set `SONATE_RESIZE_STRESS` to a number of resizes to run them against the window's
+/// backend once it opens, report the outcome and exit; for checking GPU backends by hand.
+fn resize_stress_count() -> Option<usize> {
+    std::env::var("SONATE_RESIZE_STRESS").ok()?.parse().ok()
+}
+
+/// Resize the backend back and forth `count` times, drawing a frame at each size, and check
+/// that it releases what it allocated for the previous size.
+fn stress_resizes(
+    backend: &mut dyn RenderingBackend,
+    params: &mut crate::backend::Params,
+    count: usize,
+) -> Result<GpuMemoryStats, String> {
+    let full = backend.window_inner_size();
+    let smaller = PhysicalSize::new((full.width * 2 / 3).max(1), (full.height * 2 / 3).max(1));
+    let mut stress = ResizeStress::default();
+    for n in 0..count {
+        let size = if n % 2 == 0 { smaller } else { full };
+        backend.handle_window_event(&winit::event::WindowEvent::Resized(size));
+        backend.render(&mut |canvas, age| (params.on_draw)(WindowId::PRIMARY, canvas, age, None));
+        stress.record(backend.gpu_memory_stats())?;
+    }
+    Ok(backend.gpu_memory_stats())
+}
+
+/// Physical pixels per logical pixel, falling back to 1.0 for values winit would reject.
+pub(crate) fn sanitize_scale_factor(scale_factor: f64) -> f64 {
+    if scale_factor.is_finite() && scale_factor > 0.0 {
+        scale_factor
+    } else {
+        1.0
+    }
+}
+
+/// Convert a window size from physical pixels to the logical pixels layout works in.
+pub(crate) fn physical_to_logical_size(size: PhysicalSize<u32>, scale_factor: f64) -> (f64, f64) {
+    let size = size.to_logical::<f64>(sanitize_scale_factor(scale_factor));
+    (size.width, size.height)
+}
+
+/// The physical size a window of physical `size` at scale factor `from` has at `to`, with
+/// the same logical size, in whole pixels as winit rounds them: what a framebuffer is resized
+/// to when the scale changes, fractional ones like 1.25 included.
+pub(crate) fn rescale_physical_size(
+    size: PhysicalSize<u32>,
+    from: f64,
+    to: f64,
+) -> PhysicalSize<u32> {
+    size.to_logical::<f64>(sanitize_scale_factor(from))
+        .to_physical(sanitize_scale_factor(to))
+}
+
+/// Convert a cursor position from physical pixels to the logical pixels hit testing works in.
+pub(crate) fn physical_to_logical_position(
+    position: PhysicalPosition<f64>,
+    scale_factor: f64,
+) -> (f64, f64) {
+    let position = position.to_logical::<f64>(sanitize_scale_factor(scale_factor));
+    (position.x, position.y)
+}
+
+/// Where a window goes on the desktop, see [`WindowOptions::position`], given the top left
+/// corner of the monitor it was asked to open on, if any.
+pub(crate) fn window_position(
+    position: Option<(i32, i32)>,
+    monitor: Option<PhysicalPosition<i32>>,
+) -> Option<PhysicalPosition<i32>> {
+    if position.is_none() && monitor.is_none() {
+        return None;
+    }
+    let (x, y) = position.unwrap_or_default();
+    let origin = monitor.unwrap_or_default();
+    Some(PhysicalPosition::new(origin.x + x, origin.y + y))
+}
+
+/// How winit makes a window cover `monitor`, or the one it's on for `None`, in `mode`.
+pub(crate) fn fullscreen(mode: FullscreenMode, monitor: Option<MonitorHandle>) -> Fullscreen {
+    if mode == FullscreenMode::Exclusive {
+        let video_mode = monitor.as_ref().and_then(|monitor| {
+            monitor.video_modes().max_by_key(|video_mode| {
+                let size = video_mode.size();
+                let pixels = u64::from(size.width) * u64::from(size.height);
+                (pixels, video_mode.refresh_rate_millihertz())
+            })
+        });
+        if let Some(video_mode) = video_mode {
+            return Fullscreen::Exclusive(video_mode);
         }
+        log::info!("No video mode to switch to for exclusive fullscreen, using borderless");
+    }
+    Fullscreen::Borderless(monitor)
+}
+
+/// `attributes` with the monitor, position and fullscreen mode `options` ask for, which
+/// every backend opens its window with.
+pub(crate) fn place_window(
+    attributes: WindowAttributes,
+    event_loop: &ActiveEventLoop,
+    options: &WindowOptions,
+) -> WindowAttributes {
+    let monitor = options.monitor_index.and_then(|index| {
+        event_loop
+            .available_monitors()
+            .nth(index)
+            .or_else(|| event_loop.primary_monitor())
+    });
+    let origin = monitor.as_ref().map(MonitorHandle::position);
+    let mut attributes = attributes;
+    if let Some(position) = window_position(options.position, origin) {
+        attributes = attributes.with_position(position);
     }
+    if let Some(mode) = options.fullscreen {
+        let monitor = monitor.or_else(|| event_loop.primary_monitor());
+        attributes = attributes.with_fullscreen(Some(fullscreen(mode, monitor)));
+    }
+    attributes
+}
+
+/// Whether the key makes the platform's copy shortcut with the modifiers held: Cmd+C on
+/// macOS, Ctrl+C elsewhere, ignoring Shift and Caps Lock.
+pub(crate) fn is_copy_shortcut(key: &Key, modifiers: ModifiersState) -> bool {
+    let command = if cfg!(target_os = "macos") {
+        modifiers.super_key() && !modifiers.control_key()
+    } else {
+        modifiers.control_key() && !modifiers.super_key()
+    };
+    command
+        && !modifiers.alt_key()
+        && matches!(key, Key::Character(c) if c.eq_ignore_ascii_case("c"))
+}
+
+/// The edit a key press makes in a focused editable node, if any; `text` is what the key
+/// types. Shortcuts with Ctrl or Cmd type nothing.
+pub(crate) fn edit_key(
+    key: &Key,
+    text: Option<&str>,
+    modifiers: ModifiersState,
+) -> Option<EditKey> {
+    let edit = match key {
+        Key::Named(NamedKey::Backspace) => EditKey::Backspace,
+        Key::Named(NamedKey::Delete) => EditKey::Delete,
+        Key::Named(NamedKey::ArrowLeft) => EditKey::Left,
+        Key::Named(NamedKey::ArrowRight) => EditKey::Right,
+        Key::Named(NamedKey::Home) => EditKey::Home,
+        Key::Named(NamedKey::End) => EditKey::End,
+        _ => {
+            if modifiers.control_key() || modifiers.super_key() {
+                return None;
+            }
+            let text: String = text?.chars().filter(|c| !c.is_control()).collect();
+            if text.is_empty() {
+                return None;
+            }
+            EditKey::Insert(text)
+        }
+    };
+    Some(edit)
 }
 
-/// Generic implementation that works with any backend
-fn run_with_backend_impl<'a, B: RenderingBackend>(
-    params: &'a mut crate::backend::Params,
+/// The key press handed to the host's key handler, if the key is one it can name.
+pub(crate) fn key_press(key: &Key, modifiers: ModifiersState) -> Option<KeyPress> {
+    let key = match key {
+        Key::Named(NamedKey::Space) => " ".to_owned(),
+        // Named as in the DOM.
+        Key::Named(named) => format!("{named:?}"),
+        Key::Character(text) => text.to_string(),
+        Key::Unidentified(_) | Key::Dead(_) => return None,
+    };
+    Some(KeyPress {
+        key,
+        shift: modifiers.shift_key(),
+        control: modifiers.control_key(),
+        alt: modifiers.alt_key(),
+        meta: modifiers.super_key(),
+    })
+}
+
+/// Which backend the primary window renders with, see [`crate::Engine::backend`].
+pub(crate) type BackendReport = Arc<Mutex<Option<BackendType>>>;
+
+/// Run the windowing system. The primary window opens with `backend` if given, else with
+/// the one `SONATE_BACKEND` names, else with the platform's default; each backend that
+/// fails to start is logged and the next one of [`BackendType::candidates`] tried. The
+/// other windows open with the backend that worked.
+pub fn run(
+    params: &mut crate::backend::Params,
+    backend: Option<BackendType>,
     message_sender: WindowMessageSender,
+    gpu_memory: GpuMemoryReport,
+    chosen_backend: BackendReport,
 ) -> anyhow::Result<()> {
     use winit::{
         application::ApplicationHandler,
-        event::{ElementState, MouseButton, WindowEvent},
-        event_loop::{ActiveEventLoop, EventLoop},
-        keyboard::{Key, NamedKey},
-        window::WindowId,
+        event::{ElementState, MouseButton, StartCause, WindowEvent},
+        event_loop::{ActiveEventLoop, ControlFlow, EventLoop},
     };
 
+    let candidates = BackendType::candidates(backend.or_else(BackendType::from_env));
+    let started = Instant::now();
     let mut event_loop_builder = EventLoop::<WindowMessage>::with_user_event();
     let event_loop: EventLoop<WindowMessage> = event_loop_builder.build()?;
     // Publish a proxy so non-UI threads (layout/commands) can request redraws.
     message_sender.set_proxy(event_loop.create_proxy());
 
-    struct Application<'a, B: RenderingBackend> {
-        backend: Option<B>,
-        params: &'a mut crate::backend::Params,
+    struct Window {
+        id: WindowId,
+        backend: Box<dyn RenderingBackend>,
         scale_factor: f64,
+        /// The cursor last shown over the window.
+        cursor: CursorIcon,
+    }
+
+    struct Application<'a> {
+        windows: HashMap<winit::window::WindowId, Window>,
+        params: &'a mut crate::backend::Params,
+        started: Instant,
+        gpu_memory: GpuMemoryReport,
+        /// The backends to try for the primary window, in order.
+        candidates: Vec<BackendType>,
+        /// The backend the primary window opened with, which the others open with too.
+        backend: Option<BackendType>,
+        chosen_backend: BackendReport,
+        /// Why no backend could open the primary window.
+        error: Option<anyhow::Error>,
+        /// When a window asked to be drawn again without an event, see `Params::next_redraw`.
+        wake_at: Option<Instant>,
+        /// The primary window's, for `Params::on_frame_stats`.
+        frame_counter: FrameCounter,
     }
 
-    impl<'a, B: RenderingBackend> ApplicationHandler<WindowMessage> for Application<'a, B> {
+    impl<'a> Application<'a> {
+        /// Open the primary window with the first candidate backend that starts.
+        fn open_primary(&mut self, event_loop: &ActiveEventLoop) -> anyhow::Result<()> {
+            let options = self.params.primary_window.clone();
+            let mut failures = Vec::new();
+            for &backend_type in &self.candidates {
+                let vsync = self.params.vsync;
+                match crate::backend::create_backend(backend_type, event_loop, &options, vsync) {
+                    Ok(backend) => {
+                        log::info!("Rendering with the {} backend", backend_type.name());
+                        self.backend = Some(backend_type);
+                        *self.chosen_backend.lock().unwrap() = Some(backend_type);
+                        self.insert(WindowId::PRIMARY, backend);
+                        return Ok(());
+                    }
+                    Err(err) => {
+                        log::warn!(
+                            "The {} backend failed to start: {err:#}",
+                            backend_type.name()
+                        );
+                        failures.push(format!("{}: {err:#}", backend_type.name()));
+                    }
+                }
+            }
+            anyhow::bail!("no rendering backend could start ({})", failures.join("; "))
+        }
+
+        fn open(&mut self, event_loop: &ActiveEventLoop, id: WindowId, options: &WindowOptions) {
+            if self.windows.values().any(|window| window.id == id) {
+                return;
+            }
+            let Some(backend_type) = self.backend else {
+                return;
+            };
+
+            let vsync = self.params.vsync;
+            match crate::backend::create_backend(backend_type, event_loop, options, vsync) {
+                Ok(backend) => self.insert(id, backend),
+                Err(err) => {
+                    log::error!("Could not open window {id:?}: {err:#}");
+                    // The engine counts it as open until told otherwise.
+                    (self.params.on_window_closed)(id);
+                }
+            }
+        }
+
+        fn insert(&mut self, id: WindowId, backend: Box<dyn RenderingBackend>) {
+            let scale_factor = sanitize_scale_factor(backend.scale_factor());
+            let (width, height) =
+                physical_to_logical_size(backend.window_inner_size(), scale_factor);
+            (self.params.on_resize)(id, width, height);
+            backend.request_redraw();
+
+            self.windows.insert(
+                backend.window_id(),
+                Window {
+                    id,
+                    backend,
+                    scale_factor,
+                    cursor: CursorIcon::Default,
+                },
+            );
+        }
+
+        /// Drop the window's backend; the last window closing ends the event loop.
+        fn close(&mut self, event_loop: &ActiveEventLoop, id: WindowId) {
+            let before = self.windows.len();
+            self.windows.retain(|_, window| window.id != id);
+            if self.windows.len() == before {
+                return;
+            }
+
+            if id == WindowId::PRIMARY {
+                *self.gpu_memory.lock().unwrap() = None;
+            }
+            (self.params.on_window_closed)(id);
+            if self.windows.is_empty() {
+                event_loop.exit();
+            }
+        }
+    }
+
+    impl<'a> ApplicationHandler<WindowMessage> for Application<'a> {
         fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-            assert!(self.backend.is_none());
+            assert!(self.windows.is_empty());
 
-            self.backend = Some(B::new(event_loop).expect("Failed to create rendering backend"));
+            if let Err(err) = self.open_primary(event_loop) {
+                self.error = Some(err);
+                event_loop.exit();
+                return;
+            }
 
-            if let Some(ref backend) = self.backend {
-                self.scale_factor = backend.scale_factor();
+            if let Some(count) = resize_stress_count() {
+                let window = self.windows.values_mut().next().unwrap();
+                match stress_resizes(window.backend.as_mut(), self.params, count) {
+                    Ok(stats) => log::info!("{count} resizes without leaks, ending with {stats:?}"),
+                    Err(err) => log::error!("resize stress failed at {err}"),
+                }
+                event_loop.exit();
+                return;
+            }
 
-                let physical_size = backend.window_inner_size();
-                let logical_size = physical_size.to_logical::<f64>(self.scale_factor);
-                (self.params.on_resize)(logical_size.width, logical_size.height);
-                backend.request_redraw();
+            for (id, options) in (self.params.windows)() {
+                self.open(event_loop, id, &options);
             }
         }
 
-        fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: WindowMessage) {
+        fn user_event(&mut self, event_loop: &ActiveEventLoop, event: WindowMessage) {
             match event {
                 WindowMessage::Redraw => {
-                    if let Some(ref backend) = self.backend {
-                        backend.request_redraw();
+                    for window in self.windows.values() {
+                        window.backend.request_redraw();
                     }
                 }
+                WindowMessage::Quit => event_loop.exit(),
+                WindowMessage::OpenWindow(id, options) => self.open(event_loop, id, &options),
+                WindowMessage::CloseWindow(id) => self.close(event_loop, id),
+                WindowMessage::Input(id, input) => {
+                    let Some(window) = self.windows.values_mut().find(|window| window.id == id)
+                    else {
+                        return;
+                    };
+                    let input_state = window.backend.input_state_mut();
+                    apply_pointer_input(self.params, input_state, id, input);
+                }
+                WindowMessage::SetFullscreen(id, mode) => {
+                    let Some(window) = self.windows.values().find(|window| window.id == id) else {
+                        return;
+                    };
+                    // The backend rebuilds its surfaces on the resize that follows.
+                    let window = window.backend.window();
+                    let monitor = window.current_monitor();
+                    window.set_fullscreen(mode.map(|mode| fullscreen(mode, monitor)));
+                }
+                WindowMessage::SetPosition(id, (x, y)) => {
+                    let Some(window) = self.windows.values().find(|window| window.id == id) else {
+                        return;
+                    };
+                    let position = PhysicalPosition::new(x, y);
+                    window.backend.window().set_outer_position(position);
+                }
             }
         }
 
+        fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+            if let StartCause::ResumeTimeReached { .. } = cause {
+                self.wake_at = None;
+                for window in self.windows.values() {
+                    window.backend.request_redraw();
+                }
+            }
+        }
+
+        fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+            event_loop.set_control_flow(match self.wake_at {
+                Some(at) => ControlFlow::WaitUntil(at),
+                None => ControlFlow::Wait,
+            });
+        }
+
+        fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+            // Release the windows and their GPU resources while the event loop still exists.
+            self.windows.clear();
+            *self.gpu_memory.lock().unwrap() = None;
+        }
+
         fn window_event(
             &mut self,
             event_loop: &ActiveEventLoop,
-            _window_id: WindowId,
+            window_id: winit::window::WindowId,
             event: WindowEvent,
         ) {
-            let backend = self.backend.as_mut().unwrap();
+            let Some(window) = self.windows.get_mut(&window_id) else {
+                return;
+            };
+            let id = window.id;
+            let backend = &mut window.backend;
 
             // First, let the backend handle any backend-specific events
             let backend_handled = backend.handle_window_event(&event);
@@ -133,14 +724,27 @@ fn run_with_backend_impl<'a, B: RenderingBackend>(
             // Keep the layout thread's viewport size in sync with the actual window.
             match &event {
                 WindowEvent::Resized(new_size) => {
-                    let logical_size = new_size.to_logical::<f64>(self.scale_factor);
-                    (self.params.on_resize)(logical_size.width, logical_size.height);
+                    let (width, height) = physical_to_logical_size(*new_size, window.scale_factor);
+                    (self.params.on_resize)(id, width, height);
+                    if backend.renders_on_resize() && new_size.width > 0 && new_size.height > 0 {
+                        // Until layout catches up with the new size, which redraws, this shows
+                        // the last layout at the new size rather than a stretched frame.
+                        let debug_overlay = debug_overlay(self.params, backend.input_state());
+                        let on_draw = &mut self.params.on_draw;
+                        backend.render(&mut |canvas, age| on_draw(id, canvas, age, debug_overlay));
+                    }
+                    if id == WindowId::PRIMARY {
+                        *self.gpu_memory.lock().unwrap() = Some(backend.gpu_memory_stats());
+                    }
                 }
                 WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
-                    self.scale_factor = *scale_factor;
-                    let physical_size = backend.window_inner_size();
-                    let logical_size = physical_size.to_logical::<f64>(self.scale_factor);
-                    (self.params.on_resize)(logical_size.width, logical_size.height);
+                    // E.g. the window moved to a monitor with a different DPI. The logical
+                    // size may stay the same, so redraw at the new scale regardless of layout.
+                    window.scale_factor = sanitize_scale_factor(*scale_factor);
+                    let (width, height) =
+                        physical_to_logical_size(backend.window_inner_size(), window.scale_factor);
+                    (self.params.on_resize)(id, width, height);
+                    backend.request_redraw();
                 }
                 _ => {
                     if backend_handled {
@@ -151,7 +755,29 @@ fn run_with_backend_impl<'a, B: RenderingBackend>(
 
             // Handle common events
             match event {
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    backend.input_state_mut().modifiers = modifiers.state();
+                }
                 WindowEvent::KeyboardInput { event, .. } => {
+                    let modifiers = backend.input_state().modifiers;
+                    if event.state == ElementState::Pressed {
+                        if is_copy_shortcut(&event.logical_key, modifiers) {
+                            (self.params.on_copy)(id);
+                            return;
+                        }
+                        let text = event.text.as_deref();
+                        if let Some(edit) = edit_key(&event.logical_key, text, modifiers) {
+                            if (self.params.on_key)(id, edit) {
+                                backend.request_redraw();
+                                return;
+                            }
+                        }
+                        if let Some(press) = key_press(&event.logical_key, modifiers) {
+                            if (self.params.on_key_press)(id, press) {
+                                return;
+                            }
+                        }
+                    }
                     let input_state = backend.input_state_mut();
                     match event.logical_key {
                         Key::Named(NamedKey::ArrowLeft) => input_state.x -= 10.0,
@@ -159,6 +785,9 @@ fn run_with_backend_impl<'a, B: RenderingBackend>(
                         Key::Named(NamedKey::ArrowUp) => input_state.y += 10.0,
                         Key::Named(NamedKey::ArrowDown) => input_state.y -= 10.0,
                         Key::Named(NamedKey::Escape) => event_loop.exit(),
+                        Key::Named(NamedKey::F12) => {
+                            self.params.debug_overlay = !self.params.debug_overlay;
+                        }
                         _ => return,
                     }
                     backend.request_redraw();
@@ -168,31 +797,116 @@ fn run_with_backend_impl<'a, B: RenderingBackend>(
                     button: MouseButton::Left,
                     ..
                 } => {
-                    let input_state = backend.input_state();
-                    if let Some(cursor_position) = &input_state.cursor_position {
-                        (self.params.on_click)(cursor_position.x, cursor_position.y);
+                    let cursor_position = backend.input_state().cursor_position;
+                    let input_state = backend.input_state_mut();
+                    match cursor_position {
+                        Some(position) => {
+                            let (x, y) = (position.x, position.y);
+                            let input = PointerInput::Press { x, y };
+                            apply_pointer_input(self.params, input_state, id, input);
+                        }
+                        None => input_state.pressed = true,
                     }
                 }
+                WindowEvent::MouseInput {
+                    state: ElementState::Released,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    let input_state = backend.input_state_mut();
+                    apply_pointer_input(self.params, input_state, id, PointerInput::Release);
+                }
                 WindowEvent::CursorMoved { position, .. } => {
-                    let logical_position = position.to_logical::<f64>(self.scale_factor);
-                    backend.input_state_mut().cursor_position = Some(logical_position);
+                    let (x, y) = physical_to_logical_position(position, window.scale_factor);
+                    let input_state = backend.input_state_mut();
+                    let input = PointerInput::Move { x, y };
+                    apply_pointer_input(self.params, input_state, id, input);
+                    let cursor = cursor_icon(input_state.cursor);
+                    if cursor != window.cursor {
+                        window.cursor = cursor;
+                        backend.set_cursor(cursor);
+                    }
+                    if self.params.debug_overlay {
+                        // The overlay shows the style of the node under the pointer.
+                        backend.request_redraw();
+                    }
+                    // E.g. for a tooltip to show once the pointer rests.
+                    if let Some(at) = (self.params.next_redraw)(id) {
+                        self.wake_at = Some(self.wake_at.map_or(at, |wake_at| wake_at.min(at)));
+                    }
+                }
+                WindowEvent::CursorLeft { .. } => (self.params.on_pointer_leave)(id),
+                WindowEvent::RedrawRequested => {
+                    if let Some(on_frame) = self.params.on_frame.as_mut() {
+                        on_frame(self.started.elapsed());
+                    }
+                    let debug_overlay = debug_overlay(self.params, backend.input_state());
+                    let on_draw = &mut self.params.on_draw;
+                    let render_started = Instant::now();
+                    backend.render(&mut |canvas, age| on_draw(id, canvas, age, debug_overlay));
+                    if let (WindowId::PRIMARY, Some(on_frame_stats)) =
+                        (id, self.params.on_frame_stats.as_mut())
+                    {
+                        let now = Instant::now();
+                        let render_time = now - render_started;
+                        if let Some(stats) = self.frame_counter.record(now, render_time) {
+                            on_frame_stats(stats);
+                        }
+                    }
+                    if self.params.on_frame.is_some() {
+                        // Draw continuously only while someone wants every frame.
+                        backend.request_redraw();
+                    }
+                    let next_redraw = (self.params.next_redraw)(id).into_iter();
+                    if let Some(at) = next_redraw.chain(backend.next_redraw()).min() {
+                        self.wake_at = Some(self.wake_at.map_or(at, |wake_at| wake_at.min(at)));
+                    }
+                    if id == WindowId::PRIMARY {
+                        *self.gpu_memory.lock().unwrap() = Some(backend.gpu_memory_stats());
+                    }
+                    if let Some(on_ready) = self.params.on_ready.take() {
+                        on_ready();
+                    }
+                }
+                WindowEvent::CloseRequested => {
+                    let close = match self.params.on_close_requested.as_mut() {
+                        Some(on_close_requested) => on_close_requested(),
+                        None => true,
+                    };
+                    if close {
+                        self.close(event_loop, id);
+                    }
                 }
-                WindowEvent::RedrawRequested => backend.render(self.params),
-                WindowEvent::CloseRequested => event_loop.exit(),
                 _ => {}
             }
         }
     }
 
-    // unsafe: We avoid lifetime issues by transmuting the params reference.
-    // The params always outlife the Application struct
-    let mut application = Application::<'a, B> {
-        backend: None,
+    let mut application = Application {
+        windows: HashMap::new(),
         params,
-        scale_factor: 1.0,
+        started,
+        gpu_memory: Arc::clone(&gpu_memory),
+        candidates,
+        backend: None,
+        chosen_backend,
+        error: None,
+        wake_at: None,
+        frame_counter: FrameCounter::new(FrameCounter::PERIOD),
     };
 
-    event_loop.run_app(&mut application)?;
+    let result = event_loop.run_app(&mut application);
+    message_sender.clear();
+    let error = application.error.take();
+    drop(application);
+    *gpu_memory.lock().unwrap() = None;
+    result?;
+    if let Some(err) = error {
+        return Err(err);
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod windowing_tests;
@@ -0,0 +1,410 @@
+use super::{
+    cursor_icon, cursor_on_path, edit_key, is_copy_shortcut, key_press,
+    physical_to_logical_position, physical_to_logical_size, rescale_physical_size,
+    sanitize_scale_factor, window_position, FrameCounter, FullscreenMode, WindowMessage,
+};
+use crate::editing::EditKey;
+use crate::{BackendType, Cursor, Engine, Id, KeyPress, WindowId};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
+use winit::window::CursorIcon;
+
+#[test]
+fn sizes_are_converted_to_logical_pixels() {
+    assert_eq!(
+        physical_to_logical_size(PhysicalSize::new(1600, 1200), 2.0),
+        (800.0, 600.0)
+    );
+    assert_eq!(
+        physical_to_logical_size(PhysicalSize::new(1200, 900), 1.5),
+        (800.0, 600.0)
+    );
+    assert_eq!(
+        physical_to_logical_size(PhysicalSize::new(800, 600), 1.0),
+        (800.0, 600.0)
+    );
+}
+
+#[test]
+fn cursor_positions_are_converted_to_logical_pixels() {
+    // At 2x the element at logical (100, 50) is under physical (200, 100).
+    assert_eq!(
+        physical_to_logical_position(PhysicalPosition::new(200.0, 100.0), 2.0),
+        (100.0, 50.0)
+    );
+    assert_eq!(
+        physical_to_logical_position(PhysicalPosition::new(15.0, 3.0), 1.5),
+        (10.0, 2.0)
+    );
+}
+
+#[test]
+fn sizes_are_rescaled_to_whole_physical_pixels() {
+    let size = PhysicalSize::new(800, 600);
+    for (scale, expected) in [
+        (1.0, PhysicalSize::new(800, 600)),
+        (1.25, PhysicalSize::new(1000, 750)),
+        (1.5, PhysicalSize::new(1200, 900)),
+        (2.0, PhysicalSize::new(1600, 1200)),
+    ] {
+        assert_eq!(rescale_physical_size(size, 1.0, scale), expected, "{scale}");
+        assert_eq!(rescale_physical_size(expected, scale, 1.0), size, "{scale}");
+    }
+    // 801 * 1.25 = 1001.25 and 601 * 1.25 = 751.25 round to the nearest pixel.
+    assert_eq!(
+        rescale_physical_size(PhysicalSize::new(801, 601), 1.0, 1.25),
+        PhysicalSize::new(1001, 751)
+    );
+    assert_eq!(
+        rescale_physical_size(PhysicalSize::new(1001, 751), 1.25, 1.5),
+        PhysicalSize::new(1201, 901)
+    );
+}
+
+#[test]
+fn cursor_positions_match_what_is_drawn_at_fractional_scales() {
+    // Backends scale the canvas by the same factor input is converted with, so the point
+    // drawn at logical (120, 60) is hit there.
+    for scale in [1.0, 1.25, 1.5, 2.0] {
+        let physical = PhysicalPosition::new(120.0 * scale, 60.0 * scale);
+        let (x, y) = physical_to_logical_position(physical, scale);
+        assert!(
+            (x - 120.0).abs() < 1e-9 && (y - 60.0).abs() < 1e-9,
+            "{scale}"
+        );
+    }
+    assert_eq!(
+        physical_to_logical_size(PhysicalSize::new(1000, 750), 1.25),
+        (800.0, 600.0)
+    );
+}
+
+#[test]
+fn invalid_scale_factors_fall_back_to_one() {
+    assert_eq!(sanitize_scale_factor(2.0), 2.0);
+    for invalid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(sanitize_scale_factor(invalid), 1.0);
+    }
+    assert_eq!(
+        physical_to_logical_position(PhysicalPosition::new(30.0, 40.0), 0.0),
+        (30.0, 40.0)
+    );
+}
+
+#[test]
+fn copy_shortcut_uses_the_platform_modifier() {
+    let command = if cfg!(target_os = "macos") {
+        ModifiersState::SUPER
+    } else {
+        ModifiersState::CONTROL
+    };
+    let c = |s: &str| Key::Character(s.into());
+
+    assert!(is_copy_shortcut(&c("c"), command));
+    assert!(is_copy_shortcut(&c("C"), command | ModifiersState::SHIFT));
+    assert!(!is_copy_shortcut(&c("c"), ModifiersState::empty()));
+    assert!(!is_copy_shortcut(&c("c"), command | ModifiersState::ALT));
+    assert!(!is_copy_shortcut(&c("v"), command));
+}
+
+#[test]
+fn key_presses_map_to_edits() {
+    let none = ModifiersState::empty();
+    let a = Key::Character("a".into());
+    let named = |key| Key::Named(key);
+
+    assert_eq!(
+        edit_key(&a, Some("A"), ModifiersState::SHIFT),
+        Some(EditKey::Insert("A".to_owned()))
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::Space), Some(" "), none),
+        Some(EditKey::Insert(" ".to_owned()))
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::Backspace), Some("\u{8}"), none),
+        Some(EditKey::Backspace)
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::Delete), None, none),
+        Some(EditKey::Delete)
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::ArrowLeft), None, none),
+        Some(EditKey::Left)
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::ArrowRight), None, none),
+        Some(EditKey::Right)
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::Home), None, none),
+        Some(EditKey::Home)
+    );
+    assert_eq!(
+        edit_key(&named(NamedKey::End), None, none),
+        Some(EditKey::End)
+    );
+
+    // Shortcuts, keys that type nothing and control characters aren't edits.
+    assert_eq!(edit_key(&a, Some("a"), ModifiersState::CONTROL), None);
+    assert_eq!(edit_key(&a, Some("a"), ModifiersState::SUPER), None);
+    assert_eq!(edit_key(&named(NamedKey::Shift), None, none), None);
+    assert_eq!(edit_key(&named(NamedKey::Enter), Some("\r"), none), None);
+}
+
+#[test]
+fn cursors_map_to_the_platforms() {
+    assert_eq!(cursor_icon(Cursor::Auto), CursorIcon::Default);
+    assert_eq!(cursor_icon(Cursor::Default), CursorIcon::Default);
+    assert_eq!(cursor_icon(Cursor::Pointer), CursorIcon::Pointer);
+    assert_eq!(cursor_icon(Cursor::Text), CursorIcon::Text);
+    assert_eq!(cursor_icon(Cursor::NotAllowed), CursorIcon::NotAllowed);
+    assert_eq!(cursor_icon(Cursor::ColResize), CursorIcon::ColResize);
+}
+
+#[test]
+fn the_topmost_element_with_a_cursor_picks_it() {
+    let engine = Engine::new();
+    engine.add_stylesheet(
+        ".toolbar { display: flex; width: 200px; height: 40px; cursor: pointer; }
+         .button { width: 50px; height: 20px; }
+         .disabled { cursor: not-allowed; }
+         .overlay { width: 30px; height: 30px; z-index: 1; cursor: text; }",
+    );
+    let root = engine.root_id();
+    let toolbar = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, toolbar);
+    engine.set_attribute(toolbar, "class".to_owned(), "toolbar".to_owned());
+    for (id, class) in [(2, "button"), (3, "button disabled"), (4, "overlay")] {
+        let child = engine.create_node(Id::from_u64(id), None);
+        engine.set_parent(toolbar, child);
+        engine.set_attribute(child, "class".to_owned(), class.to_owned());
+    }
+    engine.layout_headless(400.0, 100.0).unwrap();
+    let tree = engine.get_current_snapshot().unwrap();
+    let cursor_at = |x, y| cursor_on_path(&tree.tree, &tree.find_element_at_position(x, y));
+
+    // The first button has none, so it shows the toolbar's.
+    assert_eq!(cursor_at(10.0, 10.0), Cursor::Pointer);
+    assert_eq!(cursor_at(60.0, 10.0), Cursor::NotAllowed);
+    assert_eq!(cursor_at(110.0, 10.0), Cursor::Text);
+    assert_eq!(cursor_at(180.0, 30.0), Cursor::Pointer);
+    assert_eq!(cursor_at(300.0, 50.0), Cursor::Auto);
+}
+
+#[test]
+fn changes_from_other_threads_wake_the_event_loop() {
+    let engine = Engine::new();
+    engine.add_stylesheet(".box { width: 50px; } .wide { width: 80px; }");
+    let item = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(engine.root_id(), item);
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+    engine.layout_headless(200.0, 100.0).unwrap();
+
+    // Stands in for the event loop proxy, which needs a display.
+    let (tx, rx) = mpsc::channel();
+    engine.message_sender.set_receiver(move |message| {
+        let _ = tx.send(message);
+    });
+
+    let remote = engine.clone();
+    std::thread::spawn(move || remote.set_attribute(item, "class".to_owned(), "wide".to_owned()))
+        .join()
+        .unwrap();
+
+    let message = rx.recv_timeout(Duration::from_secs(5)).expect("no wake-up");
+    assert!(matches!(message, WindowMessage::Redraw));
+    // The snapshot is published before the wake-up, so the redraw shows the change.
+    let snapshot = engine.snapshot().unwrap();
+    assert_eq!(snapshot.find(item).unwrap().bounds.width, 80.0);
+
+    engine.message_sender.clear();
+    engine.set_attribute(item, "class".to_owned(), "box".to_owned());
+    assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+}
+
+#[test]
+fn quit_reaches_the_event_loop_from_other_threads() {
+    let engine = Engine::new();
+    // Nothing to quit yet.
+    engine.quit();
+
+    let (tx, rx) = mpsc::channel();
+    engine.message_sender.set_receiver(move |message| {
+        let _ = tx.send(message);
+    });
+
+    let remote = engine.clone();
+    std::thread::spawn(move || remote.quit()).join().unwrap();
+
+    // Redraws from the data thread may come first.
+    let quit = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok())
+        .find(|message| matches!(message, WindowMessage::Quit));
+    assert!(quit.is_some(), "no quit");
+}
+
+#[test]
+fn fullscreen_and_position_changes_reach_the_event_loop() {
+    let engine = Engine::new();
+    let (tx, rx) = mpsc::channel();
+    engine.message_sender.set_receiver(move |message| {
+        let _ = tx.send(message);
+    });
+
+    let remote = engine.clone();
+    std::thread::spawn(move || {
+        remote.set_fullscreen(true);
+        remote.set_window_position(-40, 120);
+        remote.set_fullscreen(false);
+    })
+    .join()
+    .unwrap();
+
+    let messages: Vec<_> = std::iter::from_fn(|| rx.recv_timeout(Duration::from_secs(5)).ok())
+        .filter(|message| !matches!(message, WindowMessage::Redraw))
+        .take(3)
+        .collect();
+    assert!(matches!(
+        messages[..],
+        [
+            WindowMessage::SetFullscreen(WindowId::PRIMARY, Some(FullscreenMode::Borderless)),
+            WindowMessage::SetPosition(WindowId::PRIMARY, (-40, 120)),
+            WindowMessage::SetFullscreen(WindowId::PRIMARY, None),
+        ]
+    ));
+}
+
+#[test]
+fn windows_are_placed_relative_to_their_monitor() {
+    let monitor = Some(PhysicalPosition::new(1920, -200));
+    // The platform places windows nobody placed.
+    assert_eq!(window_position(None, None), None);
+    assert_eq!(
+        window_position(Some((10, 20)), None),
+        Some(PhysicalPosition::new(10, 20))
+    );
+    assert_eq!(
+        window_position(None, monitor),
+        Some(PhysicalPosition::new(1920, -200))
+    );
+    assert_eq!(
+        window_position(Some((10, 20)), monitor),
+        Some(PhysicalPosition::new(1930, -180))
+    );
+}
+
+#[test]
+fn key_presses_are_named_as_in_the_dom() {
+    let press = |key: Key, modifiers| key_press(&key, modifiers).map(|press| press.key);
+    let none = ModifiersState::empty();
+    assert_eq!(
+        press(Key::Named(NamedKey::Enter), none).as_deref(),
+        Some("Enter")
+    );
+    assert_eq!(
+        press(Key::Named(NamedKey::ArrowUp), none).as_deref(),
+        Some("ArrowUp")
+    );
+    assert_eq!(
+        press(Key::Named(NamedKey::Space), none).as_deref(),
+        Some(" ")
+    );
+    assert_eq!(
+        press(Key::Character("s".into()), none).as_deref(),
+        Some("s")
+    );
+    assert_eq!(press(Key::Dead(None), none), None);
+
+    assert_eq!(
+        key_press(
+            &Key::Character("S".into()),
+            ModifiersState::CONTROL | ModifiersState::SHIFT
+        ),
+        Some(KeyPress {
+            key: "S".to_owned(),
+            shift: true,
+            control: true,
+            alt: false,
+            meta: false,
+        })
+    );
+}
+
+#[test]
+fn backends_are_named_as_in_the_environment() {
+    let default = BackendType::default();
+    let name = match default.name() {
+        "OpenGL" => "gl",
+        "Direct3D 12" => "d3d12",
+        _ => "metal",
+    };
+    assert_eq!(BackendType::from_name(name), Some(default));
+    assert_eq!(
+        BackendType::from_name(&format!(" {} ", name.to_uppercase())),
+        Some(default)
+    );
+    assert_eq!(
+        BackendType::from_name("Software"),
+        Some(BackendType::Software)
+    );
+    assert_eq!(BackendType::from_name("glide"), None);
+    assert_eq!(BackendType::from_name(""), None);
+}
+
+#[test]
+fn the_requested_backend_is_tried_first_and_once() {
+    let default = BackendType::default();
+    assert_eq!(BackendType::candidates(None).first(), Some(&default));
+    assert_eq!(
+        BackendType::candidates(Some(default)).first(),
+        Some(&default)
+    );
+    let candidates = BackendType::candidates(Some(default));
+    assert_eq!(candidates.iter().filter(|&&b| b == default).count(), 1);
+    assert_eq!(candidates.last(), Some(&BackendType::Software));
+
+    assert_eq!(
+        BackendType::candidates(Some(BackendType::Software)),
+        [BackendType::Software, default]
+    );
+}
+
+#[test]
+fn frame_stats_are_reported_once_per_period() {
+    let mut counter = FrameCounter::new(Duration::from_secs(1));
+    let start = Instant::now();
+    let ms = Duration::from_millis;
+    let render_time = ms(4);
+
+    // Frames 16 ms apart, with one late by 34 ms, until a second has passed.
+    let mut at = start;
+    assert_eq!(counter.record(at, render_time), None);
+    let mut frames = 0;
+    let stats = loop {
+        frames += 1;
+        at += if frames == 30 { ms(50) } else { ms(16) };
+        if let Some(stats) = counter.record(at, render_time) {
+            break stats;
+        }
+    };
+    assert_eq!((frames, at - start), (61, ms(1010)));
+    assert_eq!(stats.frames, 61);
+    assert_eq!(stats.max_frame_interval, ms(50));
+    assert_eq!(stats.mean_render_time, render_time);
+    assert!((stats.fps - 61.0 / 1.01).abs() < 1e-9, "{}", stats.fps);
+
+    // The next period starts at the frame that ended the last one.
+    for n in 1..=63 {
+        at += ms(16);
+        if let Some(next) = counter.record(at, ms(2)) {
+            assert_eq!(n, 63);
+            assert_eq!(next.max_frame_interval, ms(16));
+            assert_eq!(next.mean_render_time, ms(2));
+            return;
+        }
+    }
+    panic!("no stats for the second period");
+}
@@ -1,45 +1,300 @@
 use ipc_channel::ipc::IpcSender;
 use serde::{Deserialize, Serialize};
+pub use sonate_error::ErrorCode;
+use std::fmt;
+
+/// Version of the C API, as returned by `sonate_abi_version`. The worker only drives a
+/// library of the version it was built against; bump on incompatible changes.
+pub const ABI_VERSION: u32 = 10;
+
+/// Version of what the host and the worker send each other: `Hello` stays the same, the
+/// rest may not. Bump `major` when anything sent changes how it's encoded, such as a new
+/// request, and `minor` for changes of meaning that older peers can live with.
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 8, minor: 0 };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    /// Whether a peer of this version can talk to one of `other`: both have the same
+    /// major version.
+    pub fn is_compatible_with(self, other: ProtocolVersion) -> bool {
+        self.major == other.major
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The first thing the worker sends the host, before `Bootstrap`. Never changes, so that
+/// hosts and workers of any version can read it and tell a mismatch apart from a failure.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hello {
+    pub protocol_version: ProtocolVersion,
+    /// The version of the crates the sender was built from, for messages.
+    pub crate_version: String,
+}
+
+impl Hello {
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            crate_version: env!("CARGO_PKG_VERSION").to_owned(),
+        }
+    }
+}
+
+/// What the worker sends the host over the bootstrap channel once it started and said
+/// `Hello`.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Bootstrap {
+    /// The engine library is loaded; requests go to this sender.
+    Ready(IpcSender<WorkerRequest>),
+    /// No engine library could be loaded. The worker exits after sending this.
+    Failed(LoadError),
+}
+
+/// Every place the worker looked for the engine library, and why each didn't work.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadError {
+    pub attempts: Vec<LoadAttempt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadAttempt {
+    pub path: String,
+    pub failure: LoadFailure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LoadFailure {
+    /// Nothing at the path.
+    Missing,
+    /// The dynamic loader refused the file, e.g. for the wrong architecture or C library;
+    /// with the loader's message.
+    Unloadable(String),
+    /// Not a sonate library, or one from before versions were checked.
+    NoAbiVersion,
+    AbiMismatch {
+        expected: u32,
+        found: u32,
+    },
+}
+
+impl fmt::Display for LoadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadFailure::Missing => write!(f, "not found"),
+            LoadFailure::Unloadable(reason) => write!(f, "could not be loaded: {reason}"),
+            LoadFailure::NoAbiVersion => write!(f, "has no sonate_abi_version symbol"),
+            LoadFailure::AbiMismatch { expected, found } => {
+                write!(f, "has ABI version {found}, expected {expected}")
+            }
+        }
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "worker failed to load engine library:")?;
+        if self.attempts.is_empty() {
+            return write!(f, " no candidates");
+        }
+        for attempt in &self.attempts {
+            write!(f, "\n  {} {}", attempt.path, attempt.failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// What the worker replies to a request that can fail: the code of the C API function it
+/// called, as in `ErrorCode::check`.
+pub type WorkerReply = Result<(), ErrorCode>;
 
 /// Cross-process requests sent from the host (sonate_lib) to the worker process (sonate_worker).
 ///
 /// This is intentionally small and can be extended as more FFI functions are proxied.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WorkerRequest {
+    /// Create the engine, whose events go to `events` from then on.
     InitInternal {
         handle: u64,
+        events: IpcSender<WorkerEvent>,
     },
     AddStylesheet {
         handle: u64,
         css: String,
     },
+    /// Register a font under `family`, or the name stored in it, replying whether the data
+    /// made one.
+    RegisterFont {
+        handle: u64,
+        family: Option<String>,
+        data: Vec<u8>,
+        reply_to: IpcSender<WorkerReply>,
+    },
     CreateNode {
         handle: u64,
         node_id: u64,
         text: Option<String>,
     },
+    CreateElement {
+        handle: u64,
+        node_id: u64,
+        tag: String,
+        text: Option<String>,
+    },
     SetParent {
         handle: u64,
         parent_id: u64,
         child_id: u64,
     },
+    InsertBefore {
+        handle: u64,
+        parent_id: u64,
+        child_id: u64,
+        reference_id: u64,
+        reply_to: IpcSender<WorkerReply>,
+    },
+    MoveChild {
+        handle: u64,
+        parent_id: u64,
+        child_id: u64,
+        new_index: u64,
+        reply_to: IpcSender<WorkerReply>,
+    },
     SetAttribute {
         handle: u64,
         node_id: u64,
         key: String,
         value: String,
     },
+    SetText {
+        handle: u64,
+        node_id: u64,
+        text: Option<String>,
+    },
+    /// Create nodes from markup under the parent, replying with the ids of the top-level
+    /// ones, or why the markup is malformed.
+    LoadMarkup {
+        handle: u64,
+        parent_id: u64,
+        markup: String,
+        reply_to: IpcSender<Result<Vec<u64>, ErrorCode>>,
+    },
     RootId {
         handle: u64,
         reply_to: IpcSender<u64>,
     },
+    /// Ids of the node's children, in order; none for unknown nodes.
+    Children {
+        handle: u64,
+        node_id: u64,
+        reply_to: IpcSender<Vec<u64>>,
+    },
+    /// The node's parent, if it has one.
+    Parent {
+        handle: u64,
+        node_id: u64,
+        reply_to: IpcSender<Option<u64>>,
+    },
+    ExtractText {
+        handle: u64,
+        root_id: u64,
+        by_line: bool,
+        reply_to: IpcSender<Vec<TextRun>>,
+    },
+    /// What the engine's latest layout passes and frames took.
+    Stats {
+        handle: u64,
+        reply_to: IpcSender<Stats>,
+    },
+    /// Forward every diagnostic of the engine to `sink` from now on.
+    SubscribeDiagnostics {
+        handle: u64,
+        sink: IpcSender<Diagnostic>,
+    },
+    /// Run the event loop. The worker first sends `quit_to` a sender that quits it, and
+    /// replies once it ended, after sending `WorkerEvent::Closed`.
     Run {
         handle: u64,
-        reply_to: IpcSender<i32>,
+        reply_to: IpcSender<WorkerReply>,
+        quit_to: IpcSender<IpcSender<()>>,
     },
     Destroy {
         handle: u64,
-        reply_to: IpcSender<i32>,
+        reply_to: IpcSender<WorkerReply>,
+    },
+    /// Answered right away, also while the worker runs another request such as `Run`.
+    Ping {
+        reply_to: IpcSender<()>,
     },
     Shutdown,
 }
+
+/// Input and window events of the worker's event loop, sent to the host over the channel
+/// it gave with `InitInternal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WorkerEvent {
+    /// A click no listener stopped, at `x`, `y` in the window, with the ids of the nodes
+    /// under the pointer, innermost first.
+    Click { x: f64, y: f64, ids: Vec<u64> },
+    /// A key press no listener stopped and no editable node took.
+    Key {
+        /// As the DOM's `KeyboardEvent.key` names it.
+        key: String,
+        shift: bool,
+        control: bool,
+        alt: bool,
+        meta: bool,
+    },
+    /// The window opened or changed size, to `width` by `height` logical pixels.
+    Resized { width: f64, height: f64 },
+    /// The event loop ended, or failed to start.
+    Closed,
+}
+
+/// A run of visible text, as returned by `sonate_extract_text`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextRun {
+    pub id: u64,
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// What the engine's latest layout passes and frames took, as `sonate_get_stats` writes
+/// it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub mean_layout_ms: f64,
+    pub max_layout_ms: f64,
+    pub mean_paint_ms: f64,
+    pub max_paint_ms: f64,
+    pub fps: f64,
+    pub layout_nodes: u64,
+}
+
+/// A problem reported by the engine, as passed to the `sonate_set_diagnostics_callback` callback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// 0 info, 1 warning, 2 error.
+    pub severity: i32,
+    pub code: String,
+    pub message: String,
+    pub node_id: Option<u64>,
+    pub rule_index: Option<u64>,
+}
+
+#[cfg(test)]
+mod protocol_tests;
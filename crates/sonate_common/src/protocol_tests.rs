@@ -0,0 +1,19 @@
+use super::*;
+
+#[test]
+fn only_the_major_version_has_to_match() {
+    let v = |major, minor| ProtocolVersion { major, minor };
+    assert!(v(1, 0).is_compatible_with(v(1, 0)));
+    assert!(v(1, 0).is_compatible_with(v(1, 3)));
+    assert!(v(1, 3).is_compatible_with(v(1, 0)));
+    assert!(!v(1, 0).is_compatible_with(v(2, 0)));
+    assert!(!v(2, 1).is_compatible_with(v(1, 1)));
+    assert_eq!(v(2, 1).to_string(), "2.1");
+}
+
+#[test]
+fn hello_carries_this_build() {
+    let hello = Hello::current();
+    assert_eq!(hello.protocol_version, PROTOCOL_VERSION);
+    assert_eq!(hello.crate_version, env!("CARGO_PKG_VERSION"));
+}
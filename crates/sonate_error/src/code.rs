@@ -0,0 +1,107 @@
+//! The one table of error codes: their stable names and the values the C API returns.
+
+/// Declares `ErrorCode` with its variants, their C API value and their name. Giving two
+/// codes the same value makes the match in `from_ffi` unreachable, which fails the build.
+macro_rules! error_codes {
+    ($($(#[$doc:meta])* $variant:ident = $ffi:literal, $name:literal;)*) => {
+        /// What failed, as the C API and the worker protocol tell it apart. Every
+        /// [`crate::EngineError`] has one, see [`crate::EngineError::code`]; the ones about
+        /// handles and arguments only come up at the C API.
+        #[non_exhaustive]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        pub enum ErrorCode {
+            $($(#[$doc])* $variant,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$variant,)*];
+
+            /// The value C API functions that return `int` return for it; all are negative.
+            pub const fn ffi(self) -> i32 {
+                match self {
+                    $(ErrorCode::$variant => $ffi,)*
+                }
+            }
+
+            /// A name that stays the same across versions, e.g. `"document-cycle"`.
+            pub const fn name(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $name,)*
+                }
+            }
+
+            pub const fn from_ffi(code: i32) -> Option<Self> {
+                match code {
+                    $($ffi => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// A null or non-UTF-8 string, or another argument the call can't take.
+    InvalidArgument = -1, "invalid-argument";
+    WrongThread = -2, "wrong-thread";
+    /// A handle `sonate_init` never returned.
+    UnknownHandle = -3, "unknown-handle";
+    /// The handle of a destroyed engine.
+    StaleHandle = -4, "stale-handle";
+    ShuttingDown = -5, "shutting-down";
+    Reentrancy = -6, "reentrancy";
+    Io = -7, "io";
+    CssSyntax = -8, "css-syntax";
+    CssUnknownProperty = -9, "css-unknown-property";
+    CssInvalidValue = -10, "css-invalid-value";
+    DocumentSameNode = -11, "document-same-node";
+    DocumentChildNotFound = -12, "document-child-not-found";
+    DocumentParentNotFound = -13, "document-parent-not-found";
+    DocumentCycle = -14, "document-cycle";
+    DocumentNotAChild = -15, "document-not-a-child";
+    WorkerSpawn = -16, "worker-spawn";
+    WorkerLoadLibrary = -17, "worker-load-library";
+    WorkerDisconnected = -18, "worker-disconnected";
+    WindowEventLoop = -19, "window-event-loop";
+    WindowNotPresentingExternally = -20, "window-not-presenting-externally";
+    InvalidFont = -21, "invalid-font";
+    RenderFailed = -22, "render-failed";
+    /// The worker process exited; the engine in it is gone.
+    WorkerDied = -23, "worker-died";
+    /// The worker process didn't reply in time; it may still, and may take later calls.
+    WorkerTimeout = -24, "worker-timeout";
+    /// The worker process speaks another version of the protocol than the host.
+    WorkerVersionMismatch = -25, "worker-version-mismatch";
+    /// A node already has the id, or it's one the engine generates.
+    DocumentIdInUse = -26, "document-id-in-use";
+    MarkupSyntax = -27, "markup-syntax";
+    /// The engine's queue of changes is full, and the call doesn't wait for room.
+    Busy = -28, "busy";
+}
+
+impl ErrorCode {
+    /// The result a C API function returned: `Ok` for 0, the code otherwise. A library of
+    /// the same ABI version only returns codes of this table; any other value is taken as
+    /// `InvalidArgument`.
+    pub const fn check(code: i32) -> Result<(), ErrorCode> {
+        match code {
+            0 => Ok(()),
+            code => match ErrorCode::from_ffi(code) {
+                Some(code) => Err(code),
+                None => Err(ErrorCode::InvalidArgument),
+            },
+        }
+    }
+
+    /// The value a C API function returns for the result: 0 for `Ok`.
+    pub const fn ffi_result(result: Result<(), ErrorCode>) -> i32 {
+        match result {
+            Ok(()) => 0,
+            Err(code) => code.ffi(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod code_tests;
@@ -0,0 +1,36 @@
+use super::*;
+use std::collections::HashSet;
+
+#[test]
+fn codes_and_their_values_map_one_to_one() {
+    let values: HashSet<_> = ErrorCode::ALL.iter().map(|code| code.ffi()).collect();
+    assert_eq!(values.len(), ErrorCode::ALL.len());
+    for &code in ErrorCode::ALL {
+        assert!(code.ffi() < 0, "{code:?}");
+        assert_eq!(ErrorCode::from_ffi(code.ffi()), Some(code));
+        assert_eq!(ErrorCode::check(code.ffi()), Err(code));
+        assert_eq!(ErrorCode::ffi_result(Err(code)), code.ffi());
+    }
+
+    // Every value in the range is a code, so none was skipped or left over.
+    let lowest = values.iter().copied().min().unwrap();
+    for value in lowest..0 {
+        assert!(ErrorCode::from_ffi(value).is_some(), "{value}");
+    }
+    assert_eq!(ErrorCode::from_ffi(0), None);
+    assert_eq!(ErrorCode::from_ffi(lowest - 1), None);
+    assert_eq!(ErrorCode::check(0), Ok(()));
+    assert_eq!(ErrorCode::ffi_result(Ok(())), 0);
+}
+
+#[test]
+fn names_are_distinct_kebab_case() {
+    let names: HashSet<_> = ErrorCode::ALL.iter().map(|code| code.name()).collect();
+    assert_eq!(names.len(), ErrorCode::ALL.len());
+    for name in names {
+        assert!(
+            name.chars().all(|c| c.is_ascii_lowercase() || c == '-'),
+            "{name}"
+        );
+    }
+}
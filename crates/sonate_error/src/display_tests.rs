@@ -0,0 +1,90 @@
+use super::*;
+
+/// Messages are part of the API: hosts log them and match on their prefix.
+#[test]
+fn messages_stay_the_same() {
+    let io = std::io::Error::new(std::io::ErrorKind::NotFound, "no such file");
+    let errors = [
+        EngineError::Css {
+            kind: CssErrorKind::UnknownProperty,
+            location: Some(SourceLocation { line: 3, column: 7 }),
+        },
+        EngineError::Css {
+            kind: CssErrorKind::Syntax,
+            location: None,
+        },
+        EngineError::Document {
+            kind: DocumentErrorKind::Cycle,
+            id: Some(42),
+        },
+        EngineError::Document {
+            kind: DocumentErrorKind::ParentNotFound,
+            id: None,
+        },
+        EngineError::worker(WorkerErrorKind::LoadLibrary, "not found"),
+        EngineError::Worker {
+            kind: WorkerErrorKind::Disconnected,
+            source: None,
+        },
+        EngineError::worker(WorkerErrorKind::Died, "killed by signal 9"),
+        EngineError::Worker {
+            kind: WorkerErrorKind::Timeout,
+            source: None,
+        },
+        EngineError::worker(WorkerErrorKind::VersionMismatch, "2.0, expected 1.x"),
+        EngineError::window(WindowErrorKind::EventLoop, "no display"),
+        EngineError::Io(io),
+        EngineError::Reentrancy,
+        EngineError::ShuttingDown,
+        EngineError::Busy,
+        EngineError::InvalidFont,
+        EngineError::RenderFailed,
+    ];
+    let messages: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+    assert_eq!(
+        messages,
+        [
+            "css-unknown-property: unknown CSS property at line 3, column 7",
+            "css-syntax: invalid CSS syntax",
+            "document-cycle: node 42: parent is a descendant of the child",
+            "document-parent-not-found: parent node not found",
+            "worker-load-library: the worker process could not load the engine library: not found",
+            "worker-disconnected: the worker process is unreachable",
+            "worker-died: the worker process exited: killed by signal 9",
+            "worker-timeout: the worker process did not reply in time",
+            "worker-version-mismatch: the worker process speaks another protocol version: \
+             2.0, expected 1.x",
+            "window-event-loop: the event loop failed: no display",
+            "io: no such file",
+            "reentrancy: the engine is already running",
+            "shutting-down: the engine takes no more changes",
+            "busy: the engine's queue of changes is full",
+            "invalid-font: no font could be made from the data",
+            "render-failed: the offscreen target failed",
+        ]
+    );
+}
+
+#[test]
+fn sources_are_chained() {
+    let err = EngineError::window(WindowErrorKind::EventLoop, "no display");
+    assert_eq!(err.source().unwrap().to_string(), "no display");
+    assert!(EngineError::Reentrancy.source().is_none());
+}
+
+#[test]
+fn every_error_has_the_code_of_its_kind() {
+    let owner = std::thread::current().id();
+    let current = std::thread::spawn(|| std::thread::current().id())
+        .join()
+        .unwrap();
+    let err = EngineError::WrongThread { owner, current };
+    assert_eq!(err.code(), ErrorCode::WrongThread);
+    assert!(err.to_string().starts_with("wrong-thread: "));
+
+    let not_a_child = EngineError::Document {
+        kind: DocumentErrorKind::NotAChild,
+        id: Some(1),
+    };
+    assert_eq!(not_a_child.code().ffi(), -15);
+}
@@ -0,0 +1,277 @@
+//! Errors of the sonate engine, shared by its Rust API, its C API and the worker protocol.
+//!
+//! Every [`EngineError`] has an [`ErrorCode`], which the C API returns and the worker
+//! protocol sends. The codes, their names and their C values are declared in one table, so
+//! the three can't drift apart.
+
+mod code;
+
+pub use code::ErrorCode;
+
+use std::error::Error;
+use std::fmt;
+use std::thread::ThreadId;
+
+/// Where in a source text something is. Lines and columns start at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Why a stylesheet, or part of one, couldn't be parsed.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CssErrorKind {
+    Syntax,
+    UnknownProperty,
+    InvalidValue,
+}
+
+/// Why a change to the document couldn't be made.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentErrorKind {
+    /// A node was to be put under itself.
+    SameNode,
+    ChildNotFound,
+    ParentNotFound,
+    /// The parent is a descendant of the child.
+    Cycle,
+    /// The node to insert before, or to move, isn't a child of the parent.
+    NotAChild,
+    /// A node was to be created with the id of another, or with one of the ids the engine
+    /// generates.
+    IdInUse,
+}
+
+/// Why the worker process couldn't take a call.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkerErrorKind {
+    /// It couldn't be started, or didn't report in.
+    Spawn,
+    /// It couldn't load the engine library.
+    LoadLibrary,
+    /// The channel to it broke, or a reply couldn't be read.
+    Disconnected,
+    /// It exited, by crashing or being killed, and took the engine with it.
+    Died,
+    /// It didn't reply in time, e.g. because it hangs.
+    Timeout,
+    /// It was built from another version, which speaks an incompatible protocol.
+    VersionMismatch,
+}
+
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowErrorKind {
+    /// The event loop couldn't be created, or failed while running.
+    EventLoop,
+    /// A frame was asked for on a thread where the engine doesn't run with
+    /// `PresentMode::External`.
+    NotPresentingExternally,
+}
+
+/// What went wrong in the engine. Its [`ErrorCode`] tells the cases apart where the fields
+/// can't travel, as through the C API.
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum EngineError {
+    Css {
+        kind: CssErrorKind,
+        location: Option<SourceLocation>,
+    },
+    Document {
+        kind: DocumentErrorKind,
+        /// The node the change was made to: the child for changes of parents.
+        id: Option<u64>,
+    },
+    /// Markup that isn't well-formed; the source tells where and why.
+    Markup {
+        location: SourceLocation,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    Worker {
+        kind: WorkerErrorKind,
+        source: Option<Box<dyn Error + Send + Sync>>,
+    },
+    Window {
+        kind: WindowErrorKind,
+        source: Option<Box<dyn Error + Send + Sync>>,
+    },
+    Io(std::io::Error),
+    /// The engine is already running an event loop or a headless layout.
+    Reentrancy,
+    /// Called on a thread other than the one that owns the object.
+    WrongThread {
+        owner: ThreadId,
+        current: ThreadId,
+    },
+    /// The thread that applies changes to the document is gone, because it panicked or the
+    /// engine was shut down; the engine can't take changes anymore.
+    ShuttingDown,
+    /// As many changes as the engine queues wait for the thread that applies them, and the
+    /// call was not to wait for room.
+    Busy,
+    /// Font data that no font could be made from.
+    InvalidFont,
+    /// An offscreen target couldn't be created or encoded, e.g. for an empty size or PDF
+    /// margins that leave no room for content.
+    RenderFailed,
+}
+
+impl EngineError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            EngineError::Css { kind, .. } => match kind {
+                CssErrorKind::Syntax => ErrorCode::CssSyntax,
+                CssErrorKind::UnknownProperty => ErrorCode::CssUnknownProperty,
+                CssErrorKind::InvalidValue => ErrorCode::CssInvalidValue,
+            },
+            EngineError::Document { kind, .. } => match kind {
+                DocumentErrorKind::SameNode => ErrorCode::DocumentSameNode,
+                DocumentErrorKind::ChildNotFound => ErrorCode::DocumentChildNotFound,
+                DocumentErrorKind::ParentNotFound => ErrorCode::DocumentParentNotFound,
+                DocumentErrorKind::Cycle => ErrorCode::DocumentCycle,
+                DocumentErrorKind::NotAChild => ErrorCode::DocumentNotAChild,
+                DocumentErrorKind::IdInUse => ErrorCode::DocumentIdInUse,
+            },
+            EngineError::Markup { .. } => ErrorCode::MarkupSyntax,
+            EngineError::Worker { kind, .. } => match kind {
+                WorkerErrorKind::Spawn => ErrorCode::WorkerSpawn,
+                WorkerErrorKind::LoadLibrary => ErrorCode::WorkerLoadLibrary,
+                WorkerErrorKind::Disconnected => ErrorCode::WorkerDisconnected,
+                WorkerErrorKind::Died => ErrorCode::WorkerDied,
+                WorkerErrorKind::Timeout => ErrorCode::WorkerTimeout,
+                WorkerErrorKind::VersionMismatch => ErrorCode::WorkerVersionMismatch,
+            },
+            EngineError::Window { kind, .. } => match kind {
+                WindowErrorKind::EventLoop => ErrorCode::WindowEventLoop,
+                WindowErrorKind::NotPresentingExternally => {
+                    ErrorCode::WindowNotPresentingExternally
+                }
+            },
+            EngineError::Io(_) => ErrorCode::Io,
+            EngineError::Reentrancy => ErrorCode::Reentrancy,
+            EngineError::WrongThread { .. } => ErrorCode::WrongThread,
+            EngineError::ShuttingDown => ErrorCode::ShuttingDown,
+            EngineError::Busy => ErrorCode::Busy,
+            EngineError::InvalidFont => ErrorCode::InvalidFont,
+            EngineError::RenderFailed => ErrorCode::RenderFailed,
+        }
+    }
+
+    pub fn worker(kind: WorkerErrorKind, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        EngineError::Worker {
+            kind,
+            source: Some(source.into()),
+        }
+    }
+
+    pub fn window(kind: WindowErrorKind, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        EngineError::Window {
+            kind,
+            source: Some(source.into()),
+        }
+    }
+}
+
+/// The code's name, what went wrong, and the message of the source if there is one.
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.code().name())?;
+        match self {
+            EngineError::Css { kind, location } => {
+                f.write_str(match kind {
+                    CssErrorKind::Syntax => "invalid CSS syntax",
+                    CssErrorKind::UnknownProperty => "unknown CSS property",
+                    CssErrorKind::InvalidValue => "invalid CSS value",
+                })?;
+                if let Some(SourceLocation { line, column }) = location {
+                    write!(f, " at line {line}, column {column}")?;
+                }
+                Ok(())
+            }
+            EngineError::Document { kind, id } => {
+                if let Some(id) = id {
+                    write!(f, "node {id}: ")?;
+                }
+                f.write_str(match kind {
+                    DocumentErrorKind::SameNode => "parent and child cannot be the same",
+                    DocumentErrorKind::ChildNotFound => "child node not found",
+                    DocumentErrorKind::ParentNotFound => "parent node not found",
+                    DocumentErrorKind::Cycle => "parent is a descendant of the child",
+                    DocumentErrorKind::NotAChild => "node is not a child of the parent",
+                    DocumentErrorKind::IdInUse => "the id is taken",
+                })
+            }
+            EngineError::Markup { source, .. } => write!(f, "malformed markup at {source}"),
+            EngineError::Worker { kind, source } => {
+                f.write_str(match kind {
+                    WorkerErrorKind::Spawn => "the worker process could not be started",
+                    WorkerErrorKind::LoadLibrary => {
+                        "the worker process could not load the engine library"
+                    }
+                    WorkerErrorKind::Disconnected => "the worker process is unreachable",
+                    WorkerErrorKind::Died => "the worker process exited",
+                    WorkerErrorKind::Timeout => "the worker process did not reply in time",
+                    WorkerErrorKind::VersionMismatch => {
+                        "the worker process speaks another protocol version"
+                    }
+                })?;
+                write_source(f, source)
+            }
+            EngineError::Window { kind, source } => {
+                f.write_str(match kind {
+                    WindowErrorKind::EventLoop => "the event loop failed",
+                    WindowErrorKind::NotPresentingExternally => {
+                        "the engine does not present to a host here"
+                    }
+                })?;
+                write_source(f, source)
+            }
+            EngineError::Io(err) => write!(f, "{err}"),
+            EngineError::Reentrancy => f.write_str("the engine is already running"),
+            EngineError::WrongThread { owner, current } => {
+                write!(f, "called on {current:?}, owned by {owner:?}")
+            }
+            EngineError::ShuttingDown => f.write_str("the engine takes no more changes"),
+            EngineError::Busy => f.write_str("the engine's queue of changes is full"),
+            EngineError::InvalidFont => f.write_str("no font could be made from the data"),
+            EngineError::RenderFailed => f.write_str("the offscreen target failed"),
+        }
+    }
+}
+
+fn write_source(
+    f: &mut fmt::Formatter<'_>,
+    source: &Option<Box<dyn Error + Send + Sync>>,
+) -> fmt::Result {
+    match source {
+        Some(source) => write!(f, ": {source}"),
+        None => Ok(()),
+    }
+}
+
+impl Error for EngineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EngineError::Markup { source, .. } => Some(source.as_ref()),
+            EngineError::Worker { source, .. } | EngineError::Window { source, .. } => source
+                .as_deref()
+                .map(|source| source as &(dyn Error + 'static)),
+            EngineError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EngineError {
+    fn from(err: std::io::Error) -> Self {
+        EngineError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod display_tests;
@@ -26,6 +26,155 @@ typedef size_t sonate_engine_handle_t;
 /* ID type for nodes and other engine-owned objects. */
 typedef uint64_t sonate_id_t;
 
+/* A run of visible text (matches Rust: SonateTextRun). Bounds are in logical pixels. */
+typedef struct sonate_text_run_t {
+    sonate_id_t id;
+    char* text; /* null-terminated UTF-8, owned by the array */
+    double x;
+    double y;
+    double width;
+    double height;
+} sonate_text_run_t;
+
+/*
+ * What the latest layout passes and frames of the primary window took (matches Rust:
+ * SonateStats), in milliseconds. Zero for what didn't happen yet.
+ */
+typedef struct sonate_stats_t {
+    double mean_layout_ms;
+    double max_layout_ms;
+    double mean_paint_ms; /* painting a frame, without presenting it */
+    double max_paint_ms;
+    double fps;
+    uint64_t layout_nodes; /* nodes the last layout pass sized and positioned */
+} sonate_stats_t;
+
+/*
+ * What functions that return int return on failure, 0 meaning success. Each value stands
+ * for one kind of failure across versions (matches Rust: sonate_error::ErrorCode), and log
+ * lines about it start with its name, e.g. "document-cycle".
+ */
+
+/* A null or non-UTF-8 string, or another argument the call can't take. */
+#define SONATE_ERR_INVALID_ARGUMENT (-1)
+
+/* Called on another thread than the one that created the engine. Checked in debug builds,
+ * and in release builds for engines created with sonate_init_strict. */
+#define SONATE_ERR_WRONG_THREAD (-2)
+
+/* A handle sonate_init never returned. */
+#define SONATE_ERR_UNKNOWN_HANDLE (-3)
+
+/* The handle of a destroyed engine, also when a later engine got the same slot: handles
+ * carry a generation and are never reused. */
+#define SONATE_ERR_STALE_HANDLE (-4)
+
+/* The engine takes no more changes, e.g. because its data thread panicked. */
+#define SONATE_ERR_SHUTTING_DOWN (-5)
+
+/* The engine is already running an event loop or a headless layout, or runs in a worker
+ * process and was called from one of its event callbacks. */
+#define SONATE_ERR_REENTRANCY (-6)
+
+#define SONATE_ERR_IO (-7)
+#define SONATE_ERR_CSS_SYNTAX (-8)
+#define SONATE_ERR_CSS_UNKNOWN_PROPERTY (-9)
+#define SONATE_ERR_CSS_INVALID_VALUE (-10)
+
+/* A change to the tree was refused: sonate_insert_before and sonate_move_child. */
+#define SONATE_ERR_DOCUMENT_SAME_NODE (-11)
+#define SONATE_ERR_DOCUMENT_CHILD_NOT_FOUND (-12)
+#define SONATE_ERR_DOCUMENT_PARENT_NOT_FOUND (-13)
+#define SONATE_ERR_DOCUMENT_CYCLE (-14)
+#define SONATE_ERR_DOCUMENT_NOT_A_CHILD (-15)
+
+/* Engines running in a worker process. */
+#define SONATE_ERR_WORKER_SPAWN (-16)
+#define SONATE_ERR_WORKER_LOAD_LIBRARY (-17)
+#define SONATE_ERR_WORKER_DISCONNECTED (-18)
+
+#define SONATE_ERR_WINDOW_EVENT_LOOP (-19)
+#define SONATE_ERR_WINDOW_NOT_PRESENTING_EXTERNALLY (-20)
+#define SONATE_ERR_INVALID_FONT (-21)
+#define SONATE_ERR_RENDER_FAILED (-22)
+
+/* The worker process exited, e.g. because it crashed or was killed. Every later call on the
+ * handle returns it too, unless the worker was restarted: see sonate_set_worker_restart. */
+#define SONATE_ERR_WORKER_DIED (-23)
+
+/* The worker process didn't reply within the timeout set with sonate_set_ipc_timeout_ms.
+ * It may still run and take later calls. */
+#define SONATE_ERR_WORKER_TIMEOUT (-24)
+
+/* The worker process was built from a version that speaks another protocol; sonate_init
+ * logs this and returns 0. */
+#define SONATE_ERR_WORKER_VERSION_MISMATCH (-25)
+/* A node already has the id. sonate_create_node can't wait to find out, and reports an
+ * "id-in-use" diagnostic instead. */
+#define SONATE_ERR_DOCUMENT_ID_IN_USE (-26)
+/* Markup that isn't well-formed; where and why is logged. */
+#define SONATE_ERR_MARKUP_SYNTAX (-27)
+/* The engine's queue of changes is full, and the call doesn't wait for room. */
+#define SONATE_ERR_BUSY (-28)
+
+/* Diagnostic severities (matches sonate_diagnostic_t.severity). */
+#define SONATE_SEVERITY_INFO 0
+#define SONATE_SEVERITY_WARNING 1
+#define SONATE_SEVERITY_ERROR 2
+
+/* A problem reported by the engine (matches Rust: SonateDiagnostic). Strings are only valid during the callback. */
+typedef struct sonate_diagnostic_t {
+    int severity;
+    const char* code; /* stable identifier, e.g. "css-unknown-property" */
+    const char* message;
+    sonate_id_t node_id; /* valid if has_node */
+    bool has_node;
+    int64_t rule_index; /* stylesheet rule index, or -1 */
+} sonate_diagnostic_t;
+
+typedef void (*sonate_diagnostic_callback_t)(const sonate_diagnostic_t* diagnostic, void* user_data);
+
+/* Told that the worker process of an engine exited, and whether it was restarted. */
+typedef void (*sonate_worker_exit_callback_t)(sonate_engine_handle_t handle, bool restarted, void* user_data);
+
+/* Told of a click at x, y in the window, with the ids of the id_count nodes under the pointer,
+ * innermost first. ids is only valid during the call. */
+typedef void (*sonate_click_callback_t)(void* user_data, double x, double y, const sonate_id_t* ids, size_t id_count);
+
+/* Log levels (matches Rust: SONATE_LOG_*). */
+#define SONATE_LOG_OFF 0
+#define SONATE_LOG_ERROR 1
+#define SONATE_LOG_WARN 2
+#define SONATE_LOG_INFO 3
+#define SONATE_LOG_DEBUG 4
+#define SONATE_LOG_TRACE 5
+
+/* Receives a log line without a trailing newline, only valid during the call. */
+typedef void (*sonate_log_callback_t)(int level, const char* message);
+
+/*
+ * Version of this API, checked by the worker process before it drives the library.
+ */
+SONATE_API uint32_t sonate_abi_version(void);
+
+/*
+ * Send the library's log lines to callback instead of standard error, where they are
+ * filtered by RUST_LOG. Lines name the engine handle and node id they are about.
+ *
+ * callback:
+ *   called on whichever thread logged the line; NULL to log to standard error again.
+ *   Engines running in a worker process log to that process's standard error.
+ */
+SONATE_API void sonate_set_log_callback(sonate_log_callback_t callback);
+
+/*
+ * Set the most detailed level passed to the log callback; SONATE_LOG_WARN until set.
+ *
+ * Returns:
+ *   0 on success, SONATE_ERR_INVALID_ARGUMENT if level isn't one of the SONATE_LOG_* levels
+ */
+SONATE_API int sonate_set_log_level(int level);
+
 /*
  * Initialize the sonate engine.
  *
@@ -38,18 +187,44 @@ typedef uint64_t sonate_id_t;
  */
 SONATE_API sonate_engine_handle_t sonate_init(bool use_same_process);
 
+/*
+ * Like sonate_init, but calls on other threads than this one fail in release builds too,
+ * with SONATE_ERR_WRONG_THREAD where an int is returned.
+ */
+SONATE_API sonate_engine_handle_t sonate_init_strict(bool use_same_process);
+
 /*
  * Add a CSS stylesheet to the engine.
  *
  * css_content: null-terminated UTF-8 string (must not be NULL)
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_add_stylesheet(sonate_engine_handle_t handle, const char* css_content);
+
+/*
+ * Make a TrueType/OpenType font available to the engine, before installed fonts of the same
+ * family. Registering a family again replaces its font. Text is re-measured on the next
+ * layout, also while the event loop runs.
+ *
+ * family: null-terminated UTF-8 name to register the font under, or NULL for the name
+ *         stored in the font
+ * data, len: the font file's bytes, copied before the call returns
+ *
+ * Returns:
+ *   0 on success, SONATE_ERR_INVALID_FONT if no font could be made from the data, or another
+ *   SONATE_ERR_* code
  */
-SONATE_API void sonate_add_stylesheet(sonate_engine_handle_t handle, const char* css_content);
+SONATE_API int sonate_register_font(sonate_engine_handle_t handle, const char* family, const uint8_t* data, size_t len);
 
 /*
  * Create a new document node.
  *
  * node_id:
- *   caller-provided node id (must be non-zero; 0 is reserved for root)
+ *   caller-provided node id (must be non-zero, 0 is the root's, and below 2^63, where the
+ *   ids the engine generates start); a node created with the id of another is ignored and
+ *   reported as a diagnostic
  *
  * text_content:
  *   optional null-terminated UTF-8 string (may be NULL)
@@ -59,17 +234,66 @@ SONATE_API void sonate_add_stylesheet(sonate_engine_handle_t handle, const char*
  */
 SONATE_API sonate_id_t sonate_create_node(sonate_engine_handle_t handle, sonate_id_t node_id, const char* text_content);
 
+/*
+ * Create a new element: a node with a tag, which type selectors such as `button { ... }`
+ * match, in any case. Nodes from sonate_create_node have no tag.
+ *
+ * tag:
+ *   null-terminated UTF-8 element name (must not be NULL)
+ *
+ * node_id and text_content as for sonate_create_node.
+ *
+ * Returns:
+ *   node_id on success, 0 on error
+ */
+SONATE_API sonate_id_t sonate_create_element(sonate_engine_handle_t handle, sonate_id_t node_id, const char* tag, const char* text_content);
+
 /*
  * Set parent-child relationship between nodes.
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_parent(sonate_engine_handle_t handle, sonate_id_t parent_id, sonate_id_t child_id);
+
+/*
+ * Put a node under a parent right before reference_id, which must be a child of the parent.
+ * The node is moved there if it already is a child of the parent.
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
  */
-SONATE_API void sonate_set_parent(sonate_engine_handle_t handle, sonate_id_t parent_id, sonate_id_t child_id);
+SONATE_API int sonate_insert_before(sonate_engine_handle_t handle, sonate_id_t parent_id, sonate_id_t child_id, sonate_id_t reference_id);
 
 /*
- * Set an attribute on a node.
+ * Move a child of a parent to new_index among the parent's children; an index past the end
+ * moves it after the last child.
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_move_child(sonate_engine_handle_t handle, sonate_id_t parent_id, sonate_id_t child_id, size_t new_index);
+
+/*
+ * Set an attribute on a node. The "style" attribute sets its inline style.
  *
  * key/value: null-terminated UTF-8 strings (must not be NULL)
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_attribute(sonate_engine_handle_t handle, sonate_id_t node_id, const char* key, const char* value);
+
+/*
+ * Replace the text of a node.
+ *
+ * text_content:
+ *   optional null-terminated UTF-8 string; NULL makes the node a plain container
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
  */
-SONATE_API void sonate_set_attribute(sonate_engine_handle_t handle, sonate_id_t node_id, const char* key, const char* value);
+SONATE_API int sonate_set_text(sonate_engine_handle_t handle, sonate_id_t node_id, const char* text_content);
 
 /*
  * Get the root node ID of the document.
@@ -79,19 +303,192 @@ SONATE_API void sonate_set_attribute(sonate_engine_handle_t handle, sonate_id_t
  */
 SONATE_API sonate_id_t sonate_root_id(sonate_engine_handle_t handle);
 
+/*
+ * Create nodes from XML-like markup such as <div class="row"><span>Hello</span></div>
+ * under parent_id, the root id (0) for the document. Elements may close themselves, like
+ * <img/>; the five XML entities are decoded, and a "style" attribute sets the inline style.
+ *
+ * markup: null-terminated UTF-8 string (must not be NULL)
+ *
+ * out_ids/capacity:
+ *   receive the ids of the first capacity top-level nodes; out_ids may be NULL if capacity
+ *   is 0
+ *
+ * out_count:
+ *   receives the number of top-level nodes (must not be NULL), which may be more than
+ *   capacity: sonate_children gives the rest
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code: SONATE_ERR_MARKUP_SYNTAX for malformed markup,
+ *   of which nothing is created
+ */
+SONATE_API int sonate_load_markup(sonate_engine_handle_t handle, sonate_id_t parent_id, const char* markup, sonate_id_t* out_ids, size_t capacity, size_t* out_count);
+
+/*
+ * Get the children of a node, in order. Unknown nodes have none.
+ *
+ * out_ids/capacity:
+ *   receive the ids of the first capacity children; out_ids may be NULL if capacity is 0
+ *
+ * out_count:
+ *   receives the number of children (must not be NULL), which may be more than capacity:
+ *   call again with a larger buffer to get them all
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_children(sonate_engine_handle_t handle, sonate_id_t node_id, sonate_id_t* out_ids, size_t capacity, size_t* out_count);
+
+/*
+ * Get the parent of a node.
+ *
+ * out_parent/out_has_parent:
+ *   receive the parent's id and whether there is one (must not be NULL); roots, detached
+ *   and unknown nodes have none
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_parent(sonate_engine_handle_t handle, sonate_id_t node_id, sonate_id_t* out_parent, bool* out_has_parent);
+
+/*
+ * Extract the visible text of the document or a subtree, in reading order.
+ *
+ * root_id:
+ *   subtree to extract; the root id (0) for the whole document
+ *
+ * by_line:
+ *   true => one run per wrapped line, false => one run per text node
+ *
+ * out_runs/out_count:
+ *   receive an array of runs (must not be NULL). Release it with sonate_free_text_runs.
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_extract_text(sonate_engine_handle_t handle, sonate_id_t root_id, bool by_line, sonate_text_run_t** out_runs, size_t* out_count);
+
+/*
+ * Release runs returned by sonate_extract_text. NULL is ignored.
+ */
+SONATE_API void sonate_free_text_runs(sonate_text_run_t* runs, size_t count);
+
+/*
+ * Get how long the engine's latest layout passes took, and painting the latest frames of
+ * its primary window.
+ *
+ * out_stats:
+ *   receives the stats (must not be NULL)
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code
+ */
+SONATE_API int sonate_get_stats(sonate_engine_handle_t handle, sonate_stats_t* out_stats);
+
+/*
+ * Set the function called with each diagnostic the engine reports: stylesheet errors,
+ * mutations of unknown nodes, invalid parents and the like. Repeats are rate-limited.
+ *
+ * callback:
+ *   called on a background thread with user_data; NULL to stop receiving diagnostics
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_diagnostics_callback(sonate_engine_handle_t handle, sonate_diagnostic_callback_t callback, void* user_data);
+
+/*
+ * Set the function called when the worker process of the engine exits without being
+ * destroyed. Does nothing for engines running in this process.
+ *
+ * callback:
+ *   called on a background thread with user_data; NULL to not be told
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_worker_exit_callback(sonate_engine_handle_t handle, sonate_worker_exit_callback_t callback, void* user_data);
+
+/*
+ * Set the function called with each click in the engine's window that no event listener
+ * stopped. Takes effect also while sonate_run runs, and works the same for engines running
+ * in a worker process, whose clicks are sent back to this one.
+ *
+ * callback:
+ *   called on the thread running sonate_run, during that call, with user_data; NULL to not
+ *   be told. It may change and read the document, except of an engine in a worker process,
+ *   whose calls fail with SONATE_ERR_REENTRANCY until sonate_run returns
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_click_callback(sonate_engine_handle_t handle, sonate_click_callback_t callback, void* user_data);
+
+/*
+ * Restart the worker process once if it exits, and bring the new one to where the old one
+ * was: its stylesheets, nodes, attributes and text, and its diagnostics subscription. The
+ * call that finds the worker gone still returns SONATE_ERR_WORKER_DIED; later calls reach
+ * the new worker. Off until enabled, and off again after a restart. Does nothing for
+ * engines running in this process.
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_worker_restart(sonate_engine_handle_t handle, bool enabled);
+
+/*
+ * Set how long calls wait for each reply of the worker process before they fail with
+ * SONATE_ERR_WORKER_TIMEOUT; 5 seconds until set. While sonate_run runs, it's how long the
+ * worker has to answer the pings sent every second. Does nothing for engines running in
+ * this process.
+ *
+ * timeout_ms:
+ *   the timeout in milliseconds; 0 to wait as long as the worker runs
+ *
+ * Returns:
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
+ */
+SONATE_API int sonate_set_ipc_timeout_ms(sonate_engine_handle_t handle, uint32_t timeout_ms);
+
+/*
+ * Check that the worker process of the engine still takes requests, e.g. before an
+ * expensive one. The worker answers also while it runs the event loop. For engines running
+ * in this process, checks that the thread applying changes didn't stop after a panic; a
+ * panicking change is dropped without stopping it.
+ *
+ * Returns:
+ *   0 if the worker answered, SONATE_ERR_WORKER_TIMEOUT if it didn't in time,
+ *   SONATE_ERR_WORKER_DIED if it exited, SONATE_ERR_SHUTTING_DOWN if the thread applying
+ *   changes stopped, or another SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the
+ *   wrong thread
+ */
+SONATE_API int sonate_ping(sonate_engine_handle_t handle);
+
 /*
  * Run the engine event loop (blocking).
  *
  * Returns:
- *   0 on success, -1 on error
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread;
+ *   SONATE_ERR_WORKER_DIED right after the worker process exits, if it does while running
  */
 SONATE_API int sonate_run(sonate_engine_handle_t handle);
 
 /*
- * Cleanup and destroy an engine instance.
+ * Close the window of a running engine, making sonate_run return 0. Unlike the other
+ * functions, this may be called from any thread.
+ *
+ * Returns:
+ *   0 on success, also when no window is open, SONATE_ERR_UNKNOWN_HANDLE or
+ *   SONATE_ERR_STALE_HANDLE for a handle of no engine
+ */
+SONATE_API int sonate_quit(sonate_engine_handle_t handle);
+
+/*
+ * Cleanup and destroy an engine instance. Waits for the engine's data thread to exit, and
+ * for its worker process, which is killed unless it exits within two seconds.
  *
  * Returns:
- *   0 on success, -1 on error
+ *   0 on success, or a SONATE_ERR_* code such as SONATE_ERR_WRONG_THREAD on the wrong thread
  */
 SONATE_API int sonate_destroy(sonate_engine_handle_t handle);
 
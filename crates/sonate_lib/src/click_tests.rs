@@ -0,0 +1,150 @@
+use super::*;
+use sonate_common::WorkerEvent;
+use std::ptr;
+
+/// Copies what it's told into the `Vec` that `user_data` points to.
+extern "C" fn record_click(
+    user_data: *mut c_void,
+    x: f64,
+    y: f64,
+    ids: *const SonateId,
+    id_count: usize,
+) {
+    let clicks = unsafe { &mut *(user_data as *mut Vec<(f64, f64, Vec<SonateId>)>) };
+    let ids = unsafe { std::slice::from_raw_parts(ids, id_count) };
+    clicks.push((x, y, ids.to_vec()));
+}
+
+/// Copies the kind and key of what it's told into the `Vec` that `user_data` points to.
+extern "C" fn record_event(user_data: *mut c_void, event: *const SonateEvent) {
+    let events = unsafe { &mut *(user_data as *mut Vec<(c_int, Option<String>)>) };
+    let event = unsafe { &*event };
+    let key = (!event.key.is_null()).then(|| {
+        unsafe { CStr::from_ptr(event.key) }
+            .to_str()
+            .unwrap()
+            .to_owned()
+    });
+    events.push((event.kind, key));
+}
+
+/// Adds a node under the first of the ids to the engine whose handle `user_data` points to.
+extern "C" fn add_node_to_clicked(
+    user_data: *mut c_void,
+    _x: f64,
+    _y: f64,
+    ids: *const SonateId,
+    id_count: usize,
+) {
+    let handle = unsafe { *(user_data as *const EngineHandle) };
+    let ids = unsafe { std::slice::from_raw_parts(ids, id_count) };
+    assert_eq!(sonate_create_node(handle, 5, ptr::null()), 5);
+    assert_eq!(sonate_set_parent(handle, ids[0], 5), 0);
+}
+
+fn click(x: f64, y: f64, ids: &[SonateId]) -> WorkerEvent {
+    WorkerEvent::Click {
+        x,
+        y,
+        ids: ids.to_vec(),
+    }
+}
+
+#[test]
+fn clicks_reach_the_callback_with_the_ids_under_the_pointer() {
+    let handle = sonate_init(true);
+    assert_ne!(handle, 0);
+    let mut clicks: Vec<(f64, f64, Vec<SonateId>)> = Vec::new();
+    let user_data = &mut clicks as *mut _ as *mut c_void;
+    assert_eq!(
+        sonate_set_click_callback(handle, Some(record_click), user_data),
+        0
+    );
+
+    // As the event loop does; the ids are dropped right after.
+    deliver_event(handle, &click(12.0, 34.0, &[3, 1, 0]));
+    deliver_event(handle, &click(5.0, 6.0, &[]));
+    deliver_event(
+        handle,
+        &WorkerEvent::Resized {
+            width: 1.0,
+            height: 2.0,
+        },
+    );
+
+    assert_eq!(sonate_set_click_callback(handle, None, ptr::null_mut()), 0);
+    deliver_event(handle, &click(7.0, 8.0, &[0]));
+    assert_eq!(
+        clicks,
+        [(12.0, 34.0, vec![3, 1, 0]), (5.0, 6.0, Vec::new())]
+    );
+
+    assert_eq!(sonate_destroy(handle), 0);
+    assert_eq!(
+        sonate_set_click_callback(handle, Some(record_click), user_data),
+        SONATE_ERR_STALE_HANDLE
+    );
+}
+
+#[test]
+fn the_worker_is_told_of_every_event_but_the_end() {
+    let handle = sonate_init(true);
+    let mut events: Vec<(c_int, Option<String>)> = Vec::new();
+    let user_data = &mut events as *mut _ as *mut c_void;
+    assert_eq!(
+        sonate_set_event_callback_internal(handle, Some(record_event), user_data),
+        0
+    );
+
+    deliver_event(handle, &click(1.0, 2.0, &[0]));
+    deliver_event(
+        handle,
+        &WorkerEvent::Key {
+            key: "Enter".to_owned(),
+            shift: false,
+            control: true,
+            alt: false,
+            meta: false,
+        },
+    );
+    deliver_event(
+        handle,
+        &WorkerEvent::Resized {
+            width: 1.0,
+            height: 2.0,
+        },
+    );
+    deliver_event(handle, &WorkerEvent::Closed);
+    assert_eq!(
+        events,
+        [
+            (SONATE_EVENT_CLICK, None),
+            (SONATE_EVENT_KEY, Some("Enter".to_owned())),
+            (SONATE_EVENT_RESIZED, None),
+        ]
+    );
+    assert_eq!(sonate_destroy(handle), 0);
+}
+
+#[test]
+fn the_click_callback_may_change_the_document() {
+    let mut handle = sonate_init(true);
+    let root = sonate_root_id(handle);
+    let user_data = &mut handle as *mut _ as *mut c_void;
+    assert_eq!(
+        sonate_set_click_callback(handle, Some(add_node_to_clicked), user_data),
+        0
+    );
+
+    // As `sonate_run` passes the events on, which leaves the engine usable meanwhile.
+    deliver_event(handle, &click(1.0, 2.0, &[root]));
+
+    let (mut parent, mut has_parent) = (0, false);
+    assert_eq!(
+        unsafe { sonate_parent(handle, 5, &mut parent, &mut has_parent) },
+        0
+    );
+    assert!(has_parent);
+    assert_eq!(parent, root);
+    assert_eq!(sonate_destroy(handle), 0);
+}
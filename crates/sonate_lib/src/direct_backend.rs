@@ -1,51 +1,276 @@
-use crate::engine_backend::{EngineBackend, SonateId};
-use sonate::{Engine, Id, Params};
+use crate::engine_backend::{DiagnosticSink, EngineBackend, EventSink, QuitHandle, SonateId};
+use log::error;
+use sonate::{
+    Engine, EngineError, EngineHealth, EngineOptions, Id, Params, Severity, TextGranularity,
+};
+use sonate_common::WorkerEvent;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 pub struct DirectBackend {
+    handle: usize,
     engine: Engine,
 }
 
 impl DirectBackend {
-    pub fn new() -> Self {
+    pub fn new(handle: usize, strict_threading: bool) -> Self {
         Self {
-            engine: Engine::new(),
+            handle,
+            engine: Engine::with_options(EngineOptions {
+                strict_threading,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Log why a change couldn't be handed to the engine: the C API has no way to return
+    /// more than its code, and panicking would abort the host. Returns 0 if it was, the
+    /// error's code otherwise.
+    fn report(&self, node_id: Option<SonateId>, result: Result<(), EngineError>) -> i32 {
+        let Err(err) = result else {
+            return 0;
+        };
+        match node_id {
+            Some(node_id) => error!(
+                "engine {}: node {node_id} did not take the change: {err}",
+                self.handle
+            ),
+            None => error!("engine {}: did not take the change: {err}", self.handle),
+        }
+        err.code().ffi()
+    }
+
+    /// The code of [`EngineError::ShuttingDown`] once the engine's data thread stopped, for
+    /// the calls that would panic then rather than fail.
+    fn check_health(&self) -> Result<(), i32> {
+        match self.engine.health() {
+            EngineHealth::Unhealthy(_) => Err(self.report(None, Err(EngineError::ShuttingDown))),
+            _ => Ok(()),
         }
     }
 }
 
 impl EngineBackend for DirectBackend {
-    fn add_stylesheet(&self, css: String) {
-        self.engine.add_stylesheet(&css);
+    fn add_stylesheet(&self, css: String) -> i32 {
+        self.report(None, self.engine.try_add_stylesheet(&css))
+    }
+
+    fn register_font(&self, family: Option<String>, data: Vec<u8>) -> i32 {
+        self.report(
+            None,
+            self.engine
+                .register_font(family.as_deref(), &data)
+                .map(drop),
+        )
+    }
+
+    fn create_node(&self, node_id: SonateId, text: Option<String>) -> i32 {
+        self.report(
+            Some(node_id),
+            self.engine
+                .try_create_node(Id::from_u64(node_id), text)
+                .map(drop),
+        )
+    }
+
+    fn create_element(&self, node_id: SonateId, tag: String, text: Option<String>) -> i32 {
+        self.report(
+            Some(node_id),
+            self.engine
+                .try_create_element(Id::from_u64(node_id), &tag, text)
+                .map(drop),
+        )
+    }
+
+    fn set_parent(&self, parent_id: SonateId, child_id: SonateId) -> i32 {
+        self.report(
+            Some(child_id),
+            self.engine
+                .try_set_parent(Id::from_u64(parent_id), Id::from_u64(child_id)),
+        )
     }
 
-    fn create_node(&self, node_id: SonateId, text: Option<String>) {
-        let _ = self.engine.create_node(Id::from_u64(node_id), text);
+    fn insert_before(
+        &self,
+        parent_id: SonateId,
+        child_id: SonateId,
+        reference_id: SonateId,
+    ) -> i32 {
+        let result = self.engine.insert_before(
+            Id::from_u64(parent_id),
+            Id::from_u64(child_id),
+            Id::from_u64(reference_id),
+        );
+        self.report(Some(child_id), result)
     }
 
-    fn set_parent(&self, parent_id: SonateId, child_id: SonateId) {
-        self.engine
-            .set_parent(Id::from_u64(parent_id), Id::from_u64(child_id));
+    fn move_child(&self, parent_id: SonateId, child_id: SonateId, new_index: usize) -> i32 {
+        let result =
+            self.engine
+                .move_child(Id::from_u64(parent_id), Id::from_u64(child_id), new_index);
+        self.report(Some(child_id), result)
     }
 
-    fn set_attribute(&self, node_id: SonateId, key: String, value: String) {
-        self.engine.set_attribute(Id::from_u64(node_id), key, value);
+    fn set_attribute(&self, node_id: SonateId, key: String, value: String) -> i32 {
+        self.report(
+            Some(node_id),
+            self.engine
+                .try_set_attribute(Id::from_u64(node_id), key, value),
+        )
     }
 
-    fn root_id(&self) -> SonateId {
-        self.engine.root_id().as_u64()
+    fn set_text(&self, node_id: SonateId, text: Option<String>) -> i32 {
+        self.report(
+            Some(node_id),
+            self.engine.try_set_text(Id::from_u64(node_id), text),
+        )
+    }
+
+    fn load_markup(&self, parent_id: SonateId, markup: String) -> Result<Vec<SonateId>, i32> {
+        self.check_health()?;
+        match self
+            .engine
+            .load_markup_into(Id::from_u64(parent_id), &markup)
+        {
+            Ok(ids) => Ok(ids.into_iter().map(|id| id.as_u64()).collect()),
+            Err(err) => Err(self.report(Some(parent_id), Err(err.into()))),
+        }
     }
 
-    fn run(&self) -> i32 {
-        match self.engine.run(Params { on_click: None }) {
+    fn root_id(&self) -> Result<SonateId, i32> {
+        Ok(self.engine.root_id().as_u64())
+    }
+
+    fn children(&self, node_id: SonateId) -> Result<Vec<SonateId>, i32> {
+        self.check_health()?;
+        let children = self.engine.children(Id::from_u64(node_id));
+        Ok(children.into_iter().map(|id| id.as_u64()).collect())
+    }
+
+    fn parent(&self, node_id: SonateId) -> Result<Option<SonateId>, i32> {
+        self.check_health()?;
+        Ok(self
+            .engine
+            .parent(Id::from_u64(node_id))
+            .map(|id| id.as_u64()))
+    }
+
+    fn extract_text(
+        &self,
+        root_id: SonateId,
+        by_line: bool,
+    ) -> Result<Vec<sonate_common::TextRun>, i32> {
+        let granularity = if by_line {
+            TextGranularity::Line
+        } else {
+            TextGranularity::Node
+        };
+
+        let runs = self
+            .engine
+            .extract_text_with(Some(Id::from_u64(root_id)), granularity)
+            .into_iter()
+            .map(|run| sonate_common::TextRun {
+                id: run.id.as_u64(),
+                text: run.text,
+                x: run.bounds.x,
+                y: run.bounds.y,
+                width: run.bounds.width,
+                height: run.bounds.height,
+            })
+            .collect();
+        Ok(runs)
+    }
+
+    fn stats(&self) -> Result<sonate_common::Stats, i32> {
+        let stats = self.engine.stats();
+        Ok(sonate_common::Stats {
+            mean_layout_ms: stats.mean_layout_ms,
+            max_layout_ms: stats.max_layout_ms,
+            mean_paint_ms: stats.mean_paint_ms,
+            max_paint_ms: stats.max_paint_ms,
+            fps: stats.fps,
+            layout_nodes: stats.layout_nodes,
+        })
+    }
+
+    fn subscribe_diagnostics(&self, sink: DiagnosticSink) -> i32 {
+        let diagnostics = self.engine.subscribe_diagnostics();
+        // Ends when the engine, and with it the sending side, is dropped.
+        thread::spawn(move || {
+            for diagnostic in diagnostics {
+                sink(sonate_common::Diagnostic {
+                    severity: match diagnostic.severity {
+                        Severity::Info => 0,
+                        Severity::Warning => 1,
+                        Severity::Error => 2,
+                    },
+                    code: diagnostic.code.as_str().to_owned(),
+                    message: diagnostic.message,
+                    node_id: diagnostic.node.map(|id| id.as_u64()),
+                    rule_index: diagnostic.rule_index.map(|index| index as u64),
+                });
+            }
+        });
+        0
+    }
+
+    fn set_restart_on_exit(&self, _restart: bool) {}
+
+    fn set_reply_timeout(&self, _timeout: Option<Duration>) {}
+
+    fn ping(&self) -> i32 {
+        self.check_health().err().unwrap_or(0)
+    }
+
+    fn run(&self, on_event: EventSink) -> i32 {
+        let on_event: Rc<dyn Fn(WorkerEvent)> = Rc::from(on_event);
+        let (on_click, on_key, on_resize) = (on_event.clone(), on_event.clone(), on_event.clone());
+        let params = Params {
+            on_click: Some(Box::new(move |x, y, path| {
+                let ids = path.iter().map(Id::as_u64).collect();
+                on_click(WorkerEvent::Click { x, y, ids });
+            })),
+            on_key: Some(Box::new(move |press| {
+                on_key(WorkerEvent::Key {
+                    key: press.key.clone(),
+                    shift: press.shift,
+                    control: press.control,
+                    alt: press.alt,
+                    meta: press.meta,
+                });
+                false
+            })),
+            on_resize: Some(Box::new(move |width, height| {
+                on_resize(WorkerEvent::Resized { width, height })
+            })),
+            ..Default::default()
+        };
+        let result = self.engine.run(params);
+        on_event(WorkerEvent::Closed);
+        match result {
             Ok(()) => 0,
             Err(err) => {
-                eprintln!("sonate_run failed: {:?}", err);
-                -1
+                error!("engine {}: sonate_run failed: {err}", self.handle);
+                err.code().ffi()
             }
         }
     }
 
+    fn quit_handle(&self) -> QuitHandle {
+        let engine = self.engine.clone();
+        Arc::new(move || engine.quit())
+    }
+
     fn destroy(&self) -> i32 {
-        0
+        match self.engine.shutdown() {
+            Ok(()) => 0,
+            Err(err) => {
+                error!("engine {}: sonate_destroy failed: {err}", self.handle);
+                err.code().ffi()
+            }
+        }
     }
 }
@@ -1,11 +1,62 @@
+use std::sync::Arc;
+use std::time::Duration;
+
 pub type SonateId = u64;
 
-pub trait EngineBackend: Send {
-    fn add_stylesheet(&self, css: String);
-    fn create_node(&self, node_id: SonateId, text: Option<String>);
-    fn set_parent(&self, parent_id: SonateId, child_id: SonateId);
-    fn set_attribute(&self, node_id: SonateId, key: String, value: String);
-    fn root_id(&self) -> SonateId;
-    fn run(&self) -> i32;
+/// Receives diagnostics on a background thread. Shared, as a restarted worker is
+/// subscribed again.
+pub type DiagnosticSink = Arc<dyn Fn(sonate_common::Diagnostic) + Send + Sync>;
+
+/// Told of the events of the event loop, on the thread that runs it.
+pub type EventSink = Box<dyn Fn(sonate_common::WorkerEvent)>;
+
+/// Closes the engine's window, if one is open, from any thread.
+pub type QuitHandle = Arc<dyn Fn() + Send + Sync>;
+
+/// Methods that return `i32` return 0 on success, or the code of the error. Shared rather
+/// than locked, as the callbacks of `run` may call the engine back.
+pub trait EngineBackend: Send + Sync {
+    fn add_stylesheet(&self, css: String) -> i32;
+    /// Make a font available under `family`, or the name stored in it.
+    fn register_font(&self, family: Option<String>, data: Vec<u8>) -> i32;
+    fn create_node(&self, node_id: SonateId, text: Option<String>) -> i32;
+    fn create_element(&self, node_id: SonateId, tag: String, text: Option<String>) -> i32;
+    fn set_parent(&self, parent_id: SonateId, child_id: SonateId) -> i32;
+    fn insert_before(&self, parent_id: SonateId, child_id: SonateId, reference_id: SonateId)
+        -> i32;
+    fn move_child(&self, parent_id: SonateId, child_id: SonateId, new_index: usize) -> i32;
+    fn set_attribute(&self, node_id: SonateId, key: String, value: String) -> i32;
+    fn set_text(&self, node_id: SonateId, text: Option<String>) -> i32;
+    /// Create nodes from markup under the parent and return the ids of the top-level ones.
+    fn load_markup(&self, parent_id: SonateId, markup: String) -> Result<Vec<SonateId>, i32>;
+    fn root_id(&self) -> Result<SonateId, i32>;
+    /// Ids of the node's children, in order; empty for unknown nodes.
+    fn children(&self, node_id: SonateId) -> Result<Vec<SonateId>, i32>;
+    /// The node's parent; `None` for unknown and detached nodes and for roots.
+    fn parent(&self, node_id: SonateId) -> Result<Option<SonateId>, i32>;
+    fn extract_text(
+        &self,
+        root_id: SonateId,
+        by_line: bool,
+    ) -> Result<Vec<sonate_common::TextRun>, i32>;
+    /// What the latest layout passes and frames took.
+    fn stats(&self) -> Result<sonate_common::Stats, i32>;
+    /// Start delivering the engine's diagnostics to `sink`. Called at most once per engine.
+    fn subscribe_diagnostics(&self, sink: DiagnosticSink) -> i32;
+    /// Whether to restart the worker process the next time it exits. Engines without one
+    /// ignore it.
+    fn set_restart_on_exit(&self, restart: bool);
+    /// How long to wait for each reply of the worker process; `None` to wait as long as it
+    /// runs. Engines without one ignore it.
+    fn set_reply_timeout(&self, timeout: Option<Duration>);
+    /// Check that the worker process, if there is one, still takes requests, and that the
+    /// engine's data thread still runs.
+    fn ping(&self) -> i32;
+    /// Run the event loop until the window is closed, passing its events to `on_event`. An
+    /// engine that can't take calls from `on_event` fails them with the reentrancy code.
+    fn run(&self, on_event: EventSink) -> i32;
+    /// Called once, when the engine is registered, as only `sonate_quit` may use the engine
+    /// from other threads than its own.
+    fn quit_handle(&self) -> QuitHandle;
     fn destroy(&self) -> i32;
 }
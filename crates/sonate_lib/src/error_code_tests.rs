@@ -0,0 +1,78 @@
+use super::*;
+use std::collections::HashMap;
+use std::ptr;
+
+/// The `SONATE_ERR_*` defines of the header, by name without the prefix.
+fn header_codes() -> HashMap<String, c_int> {
+    include_str!("../include/sonate.h")
+        .lines()
+        .filter_map(|line| {
+            let define = line.strip_prefix("#define SONATE_ERR_")?;
+            let (name, value) = define.split_once(' ')?;
+            let value = value.trim().trim_start_matches('(').trim_end_matches(')');
+            Some((name.to_owned(), value.parse().unwrap()))
+        })
+        .collect()
+}
+
+#[test]
+fn the_header_declares_every_code_with_its_value() {
+    let header = header_codes();
+    for &code in ErrorCode::ALL {
+        let name = code.name().to_ascii_uppercase().replace('-', "_");
+        assert_eq!(header.get(&name), Some(&code.ffi()), "SONATE_ERR_{name}");
+    }
+    assert_eq!(header.len(), ErrorCode::ALL.len(), "{header:?}");
+}
+
+#[test]
+fn refused_changes_return_the_code_of_the_reason() {
+    let handle = sonate_init(true);
+    let root = sonate_root_id(handle);
+    let stray = sonate_create_node(handle, 5, ptr::null());
+
+    assert_eq!(
+        sonate_move_child(handle, root, stray, 0),
+        SONATE_ERR_DOCUMENT_NOT_A_CHILD
+    );
+    assert_eq!(
+        sonate_insert_before(handle, 404, stray, root),
+        SONATE_ERR_DOCUMENT_PARENT_NOT_FOUND
+    );
+    assert_eq!(sonate_destroy(handle), 0);
+}
+
+#[test]
+fn elements_need_a_tag() {
+    let handle = sonate_init(true);
+    let tag = c"button";
+    assert_eq!(
+        sonate_create_element(handle, 5, tag.as_ptr(), ptr::null()),
+        5
+    );
+    assert_eq!(
+        sonate_create_element(handle, 6, ptr::null(), ptr::null()),
+        0
+    );
+    assert_eq!(
+        sonate_create_element(handle, 0, tag.as_ptr(), ptr::null()),
+        0
+    );
+    assert_eq!(sonate_destroy(handle), 0);
+}
+
+#[test]
+fn the_ids_of_the_engine_are_not_for_nodes() {
+    let handle = sonate_init(true);
+    let generated = 1 << 63;
+    assert_eq!(sonate_create_node(handle, generated, ptr::null()), 0);
+    assert_eq!(
+        sonate_create_element(handle, generated + 1, c"div".as_ptr(), ptr::null()),
+        0
+    );
+    assert_eq!(
+        sonate_create_node(handle, generated - 1, ptr::null()),
+        generated - 1
+    );
+    assert_eq!(sonate_destroy(handle), 0);
+}
@@ -1,16 +1,25 @@
 use std::collections::HashMap;
-use std::ffi::CStr;
-use std::os::raw::{c_char, c_int};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 
 mod direct_backend;
 mod engine_backend;
+mod logging;
+mod registry;
 mod worker_backend;
 
 use direct_backend::DirectBackend;
-use engine_backend::EngineBackend;
+use engine_backend::{EngineBackend, QuitHandle};
+use log::error;
+pub use logging::{
+    SonateLogCallback, SONATE_LOG_DEBUG, SONATE_LOG_ERROR, SONATE_LOG_INFO, SONATE_LOG_OFF,
+    SONATE_LOG_TRACE, SONATE_LOG_WARN,
+};
+use registry::{Missing, Registry};
+use sonate::{ErrorCode, Id, ThreadAffinity};
 use worker_backend::WorkerBackend;
 
 /// Handle type for engine instances
@@ -19,13 +28,257 @@ pub type EngineHandle = usize;
 /// ID type for nodes and other engine-owned objects.
 pub type SonateId = u64;
 
+// What functions that return `c_int` return on failure, one per `ErrorCode` and with the
+// same value. The table in `sonate_error` is the source; include/sonate.h repeats it.
+
+/// Returned for a null or non-UTF-8 string, or another argument the call can't take.
+pub const SONATE_ERR_INVALID_ARGUMENT: c_int = ErrorCode::InvalidArgument.ffi();
+/// Returned when called on a thread other than the one that created the engine. See
+/// `sonate_init_strict`.
+pub const SONATE_ERR_WRONG_THREAD: c_int = ErrorCode::WrongThread.ffi();
+/// Returned for a handle `sonate_init` never returned.
+pub const SONATE_ERR_UNKNOWN_HANDLE: c_int = ErrorCode::UnknownHandle.ffi();
+/// Returned for the handle of a destroyed engine, also when a later engine got the same
+/// slot.
+pub const SONATE_ERR_STALE_HANDLE: c_int = ErrorCode::StaleHandle.ffi();
+
+pub const SONATE_ERR_SHUTTING_DOWN: c_int = ErrorCode::ShuttingDown.ffi();
+pub const SONATE_ERR_REENTRANCY: c_int = ErrorCode::Reentrancy.ffi();
+pub const SONATE_ERR_IO: c_int = ErrorCode::Io.ffi();
+pub const SONATE_ERR_CSS_SYNTAX: c_int = ErrorCode::CssSyntax.ffi();
+pub const SONATE_ERR_CSS_UNKNOWN_PROPERTY: c_int = ErrorCode::CssUnknownProperty.ffi();
+pub const SONATE_ERR_CSS_INVALID_VALUE: c_int = ErrorCode::CssInvalidValue.ffi();
+pub const SONATE_ERR_DOCUMENT_SAME_NODE: c_int = ErrorCode::DocumentSameNode.ffi();
+pub const SONATE_ERR_DOCUMENT_CHILD_NOT_FOUND: c_int = ErrorCode::DocumentChildNotFound.ffi();
+pub const SONATE_ERR_DOCUMENT_PARENT_NOT_FOUND: c_int = ErrorCode::DocumentParentNotFound.ffi();
+pub const SONATE_ERR_DOCUMENT_CYCLE: c_int = ErrorCode::DocumentCycle.ffi();
+pub const SONATE_ERR_DOCUMENT_NOT_A_CHILD: c_int = ErrorCode::DocumentNotAChild.ffi();
+pub const SONATE_ERR_WORKER_SPAWN: c_int = ErrorCode::WorkerSpawn.ffi();
+pub const SONATE_ERR_WORKER_LOAD_LIBRARY: c_int = ErrorCode::WorkerLoadLibrary.ffi();
+pub const SONATE_ERR_WORKER_DISCONNECTED: c_int = ErrorCode::WorkerDisconnected.ffi();
+pub const SONATE_ERR_WINDOW_EVENT_LOOP: c_int = ErrorCode::WindowEventLoop.ffi();
+pub const SONATE_ERR_WINDOW_NOT_PRESENTING_EXTERNALLY: c_int =
+    ErrorCode::WindowNotPresentingExternally.ffi();
+pub const SONATE_ERR_INVALID_FONT: c_int = ErrorCode::InvalidFont.ffi();
+pub const SONATE_ERR_RENDER_FAILED: c_int = ErrorCode::RenderFailed.ffi();
+/// Returned once the worker process of the engine exited, unless it was restarted. See
+/// `sonate_set_worker_restart`.
+pub const SONATE_ERR_WORKER_DIED: c_int = ErrorCode::WorkerDied.ffi();
+/// Returned when the worker process doesn't reply within the timeout set with
+/// `sonate_set_ipc_timeout_ms`.
+pub const SONATE_ERR_WORKER_TIMEOUT: c_int = ErrorCode::WorkerTimeout.ffi();
+pub const SONATE_ERR_WORKER_VERSION_MISMATCH: c_int = ErrorCode::WorkerVersionMismatch.ffi();
+/// The code of a node created with the id of another. `sonate_create_node` can't wait to
+/// find out, and reports a diagnostic instead.
+pub const SONATE_ERR_DOCUMENT_ID_IN_USE: c_int = ErrorCode::DocumentIdInUse.ffi();
+/// The code of markup that isn't well-formed; where and why is logged.
+pub const SONATE_ERR_MARKUP_SYNTAX: c_int = ErrorCode::MarkupSyntax.ffi();
+/// The code of a full queue of changes, from calls that don't wait for room.
+pub const SONATE_ERR_BUSY: c_int = ErrorCode::Busy.ffi();
+
+/// A run of visible text returned by `sonate_extract_text`.
+#[repr(C)]
+pub struct SonateTextRun {
+    pub id: SonateId,
+    /// Null-terminated UTF-8 text, owned by the array it is in.
+    pub text: *mut c_char,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// What the engine's latest layout passes and frames took, written by `sonate_get_stats`.
+/// Zero for what didn't happen yet.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SonateStats {
+    pub mean_layout_ms: f64,
+    pub max_layout_ms: f64,
+    /// Painting a frame of the primary window, without presenting it
+    pub mean_paint_ms: f64,
+    pub max_paint_ms: f64,
+    /// Frames per second of the primary window
+    pub fps: f64,
+    /// The nodes the last layout pass sized and positioned
+    pub layout_nodes: u64,
+}
+
+/// A problem reported by the engine, passed to the callback set with
+/// `sonate_set_diagnostics_callback`. The strings are only valid during the callback.
+#[repr(C)]
+pub struct SonateDiagnostic {
+    /// 0 info, 1 warning, 2 error
+    pub severity: c_int,
+    /// Stable identifier such as "css-unknown-property"
+    pub code: *const c_char,
+    pub message: *const c_char,
+    /// The node the problem is about, if `has_node`
+    pub node_id: SonateId,
+    pub has_node: bool,
+    /// Index of the stylesheet rule the problem is about, or -1
+    pub rule_index: i64,
+}
+
+pub type SonateDiagnosticCallback =
+    extern "C" fn(diagnostic: *const SonateDiagnostic, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct DiagnosticCallback {
+    callback: SonateDiagnosticCallback,
+    user_data: *mut c_void,
+}
+
+// The host is told the callback runs on a background thread along with its user data.
+unsafe impl Send for DiagnosticCallback {}
+
+/// Told that the worker process of an engine exited, and whether it was restarted.
+pub type SonateWorkerExitCallback =
+    extern "C" fn(handle: EngineHandle, restarted: bool, user_data: *mut c_void);
+
+#[derive(Clone, Copy)]
+struct WorkerExitCallback {
+    callback: SonateWorkerExitCallback,
+    user_data: *mut c_void,
+}
+
+// As for diagnostics, the host is told the callback runs on a background thread.
+unsafe impl Send for WorkerExitCallback {}
+
+/// Told of a click no event listener stopped, at `x`, `y` in the window, with the ids of the
+/// `id_count` nodes under the pointer, innermost first. `ids` is only valid during the call.
+pub type SonateClickCallback =
+    extern "C" fn(user_data: *mut c_void, x: f64, y: f64, ids: *const SonateId, id_count: usize);
+
+#[derive(Clone, Copy)]
+struct ClickCallback {
+    callback: SonateClickCallback,
+    user_data: *mut c_void,
+}
+
+// The host is told the callback runs on the thread running `sonate_run`, which may not be
+// the one that set it, along with its user data.
+unsafe impl Send for ClickCallback {}
+
+/// An event of the event loop, passed to the callback set with
+/// `sonate_set_event_callback_internal`. The pointers are only valid during the callback.
+#[repr(C)]
+pub struct SonateEvent {
+    /// One of the `SONATE_EVENT_*` kinds
+    pub kind: c_int,
+    /// Where a click was, or the new width and height of the window
+    pub x: f64,
+    pub y: f64,
+    /// The nodes under the pointer for a click, innermost first
+    pub ids: *const SonateId,
+    pub id_count: usize,
+    /// Null-terminated UTF-8 name of the key for a key press, null otherwise
+    pub key: *const c_char,
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl SonateEvent {
+    /// An event of no kind, to fill in.
+    fn empty() -> Self {
+        Self {
+            kind: -1,
+            x: 0.0,
+            y: 0.0,
+            ids: std::ptr::null(),
+            id_count: 0,
+            key: std::ptr::null(),
+            shift: false,
+            control: false,
+            alt: false,
+            meta: false,
+        }
+    }
+}
+
+pub const SONATE_EVENT_CLICK: c_int = 0;
+pub const SONATE_EVENT_KEY: c_int = 1;
+pub const SONATE_EVENT_RESIZED: c_int = 2;
+
+pub type SonateEventCallback = extern "C" fn(user_data: *mut c_void, event: *const SonateEvent);
+
+#[derive(Clone, Copy)]
+struct EventCallback {
+    callback: SonateEventCallback,
+    user_data: *mut c_void,
+}
+
+// As for clicks, the callback runs on the thread running `sonate_run`.
+unsafe impl Send for EventCallback {}
+
 type EngineBox = Box<dyn EngineBackend>;
-type EngineRef = Arc<Mutex<EngineBox>>;
+type EngineRef = Arc<EngineBox>;
+
+/// An engine with the thread that created it. Every entry point checks the calling thread
+/// before using the engine, except for diagnostics delivery, which only reads the
+/// callbacks and runs on a background thread by design, and `sonate_quit`, which is for
+/// stopping the event loop that blocks the owning thread.
+struct EngineInstance {
+    engine: EngineRef,
+    affinity: ThreadAffinity,
+    /// Usable from any thread, unlike `engine`.
+    quit: QuitHandle,
+}
 
-static ENGINE_INSTANCES: std::sync::LazyLock<Mutex<HashMap<EngineHandle, EngineRef>>> =
+static ENGINE_INSTANCES: Registry<EngineInstance> = Registry::new();
+
+/// Engines subscribed to diagnostics, with the callback currently set for each.
+static DIAGNOSTIC_CALLBACKS: std::sync::LazyLock<
+    Mutex<HashMap<EngineHandle, Option<DiagnosticCallback>>>,
+> = std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The callback set for each engine told when its worker exits.
+static WORKER_EXIT_CALLBACKS: std::sync::LazyLock<
+    Mutex<HashMap<EngineHandle, WorkerExitCallback>>,
+> = std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// The callback set for each engine told of clicks.
+static CLICK_CALLBACKS: std::sync::LazyLock<Mutex<HashMap<EngineHandle, ClickCallback>>> =
     std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
 
-static NEXT_HANDLE: AtomicUsize = AtomicUsize::new(1);
+/// The callback `sonate_worker` set for each engine it runs.
+static EVENT_CALLBACKS: std::sync::LazyLock<Mutex<HashMap<EngineHandle, EventCallback>>> =
+    std::sync::LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Version of this API, which `sonate_worker` checks before driving the library
+#[no_mangle]
+pub extern "C" fn sonate_abi_version() -> u32 {
+    sonate_common::ABI_VERSION
+}
+
+/// Send the library's log lines to a callback instead of standard error
+///
+/// # Arguments
+/// * `callback` - Called with a `SONATE_LOG_*` level and the line; null to log to standard
+///   error again, filtered by the `RUST_LOG` environment variable
+///
+/// Engines running in a worker process log to that process's standard error.
+#[no_mangle]
+pub extern "C" fn sonate_set_log_callback(callback: Option<SonateLogCallback>) {
+    logging::set_callback(callback);
+}
+
+/// Set the most detailed level passed to the log callback; `SONATE_LOG_WARN` until set
+///
+/// # Returns
+/// * 0 on success, SONATE_ERR_INVALID_ARGUMENT if `level` isn't one of the `SONATE_LOG_*`
+///   levels
+#[no_mangle]
+pub extern "C" fn sonate_set_log_level(level: c_int) -> c_int {
+    if logging::set_level(level) {
+        0
+    } else {
+        error!("invalid log level {level}");
+        SONATE_ERR_INVALID_ARGUMENT
+    }
+}
 
 /// Initialize the sonate engine
 ///
@@ -37,38 +290,94 @@ static NEXT_HANDLE: AtomicUsize = AtomicUsize::new(1);
 /// * Engine handle on success, 0 on error
 #[no_mangle]
 pub extern "C" fn sonate_init(use_same_process: bool) -> EngineHandle {
-    let handle = NEXT_HANDLE.fetch_add(1, Ordering::SeqCst);
+    init(use_same_process, false)
+}
+
+/// Like `sonate_init`, but calls on other threads than this one fail with
+/// `SONATE_ERR_WRONG_THREAD` in release builds too, rather than only in debug builds.
+#[no_mangle]
+pub extern "C" fn sonate_init_strict(use_same_process: bool) -> EngineHandle {
+    init(use_same_process, true)
+}
+
+fn init(use_same_process: bool, strict_threading: bool) -> EngineHandle {
+    logging::init();
+
+    // The worker is told the handle, so it's reserved before the worker is started, and
+    // the registry isn't locked meanwhile.
+    let handle = ENGINE_INSTANCES.reserve();
 
     let backend: EngineBox = if use_same_process {
-        Box::new(DirectBackend::new())
+        Box::new(DirectBackend::new(handle, strict_threading))
     } else {
-        match WorkerBackend::new(handle) {
+        let on_exit = Arc::new(move |restarted| deliver_worker_exit(handle, restarted));
+        match WorkerBackend::new(handle, on_exit) {
             Ok(b) => Box::new(b),
             Err(e) => {
-                eprintln!("Failed to create worker instance: {}", e);
+                error!("engine {handle}: failed to create worker instance: {e}");
+                ENGINE_INSTANCES.release(handle);
                 return 0;
             }
         }
     };
 
-    ENGINE_INSTANCES
-        .lock()
-        .unwrap()
-        .insert(handle, Arc::new(Mutex::new(backend)));
-
+    let instance = engine_instance(backend, ThreadAffinity::current(strict_threading));
+    ENGINE_INSTANCES.fill(handle, instance);
     handle
 }
 
 #[no_mangle]
 pub extern "C" fn sonate_init_internal(handle: EngineHandle) {
-    ENGINE_INSTANCES
-        .lock()
-        .unwrap()
-        .insert(handle, Arc::new(Mutex::new(Box::new(DirectBackend::new()))));
+    logging::init();
+    let instance = engine_instance(
+        Box::new(DirectBackend::new(handle, false)),
+        ThreadAffinity::current(false),
+    );
+    ENGINE_INSTANCES.insert_at(handle, instance);
 }
 
-fn get_engine(handle: EngineHandle) -> Option<EngineRef> {
-    ENGINE_INSTANCES.lock().unwrap().get(&handle).cloned()
+fn engine_instance(backend: EngineBox, affinity: ThreadAffinity) -> EngineInstance {
+    EngineInstance {
+        quit: backend.quit_handle(),
+        engine: Arc::new(backend),
+        affinity,
+    }
+}
+
+/// Why an entry point can't use an engine handle.
+enum LookupError {
+    Missing(Missing),
+    WrongThread(sonate::EngineError),
+}
+
+impl LookupError {
+    /// Log the problem and return the error code for it.
+    fn report(self, handle: EngineHandle) -> c_int {
+        logging::init();
+        match self {
+            LookupError::Missing(Missing::Unknown) => {
+                error!("engine {handle}: handle not found");
+                SONATE_ERR_UNKNOWN_HANDLE
+            }
+            LookupError::Missing(Missing::Stale) => {
+                error!("engine {handle}: handle of a destroyed engine");
+                SONATE_ERR_STALE_HANDLE
+            }
+            LookupError::WrongThread(err) => {
+                error!("engine {handle}: {err}");
+                SONATE_ERR_WRONG_THREAD
+            }
+        }
+    }
+}
+
+fn get_engine(handle: EngineHandle) -> Result<EngineRef, LookupError> {
+    let instance = ENGINE_INSTANCES.get(handle).map_err(LookupError::Missing)?;
+    instance
+        .affinity
+        .check()
+        .map_err(LookupError::WrongThread)?;
+    Ok(Arc::clone(&instance.engine))
 }
 
 /// Add a CSS stylesheet to the engine
@@ -76,32 +385,77 @@ fn get_engine(handle: EngineHandle) -> Option<EngineRef> {
 /// # Arguments
 /// * `handle` - Engine handle returned from sonate_init
 /// * `css_content` - Null-terminated CSS string
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
 #[no_mangle]
-pub extern "C" fn sonate_add_stylesheet(handle: EngineHandle, css_content: *const c_char) {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return;
-    }
-
+pub extern "C" fn sonate_add_stylesheet(handle: EngineHandle, css_content: *const c_char) -> c_int {
     if css_content.is_null() {
-        eprintln!("CSS content is null");
-        return;
+        error!("engine {handle}: CSS content is null");
+        return SONATE_ERR_INVALID_ARGUMENT;
     }
 
     let css_str = match unsafe { CStr::from_ptr(css_content) }.to_str() {
         Ok(s) => s.to_string(),
         Err(e) => {
-            eprintln!("Invalid UTF-8 in CSS content: {}", e);
-            return;
+            error!("engine {handle}: invalid UTF-8 in CSS content: {e}");
+            return SONATE_ERR_INVALID_ARGUMENT;
         }
     };
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
     };
 
-    engine.lock().unwrap().add_stylesheet(css_str);
+    engine.add_stylesheet(css_str)
+}
+
+/// Make a TrueType/OpenType font available to the engine, before installed fonts of the
+/// same family. Text is re-measured on the next layout.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `family` - Optional null-terminated family name, overriding the name stored in the font
+///   (can be null)
+/// * `data` - The font file's bytes, copied before returning
+/// * `len` - Number of bytes at `data`
+///
+/// # Returns
+/// * 0 on success, SONATE_ERR_INVALID_FONT if no font could be made from the data, or
+///   another `SONATE_ERR_*` code
+#[no_mangle]
+pub extern "C" fn sonate_register_font(
+    handle: EngineHandle,
+    family: *const c_char,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    if data.is_null() || len == 0 {
+        error!("engine {handle}: font data is null or empty");
+        return SONATE_ERR_INVALID_ARGUMENT;
+    }
+
+    let family = if family.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(family) }.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(e) => {
+                error!("engine {handle}: invalid UTF-8 in font family: {e}");
+                return SONATE_ERR_INVALID_ARGUMENT;
+            }
+        }
+    };
+    let data = unsafe { std::slice::from_raw_parts(data, len) }.to_vec();
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    engine.register_font(family, data)
 }
 
 /// Create a new document node
@@ -111,41 +465,110 @@ pub extern "C" fn sonate_add_stylesheet(handle: EngineHandle, css_content: *cons
 /// * `text_content` - Optional null-terminated text content (can be null)
 ///
 /// # Returns
-/// * Node ID on success, 0 on error (since root is always ID 0, we can distinguish)
+/// * Node ID on success, 0 on error (since root is always ID 0, we can distinguish), also
+///   when the worker process the engine runs in exited
 #[no_mangle]
 pub extern "C" fn sonate_create_node(
     handle: EngineHandle,
     node_id: SonateId,
     text_content: *const c_char,
 ) -> SonateId {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
+    if node_id == 0 {
+        error!("engine {handle}: invalid node id (0 is reserved for root)");
+        return 0;
+    }
+    if node_id >= Id::GENERATED_BASE {
+        error!("engine {handle}: invalid node id {node_id} (ids from 2^63 are the engine's)");
         return 0;
     }
 
+    let text = if text_content.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(text_content) }.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(e) => {
+                error!("engine {handle}: invalid UTF-8 in text content of node {node_id}: {e}");
+                return 0;
+            }
+        }
+    };
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => {
+            err.report(handle);
+            return 0;
+        }
+    };
+
+    let code = engine.create_node(node_id, text);
+    if code != 0 {
+        return 0;
+    }
+    node_id
+}
+
+/// Create a new element, a node with a tag that type selectors match
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `tag` - Null-terminated element name, in any case
+/// * `text_content` - Optional null-terminated text content (can be null)
+///
+/// # Returns
+/// * Node ID on success, 0 on error, as for `sonate_create_node`
+#[no_mangle]
+pub extern "C" fn sonate_create_element(
+    handle: EngineHandle,
+    node_id: SonateId,
+    tag: *const c_char,
+    text_content: *const c_char,
+) -> SonateId {
     if node_id == 0 {
-        eprintln!("Invalid node id (0 is reserved for root)");
+        error!("engine {handle}: invalid node id (0 is reserved for root)");
+        return 0;
+    }
+    if node_id >= Id::GENERATED_BASE {
+        error!("engine {handle}: invalid node id {node_id} (ids from 2^63 are the engine's)");
+        return 0;
+    }
+    if tag.is_null() {
+        error!("engine {handle}: null tag for node {node_id}");
         return 0;
     }
 
+    let tag = match unsafe { CStr::from_ptr(tag) }.to_str() {
+        Ok(s) => s.to_string(),
+        Err(e) => {
+            error!("engine {handle}: invalid UTF-8 in tag of node {node_id}: {e}");
+            return 0;
+        }
+    };
     let text = if text_content.is_null() {
         None
     } else {
         match unsafe { CStr::from_ptr(text_content) }.to_str() {
             Ok(s) => Some(s.to_string()),
             Err(e) => {
-                eprintln!("Invalid UTF-8 in text content: {}", e);
+                error!("engine {handle}: invalid UTF-8 in text content of node {node_id}: {e}");
                 return 0;
             }
         }
     };
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return 0;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => {
+            err.report(handle);
+            return 0;
+        }
     };
 
-    engine.lock().unwrap().create_node(node_id, text);
+    let code = engine.create_element(node_id, tag, text);
+    if code != 0 {
+        return 0;
+    }
     node_id
 }
 
@@ -157,20 +580,72 @@ pub extern "C" fn sonate_create_node(
 /// * `child_id` - ID of the child node
 ///
 /// # Returns
-/// * 0 on success, -1 on error
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
 #[no_mangle]
-pub extern "C" fn sonate_set_parent(handle: EngineHandle, parent_id: SonateId, child_id: SonateId) {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return;
-    }
+pub extern "C" fn sonate_set_parent(
+    handle: EngineHandle,
+    parent_id: SonateId,
+    child_id: SonateId,
+) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return;
+    engine.set_parent(parent_id, child_id)
+}
+
+/// Put a node under a parent right before one of the parent's children
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `parent_id` - ID of the parent node
+/// * `child_id` - ID of the node to insert, moved there if it's already a child
+/// * `reference_id` - ID of the child of the parent to insert before
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_insert_before(
+    handle: EngineHandle,
+    parent_id: SonateId,
+    child_id: SonateId,
+    reference_id: SonateId,
+) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    engine.insert_before(parent_id, child_id, reference_id)
+}
+
+/// Move a child of a parent to another index among the parent's children
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `parent_id` - ID of the parent node
+/// * `child_id` - ID of the child to move
+/// * `new_index` - Where to move it; past the end moves it after the last child
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_move_child(
+    handle: EngineHandle,
+    parent_id: SonateId,
+    child_id: SonateId,
+    new_index: usize,
+) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
     };
 
-    engine.lock().unwrap().set_parent(parent_id, child_id);
+    engine.move_child(parent_id, child_id, new_index)
 }
 
 /// Set an attribute on a node
@@ -182,49 +657,81 @@ pub extern "C" fn sonate_set_parent(handle: EngineHandle, parent_id: SonateId, c
 /// * `value` - Null-terminated attribute value string
 ///
 /// # Returns
-/// * 0 on success, -1 on error
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
 #[no_mangle]
 pub extern "C" fn sonate_set_attribute(
     handle: EngineHandle,
     node_id: SonateId,
     key: *const c_char,
     value: *const c_char,
-) {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return;
-    }
-
+) -> c_int {
     if key.is_null() || value.is_null() {
-        eprintln!("Key or value is null");
-        return;
+        error!("engine {handle}: attribute key or value of node {node_id} is null");
+        return SONATE_ERR_INVALID_ARGUMENT;
     }
 
     let key_str = match unsafe { CStr::from_ptr(key) }.to_str() {
         Ok(s) => s.to_string(),
         Err(e) => {
-            eprintln!("Invalid UTF-8 in attribute key: {}", e);
-            return;
+            error!("engine {handle}: invalid UTF-8 in attribute key of node {node_id}: {e}");
+            return SONATE_ERR_INVALID_ARGUMENT;
         }
     };
 
     let value_str = match unsafe { CStr::from_ptr(value) }.to_str() {
         Ok(s) => s.to_string(),
         Err(e) => {
-            eprintln!("Invalid UTF-8 in attribute value: {}", e);
-            return;
+            error!("engine {handle}: invalid UTF-8 in attribute value of node {node_id}: {e}");
+            return SONATE_ERR_INVALID_ARGUMENT;
         }
     };
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
     };
 
-    engine
-        .lock()
-        .unwrap()
-        .set_attribute(node_id, key_str, value_str);
+    engine.set_attribute(node_id, key_str, value_str)
+}
+
+/// Replace the text of a node
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `node_id` - ID of the node
+/// * `text_content` - Null-terminated UTF-8 string, or null to make the node a plain container
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+///
+/// # Safety
+/// `text_content` must be null or point to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_set_text(
+    handle: EngineHandle,
+    node_id: SonateId,
+    text_content: *const c_char,
+) -> c_int {
+    let text = if text_content.is_null() {
+        None
+    } else {
+        match unsafe { CStr::from_ptr(text_content) }.to_str() {
+            Ok(s) => Some(s.to_string()),
+            Err(e) => {
+                error!("engine {handle}: invalid UTF-8 in text content of node {node_id}: {e}");
+                return SONATE_ERR_INVALID_ARGUMENT;
+            }
+        }
+    };
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    engine.set_text(node_id, text)
 }
 
 /// Get the root node ID of the document
@@ -233,66 +740,689 @@ pub extern "C" fn sonate_set_attribute(
 /// * `handle` - Engine handle returned from sonate_init
 ///
 /// # Returns
-/// * Root node ID (always 0 for the document root), or 0 if handle is invalid
+/// * Root node ID (always 0 for the document root), or 0 if handle is invalid or the worker
+///   process the engine runs in exited
 #[no_mangle]
 pub extern "C" fn sonate_root_id(handle: EngineHandle) -> SonateId {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return 0;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => {
+            err.report(handle);
+            return 0;
+        }
+    };
+
+    let id = engine.root_id();
+    id.unwrap_or(0)
+}
+
+/// Create nodes from XML-like markup such as `<div class="row"><span>Hello</span></div>`
+/// under a node
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `parent_id` - Node to create them under; the root ID (0) for the document
+/// * `markup` - Null-terminated UTF-8 markup
+/// * `out_ids` - Receives the ids of the first `capacity` top-level nodes; may be null if
+///   `capacity` is 0
+/// * `capacity` - How many ids `out_ids` has room for
+/// * `out_count` - Receives the number of top-level nodes, which may be more than
+///   `capacity`: sonate_children gives the rest
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code: SONATE_ERR_MARKUP_SYNTAX for malformed markup,
+///   of which nothing is created
+///
+/// # Safety
+/// `out_ids` must be valid for `capacity` writes and `out_count` for one.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_load_markup(
+    handle: EngineHandle,
+    parent_id: SonateId,
+    markup: *const c_char,
+    out_ids: *mut SonateId,
+    capacity: usize,
+    out_count: *mut usize,
+) -> c_int {
+    if out_count.is_null() || (out_ids.is_null() && capacity > 0) {
+        error!("engine {handle}: output pointers are null");
+        return SONATE_ERR_INVALID_ARGUMENT;
+    }
+    if markup.is_null() {
+        error!("engine {handle}: null markup");
+        return SONATE_ERR_INVALID_ARGUMENT;
     }
+    let markup = match unsafe { CStr::from_ptr(markup) }.to_str() {
+        Ok(s) => s.to_string(),
+        Err(e) => {
+            error!("engine {handle}: invalid UTF-8 in markup: {e}");
+            return SONATE_ERR_INVALID_ARGUMENT;
+        }
+    };
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return 0;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
     };
 
-    let id = engine.lock().unwrap().root_id();
-    id
+    let ids = match engine.load_markup(parent_id, markup) {
+        Ok(ids) => ids,
+        Err(code) => return code,
+    };
+    unsafe { copy_ids(&ids, out_ids, capacity, out_count) };
+    0
 }
 
-/// Run the engine event loop (blocking).
+/// Copy the first `capacity` of `ids` to `out_ids`, and how many there are to `out_count`.
+///
+/// # Safety
+/// As for the buffers of sonate_children.
+unsafe fn copy_ids(
+    ids: &[SonateId],
+    out_ids: *mut SonateId,
+    capacity: usize,
+    out_count: *mut usize,
+) {
+    let copied = ids.len().min(capacity);
+    unsafe {
+        if copied > 0 {
+            std::ptr::copy_nonoverlapping(ids.as_ptr(), out_ids, copied);
+        }
+        *out_count = ids.len();
+    }
+}
+
+/// Get the children of a node, in order
 ///
 /// # Arguments
 /// * `handle` - Engine handle returned from sonate_init
+/// * `node_id` - Node whose children to get; unknown nodes have none
+/// * `out_ids` - Receives the ids of the first `capacity` children; may be null if
+///   `capacity` is 0
+/// * `capacity` - How many ids `out_ids` has room for
+/// * `out_count` - Receives the number of children, which may be more than `capacity`:
+///   call again with a larger buffer to get them all
 ///
 /// # Returns
-/// * 0 on success, -1 on error
+/// * 0 on success, or a `SONATE_ERR_*` code
+///
+/// # Safety
+/// `out_ids` must be valid for `capacity` writes and `out_count` for one.
 #[no_mangle]
-pub extern "C" fn sonate_run(handle: EngineHandle) -> c_int {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return -1;
+pub unsafe extern "C" fn sonate_children(
+    handle: EngineHandle,
+    node_id: SonateId,
+    out_ids: *mut SonateId,
+    capacity: usize,
+    out_count: *mut usize,
+) -> c_int {
+    if out_count.is_null() || (out_ids.is_null() && capacity > 0) {
+        error!("engine {handle}: output pointers are null");
+        return SONATE_ERR_INVALID_ARGUMENT;
+    }
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    let children = match engine.children(node_id) {
+        Ok(children) => children,
+        Err(code) => return code,
+    };
+    unsafe { copy_ids(&children, out_ids, capacity, out_count) };
+    0
+}
+
+/// Get the parent of a node
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `node_id` - Node whose parent to get
+/// * `out_parent` - Receives the parent's id, if it has one
+/// * `out_has_parent` - Receives whether it has one: false for roots, detached and unknown
+///   nodes
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code
+///
+/// # Safety
+/// `out_parent` and `out_has_parent` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_parent(
+    handle: EngineHandle,
+    node_id: SonateId,
+    out_parent: *mut SonateId,
+    out_has_parent: *mut bool,
+) -> c_int {
+    if out_parent.is_null() || out_has_parent.is_null() {
+        error!("engine {handle}: output pointers are null");
+        return SONATE_ERR_INVALID_ARGUMENT;
+    }
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    let parent = match engine.parent(node_id) {
+        Ok(parent) => parent,
+        Err(code) => return code,
+    };
+    unsafe {
+        *out_parent = parent.unwrap_or(0);
+        *out_has_parent = parent.is_some();
+    }
+    0
+}
+
+/// Extract the visible text of the document or a subtree, in reading order
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `root_id` - Subtree to extract; the root ID (0) for the whole document
+/// * `by_line` - If true, one run per wrapped line instead of one per text node
+/// * `out_runs` - Receives an array of runs, to be released with sonate_free_text_runs
+/// * `out_count` - Receives the number of runs
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+///
+/// # Safety
+/// `out_runs` and `out_count` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_extract_text(
+    handle: EngineHandle,
+    root_id: SonateId,
+    by_line: bool,
+    out_runs: *mut *mut SonateTextRun,
+    out_count: *mut usize,
+) -> c_int {
+    if out_runs.is_null() || out_count.is_null() {
+        error!("engine {handle}: output pointers are null");
+        return SONATE_ERR_INVALID_ARGUMENT;
+    }
+
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    let runs = engine.extract_text(root_id, by_line);
+    let runs = match runs {
+        Ok(runs) => runs,
+        Err(code) => return code,
+    };
+    let runs: Box<[SonateTextRun]> = runs
+        .into_iter()
+        .map(|run| SonateTextRun {
+            id: run.id,
+            // Text nodes can't contain NUL when created through the C API; drop any that do.
+            text: CString::new(run.text.replace('\0', ""))
+                .unwrap_or_default()
+                .into_raw(),
+            x: run.x,
+            y: run.y,
+            width: run.width,
+            height: run.height,
+        })
+        .collect();
+
+    unsafe {
+        *out_count = runs.len();
+        *out_runs = Box::into_raw(runs) as *mut SonateTextRun;
+    }
+    0
+}
+
+/// Release runs returned by sonate_extract_text
+///
+/// # Safety
+/// `runs` and `count` must come from one successful sonate_extract_text call, and the
+/// runs must not be used afterwards. Null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_free_text_runs(runs: *mut SonateTextRun, count: usize) {
+    if runs.is_null() {
+        return;
+    }
+
+    let runs = unsafe { Box::from_raw(std::ptr::slice_from_raw_parts_mut(runs, count)) };
+    for run in runs.iter() {
+        drop(unsafe { CString::from_raw(run.text) });
+    }
+}
+
+/// Get how long the engine's latest layout passes took, and painting the latest frames of
+/// its primary window
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `out_stats` - Receives the stats
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code
+///
+/// # Safety
+/// `out_stats` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sonate_get_stats(
+    handle: EngineHandle,
+    out_stats: *mut SonateStats,
+) -> c_int {
+    if out_stats.is_null() {
+        error!("engine {handle}: output pointer is null");
+        return SONATE_ERR_INVALID_ARGUMENT;
     }
 
-    let Some(engine) = get_engine(handle) else {
-        eprintln!("Engine handle not found");
-        return -1;
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
     };
 
-    let code = engine.lock().unwrap().run();
+    let stats = match engine.stats() {
+        Ok(stats) => stats,
+        Err(code) => return code,
+    };
+    unsafe {
+        *out_stats = SonateStats {
+            mean_layout_ms: stats.mean_layout_ms,
+            max_layout_ms: stats.max_layout_ms,
+            mean_paint_ms: stats.mean_paint_ms,
+            max_paint_ms: stats.max_paint_ms,
+            fps: stats.fps,
+            layout_nodes: stats.layout_nodes,
+        };
+    }
+    0
+}
+
+/// Set the function called with each diagnostic the engine reports: stylesheet errors,
+/// mutations of unknown nodes, invalid parents and the like. Repeats are rate-limited.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `callback` - Called on a background thread; null to stop receiving diagnostics
+/// * `user_data` - Passed to every call of `callback`
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_set_diagnostics_callback(
+    handle: EngineHandle,
+    callback: Option<SonateDiagnosticCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    let callback = callback.map(|callback| DiagnosticCallback {
+        callback,
+        user_data,
+    });
+
+    let mut callbacks = DIAGNOSTIC_CALLBACKS.lock().unwrap();
+    let subscribed = callbacks.insert(handle, callback).is_some();
+    drop(callbacks);
+
+    if subscribed {
+        return 0;
+    }
+    let code = engine.subscribe_diagnostics(Arc::new(move |diagnostic| {
+        deliver_diagnostic(handle, diagnostic)
+    }));
+    if code != 0 {
+        // Not subscribed, so a later call tries again.
+        DIAGNOSTIC_CALLBACKS.lock().unwrap().remove(&handle);
+    }
     code
 }
 
-/// Cleanup and destroy an engine instance
+fn deliver_diagnostic(handle: EngineHandle, diagnostic: sonate_common::Diagnostic) {
+    // Copied out so the callback may set another callback without deadlocking.
+    let Some(Some(callback)) = DIAGNOSTIC_CALLBACKS.lock().unwrap().get(&handle).copied() else {
+        return;
+    };
+
+    let code = CString::new(diagnostic.code).unwrap_or_default();
+    let message = CString::new(diagnostic.message.replace('\0', "")).unwrap_or_default();
+    let diagnostic = SonateDiagnostic {
+        severity: diagnostic.severity,
+        code: code.as_ptr(),
+        message: message.as_ptr(),
+        node_id: diagnostic.node_id.unwrap_or(0),
+        has_node: diagnostic.node_id.is_some(),
+        rule_index: diagnostic.rule_index.map_or(-1, |index| index as i64),
+    };
+
+    (callback.callback)(&diagnostic, callback.user_data);
+}
+
+/// Set the function called when the worker process of the engine exits without being
+/// destroyed. Does nothing for engines running in this process.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `callback` - Called on a background thread with whether the worker was restarted; null
+///   to not be told
+/// * `user_data` - Passed to every call of `callback`
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_set_worker_exit_callback(
+    handle: EngineHandle,
+    callback: Option<SonateWorkerExitCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    if let Err(err) = get_engine(handle) {
+        return err.report(handle);
+    }
+
+    let mut callbacks = WORKER_EXIT_CALLBACKS.lock().unwrap();
+    match callback {
+        Some(callback) => {
+            callbacks.insert(
+                handle,
+                WorkerExitCallback {
+                    callback,
+                    user_data,
+                },
+            );
+        }
+        None => {
+            callbacks.remove(&handle);
+        }
+    }
+    0
+}
+
+fn deliver_worker_exit(handle: EngineHandle, restarted: bool) {
+    // Copied out so the callback may set another callback without deadlocking.
+    let Some(callback) = WORKER_EXIT_CALLBACKS.lock().unwrap().get(&handle).copied() else {
+        return;
+    };
+    (callback.callback)(handle, restarted, callback.user_data);
+}
+
+/// Set the function called with each click in the engine's window that no event listener
+/// stopped. Takes effect also while `sonate_run` runs, and works the same for engines
+/// running in a worker process, whose clicks are sent back to this one.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `callback` - Called on the thread running `sonate_run`, during that call; null to not
+///   be told. The `ids` it gets are only valid until it returns. It may change and read the
+///   document, except of an engine in a worker process, whose calls fail with
+///   SONATE_ERR_REENTRANCY until `sonate_run` returns
+/// * `user_data` - Passed to every call of `callback`
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_set_click_callback(
+    handle: EngineHandle,
+    callback: Option<SonateClickCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    if let Err(err) = get_engine(handle) {
+        return err.report(handle);
+    }
+
+    let mut callbacks = CLICK_CALLBACKS.lock().unwrap();
+    match callback {
+        Some(callback) => {
+            callbacks.insert(
+                handle,
+                ClickCallback {
+                    callback,
+                    user_data,
+                },
+            );
+        }
+        None => {
+            callbacks.remove(&handle);
+        }
+    }
+    0
+}
+
+/// Set the function called with the clicks, key presses and resizes of the event loop, for
+/// `sonate_worker` to send them to the host. Like the click callback, it's called on the
+/// thread running `sonate_run`; null to stop calling it.
+#[no_mangle]
+pub extern "C" fn sonate_set_event_callback_internal(
+    handle: EngineHandle,
+    callback: Option<SonateEventCallback>,
+    user_data: *mut c_void,
+) -> c_int {
+    if let Err(err) = get_engine(handle) {
+        return err.report(handle);
+    }
+
+    let mut callbacks = EVENT_CALLBACKS.lock().unwrap();
+    match callback {
+        Some(callback) => {
+            callbacks.insert(
+                handle,
+                EventCallback {
+                    callback,
+                    user_data,
+                },
+            );
+        }
+        None => {
+            callbacks.remove(&handle);
+        }
+    }
+    0
+}
+
+/// Pass an event of the event loop to the callbacks set for it. `Closed` has none: it's when
+/// `sonate_run` returns.
+fn deliver_event(handle: EngineHandle, event: &sonate_common::WorkerEvent) {
+    use sonate_common::WorkerEvent;
+
+    // Copied out so the callbacks may set other callbacks without deadlocking.
+    let on_click = CLICK_CALLBACKS.lock().unwrap().get(&handle).copied();
+    let on_event = EVENT_CALLBACKS.lock().unwrap().get(&handle).copied();
+
+    if let (WorkerEvent::Click { x, y, ids }, Some(on_click)) = (event, on_click) {
+        (on_click.callback)(on_click.user_data, *x, *y, ids.as_ptr(), ids.len());
+    }
+    let Some(on_event) = on_event else {
+        return;
+    };
+    let mut key = None;
+    let event = match event {
+        WorkerEvent::Click { x, y, ids } => SonateEvent {
+            kind: SONATE_EVENT_CLICK,
+            x: *x,
+            y: *y,
+            ids: ids.as_ptr(),
+            id_count: ids.len(),
+            ..SonateEvent::empty()
+        },
+        WorkerEvent::Key {
+            key: name,
+            shift,
+            control,
+            alt,
+            meta,
+        } => {
+            let name = key.insert(CString::new(name.replace('\0', "")).unwrap_or_default());
+            SonateEvent {
+                kind: SONATE_EVENT_KEY,
+                key: name.as_ptr(),
+                shift: *shift,
+                control: *control,
+                alt: *alt,
+                meta: *meta,
+                ..SonateEvent::empty()
+            }
+        }
+        WorkerEvent::Resized { width, height } => SonateEvent {
+            kind: SONATE_EVENT_RESIZED,
+            x: *width,
+            y: *height,
+            ..SonateEvent::empty()
+        },
+        WorkerEvent::Closed => return,
+    };
+    (on_event.callback)(on_event.user_data, &event);
+}
+
+/// Restart the worker process once if it exits, and bring the new one to where the old one
+/// was: its stylesheets, nodes, attributes and text, and its diagnostics subscription.
+/// Changes are kept for this from when the engine is created. The call that finds the worker
+/// gone still returns SONATE_ERR_WORKER_DIED; later calls reach the new worker. Off until
+/// enabled, and off again after a restart. Does nothing for engines running in this process.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+/// * `enabled` - Whether to restart the worker the next time it exits
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_set_worker_restart(handle: EngineHandle, enabled: bool) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    engine.set_restart_on_exit(enabled);
+    0
+}
+
+/// Set how long calls wait for each reply of the worker process before they fail with
+/// SONATE_ERR_WORKER_TIMEOUT; 5 seconds until set. While `sonate_run` runs, it's how long
+/// the worker has to answer the pings sent every second. Does nothing for engines running
+/// in this process.
 ///
 /// # Arguments
 /// * `handle` - Engine handle returned from sonate_init
+/// * `timeout_ms` - The timeout in milliseconds; 0 to wait as long as the worker runs
 ///
 /// # Returns
-/// * 0 on success, -1 on error
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_set_ipc_timeout_ms(handle: EngineHandle, timeout_ms: u32) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    let timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms.into()));
+    engine.set_reply_timeout(timeout);
+    0
+}
+
+/// Check that the worker process of the engine still takes requests, e.g. before an
+/// expensive one. The worker answers also while it runs the event loop. For engines running
+/// in this process, checks that the thread applying changes didn't stop after a panic; a
+/// panicking change is dropped without stopping it.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+///
+/// # Returns
+/// * 0 if the worker answered, SONATE_ERR_WORKER_TIMEOUT if it didn't in time,
+///   SONATE_ERR_WORKER_DIED if it exited, SONATE_ERR_SHUTTING_DOWN if the thread applying
+///   changes stopped, or another `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when
+///   called on the wrong thread
+#[no_mangle]
+pub extern "C" fn sonate_ping(handle: EngineHandle) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    engine.ping()
+}
+
+/// Run the engine event loop (blocking).
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread; SONATE_ERR_WORKER_DIED right after the worker process exits, if it
+///   does while running
+#[no_mangle]
+pub extern "C" fn sonate_run(handle: EngineHandle) -> c_int {
+    let engine = match get_engine(handle) {
+        Ok(engine) => engine,
+        Err(err) => return err.report(handle),
+    };
+
+    // Not locked while it runs, so the callbacks may change the document.
+    let on_event = Box::new(move |event| deliver_event(handle, &event));
+    engine.run(on_event)
+}
+
+/// Close the window of a running engine, making sonate_run return 0. Unlike the other
+/// entry points, this may be called from any thread.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+///
+/// # Returns
+/// * 0 on success, also when no window is open, SONATE_ERR_UNKNOWN_HANDLE or
+///   SONATE_ERR_STALE_HANDLE for a handle of no engine
+#[no_mangle]
+pub extern "C" fn sonate_quit(handle: EngineHandle) -> c_int {
+    match ENGINE_INSTANCES.get(handle) {
+        Ok(instance) => (instance.quit)(),
+        Err(missing) => return LookupError::Missing(missing).report(handle),
+    }
+    0
+}
+
+/// Cleanup and destroy an engine instance. Waits for the engine's data thread to exit, and
+/// for its worker process, which is killed unless it exits within two seconds.
+///
+/// # Arguments
+/// * `handle` - Engine handle returned from sonate_init
+///
+/// # Returns
+/// * 0 on success, or a `SONATE_ERR_*` code such as SONATE_ERR_WRONG_THREAD when called on
+///   the wrong thread
 #[no_mangle]
 pub extern "C" fn sonate_destroy(handle: EngineHandle) -> c_int {
-    if handle == 0 {
-        eprintln!("Invalid engine handle");
-        return -1;
+    if let Err(err) = get_engine(handle) {
+        return err.report(handle);
     }
 
-    let engine = ENGINE_INSTANCES.lock().unwrap().remove(&handle);
-    let Some(engine) = engine else {
-        eprintln!("Engine handle not found");
-        return -1;
+    let instance = match ENGINE_INSTANCES.remove(handle) {
+        Ok(instance) => instance,
+        // Destroyed by another thread meanwhile.
+        Err(missing) => return LookupError::Missing(missing).report(handle),
     };
+    DIAGNOSTIC_CALLBACKS.lock().unwrap().remove(&handle);
+    WORKER_EXIT_CALLBACKS.lock().unwrap().remove(&handle);
+    CLICK_CALLBACKS.lock().unwrap().remove(&handle);
+    EVENT_CALLBACKS.lock().unwrap().remove(&handle);
 
-    let code = engine.lock().unwrap().destroy();
-    code
+    instance.engine.destroy()
 }
+
+#[cfg(test)]
+mod click_tests;
+
+#[cfg(test)]
+mod error_code_tests;
+
+#[cfg(test)]
+mod threading_tests;
+
+#[cfg(test)]
+mod tree_tests;
@@ -0,0 +1,121 @@
+//! Where the library's log lines go: to the callback set with `sonate_set_log_callback`, or
+//! else to standard error, filtered by `RUST_LOG` as `env_logger` does.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::sync::{LazyLock, Once, RwLock};
+
+/// Levels passed to the log callback and taken by `sonate_set_log_level`.
+pub const SONATE_LOG_OFF: c_int = 0;
+pub const SONATE_LOG_ERROR: c_int = 1;
+pub const SONATE_LOG_WARN: c_int = 2;
+pub const SONATE_LOG_INFO: c_int = 3;
+pub const SONATE_LOG_DEBUG: c_int = 4;
+pub const SONATE_LOG_TRACE: c_int = 5;
+
+/// Receives a log line, without a trailing newline. Called on whichever thread logged it:
+/// the host's, the engine's data thread or the worker's.
+pub type SonateLogCallback = extern "C" fn(level: c_int, message: *const c_char);
+
+struct Logger {
+    callback: RwLock<Option<SonateLogCallback>>,
+    /// The most detailed level passed to the callback.
+    callback_level: RwLock<LevelFilter>,
+    /// Used while no callback is set.
+    stderr: env_logger::Logger,
+}
+
+static LOGGER: LazyLock<Logger> = LazyLock::new(|| Logger {
+    callback: RwLock::new(None),
+    callback_level: RwLock::new(LevelFilter::Warn),
+    stderr: env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"))
+        .build(),
+});
+
+/// Make this library's logger the process's, unless the host installed its own through a
+/// Rust `log` crate it shares with us.
+pub(crate) fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        if log::set_logger(&*LOGGER).is_ok() {
+            LOGGER.update_max_level();
+        }
+    });
+}
+
+pub(crate) fn set_callback(callback: Option<SonateLogCallback>) {
+    init();
+    *LOGGER.callback.write().unwrap() = callback;
+    LOGGER.update_max_level();
+}
+
+/// Returns whether `level` is one of the `SONATE_LOG_*` levels.
+pub(crate) fn set_level(level: c_int) -> bool {
+    init();
+    let filter = match level {
+        SONATE_LOG_OFF => LevelFilter::Off,
+        SONATE_LOG_ERROR => LevelFilter::Error,
+        SONATE_LOG_WARN => LevelFilter::Warn,
+        SONATE_LOG_INFO => LevelFilter::Info,
+        SONATE_LOG_DEBUG => LevelFilter::Debug,
+        SONATE_LOG_TRACE => LevelFilter::Trace,
+        _ => return false,
+    };
+    *LOGGER.callback_level.write().unwrap() = filter;
+    LOGGER.update_max_level();
+    true
+}
+
+impl Logger {
+    fn max_level(&self) -> LevelFilter {
+        match *self.callback.read().unwrap() {
+            Some(_) => *self.callback_level.read().unwrap(),
+            None => self.stderr.filter(),
+        }
+    }
+
+    /// Let the `log` macros skip formatting lines nobody takes.
+    fn update_max_level(&self) {
+        log::set_max_level(self.max_level());
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match *self.callback.read().unwrap() {
+            Some(_) => metadata.level() <= *self.callback_level.read().unwrap(),
+            None => self.stderr.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        // Copied out, so the callback may log or set another callback.
+        let callback = *self.callback.read().unwrap();
+        let Some(callback) = callback else {
+            self.stderr.log(record);
+            return;
+        };
+        if record.level() > *self.callback_level.read().unwrap() {
+            return;
+        }
+
+        let message = record.args().to_string().replace('\0', "");
+        let message = CString::new(message).unwrap_or_default();
+        let level = match record.level() {
+            Level::Error => SONATE_LOG_ERROR,
+            Level::Warn => SONATE_LOG_WARN,
+            Level::Info => SONATE_LOG_INFO,
+            Level::Debug => SONATE_LOG_DEBUG,
+            Level::Trace => SONATE_LOG_TRACE,
+        };
+        callback(level, message.as_ptr());
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+    }
+}
+
+#[cfg(test)]
+mod logging_tests;
@@ -0,0 +1,35 @@
+use super::*;
+use crate::{sonate_run, sonate_set_log_callback, sonate_set_log_level};
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+static LINES: Mutex<Vec<(c_int, String)>> = Mutex::new(Vec::new());
+
+extern "C" fn keep(level: c_int, message: *const c_char) {
+    let message = unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned();
+    LINES.lock().unwrap().push((level, message));
+}
+
+#[test]
+fn errors_reach_the_host_callback_with_the_engine_handle() {
+    sonate_set_log_callback(Some(keep));
+    assert_eq!(sonate_set_log_level(SONATE_LOG_ERROR), 0);
+    assert_eq!(sonate_set_log_level(SONATE_LOG_TRACE + 1), -1);
+
+    // Never handed out, so no other test logs about it.
+    let handle = 0x7777;
+    assert_eq!(sonate_run(handle), crate::SONATE_ERR_UNKNOWN_HANDLE);
+    sonate_set_log_callback(None);
+    sonate_set_log_level(SONATE_LOG_WARN);
+
+    let prefix = format!("engine {handle}:");
+    let lines = LINES.lock().unwrap();
+    let ours: Vec<_> = lines
+        .iter()
+        .filter(|(_, message)| message.starts_with(&prefix))
+        .collect();
+    assert_eq!(ours.len(), 1, "{lines:?}");
+    assert_eq!(ours[0].0, SONATE_LOG_ERROR);
+}
@@ -0,0 +1,173 @@
+//! The engines behind the handles the C API hands out.
+//!
+//! A handle is a slot index in the low half of its bits and the slot's generation in the
+//! high half. Destroying an engine bumps the generation of its slot, so the handle of a
+//! destroyed engine stays invalid when the slot is reused. Looking up a handle only takes
+//! a read lock, which calls on other engines share; the write lock is only taken to create
+//! and destroy engines.
+
+use std::sync::{Arc, Mutex, RwLock};
+
+const INDEX_BITS: u32 = usize::BITS / 2;
+const INDEX_MASK: usize = (1 << INDEX_BITS) - 1;
+const GENERATION_MASK: usize = usize::MAX >> INDEX_BITS;
+
+/// Why a handle doesn't refer to an entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Missing {
+    /// The registry never handed it out.
+    Unknown,
+    /// Its entry was removed.
+    Stale,
+}
+
+struct Slot<T> {
+    generation: usize,
+    state: State<T>,
+}
+
+enum State<T> {
+    Free,
+    /// Handed out by `reserve`, not filled yet.
+    Reserved,
+    Used(Arc<T>),
+}
+
+pub(crate) struct Registry<T> {
+    slots: RwLock<Vec<Slot<T>>>,
+    /// Indices of slots whose entry was removed, reused before the slots grow.
+    free: Mutex<Vec<usize>>,
+}
+
+impl<T> Registry<T> {
+    pub const fn new() -> Self {
+        Self {
+            slots: RwLock::new(Vec::new()),
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Hand out a handle for an entry that is `fill`ed in later, or `release`d if it can't
+    /// be created. Until then, looking it up finds nothing.
+    pub fn reserve(&self) -> usize {
+        let reused = self.free.lock().unwrap().pop();
+        let mut slots = self.slots.write().unwrap();
+        let index = reused.unwrap_or_else(|| {
+            slots.push(Slot {
+                generation: 0,
+                state: State::Free,
+            });
+            slots.len() - 1
+        });
+        slots[index].state = State::Reserved;
+        encode(index, slots[index].generation)
+    }
+
+    pub fn fill(&self, handle: usize, value: T) {
+        let Some((index, generation)) = decode(handle) else {
+            return;
+        };
+        let mut slots = self.slots.write().unwrap();
+        if let Some(slot) = slots.get_mut(index) {
+            if slot.generation == generation && matches!(slot.state, State::Reserved) {
+                slot.state = State::Used(Arc::new(value));
+            }
+        }
+    }
+
+    /// Give back a reserved handle that was never filled.
+    pub fn release(&self, handle: usize) {
+        let Some((index, generation)) = decode(handle) else {
+            return;
+        };
+        let mut slots = self.slots.write().unwrap();
+        if let Some(slot) = slots.get_mut(index) {
+            if slot.generation == generation && matches!(slot.state, State::Reserved) {
+                slot.free();
+                drop(slots);
+                self.free.lock().unwrap().push(index);
+            }
+        }
+    }
+
+    /// Put an entry at a handle another registry handed out, as a worker process does
+    /// for the handles of the host's library.
+    pub fn insert_at(&self, handle: usize, value: T) {
+        let Some((index, generation)) = decode(handle) else {
+            return;
+        };
+        let mut slots = self.slots.write().unwrap();
+        while slots.len() <= index {
+            slots.push(Slot {
+                generation: 0,
+                state: State::Free,
+            });
+        }
+        slots[index] = Slot {
+            generation,
+            state: State::Used(Arc::new(value)),
+        };
+    }
+
+    pub fn get(&self, handle: usize) -> Result<Arc<T>, Missing> {
+        let (index, generation) = decode(handle).ok_or(Missing::Unknown)?;
+        let slots = self.slots.read().unwrap();
+        match slots.get(index).map(|slot| slot.lookup(generation)) {
+            Some(Ok(State::Used(value))) => Ok(Arc::clone(value)),
+            Some(Err(missing)) => Err(missing),
+            _ => Err(Missing::Unknown),
+        }
+    }
+
+    /// Take the entry out, making its handle stale.
+    pub fn remove(&self, handle: usize) -> Result<Arc<T>, Missing> {
+        let (index, generation) = decode(handle).ok_or(Missing::Unknown)?;
+        let mut slots = self.slots.write().unwrap();
+        let slot = slots.get_mut(index).ok_or(Missing::Unknown)?;
+        let State::Used(value) = slot.lookup(generation)? else {
+            return Err(Missing::Unknown);
+        };
+        let value = Arc::clone(value);
+        slot.free();
+        drop(slots);
+        self.free.lock().unwrap().push(index);
+        Ok(value)
+    }
+}
+
+impl<T> Slot<T> {
+    /// The state of the slot, if `generation` is its current one.
+    fn lookup(&self, generation: usize) -> Result<&State<T>, Missing> {
+        if generation == self.generation {
+            Ok(&self.state)
+        } else if is_older(generation, self.generation) {
+            Err(Missing::Stale)
+        } else {
+            Err(Missing::Unknown)
+        }
+    }
+
+    fn free(&mut self) {
+        self.state = State::Free;
+        self.generation = (self.generation + 1) & GENERATION_MASK;
+    }
+}
+
+/// Index 0 is never handed out, so the handle 0 stays an error.
+fn encode(index: usize, generation: usize) -> usize {
+    (generation << INDEX_BITS) | (index + 1)
+}
+
+fn decode(handle: usize) -> Option<(usize, usize)> {
+    let index = (handle & INDEX_MASK).checked_sub(1)?;
+    Some((index, handle >> INDEX_BITS))
+}
+
+/// Whether `generation` came before `current`, which generations count up to, wrapping
+/// around.
+fn is_older(generation: usize, current: usize) -> bool {
+    current.wrapping_sub(generation) & GENERATION_MASK <= GENERATION_MASK / 2
+}
+
+#[cfg(test)]
+mod registry_tests;
@@ -0,0 +1,80 @@
+use super::*;
+use std::thread;
+
+#[test]
+fn a_reused_slot_does_not_take_the_old_handle() {
+    let registry = Registry::new();
+    let first = registry.reserve();
+    registry.fill(first, "first");
+    assert_eq!(registry.remove(first).as_deref(), Ok(&"first"));
+
+    let second = registry.reserve();
+    registry.fill(second, "second");
+    assert_ne!(first, second);
+    assert_eq!(first & INDEX_MASK, second & INDEX_MASK);
+
+    assert_eq!(registry.get(first), Err(Missing::Stale));
+    assert_eq!(registry.remove(first), Err(Missing::Stale));
+    assert_eq!(registry.get(second).as_deref(), Ok(&"second"));
+}
+
+#[test]
+fn handles_never_handed_out_are_unknown() {
+    let registry = Registry::new();
+    let handle = registry.reserve();
+    registry.fill(handle, ());
+
+    assert_eq!(registry.get(0), Err(Missing::Unknown));
+    assert_eq!(registry.get(handle + 1), Err(Missing::Unknown));
+    assert_eq!(
+        registry.get(handle + (1 << INDEX_BITS)),
+        Err(Missing::Unknown)
+    );
+}
+
+#[test]
+fn reserved_handles_are_found_once_filled() {
+    let registry = Registry::new();
+    let released = registry.reserve();
+    assert_eq!(registry.get(released), Err(Missing::Unknown));
+    registry.release(released);
+    assert_eq!(registry.get(released), Err(Missing::Stale));
+
+    let handle = registry.reserve();
+    assert_ne!(handle, released);
+    registry.fill(handle, 7);
+    assert_eq!(registry.get(handle).as_deref(), Ok(&7));
+
+    // A fabricated handle of a free slot doesn't free it twice.
+    assert_eq!(registry.remove(handle), Ok(Arc::new(7)));
+    let free_slot = handle + (1 << INDEX_BITS);
+    assert_eq!(registry.remove(free_slot), Err(Missing::Unknown));
+    assert_eq!(registry.free.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn entries_can_be_put_at_handles_of_another_registry() {
+    let host = Registry::new();
+    host.fill(host.reserve(), ());
+    let handle = host.reserve();
+
+    let worker = Registry::new();
+    worker.insert_at(handle, "engine");
+    assert_eq!(worker.get(handle).as_deref(), Ok(&"engine"));
+    assert_eq!(worker.get(handle - 1), Err(Missing::Unknown));
+}
+
+#[test]
+fn lookups_do_not_wait_for_one_another() {
+    let registry = Arc::new(Registry::new());
+    let handle = registry.reserve();
+    registry.fill(handle, ());
+
+    // Another lookup holds the lock for as long as it likes.
+    let _lookup = registry.slots.read().unwrap();
+    let other = Arc::clone(&registry);
+    let found = thread::spawn(move || other.get(handle).is_ok())
+        .join()
+        .unwrap();
+    assert!(found);
+}
@@ -0,0 +1,89 @@
+use super::*;
+use std::ptr;
+use std::thread;
+
+#[test]
+fn strict_engine_rejects_calls_from_other_threads() {
+    let handle = sonate_init_strict(true);
+    assert_ne!(handle, 0);
+
+    let code = thread::spawn(move || {
+        let mut runs = ptr::null_mut();
+        let mut count = 0;
+        let extract = unsafe { sonate_extract_text(handle, 0, false, &mut runs, &mut count) };
+        (extract, sonate_run(handle), sonate_destroy(handle))
+    })
+    .join()
+    .unwrap();
+    assert_eq!(
+        code,
+        (
+            SONATE_ERR_WRONG_THREAD,
+            SONATE_ERR_WRONG_THREAD,
+            SONATE_ERR_WRONG_THREAD
+        )
+    );
+
+    // Still usable, and destroyable, from the thread that created it.
+    let css = CString::new(".label { font-size: 20px; }").unwrap();
+    assert_eq!(sonate_add_stylesheet(handle, css.as_ptr()), 0);
+    assert_eq!(sonate_root_id(handle), 0);
+    assert_eq!(sonate_destroy(handle), 0);
+}
+
+#[test]
+fn quit_works_from_any_thread() {
+    let handle = sonate_init_strict(true);
+    assert_ne!(handle, 0);
+
+    // No window is open, so there's nothing to close, but the call is accepted.
+    assert_eq!(
+        thread::spawn(move || sonate_quit(handle)).join().unwrap(),
+        0
+    );
+    assert_eq!(sonate_quit(handle), 0);
+
+    assert_eq!(sonate_destroy(handle), 0);
+    assert_eq!(sonate_quit(handle), SONATE_ERR_STALE_HANDLE);
+}
+
+#[test]
+fn destroyed_and_unknown_handles_are_told_apart() {
+    let handle = sonate_init(true);
+    assert_eq!(sonate_destroy(handle), 0);
+    let later = sonate_init(true);
+    assert_ne!(later, handle);
+
+    let css = CString::new(".label { color: red; }").unwrap();
+    assert_eq!(
+        sonate_add_stylesheet(handle, css.as_ptr()),
+        SONATE_ERR_STALE_HANDLE
+    );
+    assert_eq!(sonate_destroy(handle), SONATE_ERR_STALE_HANDLE);
+    assert_eq!(sonate_run(0), SONATE_ERR_UNKNOWN_HANDLE);
+    assert_eq!(sonate_add_stylesheet(later, css.as_ptr()), 0);
+    assert_eq!(sonate_destroy(later), 0);
+}
+
+#[test]
+fn engines_are_used_from_many_threads_at_once() {
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            thread::spawn(|| {
+                let handle = sonate_init(true);
+                assert_ne!(handle, 0);
+                let node = sonate_create_node(handle, 1, ptr::null());
+                let key = CString::new("class").unwrap();
+                for i in 0..1000 {
+                    let value = CString::new(format!("item-{i}")).unwrap();
+                    let code = sonate_set_attribute(handle, node, key.as_ptr(), value.as_ptr());
+                    assert_eq!(code, 0);
+                }
+                assert_eq!(sonate_destroy(handle), 0);
+            })
+        })
+        .collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
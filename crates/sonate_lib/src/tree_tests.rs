@@ -0,0 +1,93 @@
+use super::*;
+use std::ptr;
+
+/// The children of the node, asking with a buffer of `capacity` first.
+fn children(handle: EngineHandle, node_id: SonateId, capacity: usize) -> (Vec<SonateId>, usize) {
+    let mut ids = vec![0; capacity];
+    let mut count = 0;
+    let code = unsafe { sonate_children(handle, node_id, ids.as_mut_ptr(), capacity, &mut count) };
+    assert_eq!(code, 0);
+    ids.truncate(count.min(capacity));
+    (ids, count)
+}
+
+fn parent(handle: EngineHandle, node_id: SonateId) -> Option<SonateId> {
+    let (mut parent, mut has_parent) = (SonateId::MAX, true);
+    let code = unsafe { sonate_parent(handle, node_id, &mut parent, &mut has_parent) };
+    assert_eq!(code, 0);
+    has_parent.then_some(parent)
+}
+
+#[test]
+fn children_and_parents_can_be_read_back() {
+    let handle = sonate_init(true);
+    let root = sonate_root_id(handle);
+    for id in [1, 2, 3] {
+        sonate_create_node(handle, id, ptr::null());
+        sonate_set_parent(handle, root, id);
+    }
+    sonate_create_node(handle, 4, ptr::null());
+    sonate_set_parent(handle, 2, 4);
+    sonate_insert_before(handle, root, 3, 1);
+
+    assert_eq!(children(handle, root, 8), (vec![3, 1, 2], 3));
+    // A buffer too small gets the first ones, and how many there are.
+    assert_eq!(children(handle, root, 2), (vec![3, 1], 3));
+    assert_eq!(children(handle, root, 0), (vec![], 3));
+    assert_eq!(children(handle, 404, 8), (vec![], 0));
+
+    assert_eq!(parent(handle, 4), Some(2));
+    assert_eq!(parent(handle, 1), Some(root));
+    assert_eq!(parent(handle, root), None);
+    assert_eq!(parent(handle, 404), None);
+
+    let mut count = 0;
+    assert_eq!(
+        unsafe { sonate_children(handle, root, ptr::null_mut(), 1, &mut count) },
+        SONATE_ERR_INVALID_ARGUMENT
+    );
+    assert_eq!(sonate_destroy(handle), 0);
+}
+
+#[test]
+fn markup_loads_under_a_parent() {
+    let handle = sonate_init(true);
+    let root = sonate_root_id(handle);
+    let container = sonate_create_node(handle, 1, ptr::null());
+    sonate_set_parent(handle, root, container);
+
+    let markup = c"<b class='x'>one</b> two <i/>";
+    let mut ids = [0; 2];
+    let mut count = 0;
+    let code = unsafe {
+        sonate_load_markup(
+            handle,
+            container,
+            markup.as_ptr(),
+            ids.as_mut_ptr(),
+            2,
+            &mut count,
+        )
+    };
+    assert_eq!(code, 0);
+    assert_eq!(count, 3);
+    // The rest are the container's children.
+    let (children, _) = children(handle, container, 8);
+    assert_eq!(children[..2], ids);
+    assert_eq!(parent(handle, children[2]), Some(container));
+
+    let code = unsafe {
+        sonate_load_markup(
+            handle,
+            container,
+            c"<b>".as_ptr(),
+            ptr::null_mut(),
+            0,
+            &mut count,
+        )
+    };
+    assert_eq!(code, SONATE_ERR_MARKUP_SYNTAX);
+    assert_eq!(children.len(), 3);
+    assert_eq!(self::children(handle, container, 8).1, 3);
+    assert_eq!(sonate_destroy(handle), 0);
+}
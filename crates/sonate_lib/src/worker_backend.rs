@@ -1,185 +1,834 @@
-use crate::engine_backend::{EngineBackend, SonateId};
-use ipc_channel::ipc::{self, IpcOneShotServer, IpcSender};
+use crate::engine_backend::{DiagnosticSink, EngineBackend, EventSink, QuitHandle, SonateId};
+use ipc_channel::ipc::{self, IpcError, IpcOneShotServer, IpcReceiver, IpcSender, TryRecvError};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use sonate::{EngineError, WorkerErrorKind};
+use sonate_common::{
+    Bootstrap, Diagnostic, ErrorCode, Hello, WorkerEvent, WorkerReply, WorkerRequest,
+    PROTOCOL_VERSION,
+};
+use std::error::Error;
 use std::os::raw::c_int;
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
 
-pub struct WorkerBackend {
-    handle: usize,
+/// Returned when a channel can't be created or a reply can't be read.
+const DISCONNECTED: c_int = ErrorCode::WorkerDisconnected.ffi();
+
+/// Returned from the call that finds the worker gone on, unless it's restarted.
+const DIED: c_int = ErrorCode::WorkerDied.ffi();
+
+/// Returned when the worker doesn't reply within the timeout.
+const TIMEOUT: c_int = ErrorCode::WorkerTimeout.ffi();
+
+/// Returned when called from an event callback of `run`.
+const REENTRANCY: c_int = ErrorCode::Reentrancy.ffi();
+
+/// How often a wait for a reply checks that the worker is still running.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long the worker has to exit once asked to, before it's killed.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the worker has to reply, until set with `sonate_set_ipc_timeout_ms`.
+const DEFAULT_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the worker is pinged while it runs the event loop, whose reply only comes
+/// once the window is closed.
+const RUN_PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the events of the worker's event loop are passed on while it runs: at most
+/// this late.
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Told on a background thread that the worker exited, and whether it was restarted.
+pub type ExitNotifier = Arc<dyn Fn(bool) + Send + Sync>;
+
+/// Starts a worker process and waits for it to report in.
+type Spawner = Box<dyn Fn() -> Result<Worker, EngineError> + Send + Sync>;
+
+/// A worker process and the sender its requests go to.
+struct Worker {
     process: Child,
-    sender: IpcSender<sonate_common::WorkerRequest>,
+    sender: IpcSender<WorkerRequest>,
 }
 
-impl WorkerBackend {
-    pub fn new(handle: usize) -> std::io::Result<Self> {
+impl Worker {
+    fn spawn() -> Result<Self, EngineError> {
         // Worker connects back and sends an IpcSender that we can use to send requests.
-        let (server, server_name) =
-            IpcOneShotServer::<IpcSender<sonate_common::WorkerRequest>>::new()
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        let (server, server_name) = IpcOneShotServer::<Hello>::new()
+            .map_err(|e| EngineError::worker(WorkerErrorKind::Spawn, e))?;
+
+        let mut process = spawn_worker("ipc_channel", &server_name)
+            .map_err(|e| EngineError::worker(WorkerErrorKind::Spawn, e))?;
+
+        match accept_bootstrap(server) {
+            Ok(sender) => Ok(Self { process, sender }),
+            Err(e) => {
+                let _ = process.kill();
+                let _ = process.wait();
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Ask the worker to exit, and kill it unless it does within `SHUTDOWN_TIMEOUT`.
+impl Drop for Worker {
+    fn drop(&mut self) {
+        if self.sender.send(WorkerRequest::Shutdown).is_ok() {
+            let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+            while Instant::now() < deadline {
+                match self.process.try_wait() {
+                    Ok(Some(_)) => return,
+                    Ok(None) => thread::sleep(EXIT_POLL_INTERVAL),
+                    Err(_) => break,
+                }
+            }
+        }
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
 
-        let process = spawn_worker("ipc_channel", &server_name)?;
+/// A change the worker took, kept to bring a restarted worker to the same document.
+enum Change {
+    /// One made by a request without a reply.
+    Request(WorkerRequest),
+    InsertBefore {
+        parent_id: SonateId,
+        child_id: SonateId,
+        reference_id: SonateId,
+    },
+    MoveChild {
+        parent_id: SonateId,
+        child_id: SonateId,
+        new_index: u64,
+    },
+    /// Made again in order, the markup gets the ids it got before.
+    LoadMarkup { parent_id: SonateId, markup: String },
+    RegisterFont {
+        family: Option<String>,
+        data: Vec<u8>,
+    },
+}
 
-        let (_rx, sender) = server
-            .accept()
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+impl Change {
+    /// The request that makes the change again. Its reply isn't read: the change was
+    /// taken before.
+    fn request(&self, handle: u64) -> std::io::Result<WorkerRequest> {
+        Ok(match *self {
+            Change::Request(ref request) => request.clone(),
+            Change::InsertBefore {
+                parent_id,
+                child_id,
+                reference_id,
+            } => WorkerRequest::InsertBefore {
+                handle,
+                parent_id,
+                child_id,
+                reference_id,
+                reply_to: ipc::channel()?.0,
+            },
+            Change::MoveChild {
+                parent_id,
+                child_id,
+                new_index,
+            } => WorkerRequest::MoveChild {
+                handle,
+                parent_id,
+                child_id,
+                new_index,
+                reply_to: ipc::channel()?.0,
+            },
+            Change::LoadMarkup {
+                parent_id,
+                ref markup,
+            } => WorkerRequest::LoadMarkup {
+                handle,
+                parent_id,
+                markup: markup.clone(),
+                reply_to: ipc::channel()?.0,
+            },
+            Change::RegisterFont {
+                ref family,
+                ref data,
+            } => WorkerRequest::RegisterFont {
+                handle,
+                family: family.clone(),
+                data: data.clone(),
+                reply_to: ipc::channel()?.0,
+            },
+        })
+    }
+}
 
+struct State {
+    /// `None` once the worker has exited, unless it was restarted.
+    worker: Option<Worker>,
+    /// Every change the worker took, in order.
+    journal: Vec<Change>,
+    diagnostics: Option<DiagnosticSink>,
+    /// Whether to restart the worker the next time it exits.
+    restart: bool,
+    /// How long to wait for replies; `None` to wait as long as the worker runs.
+    reply_timeout: Option<Duration>,
+}
+
+pub struct WorkerBackend {
+    handle: usize,
+    state: Mutex<State>,
+    /// Reaches the worker while it runs the event loop, which blocks its request loop.
+    quit: Arc<Mutex<Option<IpcSender<()>>>>,
+    /// The thread passing on the events of `run`, while it does.
+    runner: Mutex<Option<ThreadId>>,
+    on_exit: ExitNotifier,
+    spawn: Spawner,
+    /// Given to every worker started, for the events of its event loop. Locked only as the
+    /// channel's ends can't be shared.
+    events_tx: Mutex<IpcSender<WorkerEvent>>,
+    events: Mutex<IpcReceiver<WorkerEvent>>,
+}
+
+impl WorkerBackend {
+    pub fn new(handle: usize, on_exit: ExitNotifier) -> Result<Self, EngineError> {
+        Self::with_spawner(handle, on_exit, Box::new(Worker::spawn))
+    }
+
+    fn with_spawner(
+        handle: usize,
+        on_exit: ExitNotifier,
+        spawn: Spawner,
+    ) -> Result<Self, EngineError> {
+        let (events_tx, events) =
+            ipc::channel().map_err(|e| EngineError::worker(WorkerErrorKind::Spawn, e))?;
         let backend = Self {
             handle,
-            process,
-            sender,
+            state: Mutex::new(State {
+                worker: None,
+                journal: Vec::new(),
+                diagnostics: None,
+                restart: false,
+                reply_timeout: Some(DEFAULT_REPLY_TIMEOUT),
+            }),
+            quit: Arc::new(Mutex::new(None)),
+            runner: Mutex::new(None),
+            on_exit,
+            spawn,
+            events_tx: Mutex::new(events_tx),
+            events: Mutex::new(events),
         };
-
-        backend.init_internal();
+        let worker = backend.start()?;
+        backend.state.lock().unwrap().worker = Some(worker);
         Ok(backend)
     }
 
-    fn init_internal(&self) {
-        if let Err(e) = self
+    /// Start a worker and have it create the engine.
+    fn start(&self) -> Result<Worker, EngineError> {
+        let worker = (self.spawn)()?;
+        worker
             .sender
-            .send(sonate_common::WorkerRequest::InitInternal {
+            .send(WorkerRequest::InitInternal {
                 handle: self.handle as u64,
+                events: self.events_tx.lock().unwrap().clone(),
             })
-        {
-            eprintln!("Failed to send InitInternal to worker: {e}");
+            .map_err(|e| EngineError::worker(WorkerErrorKind::Disconnected, e))?;
+        Ok(worker)
+    }
+
+    /// The state, waiting for other calls to finish with it; unless called from an event
+    /// callback of `run`, which holds it, as the worker takes no requests until its window
+    /// closes.
+    fn state(&self) -> Result<MutexGuard<'_, State>, c_int> {
+        if *self.runner.lock().unwrap() == Some(thread::current().id()) {
+            error!("engine {}: called back while running", self.handle);
+            return Err(REENTRANCY);
         }
+        Ok(self.state.lock().unwrap())
+    }
+
+    fn channel<T>(&self) -> Result<(IpcSender<T>, IpcReceiver<T>), c_int>
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+    {
+        ipc::channel().map_err(|e| {
+            error!("engine {}: failed to create channel: {e}", self.handle);
+            DISCONNECTED
+        })
     }
 
-    fn shutdown(&self) {
-        let _ = self.sender.send(sonate_common::WorkerRequest::Shutdown);
+    /// Send a request, unless the worker has exited.
+    fn send(&self, state: &mut State, name: &str, request: WorkerRequest) -> Result<(), c_int> {
+        let Some(worker) = &mut state.worker else {
+            return Err(DIED);
+        };
+        if let Ok(Some(status)) = worker.process.try_wait() {
+            return Err(self.died(state, format!("exited ({status})")));
+        }
+        if let Err(e) = worker.sender.send(request) {
+            return Err(self.died(state, format!("did not take {name}: {e}")));
+        }
+        Ok(())
+    }
+
+    /// Wait up to `timeout` for a reply, checking every so often that the worker still
+    /// runs: the reply channel doesn't always break when the worker dies.
+    fn wait<T>(
+        &self,
+        state: &mut State,
+        name: &str,
+        reply: &IpcReceiver<T>,
+        timeout: Option<Duration>,
+    ) -> Result<T, c_int>
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+    {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let poll = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return Err(TIMEOUT);
+                    }
+                    left.min(EXIT_POLL_INTERVAL)
+                }
+                None => EXIT_POLL_INTERVAL,
+            };
+            match reply.try_recv_timeout(poll) {
+                Ok(value) => return Ok(value),
+                Err(TryRecvError::Empty) => {
+                    let Some(worker) = &mut state.worker else {
+                        return Err(DIED);
+                    };
+                    if let Ok(Some(status)) = worker.process.try_wait() {
+                        return Err(self.died(state, format!("exited ({status})")));
+                    }
+                }
+                Err(TryRecvError::IpcError(IpcError::Disconnected)) => {
+                    return Err(self.died(state, format!("dropped the reply to {name}")));
+                }
+                Err(TryRecvError::IpcError(e)) => {
+                    error!(
+                        "engine {}: failed to receive {name} response: {e}",
+                        self.handle
+                    );
+                    return Err(DISCONNECTED);
+                }
+            }
+        }
+    }
+
+    /// Wait for a reply within the reply timeout.
+    fn wait_in_time<T>(
+        &self,
+        state: &mut State,
+        name: &str,
+        reply: &IpcReceiver<T>,
+    ) -> Result<T, c_int>
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+    {
+        let timeout = state.reply_timeout;
+        let value = self.wait(state, name, reply, timeout);
+        if value.as_ref().is_err_and(|&code| code == TIMEOUT) {
+            error!(
+                "engine {}: worker did not reply to {name} within {timeout:?}",
+                self.handle
+            );
+        }
+        value
+    }
+
+    /// Send a request with a channel for its reply, and wait for the reply.
+    fn call<T>(
+        &self,
+        state: &mut State,
+        name: &str,
+        request: impl FnOnce(IpcSender<T>) -> WorkerRequest,
+    ) -> Result<T, c_int>
+    where
+        T: for<'de> Deserialize<'de> + Serialize,
+    {
+        let (reply_tx, reply_rx) = self.channel()?;
+        self.send(state, name, request(reply_tx))?;
+        self.wait_in_time(state, name, &reply_rx)
+    }
+
+    /// The next event of a worker's event loop that wasn't passed on yet, if one came.
+    pub(crate) fn poll_event(&self) -> Option<WorkerEvent> {
+        self.events.lock().unwrap().try_recv().ok()
+    }
+
+    fn ping_worker(&self, state: &mut State) -> Result<(), c_int> {
+        self.call(state, "Ping", |reply_to| WorkerRequest::Ping { reply_to })
+    }
+
+    /// Send a change without a reply, and keep it if it was sent.
+    fn change(&self, name: &str, request: WorkerRequest) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        match self.send(&mut state, name, request.clone()) {
+            Ok(()) => {
+                state.journal.push(Change::Request(request));
+                0
+            }
+            Err(code) => code,
+        }
+    }
+
+    /// The code for the reply to a change, which is kept if the worker took it.
+    fn record(state: &mut State, reply: Result<WorkerReply, c_int>, change: Change) -> c_int {
+        match reply {
+            Ok(Ok(())) => {
+                state.journal.push(change);
+                0
+            }
+            Ok(Err(code)) => code.ffi(),
+            Err(code) => code,
+        }
+    }
+
+    /// Give up on the worker, and restart it if that was asked for. Returns the code for
+    /// the call that found out.
+    fn died(&self, state: &mut State, why: String) -> c_int {
+        // Dropping it stops the process, in case only the channel broke.
+        state.worker = None;
+        error!("engine {}: worker process {why}", self.handle);
+
+        let restarted = std::mem::take(&mut state.restart) && self.restart(state);
+        // On another thread, so the host may call the engine from the callback.
+        let on_exit = Arc::clone(&self.on_exit);
+        thread::spawn(move || on_exit(restarted));
+        DIED
+    }
+
+    /// Start a new worker and bring it to where the old one was. Returns whether it worked.
+    fn restart(&self, state: &mut State) -> bool {
+        let restored = self
+            .start()
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|worker| {
+                self.restore(&worker, state)?;
+                Ok(worker)
+            });
+        match restored {
+            Ok(worker) => {
+                info!(
+                    "engine {}: restarted the worker with {} changes",
+                    self.handle,
+                    state.journal.len()
+                );
+                state.worker = Some(worker);
+                true
+            }
+            Err(e) => {
+                error!("engine {}: failed to restart the worker: {e}", self.handle);
+                false
+            }
+        }
+    }
+
+    /// Replay the journal to a new worker and subscribe it to diagnostics if the old one was.
+    fn restore(&self, worker: &Worker, state: &State) -> Result<(), Box<dyn Error>> {
+        let handle = self.handle as u64;
+        for change in &state.journal {
+            worker.sender.send(change.request(handle)?)?;
+        }
+        if let Some(sink) = &state.diagnostics {
+            let (sink_tx, sink_rx) = ipc::channel()?;
+            worker.sender.send(WorkerRequest::SubscribeDiagnostics {
+                handle,
+                sink: sink_tx,
+            })?;
+            forward_diagnostics(sink_rx, Arc::clone(sink));
+        }
+        Ok(())
     }
 }
 
 impl EngineBackend for WorkerBackend {
-    fn add_stylesheet(&self, css: String) {
-        if let Err(e) = self
-            .sender
-            .send(sonate_common::WorkerRequest::AddStylesheet {
+    fn add_stylesheet(&self, css: String) -> c_int {
+        self.change(
+            "AddStylesheet",
+            WorkerRequest::AddStylesheet {
                 handle: self.handle as u64,
                 css,
-            })
-        {
-            eprintln!("Failed to send AddStylesheet to worker: {e}");
-        }
+            },
+        )
     }
 
-    fn create_node(&self, node_id: SonateId, text: Option<String>) {
-        if let Err(e) = self.sender.send(sonate_common::WorkerRequest::CreateNode {
-            handle: self.handle as u64,
-            node_id,
-            text,
-        }) {
-            eprintln!("Failed to send CreateNode to worker: {e}");
-        }
+    fn register_font(&self, family: Option<String>, data: Vec<u8>) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let reply = self.call(&mut state, "RegisterFont", |reply_to| {
+            WorkerRequest::RegisterFont {
+                handle: self.handle as u64,
+                family: family.clone(),
+                data: data.clone(),
+                reply_to,
+            }
+        });
+        Self::record(&mut state, reply, Change::RegisterFont { family, data })
     }
 
-    fn set_parent(&self, parent_id: SonateId, child_id: SonateId) {
-        if let Err(e) = self.sender.send(sonate_common::WorkerRequest::SetParent {
-            handle: self.handle as u64,
+    fn create_node(&self, node_id: SonateId, text: Option<String>) -> c_int {
+        self.change(
+            "CreateNode",
+            WorkerRequest::CreateNode {
+                handle: self.handle as u64,
+                node_id,
+                text,
+            },
+        )
+    }
+
+    fn create_element(&self, node_id: SonateId, tag: String, text: Option<String>) -> c_int {
+        self.change(
+            "CreateElement",
+            WorkerRequest::CreateElement {
+                handle: self.handle as u64,
+                node_id,
+                tag,
+                text,
+            },
+        )
+    }
+
+    fn set_parent(&self, parent_id: SonateId, child_id: SonateId) -> c_int {
+        self.change(
+            "SetParent",
+            WorkerRequest::SetParent {
+                handle: self.handle as u64,
+                parent_id,
+                child_id,
+            },
+        )
+    }
+
+    fn insert_before(
+        &self,
+        parent_id: SonateId,
+        child_id: SonateId,
+        reference_id: SonateId,
+    ) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let reply = self.call(&mut state, "InsertBefore", |reply_to| {
+            WorkerRequest::InsertBefore {
+                handle: self.handle as u64,
+                parent_id,
+                child_id,
+                reference_id,
+                reply_to,
+            }
+        });
+        let change = Change::InsertBefore {
             parent_id,
             child_id,
-        }) {
-            eprintln!("Failed to send SetParent to worker: {e}");
-        }
+            reference_id,
+        };
+        Self::record(&mut state, reply, change)
     }
 
-    fn set_attribute(&self, node_id: SonateId, key: String, value: String) {
-        if let Err(e) = self
-            .sender
-            .send(sonate_common::WorkerRequest::SetAttribute {
+    fn move_child(&self, parent_id: SonateId, child_id: SonateId, new_index: usize) -> c_int {
+        let new_index = new_index as u64;
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let reply = self.call(&mut state, "MoveChild", |reply_to| {
+            WorkerRequest::MoveChild {
+                handle: self.handle as u64,
+                parent_id,
+                child_id,
+                new_index,
+                reply_to,
+            }
+        });
+        let change = Change::MoveChild {
+            parent_id,
+            child_id,
+            new_index,
+        };
+        Self::record(&mut state, reply, change)
+    }
+
+    fn set_attribute(&self, node_id: SonateId, key: String, value: String) -> c_int {
+        self.change(
+            "SetAttribute",
+            WorkerRequest::SetAttribute {
                 handle: self.handle as u64,
                 node_id,
                 key,
                 value,
-            })
-        {
-            eprintln!("Failed to send SetAttribute to worker: {e}");
-        }
+            },
+        )
     }
 
-    fn root_id(&self) -> SonateId {
-        let (reply_tx, reply_rx) = match ipc::channel::<u64>() {
-            Ok(ch) => ch,
-            Err(e) => {
-                eprintln!("Failed to create reply channel: {e}");
-                return 0;
+    fn set_text(&self, node_id: SonateId, text: Option<String>) -> c_int {
+        self.change(
+            "SetText",
+            WorkerRequest::SetText {
+                handle: self.handle as u64,
+                node_id,
+                text,
+            },
+        )
+    }
+
+    fn load_markup(&self, parent_id: SonateId, markup: String) -> Result<Vec<SonateId>, c_int> {
+        let mut state = self.state()?;
+        let reply = self.call(&mut state, "LoadMarkup", |reply_to| {
+            WorkerRequest::LoadMarkup {
+                handle: self.handle as u64,
+                parent_id,
+                markup: markup.clone(),
+                reply_to,
             }
-        };
+        })?;
+        let ids = reply.map_err(ErrorCode::ffi)?;
+        state.journal.push(Change::LoadMarkup { parent_id, markup });
+        Ok(ids)
+    }
 
-        if let Err(e) = self.sender.send(sonate_common::WorkerRequest::RootId {
+    fn root_id(&self) -> Result<SonateId, c_int> {
+        let mut state = self.state()?;
+        self.call(&mut state, "RootId", |reply_to| WorkerRequest::RootId {
             handle: self.handle as u64,
-            reply_to: reply_tx,
-        }) {
-            eprintln!("Failed to send RootId to worker: {e}");
-            return 0;
-        }
+            reply_to,
+        })
+    }
 
-        match reply_rx.recv() {
-            Ok(id) => id,
-            Err(e) => {
-                eprintln!("Failed to receive RootId response: {e}");
-                0
+    fn children(&self, node_id: SonateId) -> Result<Vec<SonateId>, c_int> {
+        let mut state = self.state()?;
+        self.call(&mut state, "Children", |reply_to| WorkerRequest::Children {
+            handle: self.handle as u64,
+            node_id,
+            reply_to,
+        })
+    }
+
+    fn parent(&self, node_id: SonateId) -> Result<Option<SonateId>, c_int> {
+        let mut state = self.state()?;
+        self.call(&mut state, "Parent", |reply_to| WorkerRequest::Parent {
+            handle: self.handle as u64,
+            node_id,
+            reply_to,
+        })
+    }
+
+    fn extract_text(
+        &self,
+        root_id: SonateId,
+        by_line: bool,
+    ) -> Result<Vec<sonate_common::TextRun>, c_int> {
+        let mut state = self.state()?;
+        self.call(&mut state, "ExtractText", |reply_to| {
+            WorkerRequest::ExtractText {
+                handle: self.handle as u64,
+                root_id,
+                by_line,
+                reply_to,
             }
+        })
+    }
+
+    fn stats(&self) -> Result<sonate_common::Stats, c_int> {
+        let mut state = self.state()?;
+        self.call(&mut state, "Stats", |reply_to| WorkerRequest::Stats {
+            handle: self.handle as u64,
+            reply_to,
+        })
+    }
+
+    fn subscribe_diagnostics(&self, sink: DiagnosticSink) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let (sink_tx, sink_rx) = match self.channel::<Diagnostic>() {
+            Ok(ch) => ch,
+            Err(code) => return code,
+        };
+        let request = WorkerRequest::SubscribeDiagnostics {
+            handle: self.handle as u64,
+            sink: sink_tx,
+        };
+        if let Err(code) = self.send(&mut state, "SubscribeDiagnostics", request) {
+            return code;
+        }
+        state.diagnostics = Some(Arc::clone(&sink));
+        forward_diagnostics(sink_rx, sink);
+        0
+    }
+
+    fn set_restart_on_exit(&self, restart: bool) {
+        if let Ok(mut state) = self.state() {
+            state.restart = restart;
         }
     }
 
-    fn run(&self) -> c_int {
-        let (reply_tx, reply_rx) = match ipc::channel::<i32>() {
+    fn set_reply_timeout(&self, timeout: Option<Duration>) {
+        if let Ok(mut state) = self.state() {
+            state.reply_timeout = timeout;
+        }
+    }
+
+    fn ping(&self) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        match self.ping_worker(&mut state) {
+            Ok(()) => 0,
+            Err(code) => code,
+        }
+    }
+
+    fn run(&self, on_event: EventSink) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        let (reply_tx, reply_rx) = match self.channel::<WorkerReply>() {
             Ok(ch) => ch,
-            Err(e) => {
-                eprintln!("Failed to create reply channel: {e}");
-                return -1;
-            }
+            Err(code) => return code,
+        };
+        let (quit_to_tx, quit_to_rx) = match self.channel::<IpcSender<()>>() {
+            Ok(ch) => ch,
+            Err(code) => return code,
         };
 
-        if let Err(e) = self.sender.send(sonate_common::WorkerRequest::Run {
+        let request = WorkerRequest::Run {
             handle: self.handle as u64,
             reply_to: reply_tx,
-        }) {
-            eprintln!("Failed to send Run to worker: {e}");
-            return -1;
+            quit_to: quit_to_tx,
+        };
+        if let Err(code) = self.send(&mut state, "Run", request) {
+            return code;
         }
 
-        match reply_rx.recv() {
-            Ok(code) => code,
-            Err(e) => {
-                eprintln!("Failed to receive Run response: {e}");
-                -1
+        match self.wait_in_time(&mut state, "Run", &quit_to_rx) {
+            Ok(quit) => *self.quit.lock().unwrap() = Some(quit),
+            Err(code) => return code,
+        }
+        *self.runner.lock().unwrap() = Some(thread::current().id());
+
+        // Events are passed on here, on the thread that called `run`. However long the
+        // window is open, the worker has to answer pings in time.
+        let mut pinged = Instant::now();
+        let code = loop {
+            match self.wait(&mut state, "Run", &reply_rx, Some(EVENT_POLL_INTERVAL)) {
+                Ok(reply) => break ErrorCode::ffi_result(reply),
+                Err(TIMEOUT) => {
+                    while let Some(event) = self.poll_event() {
+                        on_event(event);
+                    }
+                    if pinged.elapsed() >= RUN_PING_INTERVAL {
+                        if let Err(code) = self.ping_worker(&mut state) {
+                            break code;
+                        }
+                        pinged = Instant::now();
+                    }
+                }
+                Err(code) => break code,
             }
+        };
+        // The worker sends what's left, such as `Closed`, before it replies.
+        while let Some(event) = self.poll_event() {
+            on_event(event);
         }
+        *self.runner.lock().unwrap() = None;
+        // Dropping the sender ends the worker's wait for a quit.
+        *self.quit.lock().unwrap() = None;
+        code
     }
 
-    fn destroy(&self) -> c_int {
-        let (reply_tx, reply_rx) = match ipc::channel::<i32>() {
-            Ok(ch) => ch,
-            Err(e) => {
-                eprintln!("Failed to create reply channel: {e}");
-                return -1;
+    fn quit_handle(&self) -> QuitHandle {
+        let quit = Arc::clone(&self.quit);
+        let handle = self.handle;
+        Arc::new(move || {
+            if let Some(sender) = quit.lock().unwrap().as_ref() {
+                if let Err(e) = sender.send(()) {
+                    error!("engine {handle}: failed to send Quit to worker: {e}");
+                }
             }
-        };
+        })
+    }
 
-        if let Err(e) = self.sender.send(sonate_common::WorkerRequest::Destroy {
+    fn destroy(&self) -> c_int {
+        let mut state = match self.state() {
+            Ok(state) => state,
+            Err(code) => return code,
+        };
+        match self.call(&mut state, "Destroy", |reply_to| WorkerRequest::Destroy {
             handle: self.handle as u64,
-            reply_to: reply_tx,
+            reply_to,
         }) {
-            eprintln!("Failed to send Destroy to worker: {e}");
-            return -1;
+            Ok(reply) => {
+                // The worker runs no other engine, so it's shut down before this returns.
+                state.restart = false;
+                state.worker = None;
+                ErrorCode::ffi_result(reply)
+            }
+            Err(code) => code,
         }
+    }
+}
 
-        match reply_rx.recv() {
-            Ok(code) => code,
-            Err(e) => {
-                eprintln!("Failed to receive Destroy response: {e}");
-                -1
-            }
+/// Pass the diagnostics of a worker to the sink until the worker exits.
+fn forward_diagnostics(diagnostics: IpcReceiver<Diagnostic>, sink: DiagnosticSink) {
+    thread::spawn(move || {
+        while let Ok(diagnostic) = diagnostics.recv() {
+            sink(diagnostic);
         }
+    });
+}
+
+/// Wait for the worker to report in: with the protocol it speaks, then with the sender
+/// requests go to, or with why it couldn't load the engine library.
+fn accept_bootstrap(
+    server: IpcOneShotServer<Hello>,
+) -> Result<IpcSender<WorkerRequest>, EngineError> {
+    let (rx, hello) = server
+        .accept()
+        .map_err(|e| EngineError::worker(WorkerErrorKind::Spawn, e.to_string()))?;
+    check_hello(&hello)?;
+    let bootstrap = rx
+        .to_opaque()
+        .to::<Bootstrap>()
+        .recv()
+        .map_err(|e| EngineError::worker(WorkerErrorKind::Spawn, e.to_string()))?;
+    match bootstrap {
+        Bootstrap::Ready(sender) => Ok(sender),
+        Bootstrap::Failed(err) => Err(EngineError::worker(WorkerErrorKind::LoadLibrary, err)),
     }
 }
 
-impl Drop for WorkerBackend {
-    fn drop(&mut self) {
-        self.shutdown();
-        let _ = self.process.kill();
+/// Refuse a worker that speaks an incompatible protocol: what it sends could be misread.
+fn check_hello(hello: &Hello) -> Result<(), EngineError> {
+    if hello.protocol_version.is_compatible_with(PROTOCOL_VERSION) {
+        return Ok(());
     }
+    Err(EngineError::worker(
+        WorkerErrorKind::VersionMismatch,
+        format!(
+            "worker {} speaks protocol {}, host {} speaks {PROTOCOL_VERSION}",
+            hello.crate_version,
+            hello.protocol_version,
+            env!("CARGO_PKG_VERSION"),
+        ),
+    ))
 }
 
 #[cfg(windows)]
@@ -190,7 +839,7 @@ const WORKER_FILE: &str = "sonate_worker";
 fn spawn_worker(method: &str, connection_key: &str) -> std::io::Result<Child> {
     let worker_path = resolve_worker_path().expect("Failed to resolve worker path");
 
-    println!("Running worker at {worker_path:?}");
+    info!("running worker at {worker_path:?}");
 
     Command::new(worker_path)
         .arg(method)
@@ -217,3 +866,6 @@ fn resolve_worker_path() -> Option<PathBuf> {
     // We do not do PATH lookup, so we return None
     None
 }
+
+#[cfg(test)]
+mod worker_backend_tests;
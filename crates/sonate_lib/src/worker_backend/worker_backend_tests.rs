@@ -0,0 +1,467 @@
+use super::accept_bootstrap;
+use ipc_channel::ipc::{self, IpcOneShotServer, IpcSender};
+use sonate_common::{
+    Bootstrap, ErrorCode, Hello, LoadAttempt, LoadError, LoadFailure, ProtocolVersion,
+    WorkerRequest, PROTOCOL_VERSION,
+};
+use std::error::Error;
+use std::thread;
+
+/// Stand in for the worker process: connect to the host's server, say `hello` and report
+/// `bootstrap`.
+fn fake_worker(
+    hello: Hello,
+    bootstrap: impl FnOnce() -> Bootstrap + Send + 'static,
+) -> IpcOneShotServer<Hello> {
+    let (server, name) = IpcOneShotServer::<Hello>::new().unwrap();
+    thread::spawn(move || {
+        let sender = IpcSender::<Hello>::connect(name).unwrap();
+        sender.send(hello).unwrap();
+        // The host may have hung up.
+        let _ = sender.to_opaque().to::<Bootstrap>().send(bootstrap());
+    });
+    server
+}
+
+#[test]
+fn load_failures_reach_the_host_with_every_candidate() {
+    let server = fake_worker(Hello::current(), || {
+        Bootstrap::Failed(LoadError {
+            attempts: vec![
+                LoadAttempt {
+                    path: "/opt/app/libsonate.so".to_owned(),
+                    failure: LoadFailure::Missing,
+                },
+                LoadAttempt {
+                    path: "/opt/app/libsonate-x86_64-unknown-linux-gnu.so".to_owned(),
+                    failure: LoadFailure::Unloadable("wrong ELF class: ELFCLASS32".to_owned()),
+                },
+                LoadAttempt {
+                    path: "/srv/libsonate.so".to_owned(),
+                    failure: LoadFailure::AbiMismatch {
+                        expected: 2,
+                        found: 1,
+                    },
+                },
+            ],
+        })
+    });
+
+    let err = accept_bootstrap(server).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::WorkerLoadLibrary);
+    let message = err.source().unwrap().to_string();
+    assert_eq!(
+        message,
+        "worker failed to load engine library:\n  \
+         /opt/app/libsonate.so not found\n  \
+         /opt/app/libsonate-x86_64-unknown-linux-gnu.so could not be loaded: \
+         wrong ELF class: ELFCLASS32\n  \
+         /srv/libsonate.so has ABI version 1, expected 2"
+    );
+}
+
+#[test]
+fn a_loaded_worker_hands_over_its_request_sender() {
+    let (requests, received) = ipc::channel::<WorkerRequest>().unwrap();
+    let server = fake_worker(Hello::current(), move || Bootstrap::Ready(requests));
+
+    let sender = accept_bootstrap(server).unwrap();
+    sender.send(WorkerRequest::Shutdown).unwrap();
+    assert!(matches!(received.recv().unwrap(), WorkerRequest::Shutdown));
+}
+
+#[test]
+fn a_worker_of_another_protocol_is_refused() {
+    let (requests, _received) = ipc::channel::<WorkerRequest>().unwrap();
+    let hello = Hello {
+        protocol_version: ProtocolVersion {
+            major: PROTOCOL_VERSION.major + 1,
+            minor: 0,
+        },
+        crate_version: "9.9.9".to_owned(),
+    };
+    let server = fake_worker(hello, move || Bootstrap::Ready(requests));
+
+    let err = accept_bootstrap(server).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::WorkerVersionMismatch);
+    let message = err.source().unwrap().to_string();
+    assert_eq!(
+        message,
+        format!(
+            "worker 9.9.9 speaks protocol {}.0, host {} speaks {PROTOCOL_VERSION}",
+            PROTOCOL_VERSION.major + 1,
+            env!("CARGO_PKG_VERSION"),
+        )
+    );
+}
+
+#[test]
+fn a_worker_of_another_minor_version_is_taken() {
+    let (requests, _received) = ipc::channel::<WorkerRequest>().unwrap();
+    let hello = Hello {
+        protocol_version: ProtocolVersion {
+            minor: PROTOCOL_VERSION.minor + 1,
+            ..PROTOCOL_VERSION
+        },
+        crate_version: "0.0.0".to_owned(),
+    };
+    let server = fake_worker(hello, move || Bootstrap::Ready(requests));
+    assert!(accept_bootstrap(server).is_ok());
+}
+
+/// Supervision of fake workers, which are `cat` processes killed with `kill`. They exit
+/// when asked to shut down, as their input is closed then.
+#[cfg(unix)]
+mod supervision {
+    use super::super::{ExitNotifier, Spawner, Worker, WorkerBackend, SHUTDOWN_TIMEOUT};
+    use crate::engine_backend::EngineBackend;
+    use ipc_channel::ipc;
+    use sonate_common::{ErrorCode, WorkerEvent, WorkerRequest};
+    use std::process::{Command, Stdio};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// A backend on fake workers: each a `cat` process with a thread in this process
+    /// taking its requests. Every change is taken, except moves, which are refused; text is
+    /// never extracted and runs never end, after a click.
+    struct Fake {
+        backend: WorkerBackend,
+        /// The requests each started worker received, by name, except pings.
+        received: Arc<Mutex<Vec<Vec<String>>>>,
+        pids: Arc<Mutex<Vec<u32>>>,
+        /// Whether the workers were restarted, for each exit they were told of.
+        exits: mpsc::Receiver<bool>,
+        /// Stops the workers answering pings.
+        wedged: Arc<AtomicBool>,
+        /// Stops the workers exiting when asked to.
+        stubborn: Arc<AtomicBool>,
+    }
+
+    impl Fake {
+        fn new() -> Self {
+            let received = Arc::default();
+            let pids = Arc::default();
+            let wedged = Arc::default();
+            let stubborn = Arc::default();
+            let (exits_tx, exits) = mpsc::channel();
+            let exits_tx = Mutex::new(exits_tx);
+            let on_exit: ExitNotifier = Arc::new(move |restarted| {
+                let _ = exits_tx.lock().unwrap().send(restarted);
+            });
+            let spawner = fake_spawner(&received, &pids, &wedged, &stubborn);
+            Self {
+                backend: WorkerBackend::with_spawner(7, on_exit, spawner).unwrap(),
+                received,
+                pids,
+                exits,
+                wedged,
+                stubborn,
+            }
+        }
+
+        fn kill(&self, worker: usize) {
+            let pid = self.pids.lock().unwrap()[worker];
+            kill(pid);
+        }
+    }
+
+    fn fake_spawner(
+        received: &Arc<Mutex<Vec<Vec<String>>>>,
+        pids: &Arc<Mutex<Vec<u32>>>,
+        wedged: &Arc<AtomicBool>,
+        stubborn: &Arc<AtomicBool>,
+    ) -> Spawner {
+        let (received, pids) = (received.clone(), pids.clone());
+        let (wedged, stubborn) = (wedged.clone(), stubborn.clone());
+        Box::new(move || {
+            let mut process = Command::new("cat").stdin(Stdio::piped()).spawn().unwrap();
+            pids.lock().unwrap().push(process.id());
+            let mut input = process.stdin.take();
+            let (sender, requests) = ipc::channel::<WorkerRequest>().unwrap();
+            let (received, wedged) = (Arc::clone(&received), Arc::clone(&wedged));
+            let stubborn = Arc::clone(&stubborn);
+            let worker = {
+                let mut received = received.lock().unwrap();
+                received.push(Vec::new());
+                received.len() - 1
+            };
+            thread::spawn(move || {
+                let (mut running, mut unanswered) = (Vec::new(), Vec::new());
+                let mut events = None;
+                while let Ok(request) = requests.recv() {
+                    let name = format!("{request:?}");
+                    let name = name.split([' ', '{']).next().unwrap().to_owned();
+                    // Before the reply, which the test may check the requests after.
+                    if !matches!(request, WorkerRequest::Ping { .. }) {
+                        received.lock().unwrap()[worker].push(name);
+                    }
+                    match request {
+                        WorkerRequest::Ping { reply_to } => {
+                            if wedged.load(Ordering::SeqCst) {
+                                unanswered.push(WorkerRequest::Ping { reply_to });
+                            } else {
+                                let _ = reply_to.send(());
+                            }
+                        }
+                        WorkerRequest::InitInternal { events: sender, .. } => {
+                            events = Some(sender);
+                        }
+                        WorkerRequest::InsertBefore { reply_to, .. }
+                        | WorkerRequest::RegisterFont { reply_to, .. } => {
+                            let _ = reply_to.send(Ok(()));
+                        }
+                        WorkerRequest::MoveChild { reply_to, .. } => {
+                            let _ = reply_to.send(Err(ErrorCode::DocumentNotAChild));
+                        }
+                        WorkerRequest::RootId { reply_to, .. } => {
+                            let _ = reply_to.send(0);
+                        }
+                        WorkerRequest::Destroy { reply_to, .. } => {
+                            let _ = reply_to.send(Ok(()));
+                        }
+                        WorkerRequest::Shutdown if !stubborn.load(Ordering::SeqCst) => {
+                            drop(input.take());
+                        }
+                        WorkerRequest::Run {
+                            reply_to, quit_to, ..
+                        } => {
+                            let (quit, quit_rx) = ipc::channel().unwrap();
+                            let _ = quit_to.send(quit);
+                            if let Some(events) = &events {
+                                let _ = events.send(WorkerEvent::Click {
+                                    x: 1.0,
+                                    y: 2.0,
+                                    ids: vec![3, 0],
+                                });
+                            }
+                            running.push((reply_to, quit_rx));
+                        }
+                        // Kept, so their reply channels stay open.
+                        request => unanswered.push(request),
+                    }
+                }
+            });
+            Ok(Worker { process, sender })
+        })
+    }
+
+    fn kill(pid: u32) {
+        let status = Command::new("kill")
+            .args(["-9", &pid.to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn run_returns_soon_after_the_worker_is_killed() {
+        let fake = Fake::new();
+        let pid = fake.pids.lock().unwrap()[0];
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            kill(pid);
+        });
+
+        let started = Instant::now();
+        assert_eq!(
+            fake.backend.run(Box::new(|_| {})),
+            ErrorCode::WorkerDied.ffi()
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(!fake.exits.recv_timeout(Duration::from_secs(5)).unwrap());
+
+        // Every later call fails the same way, without starting another worker.
+        let backend = &fake.backend;
+        assert_eq!(
+            backend.add_stylesheet("div {}".to_owned()),
+            ErrorCode::WorkerDied.ffi()
+        );
+        assert_eq!(backend.root_id(), Err(ErrorCode::WorkerDied.ffi()));
+        assert_eq!(backend.ping(), ErrorCode::WorkerDied.ffi());
+        assert_eq!(backend.destroy(), ErrorCode::WorkerDied.ffi());
+        assert_eq!(fake.pids.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn events_of_the_worker_reach_the_thread_that_runs_it() {
+        let fake = Fake::new();
+        let pid = fake.pids.lock().unwrap()[0];
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            kill(pid);
+        });
+
+        let clicks = Arc::new(Mutex::new(Vec::new()));
+        let on_click = {
+            let clicks = Arc::clone(&clicks);
+            let runner = thread::current().id();
+            Box::new(move |event| {
+                assert_eq!(thread::current().id(), runner);
+                clicks.lock().unwrap().push(event);
+            })
+        };
+        assert_eq!(fake.backend.run(on_click), ErrorCode::WorkerDied.ffi());
+        let click = WorkerEvent::Click {
+            x: 1.0,
+            y: 2.0,
+            ids: vec![3, 0],
+        };
+        assert_eq!(*clicks.lock().unwrap(), [click]);
+        assert_eq!(fake.backend.poll_event(), None);
+    }
+
+    #[test]
+    fn calls_from_the_events_of_a_run_are_refused() {
+        let fake = Arc::new(Fake::new());
+        let pid = fake.pids.lock().unwrap()[0];
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            kill(pid);
+        });
+
+        let codes = Arc::new(Mutex::new(Vec::new()));
+        let on_click = {
+            let (fake, codes) = (Arc::clone(&fake), Arc::clone(&codes));
+            Box::new(move |_| {
+                codes
+                    .lock()
+                    .unwrap()
+                    .push(fake.backend.create_node(5, None));
+            })
+        };
+        // Rather than waiting for the state `run` holds.
+        assert_eq!(fake.backend.run(on_click), ErrorCode::WorkerDied.ffi());
+        assert_eq!(*codes.lock().unwrap(), [ErrorCode::Reentrancy.ffi()]);
+        assert!(!fake.received.lock().unwrap()[0].contains(&"CreateNode".to_owned()));
+    }
+
+    #[test]
+    fn calls_from_two_threads_at_once_wait_for_each_other() {
+        let fake = Fake::new();
+        let backend = &fake.backend;
+        let codes = thread::scope(|scope| {
+            let pings = scope.spawn(|| (0..50).map(|_| backend.ping()).collect::<Vec<_>>());
+            let nodes: Vec<_> = (1..=50).map(|id| backend.create_node(id, None)).collect();
+            [pings.join().unwrap(), nodes].concat()
+        });
+        assert_eq!(codes, [0; 100]);
+    }
+
+    #[test]
+    fn a_restarted_worker_gets_the_taken_changes_again() {
+        let fake = Fake::new();
+        let backend = &fake.backend;
+        backend.set_restart_on_exit(true);
+        assert_eq!(backend.add_stylesheet("div {}".to_owned()), 0);
+        assert_eq!(backend.register_font(None, vec![0; 4]), 0);
+        assert_eq!(backend.create_node(1, None), 0);
+        assert_eq!(backend.set_parent(0, 1), 0);
+        assert_eq!(backend.create_node(2, Some("a".to_owned())), 0);
+        assert_eq!(backend.insert_before(0, 2, 1), 0);
+        assert_eq!(
+            backend.move_child(0, 2, 1),
+            ErrorCode::DocumentNotAChild.ffi()
+        );
+        assert_eq!(
+            backend.set_attribute(1, "class".to_owned(), "x".to_owned()),
+            0
+        );
+        assert_eq!(backend.set_text(2, Some("b".to_owned())), 0);
+        assert_eq!(backend.subscribe_diagnostics(Arc::new(|_| {})), 0);
+
+        fake.kill(0);
+        // The call that finds out fails; the exit is told with the restart.
+        assert_eq!(backend.run(Box::new(|_| {})), ErrorCode::WorkerDied.ffi());
+        assert!(fake.exits.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert_eq!(backend.root_id(), Ok(0));
+
+        // The refused move and the reads aren't replayed.
+        assert_eq!(
+            fake.received.lock().unwrap()[1],
+            [
+                "InitInternal",
+                "AddStylesheet",
+                "RegisterFont",
+                "CreateNode",
+                "SetParent",
+                "CreateNode",
+                "InsertBefore",
+                "SetAttribute",
+                "SetText",
+                "SubscribeDiagnostics",
+                "RootId",
+            ]
+        );
+
+        // Restarts are one-shot.
+        fake.kill(1);
+        assert_eq!(backend.run(Box::new(|_| {})), ErrorCode::WorkerDied.ffi());
+        assert!(!fake.exits.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert_eq!(fake.pids.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn a_stuck_reply_times_out_and_later_calls_still_work() {
+        let fake = Fake::new();
+        let backend = &fake.backend;
+        backend.set_reply_timeout(Some(Duration::from_millis(200)));
+
+        let started = Instant::now();
+        assert_eq!(
+            backend.extract_text(0, false).unwrap_err(),
+            ErrorCode::WorkerTimeout.ffi()
+        );
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        assert_eq!(backend.ping(), 0);
+        assert_eq!(backend.root_id(), Ok(0));
+        fake.wedged.store(true, Ordering::SeqCst);
+        assert_eq!(backend.ping(), ErrorCode::WorkerTimeout.ffi());
+    }
+
+    #[test]
+    fn run_gives_up_on_a_worker_that_stops_answering_pings() {
+        let fake = Fake::new();
+        fake.backend
+            .set_reply_timeout(Some(Duration::from_millis(200)));
+        let wedged = Arc::clone(&fake.wedged);
+        thread::spawn(move || {
+            // Past the first ping, which is answered.
+            thread::sleep(Duration::from_millis(1500));
+            wedged.store(true, Ordering::SeqCst);
+        });
+
+        let started = Instant::now();
+        assert_eq!(
+            fake.backend.run(Box::new(|_| {})),
+            ErrorCode::WorkerTimeout.ffi()
+        );
+        assert!(started.elapsed() > Duration::from_secs(2));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn destroy_shuts_the_worker_down() {
+        let fake = Fake::new();
+        assert_eq!(fake.backend.destroy(), 0);
+        let received = fake.received.lock().unwrap()[0].clone();
+        assert_eq!(received[received.len() - 2..], ["Destroy", "Shutdown"]);
+        assert_eq!(fake.backend.ping(), ErrorCode::WorkerDied.ffi());
+    }
+
+    #[test]
+    fn a_worker_that_does_not_exit_when_asked_is_killed() {
+        let fake = Fake::new();
+        fake.stubborn.store(true, Ordering::SeqCst);
+
+        let started = Instant::now();
+        assert_eq!(fake.backend.destroy(), 0);
+        assert!(started.elapsed() >= SHUTDOWN_TIMEOUT);
+        let pid = fake.pids.lock().unwrap()[0].to_string();
+        let alive = Command::new("kill").args(["-0", &pid]).status().unwrap();
+        assert!(!alive.success());
+    }
+}
@@ -2,63 +2,166 @@ extern crate proc_macro;
 
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Field, Fields, GenericParam, Ident, Type,
+};
 
-#[proc_macro_derive(MergeProperties, attributes(merge_by_method_call))]
+/// Derives `merge(&mut self, other: &Self)`, which copies what `other` sets over `self`, one
+/// field at a time:
+///
+/// - `Option` fields are copied where `other`'s is `Some`, as are fields marked
+///   `#[merge_if_set]`.
+/// - Fields marked `#[merge_by_method_call]` are merged with their own `merge`.
+/// - Other fields are always copied.
+///
+/// A `#[merge_if_set]` field whose type is one of the struct's type parameters makes `merge`
+/// a method of the struct with that parameter wrapped in an `Option`: on `Directional<T>`,
+/// whose sides are `T`, it's implemented for `Directional<Option<T>>`.
+#[proc_macro_derive(MergeProperties, attributes(merge_by_method_call, merge_if_set))]
 pub fn derive_merge_properties(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
-    let name = &input.ident;
+    derive(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum Strategy {
+    MethodCall,
+    IfSet,
+    Overwrite,
+}
 
-    let fields = if let Data::Struct(data) = &input.data {
-        if let Fields::Named(fields) = &data.fields {
-            &fields.named
-        } else {
-            unimplemented!()
+fn derive(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "MergeProperties needs named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "MergeProperties only derives for structs",
+            ))
         }
-    } else {
-        unimplemented!()
     };
 
-    let merge_fields = fields.iter().map(|field| {
+    // Type parameters the `#[merge_if_set]` fields are, which `merge` takes as `Option`s.
+    let mut optional_params: Vec<&Ident> = Vec::new();
+    let mut merge_fields = Vec::new();
+    for field in fields {
         let field_name = &field.ident;
-
-        let merge_by_method_call = field
-            .attrs
-            .iter()
-            .any(|attr| attr.path().is_ident("merge_by_method_call"));
-
-        if merge_by_method_call {
-            return quote! {
+        let merge = match strategy(field)? {
+            Strategy::MethodCall => quote! {
                 self.#field_name.merge(&other.#field_name);
-            };
-        }
-
-        let is_option = if let syn::Type::Path(ty) = &field.ty {
-            ty.path.segments.len() == 1 && ty.path.segments[0].ident == "Option"
-        } else {
-            false
-        };
-
-        if is_option {
-            quote! {
-                if let Some(value) = &other.#field_name {
-                    self.#field_name = Some(value.clone());
+            },
+            Strategy::IfSet => {
+                if let Some(param) = type_param(&field.ty, input) {
+                    if !optional_params.contains(&param) {
+                        optional_params.push(param);
+                    }
+                } else if !is_option(&field.ty) {
+                    return Err(syn::Error::new_spanned(
+                        &field.ty,
+                        "#[merge_if_set] needs an `Option` or a type parameter",
+                    ));
+                }
+                quote! {
+                    if let Some(value) = &other.#field_name {
+                        self.#field_name = Some(value.clone());
+                    }
                 }
             }
-        } else {
-            quote! {
+            Strategy::Overwrite => quote! {
                 self.#field_name = other.#field_name.clone();
-            }
+            },
+        };
+        merge_fields.push(merge);
+    }
+
+    let mut generics = input.generics.clone();
+    for param in &optional_params {
+        generics
+            .make_where_clause()
+            .predicates
+            .push(parse_quote!(#param: Clone));
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let type_args = input.generics.params.iter().map(|param| match param {
+        GenericParam::Type(ty) if optional_params.contains(&&ty.ident) => {
+            let ident = &ty.ident;
+            quote!(Option<#ident>)
+        }
+        GenericParam::Type(ty) => {
+            let ident = &ty.ident;
+            quote!(#ident)
+        }
+        GenericParam::Lifetime(lifetime) => {
+            let lifetime = &lifetime.lifetime;
+            quote!(#lifetime)
+        }
+        GenericParam::Const(constant) => {
+            let ident = &constant.ident;
+            quote!(#ident)
         }
     });
+    let type_args = if input.generics.params.is_empty() {
+        quote!()
+    } else {
+        quote!(<#(#type_args),*>)
+    };
 
-    let expanded = quote! {
-        impl #name {
+    Ok(quote! {
+        impl #impl_generics #name #type_args #where_clause {
             pub fn merge(&mut self, other: &Self) {
                 #(#merge_fields)*
             }
         }
-    };
+    })
+}
+
+fn strategy(field: &Field) -> syn::Result<Strategy> {
+    let method_call = field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("merge_by_method_call"));
+    let if_set = field
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("merge_if_set"));
+    match (method_call, if_set) {
+        (true, true) => Err(syn::Error::new_spanned(
+            &field.ident,
+            "#[merge_by_method_call] and #[merge_if_set] can't be combined",
+        )),
+        (true, false) => Ok(Strategy::MethodCall),
+        (false, true) => Ok(Strategy::IfSet),
+        (false, false) if is_option(&field.ty) => Ok(Strategy::IfSet),
+        (false, false) => Ok(Strategy::Overwrite),
+    }
+}
 
-    TokenStream::from(expanded)
+fn is_option(ty: &Type) -> bool {
+    if let Type::Path(ty) = ty {
+        ty.path.segments.len() == 1 && ty.path.segments[0].ident == "Option"
+    } else {
+        false
+    }
+}
+
+/// The type parameter of `input` that `ty` is, if any.
+fn type_param<'a>(ty: &Type, input: &'a DeriveInput) -> Option<&'a Ident> {
+    let Type::Path(ty) = ty else {
+        return None;
+    };
+    let ident = ty.path.get_ident()?;
+    input
+        .generics
+        .type_params()
+        .find_map(|param| (param.ident == *ident && ty.qself.is_none()).then_some(&param.ident))
 }
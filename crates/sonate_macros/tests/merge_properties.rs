@@ -0,0 +1,98 @@
+use sonate_macros::MergeProperties;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Sides<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Clone> Sides<Option<T>> {
+    fn merge(&mut self, other: &Self) {
+        if let Some(value) = &other.start {
+            self.start = Some(value.clone());
+        }
+        if let Some(value) = &other.end {
+            self.end = Some(value.clone());
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, MergeProperties)]
+struct Properties {
+    kind: u8,
+    width: Option<u32>,
+    #[merge_if_set]
+    height: Option<u32>,
+    #[merge_by_method_call]
+    sides: Sides<Option<u32>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, MergeProperties)]
+struct Directional<T> {
+    #[merge_if_set]
+    top: T,
+    #[merge_if_set]
+    right: T,
+    #[merge_if_set]
+    bottom: T,
+    #[merge_if_set]
+    left: T,
+}
+
+#[test]
+fn options_merge_where_set_and_other_fields_are_overwritten() {
+    let mut properties = Properties {
+        kind: 1,
+        width: Some(10),
+        height: Some(20),
+        sides: Sides {
+            start: Some(1),
+            end: None,
+        },
+    };
+    properties.merge(&Properties {
+        sides: Sides {
+            start: None,
+            end: Some(2),
+        },
+        ..Default::default()
+    });
+    assert_eq!(
+        properties,
+        Properties {
+            kind: 0,
+            width: Some(10),
+            height: Some(20),
+            sides: Sides {
+                start: Some(1),
+                end: Some(2),
+            },
+        }
+    );
+}
+
+#[test]
+fn type_parameters_merge_as_options() {
+    let mut all = Directional {
+        top: Some(10),
+        right: Some(10),
+        bottom: Some(10),
+        left: Some(10),
+    };
+    let left = Directional {
+        left: Some(5),
+        ..Default::default()
+    };
+
+    let mut merged = all.clone();
+    merged.merge(&left);
+    assert_eq!(merged.top, Some(10));
+    assert_eq!(merged.left, Some(5));
+
+    let mut merged = left.clone();
+    merged.merge(&all);
+    assert_eq!(merged, all);
+
+    all.merge(&Directional::default());
+    assert_eq!(all.bottom, Some(10));
+}
@@ -0,0 +1,5 @@
+fn main() {
+    // Library names may carry the target triple, see `loader::candidates`.
+    let target = std::env::var("TARGET").expect("cargo sets TARGET for build scripts");
+    println!("cargo:rustc-env=SONATE_WORKER_TARGET={target}");
+}
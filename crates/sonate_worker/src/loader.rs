@@ -0,0 +1,97 @@
+use libloading::Library;
+use sonate_common::{LoadAttempt, LoadError, LoadFailure, ABI_VERSION};
+use std::path::{Path, PathBuf};
+
+type SonateAbiVersion = unsafe extern "C" fn() -> u32;
+
+/// Where the engine library may be, in the order they're tried: `SONATE_LIBRARY_PATH`, next
+/// to the worker under the platform's name and under names with the target triple, and the
+/// current directory.
+pub fn candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(path) = std::env::var_os("SONATE_LIBRARY_PATH") {
+        candidates.push(PathBuf::from(path));
+    }
+
+    let name = default_library_name();
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(PathBuf::from))
+    {
+        candidates.push(dir.join(&name));
+        for name in target_library_names() {
+            candidates.push(dir.join(name));
+        }
+    }
+
+    if let Ok(dir) = std::env::current_dir() {
+        candidates.push(dir.join(&name));
+    }
+    candidates
+}
+
+/// Load the first candidate that is a sonate library of the ABI version this worker was
+/// built against.
+pub fn load(candidates: &[PathBuf]) -> Result<Library, LoadError> {
+    let mut attempts = Vec::new();
+    for path in candidates {
+        match load_one(path) {
+            Ok(library) => return Ok(library),
+            Err(failure) => attempts.push(LoadAttempt {
+                path: path.display().to_string(),
+                failure,
+            }),
+        }
+    }
+    Err(LoadError { attempts })
+}
+
+fn load_one(path: &Path) -> Result<Library, LoadFailure> {
+    if !path.exists() {
+        return Err(LoadFailure::Missing);
+    }
+
+    // Loading runs the library's initializers; candidates are expected to be sonate builds.
+    let library =
+        unsafe { Library::new(path) }.map_err(|e| LoadFailure::Unloadable(e.to_string()))?;
+
+    let found = unsafe {
+        let abi_version = library
+            .get::<SonateAbiVersion>(b"sonate_abi_version\0")
+            .map_err(|_| LoadFailure::NoAbiVersion)?;
+        abi_version()
+    };
+    if found != ABI_VERSION {
+        return Err(LoadFailure::AbiMismatch {
+            expected: ABI_VERSION,
+            found,
+        });
+    }
+    Ok(library)
+}
+
+fn default_library_name() -> String {
+    if cfg!(target_os = "windows") {
+        "sonate.dll".to_string()
+    } else if cfg!(target_os = "macos") {
+        "libsonate.dylib".to_string()
+    } else {
+        "libsonate.so".to_string()
+    }
+}
+
+/// The platform's name with the target triple, as release artifacts are often renamed to.
+fn target_library_names() -> Vec<String> {
+    let target = env!("SONATE_WORKER_TARGET");
+    let (stem, extension) = default_library_name()
+        .rsplit_once('.')
+        .map(|(stem, extension)| (stem.to_owned(), extension.to_owned()))
+        .unwrap();
+    vec![
+        format!("{stem}-{target}.{extension}"),
+        format!("{stem}_{target}.{extension}"),
+    ]
+}
+
+#[cfg(test)]
+mod loader_tests;
@@ -0,0 +1,42 @@
+use super::{candidates, load};
+use sonate_common::LoadFailure;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("sonate_worker_{}_{name}", std::process::id()))
+}
+
+#[test]
+fn every_candidate_is_tried_and_its_failure_kept() {
+    let missing = temp_path("missing.so");
+    // Not a shared library at all, as a build for another platform looks to the loader.
+    let garbage = temp_path("garbage.so");
+    std::fs::write(&garbage, b"not a shared library").unwrap();
+
+    let err = load(&[missing.clone(), garbage.clone()]).unwrap_err();
+    std::fs::remove_file(&garbage).unwrap();
+
+    assert_eq!(err.attempts.len(), 2);
+    assert_eq!(err.attempts[0].path, missing.display().to_string());
+    assert_eq!(err.attempts[0].failure, LoadFailure::Missing);
+    assert_eq!(err.attempts[1].path, garbage.display().to_string());
+    assert!(
+        matches!(&err.attempts[1].failure, LoadFailure::Unloadable(reason) if !reason.is_empty()),
+        "{:?}",
+        err.attempts[1].failure
+    );
+
+    let message = err.to_string();
+    assert!(message.starts_with("worker failed to load engine library:"));
+    assert!(message.contains(&format!("{} not found", missing.display())));
+}
+
+#[test]
+fn candidates_include_names_with_the_target_triple() {
+    let all = candidates();
+    assert!(all.len() >= 3, "{all:?}");
+    let target = env!("SONATE_WORKER_TARGET");
+    assert!(all
+        .iter()
+        .any(|path| path.to_string_lossy().contains(target)));
+}
@@ -1,23 +1,156 @@
 use ipc_channel::ipc;
-use libloading::Library;
-use sonate_common::WorkerRequest;
+use ipc_channel::ipc::IpcSender;
+use log::{error, warn};
+use sonate_common::{
+    Bootstrap, Diagnostic, ErrorCode, Hello, Stats, TextRun, WorkerEvent, WorkerRequest,
+};
+use std::collections::HashMap;
 use std::env;
-use std::ffi::CString;
-use std::os::raw::c_char;
-use std::path::{Path, PathBuf};
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::mpsc;
+use std::thread;
+
+mod loader;
 
 type EngineHandle = usize;
 
 type SonateInitInternal = unsafe extern "C" fn(EngineHandle);
-type SonateAddStylesheet = unsafe extern "C" fn(EngineHandle, *const c_char);
+type SonateAddStylesheet = unsafe extern "C" fn(EngineHandle, *const c_char) -> c_int;
+type SonateRegisterFont =
+    unsafe extern "C" fn(EngineHandle, *const c_char, *const u8, usize) -> c_int;
 type SonateCreateNode = unsafe extern "C" fn(EngineHandle, u64, *const c_char) -> u64;
-type SonateSetParent = unsafe extern "C" fn(EngineHandle, u64, u64);
-type SonateSetAttribute = unsafe extern "C" fn(EngineHandle, u64, *const c_char, *const c_char);
+type SonateCreateElement =
+    unsafe extern "C" fn(EngineHandle, u64, *const c_char, *const c_char) -> u64;
+type SonateSetParent = unsafe extern "C" fn(EngineHandle, u64, u64) -> c_int;
+type SonateInsertBefore = unsafe extern "C" fn(EngineHandle, u64, u64, u64) -> c_int;
+type SonateMoveChild = unsafe extern "C" fn(EngineHandle, u64, u64, usize) -> c_int;
+type SonateSetAttribute =
+    unsafe extern "C" fn(EngineHandle, u64, *const c_char, *const c_char) -> c_int;
+type SonateSetText = unsafe extern "C" fn(EngineHandle, u64, *const c_char) -> c_int;
+type SonateLoadMarkup =
+    unsafe extern "C" fn(EngineHandle, u64, *const c_char, *mut u64, usize, *mut usize) -> i32;
 type SonateRootId = unsafe extern "C" fn(EngineHandle) -> u64;
+type SonateChildren = unsafe extern "C" fn(EngineHandle, u64, *mut u64, usize, *mut usize) -> i32;
+type SonateParent = unsafe extern "C" fn(EngineHandle, u64, *mut u64, *mut bool) -> i32;
+type SonateExtractText =
+    unsafe extern "C" fn(EngineHandle, u64, bool, *mut *mut SonateTextRun, *mut usize) -> i32;
+type SonateFreeTextRuns = unsafe extern "C" fn(*mut SonateTextRun, usize);
+type SonateGetStats = unsafe extern "C" fn(EngineHandle, *mut SonateStats) -> i32;
+type SonateDiagnosticCallback = extern "C" fn(*const SonateDiagnostic, *mut c_void);
+type SonateSetDiagnosticsCallback =
+    unsafe extern "C" fn(EngineHandle, Option<SonateDiagnosticCallback>, *mut c_void) -> i32;
+type SonateEventCallback = extern "C" fn(*mut c_void, *const SonateEvent);
+type SonateSetEventCallback =
+    unsafe extern "C" fn(EngineHandle, Option<SonateEventCallback>, *mut c_void) -> i32;
 type SonateRun = unsafe extern "C" fn(EngineHandle) -> i32;
+type SonateQuit = unsafe extern "C" fn(EngineHandle) -> i32;
 type SonateDestroy = unsafe extern "C" fn(EngineHandle) -> i32;
 
+/// Mirrors `SonateTextRun` in sonate_lib.
+#[repr(C)]
+struct SonateTextRun {
+    id: u64,
+    text: *mut c_char,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Mirrors `SonateStats` in sonate_lib.
+#[repr(C)]
+#[derive(Default)]
+struct SonateStats {
+    mean_layout_ms: f64,
+    max_layout_ms: f64,
+    mean_paint_ms: f64,
+    max_paint_ms: f64,
+    fps: f64,
+    layout_nodes: u64,
+}
+
+/// Mirrors `SonateDiagnostic` in sonate_lib.
+#[repr(C)]
+struct SonateDiagnostic {
+    severity: c_int,
+    code: *const c_char,
+    message: *const c_char,
+    node_id: u64,
+    has_node: bool,
+    rule_index: i64,
+}
+
+/// Sends diagnostics to the host; `user_data` is a leaked `IpcSender<Diagnostic>`.
+extern "C" fn forward_diagnostic(diagnostic: *const SonateDiagnostic, user_data: *mut c_void) {
+    let (diagnostic, sink) =
+        unsafe { (&*diagnostic, &*(user_data as *const IpcSender<Diagnostic>)) };
+    let _ = sink.send(Diagnostic {
+        severity: diagnostic.severity,
+        code: unsafe { CStr::from_ptr(diagnostic.code) }
+            .to_string_lossy()
+            .into_owned(),
+        message: unsafe { CStr::from_ptr(diagnostic.message) }
+            .to_string_lossy()
+            .into_owned(),
+        node_id: diagnostic.has_node.then_some(diagnostic.node_id),
+        rule_index: u64::try_from(diagnostic.rule_index).ok(),
+    });
+}
+
+/// Mirrors `SonateEvent` in sonate_lib.
+#[repr(C)]
+struct SonateEvent {
+    kind: c_int,
+    x: f64,
+    y: f64,
+    ids: *const u64,
+    id_count: usize,
+    key: *const c_char,
+    shift: bool,
+    control: bool,
+    alt: bool,
+    meta: bool,
+}
+
+const SONATE_EVENT_CLICK: c_int = 0;
+const SONATE_EVENT_KEY: c_int = 1;
+const SONATE_EVENT_RESIZED: c_int = 2;
+
+/// Sends events to the host; `user_data` is the engine's `IpcSender<WorkerEvent>`. Fails
+/// quietly once the host stopped listening: it's about to go away too.
+extern "C" fn forward_event(user_data: *mut c_void, event: *const SonateEvent) {
+    let (event, events) = unsafe { (&*event, &*(user_data as *const IpcSender<WorkerEvent>)) };
+    let event = match event.kind {
+        SONATE_EVENT_CLICK => WorkerEvent::Click {
+            x: event.x,
+            y: event.y,
+            ids: unsafe { std::slice::from_raw_parts(event.ids, event.id_count) }.to_vec(),
+        },
+        SONATE_EVENT_KEY => WorkerEvent::Key {
+            key: unsafe { CStr::from_ptr(event.key) }
+                .to_string_lossy()
+                .into_owned(),
+            shift: event.shift,
+            control: event.control,
+            alt: event.alt,
+            meta: event.meta,
+        },
+        SONATE_EVENT_RESIZED => WorkerEvent::Resized {
+            width: event.x,
+            height: event.y,
+        },
+        kind => {
+            warn!("worker: unknown event kind {kind}");
+            return;
+        }
+    };
+    let _ = events.send(event);
+}
+
 fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
     let args: Vec<String> = env::args().collect();
 
     // args[0] = exe
@@ -27,32 +160,40 @@ fn main() {
     let connection_key = args.get(2).map(|s| s.as_str()).unwrap_or("");
 
     if method != "ipc_channel" {
-        eprintln!("worker: unsupported method '{method}'");
+        error!("worker: unsupported method '{method}'");
         std::process::exit(2);
     }
 
     if connection_key.is_empty() {
-        eprintln!("worker: missing connection key");
+        error!("worker: missing connection key");
         std::process::exit(2);
     }
 
-    // Connect to the host's one-shot server and send back a channel sender.
-    let bootstrap = ipc::IpcSender::connect(connection_key.to_string())
+    // Connect to the host's one-shot server, say which protocol we speak, and send back a
+    // channel sender, or why the engine library couldn't be loaded. The host hangs up if it
+    // speaks another protocol.
+    let hello = ipc::IpcSender::<Hello>::connect(connection_key.to_string())
         .expect("worker: failed to connect to host");
-    let (tx, rx) = ipc::channel::<WorkerRequest>().expect("worker: failed to create channel");
-    bootstrap
-        .send(tx)
-        .expect("worker: failed to send channel sender to host");
+    hello
+        .send(Hello::current())
+        .expect("worker: failed to send hello to host");
+    let bootstrap = hello.to_opaque().to::<Bootstrap>();
 
     // Load sonate dynamic library once and keep it alive for the whole process.
-    let lib_path = resolve_library_path();
-    let lib = unsafe {
-        Library::new(&lib_path).unwrap_or_else(|e| {
-            eprintln!("worker: failed to load sonate library at {lib_path:?}: {e}");
+    let lib = match loader::load(&loader::candidates()) {
+        Ok(lib) => lib,
+        Err(err) => {
+            error!("{err}");
+            let _ = bootstrap.send(Bootstrap::Failed(err));
             std::process::exit(3);
-        })
+        }
     };
 
+    let (tx, rx) = ipc::channel::<WorkerRequest>().expect("worker: failed to create channel");
+    bootstrap
+        .send(Bootstrap::Ready(tx))
+        .expect("worker: failed to send channel sender to host");
+
     unsafe {
         let sonate_init_internal: libloading::Symbol<SonateInitInternal> = lib
             .get(b"sonate_init_internal\0")
@@ -60,46 +201,137 @@ fn main() {
         let sonate_add_stylesheet: libloading::Symbol<SonateAddStylesheet> = lib
             .get(b"sonate_add_stylesheet\0")
             .expect("worker: missing symbol sonate_add_stylesheet");
+        let sonate_register_font: libloading::Symbol<SonateRegisterFont> = lib
+            .get(b"sonate_register_font\0")
+            .expect("worker: missing symbol sonate_register_font");
         let sonate_create_node: libloading::Symbol<SonateCreateNode> = lib
             .get(b"sonate_create_node\0")
             .expect("worker: missing symbol sonate_create_node");
+        let sonate_create_element: libloading::Symbol<SonateCreateElement> = lib
+            .get(b"sonate_create_element\0")
+            .expect("worker: missing symbol sonate_create_element");
         let sonate_set_parent: libloading::Symbol<SonateSetParent> = lib
             .get(b"sonate_set_parent\0")
             .expect("worker: missing symbol sonate_set_parent");
+        let sonate_insert_before: libloading::Symbol<SonateInsertBefore> = lib
+            .get(b"sonate_insert_before\0")
+            .expect("worker: missing symbol sonate_insert_before");
+        let sonate_move_child: libloading::Symbol<SonateMoveChild> = lib
+            .get(b"sonate_move_child\0")
+            .expect("worker: missing symbol sonate_move_child");
         let sonate_set_attribute: libloading::Symbol<SonateSetAttribute> = lib
             .get(b"sonate_set_attribute\0")
             .expect("worker: missing symbol sonate_set_attribute");
+        let sonate_set_text: libloading::Symbol<SonateSetText> = lib
+            .get(b"sonate_set_text\0")
+            .expect("worker: missing symbol sonate_set_text");
+        let sonate_load_markup: libloading::Symbol<SonateLoadMarkup> = lib
+            .get(b"sonate_load_markup\0")
+            .expect("worker: missing symbol sonate_load_markup");
         let sonate_root_id: libloading::Symbol<SonateRootId> = lib
             .get(b"sonate_root_id\0")
             .expect("worker: missing symbol sonate_root_id");
+        let sonate_children: libloading::Symbol<SonateChildren> = lib
+            .get(b"sonate_children\0")
+            .expect("worker: missing symbol sonate_children");
+        let sonate_parent: libloading::Symbol<SonateParent> = lib
+            .get(b"sonate_parent\0")
+            .expect("worker: missing symbol sonate_parent");
+        let sonate_extract_text: libloading::Symbol<SonateExtractText> = lib
+            .get(b"sonate_extract_text\0")
+            .expect("worker: missing symbol sonate_extract_text");
+        let sonate_free_text_runs: libloading::Symbol<SonateFreeTextRuns> = lib
+            .get(b"sonate_free_text_runs\0")
+            .expect("worker: missing symbol sonate_free_text_runs");
+        let sonate_get_stats: libloading::Symbol<SonateGetStats> = lib
+            .get(b"sonate_get_stats\0")
+            .expect("worker: missing symbol sonate_get_stats");
+        let sonate_set_diagnostics_callback: libloading::Symbol<SonateSetDiagnosticsCallback> = lib
+            .get(b"sonate_set_diagnostics_callback\0")
+            .expect("worker: missing symbol sonate_set_diagnostics_callback");
+        let sonate_set_event_callback_internal: libloading::Symbol<SonateSetEventCallback> = lib
+            .get(b"sonate_set_event_callback_internal\0")
+            .expect("worker: missing symbol sonate_set_event_callback_internal");
         let sonate_run: libloading::Symbol<SonateRun> = lib
             .get(b"sonate_run\0")
             .expect("worker: missing symbol sonate_run");
+        let sonate_quit: SonateQuit = *lib
+            .get::<SonateQuit>(b"sonate_quit\0")
+            .expect("worker: missing symbol sonate_quit");
         let sonate_destroy: libloading::Symbol<SonateDestroy> = lib
             .get(b"sonate_destroy\0")
             .expect("worker: missing symbol sonate_destroy");
 
-        loop {
-            let msg = match rx.recv() {
-                Ok(m) => m,
+        // Requests are run on this thread, which the event loop must run on, and received
+        // on another, which answers pings meanwhile.
+        let (requests_tx, requests) = mpsc::channel();
+        thread::spawn(move || loop {
+            match rx.recv() {
+                Ok(WorkerRequest::Ping { reply_to }) => {
+                    let _ = reply_to.send(());
+                }
+                Ok(msg) => {
+                    if requests_tx.send(msg).is_err() {
+                        break;
+                    }
+                }
                 Err(e) => {
-                    eprintln!("worker: ipc receive error: {e}");
+                    error!("worker: ipc receive error: {e}");
                     break;
                 }
+            }
+        });
+
+        // The sender of each engine's events, which its event callback is given.
+        let mut event_senders: HashMap<u64, Box<IpcSender<WorkerEvent>>> = HashMap::new();
+
+        loop {
+            let Ok(msg) = requests.recv() else {
+                break;
             };
 
             match msg {
-                WorkerRequest::InitInternal { handle } => {
+                WorkerRequest::InitInternal { handle, events } => {
                     sonate_init_internal(handle as EngineHandle);
+                    // Boxed, so it stays where the callback was told it is.
+                    event_senders.insert(handle, Box::new(events));
+                    let events = &event_senders[&handle];
+                    sonate_set_event_callback_internal(
+                        handle as EngineHandle,
+                        Some(forward_event),
+                        &**events as *const IpcSender<WorkerEvent> as *mut c_void,
+                    );
                 }
                 WorkerRequest::AddStylesheet { handle, css } => match CString::new(css) {
                     Ok(c_css) => {
-                        sonate_add_stylesheet(handle as EngineHandle, c_css.as_ptr());
+                        let _ = sonate_add_stylesheet(handle as EngineHandle, c_css.as_ptr());
                     }
                     Err(_) => {
-                        eprintln!("worker: stylesheet contains interior NUL byte");
+                        warn!("engine {handle}: stylesheet contains interior NUL byte");
                     }
                 },
+                WorkerRequest::RegisterFont {
+                    handle,
+                    family,
+                    data,
+                    reply_to,
+                } => {
+                    let code = match family.map(CString::new).transpose() {
+                        Ok(c_family) => sonate_register_font(
+                            handle as EngineHandle,
+                            c_family
+                                .as_ref()
+                                .map_or(std::ptr::null(), |family| family.as_ptr()),
+                            data.as_ptr(),
+                            data.len(),
+                        ),
+                        Err(_) => {
+                            warn!("engine {handle}: font family contains interior NUL byte");
+                            ErrorCode::InvalidArgument.ffi()
+                        }
+                    };
+                    let _ = reply_to.send(ErrorCode::check(code));
+                }
                 WorkerRequest::CreateNode {
                     handle,
                     node_id,
@@ -122,17 +354,79 @@ fn main() {
                                 );
                             }
                             Err(_) => {
-                                eprintln!("worker: text content contains interior NUL byte");
+                                warn!("engine {handle}: text of node {node_id} contains interior NUL byte");
                             }
                         },
                     };
                 }
+                WorkerRequest::CreateElement {
+                    handle,
+                    node_id,
+                    tag,
+                    text,
+                } => {
+                    let c_tag = match CString::new(tag) {
+                        Ok(s) => s,
+                        Err(_) => {
+                            warn!(
+                                "engine {handle}: tag of node {node_id} contains interior NUL byte"
+                            );
+                            continue;
+                        }
+                    };
+                    let c_text = match text.map(CString::new).transpose() {
+                        Ok(s) => s,
+                        Err(_) => {
+                            warn!("engine {handle}: text of node {node_id} contains interior NUL byte");
+                            continue;
+                        }
+                    };
+
+                    let _ = sonate_create_element(
+                        handle as EngineHandle,
+                        node_id,
+                        c_tag.as_ptr(),
+                        c_text
+                            .as_ref()
+                            .map_or(std::ptr::null(), |text| text.as_ptr()),
+                    );
+                }
                 WorkerRequest::SetParent {
                     handle,
                     parent_id,
                     child_id,
                 } => {
-                    sonate_set_parent(handle as EngineHandle, parent_id, child_id);
+                    let _ = sonate_set_parent(handle as EngineHandle, parent_id, child_id);
+                }
+                WorkerRequest::InsertBefore {
+                    handle,
+                    parent_id,
+                    child_id,
+                    reference_id,
+                    reply_to,
+                } => {
+                    let code = sonate_insert_before(
+                        handle as EngineHandle,
+                        parent_id,
+                        child_id,
+                        reference_id,
+                    );
+                    let _ = reply_to.send(ErrorCode::check(code));
+                }
+                WorkerRequest::MoveChild {
+                    handle,
+                    parent_id,
+                    child_id,
+                    new_index,
+                    reply_to,
+                } => {
+                    let code = sonate_move_child(
+                        handle as EngineHandle,
+                        parent_id,
+                        child_id,
+                        new_index as usize,
+                    );
+                    let _ = reply_to.send(ErrorCode::check(code));
                 }
                 WorkerRequest::SetAttribute {
                     handle,
@@ -143,36 +437,196 @@ fn main() {
                     let c_key = match CString::new(key) {
                         Ok(s) => s,
                         Err(_) => {
-                            eprintln!("worker: attribute key contains interior NUL byte");
+                            warn!("engine {handle}: attribute key of node {node_id} contains interior NUL byte");
                             continue;
                         }
                     };
                     let c_value = match CString::new(value) {
                         Ok(s) => s,
                         Err(_) => {
-                            eprintln!("worker: attribute value contains interior NUL byte");
+                            warn!("engine {handle}: attribute value of node {node_id} contains interior NUL byte");
                             continue;
                         }
                     };
 
-                    sonate_set_attribute(
+                    let _ = sonate_set_attribute(
                         handle as EngineHandle,
                         node_id,
                         c_key.as_ptr(),
                         c_value.as_ptr(),
                     );
                 }
+                WorkerRequest::SetText {
+                    handle,
+                    node_id,
+                    text,
+                } => match text.map(CString::new).transpose() {
+                    Ok(c_text) => {
+                        let text_ptr = c_text.as_ref().map_or(std::ptr::null(), |s| s.as_ptr());
+                        let _ = sonate_set_text(handle as EngineHandle, node_id, text_ptr);
+                    }
+                    Err(_) => {
+                        warn!("engine {handle}: text of node {node_id} contains interior NUL byte");
+                    }
+                },
+                WorkerRequest::LoadMarkup {
+                    handle,
+                    parent_id,
+                    markup,
+                    reply_to,
+                } => {
+                    let Ok(c_markup) = CString::new(markup) else {
+                        warn!("engine {handle}: markup contains interior NUL byte");
+                        let _ = reply_to.send(Err(ErrorCode::InvalidArgument));
+                        continue;
+                    };
+                    // Each top-level node starts with `<`, or is text between them.
+                    let mut ids =
+                        vec![0; c_markup.as_bytes().iter().filter(|b| **b == b'<').count() + 1];
+                    let mut count = 0;
+                    let code = sonate_load_markup(
+                        handle as EngineHandle,
+                        parent_id,
+                        c_markup.as_ptr(),
+                        ids.as_mut_ptr(),
+                        ids.len(),
+                        &mut count,
+                    );
+                    ids.truncate(count);
+                    let _ = reply_to.send(ErrorCode::check(code).map(|()| ids));
+                }
                 WorkerRequest::RootId { handle, reply_to } => {
                     let id = sonate_root_id(handle as EngineHandle);
                     let _ = reply_to.send(id);
                 }
-                WorkerRequest::Run { handle, reply_to } => {
+                WorkerRequest::Children {
+                    handle,
+                    node_id,
+                    reply_to,
+                } => {
+                    let mut children = Vec::new();
+                    let mut count = 0;
+                    // Asks again with room for them all; nothing else changes the document
+                    // meanwhile, as requests run on this thread.
+                    loop {
+                        if sonate_children(
+                            handle as EngineHandle,
+                            node_id,
+                            children.as_mut_ptr(),
+                            children.len(),
+                            &mut count,
+                        ) != 0
+                        {
+                            count = 0;
+                            break;
+                        }
+                        if count <= children.len() {
+                            break;
+                        }
+                        children.resize(count, 0);
+                    }
+                    children.truncate(count);
+                    let _ = reply_to.send(children);
+                }
+                WorkerRequest::Parent {
+                    handle,
+                    node_id,
+                    reply_to,
+                } => {
+                    let mut parent = 0;
+                    let mut has_parent = false;
+                    let code = sonate_parent(
+                        handle as EngineHandle,
+                        node_id,
+                        &mut parent,
+                        &mut has_parent,
+                    );
+                    let _ = reply_to.send((code == 0 && has_parent).then_some(parent));
+                }
+                WorkerRequest::ExtractText {
+                    handle,
+                    root_id,
+                    by_line,
+                    reply_to,
+                } => {
+                    let mut runs_ptr = std::ptr::null_mut();
+                    let mut count = 0;
+                    let mut runs = Vec::new();
+                    if sonate_extract_text(
+                        handle as EngineHandle,
+                        root_id,
+                        by_line,
+                        &mut runs_ptr,
+                        &mut count,
+                    ) == 0
+                    {
+                        for run in std::slice::from_raw_parts(runs_ptr, count) {
+                            runs.push(TextRun {
+                                id: run.id,
+                                text: CStr::from_ptr(run.text).to_string_lossy().into_owned(),
+                                x: run.x,
+                                y: run.y,
+                                width: run.width,
+                                height: run.height,
+                            });
+                        }
+                        sonate_free_text_runs(runs_ptr, count);
+                    }
+                    let _ = reply_to.send(runs);
+                }
+                WorkerRequest::Stats { handle, reply_to } => {
+                    let mut stats = SonateStats::default();
+                    sonate_get_stats(handle as EngineHandle, &mut stats);
+                    let _ = reply_to.send(Stats {
+                        mean_layout_ms: stats.mean_layout_ms,
+                        max_layout_ms: stats.max_layout_ms,
+                        mean_paint_ms: stats.mean_paint_ms,
+                        max_paint_ms: stats.max_paint_ms,
+                        fps: stats.fps,
+                        layout_nodes: stats.layout_nodes,
+                    });
+                }
+                WorkerRequest::SubscribeDiagnostics { handle, sink } => {
+                    // Lives as long as the worker: the host subscribes once per engine.
+                    let sink = Box::into_raw(Box::new(sink));
+                    sonate_set_diagnostics_callback(
+                        handle as EngineHandle,
+                        Some(forward_diagnostic),
+                        sink as *mut c_void,
+                    );
+                }
+                WorkerRequest::Run {
+                    handle,
+                    reply_to,
+                    quit_to,
+                } => {
+                    // The event loop blocks this thread; ends when the host drops the sender.
+                    match ipc::channel::<()>() {
+                        Ok((quit_tx, quit_rx)) => {
+                            let _ = quit_to.send(quit_tx);
+                            thread::spawn(move || {
+                                if quit_rx.recv().is_ok() {
+                                    sonate_quit(handle as EngineHandle);
+                                }
+                            });
+                        }
+                        Err(e) => error!("engine {handle}: failed to create quit channel: {e}"),
+                    }
                     let code = sonate_run(handle as EngineHandle);
-                    let _ = reply_to.send(code);
+                    if let Some(events) = event_senders.get(&handle) {
+                        let _ = events.send(WorkerEvent::Closed);
+                    }
+                    let _ = reply_to.send(ErrorCode::check(code));
                 }
                 WorkerRequest::Destroy { handle, reply_to } => {
                     let code = sonate_destroy(handle as EngineHandle);
-                    let _ = reply_to.send(code);
+                    if code == 0 {
+                        event_senders.remove(&handle);
+                    }
+                    let _ = reply_to.send(ErrorCode::check(code));
+                }
+                WorkerRequest::Ping { reply_to } => {
+                    let _ = reply_to.send(());
                 }
                 WorkerRequest::Shutdown => {
                     break;
@@ -181,32 +635,3 @@ fn main() {
         }
     }
 }
-
-fn resolve_library_path() -> PathBuf {
-    if let Ok(path) = std::env::var("SONATE_LIBRARY_PATH") {
-        return PathBuf::from(path);
-    }
-
-    let name = default_library_name();
-
-    if let Ok(exe_path) = std::env::current_exe() {
-        if let Some(dir) = exe_path.parent() {
-            let candidate = dir.join(&name);
-            if candidate.exists() {
-                return candidate;
-            }
-        }
-    }
-
-    Path::new(&name).to_path_buf()
-}
-
-fn default_library_name() -> String {
-    if cfg!(target_os = "windows") {
-        "sonate.dll".to_string()
-    } else if cfg!(target_os = "macos") {
-        "libsonate.dylib".to_string()
-    } else {
-        "libsonate.so".to_string()
-    }
-}
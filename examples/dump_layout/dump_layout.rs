@@ -0,0 +1,54 @@
+use sonate::{Engine, Id};
+
+/// Lays out the same document as the rust_usage example, without opening a window,
+/// and prints the resulting snapshot as JSON.
+///
+/// Run with `cargo run -p sonate --example dump_layout --features serde`.
+fn main() {
+    env_logger::init();
+
+    let engine = Engine::new();
+
+    engine.add_stylesheet(
+        r#"
+        .flex_container {
+            display: flex;
+            flex-direction: row;
+            gap: 10px;
+            padding: 10px;
+        }
+
+        .red_box {
+            background-color: #ff0000;
+            border-width: 2px;
+            border-color: black;
+            border-radius: 8px;
+            margin: 10px;
+        }
+
+        .green_box {
+            background-color: green;
+            border-radius: 12px 4px;
+        }
+    "#,
+    );
+
+    let root = engine.root_id();
+    let a = engine.create_node(Id::from_u64(1), Some("Hello".to_string()));
+    let b = engine.create_node(Id::from_u64(2), Some("World".to_string()));
+    let c = engine.create_node(Id::from_u64(3), Some("xD".to_string()));
+
+    engine.set_parent(root, a);
+    engine.set_parent(root, b);
+    engine.set_parent(root, c);
+
+    engine.set_attribute(root, "class".to_owned(), "flex_container".to_owned());
+    engine.set_attribute(a, "class".to_owned(), "red_box".to_owned());
+    engine.set_attribute(b, "class".to_owned(), "green_box".to_owned());
+
+    let snapshot = engine
+        .layout_headless(800.0, 600.0)
+        .expect("engine is not running a window");
+
+    println!("{}", snapshot.to_json());
+}
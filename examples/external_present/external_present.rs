@@ -0,0 +1,329 @@
+//! A host with its own OpenGL window that composites the engine's UI over what it draws
+//! itself: it clears its framebuffer to a slowly changing color, asks the engine for a frame
+//! and draws the returned texture on a full-window quad. Clicks are passed on to the engine,
+//! which toggles the button under them.
+
+#[cfg(target_os = "linux")]
+fn main() {
+    env_logger::init();
+    host::run();
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    eprintln!("Rendering into a host's graphics context is only supported with OpenGL on Linux.");
+}
+
+#[cfg(target_os = "linux")]
+mod host {
+    use glutin::{
+        config::{ConfigTemplateBuilder, GlConfig},
+        context::{ContextAttributesBuilder, PossiblyCurrentContext},
+        display::{GetGlDisplay, GlDisplay},
+        prelude::{GlSurface, NotCurrentGlContext},
+        surface::{Surface, SurfaceAttributesBuilder, WindowSurface},
+    };
+    use glutin_winit::DisplayBuilder;
+    use raw_window_handle::HasWindowHandle;
+    use sonate::{Engine, FrameHandle, Id, Params, PointerInput, PresentMode, WindowId};
+    use std::{cell::Cell, ffi::CString, num::NonZeroU32, rc::Rc, time::Instant};
+    use winit::{
+        application::ApplicationHandler,
+        dpi::LogicalSize,
+        event::{ElementState, MouseButton, WindowEvent},
+        event_loop::{ActiveEventLoop, EventLoop},
+        window::{Window, WindowAttributes, WindowId as HostWindowId},
+    };
+
+    const VERTEX_SHADER: &str = r#"
+        #version 330 core
+        layout(location = 0) in vec2 position;
+        out vec2 uv;
+        void main() {
+            uv = position * 0.5 + 0.5;
+            gl_Position = vec4(position, 0.0, 1.0);
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        #version 330 core
+        in vec2 uv;
+        out vec4 color;
+        uniform sampler2D ui;
+        void main() {
+            color = texture(ui, uv);
+        }
+    "#;
+
+    pub fn run() {
+        let engine = Engine::new();
+        engine.add_stylesheet(
+            r#"
+            .stage { display: flex; padding: 40px; }
+            .button {
+                width: 160px;
+                height: 48px;
+                padding-left: 16px;
+                border-radius: 8px;
+                background-color: #3366cc;
+                color: #ffffff;
+            }
+            .button.on { background-color: #cc6633; }
+        "#,
+        );
+        let root = engine.root_id();
+        engine.set_attribute(root, "class".to_owned(), "stage".to_owned());
+        let button = engine.create_node(Id::from_u64(1), Some("Toggle me".to_owned()));
+        engine.set_parent(root, button);
+        engine.set_attribute(button, "class".to_owned(), "button".to_owned());
+
+        let on = Rc::new(Cell::new(false));
+        let params = Params {
+            present_mode: PresentMode::External,
+            on_click: Some(Box::new({
+                let engine = engine.clone();
+                move |_x, _y, elements| {
+                    if elements.contains(&button) {
+                        on.set(!on.get());
+                        let class = if on.get() { "button on" } else { "button" };
+                        engine.set_attribute(button, "class".to_owned(), class.to_owned());
+                    }
+                }
+            })),
+            ..Default::default()
+        };
+        // Returns right away: frames are rendered when the host asks for them.
+        engine.run(params).expect("engine failed to start");
+
+        let event_loop = EventLoop::new().expect("could not create event loop");
+        let mut host = Host {
+            engine,
+            gl: None,
+            cursor: None,
+            started: Instant::now(),
+        };
+        event_loop.run_app(&mut host).expect("event loop failed");
+    }
+
+    struct Host {
+        engine: Engine,
+        gl: Option<Gl>,
+        /// Where the pointer is, in logical pixels.
+        cursor: Option<(f64, f64)>,
+        started: Instant,
+    }
+
+    struct Gl {
+        program: u32,
+        quad: u32,
+        surface: Surface<WindowSurface>,
+        context: PossiblyCurrentContext,
+        window: Window,
+    }
+
+    impl ApplicationHandler for Host {
+        fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+            if self.gl.is_none() {
+                let gl = Gl::new(event_loop);
+                let size = gl.window.inner_size();
+                let scale_factor = gl.window.scale_factor();
+                let size = size.to_logical::<f64>(scale_factor);
+                self.engine
+                    .set_frame_size(size.width, size.height, scale_factor);
+                self.gl = Some(gl);
+            }
+        }
+
+        fn window_event(
+            &mut self,
+            event_loop: &ActiveEventLoop,
+            _window_id: HostWindowId,
+            event: WindowEvent,
+        ) {
+            let Some(gl) = self.gl.as_mut() else {
+                return;
+            };
+            let scale_factor = gl.window.scale_factor();
+            match event {
+                WindowEvent::CloseRequested => {
+                    self.engine.quit();
+                    event_loop.exit();
+                }
+                WindowEvent::Resized(size) => {
+                    gl.surface.resize(
+                        &gl.context,
+                        NonZeroU32::new(size.width.max(1)).unwrap(),
+                        NonZeroU32::new(size.height.max(1)).unwrap(),
+                    );
+                    let size = size.to_logical::<f64>(scale_factor);
+                    self.engine
+                        .set_frame_size(size.width, size.height, scale_factor);
+                }
+                WindowEvent::CursorMoved { position, .. } => {
+                    let position = position.to_logical::<f64>(scale_factor);
+                    let (x, y) = (position.x, position.y);
+                    let input = PointerInput::Move { x, y };
+                    self.engine.inject_input(WindowId::PRIMARY, input);
+                    self.cursor = Some((x, y));
+                }
+                WindowEvent::MouseInput {
+                    state,
+                    button: MouseButton::Left,
+                    ..
+                } => {
+                    let input = match (state, self.cursor) {
+                        (ElementState::Pressed, Some((x, y))) => PointerInput::Press { x, y },
+                        (ElementState::Pressed, None) => return,
+                        (ElementState::Released, _) => PointerInput::Release,
+                    };
+                    self.engine.inject_input(WindowId::PRIMARY, input);
+                }
+                WindowEvent::RedrawRequested => {
+                    gl.draw(&self.engine, self.started.elapsed().as_secs_f32());
+                    gl.window.request_redraw();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl Gl {
+        fn new(event_loop: &ActiveEventLoop) -> Self {
+            let attributes = WindowAttributes::default()
+                .with_title("Sonate - external presentation")
+                .with_inner_size(LogicalSize::new(640.0, 400.0));
+            let (window, config) = DisplayBuilder::new()
+                .with_window_attributes(Some(attributes))
+                .build(event_loop, ConfigTemplateBuilder::new(), |configs| {
+                    configs
+                        .reduce(|best, config| {
+                            if config.num_samples() < best.num_samples() {
+                                config
+                            } else {
+                                best
+                            }
+                        })
+                        .unwrap()
+                })
+                .expect("could not create a window with an OpenGL config");
+            let window = window.expect("could not create a window");
+            let raw_window_handle = window.window_handle().unwrap().as_raw();
+
+            let display = config.display();
+            let context_attributes = ContextAttributesBuilder::new().build(Some(raw_window_handle));
+            let size = window.inner_size();
+            let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+                raw_window_handle,
+                NonZeroU32::new(size.width.max(1)).unwrap(),
+                NonZeroU32::new(size.height.max(1)).unwrap(),
+            );
+            let (surface, context) = unsafe {
+                let context = display
+                    .create_context(&config, &context_attributes)
+                    .expect("could not create an OpenGL context");
+                let surface = display
+                    .create_window_surface(&config, &surface_attributes)
+                    .expect("could not create an OpenGL surface");
+                let context = context
+                    .make_current(&surface)
+                    .expect("could not make the context current");
+                (surface, context)
+            };
+            gl::load_with(|name| display.get_proc_address(&CString::new(name).unwrap()));
+
+            let (program, quad) = unsafe { (link_program(), create_quad()) };
+            Self {
+                program,
+                quad,
+                surface,
+                context,
+                window,
+            }
+        }
+
+        fn draw(&mut self, engine: &Engine, seconds: f32) {
+            let size = self.window.inner_size();
+            unsafe {
+                // The host's own content.
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, size.width as i32, size.height as i32);
+                let shade = seconds.sin() * 0.25 + 0.5;
+                gl::ClearColor(0.1, shade, 0.3, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT);
+            }
+
+            let texture = match engine.render_frame() {
+                Ok(FrameHandle::Gl { texture, .. }) => texture,
+                Ok(frame) => panic!("expected an OpenGL texture, got {frame:?}"),
+                Err(err) => {
+                    eprintln!("The engine could not render a frame: {err:?}");
+                    return;
+                }
+            };
+
+            unsafe {
+                // Rendering changed the context's state, so set up everything drawn with.
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                gl::Viewport(0, 0, size.width as i32, size.height as i32);
+                gl::Disable(gl::SCISSOR_TEST);
+                gl::Disable(gl::STENCIL_TEST);
+                gl::Disable(gl::DEPTH_TEST);
+                gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+                gl::Enable(gl::BLEND);
+                // The texture has premultiplied alpha.
+                gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+                gl::UseProgram(self.program);
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, texture);
+                gl::Uniform1i(gl::GetUniformLocation(self.program, c"ui".as_ptr()), 0);
+                gl::BindVertexArray(self.quad);
+                gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            }
+            let _ = self.surface.swap_buffers(&self.context);
+        }
+    }
+
+    unsafe fn compile_shader(kind: u32, source: &str) -> u32 {
+        let shader = gl::CreateShader(kind);
+        let source = CString::new(source).unwrap();
+        gl::ShaderSource(shader, 1, &source.as_ptr(), std::ptr::null());
+        gl::CompileShader(shader);
+        let mut compiled = 0;
+        gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut compiled);
+        assert_eq!(compiled, gl::TRUE as i32, "shader failed to compile");
+        shader
+    }
+
+    unsafe fn link_program() -> u32 {
+        let program = gl::CreateProgram();
+        gl::AttachShader(program, compile_shader(gl::VERTEX_SHADER, VERTEX_SHADER));
+        gl::AttachShader(
+            program,
+            compile_shader(gl::FRAGMENT_SHADER, FRAGMENT_SHADER),
+        );
+        gl::LinkProgram(program);
+        let mut linked = 0;
+        gl::GetProgramiv(program, gl::LINK_STATUS, &mut linked);
+        assert_eq!(linked, gl::TRUE as i32, "shader program failed to link");
+        program
+    }
+
+    /// A vertex array with a quad covering the viewport, as a triangle strip.
+    unsafe fn create_quad() -> u32 {
+        let corners: [f32; 8] = [-1.0, -1.0, 1.0, -1.0, -1.0, 1.0, 1.0, 1.0];
+        let (mut vertex_array, mut buffer) = (0, 0);
+        gl::GenVertexArrays(1, &mut vertex_array);
+        gl::BindVertexArray(vertex_array);
+        gl::GenBuffers(1, &mut buffer);
+        gl::BindBuffer(gl::ARRAY_BUFFER, buffer);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            std::mem::size_of_val(&corners) as isize,
+            corners.as_ptr().cast(),
+            gl::STATIC_DRAW,
+        );
+        gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+        gl::EnableVertexAttribArray(0);
+        vertex_array
+    }
+}
@@ -0,0 +1,59 @@
+use sonate::{Engine, Id, Params};
+use std::f64::consts::TAU;
+
+/// How far the box travels, in px.
+const TRAVEL: u32 = 300;
+
+/// Seconds per back-and-forth.
+const PERIOD: f64 = 2.0;
+
+fn main() {
+    env_logger::init();
+
+    let engine = Engine::new();
+
+    // One class per pixel of travel, since nodes are styled through classes.
+    let mut css = String::from(
+        r#"
+        .stage {
+            display: flex;
+            flex-direction: column;
+            padding: 20px;
+        }
+
+        .box {
+            width: 60px;
+            height: 60px;
+            background-color: #3366cc;
+            border-radius: 8px;
+        }
+    "#,
+    );
+    for x in 0..=TRAVEL {
+        css.push_str(&format!(".x{x} {{ margin-left: {x}px; }}\n"));
+    }
+    engine.add_stylesheet(&css);
+
+    let root = engine.root_id();
+    let moving_box = engine.create_node(Id::from_u64(1), None);
+    engine.set_parent(root, moving_box);
+    engine.set_attribute(root, "class".to_owned(), "stage".to_owned());
+    engine.set_attribute(moving_box, "class".to_owned(), "box x0".to_owned());
+
+    let params = Params {
+        on_frame: {
+            let engine = engine.clone();
+
+            Some(Box::new(move |elapsed| {
+                let phase = (elapsed.as_secs_f64() / PERIOD * TAU).sin() * 0.5 + 0.5;
+                let x = (phase * TRAVEL as f64).round() as u32;
+                engine.set_attribute(moving_box, "class".to_owned(), format!("box x{x}"));
+            }))
+        },
+        ..Default::default()
+    };
+
+    if let Err(e) = engine.run(params) {
+        eprintln!("Error encountered: {:?}", e);
+    }
+}
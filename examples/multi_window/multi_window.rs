@@ -0,0 +1,60 @@
+use sonate::{Engine, Id, Params, WindowOptions};
+
+fn main() {
+    env_logger::init();
+
+    let engine = Engine::new();
+
+    // Both windows are styled by the same stylesheet.
+    engine.add_stylesheet(
+        r#"
+        .stage {
+            display: flex;
+            flex-direction: row;
+            gap: 20px;
+            padding: 20px;
+        }
+
+        .swatch {
+            width: 100px;
+            height: 100px;
+            border-radius: 12px;
+        }
+
+        .blue { background-color: #3366cc; }
+        .orange { background-color: #ff9933; }
+    "#,
+    );
+
+    let palette = engine.create_window(WindowOptions {
+        title: Some("Palette".to_owned()),
+        width: 300.0,
+        height: 200.0,
+        ..Default::default()
+    });
+
+    fill(&engine, engine.root_id(), 1, &["blue", "blue", "orange"]);
+    fill(&engine, palette.root_id(), 10, &["orange", "blue"]);
+
+    let params = Params {
+        on_click: Some(Box::new(|x, y, elements| {
+            println!("Clicked at ({x}, {y}): {elements:?}");
+        })),
+        ..Default::default()
+    };
+
+    // Closing the palette leaves the main window open; closing both ends `run`.
+    if let Err(e) = engine.run(params) {
+        eprintln!("Error encountered: {:?}", e);
+    }
+}
+
+/// Lay out swatches of the given colors under `root`, numbering nodes from `first_id`.
+fn fill(engine: &Engine, root: Id, first_id: u64, colors: &[&str]) {
+    engine.set_attribute(root, "class".to_owned(), "stage".to_owned());
+    for (n, color) in (first_id..).zip(colors) {
+        let swatch = engine.create_node(Id::from_u64(n), None);
+        engine.set_parent(root, swatch);
+        engine.set_attribute(swatch, "class".to_owned(), format!("swatch {color}"));
+    }
+}
@@ -1,6 +1,8 @@
 use sonate::{Engine, Id};
 
 fn main() {
+    env_logger::init();
+
     // Create a thread-safe CSS engine
     let engine = Engine::new();
 
@@ -53,6 +55,7 @@ fn main() {
         on_click: Some(Box::new(|x, y, elements| {
             println!("Clicked at ({}, {}), elements: {:?}", x, y, elements);
         })),
+        ..Default::default()
     };
 
     if let Err(e) = engine.run(params) {
@@ -196,6 +196,8 @@ fn div(engine: &Engine, text: Option<String>, parent: Id, class: &str) -> Id {
 }
 
 fn main() {
+    env_logger::init();
+
     // Create a thread-safe CSS engine
     let engine = Engine::new();
 
@@ -435,6 +437,7 @@ fn main() {
                 apply_state(&engine, &mut state, flex_container, item1, item2, item3);
             }))
         },
+        ..Default::default()
     };
 
     if let Err(e) = engine.run(params) {
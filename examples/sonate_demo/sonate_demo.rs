@@ -0,0 +1,159 @@
+//! A gallery of layouts: press 1 to 6 to switch scenes, and F12 to toggle the layout
+//! debugging overlay, which outlines every node, tints margins orange and padding green,
+//! and shows the style of the node under the pointer.
+
+use sonate::{Engine, Id, Params};
+
+const CSS: &str = r#"
+    .gallery { display: flex; flex-direction: column; padding: 16px; row-gap: 12px; }
+    .title { font-size: 18px; color: #333333; }
+    .hint { font-size: 12px; color: #777777; }
+    .hidden { display: none; }
+
+    .scene { display: flex; padding: 12px; background-color: #f2f2f2; }
+    .row { flex-direction: row; column-gap: 8px; }
+    .column { flex-direction: column; row-gap: 8px; width: 200px; }
+    .wrap { flex-wrap: wrap; width: 360px; gap: 8px; }
+    .stretch { flex-direction: row; width: 480px; }
+
+    .box { width: 80px; height: 48px; padding: 4px; margin: 4px; background-color: #6fa8dc; }
+    .tall { height: 80px; }
+    .wide { width: 140px; }
+    .grow { width: 40px; flex-grow: 1; background-color: #93c47d; }
+    .grow-more { width: 40px; flex-grow: 3; background-color: #f6b26b; }
+    .shrink { width: 300px; flex-shrink: 2; background-color: #e06666; }
+
+    .outer { flex-direction: row; padding: 12px; column-gap: 12px; }
+    .panel { display: flex; flex-direction: column; padding: 8px; row-gap: 4px;
+             border: 2px solid #999999; background-color: #ffffff; }
+    .inner { display: flex; flex-direction: row; column-gap: 4px; }
+    .chip { width: 32px; height: 20px; background-color: #8e7cc3; margin: 2px; }
+
+    .text { flex-direction: column; row-gap: 6px; }
+    .small { font-size: 11px; }
+    .large { font-size: 24px; color: #3d85c6; }
+    .padded { padding: 6px 12px; border: 1px solid #3d85c6; }
+"#;
+
+/// Each scene's name and markup.
+const SCENES: [(&str, &str); 6] = [
+    (
+        "Flex row",
+        r#"<div class="scene row">
+            <div class="box">One</div>
+            <div class="box tall">Two</div>
+            <div class="box">Three</div>
+        </div>"#,
+    ),
+    (
+        "Flex column",
+        r#"<div class="scene column">
+            <div class="box">One</div>
+            <div class="box wide">Two</div>
+            <div class="box">Three</div>
+        </div>"#,
+    ),
+    (
+        "Wrapping",
+        r#"<div class="scene row wrap">
+            <div class="box">1</div><div class="box">2</div><div class="box">3</div>
+            <div class="box">4</div><div class="box">5</div><div class="box">6</div>
+            <div class="box">7</div>
+        </div>"#,
+    ),
+    (
+        "Grow and shrink",
+        r#"<div class="scene stretch">
+            <div class="box grow">grow 1</div>
+            <div class="box grow-more">grow 3</div>
+            <div class="box shrink">shrink 2</div>
+        </div>"#,
+    ),
+    (
+        "Nested containers",
+        r#"<div class="scene outer">
+            <div class="panel">
+                <div class="inner"><div class="chip"/><div class="chip"/><div class="chip"/></div>
+                <div class="inner"><div class="chip"/><div class="chip"/></div>
+            </div>
+            <div class="panel">
+                <div class="inner"><div class="chip"/></div>
+                <div class="inner"><div class="chip"/><div class="chip"/><div class="chip"/></div>
+            </div>
+        </div>"#,
+    ),
+    (
+        "Text",
+        r#"<div class="scene text">
+            <div class="small">Small print, 11px.</div>
+            <div>Body text at the default size.</div>
+            <div class="large">Large and blue, 24px.</div>
+            <div class="padded">Text in a padded, bordered box.</div>
+        </div>"#,
+    ),
+];
+
+fn title(index: usize) -> String {
+    format!("{}. {}", index + 1, SCENES[index].0)
+}
+
+fn main() {
+    env_logger::init();
+
+    let engine = Engine::new();
+    engine.add_stylesheet(CSS);
+    let root = engine.root_id();
+    engine.set_attribute(root, "class".to_owned(), "gallery".to_owned());
+
+    let header = engine
+        .load_markup(&format!(
+            r#"<div class="title">{}</div>
+            <div class="hint">1-6 switch scenes, F12 toggles the layout overlay</div>"#,
+            title(0)
+        ))
+        .expect("the header is well-formed");
+    let heading = header[0];
+
+    // Every scene is loaded once, each in a slot of its own; switching hides all slots
+    // but one.
+    let slots: Vec<Id> = SCENES
+        .iter()
+        .map(|(_, markup)| {
+            engine
+                .load_markup(&format!("<div>{markup}</div>"))
+                .expect("the scenes are well-formed")[0]
+        })
+        .collect();
+    let show = move |engine: &Engine, index: usize| {
+        for (i, &slot) in slots.iter().enumerate() {
+            let class = if i == index { "" } else { "hidden" };
+            engine.set_attribute(slot, "class".to_owned(), class.to_owned());
+        }
+        engine.set_text(heading, Some(title(index)));
+    };
+    show(&engine, 0);
+
+    let params = Params {
+        on_key: {
+            let engine = engine.clone();
+            Some(Box::new(move |press| {
+                let Some(index) = press
+                    .key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|n| n.checked_sub(1))
+                    .filter(|&index| index < SCENES.len())
+                else {
+                    return false;
+                };
+                show(&engine, index);
+                true
+            }))
+        },
+        ..Default::default()
+    };
+
+    if let Err(e) = engine.run(params) {
+        eprintln!("Error encountered: {:?}", e);
+    }
+}
@@ -0,0 +1,62 @@
+use sonate::{Engine, Id, Params};
+use std::thread;
+use std::time::Duration;
+
+fn main() {
+    env_logger::init();
+
+    let engine = Engine::new();
+
+    engine.add_stylesheet(
+        r#"
+        .stage {
+            display: flex;
+            flex-direction: row;
+            gap: 20px;
+            padding: 20px;
+        }
+
+        .light {
+            width: 80px;
+            height: 80px;
+            border-radius: 40px;
+            background-color: #444444;
+        }
+
+        .on {
+            background-color: #ffcc00;
+        }
+    "#,
+    );
+
+    let root = engine.root_id();
+    engine.set_attribute(root, "class".to_owned(), "stage".to_owned());
+    let lights: Vec<Id> = (1..=3)
+        .map(|n| {
+            let light = engine.create_node(Id::from_u64(n), None);
+            engine.set_parent(root, light);
+            light
+        })
+        .collect();
+
+    // Nothing happens in the window while this runs: the engine wakes the event loop
+    // whenever a change has been laid out.
+    let timer_engine = engine.clone();
+    thread::spawn(move || {
+        for tick in 0.. {
+            for (i, light) in lights.iter().enumerate() {
+                let class = if tick % lights.len() == i {
+                    "light on"
+                } else {
+                    "light"
+                };
+                timer_engine.set_attribute(*light, "class".to_owned(), class.to_owned());
+            }
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+
+    if let Err(e) = engine.run(Params::default()) {
+        eprintln!("Error encountered: {:?}", e);
+    }
+}